@@ -0,0 +1,64 @@
+#!/usr/bin/env bash
+
+# Stands up a kind cluster for the integration suite and port-forwards the ACM, the AIM, and the
+# fake registry onto localhost. Run this from the it-tests directory and then run
+#
+#   cargo test --release -p it-tests --features integration
+#
+# The cluster is reused if it already exists. Tear it down with `kind delete cluster --name ocf-it`.
+
+set -e
+
+function abspath() {
+  # $1 : relative filename
+  echo "$(cd "$(dirname "$1")" && pwd)/$(basename "$1")"
+}
+
+ROOT=$(abspath ../)
+CLUSTER="${CLUSTER:-ocf-it}"
+TARGET="${ROOT}"/target
+TARGET_IMAGES="${TARGET}"/images
+TARGET_CONNECTORS="${TARGET}"/it-tests
+APP_VERSION=$(grep '^appVersion:' "${ROOT}"/helm/Chart.yaml | awk '{print $2}')
+
+export REGISTRY="registry.kube-system"
+
+if ! kind get clusters | grep -qx "${CLUSTER}"; then
+  kind create cluster --name "${CLUSTER}" --config fixtures/kind.yaml
+fi
+kubectl config use-context kind-"${CLUSTER}"
+
+kubectl apply -f fixtures/registry.yaml
+kubectl -n kube-system rollout status deployment/registry
+
+# The ACM, the AIM, and containerd are loaded straight into the node under the chart's appVersion.
+cargo build --release --workspace --manifest-path "${ROOT}"/Cargo.toml
+(cd "${ROOT}" && ./package.sh)
+for service in "${TARGET_IMAGES}"/* ; do
+  name=$(basename "${service}")
+  reference=$(docker load -i "${service}"/"${name}" | awk '/Loaded image:/ {print $3}')
+  docker tag "${reference}" "${REGISTRY}"/ocf-system/"${name}":"${APP_VERSION}"
+  kind load docker-image --name "${CLUSTER}" "${REGISTRY}"/ocf-system/"${name}":"${APP_VERSION}"
+done
+
+# Each test connector image is the same binary, told how to behave by its MODE. The unpullable
+# image never runs, it only needs an image config of its own for the suite to delete.
+rm -rf "${TARGET_CONNECTORS:?}"
+mkdir -p "${TARGET_CONNECTORS}"
+cp connector/Dockerfile "${TARGET}"/release/test-connector "${TARGET_CONNECTORS}"/
+for mode in healthy crashing unpullable ; do
+  docker build --build-arg MODE="${mode}" -t it-tests/"${mode}":latest "${TARGET_CONNECTORS}"
+  docker save -o "${TARGET_CONNECTORS}"/"${mode}".img it-tests/"${mode}":latest
+done
+
+helm upgrade --install ocf "${ROOT}"/helm --wait \
+  --set registry.implementation=Minikube \
+  --set registry.registry="${REGISTRY}" \
+  --set registry.repository=ocf \
+  --set development.pull_services_from_local=true
+
+kubectl -n ocf-system port-forward svc/acm 8000:80 >/dev/null &
+kubectl -n ocf-system port-forward svc/aim 8001:80 >/dev/null &
+kubectl -n kube-system port-forward svc/registry 5000:80 >/dev/null &
+echo "The ACM, the AIM, and the registry are forwarded to localhost:8000, :8001, and :5000"
+wait
@@ -0,0 +1,38 @@
+//! The test connector is the tiny connector image that the integration suite deploys. How it
+//! behaves is chosen by the `TEST_CONNECTOR_MODE` environment variable, which is baked into each
+//! of its images (see `connector/Dockerfile`).
+//!
+//! * `healthy` serves the gRPC health service on the connector port for as long as it lives.
+//! * `crashing` exits with a failure before ever serving anything.
+//! * `unpullable` is never run, as the suite deletes its image config once it is installed.
+use tonic::transport::Server;
+
+/// The port that the ACM health checks connectors on.
+const PORT: u16 = 8080;
+
+#[tokio::main]
+async fn main() {
+    let mode = std::env::var("TEST_CONNECTOR_MODE").unwrap_or_else(|_| "healthy".to_string());
+    match mode.as_str() {
+        "healthy" => serve().await,
+        "crashing" => {
+            eprintln!("The test connector is crashing on purpose");
+            std::process::exit(1);
+        }
+        mode => {
+            eprintln!("Unknown TEST_CONNECTOR_MODE {}", mode);
+            std::process::exit(2);
+        }
+    }
+}
+
+async fn serve() {
+    let (_reporter, health) = tonic_health::server::health_reporter();
+    let address = ([0, 0, 0, 0], PORT).into();
+    println!("The test connector is serving on {}", address);
+    Server::builder()
+        .add_service(health)
+        .serve(address)
+        .await
+        .expect("the test connector failed to serve");
+}
@@ -0,0 +1,207 @@
+//! The end-to-end integration suite of the ACM and the AIM, along with the fixtures that it is
+//! built upon.
+//!
+//! The suite runs against a live cluster and is gated behind the `integration` feature, so a
+//! plain `cargo test` skips it entirely. To run it against a local [kind](https://kind.sigs.k8s.io)
+//! cluster:
+//!
+//! ```text
+//! cd k8s/it-tests
+//! ./setup.sh
+//! cargo test --release -p it-tests --features integration
+//! ```
+//!
+//! `setup.sh` creates the cluster, stands up the [fake registry](Registry) within it, loads the
+//! ACM and the AIM, builds the [test connector](Connector) images, installs the chart, and
+//! port-forwards both services (as well as the registry) onto localhost. A minikube cluster with
+//! its registry addon enabled works just as well, so long as the same three addresses are
+//! forwarded. Each address may be overridden by `ACM_URL`, `AIM_URL`, and `REGISTRY_URL`.
+use client_sdk::Client;
+use k8s_openapi::api::core::v1::Pod;
+use kube::Api;
+use serde::Deserialize;
+use std::path::PathBuf;
+use tokio::sync::OnceCell;
+
+/// The address of the port-forwarded ACM, should `ACM_URL` not be set.
+pub const DEFAULT_ACM_URL: &str = "http://localhost:8000";
+/// The address of the port-forwarded AIM, should `AIM_URL` not be set.
+pub const DEFAULT_AIM_URL: &str = "http://localhost:8001";
+/// The address of the port-forwarded fake registry, should `REGISTRY_URL` not be set.
+pub const DEFAULT_REGISTRY_URL: &str = "http://localhost:5000";
+/// The repository that the AIM is configured with by `setup.sh`.
+pub const REPOSITORY: &str = "ocf";
+/// The namespace that connectors are deployed into.
+pub const NAMESPACE: &str = "ocf";
+
+fn var(name: &str, default: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| default.to_string())
+}
+
+/// Constructs a Client of the ACM and the AIM under test.
+pub fn client() -> Client {
+    Client::new(
+        var("ACM_URL", DEFAULT_ACM_URL),
+        var("AIM_URL", DEFAULT_AIM_URL),
+    )
+}
+
+/// Returns the name of the given pod.
+pub fn pod_name(pod: &Pod) -> String {
+    pod.metadata.name.clone().expect("pods always have a name")
+}
+
+/// Asks Kubernetes itself (rather than the ACM) whether the given connector pod exists.
+pub async fn pod_exists(name: &str) -> bool {
+    let client = kube::Client::try_default()
+        .await
+        .expect("no kubeconfig for the cluster under test");
+    let pods: Api<Pod> = Api::namespaced(client, NAMESPACE);
+    match pods.get(name).await {
+        Ok(_) => true,
+        Err(kube::Error::Api(err)) if err.code == 404 => false,
+        Err(err) => panic!("failed to get the pod {}, {}", name, err),
+    }
+}
+
+/// A Connector is one of the test connector images built by `setup.sh`. Each is the very same
+/// binary (`src/bin/test-connector.rs`), told how to behave by its image.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Connector {
+    /// Serves the gRPC health service, and so becomes ready.
+    Healthy,
+    /// Exits with a failure before ever serving anything.
+    Crashing,
+    /// Is installed, but its image config is then deleted from the registry, so that the AIM
+    /// still finds its tag while Kubernetes fails to pull it.
+    Unpullable,
+}
+
+impl Connector {
+    /// The image archive of this connector, as an install to the AIM expects it.
+    pub fn archive(self) -> PathBuf {
+        let name = match self {
+            Connector::Healthy => "healthy.img",
+            Connector::Crashing => "crashing.img",
+            Connector::Unpullable => "unpullable.img",
+        };
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../target/it-tests")
+            .join(name)
+    }
+
+    /// Installs this connector via the AIM (once per test binary), returning its tag.
+    pub async fn installed(self) -> String {
+        static HEALTHY: OnceCell<String> = OnceCell::const_new();
+        static CRASHING: OnceCell<String> = OnceCell::const_new();
+        static UNPULLABLE: OnceCell<String> = OnceCell::const_new();
+        let cell = match self {
+            Connector::Healthy => &HEALTHY,
+            Connector::Crashing => &CRASHING,
+            Connector::Unpullable => &UNPULLABLE,
+        };
+        cell.get_or_init(|| async {
+            let tag = client()
+                .install(self.archive())
+                .await
+                .unwrap_or_else(|err| panic!("failed to install the {:?} connector, {}", self, err))
+                .tag;
+            if self == Connector::Unpullable {
+                Registry::default().delete_config(&tag).await;
+            }
+            tag
+        })
+        .await
+        .clone()
+    }
+}
+
+/// The Registry is the fake OCI registry (`fixtures/registry.yaml`) that the AIM installs into,
+/// queried directly so that a test may check what the AIM actually did to it.
+pub struct Registry {
+    http: reqwest::Client,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct Tags {
+    tags: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    config: Descriptor,
+}
+
+#[derive(Deserialize)]
+struct Descriptor {
+    digest: String,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry {
+            http: reqwest::Client::new(),
+            url: var("REGISTRY_URL", DEFAULT_REGISTRY_URL),
+        }
+    }
+}
+
+impl Registry {
+    /// Lists every tag within the [repository](REPOSITORY). The registry reports no tags at all
+    /// (rather than an empty list) once the last one is deleted.
+    pub async fn tags(&self) -> Vec<String> {
+        let url = format!("{}/v2/{}/tags/list", self.url, REPOSITORY);
+        let response = self
+            .http
+            .get(&url)
+            .send()
+            .await
+            .unwrap_or_else(|err| panic!("failed to reach the registry at {}, {}", url, err));
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return vec![];
+        }
+        response
+            .json::<Tags>()
+            .await
+            .unwrap_or_else(|err| panic!("the registry listed {} unexpectedly, {}", url, err))
+            .tags
+            .unwrap_or_default()
+    }
+
+    /// Deletes the image config of the given tag while leaving its manifest in place, such that
+    /// the tag is still listed but can no longer be pulled.
+    pub async fn delete_config(&self, tag: &str) {
+        let url = format!("{}/v2/{}/manifests/{}", self.url, REPOSITORY, tag);
+        let manifest = self
+            .http
+            .get(&url)
+            .header(
+                "Accept",
+                "application/vnd.docker.distribution.manifest.v2+json",
+            )
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .unwrap_or_else(|err| panic!("failed to get the manifest at {}, {}", url, err))
+            .json::<Manifest>()
+            .await
+            .unwrap_or_else(|err| panic!("the registry served {} unexpectedly, {}", url, err));
+        let url = format!(
+            "{}/v2/{}/blobs/{}",
+            self.url, REPOSITORY, manifest.config.digest
+        );
+        let response = self
+            .http
+            .delete(&url)
+            .send()
+            .await
+            .unwrap_or_else(|err| panic!("failed to reach the registry at {}, {}", url, err));
+        // A config that is already gone was deleted by an earlier run against the same registry.
+        if response.status() != reqwest::StatusCode::NOT_FOUND {
+            response
+                .error_for_status()
+                .unwrap_or_else(|err| panic!("failed to delete {}, {}", url, err));
+        }
+    }
+}
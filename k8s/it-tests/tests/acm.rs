@@ -0,0 +1,102 @@
+//! The lifecycle of connector pods as driven through the ACM.
+#![cfg(feature = "integration")]
+use client_sdk::ClientError;
+use error::AcmError;
+use it_tests::*;
+
+/// How long (in seconds) a pod is given to be entirely gone from Kubernetes.
+const DELETE_TIMEOUT: u64 = 120;
+
+#[tokio::test]
+async fn deploy_wait_refresh_delete() {
+    let client = client();
+    let tag = Connector::Healthy.installed().await;
+    let deployment = client
+        .deploy(&tag, "it-lifecycle", Some(300))
+        .await
+        .unwrap();
+    let name = pod_name(&deployment.pod);
+    let leased = client.wait(&deployment.ticket).await.unwrap();
+    assert_eq!(pod_name(&leased.pod), name);
+    // Every waiter is handed the same result, not just the first.
+    let again = client.wait(&deployment.ticket).await.unwrap();
+    assert_eq!(pod_name(&again.pod), name);
+    let refreshed = client.refresh(&leased.ticket.ticket).await.unwrap();
+    assert_eq!(refreshed.ticket, leased.ticket.ticket);
+    assert!(refreshed.execution_date >= leased.ticket.execution_date);
+    client.delete(&refreshed.ticket).await.unwrap();
+    client
+        .wait_delete(&name, Some(DELETE_TIMEOUT))
+        .await
+        .unwrap();
+    assert!(!pod_exists(&name).await);
+    match client.wait(&refreshed.ticket).await {
+        Err(ClientError::Gone(_)) | Err(ClientError::NotFound(_)) => (),
+        result => panic!("waiting on a deleted pod returned {:?}", result),
+    }
+}
+
+#[tokio::test]
+async fn garbage_collects_expired_pods() {
+    let client = client();
+    let tag = Connector::Healthy.installed().await;
+    let deployment = client.deploy(&tag, "it-expiry", Some(5)).await.unwrap();
+    let name = pod_name(&deployment.pod);
+    let leased = client.wait(&deployment.ticket).await.unwrap();
+    // Nothing refreshes the pod, so the garbage collector deletes it once its TTL elapses.
+    client
+        .wait_delete(&name, Some(DELETE_TIMEOUT))
+        .await
+        .unwrap();
+    assert!(!pod_exists(&name).await);
+    assert!(client.refresh(&leased.ticket.ticket).await.is_err());
+}
+
+#[tokio::test]
+async fn detects_crashes() {
+    let client = client();
+    let tag = Connector::Crashing.installed().await;
+    let deployment = client.deploy(&tag, "it-crash", Some(300)).await.unwrap();
+    let name = pod_name(&deployment.pod);
+    let err = client.wait(&deployment.ticket).await.unwrap_err();
+    // Whether the crash is seen as such, as a termination, or as a reboot depends upon which
+    // event the ACM happens to observe first.
+    let crashed = ["ACM-1101", "ACM-1102", "ACM-1108"];
+    assert!(
+        crashed.contains(&err.error_code().unwrap_or_default()),
+        "waiting on a crashing pod returned {:?}",
+        err
+    );
+    client.delete(&deployment.ticket).await.unwrap();
+    client
+        .wait_delete(&name, Some(DELETE_TIMEOUT))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn refuses_uninstalled_tags() {
+    match client()
+        .deploy("it-never-installed", "it-missing", Some(300))
+        .await
+    {
+        // That is, the AIM's TagNotFound.
+        Err(err @ ClientError::NotFound(_)) => assert_eq!(err.error_code(), Some("AIM-1000")),
+        result => panic!("deploying an uninstalled tag returned {:?}", result),
+    }
+}
+
+#[tokio::test]
+async fn reports_err_image_pull() {
+    let client = client();
+    let tag = Connector::Unpullable.installed().await;
+    let deployment = client.deploy(&tag, "it-pull", Some(300)).await.unwrap();
+    let name = pod_name(&deployment.pod);
+    let err = client.wait(&deployment.ticket).await.unwrap_err();
+    assert_eq!(err.error_code(), Some("K8S-1000"), "{:?}", err);
+    client.delete(&deployment.ticket).await.unwrap();
+    client
+        .wait_delete(&name, Some(DELETE_TIMEOUT))
+        .await
+        .unwrap();
+}
@@ -0,0 +1,33 @@
+//! The lifecycle of connector images as driven through the AIM, checked against the fake registry.
+#![cfg(feature = "integration")]
+use client_sdk::ClientError;
+use it_tests::*;
+
+/// Note that the Minikube registry implementation uninstalls by digest, so this also uninstalls
+/// any other tag of the same connector (such as those installed by the ACM suite).
+#[tokio::test]
+async fn install_list_uninstall() {
+    let client = client();
+    let registry = Registry::default();
+    let image = client.install(Connector::Healthy.archive()).await.unwrap();
+    assert!(client
+        .list()
+        .await
+        .unwrap()
+        .iter()
+        .any(|listed| listed.tag == image.tag));
+    assert_eq!(client.get(&image.tag).await.unwrap(), image);
+    assert!(registry.tags().await.contains(&image.tag));
+    client.uninstall(&image.tag).await.unwrap();
+    assert!(!client
+        .list()
+        .await
+        .unwrap()
+        .iter()
+        .any(|listed| listed.tag == image.tag));
+    assert!(!registry.tags().await.contains(&image.tag));
+    match client.get(&image.tag).await {
+        Err(ClientError::NotFound(_)) => (),
+        result => panic!("getting an uninstalled tag returned {:?}", result),
+    }
+}
@@ -0,0 +1,326 @@
+//! audit keeps an append-only trail of every mutating operation (deploys, deletes, refreshes,
+//! installs, and uninstalls) that is made of the ACM and the AIM, recording who made it, what it
+//! was, when it was made, and how it turned out.
+//!
+//! Every [Entry](Entry) is written into the [sink](AUDIT_SINK) that is configured, and the most
+//! recent are also kept in memory so that they may be queried by the services' `/audit` endpoints.
+pub mod sink;
+
+pub use sink::{AuditSinkFailed, FileSink, Sink, WebhookSink};
+
+use kind::Kind;
+use rocket::request::{self, FromRequest, Request};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The environment variable that selects the sink that audit entries are written into. Valid
+/// sinks are
+///
+///   1. `file`: Every entry is appended, as a line of JSON, to the file at [AUDIT_PATH](AUDIT_PATH).
+///   2. `events`: Every entry is posted as a Kubernetes Event, for those services that may post them.
+///   3. `webhook`: Every entry is POSTed, as JSON, to the URL at [AUDIT_WEBHOOK](AUDIT_WEBHOOK).
+///   4. `none`: Entries are only kept in memory (the default).
+pub const AUDIT_SINK: &str = "AUDIT_SINK";
+
+/// The environment variable that configures the file that the `file` sink appends to. Each service
+/// has a default of its own.
+pub const AUDIT_PATH: &str = "AUDIT_PATH";
+
+/// The environment variable that configures the URL that the `webhook` sink POSTs to.
+pub const AUDIT_WEBHOOK: &str = "AUDIT_WEBHOOK";
+
+/// The environment variable that configures how many of the most recent entries are kept in memory
+/// for the sake of the `/audit` endpoints.
+pub const AUDIT_RETAINED: &str = "AUDIT_RETAINED";
+
+/// The default for [AUDIT_RETAINED](AUDIT_RETAINED).
+pub const DEFAULT_AUDIT_RETAINED: usize = 1000;
+
+/// The header by which a client MAY identify itself (for example, with the ID of its Alation
+/// instance). Clients that do not are identified by their source IP instead.
+pub const CLIENT_ID_HEADER: &str = "X-Client-Id";
+
+/// An Action is a single kind of mutating operation.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Deploy,
+    Delete,
+    Refresh,
+    Install,
+    Uninstall,
+    Promote,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let action = match self {
+            Action::Deploy => "deploy",
+            Action::Delete => "delete",
+            Action::Refresh => "refresh",
+            Action::Install => "install",
+            Action::Uninstall => "uninstall",
+            Action::Promote => "promote",
+        };
+        write!(f, "{}", action)
+    }
+}
+
+/// An Outcome is how an [Action](Action) turned out.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Succeeded,
+    Failed,
+}
+
+/// An Entry is the record of a single mutating operation.
+#[derive(Serialize, Deserialize, Clone, Debug, Kind)]
+pub struct Entry {
+    /// The Unix timestamp at which the operation finished.
+    pub at: u64,
+    /// Who made the operation, as identified by their [Actor](Actor).
+    pub actor: String,
+    pub action: Action,
+    /// What was operated upon, such as the `<namespace>/<name>` of a pod or the tag of an image.
+    pub target: String,
+    pub outcome: Outcome,
+    /// The error that the operation failed with, should it have.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Why the target was deleted, should the operation be a deletion. This is the `k8s` crate's
+    /// `DeletionCause` as it is displayed (such as `user` or `ttl_expired`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cause: Option<String>,
+}
+
+impl Entry {
+    /// The entry of the given action having been made, with the given result, just now.
+    pub fn new<T, E: Display>(
+        actor: &Actor,
+        action: Action,
+        target: impl ToString,
+        result: &std::result::Result<T, E>,
+    ) -> Entry {
+        Entry {
+            at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |now| now.as_secs()),
+            actor: actor.to_string(),
+            action,
+            target: target.to_string(),
+            outcome: match result {
+                Ok(_) => Outcome::Succeeded,
+                Err(_) => Outcome::Failed,
+            },
+            error: result.as_ref().err().map(ToString::to_string),
+            cause: None,
+        }
+    }
+
+    /// This entry, recording that its target was deleted for the given cause.
+    pub fn with_cause(self, cause: impl Display) -> Entry {
+        Entry {
+            cause: Some(cause.to_string()),
+            ..self
+        }
+    }
+}
+
+/// An Actor is a request guard that identifies who made a request, being its
+/// [CLIENT_ID_HEADER](CLIENT_ID_HEADER) or, failing that, its source IP. It never fails.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Actor(String);
+
+impl Actor {
+    /// Identifies who made the given request.
+    pub fn of(request: &Request<'_>) -> Actor {
+        match request.headers().get_one(CLIENT_ID_HEADER) {
+            Some(id) if !id.trim().is_empty() => Actor(id.trim().to_string()),
+            _ => Actor(
+                request
+                    .client_ip()
+                    .map(|ip| ip.to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+            ),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Actor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<&str> for Actor {
+    fn from(actor: &str) -> Self {
+        Actor(actor.to_string())
+    }
+}
+
+impl From<String> for Actor {
+    fn from(actor: String) -> Self {
+        Actor(actor)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Actor {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        request::Outcome::Success(Actor::of(request))
+    }
+}
+
+/// An Auditor writes every [Entry](Entry) into its [Sink](Sink) (should it have one) while keeping
+/// the most recent in memory.
+pub struct Auditor {
+    sink: Option<Box<dyn Sink>>,
+    journal: Mutex<VecDeque<Entry>>,
+    retained: usize,
+    /// Serializes writers so that entries reach the sink in the order that they were recorded.
+    writer: tokio::sync::Mutex<()>,
+}
+
+impl Auditor {
+    pub fn new(sink: Option<Box<dyn Sink>>, retained: usize) -> Auditor {
+        Auditor {
+            sink,
+            journal: Mutex::new(VecDeque::new()),
+            retained,
+            writer: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Constructs the Auditor configured by the [AUDIT_SINK](AUDIT_SINK) environment variable
+    /// (and its friends). The `file` sink defaults to the given path, while the `events` sink is
+    /// constructed by the given function (or refused altogether should the service not be able
+    /// to post Kubernetes Events).
+    ///
+    /// This function PANICS if the configuration is invalid.
+    pub fn configured(default_path: PathBuf, events: Option<fn() -> Box<dyn Sink>>) -> Auditor {
+        let var = |var: &str| {
+            std::env::var(var)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let sink = var(AUDIT_SINK).unwrap_or_default();
+        let sink: Option<Box<dyn Sink>> = match sink.to_lowercase().as_str() {
+            "" | "none" => None,
+            "file" => Some(Box::new(FileSink::new(
+                var(AUDIT_PATH).map_or(default_path, PathBuf::from),
+            ))),
+            "events" => match events {
+                Some(events) => Some(events()),
+                None => panic!(
+                    "The {} environment variable was set to 'events', however this service \
+                    cannot post Kubernetes Events. It can be one of file, webhook, or none",
+                    AUDIT_SINK
+                ),
+            },
+            "webhook" => Some(Box::new(WebhookSink::new(
+                var(AUDIT_WEBHOOK).unwrap_or_else(|| {
+                    panic!(
+                        "The {} environment variable must be set when {} is webhook",
+                        AUDIT_WEBHOOK, AUDIT_SINK
+                    )
+                }),
+            ))),
+            _ => panic!(
+                "The {} environment variable was set to '{}'. It can be one of file, events, \
+                webhook, or none",
+                AUDIT_SINK, sink
+            ),
+        };
+        let retained = match var(AUDIT_RETAINED) {
+            Some(retained) => retained.parse().unwrap_or_else(|_| {
+                panic!(
+                    "The {} environment variable must be a non-negative integer, got '{}'",
+                    AUDIT_RETAINED, retained
+                )
+            }),
+            None => DEFAULT_AUDIT_RETAINED,
+        };
+        Auditor::new(sink, retained)
+    }
+
+    /// Records the given entry. Failing to write the entry into the sink is logged rather than
+    /// returned, as the operation that it records has already been made either way.
+    pub async fn record(&self, entry: Entry) {
+        {
+            let mut journal = self.journal.lock().unwrap();
+            journal.push_back(entry.clone());
+            while journal.len() > self.retained {
+                journal.pop_front();
+            }
+        }
+        if let Some(sink) = &self.sink {
+            let _writer = self.writer.lock().await;
+            if let Err(err) = sink.write(&entry).await {
+                log::warn!("{}", err);
+            }
+        }
+    }
+
+    /// Returns (at most) the given number of the most recent entries, oldest first.
+    pub fn recent(&self, limit: usize) -> Vec<Entry> {
+        let journal = self.journal.lock().unwrap();
+        journal
+            .iter()
+            .skip(journal.len().saturating_sub(limit))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(target: &str, result: std::result::Result<(), &str>) -> Entry {
+        Entry::new(&Actor::from("alation"), Action::Deploy, target, &result)
+    }
+
+    #[tokio::test]
+    async fn retains_the_most_recent() {
+        let auditor = Auditor::new(None, 2);
+        auditor.record(entry("ocf/a", Ok(()))).await;
+        auditor.record(entry("ocf/b", Err("no"))).await;
+        auditor.record(entry("ocf/c", Ok(()))).await;
+        let targets = |entries: Vec<Entry>| {
+            entries
+                .into_iter()
+                .map(|entry| entry.target)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(targets(auditor.recent(10)), vec!["ocf/b", "ocf/c"]);
+        assert_eq!(targets(auditor.recent(1)), vec!["ocf/c"]);
+        assert_eq!(auditor.recent(2)[0].outcome, Outcome::Failed);
+        assert_eq!(auditor.recent(2)[0].error.as_deref(), Some("no"));
+    }
+
+    #[test]
+    fn serializes() {
+        let entry = entry("ocf/a", Ok(()));
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["actor"], "alation");
+        assert_eq!(json["action"], "deploy");
+        assert_eq!(json["outcome"], "succeeded");
+        assert!(json.get("error").is_none());
+        assert!(json.get("cause").is_none());
+        let json = serde_json::to_value(entry.with_cause("ttl_expired")).unwrap();
+        assert_eq!(json["cause"], "ttl_expired");
+    }
+}
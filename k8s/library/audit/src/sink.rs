@@ -0,0 +1,136 @@
+use crate::Entry;
+use async_trait::async_trait;
+use error::*;
+use result::Result;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// How long the [WebhookSink](WebhookSink) waits upon its webhook before giving up on an entry.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A Sink is somewhere that audit [entries](Entry) are written to. Entries are only ever
+/// appended, never rewritten.
+#[async_trait]
+pub trait Sink: Send + Sync {
+    async fn write(&self, entry: &Entry) -> Result<()>;
+}
+
+/// A FileSink appends every entry, as a line of JSON, to a single file (which may well be within a
+/// mounted PersistentVolumeClaim).
+#[derive(Clone, Debug)]
+pub struct FileSink {
+    path: PathBuf,
+}
+
+impl FileSink {
+    pub fn new<P: AsRef<Path>>(path: P) -> FileSink {
+        FileSink {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for FileSink {
+    async fn write(&self, entry: &Entry) -> Result<()> {
+        let failed = |err: &dyn ToString| AuditSinkFailed::new(entry, err.to_string());
+        let mut line = serde_json::to_vec(entry).map_err(|err| failed(&err))?;
+        line.push(b'\n');
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(|err| failed(&err))?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|err| failed(&err))?;
+        file.write_all(&line).await.map_err(|err| failed(&err))?;
+        file.flush().await.map_err(|err| failed(&err))?;
+        Ok(())
+    }
+}
+
+/// A WebhookSink POSTs every entry, as JSON, to a URL.
+#[derive(Clone, Debug)]
+pub struct WebhookSink {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new<U: ToString>(url: U) -> WebhookSink {
+        WebhookSink {
+            url: url.to_string(),
+            client: reqwest::Client::builder()
+                .timeout(WEBHOOK_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for WebhookSink {
+    async fn write(&self, entry: &Entry) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(entry)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| AuditSinkFailed::new(entry, err))?;
+        Ok(())
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error("Failed to write the audit entry of the {action} of {target} into its sink, {cause}.")]
+#[error_code("AUDIT-1000")]
+pub struct AuditSinkFailed {
+    action: String,
+    target: String,
+    cause: String,
+}
+
+impl AuditSinkFailed {
+    pub fn new<E: ToString>(entry: &Entry, cause: E) -> AuditSinkFailed {
+        AuditSinkFailed {
+            action: entry.action.to_string(),
+            target: entry.target.clone(),
+            cause: cause.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Action, Actor};
+
+    #[tokio::test]
+    async fn appends() {
+        let path = std::env::temp_dir().join(format!("audit-{}.log", std::process::id()));
+        let sink = FileSink::new(&path);
+        for target in &["ocf/a", "ocf/b"] {
+            let entry = Entry::new(
+                &Actor::from("alation"),
+                Action::Delete,
+                target,
+                &std::result::Result::<(), &str>::Ok(()),
+            );
+            sink.write(&entry).await.unwrap();
+        }
+        let written = tokio::fs::read_to_string(&path).await.unwrap();
+        let _ = tokio::fs::remove_file(&path).await;
+        let targets = written
+            .lines()
+            .map(|line| serde_json::from_str::<Entry>(line).unwrap().target)
+            .collect::<Vec<_>>();
+        assert_eq!(targets, vec!["ocf/a", "ocf/b"]);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{
+    BatchRefreshReport, Client, ClientError, Deployment, KeepAliveTicket, PodTicket, Result, Retry,
+};
+use k8s_openapi::api::core::v1::Pod;
+
+impl Client {
+    /// Deploys the given tag using the given name as a prefix for the new pod.
+    ///
+    /// The returned pod is NOT ready for consumption. Callers MUST [wait](Client::wait) on it (by
+    /// the returned ticket) before attempting any communication with it.
+    ///
+    /// As deployment is not idempotent, this call is only retried if the ACM could not be
+    /// reached at all.
+    pub async fn deploy<T: AsRef<str>, N: AsRef<str>>(
+        &self,
+        tag: T,
+        name: N,
+        ttl: Option<u64>,
+    ) -> Result<Deployment> {
+        let url = self.acm("/deploy");
+        let deployed = self
+            .call_with_ticket(Retry::ConnectOnly, |http| {
+                let request = http
+                    .post(&url)
+                    .query(&[("tag", tag.as_ref()), ("name", name.as_ref())]);
+                match ttl {
+                    Some(ttl) => request.query(&[("ttl", ttl)]),
+                    None => request,
+                }
+            })
+            .await?;
+        deployment(url, deployed)
+    }
+
+    /// Deploys the image of the given digest (such as `sha256:cb1f...`) exactly as
+    /// [deploy](Client::deploy) does a tag, except that the pod references the image by its digest
+    /// (and carries it under its `ocf.alation.com/digest` label). A digest that no installed image
+    /// is of fails with a `DigestNotInstalled` (`ACM-3101`).
+    pub async fn deploy_digest<D: AsRef<str>, N: AsRef<str>>(
+        &self,
+        digest: D,
+        name: N,
+        ttl: Option<u64>,
+    ) -> Result<Deployment> {
+        let url = self.acm("/deploy");
+        let deployed = self
+            .call_with_ticket(Retry::ConnectOnly, |http| {
+                let request = http
+                    .post(&url)
+                    .query(&[("digest", digest.as_ref()), ("name", name.as_ref())]);
+                match ttl {
+                    Some(ttl) => request.query(&[("ttl", ttl)]),
+                    None => request,
+                }
+            })
+            .await?;
+        deployment(url, deployed)
+    }
+
+    /// Deploys the given tag exactly as [deploy](Client::deploy) does, except that should the
+    /// generated name collide with an existing pod then a `NameConflict` (`K8S-1002`) describing
+    /// the existing pod is returned rather than the name being regenerated.
+    pub async fn deploy_without_resuffix<T: AsRef<str>, N: AsRef<str>>(
+        &self,
+        tag: T,
+        name: N,
+        ttl: Option<u64>,
+    ) -> Result<Deployment> {
+        let url = self.acm("/deploy");
+        let deployed = self
+            .call_with_ticket(Retry::ConnectOnly, |http| {
+                let request = http.post(&url).query(&[
+                    ("tag", tag.as_ref()),
+                    ("name", name.as_ref()),
+                    ("resuffix", "false"),
+                ]);
+                match ttl {
+                    Some(ttl) => request.query(&[("ttl", ttl)]),
+                    None => request,
+                }
+            })
+            .await?;
+        deployment(url, deployed)
+    }
+
+    /// Dry-runs a deploy of the given tag, returning the pod that would have been created. Nothing
+    /// is created, so this is safe to call as a pre-flight check (say, before an upgrade). A tag
+    /// that is not installed fails with an `ImageNotInstalled` (`ACM-2600`).
+    pub async fn deploy_dry_run<T: AsRef<str>, N: AsRef<str>>(
+        &self,
+        tag: T,
+        name: N,
+    ) -> Result<Pod> {
+        let url = self.acm("/deploy");
+        self.call(Retry::Idempotent, |http| {
+            http.post(&url).query(&[
+                ("tag", tag.as_ref()),
+                ("name", name.as_ref()),
+                ("dry_run", "true"),
+            ])
+        })
+        .await
+    }
+
+    /// Waits for the pod of the given ticket (as returned by its [deploy](Client::deploy)) to
+    /// become fully provisioned and healthy, returning a refreshed ticket.
+    pub async fn wait<T: AsRef<str>>(&self, ticket: T) -> Result<PodTicket> {
+        let url = self.acm("/wait");
+        self.call(Retry::Idempotent, |http| {
+            http.get(&url).query(&[("ticket", ticket.as_ref())])
+        })
+        .await
+    }
+
+    /// Resets the garbage collection countdown for the given ticket.
+    pub async fn refresh<T: AsRef<str>>(&self, ticket: T) -> Result<KeepAliveTicket> {
+        let url = self.acm("/refresh");
+        self.call(Retry::Idempotent, |http| {
+            http.post(&url).query(&[("ticket", ticket.as_ref())])
+        })
+        .await
+    }
+
+    /// Resets the garbage collection countdown for every one of the given tickets at once. The
+    /// failure to refresh one ticket does not fail the call, and is rather reported among the
+    /// results of the returned report.
+    pub async fn refresh_batch<T: AsRef<str>>(&self, tickets: &[T]) -> Result<BatchRefreshReport> {
+        let url = self.acm("/refresh/batch");
+        let tickets: Vec<&str> = tickets.iter().map(AsRef::as_ref).collect();
+        let body = serde_json::to_string(&tickets).expect("a list of strings is always JSON");
+        self.call(Retry::Idempotent, |http| {
+            http.post(&url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone())
+        })
+        .await
+    }
+
+    /// Deletes the pod of the given ticket. Deleting a pod that is already gone succeeds.
+    pub async fn delete<T: AsRef<str>>(&self, ticket: T) -> Result<()> {
+        let url = self.acm("/delete");
+        self.call(Retry::Idempotent, |http| {
+            http.delete(&url).query(&[("ticket", ticket.as_ref())])
+        })
+        .await
+    }
+
+    /// Waits for the pod with the given ID to be entirely gone from Kubernetes. An optional
+    /// timeout (in seconds) overrides the ACM's default.
+    pub async fn wait_delete<I: AsRef<str>>(&self, id: I, timeout: Option<u64>) -> Result<()> {
+        let url = self.acm("/wait-delete");
+        self.call(Retry::Idempotent, |http| {
+            let request = http.get(&url).query(&[("id", id.as_ref())]);
+            match timeout {
+                Some(timeout) => request.query(&[("timeout", timeout)]),
+                None => request,
+            }
+        })
+        .await
+    }
+}
+
+/// Pairs the pod deployed at the given URL with its ticket, which every deploy MUST be answered
+/// with.
+fn deployment(url: String, (pod, ticket): (Pod, Option<String>)) -> Result<Deployment> {
+    match ticket {
+        Some(ticket) => Ok(Deployment { pod, ticket }),
+        None => Err(ClientError::MissingTicket { url }),
+    }
+}
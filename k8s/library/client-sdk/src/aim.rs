@@ -0,0 +1,204 @@
+use crate::{
+    send, Client, ClientError, Deprecation, Image, Inspection, InstallProgress, Lookup, Result,
+    Retry,
+};
+use std::path::Path;
+use tokio_util::io::ReaderStream;
+
+impl Client {
+    /// Installs the OCI image at the given path into the AIM's configured registry.
+    ///
+    /// The image is streamed from disk rather than read into memory. As the body can only be
+    /// streamed once, and as installation is not idempotent, this call is never retried.
+    pub async fn install<P: AsRef<Path>>(&self, image: P) -> Result<Image> {
+        let body = stream(image.as_ref()).await?;
+        send(self.http.post(self.aim("/install")).body(body)).await
+    }
+
+    /// Installs the OCI image at the given path exactly as [install](Client::install) does, except
+    /// that the AIM verifies the upload against the given SHA-256 (in hex) before importing it. An
+    /// upload that was corrupted along the way fails with a `ChecksumMismatch` (`AIM-1801`).
+    pub async fn install_with_checksum<P: AsRef<Path>, C: AsRef<str>>(
+        &self,
+        image: P,
+        sha256: C,
+    ) -> Result<Image> {
+        let body = stream(image.as_ref()).await?;
+        send(
+            self.http
+                .post(self.aim("/install"))
+                .header("X-Checksum-Sha256", sha256.as_ref())
+                .body(body),
+        )
+        .await
+    }
+
+    /// Uploads the OCI image at the given path and returns as soon as the upload completes,
+    /// leaving the AIM to import, retag, and push the image in the background. The returned
+    /// [InstallProgress](InstallProgress) carries the ID to poll via
+    /// [install_progress](Client::install_progress).
+    ///
+    /// As with [install](Client::install), this call is never retried.
+    pub async fn install_detached<P: AsRef<Path>>(&self, image: P) -> Result<InstallProgress> {
+        let body = stream(image.as_ref()).await?;
+        send(
+            self.http
+                .post(self.aim("/install"))
+                .query(&[("detach", "true")])
+                .body(body),
+        )
+        .await
+    }
+
+    /// Installs the image at the given reference within a remote registry (such as
+    /// `public.ecr.aws/alation/oracle:1.2.0`), which the AIM pulls itself rather than having it
+    /// uploaded. Credentials, if any, are a `(username, password)` pair for the remote registry.
+    ///
+    /// As with [install](Client::install), this call is never retried.
+    pub async fn install_remote<R: AsRef<str>>(
+        &self,
+        reference: R,
+        credentials: Option<(&str, &str)>,
+    ) -> Result<Image> {
+        let mut body = serde_json::json!({ "reference": reference.as_ref() });
+        if let Some((username, password)) = credentials {
+            body["username"] = username.into();
+            body["password"] = password.into();
+        }
+        send(
+            self.http
+                .post(self.aim("/install/remote"))
+                .header("Content-Type", "application/json")
+                .body(body.to_string()),
+        )
+        .await
+    }
+
+    /// Returns the progress of the install with the given ID, or a [NotFound](ClientError::NotFound)
+    /// if the AIM does not know of the install.
+    pub async fn install_progress<I: AsRef<str>>(&self, id: I) -> Result<InstallProgress> {
+        let url = self.aim("/install/progress");
+        self.call(Retry::Idempotent, |http| {
+            http.get(&url).query(&[("id", id.as_ref())])
+        })
+        .await
+    }
+
+    /// Cancels the install with the given ID, returning its progress, or a
+    /// [NotFound](ClientError::NotFound) if the AIM does not know of the install. Only an install
+    /// that is underway is cancelled.
+    pub async fn cancel_install<I: AsRef<str>>(&self, id: I) -> Result<InstallProgress> {
+        let url = self.aim("/install");
+        self.call(Retry::Idempotent, |http| {
+            http.delete(&url).query(&[("id", id.as_ref())])
+        })
+        .await
+    }
+
+    /// Returns every image installed in the AIM's configured registry.
+    pub async fn list(&self) -> Result<Vec<Image>> {
+        let url = self.aim("/list");
+        self.call(Retry::Idempotent, |http| http.get(&url)).await
+    }
+
+    /// Returns the image for the given tag, or a [NotFound](ClientError::NotFound) if no such
+    /// tag is installed.
+    pub async fn get<T: AsRef<str>>(&self, tag: T) -> Result<Image> {
+        let url = self.aim("/get");
+        self.call(Retry::Idempotent, |http| {
+            http.get(&url).query(&[("tag", tag.as_ref())])
+        })
+        .await
+    }
+
+    /// Returns the [Inspection](Inspection) of the given tag, or a [NotFound](ClientError::NotFound)
+    /// if no such tag is installed.
+    pub async fn inspect<T: AsRef<str>>(&self, tag: T) -> Result<Inspection> {
+        let url = self.aim("/inspect");
+        self.call(Retry::Idempotent, |http| {
+            http.get(&url).query(&[("tag", tag.as_ref())])
+        })
+        .await
+    }
+
+    /// Returns whether the given tag is installed. Unlike [get](Client::get), a tag that is not
+    /// installed is NOT an error.
+    pub async fn lookup<T: AsRef<str>>(&self, tag: T) -> Result<Lookup> {
+        let url = self.aim("/lookup");
+        self.call(Retry::Idempotent, |http| {
+            http.get(&url).query(&[("tag", tag.as_ref())])
+        })
+        .await
+    }
+
+    /// Deprecates the given tag with the given message and an optional sunset (a Unix timestamp),
+    /// replacing any previous deprecation of the tag.
+    pub async fn deprecate<T: AsRef<str>, M: AsRef<str>>(
+        &self,
+        tag: T,
+        message: M,
+        sunset: Option<i64>,
+    ) -> Result<Deprecation> {
+        let url = self.aim("/deprecate");
+        self.call(Retry::Idempotent, |http| {
+            let request = http
+                .post(&url)
+                .query(&[("tag", tag.as_ref()), ("message", message.as_ref())]);
+            match sunset {
+                Some(sunset) => request.query(&[("sunset", sunset)]),
+                None => request,
+            }
+        })
+        .await
+    }
+
+    /// Lifts the deprecation of the given tag. Lifting a deprecation that does not exist succeeds.
+    pub async fn undeprecate<T: AsRef<str>>(&self, tag: T) -> Result<()> {
+        let url = self.aim("/deprecate");
+        self.call(Retry::Idempotent, |http| {
+            http.delete(&url).query(&[("tag", tag.as_ref())])
+        })
+        .await
+    }
+
+    /// Promotes the given tag to the given alias (say, `production`), returning the alias's image.
+    /// Promoting a tag to an alias that it already carries succeeds.
+    pub async fn promote<T: AsRef<str>, A: AsRef<str>>(&self, tag: T, alias: A) -> Result<Image> {
+        let url = self.aim("/promote");
+        self.call(Retry::Idempotent, |http| {
+            http.post(&url)
+                .query(&[("tag", tag.as_ref()), ("alias", alias.as_ref())])
+        })
+        .await
+    }
+
+    /// Returns every tag that refers to the same digest as the given tag, the given tag included.
+    pub async fn aliases<T: AsRef<str>>(&self, tag: T) -> Result<Vec<Image>> {
+        let url = self.aim("/aliases");
+        self.call(Retry::Idempotent, |http| {
+            http.get(&url).query(&[("tag", tag.as_ref())])
+        })
+        .await
+    }
+
+    /// Uninstalls the given tag from the AIM's configured registry. Uninstalling a tag
+    /// that does not exist succeeds.
+    pub async fn uninstall<T: AsRef<str>>(&self, tag: T) -> Result<()> {
+        let url = self.aim("/uninstall");
+        self.call(Retry::Idempotent, |http| {
+            http.delete(&url).query(&[("tag", tag.as_ref())])
+        })
+        .await
+    }
+}
+
+/// Opens the file at the given path as a streaming request body.
+async fn stream(path: &Path) -> Result<reqwest::Body> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|cause| ClientError::Io {
+            path: path.display().to_string(),
+            cause,
+        })?;
+    Ok(reqwest::Body::wrap_stream(ReaderStream::new(file)))
+}
@@ -0,0 +1,180 @@
+use error::{AcmError, HttpCode, Kind, Status};
+use response::GenericError;
+use thiserror::Error;
+
+/// A ClientError is any failure that occurred while calling either the ACM or the AIM.
+///
+/// Errors returned by the remote service itself are sorted by their HTTP status into
+/// [NotFound](ClientError::NotFound), [Gone](ClientError::Gone), and [Remote](ClientError::Remote).
+/// In all three cases the [kind](error::Kind), [error code](error::AcmError::error_code), and
+/// [HTTP code](error::HttpCode) of the ClientError are those of the original error. As such,
+/// a service that forwards a ClientError to its own callers forwards it verbatim.
+///
+/// ```
+/// use client_sdk::ClientError;
+/// use error::{AcmError, Kind};
+/// use response::ParsedResponse;
+///
+/// let body = r#"{
+///     "payload": null,
+///     "error": {"kind": "TagNotFound", "error_code": "AIM-1000", "message": "nope", "cause": null}
+/// }"#;
+/// let err: ClientError = ParsedResponse::<()>::from_slice(404, body.as_bytes())
+///     .unwrap()
+///     .into_result()
+///     .unwrap_err()
+///     .into();
+/// assert!(matches!(err, ClientError::NotFound(_)));
+/// assert_eq!(err.kind(), "TagNotFound");
+/// assert_eq!(err.error_code(), Some("AIM-1000"));
+/// ```
+#[derive(Error, Debug)]
+pub enum ClientError {
+    /// The request could not be sent, or the response could not be received.
+    #[error("The request to {url} failed")]
+    Transport {
+        url: String,
+        #[source]
+        cause: reqwest::Error,
+    },
+    /// A response was received, but it was not a valid response envelope. This is typically
+    /// the result of an intermediary (such as a load balancer) answering on the service's behalf.
+    #[error("The response from {url} (HTTP {status}) was not a valid response envelope")]
+    Malformed {
+        url: String,
+        status: u16,
+        #[source]
+        cause: serde_json::Error,
+    },
+    /// A deploy was answered without the ticket of its pod, which is typically the result of an
+    /// intermediary having stripped the [ticket header](crate::TICKET_HEADER).
+    #[error("The deploy at {url} was answered without the ticket of its pod")]
+    MissingTicket { url: String },
+    /// The image to install could not be read from the local filesystem.
+    #[error("Failed to read the image at {path}")]
+    Io {
+        path: String,
+        #[source]
+        cause: std::io::Error,
+    },
+    /// The remote service reported that the requested resource does not exist (HTTP 404).
+    #[error(transparent)]
+    NotFound(GenericError),
+    /// The remote service reported that the requested resource once existed but has since
+    /// been deleted (HTTP 410).
+    #[error(transparent)]
+    Gone(GenericError),
+    /// Any other error reported by the remote service.
+    #[error(transparent)]
+    Remote(GenericError),
+}
+
+impl ClientError {
+    pub(crate) fn transport(cause: reqwest::Error) -> ClientError {
+        ClientError::Transport {
+            url: cause.url().map(|url| url.to_string()).unwrap_or_default(),
+            cause,
+        }
+    }
+
+    /// Returns the error returned by the remote service, if this error originated there.
+    pub fn remote(&self) -> Option<&GenericError> {
+        match self {
+            ClientError::NotFound(err) | ClientError::Gone(err) | ClientError::Remote(err) => {
+                Some(err)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<GenericError> for ClientError {
+    fn from(err: GenericError) -> Self {
+        let status = err.http_code();
+        if status == Status::NotFound {
+            ClientError::NotFound(err)
+        } else if status == Status::Gone {
+            ClientError::Gone(err)
+        } else {
+            ClientError::Remote(err)
+        }
+    }
+}
+
+impl HttpCode for ClientError {
+    fn http_code(&self) -> Status {
+        match self {
+            ClientError::Transport { .. }
+            | ClientError::Malformed { .. }
+            | ClientError::MissingTicket { .. } => Status::BadGateway,
+            ClientError::Io { .. } => Status::InternalServerError,
+            ClientError::NotFound(err) | ClientError::Gone(err) | ClientError::Remote(err) => {
+                err.http_code()
+            }
+        }
+    }
+}
+
+impl Kind for ClientError {
+    fn kind(&self) -> String {
+        match self {
+            ClientError::Transport { .. } => "ClientError::Transport".to_string(),
+            ClientError::Malformed { .. } => "ClientError::Malformed".to_string(),
+            ClientError::MissingTicket { .. } => "ClientError::MissingTicket".to_string(),
+            ClientError::Io { .. } => "ClientError::Io".to_string(),
+            ClientError::NotFound(err) | ClientError::Gone(err) | ClientError::Remote(err) => {
+                err.kind()
+            }
+        }
+    }
+}
+
+impl AcmError for ClientError {
+    fn error_code(&self) -> Option<&str> {
+        match self {
+            ClientError::Transport { .. } => Some("SDK-1000"),
+            ClientError::Malformed { .. } => Some("SDK-1001"),
+            ClientError::Io { .. } => Some("SDK-1002"),
+            ClientError::MissingTicket { .. } => Some("SDK-1003"),
+            ClientError::NotFound(err) | ClientError::Gone(err) | ClientError::Remote(err) => {
+                err.error_code()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use response::ParsedResponse;
+
+    fn remote(status: u16, kind: &str) -> ClientError {
+        let body = format!(
+            r#"{{"payload": null, "error": {{"kind": "{}", "error_code": null, "message": "m", "cause": null}}}}"#,
+            kind
+        );
+        ParsedResponse::<()>::from_slice(status, body.as_bytes())
+            .unwrap()
+            .into_result()
+            .unwrap_err()
+            .into()
+    }
+
+    #[test]
+    fn sorted_by_status() {
+        assert!(matches!(
+            remote(404, "PodManagerNotFound"),
+            ClientError::NotFound(_)
+        ));
+        assert!(matches!(remote(410, "PodWasDeleted"), ClientError::Gone(_)));
+        assert!(matches!(remote(500, "PodCrashed"), ClientError::Remote(_)));
+    }
+
+    #[test]
+    fn forwards_remote_identity() {
+        let err = remote(410, "PodWasDeleted");
+        assert_eq!(err.kind(), "PodWasDeleted");
+        assert_eq!(err.http_code(), Status::Gone);
+        assert_eq!(err.remote().unwrap().message(), "m");
+    }
+}
@@ -0,0 +1,222 @@
+//! A typed, asynchronous, client for the ACM and the AIM.
+//!
+//! Every endpoint exposed by either service is available as a method on [Client](Client).
+//! The [response envelope](response::Response) is unwrapped on your behalf, and any error
+//! returned by the remote service is mapped into a [ClientError](ClientError) which carries
+//! the original [kind](error::Kind), [error code](error::AcmError::error_code), message, and
+//! HTTP status.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), client_sdk::ClientError> {
+//! use client_sdk::Client;
+//!
+//! let client = Client::default();
+//! let image = client.install("oracle.img").await?;
+//! let deployment = client.deploy(&image.tag, "oracle", None).await?;
+//! let leased = client.wait(&deployment.ticket).await?;
+//! let refreshed = client.refresh(&leased.ticket.ticket).await?;
+//! client.delete(&refreshed.ticket).await?;
+//! # Ok(())
+//! # }
+//! ```
+mod acm;
+mod aim;
+mod errors;
+mod types;
+
+pub use errors::ClientError;
+pub use types::{
+    BatchRefresh, BatchRefreshReport, ConnectorLabels, Deployment, Deprecation, Image, ImageConfig,
+    Inspection, InstallProgress, KeepAliveTicket, Lookup, Phase, Platform, PodTicket,
+};
+
+use backoff::backoff::Backoff;
+use backoff::ExponentialBackoff;
+use response::ParsedResponse;
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+/// The address of the ACM when running within the cluster.
+pub const DEFAULT_ACM: &str = "http://acm.ocf-system";
+/// The address of the AIM when running within the cluster.
+pub const DEFAULT_AIM: &str = "http://aim.ocf-system";
+/// The header under which the ACM returns the ticket of a deployed pod.
+pub const TICKET_HEADER: &str = "X-OCF-Ticket";
+/// The default maximum amount of time spent retrying any single call.
+pub const DEFAULT_RETRY_BUDGET: Duration = Duration::from_secs(30);
+
+/// The result of every call made via the [Client](Client).
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+/// A Client holds the addresses of the ACM and the AIM as well as a pooled HTTP client.
+/// Clients are cheap to clone and clones share the same connection pool.
+#[derive(Clone, Debug)]
+pub struct Client {
+    http: reqwest::Client,
+    acm: String,
+    aim: String,
+    retry_budget: Duration,
+}
+
+/// Constructs a Client targeting the in-cluster [ACM](DEFAULT_ACM) and [AIM](DEFAULT_AIM).
+impl Default for Client {
+    fn default() -> Self {
+        Client::new(DEFAULT_ACM, DEFAULT_AIM)
+    }
+}
+
+impl Client {
+    /// Constructs a Client targeting the given base addresses of the ACM and the AIM
+    /// (e.g. `http://localhost:8000`).
+    pub fn new<A: Into<String>, B: Into<String>>(acm: A, aim: B) -> Client {
+        Client {
+            http: reqwest::Client::new(),
+            acm: acm.into().trim_end_matches('/').to_string(),
+            aim: aim.into().trim_end_matches('/').to_string(),
+            retry_budget: DEFAULT_RETRY_BUDGET,
+        }
+    }
+
+    /// Sets the maximum amount of time spent retrying any single call. A budget of zero
+    /// disables retries altogether.
+    pub fn with_retry_budget(mut self, budget: Duration) -> Client {
+        self.retry_budget = budget;
+        self
+    }
+
+    fn acm<P: AsRef<str>>(&self, path: P) -> String {
+        format!("{}{}", self.acm, path.as_ref())
+    }
+
+    fn aim<P: AsRef<str>>(&self, path: P) -> String {
+        format!("{}{}", self.aim, path.as_ref())
+    }
+
+    /// Sends the request built by `request`, retrying according to the given [Retry](Retry)
+    /// policy with exponential backoff until either the call succeeds, the failure is not
+    /// retryable, or the retry budget is exhausted.
+    async fn call<T, F>(&self, retry: Retry, request: F) -> Result<T>
+    where
+        T: DeserializeOwned,
+        F: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+    {
+        let (payload, _) = self.call_with_ticket(retry, request).await?;
+        Ok(payload)
+    }
+
+    /// Calls exactly as [call](Client::call) does, also returning the [ticket](TICKET_HEADER) that
+    /// the response bore (if any).
+    async fn call_with_ticket<T, F>(&self, retry: Retry, request: F) -> Result<(T, Option<String>)>
+    where
+        T: DeserializeOwned,
+        F: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+    {
+        let mut backoff = ExponentialBackoff {
+            max_elapsed_time: Some(self.retry_budget),
+            ..Default::default()
+        };
+        loop {
+            let err = match send_with_ticket(request(&self.http)).await {
+                Ok(payload) => return Ok(payload),
+                Err(err) => err,
+            };
+            if !retry.permits(&err) {
+                return Err(err);
+            }
+            match backoff.next_backoff() {
+                Some(duration) => tokio::time::sleep(duration).await,
+                None => return Err(err),
+            }
+        }
+    }
+}
+
+/// Sends the given request exactly once and unwraps the resulting response envelope.
+async fn send<T: DeserializeOwned>(request: reqwest::RequestBuilder) -> Result<T> {
+    let (payload, _) = send_with_ticket(request).await?;
+    Ok(payload)
+}
+
+/// Sends the given request exactly as [send](send) does, also returning the
+/// [ticket](TICKET_HEADER) that the response bore (if any).
+async fn send_with_ticket<T: DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+) -> Result<(T, Option<String>)> {
+    let response = request.send().await.map_err(ClientError::transport)?;
+    let url = response.url().to_string();
+    let status = response.status().as_u16();
+    let ticket = response
+        .headers()
+        .get(TICKET_HEADER)
+        .and_then(|ticket| ticket.to_str().ok())
+        .map(str::to_string);
+    let body = response.bytes().await.map_err(ClientError::transport)?;
+    let payload = ParsedResponse::from_slice(status, &body)
+        .map_err(|cause| ClientError::Malformed { url, status, cause })?
+        .into_result()
+        .map_err(ClientError::from)?;
+    Ok((payload, ticket))
+}
+
+/// A Retry describes which failures of a given call may be safely retried.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Retry {
+    /// The call is idempotent and may be retried upon any transport failure or upon
+    /// receiving a gateway error from an intermediary.
+    Idempotent,
+    /// The call is NOT idempotent and may only be retried if it provably never reached
+    /// the server (that is, the connection itself could not be established).
+    ConnectOnly,
+}
+
+impl Retry {
+    fn permits(self, err: &ClientError) -> bool {
+        match (self, err) {
+            (_, ClientError::Transport { cause, .. }) if cause.is_connect() => true,
+            (Retry::Idempotent, ClientError::Transport { .. }) => true,
+            (Retry::Idempotent, ClientError::Malformed { status, .. }) => {
+                matches!(status, 502..=504)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trims_trailing_slashes() {
+        let client = Client::new("http://localhost:8000/", "http://localhost:8001");
+        assert_eq!(client.acm("/wait"), "http://localhost:8000/wait");
+        assert_eq!(client.aim("/list"), "http://localhost:8001/list");
+    }
+
+    #[test]
+    fn gateway_errors_are_only_retried_when_idempotent() {
+        let malformed = |status| ClientError::Malformed {
+            url: "http://acm.ocf-system/wait".to_string(),
+            status,
+            cause: serde_json::from_str::<()>("<html>").unwrap_err(),
+        };
+        assert!(Retry::Idempotent.permits(&malformed(503)));
+        assert!(!Retry::Idempotent.permits(&malformed(200)));
+        assert!(!Retry::ConnectOnly.permits(&malformed(503)));
+    }
+
+    #[test]
+    fn remote_errors_are_never_retried() {
+        let body = r#"{
+            "payload": null,
+            "error": {"kind": "NotReady", "error_code": "ACM-1300", "message": "nope", "cause": null}
+        }"#;
+        let err: ClientError = ParsedResponse::<()>::from_slice(503, body.as_bytes())
+            .unwrap()
+            .into_result()
+            .unwrap_err()
+            .into();
+        assert!(!Retry::Idempotent.permits(&err));
+        assert!(!Retry::ConnectOnly.permits(&err));
+    }
+}
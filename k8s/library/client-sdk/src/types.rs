@@ -0,0 +1,271 @@
+use k8s_openapi::api::core::v1::Pod;
+use response::GenericError;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// An Image is a pairing of a tag and a digest as installed in the AIM's configured registry.
+#[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Image {
+    pub tag: String,
+    pub digest: String,
+    /// The labels that name the connector within the image, should it have any.
+    #[serde(default)]
+    pub labels: ConnectorLabels,
+}
+
+/// ConnectorLabels are the human-meaningful labels of an image, read out of the
+/// `org.opencontainers.image.title`, `.version`, and `.vendor` labels that it was built with.
+#[derive(Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct ConnectorLabels {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub vendor: Option<String>,
+}
+
+/// An Inspection is what the AIM knows about an installed image beyond its `tag:digest` pairing.
+#[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Inspection {
+    pub tag: String,
+    pub digest: String,
+    /// Every platform that the image may be run on. This list is empty if the
+    /// registry did not report the image's platform.
+    pub platforms: Vec<Platform>,
+    /// The image's deprecation, if it has been deprecated.
+    #[serde(default)]
+    pub deprecation: Option<Deprecation>,
+    /// How the image runs. This is absent from the inspections of AIMs that predate it.
+    #[serde(default)]
+    pub config: Option<ImageConfig>,
+}
+
+/// An ImageConfig is what an image's config blob says of how it runs, as reported by the AIM.
+#[derive(Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct ImageConfig {
+    #[serde(default)]
+    pub entrypoint: Vec<String>,
+    #[serde(default)]
+    pub cmd: Vec<String>,
+    /// Every port that the image exposes, in the form of `<port>/<protocol>` (e.g. `8080/tcp`).
+    #[serde(default)]
+    pub exposed_ports: Vec<String>,
+    #[serde(default)]
+    pub env: Vec<String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub layers: usize,
+    /// The size, in bytes, of the config blob and every layer.
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub created: Option<String>,
+}
+
+/// A Lookup is whether a tag is installed in the AIM's configured registry, as returned by
+/// [lookup](crate::Client::lookup).
+#[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Lookup {
+    pub tag: String,
+    /// The image reference (that is, `<registry>/<repository>:<tag>`) that the tag is pulled by.
+    pub reference: String,
+    pub installed: bool,
+    /// The digest of the tag, should it be installed.
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
+/// A Deprecation marks an installed image as slated for retirement. The ACM warns upon deploying
+/// a deprecated image and (by default) refuses to deploy it at all once its `sunset` has passed.
+#[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Deprecation {
+    pub tag: String,
+    pub message: String,
+    /// The Unix timestamp after which the image SHOULD no longer be deployed.
+    #[serde(default)]
+    pub sunset: Option<i64>,
+    /// The Unix timestamp at which the image was deprecated.
+    pub deprecated_at: i64,
+}
+
+/// A Platform is the operating system and CPU architecture that an image was built for.
+/// It is displayed as `os/architecture[/variant]`.
+#[derive(Deserialize, Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Platform {
+    pub os: String,
+    pub architecture: String,
+    #[serde(default)]
+    pub variant: Option<String>,
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.variant.as_ref() {
+            Some(variant) => write!(f, "{}/{}/{}", self.os, self.architecture, variant),
+            None => write!(f, "{}/{}", self.os, self.architecture),
+        }
+    }
+}
+
+/// An InstallProgress is the progress of an install as reported by
+/// [install_progress](crate::Client::install_progress).
+#[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct InstallProgress {
+    pub id: String,
+    #[serde(flatten)]
+    pub phase: Phase,
+    /// The size of the uploaded image. This is not known until the upload completes.
+    pub bytes: Option<u64>,
+    /// The number of bytes pushed to the registry. This remains zero until the push completes.
+    pub bytes_pushed: u64,
+}
+
+/// A Phase is a step of the AIM's install pipeline.
+#[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum Phase {
+    /// The install is waiting for admission. A position of `1` is next in line.
+    Queued {
+        position: usize,
+    },
+    Uploading,
+    Importing,
+    Retagging,
+    Pushing,
+    Installed {
+        image: Image,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+impl Phase {
+    /// Whether or not the install has either succeeded or failed.
+    pub fn finished(&self) -> bool {
+        matches!(self, Phase::Installed { .. } | Phase::Failed { .. })
+    }
+}
+
+/// A KeepAliveTicket is issued by the ACM to clients who lease out pods.
+#[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct KeepAliveTicket {
+    /// The unique identifier for this ticket. This is the value given to
+    /// [refresh](crate::Client::refresh).
+    pub ticket: String,
+    /// The Unix timestamp of the exact moment when this ticket becomes invalid and
+    /// deletion of the backing pod will commence.
+    pub execution_date: i64,
+}
+
+/// A BatchRefresh is the outcome of the refresh of a single ticket within a
+/// [batch refresh](crate::Client::refresh_batch). Exactly one of `ticket` and `error` is present.
+#[derive(Deserialize, Debug)]
+pub struct BatchRefresh {
+    pub ticket: Option<KeepAliveTicket>,
+    /// The error that the refresh was met with, as the ACM reported it.
+    pub error: Option<GenericError>,
+}
+
+/// A BatchRefreshReport is the outcome of a [batch refresh](crate::Client::refresh_batch), keyed
+/// by ticket.
+#[derive(Deserialize, Debug)]
+pub struct BatchRefreshReport {
+    pub namespace: String,
+    /// The number of tickets that were successfully refreshed.
+    pub refreshed: usize,
+    pub results: BTreeMap<String, BatchRefresh>,
+}
+
+/// A Deployment is a freshly deployed pod (which is NOT yet ready for consumption) and the ticket
+/// that it is to be [waited](crate::Client::wait) on, refreshed, and deleted with, as returned by
+/// [deploy](crate::Client::deploy).
+#[derive(Debug, Clone)]
+pub struct Deployment {
+    pub pod: Pod,
+    pub ticket: String,
+}
+
+/// A PodTicket is the pairing of a fully provisioned pod and its [KeepAliveTicket](KeepAliveTicket)
+/// as returned by [wait](crate::Client::wait).
+#[derive(Deserialize, Debug, Clone)]
+pub struct PodTicket {
+    pub pod: Pod,
+    pub ticket: KeepAliveTicket,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn install_progress() {
+        let raw = r#"{"id": "a", "phase": "pushing", "bytes": 42, "bytes_pushed": 0}"#;
+        let progress: InstallProgress = serde_json::from_str(raw).unwrap();
+        assert_eq!(progress.phase, Phase::Pushing);
+        assert_eq!(progress.bytes, Some(42));
+        assert!(!progress.phase.finished());
+        let raw = r#"{
+            "id": "a",
+            "phase": "installed",
+            "image": {"tag": "t", "digest": "d"},
+            "bytes": 42,
+            "bytes_pushed": 42
+        }"#;
+        let progress: InstallProgress = serde_json::from_str(raw).unwrap();
+        assert!(progress.phase.finished());
+    }
+
+    #[test]
+    fn inspection_deprecation() {
+        let raw = r#"{"tag": "t", "digest": "d", "platforms": []}"#;
+        let inspection: Inspection = serde_json::from_str(raw).unwrap();
+        assert_eq!(inspection.deprecation, None);
+        let raw = r#"{
+            "tag": "t",
+            "digest": "d",
+            "platforms": [],
+            "deprecation": {"tag": "t", "message": "old", "sunset": 10, "deprecated_at": 5}
+        }"#;
+        let inspection: Inspection = serde_json::from_str(raw).unwrap();
+        let deprecation = inspection.deprecation.unwrap();
+        assert_eq!(deprecation.message, "old");
+        assert_eq!(deprecation.sunset, Some(10));
+    }
+
+    #[test]
+    fn lookup_of_an_absent_tag() {
+        let raw = r#"{"tag": "t", "reference": "registry.kurl/ocf:t", "installed": false}"#;
+        let lookup: Lookup = serde_json::from_str(raw).unwrap();
+        assert!(!lookup.installed);
+        assert_eq!(lookup.digest, None);
+    }
+
+    #[test]
+    fn batch_refresh_report() {
+        let raw = r#"{
+            "namespace": "ocf",
+            "refreshed": 1,
+            "results": {
+                "a": {"ticket": {"ticket": "a", "execution_date": 10}, "error": null},
+                "b": {
+                    "ticket": null,
+                    "error": {"kind": "PodWasDeleted", "error_code": "ACM-1002", "message": "gone"}
+                }
+            }
+        }"#;
+        let report: BatchRefreshReport = serde_json::from_str(raw).unwrap();
+        assert_eq!(report.refreshed, 1);
+        assert_eq!(
+            report.results["a"].ticket.as_ref().unwrap().execution_date,
+            10
+        );
+        assert_eq!(
+            report.results["b"].error.as_ref().unwrap().message(),
+            "gone"
+        );
+    }
+}
@@ -0,0 +1,840 @@
+use crate::{configured, Invalid, Source, Validator};
+use kind::Kind;
+use secret::Secret;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// The minimum length of each of the [ticket signing keys](AcmConfig::ticket_signing_keys).
+pub const MINIMUM_TICKET_SIGNING_KEY_LENGTH: usize = 32;
+
+/// The commands that operators may exec within connectors should
+/// [EXEC_COMMANDS](AcmConfig::exec_commands) not be set. These are short, read-only, commands that
+/// are useful for debugging a connector.
+pub const DEFAULT_EXEC_COMMANDS: &[&str] = &[
+    "env",
+    "cat /proc/meminfo",
+    "cat /proc/cpuinfo",
+    "cat /proc/1/status",
+    "df -h",
+    "ps aux",
+];
+
+/// The namespace of the OCF's own components, which connectors may never be deployed into.
+const OCF_SYSTEM_NAMESPACE: &str = "ocf-system";
+
+/// The settings of the ACM.
+///
+/// Settings that belong to one of our libraries (say, the retry policies) are still
+/// read by that library. Their values may nonetheless be given within the
+/// [configuration file](crate::CONFIG_FILE).
+#[derive(Clone, Debug, Serialize, Kind)]
+pub struct AcmConfig {
+    /// The registry that connector images are pulled from, configured by `REGISTRY`. This
+    /// defaults to `registry.kurl`.
+    pub registry: String,
+    /// The repository within the [registry](AcmConfig::registry) that connector images are kept
+    /// in, configured by `REPOSITORY`. This defaults to `ocf`.
+    pub repository: String,
+    /// The directory of the ACM's store, configured by `STORE_PATH`. This defaults to
+    /// `/var/lib/acm`.
+    pub store_path: PathBuf,
+    /// The token that admits operators to the endpoints meant for them, configured by
+    /// `OPERATOR_TOKEN`. Those endpoints are disabled altogether should it not be set.
+    pub operator_token: Option<Secret>,
+    /// The webhooks that are notified of the lifecycle transitions of managed pods (their becoming
+    /// ready, crashing, being garbage collected, and being deleted), configured by
+    /// `LIFECYCLE_WEBHOOKS` as a comma separated list of URLs. No webhooks are notified by default.
+    pub lifecycle_webhooks: Vec<String>,
+    /// The key with which every lifecycle notification is signed (as an HMAC-SHA256 of its body),
+    /// configured by `LIFECYCLE_WEBHOOK_SECRET`. This is mandatory should there be any
+    /// [lifecycle webhooks](AcmConfig::lifecycle_webhooks).
+    pub lifecycle_webhook_secret: Option<Secret>,
+    /// The URL of the AIM that the ACM consults about images (such as whether a tag is installed),
+    /// configured by `AIM`. This defaults to the in-cluster `http://aim.ocf-system`.
+    pub aim: String,
+    /// Whether a deploy first asks the [AIM](AcmConfig::aim) whether its tag is installed, refusing
+    /// the deploy outright should it not be, configured by `VALIDATE_TAGS`. This defaults to `true`.
+    pub validate_tags: bool,
+    /// Whether the ACM forwards requests to its connectors through its `/proxy` endpoints,
+    /// configured by `PROXY`. This defaults to `false`, in which case the endpoints are not
+    /// mounted at all. The proxy may only be enabled alongside
+    /// [ticket signing keys](AcmConfig::ticket_signing_keys), as it is the ticket alone that
+    /// keeps one client from calling the connector of another.
+    pub proxy: bool,
+    /// The keys with which keep-alive tickets are signed (as an HMAC-SHA256), configured by
+    /// `TICKET_SIGNING_KEYS` as a comma separated list. The first key signs every ticket, while
+    /// every key is accepted when verifying one, so that keys may be rotated by prepending the new
+    /// key and later dropping the old one. Tickets are merely the names of their pods (and so are
+    /// not signed at all) should there be no keys.
+    pub ticket_signing_keys: Vec<Secret>,
+    /// The number of idle, pre-warmed, pods that are kept running for each tag, configured by
+    /// `WARM_POOLS` as a comma separated list of `<tag>=<size>` entries. A deploy of a pooled tag is
+    /// handed one of them rather than waiting on a cold start. No pods are pooled by default.
+    pub warm_pools: BTreeMap<String, usize>,
+    /// The number of seconds that a pooled pod may sit idle before it is replaced by a fresh one,
+    /// configured by `WARM_POOL_MAX_IDLE`. This defaults to one hour.
+    pub warm_pool_max_idle: u64,
+    /// The commands (modulo whitespace) that operators may exec within connectors, configured by
+    /// `EXEC_COMMANDS` as a comma separated list. This defaults to the
+    /// [DEFAULT_EXEC_COMMANDS](DEFAULT_EXEC_COMMANDS).
+    pub exec_commands: BTreeSet<String>,
+    /// The sink that the logs of every connector are archived into, configured by `LOG_SINK`.
+    /// Either `file` (a file per job beneath the [LOG_SINK_PATH](AcmConfig::log_sink_path)), `s3`
+    /// (an object per job within the [LOG_SINK_BUCKET](AcmConfig::log_sink_bucket)), `memory`
+    /// (the last [LOG_SINK_BYTES](AcmConfig::log_sink_bytes) of each of the most recent jobs), or
+    /// `none` (case insensitive). Connector logs are not archived by default.
+    pub log_sink: String,
+    /// The directory that the `file` sink writes into, configured by `LOG_SINK_PATH`. This
+    /// defaults to the `logs` directory of the [store](AcmConfig::store_path).
+    pub log_sink_path: PathBuf,
+    /// The bucket that the `s3` sink writes into, configured by `LOG_SINK_BUCKET`. This is
+    /// mandatory when the [sink](AcmConfig::log_sink) is `s3`. The credentials and region are
+    /// taken from the usual `AWS_*` settings.
+    pub log_sink_bucket: Option<String>,
+    /// The prefix (such as `ocf/`) of the key of every object written by the `s3` sink, configured
+    /// by `LOG_SINK_PREFIX`. There is no prefix by default.
+    pub log_sink_prefix: String,
+    /// The number of bytes of each job's logs (the last bytes, that is) that the `memory` sink
+    /// keeps, configured by `LOG_SINK_BYTES`. This defaults to one mebibyte.
+    pub log_sink_bytes: usize,
+    /// Whether deploying an image past its sunset is rejected (rather than merely warned about),
+    /// configured by `REJECT_AFTER_SUNSET`. This defaults to `true`.
+    pub reject_after_sunset: bool,
+    /// The number of bytes of a crashed connector's logs (the last bytes, that is) that are
+    /// captured, configured by `CRASH_LOG_BYTES`. Zero disables capturing. This defaults to 64
+    /// kibibytes.
+    pub crash_log_bytes: usize,
+    /// The number of the last lines of a crashed connector's logs that are given (as its `cause`)
+    /// within the error returned to its waiting client, configured by `CRASH_LOG_LINES`. Zero
+    /// leaves them out. This defaults to twenty.
+    pub crash_log_lines: usize,
+    /// The namespaces that tenants may deploy connectors into in addition to the default `ocf`
+    /// namespace, configured by `TENANT_NAMESPACES` as a comma separated list. Each MUST exist,
+    /// and the ACM's service account MUST be bound to the `ocf-system` ClusterRole within each,
+    /// both of which the Helm chart takes care of.
+    pub tenant_namespaces: BTreeSet<String>,
+    /// Whether the history of every pod is also persisted (as JSON) within its annotations, so
+    /// that it may be read via `kubectl` even by another ACM, configured by
+    /// `PERSIST_EVENT_HISTORY`. This costs a patch of the pod per transition and so defaults to
+    /// `false`.
+    pub persist_event_history: bool,
+    /// How often (in seconds) the usage of every running connector is sampled in order to track
+    /// its peak, configured by `USAGE_SAMPLE_INTERVAL`. Zero disables sampling. This defaults to
+    /// thirty seconds, as metrics-server refreshes its samples every fifteen seconds anyhow.
+    pub usage_sample_interval: u64,
+    /// The maximum number of pods that the ACM may manage at once, configured by
+    /// `MAX_POD_MANAGERS`. This defaults to 2000, which leaves headroom above the ~1500 connector
+    /// peak that the ACM is sized for.
+    pub max_pod_managers: usize,
+    /// The number of retries within the budget shared by every retry against the Kubernetes API
+    /// server (and, separately, within that of the health checks of freshly started connectors),
+    /// configured by `RETRY_BUDGET_CAPACITY`. This defaults to 100.
+    pub retry_budget_capacity: u32,
+    /// The number of retries refilled into each retry budget per second, configured by
+    /// `RETRY_BUDGET_REFILL`. This defaults to 20.
+    pub retry_budget_refill: u32,
+}
+
+impl AcmConfig {
+    /// Loads the configuration of the ACM from the given source.
+    pub fn load(source: &Source) -> Result<AcmConfig, Invalid> {
+        let mut validator = Validator::new(source);
+        let lifecycle_webhooks: Vec<String> = validator
+            .string("LIFECYCLE_WEBHOOKS", "")
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(String::from)
+            .collect();
+        for url in &lifecycle_webhooks {
+            validator.check(
+                url.starts_with("http://") || url.starts_with("https://"),
+                format!(
+                    "The LIFECYCLE_WEBHOOKS setting must only list http(s) URLs, got '{}'",
+                    url
+                ),
+            );
+        }
+        let lifecycle_webhook_secret = validator
+            .secret("LIFECYCLE_WEBHOOK_SECRET")
+            .filter(|secret| !secret.raw_secret().trim().is_empty());
+        validator.check(
+            lifecycle_webhooks.is_empty() || lifecycle_webhook_secret.is_some(),
+            "The LIFECYCLE_WEBHOOK_SECRET setting is mandatory should there be any \
+LIFECYCLE_WEBHOOKS"
+                .to_string(),
+        );
+        let ticket_signing_keys: Vec<Secret> = validator
+            .secret("TICKET_SIGNING_KEYS")
+            .map(|keys| {
+                keys.raw_secret()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|key| !key.is_empty())
+                    .map(Secret::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        validator.check(
+            ticket_signing_keys
+                .iter()
+                .all(|key| key.raw_secret().len() >= MINIMUM_TICKET_SIGNING_KEY_LENGTH),
+            format!(
+                "Every one of the TICKET_SIGNING_KEYS must be at least {} characters long",
+                MINIMUM_TICKET_SIGNING_KEY_LENGTH
+            ),
+        );
+        let mut warm_pools = BTreeMap::new();
+        for entry in validator
+            .string("WARM_POOLS", "")
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            match entry.split_once('=').map(|(tag, size)| {
+                (
+                    tag.trim(),
+                    size.trim().parse::<usize>().ok().filter(|size| *size > 0),
+                )
+            }) {
+                Some((tag, Some(size))) if !tag.is_empty() => {
+                    warm_pools.insert(tag.to_string(), size);
+                }
+                _ => validator.check(
+                    false,
+                    format!(
+                        "The WARM_POOLS setting must list <tag>=<positive integer> entries, \
+got '{}'",
+                        entry
+                    ),
+                ),
+            }
+        }
+        let proxy = validator.parse("PROXY", false, "either true or false");
+        validator.check(
+            !proxy || !ticket_signing_keys.is_empty(),
+            "The proxy (PROXY) may only be enabled alongside TICKET_SIGNING_KEYS, as unsigned \
+tickets are merely the names of their pods"
+                .to_string(),
+        );
+        let exec_commands: BTreeSet<String> = match validator.optional("EXEC_COMMANDS") {
+            Some(commands) if !commands.trim().is_empty() => commands
+                .split(',')
+                .map(normalize)
+                .filter(|command| !command.is_empty())
+                .collect(),
+            _ => DEFAULT_EXEC_COMMANDS
+                .iter()
+                .map(|command| normalize(command))
+                .collect(),
+        };
+        let store_path = PathBuf::from(validator.string("STORE_PATH", "/var/lib/acm"));
+        let log_sink = validator.string("LOG_SINK", "none").trim().to_lowercase();
+        validator.check(
+            ["none", "file", "s3", "memory"].contains(&log_sink.as_str()),
+            format!(
+                "The LOG_SINK setting can be one of file, s3, memory, or none (case insensitive), \
+got '{}'",
+                log_sink
+            ),
+        );
+        let log_sink_bucket = if log_sink == "s3" {
+            validator.required("LOG_SINK_BUCKET", "when using the S3 log sink")
+        } else {
+            validator.optional("LOG_SINK_BUCKET")
+        };
+        let tenant_namespaces: BTreeSet<String> = validator
+            .string("TENANT_NAMESPACES", "")
+            .split(',')
+            .map(str::trim)
+            .filter(|namespace| !namespace.is_empty())
+            .map(String::from)
+            .collect();
+        for namespace in &tenant_namespaces {
+            validator.check(
+                is_label(namespace),
+                format!(
+                    "The TENANT_NAMESPACES setting lists '{}', which is not a valid namespace",
+                    namespace
+                ),
+            );
+        }
+        validator.check(
+            !tenant_namespaces.contains(OCF_SYSTEM_NAMESPACE),
+            format!(
+                "The TENANT_NAMESPACES setting lists '{}', however connectors may never be \
+deployed alongside the OCF's own components",
+                OCF_SYSTEM_NAMESPACE
+            ),
+        );
+        let config = AcmConfig {
+            registry: validator.string("REGISTRY", "registry.kurl"),
+            repository: validator.string("REPOSITORY", "ocf"),
+            operator_token: validator
+                .secret("OPERATOR_TOKEN")
+                .map(|token| Secret::from(token.raw_secret().trim()))
+                .filter(|token| !token.raw_secret().is_empty()),
+            lifecycle_webhooks,
+            lifecycle_webhook_secret,
+            aim: validator
+                .string("AIM", "http://aim.ocf-system")
+                .trim_end_matches('/')
+                .to_string(),
+            validate_tags: validator.parse("VALIDATE_TAGS", true, "either true or false"),
+            proxy,
+            ticket_signing_keys,
+            warm_pools,
+            warm_pool_max_idle: validator.positive_integer("WARM_POOL_MAX_IDLE", 60 * 60) as u64,
+            exec_commands,
+            log_sink,
+            log_sink_path: validator
+                .optional("LOG_SINK_PATH")
+                .filter(|path| !path.trim().is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| store_path.join("logs")),
+            log_sink_bucket,
+            log_sink_prefix: validator.string("LOG_SINK_PREFIX", ""),
+            log_sink_bytes: validator.parse(
+                "LOG_SINK_BYTES",
+                1024 * 1024,
+                "a non-negative integer",
+            ),
+            reject_after_sunset: validator.parse(
+                "REJECT_AFTER_SUNSET",
+                true,
+                "either true or false",
+            ),
+            crash_log_bytes: validator.parse(
+                "CRASH_LOG_BYTES",
+                64 * 1024,
+                "a non-negative integer",
+            ),
+            crash_log_lines: validator.parse("CRASH_LOG_LINES", 20, "a non-negative integer"),
+            tenant_namespaces,
+            persist_event_history: validator.parse(
+                "PERSIST_EVENT_HISTORY",
+                false,
+                "either true or false",
+            ),
+            usage_sample_interval: validator.parse(
+                "USAGE_SAMPLE_INTERVAL",
+                30,
+                "a non-negative integer",
+            ),
+            max_pod_managers: validator.positive_integer("MAX_POD_MANAGERS", 2000),
+            retry_budget_capacity: validator.positive_integer("RETRY_BUDGET_CAPACITY", 100) as u32,
+            retry_budget_refill: validator.positive_integer("RETRY_BUDGET_REFILL", 20) as u32,
+            store_path,
+        };
+        validator.finish()?;
+        Ok(config)
+    }
+
+    /// Loads the configuration of the ACM from the environment and the
+    /// [configuration file](crate::CONFIG_FILE).
+    ///
+    /// This function PANICS, describing every problem, should the configuration be invalid.
+    pub fn configured() -> AcmConfig {
+        configured(AcmConfig::load)
+    }
+}
+
+/// Collapses the whitespace within the given command, such that commands are compared modulo
+/// whitespace.
+pub fn normalize(command: &str) -> String {
+    command.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Whether the given namespace is a valid RFC 1123 label, as every Kubernetes namespace must be.
+fn is_label(namespace: &str) -> bool {
+    namespace.len() <= 63
+        && namespace
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !namespace.starts_with('-')
+        && !namespace.ends_with('-')
+}
+
+/// The settings of the ACM that may be changed while it runs. Editing them within the
+/// [configuration file](crate::CONFIG_FILE) takes effect once the ACM reloads it, without the
+/// ACM (and every connector that it manages) having to be restarted.
+#[derive(Clone, Debug, Serialize)]
+pub struct AcmTunables {
+    /// The TTL (in seconds) of deploys that do not ask for one, configured by `DEFAULT_TTL`. This
+    /// defaults to thirty minutes.
+    pub default_ttl: u64,
+    /// The TTL (in seconds) of deploys that do not ask for one by particular clients (as identified
+    /// by their `X-Client-Id`), in place of the [default TTL](AcmTunables::default_ttl), configured
+    /// by `DEFAULT_TTLS` as a comma separated list of `<client>=<ttl>` entries.
+    pub default_ttls: BTreeMap<String, u64>,
+    /// The shortest TTL (in seconds) that a client may ask for, configured by `MIN_TTL`. TTLs are
+    /// unbounded from below by default.
+    pub min_ttl: Option<u64>,
+    /// The longest TTL (in seconds) that a client may ask for, configured by `MAX_TTL`. TTLs are
+    /// unbounded from above by default.
+    pub max_ttl: Option<u64>,
+    /// The number of seconds that a connector has to pass its server health check, configured by
+    /// `HEALTH_CHECK_TIMEOUT`. This defaults to thirty seconds.
+    pub health_check_timeout: u64,
+    /// How strictly connectors are health checked, configured by `HEALTH_CHECK_MODE`. This
+    /// defaults to [lenient](HealthCheckMode::Lenient).
+    pub health_check_mode: HealthCheckMode,
+    /// The service whose health is asked after by [strict](HealthCheckMode::Strict) health
+    /// checks, configured by `HEALTH_CHECK_SERVICE`. This defaults to the empty name, which asks
+    /// after the health of the connector's server as a whole.
+    pub health_check_service: String,
+    /// The number of seconds between the health probes of connectors that are online, configured
+    /// by `HEALTH_PROBE_INTERVAL`. Each probe is held to the same
+    /// [mode](AcmTunables::health_check_mode) and [timeout](AcmTunables::health_check_timeout) as
+    /// the server health check. Connectors are not probed after they come online by default.
+    pub health_probe_interval: Option<u64>,
+    /// The number of consecutive health probes that a connector may fail before it is deleted as
+    /// unresponsive, configured by `HEALTH_PROBE_FAILURES`. This defaults to three.
+    pub health_probe_failures: u64,
+    /// The number of seconds that a pod may wait to be scheduled onto a node, configured by
+    /// `SCHEDULING_TIMEOUT`. A pod that is still unscheduled (such as for a lack of memory within
+    /// the cluster) once it expires is deleted, and its waiting client told why. This defaults to
+    /// five minutes.
+    pub scheduling_timeout: u64,
+    /// The rate limit of every endpoint, configured by `RATE_LIMITS`. Endpoints are not rate
+    /// limited by default.
+    pub rate_limits: String,
+    /// The filter that logs are written through, configured by `RUST_LOG`. This defaults to only
+    /// writing errors.
+    pub log_filter: String,
+    /// The number of seconds for which a deploy's `Idempotency-Key` is remembered, configured by
+    /// `IDEMPOTENCY_WINDOW`. A deploy that is replayed under the same key within this window is
+    /// answered with the pod of the original deploy. This defaults to ten minutes.
+    pub idempotency_window: u64,
+    /// The number of seconds that a connector may live for, configured by `MAX_LIFETIME`. The
+    /// garbage collector deletes a connector that outlives it no matter how often its TTL has been
+    /// refreshed. Connectors may live indefinitely by default.
+    pub max_lifetime: Option<u64>,
+    /// The number of seconds of warning that a connector is given before it is garbage collected,
+    /// configured by `GC_GRACE_PERIOD`. A connector whose TTL expires is labeled as
+    /// `gc_imminent=true` and is only deleted should it go unrefreshed for the grace period as
+    /// well. Connectors are deleted as soon as their TTL expires by default.
+    pub gc_grace_period: Option<u64>,
+}
+
+impl AcmTunables {
+    /// Loads the tunables of the ACM from the given source.
+    pub fn load(source: &Source) -> Result<AcmTunables, Invalid> {
+        let mut validator = Validator::new(source);
+        let mut default_ttls = BTreeMap::new();
+        for entry in validator
+            .string("DEFAULT_TTLS", "")
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            match entry.rsplit_once('=').map(|(client, ttl)| {
+                (
+                    client.trim(),
+                    ttl.trim().parse::<u64>().ok().filter(|ttl| *ttl > 0),
+                )
+            }) {
+                Some((client, Some(ttl))) if !client.is_empty() => {
+                    default_ttls.insert(client.to_string(), ttl);
+                }
+                _ => validator.check(
+                    false,
+                    format!(
+                        "The DEFAULT_TTLS setting must list <client>=<positive integer> entries, \
+got '{}'",
+                        entry
+                    ),
+                ),
+            }
+        }
+        let tunables = AcmTunables {
+            default_ttl: validator.positive_integer("DEFAULT_TTL", 60 * 30) as u64,
+            default_ttls,
+            min_ttl: validator
+                .optional_positive_integer("MIN_TTL")
+                .map(|ttl| ttl as u64),
+            max_ttl: validator
+                .optional_positive_integer("MAX_TTL")
+                .map(|ttl| ttl as u64),
+            health_check_timeout: validator.positive_integer("HEALTH_CHECK_TIMEOUT", 30) as u64,
+            health_check_mode: validator.parse(
+                "HEALTH_CHECK_MODE",
+                HealthCheckMode::Lenient,
+                "one of lenient, strict, or watch",
+            ),
+            health_check_service: validator.string("HEALTH_CHECK_SERVICE", ""),
+            health_probe_interval: validator
+                .optional_positive_integer("HEALTH_PROBE_INTERVAL")
+                .map(|interval| interval as u64),
+            health_probe_failures: validator.positive_integer("HEALTH_PROBE_FAILURES", 3) as u64,
+            scheduling_timeout: validator.positive_integer("SCHEDULING_TIMEOUT", 60 * 5) as u64,
+            rate_limits: validator.string("RATE_LIMITS", ""),
+            log_filter: validator.string("RUST_LOG", "error"),
+            idempotency_window: validator.positive_integer("IDEMPOTENCY_WINDOW", 60 * 10) as u64,
+            max_lifetime: validator
+                .optional_positive_integer("MAX_LIFETIME")
+                .map(|lifetime| lifetime as u64),
+            gc_grace_period: validator
+                .optional_positive_integer("GC_GRACE_PERIOD")
+                .map(|grace| grace as u64),
+        };
+        if let (Some(min), Some(max)) = (tunables.min_ttl, tunables.max_ttl) {
+            validator.check(
+                min <= max,
+                format!("The MIN_TTL ({}) may not exceed the MAX_TTL ({})", min, max),
+            );
+        }
+        validator.check(
+            tunables.permits_ttl(tunables.default_ttl),
+            format!(
+                "The DEFAULT_TTL ({}) must be within the MIN_TTL and the MAX_TTL",
+                tunables.default_ttl
+            ),
+        );
+        for (client, ttl) in &tunables.default_ttls {
+            validator.check(
+                tunables.permits_ttl(*ttl),
+                format!(
+                    "The DEFAULT_TTLS of {} ({}) must be within the MIN_TTL and the MAX_TTL",
+                    client, ttl
+                ),
+            );
+        }
+        validator.finish()?;
+        Ok(tunables)
+    }
+
+    /// Returns the TTL of deploys by the given client that do not ask for one.
+    pub fn default_ttl_for(&self, client: &str) -> u64 {
+        self.default_ttls
+            .get(client)
+            .copied()
+            .unwrap_or(self.default_ttl)
+    }
+
+    /// Returns whether the given TTL is within the [MIN_TTL](AcmTunables::min_ttl) and the
+    /// [MAX_TTL](AcmTunables::max_ttl).
+    pub fn permits_ttl(&self, ttl: u64) -> bool {
+        self.min_ttl.is_none_or(|min| ttl >= min) && self.max_ttl.is_none_or(|max| ttl <= max)
+    }
+
+    /// Loads the tunables of the ACM from the environment and the
+    /// [configuration file](crate::CONFIG_FILE).
+    ///
+    /// This function PANICS, describing every problem, should the tunables be invalid.
+    pub fn configured() -> AcmTunables {
+        configured(AcmTunables::load)
+    }
+}
+
+/// How strictly the server health check of a connector is held to the
+/// [gRPC health checking protocol](https://github.com/grpc/grpc/blob/master/doc/health-checking.md).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthCheckMode {
+    /// A connector is healthy once its gRPC server accepts a connection at all, whether or not it
+    /// implements the protocol.
+    Lenient,
+    /// A connector is only healthy once it answers a `Check` of the
+    /// [health check service](AcmTunables::health_check_service) with `SERVING`.
+    Strict,
+    /// As [strict](HealthCheckMode::Strict), after which the connector is `Watch`ed for as long as
+    /// it lives so that it may be deleted should it stop `SERVING`.
+    Watch,
+}
+
+impl HealthCheckMode {
+    /// Returns whether connectors must answer a `Check` with `SERVING` to be healthy.
+    pub fn strict(self) -> bool {
+        self != HealthCheckMode::Lenient
+    }
+}
+
+impl FromStr for HealthCheckMode {
+    type Err = ();
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.to_lowercase().as_str() {
+            "lenient" => Ok(HealthCheckMode::Lenient),
+            "strict" => Ok(HealthCheckMode::Strict),
+            "watch" => Ok(HealthCheckMode::Watch),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(settings: &[(&str, &str)]) -> Source {
+        Source {
+            file: settings
+                .iter()
+                .map(|(var, value)| (var.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn lifecycle_webhooks() {
+        let config = AcmConfig::load(&source(&[
+            (
+                "LIFECYCLE_WEBHOOKS",
+                "https://a.example.com/ocf, http://b.example.com",
+            ),
+            ("LIFECYCLE_WEBHOOK_SECRET", "hunter2"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            config.lifecycle_webhooks,
+            vec!["https://a.example.com/ocf", "http://b.example.com"]
+        );
+        assert!(AcmConfig::load(&source(&[]))
+            .unwrap()
+            .lifecycle_webhooks
+            .is_empty());
+        let invalid = AcmConfig::load(&source(&[(
+            "LIFECYCLE_WEBHOOKS",
+            "https://a.example.com,ftp://b.example.com",
+        )]))
+        .unwrap_err();
+        // The FTP URL and the missing secret.
+        assert_eq!(invalid.problems.len(), 2, "{}", invalid);
+    }
+
+    #[test]
+    fn tag_validation() {
+        let config = AcmConfig::load(&source(&[])).unwrap();
+        assert_eq!(config.aim, "http://aim.ocf-system");
+        assert!(config.validate_tags);
+        let config = AcmConfig::load(&source(&[
+            ("AIM", "http://aim.example.com/"),
+            ("VALIDATE_TAGS", "false"),
+        ]))
+        .unwrap();
+        assert_eq!(config.aim, "http://aim.example.com");
+        assert!(!config.validate_tags);
+        let invalid = AcmConfig::load(&source(&[("VALIDATE_TAGS", "sometimes")])).unwrap_err();
+        assert_eq!(invalid.problems.len(), 1, "{}", invalid);
+    }
+
+    #[test]
+    fn proxy() {
+        assert!(!AcmConfig::load(&source(&[])).unwrap().proxy);
+        assert!(
+            AcmConfig::load(&source(&[
+                ("PROXY", "true"),
+                ("TICKET_SIGNING_KEYS", "0123456789abcdef0123456789abcdef")
+            ]))
+            .unwrap()
+            .proxy
+        );
+        assert!(AcmConfig::load(&source(&[("PROXY", "maybe")])).is_err());
+        // Without signed tickets, anybody who knows the name of a pod could call its connector.
+        let unsigned = AcmConfig::load(&source(&[("PROXY", "true")])).unwrap_err();
+        assert_eq!(unsigned.problems.len(), 1, "{}", unsigned);
+    }
+
+    #[test]
+    fn ticket_signing_keys() {
+        assert!(AcmConfig::load(&source(&[]))
+            .unwrap()
+            .ticket_signing_keys
+            .is_empty());
+        let new = "n".repeat(MINIMUM_TICKET_SIGNING_KEY_LENGTH);
+        let old = "o".repeat(MINIMUM_TICKET_SIGNING_KEY_LENGTH);
+        let keys = format!("{}, {}", new, old);
+        let config = AcmConfig::load(&source(&[("TICKET_SIGNING_KEYS", &keys)])).unwrap();
+        assert_eq!(
+            config.ticket_signing_keys,
+            vec![Secret::from(new), Secret::from(old)]
+        );
+        assert!(AcmConfig::load(&source(&[("TICKET_SIGNING_KEYS", "hunter2")])).is_err());
+    }
+
+    #[test]
+    fn warm_pools() {
+        let config = AcmConfig::load(&source(&[])).unwrap();
+        assert!(config.warm_pools.is_empty());
+        assert_eq!(config.warm_pool_max_idle, 60 * 60);
+        let config = AcmConfig::load(&source(&[("WARM_POOLS", "abcd1234=3, efgh5678=1")])).unwrap();
+        assert_eq!(config.warm_pools["abcd1234"], 3);
+        assert_eq!(config.warm_pools["efgh5678"], 1);
+        let invalid = AcmConfig::load(&source(&[("WARM_POOLS", "abcd1234=0,=2")])).unwrap_err();
+        assert_eq!(invalid.problems.len(), 2, "{}", invalid);
+    }
+
+    #[test]
+    fn exec_commands() {
+        let config = AcmConfig::load(&source(&[])).unwrap();
+        assert_eq!(config.exec_commands.len(), DEFAULT_EXEC_COMMANDS.len());
+        assert!(config.exec_commands.contains("df -h"));
+        let config = AcmConfig::load(&source(&[("EXEC_COMMANDS", " ls   -la ,, uptime")])).unwrap();
+        assert_eq!(
+            config.exec_commands.into_iter().collect::<Vec<_>>(),
+            vec!["ls -la", "uptime"]
+        );
+    }
+
+    #[test]
+    fn log_sink() {
+        let config = AcmConfig::load(&source(&[("STORE_PATH", "/data")])).unwrap();
+        assert_eq!(config.log_sink, "none");
+        assert_eq!(config.log_sink_path, PathBuf::from("/data/logs"));
+        assert_eq!(config.log_sink_bytes, 1024 * 1024);
+        let config = AcmConfig::load(&source(&[
+            ("LOG_SINK", "S3"),
+            ("LOG_SINK_BUCKET", "logs"),
+            ("LOG_SINK_PREFIX", "ocf/"),
+        ]))
+        .unwrap();
+        assert_eq!(config.log_sink, "s3");
+        assert_eq!(config.log_sink_bucket.as_deref(), Some("logs"));
+        assert_eq!(config.log_sink_prefix, "ocf/");
+        let invalid = AcmConfig::load(&source(&[("LOG_SINK", "s3")])).unwrap_err();
+        assert_eq!(invalid.problems.len(), 1, "{}", invalid);
+        let invalid = AcmConfig::load(&source(&[("LOG_SINK", "syslog"), ("LOG_SINK_BYTES", "-1")]))
+            .unwrap_err();
+        assert_eq!(invalid.problems.len(), 2, "{}", invalid);
+    }
+
+    #[test]
+    fn tenant_namespaces() {
+        assert!(AcmConfig::load(&source(&[]))
+            .unwrap()
+            .tenant_namespaces
+            .is_empty());
+        let config =
+            AcmConfig::load(&source(&[("TENANT_NAMESPACES", "tenant-a, tenant-b")])).unwrap();
+        assert_eq!(
+            config.tenant_namespaces.into_iter().collect::<Vec<_>>(),
+            vec!["tenant-a", "tenant-b"]
+        );
+        let invalid = AcmConfig::load(&source(&[(
+            "TENANT_NAMESPACES",
+            "Tenant_A, -tenant, ocf-system",
+        )]))
+        .unwrap_err();
+        assert_eq!(invalid.problems.len(), 3, "{}", invalid);
+    }
+
+    #[test]
+    fn pod_management() {
+        let config = AcmConfig::load(&source(&[])).unwrap();
+        assert_eq!(config.max_pod_managers, 2000);
+        assert_eq!(config.usage_sample_interval, 30);
+        assert!(!config.persist_event_history);
+        assert!(config.reject_after_sunset);
+        assert_eq!(config.crash_log_bytes, 64 * 1024);
+        assert_eq!(config.crash_log_lines, 20);
+        assert_eq!(config.retry_budget_capacity, 100);
+        assert_eq!(config.retry_budget_refill, 20);
+        let config = AcmConfig::load(&source(&[
+            ("USAGE_SAMPLE_INTERVAL", "0"),
+            ("CRASH_LOG_BYTES", "0"),
+            ("REJECT_AFTER_SUNSET", "false"),
+        ]))
+        .unwrap();
+        assert_eq!(config.usage_sample_interval, 0);
+        assert_eq!(config.crash_log_bytes, 0);
+        assert!(!config.reject_after_sunset);
+        let invalid = AcmConfig::load(&source(&[
+            ("MAX_POD_MANAGERS", "0"),
+            ("PERSIST_EVENT_HISTORY", "yes"),
+            ("CRASH_LOG_LINES", "many"),
+            ("RETRY_BUDGET_REFILL", "0"),
+        ]))
+        .unwrap_err();
+        assert_eq!(invalid.problems.len(), 4, "{}", invalid);
+    }
+
+    #[test]
+    fn max_lifetime() {
+        assert_eq!(AcmTunables::load(&source(&[])).unwrap().max_lifetime, None);
+        let tunables = AcmTunables::load(&source(&[("MAX_LIFETIME", "86400")])).unwrap();
+        assert_eq!(tunables.max_lifetime, Some(86400));
+        assert!(AcmTunables::load(&source(&[("MAX_LIFETIME", "0")])).is_err());
+    }
+
+    #[test]
+    fn scheduling_timeout() {
+        assert_eq!(
+            AcmTunables::load(&source(&[])).unwrap().scheduling_timeout,
+            60 * 5
+        );
+        let tunables = AcmTunables::load(&source(&[("SCHEDULING_TIMEOUT", "600")])).unwrap();
+        assert_eq!(tunables.scheduling_timeout, 600);
+        assert!(AcmTunables::load(&source(&[("SCHEDULING_TIMEOUT", "0")])).is_err());
+    }
+
+    #[test]
+    fn health_check_mode() {
+        let tunables = AcmTunables::load(&source(&[])).unwrap();
+        assert_eq!(tunables.health_check_mode, HealthCheckMode::Lenient);
+        assert!(!tunables.health_check_mode.strict());
+        assert_eq!(tunables.health_check_service, "");
+        let tunables = AcmTunables::load(&source(&[
+            ("HEALTH_CHECK_MODE", "Watch"),
+            ("HEALTH_CHECK_SERVICE", "ocf.Connector"),
+        ]))
+        .unwrap();
+        assert_eq!(tunables.health_check_mode, HealthCheckMode::Watch);
+        assert!(tunables.health_check_mode.strict());
+        assert_eq!(tunables.health_check_service, "ocf.Connector");
+        assert!(AcmTunables::load(&source(&[("HEALTH_CHECK_MODE", "paranoid")])).is_err());
+    }
+
+    #[test]
+    fn health_probes() {
+        let tunables = AcmTunables::load(&source(&[])).unwrap();
+        assert_eq!(tunables.health_probe_interval, None);
+        assert_eq!(tunables.health_probe_failures, 3);
+        let tunables = AcmTunables::load(&source(&[
+            ("HEALTH_PROBE_INTERVAL", "15"),
+            ("HEALTH_PROBE_FAILURES", "5"),
+        ]))
+        .unwrap();
+        assert_eq!(tunables.health_probe_interval, Some(15));
+        assert_eq!(tunables.health_probe_failures, 5);
+        assert!(AcmTunables::load(&source(&[("HEALTH_PROBE_INTERVAL", "0")])).is_err());
+        assert!(AcmTunables::load(&source(&[("HEALTH_PROBE_FAILURES", "0")])).is_err());
+    }
+
+    #[test]
+    fn gc_grace_period() {
+        assert_eq!(
+            AcmTunables::load(&source(&[])).unwrap().gc_grace_period,
+            None
+        );
+        let tunables = AcmTunables::load(&source(&[("GC_GRACE_PERIOD", "60")])).unwrap();
+        assert_eq!(tunables.gc_grace_period, Some(60));
+        assert!(AcmTunables::load(&source(&[("GC_GRACE_PERIOD", "soon")])).is_err());
+    }
+
+    #[test]
+    fn ttl_policy() {
+        let tunables = AcmTunables::load(&source(&[
+            ("MIN_TTL", "60"),
+            ("MAX_TTL", "3600"),
+            ("DEFAULT_TTLS", "scheduler=120, alation=900"),
+        ]))
+        .unwrap();
+        assert_eq!(tunables.default_ttl_for("scheduler"), 120);
+        assert_eq!(tunables.default_ttl_for("10.0.0.1"), 60 * 30);
+        assert!(tunables.permits_ttl(60));
+        assert!(tunables.permits_ttl(3600));
+        assert!(!tunables.permits_ttl(59));
+        assert!(!tunables.permits_ttl(3601));
+        let invalid = AcmTunables::load(&source(&[
+            ("MIN_TTL", "600"),
+            ("MAX_TTL", "60"),
+            ("DEFAULT_TTLS", "scheduler=soon"),
+        ]))
+        .unwrap_err();
+        // The inverted bounds, the default TTL being out of them, and the malformed entry.
+        assert_eq!(invalid.problems.len(), 3, "{}", invalid);
+    }
+}
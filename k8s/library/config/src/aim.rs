@@ -0,0 +1,224 @@
+use crate::{configured, Invalid, Source, Validator};
+use kind::Kind;
+use secret::Secret;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// The settings of the AIM. See the AIM's `env` module for what each setting means.
+#[derive(Clone, Debug, Serialize, Kind)]
+pub struct AimConfig {
+    pub registry: String,
+    pub repository: String,
+    /// Either `ECR` or `Minikube` (case insensitive).
+    pub implementation: String,
+    pub containerd_address: String,
+    pub deprecations_path: PathBuf,
+    pub metadata_path: PathBuf,
+    /// The AWS settings are mandatory when the [implementation](AimConfig::implementation) is
+    /// `ECR`, and are otherwise ignored.
+    pub aws_region: Option<String>,
+    pub aws_access_key_id: Option<String>,
+    pub aws_secret_access_key: Option<Secret>,
+    pub aws_username: Option<String>,
+    pub ecr_max_images: Option<usize>,
+    pub ecr_max_image_age_days: Option<usize>,
+    pub max_concurrent_installs: usize,
+    pub max_queued_installs: usize,
+    pub install_retry_after: u64,
+    /// The number of bytes that the repository may hold before installs are rejected.
+    pub registry_quota: Option<usize>,
+    /// Either `none`, `clamd`, or `webhook` (case insensitive).
+    pub scanner: String,
+    pub clamd_socket: PathBuf,
+    /// Mandatory when the [scanner](AimConfig::scanner) is `webhook`, and otherwise ignored.
+    pub scan_webhook: Option<String>,
+    pub quarantine_path: PathBuf,
+    /// Either `disk` or `s3` (case insensitive).
+    pub spool: String,
+    pub spool_path: PathBuf,
+    /// Mandatory when the [spool](AimConfig::spool) is `s3`, and otherwise ignored.
+    pub spool_bucket: Option<String>,
+    pub spool_prefix: String,
+    pub spool_min_free: u64,
+    /// The number of retries within the budget shared by every budgeted retry.
+    pub retry_budget_capacity: u32,
+    /// The number of retries refilled into the retry budget per second.
+    pub retry_budget_refill: u32,
+}
+
+impl AimConfig {
+    /// Loads the configuration of the AIM from the given source.
+    pub fn load(source: &Source) -> Result<AimConfig, Invalid> {
+        let mut validator = Validator::new(source);
+        let implementation = validator.string("IMPLEMENTATION", "Minikube");
+        let ecr = implementation.eq_ignore_ascii_case("ecr");
+        validator.check(
+            ecr || implementation.eq_ignore_ascii_case("minikube"),
+            format!(
+                "The IMPLEMENTATION setting can be one of either ECR or Minikube (case insensitive), got '{}'",
+                implementation
+            ),
+        );
+        let aws = |validator: &mut Validator, var: &str| {
+            if ecr {
+                validator.required(var, "when using the ECR implementation")
+            } else {
+                validator.optional(var)
+            }
+        };
+        let aws_region = aws(&mut validator, "AWS_REGION");
+        let aws_access_key_id = aws(&mut validator, "AWS_ACCESS_KEY_ID");
+        let aws_secret_access_key = aws(&mut validator, "AWS_SECRET_ACCESS_KEY").map(Secret::from);
+        let aws_username = aws(&mut validator, "AWS_USERNAME");
+        let scanner = validator.string("SCANNER", "none");
+        validator.check(
+            ["none", "clamd", "webhook"]
+                .iter()
+                .any(|valid| scanner.eq_ignore_ascii_case(valid)),
+            format!(
+                "The SCANNER setting can be one of either none, clamd, or webhook (case insensitive), got '{}'",
+                scanner
+            ),
+        );
+        let scan_webhook = if scanner.eq_ignore_ascii_case("webhook") {
+            validator.required("SCAN_WEBHOOK", "when using the webhook scanner")
+        } else {
+            validator.optional("SCAN_WEBHOOK")
+        };
+        let spool = validator.string("SPOOL", "disk");
+        validator.check(
+            spool.eq_ignore_ascii_case("disk") || spool.eq_ignore_ascii_case("s3"),
+            format!(
+                "The SPOOL setting can be one of either disk or S3 (case insensitive), got '{}'",
+                spool
+            ),
+        );
+        let spool_bucket = if spool.eq_ignore_ascii_case("s3") {
+            validator.required("SPOOL_BUCKET", "when using the S3 spool")
+        } else {
+            validator.optional("SPOOL_BUCKET")
+        };
+        let config = AimConfig {
+            registry: validator.string("REGISTRY", "registry.kube-system"),
+            repository: validator.string("REPOSITORY", "ocf"),
+            implementation,
+            containerd_address: validator
+                .string("CONTAINERD_ADDRESS", "/run/containerd/containerd.sock"),
+            deprecations_path: PathBuf::from(
+                validator.string("DEPRECATIONS_PATH", "/var/lib/aim/deprecations.json"),
+            ),
+            metadata_path: PathBuf::from(
+                validator.string("METADATA_PATH", "/var/lib/aim/metadata.json"),
+            ),
+            aws_region,
+            aws_access_key_id,
+            aws_secret_access_key,
+            aws_username,
+            ecr_max_images: validator.optional_positive_integer("ECR_MAX_IMAGES"),
+            ecr_max_image_age_days: validator.optional_positive_integer("ECR_MAX_IMAGE_AGE_DAYS"),
+            max_concurrent_installs: validator.positive_integer("MAX_CONCURRENT_INSTALLS", 2),
+            max_queued_installs: validator.parse(
+                "MAX_QUEUED_INSTALLS",
+                8,
+                "a non-negative integer",
+            ),
+            install_retry_after: validator.positive_integer("INSTALL_RETRY_AFTER", 30) as u64,
+            registry_quota: validator.optional_positive_integer("REGISTRY_QUOTA"),
+            scanner,
+            clamd_socket: PathBuf::from(
+                validator.string("CLAMD_SOCKET", "/var/run/clamav/clamd.ctl"),
+            ),
+            scan_webhook,
+            quarantine_path: PathBuf::from(
+                validator.string("QUARANTINE_PATH", "/var/lib/aim/quarantine"),
+            ),
+            spool,
+            spool_path: validator
+                .optional("SPOOL_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| std::env::temp_dir().join("aim-spool")),
+            spool_bucket,
+            spool_prefix: validator.string("SPOOL_PREFIX", "aim/spool"),
+            spool_min_free: validator.parse(
+                "SPOOL_MIN_FREE",
+                1 << 30,
+                "a non-negative number of bytes",
+            ),
+            retry_budget_capacity: validator.positive_integer("RETRY_BUDGET_CAPACITY", 100) as u32,
+            retry_budget_refill: validator.positive_integer("RETRY_BUDGET_REFILL", 20) as u32,
+        };
+        validator.finish()?;
+        Ok(config)
+    }
+
+    /// Loads the configuration of the AIM from the environment and the
+    /// [configuration file](crate::CONFIG_FILE).
+    ///
+    /// This function PANICS, describing every problem, should the configuration be invalid.
+    pub fn configured() -> AimConfig {
+        configured(AimConfig::load)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(settings: &[(&str, &str)]) -> Source {
+        Source {
+            file: settings
+                .iter()
+                .map(|(var, value)| (var.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn defaults() {
+        let config = AimConfig::load(&source(&[])).unwrap();
+        assert_eq!(config.max_concurrent_installs, 2);
+        assert_eq!(config.max_queued_installs, 8);
+        assert_eq!(config.ecr_max_images, None);
+        assert_eq!(config.registry_quota, None);
+        assert_eq!(config.scanner, "none");
+        assert_eq!(config.spool, "disk");
+        assert_eq!(config.spool_min_free, 1 << 30);
+    }
+
+    #[test]
+    fn s3_spool_requires_bucket() {
+        let invalid =
+            AimConfig::load(&source(&[("SPOOL", "S3"), ("SPOOL_MIN_FREE", "plenty")])).unwrap_err();
+        // The bucket and the free space.
+        assert_eq!(invalid.problems.len(), 2, "{}", invalid);
+    }
+
+    #[test]
+    fn webhook_scanner_requires_url() {
+        let invalid = AimConfig::load(&source(&[("SCANNER", "webhook")])).unwrap_err();
+        assert_eq!(invalid.problems.len(), 1, "{}", invalid);
+        let invalid = AimConfig::load(&source(&[("SCANNER", "sophos")])).unwrap_err();
+        assert_eq!(invalid.problems.len(), 1, "{}", invalid);
+        let config = AimConfig::load(&source(&[
+            ("SCANNER", "Webhook"),
+            ("SCAN_WEBHOOK", "http://scanner.security/scan"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            config.scan_webhook.as_deref(),
+            Some("http://scanner.security/scan")
+        );
+    }
+
+    #[test]
+    fn ecr_requires_aws() {
+        let invalid = AimConfig::load(&source(&[
+            ("IMPLEMENTATION", "ECR"),
+            ("AWS_REGION", "us-east-2"),
+            ("MAX_CONCURRENT_INSTALLS", "0"),
+        ]))
+        .unwrap_err();
+        // The access key ID, secret access key, username, and concurrency.
+        assert_eq!(invalid.problems.len(), 4, "{}", invalid);
+    }
+}
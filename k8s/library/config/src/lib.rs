@@ -0,0 +1,339 @@
+//! config loads the settings of the ACM and the AIM into typed, validated, structs.
+//!
+//! Every setting is read from the environment variable of the same name, falling back to the
+//! optional configuration file named by [CONFIG_FILE](CONFIG_FILE), falling back to its default.
+//! Every setting is validated upon startup and every problem is reported at once, rather than
+//! each panicking lazily (and one at a time) the first time that it is used.
+#[macro_use]
+extern crate lazy_static;
+
+pub mod acm;
+pub mod aim;
+
+use secret::Secret;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// The environment variable that names an optional configuration file. The file is a flat table
+/// of settings, keyed by the names of their environment variables (in any case), and is parsed as
+/// TOML when its name ends in `.toml` and as YAML otherwise.
+///
+/// ```yaml
+/// registry: 248135293344.dkr.ecr.us-east-2.amazonaws.com
+/// max_concurrent_installs: 4
+/// ```
+///
+/// Environment variables take precedence over the file, save for those that were
+/// [exported](Source::export) out of the file to begin with.
+pub const CONFIG_FILE: &str = "CONFIG_FILE";
+
+lazy_static! {
+    /// The environment variables that were exported out of the configuration file. These follow
+    /// the file (rather than take precedence over it) whenever it is [reloaded](reload).
+    static ref EXPORTED: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// A Source is where settings are read from, that is the environment and (optionally) the
+/// [configuration file](CONFIG_FILE).
+#[derive(Debug, Default)]
+pub struct Source {
+    file: HashMap<String, String>,
+}
+
+/// A single setting within a configuration file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+}
+
+impl Display for Scalar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scalar::String(value) => write!(f, "{}", value),
+            Scalar::Integer(value) => write!(f, "{}", value),
+            Scalar::Float(value) => write!(f, "{}", value),
+            Scalar::Boolean(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl Source {
+    /// Reads the [configuration file](CONFIG_FILE), should there be one.
+    pub fn load() -> Result<Source, Invalid> {
+        match std::env::var(CONFIG_FILE) {
+            Ok(path) if !path.trim().is_empty() => Source::from_file(path.trim()),
+            _ => Ok(Source::default()),
+        }
+    }
+
+    /// Reads the given configuration file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Source, Invalid> {
+        let path = path.as_ref();
+        let invalid = |err: &dyn Display| Invalid {
+            problems: vec![format!(
+                "The configuration file {} could not be read, {}",
+                path.display(),
+                err
+            )],
+        };
+        let contents = std::fs::read_to_string(path).map_err(|err| invalid(&err))?;
+        let toml = path
+            .extension()
+            .is_some_and(|extension| extension == "toml");
+        Source::parse(&contents, toml).map_err(|err| invalid(&err))
+    }
+
+    /// Parses the given contents of a configuration file as either TOML or YAML.
+    fn parse(contents: &str, toml: bool) -> Result<Source, String> {
+        let table: HashMap<String, Scalar> = if toml {
+            toml::from_str(contents).map_err(|err| err.to_string())?
+        } else if contents.trim().is_empty() {
+            HashMap::new()
+        } else {
+            serde_yaml::from_str(contents).map_err(|err| err.to_string())?
+        };
+        Ok(Source {
+            file: table
+                .into_iter()
+                .map(|(key, value)| (key.to_uppercase(), value.to_string()))
+                .collect(),
+        })
+    }
+
+    /// Returns the given setting. A variable that is present, albeit empty, is taken to be absent.
+    pub fn get(&self, var: &str) -> Option<String> {
+        let exported = EXPORTED.lock().unwrap().contains(var);
+        std::env::var(var)
+            .ok()
+            .filter(|value| !value.is_empty() && !exported)
+            .or_else(|| self.file.get(var).cloned())
+    }
+
+    /// Exports every setting of the configuration file that is not already within the environment
+    /// into the environment, so that those settings that are read straight from the environment
+    /// (be it by our own libraries, or by the likes of the AWS SDK) see them as well.
+    ///
+    /// Settings that were exported by an earlier source are overwritten by this one, or removed
+    /// from the environment altogether should this source no longer have them.
+    pub fn export(&self) {
+        let mut exported = EXPORTED.lock().unwrap();
+        for (var, value) in &self.file {
+            if exported.contains(var)
+                || std::env::var(var).map_or(true, |existing| existing.is_empty())
+            {
+                std::env::set_var(var, value);
+                exported.insert(var.clone());
+            }
+        }
+        let file = &self.file;
+        exported.retain(|var| {
+            let kept = file.contains_key(var);
+            if !kept {
+                std::env::remove_var(var);
+            }
+            kept
+        });
+    }
+}
+
+/// A Validator reads settings out of a [Source](Source), recording every problem that it comes
+/// across rather than stopping at the first.
+pub struct Validator<'a> {
+    source: &'a Source,
+    problems: Vec<String>,
+}
+
+impl<'a> Validator<'a> {
+    pub fn new(source: &'a Source) -> Validator<'a> {
+        Validator {
+            source,
+            problems: vec![],
+        }
+    }
+
+    /// Returns the given setting, or the default should it not be set.
+    pub fn string(&self, var: &str, default: &str) -> String {
+        self.source.get(var).unwrap_or_else(|| default.to_string())
+    }
+
+    /// Returns the given setting, or `None` should it not be set.
+    pub fn optional(&self, var: &str) -> Option<String> {
+        self.source.get(var)
+    }
+
+    /// Returns the given setting, recording a problem (with the given reason for why it is
+    /// required) should it not be set.
+    pub fn required(&mut self, var: &str, reason: &str) -> Option<String> {
+        let value = self.source.get(var);
+        if value.is_none() {
+            self.problems
+                .push(format!("The {} setting is mandatory {}", var, reason));
+        }
+        value
+    }
+
+    /// Returns the given setting as a [Secret](Secret), or `None` should it not be set.
+    pub fn secret(&self, var: &str) -> Option<Secret> {
+        self.source.get(var).map(Secret::from)
+    }
+
+    /// Parses the given setting, recording a problem should it not parse.
+    pub fn parse<T: FromStr>(&mut self, var: &str, default: T, expected: &str) -> T {
+        match self.source.get(var) {
+            None => default,
+            Some(value) => value.trim().parse().unwrap_or_else(|_| {
+                self.problems.push(format!(
+                    "The {} setting must be {}, got '{}'",
+                    var, expected, value
+                ));
+                default
+            }),
+        }
+    }
+
+    /// Parses the given setting as a positive integer.
+    pub fn positive_integer(&mut self, var: &str, default: usize) -> usize {
+        self.optional_positive_integer(var).unwrap_or(default)
+    }
+
+    /// Parses the given setting as a positive integer, or returns `None` should it not be set.
+    pub fn optional_positive_integer(&mut self, var: &str) -> Option<usize> {
+        let value = self.source.get(var)?;
+        match value.trim().parse::<usize>() {
+            Ok(value) if value > 0 => Some(value),
+            _ => {
+                self.problems.push(format!(
+                    "The {} setting must be a positive integer, got '{}'",
+                    var, value
+                ));
+                None
+            }
+        }
+    }
+
+    /// Records a problem (with the given message) unless the given condition holds.
+    pub fn check(&mut self, condition: bool, problem: String) {
+        if !condition {
+            self.problems.push(problem);
+        }
+    }
+
+    /// Returns every problem that was recorded, if any.
+    pub fn finish(self) -> Result<(), Invalid> {
+        if self.problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Invalid {
+                problems: self.problems,
+            })
+        }
+    }
+}
+
+/// Invalid is every problem found within a configuration.
+#[derive(Debug)]
+pub struct Invalid {
+    pub problems: Vec<String>,
+}
+
+impl Display for Invalid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The configuration is invalid.")?;
+        for (index, problem) in self.problems.iter().enumerate() {
+            write!(f, "\n  {}. {}", index + 1, problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Invalid {}
+
+/// Loads the [Source](Source) and exports it into the environment before building a
+/// configuration out of it.
+///
+/// This function PANICS, describing every problem, should the configuration be invalid.
+fn configured<T, F: FnOnce(&Source) -> Result<T, Invalid>>(build: F) -> T {
+    let source = Source::load().unwrap_or_else(|err| panic!("{}", err));
+    source.export();
+    build(&source).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Reloads the [Source](Source) and builds a configuration out of it, exporting the source into
+/// the environment only should the configuration be valid. An invalid configuration leaves the
+/// environment exactly as it was.
+pub fn reload<T, F: FnOnce(&Source) -> Result<T, Invalid>>(build: F) -> Result<T, Invalid> {
+    let source = Source::load()?;
+    let config = build(&source)?;
+    source.export();
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_files() {
+        let yaml = Source::parse("registry: example.com\nMAX_QUEUED_INSTALLS: 4\n", false).unwrap();
+        assert_eq!(yaml.file["REGISTRY"], "example.com");
+        assert_eq!(yaml.file["MAX_QUEUED_INSTALLS"], "4");
+        let toml = Source::parse("registry = \"example.com\"\nnative = true\n", true).unwrap();
+        assert_eq!(toml.file["REGISTRY"], "example.com");
+        assert_eq!(toml.file["NATIVE"], "true");
+        assert!(Source::parse("", false).unwrap().file.is_empty());
+        assert!(Source::parse("- a list", false).is_err());
+    }
+
+    #[test]
+    fn aggregates_problems() {
+        let source = Source {
+            file: vec![
+                ("CONFIG_TEST_COUNT".to_string(), "zero".to_string()),
+                ("CONFIG_TEST_LIMIT".to_string(), "0".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let mut validator = Validator::new(&source);
+        assert_eq!(validator.parse("CONFIG_TEST_COUNT", 1u64, "an integer"), 1);
+        assert_eq!(validator.positive_integer("CONFIG_TEST_LIMIT", 2), 2);
+        assert_eq!(validator.required("CONFIG_TEST_ABSENT", "always"), None);
+        assert_eq!(validator.string("CONFIG_TEST_ABSENT", "default"), "default");
+        let invalid = validator.finish().unwrap_err();
+        assert_eq!(invalid.problems.len(), 3);
+        assert!(invalid.to_string().contains("3. The CONFIG_TEST_ABSENT"));
+    }
+
+    #[test]
+    fn exports_follow_the_file() {
+        std::env::set_var("CONFIG_TEST_PINNED", "environment");
+        let source = |pairs: &[(&str, &str)]| Source {
+            file: pairs
+                .iter()
+                .map(|(var, value)| (var.to_string(), value.to_string()))
+                .collect(),
+        };
+        let first = source(&[
+            ("CONFIG_TEST_PINNED", "file"),
+            ("CONFIG_TEST_EXPORTED", "first"),
+        ]);
+        first.export();
+        assert_eq!(first.get("CONFIG_TEST_PINNED").unwrap(), "environment");
+        assert_eq!(std::env::var("CONFIG_TEST_EXPORTED").unwrap(), "first");
+        let second = source(&[("CONFIG_TEST_EXPORTED", "second")]);
+        assert_eq!(second.get("CONFIG_TEST_EXPORTED").unwrap(), "second");
+        second.export();
+        assert_eq!(std::env::var("CONFIG_TEST_EXPORTED").unwrap(), "second");
+        source(&[]).export();
+        assert!(std::env::var("CONFIG_TEST_EXPORTED").is_err());
+        assert_eq!(std::env::var("CONFIG_TEST_PINNED").unwrap(), "environment");
+    }
+}
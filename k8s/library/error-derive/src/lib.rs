@@ -2,14 +2,150 @@ extern crate proc_macro;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Attribute, Data, DataEnum, DeriveInput, Expr, Ident, LitStr, Token};
 
-#[proc_macro_derive(AcmError)]
-pub fn acm_error(input: TokenStream) -> TokenStream {
+#[proc_macro_derive(AcmError, attributes(error_code))]
+pub fn acm_error_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = input.ident;
+    let default = match error_code(&input.attrs) {
+        Ok(code) => code,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let codes = match input.data {
+        Data::Enum(DataEnum { variants, .. }) => {
+            let mut arms = vec![];
+            let mut any = false;
+            for variant in variants.iter() {
+                let v = &variant.ident;
+                let code = match error_code(&variant.attrs) {
+                    Ok(code) => code.or_else(|| default.clone()),
+                    Err(err) => return err.to_compile_error().into(),
+                };
+                any |= code.is_some();
+                arms.push(match code {
+                    Some(code) => quote!(#name::#v { .. } => Some(#code)),
+                    None => quote!(#name::#v { .. } => None),
+                });
+            }
+            if !any {
+                None
+            } else {
+                Some(quote!(
+                    match self {
+                        #(#arms),*
+                    }
+                ))
+            }
+        }
+        _ => default.map(|code| quote!(Some(#code))),
+    };
+    match codes {
+        Some(codes) => quote!(
+            impl AcmError for #name {
+                fn error_code(&self) -> Option<&str> {
+                    #codes
+                }
+            }
+        ),
+        None => quote!(
+            impl AcmError for #name {}
+        ),
+    }
+    .into()
+}
+
+/// Extracts the string literal out of an `#[error_code("ACM-1234")]` attribute, if present.
+fn error_code(attrs: &[Attribute]) -> syn::Result<Option<LitStr>> {
+    match attrs.iter().find(|attr| attr.path.is_ident("error_code")) {
+        Some(attr) => Ok(Some(attr.parse_args::<LitStr>()?)),
+        None => Ok(None),
+    }
+}
+
+/// An umbrella for the full set of derives and attributes that an [AcmError](AcmError) requires.
+///
+/// ```ignore
+/// #[acm_error(msg = "Failed to find {name}", code = Status::NotFound, error_code = "ACM-1234")]
+/// struct NotFound {
+///     name: String,
+/// }
+/// ```
+///
+/// ...expands to...
+///
+/// ```ignore
+/// #[derive(Error, AcmError, HttpCode, Kind, Debug)]
+/// #[error("Failed to find {name}")]
+/// #[code(Status::NotFound)]
+/// #[error_code("ACM-1234")]
+/// struct NotFound {
+///     name: String,
+/// }
+/// ```
+///
+/// Every argument is optional so that enums may instead declare them on each variant.
+#[proc_macro_attribute]
+pub fn acm_error(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as Args);
+    let item = parse_macro_input!(item as DeriveInput);
+    let msg = args.msg.map(|msg| quote!(#[error(#msg)]));
+    let code = args.code.map(|code| quote!(#[code(#code)]));
+    let error_code = args
+        .error_code
+        .map(|error_code| quote!(#[error_code(#error_code)]));
     quote!(
-        impl AcmError for #name {}
+        #[derive(Error, AcmError, HttpCode, Kind, Debug)]
+        #msg
+        #code
+        #error_code
+        #item
     )
     .into()
 }
+
+/// The `key = value` arguments of an [acm_error](acm_error) attribute.
+#[derive(Default)]
+struct Args {
+    msg: Option<LitStr>,
+    code: Option<Expr>,
+    error_code: Option<LitStr>,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = Args::default();
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "msg" => once(&mut args.msg, &key, input.parse()?)?,
+                "code" => once(&mut args.code, &key, input.parse()?)?,
+                "error_code" => once(&mut args.error_code, &key, input.parse()?)?,
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        "unknown acm_error argument, expected one of msg, code, or error_code",
+                    ))
+                }
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// Sets the given argument, rejecting arguments that are given more than once.
+fn once<T>(arg: &mut Option<T>, key: &Ident, value: T) -> syn::Result<()> {
+    if arg.is_some() {
+        return Err(syn::Error::new(
+            key.span(),
+            format!("duplicate acm_error argument {}", key),
+        ));
+    }
+    *arg = Some(value);
+    Ok(())
+}
@@ -1,11 +1,12 @@
-pub use error_derive::AcmError;
+pub use error_derive::{acm_error, AcmError};
 pub use httpcode;
 pub use httpcode::{HttpCode, Status};
 pub use kind::Kind;
+use negotiation::Format;
 use rocket::request::Request;
 use rocket::response::Responder;
 use serde::{Serialize, Serializer};
-use serde_json::{json, to_string_pretty};
+use serde_json::json;
 pub use thiserror;
 pub use thiserror::Error;
 
@@ -28,6 +29,9 @@ pub use thiserror::Error;
 /// her is some {info} about this {action}!"
 /// )]
 /// #[code(Status::BadRequest)]
+/// // The (optional) stable, machine-readable, code that will show up in the 'error_code'
+/// // key of the resulting JSON. Clients SHOULD branch on this rather than on the message.
+/// #[error_code("ACM-1234")]
 /// struct MyError {
 ///     action: String,
 ///     info: String,
@@ -37,7 +41,29 @@ pub use thiserror::Error;
 ///     cause: std::io::Error,
 /// }
 /// ```
-pub trait AcmError: std::error::Error + HttpCode + Kind + Send + Sync {}
+///
+/// When deriving on an enum, an `#[error_code(..)]` on the enum itself is used as the
+/// default for any variant that does not declare its own.
+///
+/// The [acm_error](error_derive::acm_error) attribute is shorthand for all of the above.
+///
+/// ```
+/// use error::*;
+///
+/// #[acm_error(msg = "Failed to {action}", code = Status::BadRequest, error_code = "ACM-1234")]
+/// struct MyError {
+///     action: String,
+/// }
+/// ```
+pub trait AcmError: std::error::Error + HttpCode + Kind + Send + Sync {
+    /// Returns the stable, machine-readable, code for this error (e.g. `ACM-1234`), if
+    /// one was declared via `#[error_code(..)]`.
+    ///
+    /// Unlike the human-readable message, these codes MUST NOT change once released.
+    fn error_code(&self) -> Option<&str> {
+        None
+    }
+}
 
 /// This conversion supports the automatic boxing of any type that
 /// implements [AcmError](crate::AcmError).
@@ -62,6 +88,7 @@ impl<T: 'static + AcmError> From<T> for Box<dyn AcmError> {
 /// "This is the string that will show up in the 'message' key of the resulting JSON."
 /// )]
 /// #[code(Status::BadRequest)]
+/// #[error_code("ACM-1234")]
 /// struct MyError {
 ///     #[source]
 ///     cause: std::io::Error,
@@ -73,10 +100,13 @@ impl<T: 'static + AcmError> From<T> for Box<dyn AcmError> {
 /// ```ignore
 /// {
 ///     "kind": "MyError",
+///     "error_code": "ACM-1234",
 ///     "message": "This is the string that will show up in the 'message' key of the resulting JSON.",
 ///     "cause": "Failed to open file because of reasons."
 /// }
 /// ```
+///
+/// The `error_code` is `null` for errors that do not declare an `#[error_code(..)]`.
 impl Serialize for Box<dyn AcmError> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -84,6 +114,7 @@ impl Serialize for Box<dyn AcmError> {
     {
         json!({
             "kind": self.kind(),
+            "error_code": self.error_code(),
             "message": format!("{}", self),
             "cause": self.source().map(|cause| format!("{}", cause)),
         })
@@ -94,7 +125,8 @@ impl Serialize for Box<dyn AcmError> {
 /// The [Responder](rocket::response::Responder) implementation for an [AcmError](crate::AcmError)
 /// does three things:
 ///
-/// 1. Sets the content type to JSON.
+/// 1. Sets the content type to that [negotiated](negotiation::Format::negotiate) via the
+///    request's `Accept` header (pretty printed JSON by default).
 /// 2. Sets the HTTP status to the status declared in the error's `#[code(..)]` annotation.
 /// 3. Serializes the error and sends the resulting bytes over the wire.
 ///
@@ -107,19 +139,16 @@ impl Serialize for Box<dyn AcmError> {
 /// }
 /// ```
 impl<'r, 'o: 'r> Responder<'r, 'o> for Box<dyn AcmError> {
-    fn respond_to(self, _: &'r Request<'_>) -> rocket::response::Result<'o> {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'o> {
+        let format = Format::negotiate(request);
         let mut response = rocket::Response::build();
-        response.header(rocket::http::ContentType::JSON);
+        response.header(format.content_type());
         response.status(self.http_code());
-        let json = json!({
+        let body = format.render(&json!({
             "payload": null,
             "error": self,
-        });
-        // @TODO it MIGHT be possible to fail here? No idea how. If so, can read the error here
-        // and return that instead. I just have no idea what could ever cause it.
-        let json =
-            to_string_pretty(&json).unwrap_or_else(|_| panic!("failed to pretty print {}", json));
-        response.sized_body(json.len(), std::io::Cursor::new(json));
+        }));
+        response.sized_body(body.len(), std::io::Cursor::new(body));
         Ok(response.finalize())
     }
 }
@@ -175,20 +204,33 @@ mod tests {
     use rocket::local::blocking::Client;
     use rocket::routes;
 
+    /// The failing routes. Rocket re-exports a URI macro for each route, which these tests never
+    /// import.
+    #[allow(unused_imports)]
+    mod handlers {
+        use super::*;
+
+        #[get("/")]
+        pub(super) async fn fail_without_cause() -> std::result::Result<(), Box<dyn AcmError>> {
+            Err(TooBad {}.into())
+        }
+
+        #[get("/")]
+        pub(super) async fn fail_with_cause() -> std::result::Result<(), Box<dyn AcmError>> {
+            Err(TooBadWithCause::from(TooBad {}).into())
+        }
+    }
+
     #[derive(AcmError, Error, Kind, HttpCode, Debug)]
     #[error("Nice catch Blanco Niño")]
     #[code(rocket::http::Status::BadGateway)]
     struct TooBad {}
 
-    #[get("/")]
-    async fn fail_without_cause() -> std::result::Result<(), Box<dyn AcmError>> {
-        Err(TooBad {}.into())
-    }
-
     #[test]
     fn without_cause() {
         let client =
-            Client::tracked(rocket::build().mount("/", routes![fail_without_cause])).unwrap();
+            Client::tracked(rocket::build().mount("/", routes![handlers::fail_without_cause]))
+                .unwrap();
         let response = client.get("/").dispatch();
         assert_eq!(response.status(), rocket::http::Status::BadGateway);
         let got: serde_json::Value = response.into_json().unwrap();
@@ -196,6 +238,7 @@ mod tests {
             "payload": null,
             "error": {
                 "kind": "TooBad",
+                "error_code": null,
                 "message": "Nice catch Blanco Niño",
                 "cause": null
             }
@@ -206,19 +249,17 @@ mod tests {
     #[derive(AcmError, Error, Kind, HttpCode, Debug)]
     #[error("You got sacked")]
     #[code(rocket::http::Status::NotFound)]
+    #[error_code("ACM-0001")]
     struct TooBadWithCause {
         #[from]
         bad_guy: TooBad,
     }
 
-    #[get("/")]
-    async fn fail_with_cause() -> std::result::Result<(), Box<dyn AcmError>> {
-        Err(TooBadWithCause::from(TooBad {}).into())
-    }
-
     #[test]
     fn with_cause() {
-        let client = Client::tracked(rocket::build().mount("/", routes![fail_with_cause])).unwrap();
+        let client =
+            Client::tracked(rocket::build().mount("/", routes![handlers::fail_with_cause]))
+                .unwrap();
         let response = client.get("/").dispatch();
         assert_eq!(response.status(), rocket::http::Status::NotFound);
         let got: serde_json::Value = response.into_json().unwrap();
@@ -226,10 +267,89 @@ mod tests {
             "payload": null,
             "error": {
                 "kind": "TooBadWithCause",
+                "error_code": "ACM-0001",
                 "message": "You got sacked",
                 "cause": "Nice catch Blanco Niño"
             }
         });
         assert_eq!(got, want)
     }
+
+    #[derive(AcmError, Error, Kind, HttpCode, Debug)]
+    #[error_code("ACM-0002")]
+    enum Coded {
+        #[error("defaulted")]
+        #[code(rocket::http::Status::NotFound)]
+        Defaulted,
+        #[error("overridden")]
+        #[code(rocket::http::Status::NotFound)]
+        #[error_code("ACM-0003")]
+        Overridden(u32),
+    }
+
+    #[acm_error(
+        msg = "Failed to {action}",
+        code = rocket::http::Status::Conflict,
+        error_code = "ACM-0004"
+    )]
+    struct Umbrella {
+        action: String,
+    }
+
+    #[acm_error(code = rocket::http::Status::BadRequest, error_code = "ACM-0005")]
+    enum UmbrellaEnum {
+        #[error("defaulted")]
+        Defaulted,
+        #[error("overridden")]
+        #[code(rocket::http::Status::NotFound)]
+        Overridden,
+    }
+
+    #[test]
+    fn umbrella() {
+        let err = Umbrella {
+            action: "collide".to_string(),
+        };
+        assert_eq!(err.to_string(), "Failed to collide");
+        assert_eq!(err.http_code(), rocket::http::Status::Conflict);
+        assert_eq!(err.error_code(), Some("ACM-0004"));
+        assert_eq!(err.kind(), "Umbrella");
+        assert_eq!(
+            UmbrellaEnum::Defaulted.http_code(),
+            rocket::http::Status::BadRequest
+        );
+        assert_eq!(
+            UmbrellaEnum::Overridden.http_code(),
+            rocket::http::Status::NotFound
+        );
+        assert_eq!(UmbrellaEnum::Overridden.error_code(), Some("ACM-0005"));
+        assert_eq!(UmbrellaEnum::Overridden.to_string(), "overridden");
+    }
+
+    #[test]
+    fn yaml() {
+        let client =
+            Client::tracked(rocket::build().mount("/", routes![handlers::fail_without_cause]))
+                .unwrap();
+        let response = client
+            .get("/")
+            .header(rocket::http::Header::new("Accept", "application/yaml"))
+            .dispatch();
+        assert_eq!(response.status(), rocket::http::Status::BadGateway);
+        assert_eq!(
+            response.content_type(),
+            Some(rocket::http::ContentType::new("application", "yaml"))
+        );
+        let got: serde_json::Value =
+            serde_yaml::from_str(&response.into_string().unwrap()).unwrap();
+        assert_eq!(got["error"]["kind"], "TooBad");
+        assert_eq!(got["payload"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn enum_error_codes() {
+        assert_eq!(Coded::Defaulted.error_code(), Some("ACM-0002"));
+        assert_eq!(Coded::Overridden(1).error_code(), Some("ACM-0003"));
+        assert_eq!(TooBad {}.error_code(), None);
+    }
 }
@@ -2,65 +2,81 @@ extern crate proc_macro;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, Attribute, Data, DataEnum, DataStruct, DeriveInput, Expr, Fields};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Attribute, Data, DataEnum, DataUnion, DeriveInput, Expr};
 
 // https://blog.turbo.fish/proc-macro-simple-derive/
 
+/// Structs MUST carry a `#[code(<CODE>)]` attribute. Enums may carry one as the default for
+/// every variant, with any variant overriding it by way of its own `#[code(<CODE>)]`. Every
+/// variant of an enum without a default MUST carry one.
 #[proc_macro_derive(HttpCode, attributes(code))]
 pub fn derive_error(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let name = input.ident;
-    match input.data {
-        Data::Struct(DataStruct { .. }) => {
-            let code: Option<&Attribute> =
-                input.attrs.iter().find(|attr| attr.path.is_ident("code"));
-            match code {
-                Some(attribute) => {
-                    let tt: Expr = attribute.parse_args().unwrap();
-                    quote!(
-                        impl HttpCode for #name {
-                            fn http_code(&self) -> httpcode::Status {
-                                #tt
-                            }
-                        }
-                    )
-                    .into()
-                }
-                None => panic!("struct must have #[code(<CODE>)] attribute"),
+    match http_code(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn http_code(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let default = code(&input.attrs)?;
+    let body = match &input.data {
+        Data::Struct(_) => match default {
+            Some(code) => quote!(#code),
+            None => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "HttpCode requires a #[code(<CODE>)] attribute on structs",
+                ))
             }
-        }
+        },
         Data::Enum(DataEnum { variants, .. }) => {
-            let q = variants.iter().map(|variant| {
+            let mut arms = vec![];
+            for variant in variants.iter() {
                 let v = &variant.ident;
-                let code: Option<&Attribute> =
-                    variant.attrs.iter().find(|attr| attr.path.is_ident("code"));
-                if code.is_none() {
-                    panic!("variant {} missigin code attribute", v);
-                }
-                let code: Expr = code.unwrap().parse_args().unwrap();
-                match variant.fields {
-                    Fields::Unnamed(_) => quote! {
-                        #name::#v(..) => { #code }
-                    },
-                    Fields::Named(_) => quote! {
-                        #name::#v{ .. } => { #code }
-                    },
-                    Fields::Unit => quote! {
-                        #name::#v => { #code }
-                    },
-                }
-            });
-            quote!(
-                impl HttpCode for #name {
-                    fn http_code(&self) -> httpcode::Status {
-                        match self {
-                            #(#q),*
-                        }
+                let code = match code(&variant.attrs)?.or_else(|| default.clone()) {
+                    Some(code) => code,
+                    None => {
+                        return Err(syn::Error::new(
+                            v.span(),
+                            format!(
+                                "variant {} requires a #[code(<CODE>)] attribute, as {} has no \
+                                 default #[code(<CODE>)] of its own",
+                                v, name
+                            ),
+                        ))
                     }
+                };
+                arms.push(quote!(#name::#v { .. } => { #code }));
+            }
+            quote!(
+                match self {
+                    #(#arms),*
                 }
             )
-            .into()
         }
-        Data::Union(..) => panic!("just say no to unions"),
+        Data::Union(DataUnion { union_token, .. }) => {
+            return Err(syn::Error::new(
+                union_token.span(),
+                "HttpCode cannot be derived for unions, please implement it manually",
+            ))
+        }
+    };
+    Ok(quote!(
+        impl HttpCode for #name {
+            fn http_code(&self) -> httpcode::Status {
+                #body
+            }
+        }
+    ))
+}
+
+/// Extracts the expression out of a `#[code(Status::NotFound)]` attribute, if present.
+fn code(attrs: &[Attribute]) -> syn::Result<Option<Expr>> {
+    match attrs.iter().find(|attr| attr.path.is_ident("code")) {
+        Some(attr) => Ok(Some(attr.parse_args::<Expr>()?)),
+        None => Ok(None),
     }
 }
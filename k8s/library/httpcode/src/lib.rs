@@ -15,6 +15,22 @@ pub use rocket::http::Status;
 /// struct ServerDown {}
 /// ```
 ///
+/// Enums may declare a default code which applies to every variant that does not declare
+/// its own.
+///
+/// ```
+/// use httpcode::{HttpCode, Status};
+///
+/// #[derive(HttpCode)]
+/// #[code(Status::InternalServerError)]
+/// enum DatabaseError {
+///     #[code(Status::NotFound)]
+///     NoSuchRow,
+///     ConnectionLost,
+///     Corrupted(String),
+/// }
+/// ```
+///
 /// See <https://api.rocket.rs/v0.5-rc/rocket/http/struct.Status.html> for a full list of
 /// available return code.
 pub trait HttpCode {
@@ -40,6 +56,31 @@ mod tests {
         Custom,
     }
 
+    #[allow(dead_code)]
+    #[derive(HttpCode)]
+    #[code(httpcode::Status::InternalServerError)]
+    enum Defaulted {
+        Unit,
+        Tuple(u32),
+        Named {
+            field: u32,
+        },
+        #[code(httpcode::Status::Conflict)]
+        Overridden,
+    }
+
+    #[test]
+    fn defaults() {
+        let internal = httpcode::Status::InternalServerError;
+        assert_eq!(internal, Defaulted::Unit.http_code());
+        assert_eq!(internal, Defaulted::Tuple(1).http_code());
+        assert_eq!(internal, Defaulted::Named { field: 1 }.http_code());
+        assert_eq!(
+            httpcode::Status::Conflict,
+            Defaulted::Overridden.http_code()
+        );
+    }
+
     #[test]
     fn smoke() {
         assert_eq!(httpcode::Status::BadGateway, Struct {}.http_code());
@@ -0,0 +1,169 @@
+//! The PodApi is every operation upon pods that the ACM makes of the API server, abstracted such
+//! that the procedures built upon it ([deploy](crate::deploy), [delete](crate::delete), the
+//! [Informer](crate::informer::Informer), and so on) may be handed either the real API server
+//! ([KubePods](KubePods)) or an in-memory stand-in ([FakePods](crate::fake::FakePods)) and thereby
+//! be tested deterministically.
+//!
+//! Every operation names the namespace that it acts within, so a single PodApi serves every
+//! tenant namespace. Failures are the API server's own [kube::Error](kube::Error)s, so callers
+//! may continue to tell (say) a `404` apart from any other failure.
+use crate::errors::ApiError;
+use crate::watcher::{self, Event};
+use async_trait::async_trait;
+use bytes::Bytes;
+use either::Either;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::{DeleteParams, ListParams, LogParams, Patch, PatchParams, PostParams};
+use kube::core::response::Status;
+use kube::Api;
+use tokio::sync::{mpsc, oneshot};
+
+/// The events of a [watch](PodApi::watch).
+pub type PodEvents = BoxStream<'static, watcher::Result<Event<Pod>>>;
+
+/// The chunks of a [log stream](PodApi::log_stream).
+pub type LogStream = BoxStream<'static, kube::Result<Bytes>>;
+
+#[async_trait]
+pub trait PodApi: Send + Sync {
+    /// Retrieves the named pod.
+    async fn get(&self, namespace: &str, name: &str) -> kube::Result<Pod>;
+
+    /// Creates the given pod, which fails with a `409` (whose reason is `AlreadyExists`) should
+    /// a pod of the same name already exist.
+    async fn create(&self, namespace: &str, params: &PostParams, pod: &Pod) -> kube::Result<Pod>;
+
+    /// Deletes the named pod. A pod whose deletion has begun is returned via `Left`, while a pod
+    /// that is already entirely gone is confirmed via `Right`.
+    async fn delete(
+        &self,
+        namespace: &str,
+        name: &str,
+        params: &DeleteParams,
+    ) -> kube::Result<Either<Pod, Status>>;
+
+    /// Applies the given [JSON merge patch](https://datatracker.ietf.org/doc/html/rfc7386) to the
+    /// named pod. A `null` within the patch removes the key.
+    async fn patch(
+        &self,
+        namespace: &str,
+        name: &str,
+        patch: &serde_json::Value,
+    ) -> kube::Result<Pod>;
+
+    /// Watches every pod within the namespace that the given parameters select, exactly as a
+    /// [watcher](watcher::watcher) does. The watch begins with a [Restarted](Event::Restarted)
+    /// event listing every selected pod.
+    fn watch(&self, namespace: &str, params: ListParams) -> PodEvents;
+
+    /// Streams the logs of the named pod.
+    async fn log_stream(
+        &self,
+        namespace: &str,
+        name: &str,
+        params: &LogParams,
+    ) -> kube::Result<LogStream>;
+}
+
+/// KubePods is the [PodApi](PodApi) of the API server that this process runs against.
+#[derive(Clone)]
+pub struct KubePods {
+    client: kube::Client,
+}
+
+impl KubePods {
+    /// Constructs the PodApi of the API server configured within the environment.
+    ///
+    /// This function panics if there is any error encountered while constructing the required
+    /// configuration object from the environment. This is because a missing Kubernetes environment
+    /// is extremely terminal for which there truly is no alternative besides crashing.
+    pub async fn new() -> KubePods {
+        KubePods {
+            client: kube::Client::try_default()
+                .await
+                .map_err(ApiError::from)
+                .unwrap(),
+        }
+    }
+
+    fn api(&self, namespace: &str) -> Api<Pod> {
+        Api::namespaced(self.client.clone(), namespace)
+    }
+}
+
+#[async_trait]
+impl PodApi for KubePods {
+    async fn get(&self, namespace: &str, name: &str) -> kube::Result<Pod> {
+        self.api(namespace).get(name).await
+    }
+
+    async fn create(&self, namespace: &str, params: &PostParams, pod: &Pod) -> kube::Result<Pod> {
+        self.api(namespace).create(params, pod).await
+    }
+
+    async fn delete(
+        &self,
+        namespace: &str,
+        name: &str,
+        params: &DeleteParams,
+    ) -> kube::Result<Either<Pod, Status>> {
+        self.api(namespace).delete(name, params).await
+    }
+
+    async fn patch(
+        &self,
+        namespace: &str,
+        name: &str,
+        patch: &serde_json::Value,
+    ) -> kube::Result<Pod> {
+        self.api(namespace)
+            .patch(name, &PatchParams::default(), &Patch::Merge(patch))
+            .await
+    }
+
+    fn watch(&self, namespace: &str, params: ListParams) -> PodEvents {
+        watcher::watcher(self.api(namespace), params).boxed()
+    }
+
+    /// The stream handed out by the API borrows the API, so it is pumped from a coroutine of its
+    /// own which lives until either the logs end or the returned stream is dropped.
+    async fn log_stream(
+        &self,
+        namespace: &str,
+        name: &str,
+        params: &LogParams,
+    ) -> kube::Result<LogStream> {
+        let api = self.api(namespace);
+        let name = name.to_string();
+        let params = params.clone();
+        let (opened, open) = oneshot::channel();
+        let (chunks, receiver) = mpsc::channel(16);
+        tokio::spawn(async move {
+            let mut stream = match api.log_stream(&name, &params).await {
+                Ok(stream) => {
+                    let _ = opened.send(Ok(()));
+                    Box::pin(stream)
+                }
+                Err(err) => {
+                    let _ = opened.send(Err(err));
+                    return;
+                }
+            };
+            while let Some(chunk) = stream.next().await {
+                if chunks.send(chunk).await.is_err() {
+                    return;
+                }
+            }
+        });
+        open.await
+            .expect("the log stream coroutine exited without opening the stream")?;
+        Ok(
+            futures::stream::unfold(receiver, |mut receiver| async move {
+                receiver.recv().await.map(|chunk| (chunk, receiver))
+            })
+            .boxed(),
+        )
+    }
+}
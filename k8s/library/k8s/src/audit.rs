@@ -0,0 +1,48 @@
+use crate::events::{EventType, Recorder};
+use async_trait::async_trait;
+use audit::{AuditSinkFailed, Entry, Outcome, Sink};
+use result::Result;
+
+/// An EventSink posts every audit [Entry](Entry) as a Kubernetes Event attached to the pod that it
+/// targets (given as `<namespace>/<name>`), such that it shows up within `kubectl describe pod`.
+/// Entries of operations that failed are posted as `Warning` Events.
+#[derive(Clone, Debug)]
+pub struct EventSink {
+    recorder: Recorder,
+}
+
+impl EventSink {
+    /// Returns a boxed EventSink whose Events are reported by the ACM, as is wanted by
+    /// [Auditor::configured](audit::Auditor::configured).
+    pub fn boxed() -> Box<dyn Sink> {
+        Box::new(EventSink {
+            recorder: Recorder::new("acm"),
+        })
+    }
+}
+
+#[async_trait]
+impl Sink for EventSink {
+    async fn write(&self, entry: &Entry) -> Result<()> {
+        let (namespace, name) = entry
+            .target
+            .split_once('/')
+            .unwrap_or((crate::OCF_NAMESPACE, entry.target.as_str()));
+        let (type_, result) = match (&entry.outcome, &entry.error) {
+            (Outcome::Succeeded, _) => (EventType::Normal, "succeeded".to_string()),
+            (Outcome::Failed, Some(error)) => (EventType::Warning, format!("failed, {}", error)),
+            (Outcome::Failed, None) => (EventType::Warning, "failed".to_string()),
+        };
+        let mut reason = entry.action.to_string();
+        reason[..1].make_ascii_uppercase();
+        let message = format!(
+            "The {} requested by {} {}",
+            entry.action, entry.actor, result
+        );
+        self.recorder
+            .publish(namespace, name, type_, &reason, message)
+            .await
+            .map_err(|err| AuditSinkFailed::new(entry, err))?;
+        Ok(())
+    }
+}
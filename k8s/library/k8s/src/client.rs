@@ -1,15 +1,15 @@
+use crate::api::{KubePods, PodApi};
 use crate::errors::ApiError;
+use crate::logsink::LogSink;
 use async_trait::async_trait;
-use error::*;
 use futures::stream::StreamExt;
 use k8s_openapi::api::core::v1::Pod;
 use kube::api::{LogParams, ObjectMeta};
 use kube::core::Resource;
 use kube::Api;
 use kube::ResourceExt;
-use std::path::Path;
-use tokio::io::BufWriter;
-use tokio_util::io::StreamReader;
+use result::Result;
+use std::sync::Arc;
 
 /// Returns a new Kubernetes client configured for the [OCF Namespace](crate::OCF_NAMESPACE).
 ///
@@ -37,12 +37,31 @@ where
     new_with_namespace(crate::OCF_SYSTEM_NAMESPACE).await
 }
 
+/// Returns a new Kubernetes client that is NOT scoped to any namespace. This is required for
+/// cluster scoped resources, such as nodes.
+///
+/// This function panics if there is any error encountered while constructing the required
+/// configuration object from the environment. This is because a missing Kubernetes environment
+/// is extremely terminal for which there truly is no alternative besides crashing.
+pub async fn new_for_cluster<K>() -> Api<K>
+where
+    <K as Resource>::DynamicType: Default,
+    K: k8s_openapi::Metadata<Ty = ObjectMeta>,
+{
+    Api::all(
+        kube::Client::try_default()
+            .await
+            .map_err(ApiError::from)
+            .unwrap(),
+    )
+}
+
 /// Returns a new Kubernetes client configured for the given namespace.
 ///
 /// This function panics if there is any error encountered while constructing the required
 /// configuration object from the environment. This is because a missing Kubernetes environment
 /// is extremely terminal for which there truly is no alternative besides crashing.
-async fn new_with_namespace<K, N>(namespace: N) -> Api<K>
+pub async fn new_with_namespace<K, N>(namespace: N) -> Api<K>
 where
     <K as Resource>::DynamicType: Default,
     K: k8s_openapi::Metadata<Ty = ObjectMeta>,
@@ -57,47 +76,142 @@ where
     )
 }
 
+/// Returns the [PodApi](PodApi) of the API server that this process runs against.
+///
+/// This function panics if there is any error encountered while constructing the required
+/// configuration object from the environment. This is because a missing Kubernetes environment
+/// is extremely terminal for which there truly is no alternative besides crashing.
+pub async fn pods() -> Arc<dyn PodApi> {
+    Arc::new(KubePods::new().await)
+}
+
 #[async_trait]
 pub trait Logs<T> {
-    async fn stream_into<P: AsRef<Path> + Send>(&self, resource: &T, dst: P);
+    /// Follows the resource's logs into the given [LogSink](LogSink) under the given key until the
+    /// resource stops logging (that is, until its container exits), returning the number of bytes
+    /// written.
+    async fn stream_into(&self, resource: &T, sink: &dyn LogSink, key: &str) -> Result<usize>;
+
+    /// Writes (at most) the last `maximum` bytes of the resource's logs into the given
+    /// [LogSink](LogSink) under the given key, returning the number of bytes written. Unlike
+    /// [stream_into](Logs::stream_into), the logs are NOT followed, so this returns as soon as
+    /// the logs written thus far have been read.
+    ///
+    /// Should `previous` be set, then the logs are those of the previous instance of the container.
+    /// This is what is wanted of a container that has crashed and is waiting to be restarted.
+    async fn tail_into(
+        &self,
+        resource: &T,
+        sink: &dyn LogSink,
+        key: &str,
+        maximum: usize,
+        previous: bool,
+    ) -> Result<usize>;
 }
 
 #[async_trait]
-impl Logs<Pod> for Api<Pod> {
-    async fn stream_into<P: AsRef<Path> + Send>(&self, resource: &Pod, dst: P) {
+impl<P: PodApi + ?Sized> Logs<Pod> for P {
+    async fn stream_into(&self, resource: &Pod, sink: &dyn LogSink, key: &str) -> Result<usize> {
         let lp = &LogParams {
-            container: None,
+            container: connector(resource),
             follow: true,
-            limit_bytes: None,
-            pretty: false,
-            previous: false,
-            since_seconds: None,
-            tail_lines: None,
-            timestamps: false,
+            ..Default::default()
+        };
+        let mut stream = self
+            .log_stream(&namespace(resource), resource.name().as_str(), lp)
+            .await
+            .map_err(ApiError::from)?;
+        let mut writer = sink.open(key).await?;
+        let mut written = 0;
+        let mut interrupted = None;
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(chunk) => {
+                    writer.write(&chunk).await?;
+                    written += chunk.len();
+                }
+                Err(err) => {
+                    interrupted = Some(ApiError::from(err));
+                    break;
+                }
+            }
+        }
+        // Whatever was streamed before an interruption is still worth keeping.
+        writer.close().await?;
+        match interrupted {
+            Some(err) => Err(err.into()),
+            None => Ok(written),
+        }
+    }
+
+    async fn tail_into(
+        &self,
+        resource: &Pod,
+        sink: &dyn LogSink,
+        key: &str,
+        maximum: usize,
+        previous: bool,
+    ) -> Result<usize> {
+        let lp = &LogParams {
+            container: connector(resource),
+            previous,
+            ..Default::default()
         };
-        let stream = self
-            .log_stream(resource.name().as_str(), lp)
+        let mut stream = self
+            .log_stream(&namespace(resource), resource.name().as_str(), lp)
             .await
-            .unwrap()
-            .map(|err| match err {
-                Err(err) => Err(StreamError::from(err)),
-                Ok(buf) => Ok(buf),
-            });
-        let mut src = StreamReader::new(stream);
-        let mut dst = BufWriter::new(tokio::fs::File::create(dst).await.unwrap());
-        let _ = tokio::io::copy(&mut src, &mut dst).await;
+            .map_err(ApiError::from)?;
+        let mut tail = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            tail.extend_from_slice(&chunk.map_err(ApiError::from)?);
+            // Trimming only once the buffer has doubled keeps the copying down.
+            if tail.len() > maximum * 2 {
+                keep_tail(&mut tail, maximum);
+            }
+        }
+        keep_tail(&mut tail, maximum);
+        let mut writer = sink.open(key).await?;
+        writer.write(&tail).await?;
+        writer.close().await?;
+        Ok(tail.len())
     }
 }
 
-#[derive(Error, Debug)]
-#[error("this is hard")]
-struct StreamError {
-    #[from]
-    cause: kube::Error,
+/// The namespace of the given pod, which is presumed to be the [default](crate::OCF_NAMESPACE)
+/// namespace should it not name one.
+fn namespace(pod: &Pod) -> String {
+    pod.namespace()
+        .unwrap_or_else(|| crate::OCF_NAMESPACE.to_string())
+}
+
+/// Pods with sidecars have more than one container, so the connector's own container (which is
+/// always the first) must be named.
+fn connector(pod: &Pod) -> Option<String> {
+    pod.spec
+        .as_ref()
+        .and_then(|spec| spec.containers.first())
+        .map(|container| container.name.clone())
 }
 
-impl From<StreamError> for std::io::Error {
-    fn from(error: StreamError) -> Self {
-        std::io::Error::new(std::io::ErrorKind::BrokenPipe, error)
+/// Drops all but the last `maximum` bytes of the given buffer.
+fn keep_tail(buf: &mut Vec<u8>, maximum: usize) {
+    if buf.len() > maximum {
+        buf.drain(..buf.len() - maximum);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tails() {
+        let mut buf = b"first line\nsecond line\n".to_vec();
+        keep_tail(&mut buf, 12);
+        assert_eq!(buf, b"second line\n");
+        keep_tail(&mut buf, 100);
+        assert_eq!(buf, b"second line\n");
+        keep_tail(&mut buf, 0);
+        assert!(buf.is_empty());
     }
 }
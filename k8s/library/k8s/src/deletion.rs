@@ -0,0 +1,141 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The `.metadata.annotations` key under which the reason for a pod's deletion is recorded
+/// immediately before the deletion request is submitted to Kubernetes.
+pub const DELETION_CAUSE_ANNOTATION: &str = "ocf.alation.com/deletion_cause";
+
+/// A `DeletionCause` records WHY a connector pod was torn down. Every deletion issued by
+/// the ACM goes through [delete](crate::delete), which first annotates the pod with its
+/// cause so that downstream observers (the event watcher, waiting clients, operators running
+/// `kubectl describe`) can tell a user initiated delete from a garbage collection.
+///
+/// The annotation value is the [Display](std::fmt::Display) of the cause, which is one of...
+///
+/// * `user`
+/// * `ttl_expired`
+/// * `max_lifetime_exceeded`
+/// * `ill_behaved{<kind>}` where `<kind>` is the [Kind](kind::Kind) of the error that condemned the pod.
+/// * `eviction`
+///
+/// A DeletionCause is serialized as that very same string.
+///
+/// ```
+/// use k8s::deletion::DeletionCause;
+///
+/// let cause = DeletionCause::IllBehaved { kind: "PodCrashed".to_string() };
+/// assert_eq!("ill_behaved{PodCrashed}", cause.to_string());
+/// assert_eq!(cause, "ill_behaved{PodCrashed}".parse().unwrap());
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DeletionCause {
+    /// A client explicitly called the ACM's delete endpoint.
+    User,
+    /// The garbage collector's execution date was reached without a refresh.
+    TtlExpired,
+    /// The pod outlived the ACM's maximum lifetime, regardless of how recently it was refreshed.
+    MaxLifetimeExceeded,
+    /// The ACM judged the connector to be ill-behaved (it crashed, rebooted, failed
+    /// its health check, etc.). The `kind` is that of the error reported to the client.
+    IllBehaved { kind: String },
+    /// Kubernetes evicted the pod (node pressure, preemption, etc.).
+    Eviction,
+}
+
+impl Display for DeletionCause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeletionCause::User => f.write_str("user"),
+            DeletionCause::TtlExpired => f.write_str("ttl_expired"),
+            DeletionCause::MaxLifetimeExceeded => f.write_str("max_lifetime_exceeded"),
+            DeletionCause::IllBehaved { kind } => write!(f, "ill_behaved{{{}}}", kind),
+            DeletionCause::Eviction => f.write_str("eviction"),
+        }
+    }
+}
+
+impl FromStr for DeletionCause {
+    type Err = UnknownDeletionCause;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(DeletionCause::User),
+            "ttl_expired" => Ok(DeletionCause::TtlExpired),
+            "max_lifetime_exceeded" => Ok(DeletionCause::MaxLifetimeExceeded),
+            "eviction" => Ok(DeletionCause::Eviction),
+            _ => s
+                .strip_prefix("ill_behaved{")
+                .and_then(|rest| rest.strip_suffix('}'))
+                .map(|kind| DeletionCause::IllBehaved {
+                    kind: kind.to_string(),
+                })
+                .ok_or_else(|| UnknownDeletionCause {
+                    cause: s.to_string(),
+                }),
+        }
+    }
+}
+
+impl Serialize for DeletionCause {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DeletionCause {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownDeletionCause {
+    pub cause: String,
+}
+
+impl Display for UnknownDeletionCause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' is not a known deletion cause", self.cause)
+    }
+}
+
+impl std::error::Error for UnknownDeletionCause {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for cause in [
+            DeletionCause::User,
+            DeletionCause::TtlExpired,
+            DeletionCause::MaxLifetimeExceeded,
+            DeletionCause::IllBehaved {
+                kind: "PodRebooted".to_string(),
+            },
+            DeletionCause::Eviction,
+        ] {
+            assert_eq!(cause, cause.to_string().parse().unwrap());
+        }
+    }
+
+    #[test]
+    fn serializes_as_displayed() {
+        let cause = DeletionCause::IllBehaved {
+            kind: "PodCrashed".to_string(),
+        };
+        let json = serde_json::to_string(&cause).unwrap();
+        assert_eq!(json, r#""ill_behaved{PodCrashed}""#);
+        assert_eq!(serde_json::from_str::<DeletionCause>(&json).unwrap(), cause);
+    }
+
+    #[test]
+    fn unknown() {
+        assert!("ill_behaved{".parse::<DeletionCause>().is_err());
+        assert!("gremlins".parse::<DeletionCause>().is_err());
+    }
+}
@@ -0,0 +1,381 @@
+use crate::errors::ApiError;
+use error::*;
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, Pod, Service};
+use k8s_openapi::api::networking::v1::NetworkPolicy;
+use k8s_openapi::api::policy::v1::PodDisruptionBudget;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use kube::api::{DeleteParams, ObjectMeta, Patch, PatchParams, PostParams, PropagationPolicy};
+use kube::error::ErrorResponse;
+use kube::{Api, ResourceExt};
+use result::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Debug, Display, Formatter};
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+/// The `.metadata.annotations` key under which a pod records every dependent resource that
+/// could NOT be owned by the pod (and thus must be cleaned up explicitly). The value is a comma
+/// separated list of [DependentRefs](DependentRef).
+pub const DEPENDENTS_ANNOTATION: &str = "ocf.alation.com/dependents";
+
+/// The environment variable that configures the [Propagation](Propagation) of pod deletions.
+pub const DELETION_PROPAGATION: &str = "DELETION_PROPAGATION";
+
+/// A Dependent is an auxiliary resource that may be created on behalf of a connector pod
+/// (a Service fronting it, a PodDisruptionBudget protecting it, etc.) and which MUST be
+/// cleaned up alongside it.
+///
+/// Dependents are created via [create](create), which sets the pod as the dependent's owner
+/// whenever possible so that Kubernetes' own garbage collector removes the dependent when the
+/// pod is deleted. Owner references may not cross namespaces, however, so dependents that live
+/// outside of the pod's namespace are instead recorded in the pod's
+/// [DEPENDENTS_ANNOTATION](DEPENDENTS_ANNOTATION) and deleted explicitly by [cleanup](cleanup).
+///
+/// As the record lives on the pod itself, it survives restarts of the ACM and is inherited by any
+/// ACM that adopts the pod.
+pub trait Dependent:
+    kube::Resource<DynamicType = ()>
+    + k8s_openapi::Resource
+    + k8s_openapi::Metadata<Ty = ObjectMeta>
+    + Clone
+    + Debug
+    + DeserializeOwned
+    + Serialize
+    + Send
+    + Sync
+    + 'static
+{
+}
+
+impl Dependent for Service {}
+impl Dependent for PodDisruptionBudget {}
+impl Dependent for NetworkPolicy {}
+impl Dependent for PersistentVolumeClaim {}
+
+/// A DependentRef is the `<kind>/<namespace>/<name>` of a dependent that must be cleaned up
+/// explicitly.
+///
+/// ```
+/// use k8s::dependents::DependentRef;
+///
+/// let dependent: DependentRef = "Service/ocf-system/oracle-abcd1234".parse().unwrap();
+/// assert_eq!(dependent.kind, "Service");
+/// assert_eq!(dependent.namespace, "ocf-system");
+/// assert_eq!(dependent.name, "oracle-abcd1234");
+/// assert_eq!(dependent.to_string(), "Service/ocf-system/oracle-abcd1234");
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct DependentRef {
+    pub kind: String,
+    pub namespace: String,
+    pub name: String,
+}
+
+impl DependentRef {
+    fn of<K: Dependent>(resource: &K) -> DependentRef {
+        DependentRef {
+            kind: <K as k8s_openapi::Resource>::KIND.to_string(),
+            namespace: resource
+                .namespace()
+                .unwrap_or_else(|| crate::OCF_NAMESPACE.to_string()),
+            name: resource.name(),
+        }
+    }
+}
+
+impl Display for DependentRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}/{}", self.kind, self.namespace, self.name)
+    }
+}
+
+impl FromStr for DependentRef {
+    type Err = MalformedDependentRef;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().split('/').collect::<Vec<&str>>()[..] {
+            [kind, namespace, name]
+                if !kind.is_empty() && !namespace.is_empty() && !name.is_empty() =>
+            {
+                Ok(DependentRef {
+                    kind: kind.to_string(),
+                    namespace: namespace.to_string(),
+                    name: name.to_string(),
+                })
+            }
+            _ => Err(MalformedDependentRef {
+                dependent: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// A Propagation is how the deletion of a pod propagates to its dependents, configured under the
+/// [DELETION_PROPAGATION](DELETION_PROPAGATION) environment variable as one of `background`
+/// (the default), `foreground`, or `orphan`.
+///
+/// * `background`: The pod is deleted immediately and its dependents are deleted afterwards.
+/// * `foreground`: The pod lingers (with a deletion timestamp) until all of its owned dependents are deleted.
+/// * `orphan`: Dependents are left in place. This is only useful for debugging.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Propagation {
+    Background,
+    Foreground,
+    Orphan,
+}
+
+impl Propagation {
+    /// Returns the configured Propagation.
+    ///
+    /// This function PANICS if the environment variable is set to an unknown value. Consumers
+    /// SHOULD call this function on program startup so that such a misconfiguration is caught early.
+    pub fn configured() -> Propagation {
+        match std::env::var(DELETION_PROPAGATION) {
+            Ok(value) if !value.trim().is_empty() => value.parse().unwrap_or_else(|_| {
+                panic!(
+                    "the {} environment variable was set to '{}'. It can be one of either \
+                background, foreground, or orphan (case insensitive)",
+                    DELETION_PROPAGATION, value
+                )
+            }),
+            _ => Propagation::Background,
+        }
+    }
+
+    /// The [PropagationPolicy](kube::api::PropagationPolicy) submitted alongside a deletion.
+    pub fn policy(self) -> PropagationPolicy {
+        match self {
+            Propagation::Background => PropagationPolicy::Background,
+            Propagation::Foreground => PropagationPolicy::Foreground,
+            Propagation::Orphan => PropagationPolicy::Orphan,
+        }
+    }
+}
+
+impl FromStr for Propagation {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "background" => Ok(Propagation::Background),
+            "foreground" => Ok(Propagation::Foreground),
+            "orphan" => Ok(Propagation::Orphan),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Creates the given dependent on behalf of the given pod.
+///
+/// If the dependent lives in the same namespace as the pod, then the pod is set as its owner.
+/// Otherwise, the dependent is first recorded in the pod's [DEPENDENTS_ANNOTATION](DEPENDENTS_ANNOTATION)
+/// and only then created, so that a failure between the two steps can never leak the dependent.
+pub async fn create<K: Dependent>(pod: &Pod, mut dependent: K) -> Result<K> {
+    if dependent.meta().namespace.is_none() {
+        dependent.meta_mut().namespace = pod.namespace();
+    }
+    if !own(pod, &mut dependent) {
+        record(pod, DependentRef::of(&dependent)).await?;
+    }
+    let namespace = dependent
+        .namespace()
+        .unwrap_or_else(|| crate::OCF_NAMESPACE.to_string());
+    let client: Api<K> = crate::client::new_with_namespace(namespace).await;
+    Ok(client
+        .create(&PostParams::default(), &dependent)
+        .await
+        .map_err(ApiError::from)?)
+}
+
+/// Returns every dependent recorded in the given pod's [DEPENDENTS_ANNOTATION](DEPENDENTS_ANNOTATION).
+/// Malformed entries are skipped.
+pub fn recorded(pod: &Pod) -> BTreeSet<DependentRef> {
+    pod.annotations()
+        .get(DEPENDENTS_ANNOTATION)
+        .map(|dependents| parse(dependents))
+        .unwrap_or_default()
+}
+
+/// Deletes every dependent recorded in the given pod's [DEPENDENTS_ANNOTATION](DEPENDENTS_ANNOTATION).
+/// Dependents that are owned by the pod are left to Kubernetes. This procedure is idempotent and
+/// does nothing if the configured [Propagation](Propagation) is `orphan`.
+///
+/// Every recorded dependent is attempted, even if an earlier one fails. The first failure (if any)
+/// is returned.
+pub async fn cleanup(pod: &Pod) -> Result<()> {
+    if Propagation::configured() == Propagation::Orphan {
+        return Ok(());
+    }
+    let mut result = Ok(());
+    for dependent in recorded(pod) {
+        let deleted = match dependent.kind.as_str() {
+            "Service" => delete::<Service>(&dependent).await,
+            "PodDisruptionBudget" => delete::<PodDisruptionBudget>(&dependent).await,
+            "NetworkPolicy" => delete::<NetworkPolicy>(&dependent).await,
+            "PersistentVolumeClaim" => delete::<PersistentVolumeClaim>(&dependent).await,
+            _ => Err(UnsupportedDependent {
+                dependent: dependent.to_string(),
+            }
+            .into()),
+        };
+        if result.is_ok() {
+            result = deleted;
+        }
+    }
+    result
+}
+
+/// Sets the given pod as the owner of the given dependent, returning `false` if that is not
+/// possible (the pod has not yet been assigned a UID or the two live in different namespaces).
+fn own<K: Dependent>(pod: &Pod, dependent: &mut K) -> bool {
+    let uid = match pod.metadata.uid.as_ref() {
+        Some(uid) => uid.clone(),
+        None => return false,
+    };
+    if dependent.namespace() != pod.namespace() {
+        return false;
+    }
+    dependent
+        .meta_mut()
+        .owner_references
+        .get_or_insert_with(Vec::new)
+        .push(OwnerReference {
+            api_version: <Pod as k8s_openapi::Resource>::API_VERSION.to_string(),
+            kind: <Pod as k8s_openapi::Resource>::KIND.to_string(),
+            name: pod.name(),
+            uid,
+            controller: Some(true),
+            block_owner_deletion: Some(true),
+        });
+    true
+}
+
+/// Adds the given dependent to the pod's [DEPENDENTS_ANNOTATION](DEPENDENTS_ANNOTATION).
+async fn record(pod: &Pod, dependent: DependentRef) -> Result<()> {
+    let namespace = pod
+        .namespace()
+        .unwrap_or_else(|| crate::OCF_NAMESPACE.to_string());
+    let client: Api<Pod> = crate::client::new_with_namespace(namespace).await;
+    // Re-read the pod so that we do not clobber any dependent recorded since it was given to us.
+    let current = client.get(&pod.name()).await.map_err(ApiError::from)?;
+    let mut dependents = recorded(&current);
+    dependents.insert(dependent);
+    let mut patch = Pod::default();
+    patch.metadata.annotations = Some(BTreeMap::from_iter([(
+        DEPENDENTS_ANNOTATION.to_string(),
+        format(&dependents),
+    )]));
+    client
+        .patch(&pod.name(), &PatchParams::default(), &Patch::Merge(patch))
+        .await
+        .map_err(ApiError::from)?;
+    Ok(())
+}
+
+async fn delete<K: Dependent>(dependent: &DependentRef) -> Result<()> {
+    let client: Api<K> = crate::client::new_with_namespace(&dependent.namespace).await;
+    match client
+        .delete(&dependent.name, &DeleteParams::default())
+        .await
+    {
+        Ok(_) | Err(kube::error::Error::Api(ErrorResponse { code: 404, .. })) => Ok(()),
+        Err(err) => Err(ApiError::from(err).into()),
+    }
+}
+
+fn parse(dependents: &str) -> BTreeSet<DependentRef> {
+    dependents
+        .split(',')
+        .filter(|dependent| !dependent.trim().is_empty())
+        .filter_map(|dependent| dependent.parse().ok())
+        .collect()
+}
+
+fn format(dependents: &BTreeSet<DependentRef>) -> String {
+    dependents
+        .iter()
+        .map(DependentRef::to_string)
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error("'{dependent}' is not a valid dependent. Expected '<kind>/<namespace>/<name>'.")]
+#[error_code("K8S-1100")]
+pub struct MalformedDependentRef {
+    dependent: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error(
+    "The dependent '{dependent}' is of a kind that the ACM does not know how to clean up. \
+It must be deleted manually."
+)]
+#[error_code("K8S-1101")]
+pub struct UnsupportedDependent {
+    dependent: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::fixtures::pod;
+
+    fn owner(namespace: &str, uid: Option<&str>) -> Pod {
+        let mut pod = pod("oracle-abcd1234", "Running");
+        pod.metadata.namespace = Some(namespace.to_string());
+        pod.metadata.uid = uid.map(str::to_string);
+        pod
+    }
+
+    fn service(namespace: &str) -> Service {
+        let mut service = Service::default();
+        service.metadata.name = Some("oracle-abcd1234".to_string());
+        service.metadata.namespace = Some(namespace.to_string());
+        service
+    }
+
+    #[test]
+    fn owns_dependents_in_the_same_namespace() {
+        let mut dependent = service("ocf");
+        assert!(own(&owner("ocf", Some("1234")), &mut dependent));
+        let owner = &dependent.metadata.owner_references.unwrap()[0];
+        assert_eq!(owner.kind, "Pod");
+        assert_eq!(owner.api_version, "v1");
+        assert_eq!(owner.uid, "1234");
+        assert_eq!(owner.name, "oracle-abcd1234");
+    }
+
+    #[test]
+    fn cannot_own_across_namespaces() {
+        let mut dependent = service("ocf-system");
+        assert!(!own(&owner("ocf", Some("1234")), &mut dependent));
+        assert!(dependent.metadata.owner_references.is_none());
+        let mut dependent = service("ocf");
+        assert!(!own(&owner("ocf", None), &mut dependent));
+    }
+
+    #[test]
+    fn annotation_round_trip() {
+        let mut dependents = parse("Service/ocf-system/a, NetworkPolicy/ocf-system/b,,garbage");
+        assert_eq!(dependents.len(), 2);
+        dependents.insert(DependentRef::of(&service("ocf-system")));
+        let formatted = format(&dependents);
+        assert_eq!(
+            formatted,
+            "NetworkPolicy/ocf-system/b,Service/ocf-system/a,Service/ocf-system/oracle-abcd1234"
+        );
+        assert_eq!(parse(&formatted), dependents);
+    }
+
+    #[test]
+    fn propagation() {
+        assert_eq!("Foreground".parse(), Ok(Propagation::Foreground));
+        assert_eq!(" orphan ".parse(), Ok(Propagation::Orphan));
+        assert!("cascade".parse::<Propagation>().is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use k8s_openapi::api::core::v1::Pod;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The `.metadata.annotations` key under which every pod created by [deploy](crate::deploy)
+/// records the name that it was deployed as, before that name was
+/// [sanitized](names::rfc1123_subdomain) into its `.metadata.name`.
+pub const DISPLAY_NAME_ANNOTATION: &str = "ocf.alation.com/display_name";
+
+/// The `.metadata.annotations` key under which every pod created by [deploy](crate::deploy)
+/// records the tag of its image, should its image reference carry one.
+pub const TAG_ANNOTATION: &str = "ocf.alation.com/tag";
+
+/// A DisplayName is what a pod was deployed as, by which operators may identify a pod without
+/// having to decode its generated `.metadata.name`. For example, a pod deployed as
+/// `Oracle Connector v1.2.3` is named something akin to `oracle-connector-v1-2-3-5f0c...`.
+///
+/// ```
+/// use k8s::display::DisplayName;
+/// use k8s_openapi::api::core::v1::Pod;
+///
+/// let mut pod = Pod::default();
+/// let display = DisplayName::new("Oracle Connector v1.2.3", Some("abcd1234"));
+/// display.annotate(&mut pod);
+/// assert_eq!(DisplayName::of(&pod), display);
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct DisplayName {
+    pub name: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl DisplayName {
+    pub fn new<N: Into<String>, T: Into<String>>(name: N, tag: Option<T>) -> DisplayName {
+        DisplayName {
+            name: Some(name.into()),
+            tag: tag.map(Into::into),
+        }
+    }
+
+    /// Returns the DisplayName recorded within the annotations of the given pod. A pod that was
+    /// not created by [deploy](crate::deploy) has neither annotation.
+    pub fn of(pod: &Pod) -> DisplayName {
+        let annotations = pod.metadata.annotations.as_ref();
+        let annotation = |key: &str| {
+            annotations
+                .and_then(|annotations| annotations.get(key))
+                .cloned()
+        };
+        DisplayName {
+            name: annotation(DISPLAY_NAME_ANNOTATION),
+            tag: annotation(TAG_ANNOTATION),
+        }
+    }
+
+    /// Records this DisplayName within the annotations of the given pod.
+    pub fn annotate(&self, pod: &mut Pod) {
+        let annotations = pod.metadata.annotations.get_or_insert_with(BTreeMap::new);
+        if let Some(name) = &self.name {
+            annotations.insert(DISPLAY_NAME_ANNOTATION.to_string(), name.clone());
+        }
+        if let Some(tag) = &self.tag {
+            annotations.insert(TAG_ANNOTATION.to_string(), tag.clone());
+        }
+    }
+}
+
+/// Returns the tag of the given image reference (such as `abcd1234` of
+/// `registry.kube-system/ocf:abcd1234`). A reference that is [pinned](crate::pinned_digest) to a
+/// digest alone has no tag, nor is the port of a registry mistaken for one.
+pub fn reference_tag(reference: &str) -> Option<&str> {
+    let reference = reference.split('@').next().unwrap_or(reference);
+    let (repository, tag) = reference.rsplit_once(':')?;
+    if tag.contains('/') || repository.is_empty() {
+        return None;
+    }
+    Some(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_tags() {
+        assert_eq!(
+            reference_tag("registry.kube-system/ocf:abcd1234"),
+            Some("abcd1234")
+        );
+        assert_eq!(
+            reference_tag("registry.kube-system:5000/ocf:abcd1234"),
+            Some("abcd1234")
+        );
+        assert_eq!(reference_tag("registry.kube-system:5000/ocf"), None);
+        assert_eq!(
+            reference_tag("registry.kube-system/ocf@sha256:cb1ff0854b8864a6a68ee0b5e509d4d9"),
+            None
+        );
+        assert_eq!(
+            reference_tag(
+                "registry.kube-system/ocf:abcd1234@sha256:cb1ff0854b8864a6a68ee0b5e509d4d9"
+            ),
+            Some("abcd1234")
+        );
+    }
+
+    #[test]
+    fn unannotated() {
+        assert_eq!(DisplayName::of(&Pod::default()), DisplayName::default());
+    }
+}
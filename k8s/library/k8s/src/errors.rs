@@ -1,6 +1,8 @@
 use error::*;
+use k8s_openapi::api::core::v1::Pod;
 
 #[derive(Error, Kind, AcmError, HttpCode, Debug)]
+#[error_code("K8S-1001")]
 pub enum ApiError {
     #[error("The Kubernetes API server rejected our request")]
     #[code(Status::InternalServerError)]
@@ -47,6 +49,79 @@ impl From<kube::Error> for ApiError {
     }
 }
 
+#[derive(Error, Kind, AcmError, HttpCode, Debug)]
+#[error(
+    "A pod named '{name}' already exists (phase: {phase}, image: {image}, created: {created}, \
+servicer: {servicer}). Either delete it first or deploy again with resuffix enabled so that a \
+fresh name is generated."
+)]
+#[code(Status::Conflict)]
+#[error_code("K8S-1002")]
+pub struct NameConflict {
+    pub name: String,
+    pub phase: String,
+    pub image: String,
+    pub created: String,
+    pub servicer: String,
+}
+
+impl NameConflict {
+    /// Describes a conflict over the given name with the given existing pod. The existing pod
+    /// may be unknown if it was deleted between the conflict and our attempt to retrieve it.
+    pub fn new<N: Into<String>>(name: N, existing: Option<&Pod>) -> NameConflict {
+        let unknown = || "unknown".to_string();
+        NameConflict {
+            name: name.into(),
+            phase: existing
+                .and_then(|pod| pod.status.as_ref()?.phase.clone())
+                .unwrap_or_else(unknown),
+            image: existing
+                .and_then(|pod| pod.spec.as_ref()?.containers.first()?.image.clone())
+                .unwrap_or_else(unknown),
+            created: existing
+                .and_then(|pod| pod.metadata.creation_timestamp.as_ref())
+                .map(|created| created.0.to_rfc3339())
+                .unwrap_or_else(unknown),
+            servicer: existing
+                .and_then(|pod| pod.metadata.labels.as_ref()?.get("servicer").cloned())
+                .unwrap_or_else(unknown),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_conflict() {
+        let mut pod = crate::pod::new(
+            crate::OCF_NAMESPACE,
+            "registry.kurl/ocf:abcd",
+            "oracle",
+            &Default::default(),
+        )
+        .unwrap();
+        pod.status = Some(k8s_openapi::api::core::v1::PodStatus {
+            phase: Some("Running".to_string()),
+            ..Default::default()
+        });
+        let conflict = NameConflict::new("oracle-1234", Some(&pod));
+        assert_eq!(conflict.phase, "Running");
+        assert_eq!(conflict.image, "registry.kurl/ocf:abcd");
+        assert_eq!(conflict.created, "unknown");
+        assert_eq!(conflict.error_code(), Some("K8S-1002"));
+    }
+
+    #[test]
+    fn name_conflict_without_existing() {
+        let conflict = NameConflict::new("oracle-1234", None);
+        assert_eq!(conflict.name, "oracle-1234");
+        assert_eq!(conflict.phase, "unknown");
+        assert_eq!(conflict.servicer, "unknown");
+    }
+}
+
 // This is a copy paste of the API errors possible just for keeping notes to myself.
 
 // #[cfg_attr(docsrs, doc(cfg(any(feature = "config", feature = "client"))))]
@@ -0,0 +1,139 @@
+use crate::errors::ApiError;
+use error::*;
+use k8s_openapi::api::core::v1::{Event, EventSource, ObjectReference};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+use k8s_openapi::chrono::Utc;
+use kube::api::{ListParams, ObjectMeta, PostParams};
+use result::Result;
+use std::fmt::{Display, Formatter};
+
+/// The type of an Event, as shown by `kubectl describe`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EventType {
+    /// Something expected happened, such as a pod being garbage collected.
+    Normal,
+    /// Something went wrong, such as a pod failing its health check.
+    Warning,
+}
+
+impl Display for EventType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventType::Normal => write!(f, "Normal"),
+            EventType::Warning => write!(f, "Warning"),
+        }
+    }
+}
+
+/// A Recorder posts Kubernetes Events attached to pods, such that the decisions made about a pod
+/// show up within `kubectl describe pod` (and `kubectl get events`).
+///
+/// ```ignore
+/// let recorder = Recorder::new("acm");
+/// recorder
+///     .publish("ocf", "tennis", EventType::Warning, "PodRebooted", "The connector restarted")
+///     .await?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    component: String,
+}
+
+impl Recorder {
+    /// A Recorder whose Events are reported by the given component (such as `acm`).
+    pub fn new<C: ToString>(component: C) -> Recorder {
+        Recorder {
+            component: component.to_string(),
+        }
+    }
+
+    /// Posts an Event of the given type and reason (a short, UpperCamelCase, machine readable
+    /// word such as `GarbageCollected`) attached to the given pod.
+    ///
+    /// The pod need not exist anymore, in which case the Event is only shown by
+    /// `kubectl get events`.
+    pub async fn publish<M: ToString>(
+        &self,
+        namespace: &str,
+        pod: &str,
+        type_: EventType,
+        reason: &str,
+        message: M,
+    ) -> Result<()> {
+        let now = Time(Utc::now());
+        let event = Event {
+            metadata: ObjectMeta {
+                generate_name: Some(format!("{}.", pod)),
+                namespace: Some(namespace.to_string()),
+                ..Default::default()
+            },
+            involved_object: ObjectReference {
+                api_version: Some("v1".to_string()),
+                kind: Some("Pod".to_string()),
+                name: Some(pod.to_string()),
+                namespace: Some(namespace.to_string()),
+                ..Default::default()
+            },
+            reason: Some(reason.to_string()),
+            message: Some(message.to_string()),
+            type_: Some(type_.to_string()),
+            source: Some(EventSource {
+                component: Some(self.component.clone()),
+                ..Default::default()
+            }),
+            first_timestamp: Some(now.clone()),
+            last_timestamp: Some(now),
+            count: Some(1),
+            ..Default::default()
+        };
+        crate::client::new_with_namespace::<Event, _>(namespace)
+            .await
+            .create(&PostParams::default(), &event)
+            .await
+            .map_err(|err| EventFailed {
+                pod: format!("{}/{}", namespace, pod),
+                reason: reason.to_string(),
+                cause: ApiError::from(err).to_string(),
+            })?;
+        Ok(())
+    }
+}
+
+/// Lists the Events attached to the given pod (by Kubernetes and by any [Recorder](Recorder)),
+/// oldest first, such as the `FailedScheduling` Events posted by the scheduler.
+pub async fn of(namespace: &str, pod: &str) -> Result<Vec<Event>> {
+    let params = ListParams::default().fields(&format!(
+        "involvedObject.kind=Pod,involvedObject.name={}",
+        pod
+    ));
+    let mut events = crate::client::new_with_namespace::<Event, _>(namespace)
+        .await
+        .list(&params)
+        .await
+        .map_err(|err| EventsUnavailable {
+            pod: format!("{}/{}", namespace, pod),
+            cause: ApiError::from(err).to_string(),
+        })?
+        .items;
+    events.sort_by_key(|event| event.last_timestamp.as_ref().map(|time| time.0));
+    Ok(events)
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error("Failed to post the {reason} Event of {pod}, {cause}.")]
+#[error_code("K8S-1219")]
+pub struct EventFailed {
+    pod: String,
+    reason: String,
+    cause: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error("Failed to list the Events of {pod}, {cause}.")]
+#[error_code("K8S-1222")]
+pub struct EventsUnavailable {
+    pod: String,
+    cause: String,
+}
@@ -0,0 +1,170 @@
+use crate::errors::ApiError;
+use error::*;
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Status as ExecStatus;
+use kube::api::AttachParams;
+use kube::Api;
+use result::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The maximum amount of time that an [exec](exec) may run for.
+pub const EXEC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The maximum number of bytes of each of stdout and stderr that an [exec](exec) returns. Any
+/// more output is discarded, and the output marked as `truncated`.
+pub const MAXIMUM_OUTPUT: usize = 64 * 1024;
+
+/// The captured result of a command run within a connector's container.
+#[derive(Serialize, Deserialize, Kind, Debug, Clone, Default, Eq, PartialEq)]
+pub struct ExecOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// The exit code of the command, if Kubernetes reported one.
+    pub exit_code: Option<i32>,
+    /// Whether either stdout or stderr exceeded [MAXIMUM_OUTPUT](MAXIMUM_OUTPUT).
+    pub truncated: bool,
+}
+
+/// Runs the given command within the connector's container (the first container) of the pod
+/// of the given ID, capturing its stdout and stderr. Commands are given no stdin and no TTY, and
+/// may run for no longer than [EXEC_TIMEOUT](EXEC_TIMEOUT).
+pub async fn exec<S: AsRef<str>, I: AsRef<str>>(
+    namespace: S,
+    id: I,
+    command: Vec<String>,
+) -> Result<ExecOutput> {
+    let client: Api<Pod> = crate::client::new_with_namespace(namespace.as_ref()).await;
+    let pod = client.get(id.as_ref()).await.map_err(ApiError::from)?;
+    let container = pod
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.containers.first())
+        .map(|container| container.name.clone())
+        .ok_or_else(|| crate::pod::PodHasNoContainers {
+            op: "executing a command".to_string(),
+        })?;
+    let params = AttachParams::default()
+        .container(container)
+        .stdin(false)
+        .stdout(true)
+        .stderr(true);
+    let run = async {
+        let mut process = client
+            .exec(id.as_ref(), command.clone(), &params)
+            .await
+            .map_err(ApiError::from)?;
+        let stdout = process.stdout();
+        let stderr = process.stderr();
+        let (stdout, stderr, status) = tokio::join!(capture(stdout), capture(stderr), process);
+        Ok::<_, Box<dyn AcmError>>((stdout, stderr, status))
+    };
+    let ((stdout, stdout_truncated), (stderr, stderr_truncated), status) =
+        tokio::time::timeout(EXEC_TIMEOUT, run)
+            .await
+            .map_err(|_| ExecTimedOut {
+                command: command.join(" "),
+                timeout: EXEC_TIMEOUT.as_secs(),
+            })??;
+    Ok(ExecOutput {
+        stdout,
+        stderr,
+        exit_code: status.as_ref().and_then(exit_code),
+        truncated: stdout_truncated || stderr_truncated,
+    })
+}
+
+/// Reads the entirety of the given stream (so that the process is never blocked on writing to
+/// it), keeping only the first [MAXIMUM_OUTPUT](MAXIMUM_OUTPUT) bytes.
+async fn capture(stream: Option<impl AsyncRead + Unpin>) -> (String, bool) {
+    let mut stream = match stream {
+        Some(stream) => stream,
+        None => return (String::new(), false),
+    };
+    let mut output = Vec::new();
+    let mut buf = [0; 8192];
+    let mut truncated = false;
+    loop {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                let room = MAXIMUM_OUTPUT.saturating_sub(output.len());
+                truncated |= n > room;
+                output.extend_from_slice(&buf[..n.min(room)]);
+            }
+        }
+    }
+    (String::from_utf8_lossy(&output).into_owned(), truncated)
+}
+
+/// Kubernetes reports a successful exit as a `Success` and any other exit as a `Failure` whose
+/// details carry the exit code as the cause of reason `ExitCode`.
+fn exit_code(status: &ExecStatus) -> Option<i32> {
+    if status.status.as_deref() == Some("Success") {
+        return Some(0);
+    }
+    status
+        .details
+        .as_ref()?
+        .causes
+        .iter()
+        .flatten()
+        .find(|cause| cause.reason.as_deref() == Some("ExitCode"))?
+        .message
+        .as_ref()?
+        .parse()
+        .ok()
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::GatewayTimeout)]
+#[error("The command '{command}' did not complete within {timeout} seconds.")]
+#[error_code("K8S-1216")]
+pub struct ExecTimedOut {
+    command: String,
+    timeout: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Kubernetes always sends the (empty) metadata of a status, which is otherwise required.
+    fn status(mut status: serde_json::Value) -> ExecStatus {
+        status["metadata"] = serde_json::json!({});
+        serde_json::from_value(status).unwrap()
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(
+            exit_code(&status(serde_json::json!({"status": "Success"}))),
+            Some(0)
+        );
+        let failure = status(serde_json::json!({
+            "status": "Failure",
+            "reason": "NonZeroExitCode",
+            "details": {"causes": [{"reason": "ExitCode", "message": "127"}]}
+        }));
+        assert_eq!(exit_code(&failure), Some(127));
+        let failure = status(serde_json::json!({"status": "Failure", "details": {}}));
+        assert_eq!(exit_code(&failure), None);
+    }
+
+    #[test]
+    fn truncation() {
+        tokio_test::block_on(async {
+            let small: &[u8] = b"MemTotal: 1 kB";
+            assert_eq!(
+                capture(Some(small)).await,
+                ("MemTotal: 1 kB".to_string(), false)
+            );
+            let large = vec![b'a'; MAXIMUM_OUTPUT + 1];
+            let (output, truncated) = capture(Some(large.as_slice())).await;
+            assert_eq!(output.len(), MAXIMUM_OUTPUT);
+            assert!(truncated);
+            assert_eq!(capture(None::<&[u8]>).await, (String::new(), false));
+        })
+    }
+}
@@ -0,0 +1,537 @@
+//! FakePods is an in-memory [PodApi](crate::api::PodApi) for tests. It holds pods (and their logs)
+//! in memory and serves watches of them, so that the procedures built upon the PodApi may be
+//! driven step by step.
+//!
+//! The fake is a stand-in for the API server alone, there is no kubelet behind it. Pods do not
+//! progress through their lifecycle on their own; rather, a test [updates](FakePods::update) their
+//! status as it sees fit, and every watch sees each update as it happens. Deletions are immediate
+//! (no grace period is honoured), and watches never fail nor fall out of the watch window.
+//!
+//! ```
+//! use k8s::api::PodApi;
+//! use k8s::fake::FakePods;
+//! use k8s_openapi::api::core::v1::Pod;
+//!
+//! tokio_test::block_on(async {
+//!     let pods = FakePods::default();
+//!     let mut pod = Pod::default();
+//!     pod.metadata.name = Some("oracle".to_string());
+//!     pods.create("ocf", &Default::default(), &pod).await.unwrap();
+//!     pods.update("ocf", "oracle", |pod| {
+//!         pod.status.get_or_insert_with(Default::default).phase = Some("Running".to_string())
+//!     });
+//!     let pod = pods.get("ocf", "oracle").await.unwrap();
+//!     assert_eq!(pod.status.unwrap().phase.as_deref(), Some("Running"));
+//! })
+//! ```
+use crate::api::{LogStream, PodApi, PodEvents};
+use crate::watcher::{self, Event};
+use async_trait::async_trait;
+use bytes::Bytes;
+use either::Either;
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+use kube::api::{DeleteParams, ListParams, LogParams, PostParams};
+use kube::core::response::Status;
+use kube::error::ErrorResponse;
+use kube::ResourceExt;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// The namespace qualified name of a pod.
+type Key = (String, String);
+
+/// FakePods is cheap to clone, and clones share the same pods.
+#[derive(Clone, Default)]
+pub struct FakePods {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    pods: BTreeMap<Key, Pod>,
+    logs: HashMap<Key, Vec<u8>>,
+    watches: Vec<Watch>,
+    /// The last resourceVersion (and UID) handed out.
+    version: u64,
+}
+
+struct Watch {
+    namespace: String,
+    params: ListParams,
+    events: mpsc::UnboundedSender<watcher::Result<Event<Pod>>>,
+}
+
+impl FakePods {
+    /// Stores the given pod as is (into the [default](crate::OCF_NAMESPACE) namespace should it
+    /// name none), replacing any pod of the same name.
+    pub fn insert(&self, mut pod: Pod) {
+        let namespace = pod
+            .namespace()
+            .unwrap_or_else(|| crate::OCF_NAMESPACE.to_string());
+        pod.metadata.namespace = Some(namespace.clone());
+        let mut state = self.state.lock().unwrap();
+        let existed = state
+            .pods
+            .insert((namespace, pod.name()), pod.clone())
+            .is_some();
+        state.notify(if existed {
+            Event::Applied(pod)
+        } else {
+            Event::Added(pod)
+        });
+    }
+
+    /// Modifies the named pod in place (say, to move it into the `Running` phase), exactly as
+    /// the kubelet would have.
+    ///
+    /// This panics should there be no such pod, as that is a mistake in the test itself.
+    pub fn update<F: FnOnce(&mut Pod)>(&self, namespace: &str, name: &str, update: F) {
+        let mut state = self.state.lock().unwrap();
+        let version = state.next_version();
+        let pod = state
+            .pods
+            .get_mut(&(namespace.to_string(), name.to_string()))
+            .unwrap_or_else(|| panic!("there is no pod {}/{} to update", namespace, name));
+        update(pod);
+        pod.metadata.resource_version = Some(version);
+        let pod = pod.clone();
+        state.notify(Event::Applied(pod));
+    }
+
+    /// Returns the named pod, should it exist.
+    pub fn pod(&self, namespace: &str, name: &str) -> Option<Pod> {
+        self.state
+            .lock()
+            .unwrap()
+            .pods
+            .get(&(namespace.to_string(), name.to_string()))
+            .cloned()
+    }
+
+    /// Returns every pod within the given namespace, ordered by name.
+    pub fn pods(&self, namespace: &str) -> Vec<Pod> {
+        self.state
+            .lock()
+            .unwrap()
+            .pods
+            .iter()
+            .filter(|((ns, _), _)| ns == namespace)
+            .map(|(_, pod)| pod.clone())
+            .collect()
+    }
+
+    /// Sets the logs that a [log stream](PodApi::log_stream) of the named pod yields.
+    pub fn set_logs<L: Into<Vec<u8>>>(&self, namespace: &str, name: &str, logs: L) {
+        self.state
+            .lock()
+            .unwrap()
+            .logs
+            .insert((namespace.to_string(), name.to_string()), logs.into());
+    }
+}
+
+impl State {
+    fn next_version(&mut self) -> String {
+        self.version += 1;
+        self.version.to_string()
+    }
+
+    /// Hands the given event to every watch that selects its pod, forgetting any watch that has
+    /// since been dropped.
+    fn notify(&mut self, event: Event<Pod>) {
+        let pod = match &event {
+            Event::Added(pod) | Event::Applied(pod) | Event::Deleted(pod) => pod,
+            Event::Restarted(_) => unreachable!("restarts are only ever sent to new watches"),
+        };
+        let namespace = pod.namespace().unwrap_or_default();
+        self.watches.retain(|watch| {
+            if watch.namespace != namespace || !selects(&watch.params, pod) {
+                return !watch.events.is_closed();
+            }
+            watch.events.send(Ok(event.clone())).is_ok()
+        });
+    }
+}
+
+#[async_trait]
+impl PodApi for FakePods {
+    async fn get(&self, namespace: &str, name: &str) -> kube::Result<Pod> {
+        self.pod(namespace, name)
+            .ok_or_else(|| not_found(namespace, name))
+    }
+
+    async fn create(&self, namespace: &str, params: &PostParams, pod: &Pod) -> kube::Result<Pod> {
+        let mut state = self.state.lock().unwrap();
+        let key = (namespace.to_string(), pod.name());
+        if state.pods.contains_key(&key) {
+            return Err(kube::Error::Api(ErrorResponse {
+                status: "Failure".to_string(),
+                message: format!("pods \"{}\" already exists", key.1),
+                reason: "AlreadyExists".to_string(),
+                code: 409,
+            }));
+        }
+        let mut pod = pod.clone();
+        pod.metadata.namespace = Some(namespace.to_string());
+        if params.dry_run {
+            return Ok(pod);
+        }
+        let version = state.next_version();
+        pod.metadata.uid = Some(format!("fake-{}", version));
+        pod.metadata.resource_version = Some(version);
+        pod.metadata.creation_timestamp = Some(Time(k8s_openapi::chrono::Utc::now()));
+        state.pods.insert(key, pod.clone());
+        state.notify(Event::Added(pod.clone()));
+        Ok(pod)
+    }
+
+    async fn delete(
+        &self,
+        namespace: &str,
+        name: &str,
+        params: &DeleteParams,
+    ) -> kube::Result<Either<Pod, Status>> {
+        let mut state = self.state.lock().unwrap();
+        let key = (namespace.to_string(), name.to_string());
+        if params.dry_run {
+            return match state.pods.get(&key) {
+                Some(pod) => Ok(Either::Left(pod.clone())),
+                None => Err(not_found(namespace, name)),
+            };
+        }
+        let pod = state
+            .pods
+            .remove(&key)
+            .ok_or_else(|| not_found(namespace, name))?;
+        state.logs.remove(&key);
+        state.notify(Event::Deleted(pod.clone()));
+        Ok(Either::Left(pod))
+    }
+
+    async fn patch(
+        &self,
+        namespace: &str,
+        name: &str,
+        patch: &serde_json::Value,
+    ) -> kube::Result<Pod> {
+        let mut state = self.state.lock().unwrap();
+        let version = state.next_version();
+        let pod = state
+            .pods
+            .get_mut(&(namespace.to_string(), name.to_string()))
+            .ok_or_else(|| not_found(namespace, name))?;
+        let mut merged = serde_json::to_value(&*pod)?;
+        merge(&mut merged, patch);
+        *pod = serde_json::from_value(merged)?;
+        pod.metadata.resource_version = Some(version);
+        let pod = pod.clone();
+        state.notify(Event::Applied(pod.clone()));
+        Ok(pod)
+    }
+
+    fn watch(&self, namespace: &str, params: ListParams) -> PodEvents {
+        let (events, receiver) = mpsc::unbounded_channel();
+        let mut state = self.state.lock().unwrap();
+        let listed = state
+            .pods
+            .iter()
+            .filter(|((ns, _), pod)| ns == namespace && selects(&params, pod))
+            .map(|(_, pod)| pod.clone())
+            .collect();
+        let _ = events.send(Ok(Event::Restarted(listed)));
+        state.watches.push(Watch {
+            namespace: namespace.to_string(),
+            params,
+            events,
+        });
+        futures::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|event| (event, receiver))
+        })
+        .boxed()
+    }
+
+    /// The logs are those [set](FakePods::set_logs) for the pod, regardless of the parameters.
+    async fn log_stream(
+        &self,
+        namespace: &str,
+        name: &str,
+        _params: &LogParams,
+    ) -> kube::Result<LogStream> {
+        let state = self.state.lock().unwrap();
+        let key = (namespace.to_string(), name.to_string());
+        if !state.pods.contains_key(&key) {
+            return Err(not_found(namespace, name));
+        }
+        let logs = state.logs.get(&key).cloned().unwrap_or_default();
+        Ok(futures::stream::iter(vec![Ok(Bytes::from(logs))]).boxed())
+    }
+}
+
+fn not_found(namespace: &str, name: &str) -> kube::Error {
+    kube::Error::Api(ErrorResponse {
+        status: "Failure".to_string(),
+        message: format!("pods \"{}\" not found in {}", name, namespace),
+        reason: "NotFound".to_string(),
+        code: 404,
+    })
+}
+
+/// Applies the given JSON merge patch onto the given value, as per RFC 7386.
+fn merge(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let patch = match patch.as_object() {
+        Some(patch) => patch,
+        None => {
+            *target = patch.clone();
+            return;
+        }
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().unwrap();
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge(
+                target.entry(key.clone()).or_insert(serde_json::Value::Null),
+                value,
+            );
+        }
+    }
+}
+
+/// Returns whether the given parameters select the given pod. Field selectors may only select
+/// upon `metadata.name`, while label selectors may only be made up of equality (`=` or `!=`) and
+/// existence (`key` or `!key`) requirements. Anything else is a mistake in the test itself, and
+/// so panics.
+fn selects(params: &ListParams, pod: &Pod) -> bool {
+    let fields = params.field_selector.as_deref().unwrap_or_default();
+    let labels = params.label_selector.as_deref().unwrap_or_default();
+    let name = pod.name();
+    let fields_match = requirements(fields).all(|field| match field.split_once('=') {
+        Some(("metadata.name", value)) => value == name,
+        _ => panic!("the fake cannot select pods by the field {}", field),
+    });
+    let pod_labels = pod.labels();
+    fields_match
+        && requirements(labels).all(|label| {
+            if let Some((key, value)) = label.split_once("!=") {
+                pod_labels.get(key.trim()).map(String::as_str) != Some(value.trim())
+            } else if let Some((key, value)) = label.split_once('=') {
+                pod_labels.get(key.trim()).map(String::as_str) == Some(value.trim())
+            } else if let Some(key) = label.strip_prefix('!') {
+                !pod_labels.contains_key(key.trim())
+            } else if label.contains(|c: char| "() <>".contains(c)) {
+                panic!("the fake cannot select pods by the label {}", label)
+            } else {
+                pod_labels.contains_key(label)
+            }
+        })
+}
+
+fn requirements(selector: &str) -> impl Iterator<Item = &str> {
+    selector
+        .split(',')
+        .map(str::trim)
+        .filter(|requirement| !requirement.is_empty())
+}
+
+/// The pods (and the descriptions of their watch events) that the tests of this crate are built
+/// upon.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use crate::watcher::Event;
+    use k8s_openapi::api::core::v1::{Container, Pod, PodSpec, PodStatus};
+    use kube::ResourceExt;
+
+    /// A connector pod of the given name within the [ocf](crate::OCF_NAMESPACE) namespace, in the
+    /// given phase.
+    pub fn pod(name: &str, phase: &str) -> Pod {
+        let mut pod = Pod::default();
+        pod.metadata.name = Some(name.to_string());
+        pod.metadata.namespace = Some(crate::OCF_NAMESPACE.to_string());
+        pod.spec = Some(PodSpec {
+            containers: vec![Container {
+                name: "connector".to_string(),
+                image: Some("registry/ocf:oracle".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        pod.status = Some(PodStatus {
+            phase: Some(phase.to_string()),
+            ..Default::default()
+        });
+        pod
+    }
+
+    /// The given pod, bearing exactly the given labels.
+    pub fn labeled(mut pod: Pod, labels: &[(&str, &str)]) -> Pod {
+        pod.metadata.labels = Some(
+            labels
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        );
+        pod
+    }
+
+    /// Describes the given watch event by the name and phase of each of its pods, such as
+    /// `added a:Pending` or `restarted [a:Running,b:Failed]`.
+    pub fn describe<E>(event: &Result<Event<Pod>, E>) -> String {
+        let name = |pod: &Pod| {
+            let phase = pod.status.as_ref().and_then(|status| status.phase.as_ref());
+            match phase {
+                Some(phase) => format!("{}:{}", pod.name(), phase),
+                None => pod.name(),
+            }
+        };
+        match event {
+            Ok(Event::Added(pod)) => format!("added {}", name(pod)),
+            Ok(Event::Applied(pod)) => format!("applied {}", name(pod)),
+            Ok(Event::Deleted(pod)) => format!("deleted {}", name(pod)),
+            Ok(Event::Restarted(pods)) => format!(
+                "restarted [{}]",
+                pods.iter().map(name).collect::<Vec<String>>().join(",")
+            ),
+            Err(_) => "error".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{describe, labeled, pod};
+    use super::*;
+    use std::iter::FromIterator;
+
+    async fn drain(events: &mut PodEvents, n: usize) -> Vec<String> {
+        let mut described = vec![];
+        for _ in 0..n {
+            described.push(describe(&events.next().await.unwrap()));
+        }
+        described
+    }
+
+    #[test]
+    fn creates_conflicting_names() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            pods.create("ocf", &PostParams::default(), &pod("a", "Pending"))
+                .await
+                .unwrap();
+            match pods
+                .create("ocf", &PostParams::default(), &pod("a", "Pending"))
+                .await
+            {
+                Err(kube::Error::Api(ErrorResponse {
+                    code: 409, reason, ..
+                })) => {
+                    assert_eq!(reason, "AlreadyExists")
+                }
+                result => panic!("{:?}", result),
+            }
+            // The same name within another namespace is another pod altogether.
+            pods.create("tenant", &PostParams::default(), &pod("a", "Pending"))
+                .await
+                .unwrap();
+            let dry_run = PostParams {
+                dry_run: true,
+                ..Default::default()
+            };
+            pods.create("ocf", &dry_run, &pod("b", "Pending"))
+                .await
+                .unwrap();
+            assert!(pods.pod("ocf", "b").is_none());
+        })
+    }
+
+    #[test]
+    fn merge_patches() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            pods.insert(labeled(
+                pod("a", "Pending"),
+                &[("ttl", "60"), ("ocf.alation.com/pool", "abcd")],
+            ));
+            let patch = serde_json::json!({
+                "metadata": {"labels": {"ocf.alation.com/pool": null, "ttl": "120"}}
+            });
+            let patched = pods.patch("ocf", "a", &patch).await.unwrap();
+            assert_eq!(
+                patched.labels(),
+                &BTreeMap::from_iter([("ttl".to_string(), "120".to_string())])
+            );
+            assert!(matches!(
+                pods.patch("ocf", "b", &patch).await,
+                Err(kube::Error::Api(ErrorResponse { code: 404, .. }))
+            ));
+        })
+    }
+
+    #[test]
+    fn watches_selected_pods() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            pods.insert(labeled(pod("a", "Pending"), &[("connector", "a")]));
+            pods.insert(pod("unlabeled", "Pending"));
+            let mut connectors = pods.watch("ocf", ListParams::default().labels("connector"));
+            let mut named = pods.watch("ocf", ListParams::default().fields("metadata.name=b"));
+            pods.insert(labeled(pod("b", "Pending"), &[("connector", "b")]));
+            pods.update("ocf", "a", |_| ());
+            pods.delete("ocf", "b", &DeleteParams::default())
+                .await
+                .unwrap();
+            pods.insert(pod("c", "Pending"));
+            assert_eq!(
+                drain(&mut connectors, 4).await,
+                vec![
+                    "restarted [a:Pending]",
+                    "added b:Pending",
+                    "applied a:Pending",
+                    "deleted b:Pending"
+                ]
+            );
+            assert_eq!(
+                drain(&mut named, 3).await,
+                vec!["restarted []", "added b:Pending", "deleted b:Pending"]
+            );
+        })
+    }
+
+    #[test]
+    fn deletes() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            pods.insert(pod("a", "Pending"));
+            pods.set_logs("ocf", "a", "hello");
+            let mut logs = pods
+                .log_stream("ocf", "a", &LogParams::default())
+                .await
+                .unwrap();
+            assert_eq!(logs.next().await.unwrap().unwrap(), Bytes::from("hello"));
+            let dry_run = DeleteParams {
+                dry_run: true,
+                ..Default::default()
+            };
+            pods.delete("ocf", "a", &dry_run).await.unwrap();
+            assert!(pods.pod("ocf", "a").is_some());
+            pods.delete("ocf", "a", &DeleteParams::default())
+                .await
+                .unwrap();
+            assert!(pods.pods("ocf").is_empty());
+            assert!(matches!(
+                pods.delete("ocf", "a", &DeleteParams::default()).await,
+                Err(kube::Error::Api(ErrorResponse { code: 404, .. }))
+            ));
+            assert!(pods
+                .log_stream("ocf", "a", &LogParams::default())
+                .await
+                .is_err());
+        })
+    }
+}
@@ -0,0 +1,284 @@
+//! FaultyPods is a [PodApi](crate::api::PodApi) that injects faults into another, such that the
+//! error paths of whatever is built upon the PodApi may be exercised against a live cluster on
+//! demand. This module only exists with the `faults` feature, which MUST NOT be enabled in
+//! production builds.
+//!
+//! Which faults are injected is read anew for every request (and every watch event) from the
+//! shared [Faults](Faults), so faults may be switched on and off while the process runs.
+//!
+//! ```
+//! use k8s::api::PodApi;
+//! use k8s::faults::{Faults, FaultyPods};
+//! use k8s::fake::FakePods;
+//! use std::sync::{Arc, RwLock};
+//!
+//! tokio_test::block_on(async {
+//!     let faults = Arc::new(RwLock::new(Faults::default()));
+//!     let pods = FaultyPods::new(Arc::new(FakePods::default()), faults.clone());
+//!     faults.write().unwrap().api_errors = true;
+//!     assert!(pods.get("ocf", "oracle").await.is_err());
+//! })
+//! ```
+use crate::api::{LogStream, PodApi, PodEvents};
+use crate::watcher::{self, Event};
+use crate::PodExt;
+use async_trait::async_trait;
+use either::Either;
+use error::*;
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::{ContainerState, ContainerStateWaiting, ContainerStatus, Pod};
+use kube::api::{DeleteParams, ListParams, LogParams, PostParams};
+use kube::core::response::Status;
+use kube::error::ErrorResponse;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// How often an otherwise quiet watch checks whether it ought to fail or close.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The message of the waiting state of a container that containerd "failed" to pull.
+const CONTAINERD_ERROR: &str = "failed to pull and unpack image: failed to resolve reference: \
+containerd: injected fault";
+
+/// Faults are the faults that are currently being injected. Every fault is off by default.
+#[derive(Serialize, Deserialize, Kind, Debug, Clone, Default, Eq, PartialEq)]
+#[serde(default)]
+pub struct Faults {
+    /// Fails every request (and every watch, once per second) with a `503 ServiceUnavailable`,
+    /// as though the API server were down.
+    pub api_errors: bool,
+    /// Ends every watch, both those already open and those opened while this is set, as though
+    /// the API server closed the watch stream.
+    pub close_watches: bool,
+    /// Reports every container that is neither running nor terminated as having failed to pull
+    /// its image, as containerd does when it cannot reach the registry.
+    pub containerd_errors: bool,
+    /// Stalls every health check of a connector by this many seconds before it connects. The
+    /// PodApi itself pays this no mind, it is left to whoever checks the health of connectors.
+    pub health_check_delay: Option<u64>,
+}
+
+/// FaultyPods injects the shared [Faults](Faults) into the PodApi that it wraps.
+#[derive(Clone)]
+pub struct FaultyPods {
+    pods: Arc<dyn PodApi>,
+    faults: Arc<RwLock<Faults>>,
+}
+
+impl FaultyPods {
+    pub fn new(pods: Arc<dyn PodApi>, faults: Arc<RwLock<Faults>>) -> FaultyPods {
+        FaultyPods { pods, faults }
+    }
+
+    fn current(&self) -> Faults {
+        current(&self.faults)
+    }
+
+    /// Fails with a `503` should API errors currently be injected.
+    fn check(&self) -> kube::Result<()> {
+        if self.current().api_errors {
+            Err(unavailable())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[async_trait]
+impl PodApi for FaultyPods {
+    async fn get(&self, namespace: &str, name: &str) -> kube::Result<Pod> {
+        self.check()?;
+        self.pods.get(namespace, name).await
+    }
+
+    async fn create(&self, namespace: &str, params: &PostParams, pod: &Pod) -> kube::Result<Pod> {
+        self.check()?;
+        self.pods.create(namespace, params, pod).await
+    }
+
+    async fn delete(
+        &self,
+        namespace: &str,
+        name: &str,
+        params: &DeleteParams,
+    ) -> kube::Result<Either<Pod, Status>> {
+        self.check()?;
+        self.pods.delete(namespace, name, params).await
+    }
+
+    async fn patch(
+        &self,
+        namespace: &str,
+        name: &str,
+        patch: &serde_json::Value,
+    ) -> kube::Result<Pod> {
+        self.check()?;
+        self.pods.patch(namespace, name, patch).await
+    }
+
+    fn watch(&self, namespace: &str, params: ListParams) -> PodEvents {
+        let events = self.pods.watch(namespace, params);
+        let faults = self.faults.clone();
+        futures::stream::unfold(events, move |mut events| {
+            let faults = faults.clone();
+            async move {
+                loop {
+                    let injected = current(&faults);
+                    if injected.close_watches {
+                        return None;
+                    }
+                    if injected.api_errors {
+                        tokio::time::sleep(RECHECK_INTERVAL).await;
+                        let err = Err(unavailable()).context(watcher::WatchFailed);
+                        return Some((err, events));
+                    }
+                    match tokio::time::timeout(RECHECK_INTERVAL, events.next()).await {
+                        Ok(Some(event)) if current(&faults).containerd_errors => {
+                            return Some((event.map(fail_to_pull), events))
+                        }
+                        Ok(Some(event)) => return Some((event, events)),
+                        Ok(None) => return None,
+                        // Nothing happened, which is the time to check the faults anew.
+                        Err(_) => continue,
+                    }
+                }
+            }
+        })
+        .boxed()
+    }
+
+    async fn log_stream(
+        &self,
+        namespace: &str,
+        name: &str,
+        params: &LogParams,
+    ) -> kube::Result<LogStream> {
+        self.check()?;
+        self.pods.log_stream(namespace, name, params).await
+    }
+}
+
+fn current(faults: &RwLock<Faults>) -> Faults {
+    faults.read().unwrap().clone()
+}
+
+fn unavailable() -> kube::Error {
+    kube::Error::Api(ErrorResponse {
+        status: "Failure".to_string(),
+        message: "injected fault: the API server is unavailable".to_string(),
+        reason: "ServiceUnavailable".to_string(),
+        code: 503,
+    })
+}
+
+/// Rewrites every pod within the given event as though containerd failed to pull its image.
+fn fail_to_pull(event: Event<Pod>) -> Event<Pod> {
+    match event {
+        Event::Added(pod) => Event::Added(failed_to_pull(pod)),
+        Event::Applied(pod) => Event::Applied(failed_to_pull(pod)),
+        Event::Restarted(pods) => Event::Restarted(pods.into_iter().map(failed_to_pull).collect()),
+        deleted => deleted,
+    }
+}
+
+/// Rewrites the given pod such that every one of its containers is waiting on an `ErrImagePull`,
+/// unless the pod has already gotten as far as running (or terminating).
+fn failed_to_pull(mut pod: Pod) -> Pod {
+    if pod.running() || pod.terminated() {
+        return pod;
+    }
+    let containers = pod
+        .spec
+        .as_ref()
+        .map(|spec| spec.containers.clone())
+        .unwrap_or_default();
+    pod.status
+        .get_or_insert_with(Default::default)
+        .container_statuses = Some(
+        containers
+            .into_iter()
+            .map(|container| ContainerStatus {
+                name: container.name,
+                image: container.image.unwrap_or_default(),
+                state: Some(ContainerState {
+                    waiting: Some(ContainerStateWaiting {
+                        reason: Some("ErrImagePull".to_string()),
+                        message: Some(CONTAINERD_ERROR.to_string()),
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .collect(),
+    );
+    pod
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::fixtures::pod;
+    use crate::fake::FakePods;
+
+    fn faulty() -> (FakePods, Arc<RwLock<Faults>>, FaultyPods) {
+        let fake = FakePods::default();
+        let faults = Arc::new(RwLock::new(Faults::default()));
+        let pods = FaultyPods::new(Arc::new(fake.clone()), faults.clone());
+        (fake, faults, pods)
+    }
+
+    async fn next(events: &mut PodEvents) -> Option<watcher::Result<Event<Pod>>> {
+        events.next().await
+    }
+
+    #[test]
+    fn fails_requests() {
+        tokio_test::block_on(async {
+            let (fake, faults, pods) = faulty();
+            fake.insert(pod("oracle", "Pending"));
+            faults.write().unwrap().api_errors = true;
+            match pods.get("ocf", "oracle").await {
+                Err(kube::Error::Api(err)) => assert_eq!(err.code, 503),
+                result => panic!("expected a 503, got {:?}", result),
+            }
+            faults.write().unwrap().api_errors = false;
+            assert!(pods.get("ocf", "oracle").await.is_ok());
+        })
+    }
+
+    #[test]
+    fn fails_and_closes_watches() {
+        tokio_test::block_on(async {
+            let (fake, faults, pods) = faulty();
+            fake.insert(pod("oracle", "Pending"));
+            let mut events = pods.watch("ocf", ListParams::default());
+            assert!(matches!(
+                next(&mut events).await,
+                Some(Ok(Event::Restarted(_)))
+            ));
+            faults.write().unwrap().api_errors = true;
+            assert!(matches!(next(&mut events).await, Some(Err(_))));
+            faults.write().unwrap().close_watches = true;
+            assert!(next(&mut events).await.is_none());
+        })
+    }
+
+    #[test]
+    fn fails_to_pull_images() {
+        tokio_test::block_on(async {
+            let (fake, faults, pods) = faulty();
+            faults.write().unwrap().containerd_errors = true;
+            fake.insert(pod("oracle", "Pending"));
+            let mut events = pods.watch("ocf", ListParams::default());
+            match next(&mut events).await {
+                Some(Ok(Event::Restarted(pods))) => {
+                    assert!(pods[0].was_err_image_pull());
+                    assert!(pods[0].err_image_pull().is_err());
+                }
+                event => panic!("expected the pod to be listed, got {:?}", event),
+            }
+        })
+    }
+}
@@ -0,0 +1,178 @@
+use crate::dependents;
+use crate::options::GRPC_PORT_NAME;
+use crate::watcher;
+use crate::POD_LABEL;
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::{Endpoints, Pod, Service, ServicePort, ServiceSpec};
+use kube::api::{ListParams, ObjectMeta};
+use kube::{Api, ResourceExt};
+use result::Result;
+use std::collections::BTreeMap;
+use std::iter::FromIterator;
+
+/// The environment variable that, when `true`, has [deploy](crate::deploy) front every connector
+/// pod with a headless Service of the same name. This is `false` by default.
+///
+/// Pods are otherwise addressed via their `<ip-dashed>.<namespace>.pod` DNS entry, which is
+/// deprecated and which some CNIs do not serve at all.
+pub const CONNECTOR_SERVICES: &str = "CONNECTOR_SERVICES";
+
+/// The `.metadata.annotations` key under which a pod records the name of the headless Service
+/// that fronts it. Pods with this annotation are addressed via the Service's DNS entry.
+pub const SERVICE_ANNOTATION: &str = "ocf.alation.com/service";
+
+/// Returns whether [CONNECTOR_SERVICES](CONNECTOR_SERVICES) are enabled.
+///
+/// This function PANICS if the environment variable is set to neither `true` nor `false`.
+/// Consumers SHOULD call this function on program startup so that such a misconfiguration is
+/// caught early.
+pub fn enabled() -> bool {
+    match std::env::var(CONNECTOR_SERVICES) {
+        Ok(value) if !value.trim().is_empty() => {
+            value.trim().to_lowercase().parse().unwrap_or_else(|_| {
+                panic!(
+                    "the {} environment variable was set to '{}'. It can be one of either \
+                    true or false",
+                    CONNECTOR_SERVICES, value
+                )
+            })
+        }
+        _ => false,
+    }
+}
+
+/// Annotates the given (not yet created) pod such that it will be addressed via the headless
+/// Service that [create](create) makes for it.
+pub fn prepare(pod: &mut Pod) {
+    let name = pod.name();
+    pod.metadata
+        .annotations
+        .get_or_insert_with(BTreeMap::new)
+        .insert(SERVICE_ANNOTATION.to_string(), name);
+}
+
+/// Returns the headless Service that fronts the given pod. The Service shares the pod's name and
+/// namespace and publishes the pod's address before it is ready, as it is the ACM's own health
+/// check that decides when a connector is ready. Every port of the connector is published, its
+/// gRPC port (named `grpc`) first.
+pub fn service(pod: &Pod) -> Service {
+    let mut ports: Vec<ServicePort> = pod
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.containers.first())
+        .and_then(|container| container.ports.as_ref())
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(n, port)| ServicePort {
+            // The connector's gRPC port always comes first.
+            name: if n == 0 {
+                Some(GRPC_PORT_NAME.to_string())
+            } else {
+                port.name.clone()
+            },
+            port: port.container_port,
+            protocol: port.protocol.clone().or_else(|| Some("TCP".to_string())),
+            ..Default::default()
+        })
+        .collect();
+    if ports.is_empty() {
+        ports.push(ServicePort {
+            name: Some(GRPC_PORT_NAME.to_string()),
+            port: crate::sidecars::CONNECTOR_PORT,
+            protocol: Some("TCP".to_string()),
+            ..Default::default()
+        });
+    }
+    Service {
+        metadata: ObjectMeta {
+            name: Some(pod.name()),
+            namespace: pod.namespace(),
+            ..Default::default()
+        },
+        spec: Some(ServiceSpec {
+            cluster_ip: Some("None".to_string()),
+            selector: Some(BTreeMap::from_iter([(POD_LABEL.to_string(), pod.name())])),
+            publish_not_ready_addresses: Some(true),
+            ports: Some(ports),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Creates the headless Service for the given (created) pod as one of its [dependents](dependents),
+/// so that it is deleted alongside the pod.
+pub async fn create(pod: &Pod) -> Result<Service> {
+    dependents::create(pod, service(pod)).await
+}
+
+/// Returns the cluster DNS entry of the headless Service that fronts the given pod, if any.
+pub fn dns(pod: &Pod) -> Option<String> {
+    let service = pod.annotations().get(SERVICE_ANNOTATION)?.clone();
+    let namespace = pod
+        .namespace()
+        .unwrap_or_else(|| crate::OCF_NAMESPACE.to_string());
+    Some(format!("{}.{}.svc", service, namespace))
+}
+
+/// Blocks until the named Service (within the given namespace) has at least one address within its
+/// Endpoints, which is when its DNS entry begins to resolve. A Service whose pod never receives
+/// an address will cause this procedure to block forever, so callers SHOULD wrap it within a
+/// timeout.
+pub async fn wait_for_endpoints<S: AsRef<str>, N: AsRef<str>>(namespace: S, name: N) -> Result<()> {
+    let client: Api<Endpoints> = crate::client::new_with_namespace(namespace.as_ref()).await;
+    let mut events = Box::pin(watcher::watcher(
+        client,
+        ListParams::default().fields(&format!("metadata.name={}", name.as_ref())),
+    ));
+    while let Some(event) = events.next().await {
+        let ready = match event {
+            Ok(watcher::Event::Added(endpoints)) | Ok(watcher::Event::Applied(endpoints)) => {
+                has_addresses(&endpoints)
+            }
+            Ok(watcher::Event::Restarted(endpoints)) => endpoints.iter().any(has_addresses),
+            Ok(watcher::Event::Deleted(_)) => false,
+            Err(_) => {
+                // The watcher recovers on its own, we just need to not spin while it does.
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                false
+            }
+        };
+        if ready {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+fn has_addresses(endpoints: &Endpoints) -> bool {
+    endpoints.subsets.iter().flatten().any(|subset| {
+        subset.addresses.iter().flatten().next().is_some()
+            || subset.not_ready_addresses.iter().flatten().next().is_some()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::PodOptions;
+
+    #[test]
+    fn headless() {
+        let mut pod =
+            crate::pod::new("tenant", "oracle:1", "oracle", &PodOptions::default()).unwrap();
+        let name = pod.name();
+        assert_eq!(dns(&pod), None);
+        prepare(&mut pod);
+        assert_eq!(dns(&pod), Some(format!("{}.tenant.svc", name)));
+        let service = service(&pod);
+        let spec = service.spec.unwrap();
+        assert_eq!(service.metadata.name, Some(name.clone()));
+        assert_eq!(spec.cluster_ip.as_deref(), Some("None"));
+        assert_eq!(spec.selector.unwrap().get(POD_LABEL), Some(&name));
+        let ports = spec.ports.unwrap();
+        assert_eq!(ports[0].port, 8080);
+        assert_eq!(ports[0].name.as_deref(), Some(GRPC_PORT_NAME));
+    }
+}
@@ -0,0 +1,317 @@
+//! An Informer is a single [watch](crate::watcher) over every connector pod within a namespace
+//! whose events are demultiplexed to per-pod [subscriptions](Subscription).
+//!
+//! Watching each pod with its own field selected watch costs the API server one watch connection
+//! per pod, which adds up quickly once there are a great many connectors. An Informer costs one
+//! watch connection per namespace no matter how many pods are subscribed to.
+//!
+//! A Subscription sees the same events that a [watcher](crate::watcher::watcher) of just its pod
+//! would, with one exception. Should the shared watch fall out of the watch window and re-list the
+//! namespace then a [Restarted](Event::Restarted) event is NOT passed on to every subscriber,
+//! as that is not a restart of any one pod. Rather, each subscriber is told of its pod's current
+//! state (or of its deletion, should the pod no longer be listed) as though nothing happened.
+//!
+//! Should the shared watch ever end, then so does every Subscription (exactly as a watch of just
+//! the pod would have), and the namespace is watched anew on behalf of whoever subscribes next.
+use crate::api::PodApi;
+use crate::watcher::{self, Event};
+use futures::{Stream, StreamExt};
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::ListParams;
+use kube::ResourceExt;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+
+/// How long the shared watch waits before retrying after a failure from the API server.
+const RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// A failure of the shared watch, which is handed to every subscriber.
+pub type Error = Arc<watcher::Error>;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An Informer is the handle to the shared watch over the connector pods of a single namespace.
+/// Only pods carrying the [POD_LABEL](crate::POD_LABEL) (that is, those created by
+/// [deploy](crate::deploy)) are watched.
+#[derive(Clone)]
+pub struct Informer {
+    state: Arc<Mutex<State>>,
+}
+
+impl Informer {
+    /// Starts the shared watch over the given namespace by way of the given [PodApi](PodApi). The
+    /// watch runs for as long as the process does.
+    pub fn start<N: Into<String>>(pods: Arc<dyn PodApi>, namespace: N) -> Informer {
+        let namespace = namespace.into();
+        let state = Arc::new(Mutex::new(State::default()));
+        let informer = Informer {
+            state: state.clone(),
+        };
+        tokio::spawn(async move {
+            let params = ListParams::default().labels(crate::POD_LABEL);
+            loop {
+                let mut events = pods.watch(&namespace, params.clone());
+                while let Some(event) = events.next().await {
+                    let failed = event.is_err();
+                    state.lock().unwrap().dispatch(event);
+                    if failed {
+                        tokio::time::sleep(RETRY_DELAY).await;
+                    }
+                }
+                state.lock().unwrap().close();
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        });
+        informer
+    }
+
+    /// Subscribes to the events of the pod of the given name. Should the shared watch have already
+    /// listed the namespace then the subscription begins with a [Restarted](Event::Restarted) event
+    /// holding the pod (if it exists), exactly as a fresh watch of the pod would.
+    pub fn subscribe<N: Into<String>>(&self, name: N) -> Subscription {
+        let name = name.into();
+        let (id, events) = self.state.lock().unwrap().subscribe(&name);
+        Subscription {
+            state: self.state.clone(),
+            name,
+            id,
+            events,
+        }
+    }
+}
+
+/// A Subscription is the [Stream](Stream) of the events of a single pod. The subscription ends
+/// when it is dropped.
+pub struct Subscription {
+    state: Arc<Mutex<State>>,
+    name: String,
+    id: u64,
+    events: mpsc::UnboundedReceiver<Result<Event<Pod>>>,
+}
+
+impl Stream for Subscription {
+    type Item = Result<Event<Pod>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().events.poll_recv(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.state.lock().unwrap().unsubscribe(&self.name, self.id);
+    }
+}
+
+type Subscriber = mpsc::UnboundedSender<Result<Event<Pod>>>;
+
+/// The State of an [Informer](Informer) is the latest known state of every watched pod alongside
+/// the subscribers of each pod.
+#[derive(Default)]
+struct State {
+    /// Whether the namespace has been listed at least once.
+    listed: bool,
+    pods: HashMap<String, Pod>,
+    subscribers: HashMap<String, HashMap<u64, Subscriber>>,
+    next_id: u64,
+}
+
+impl State {
+    fn subscribe(&mut self, name: &str) -> (u64, mpsc::UnboundedReceiver<Result<Event<Pod>>>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        if self.listed {
+            let pod = self.pods.get(name).cloned().into_iter().collect();
+            let _ = sender.send(Ok(Event::Restarted(pod)));
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.subscribers
+            .entry(name.to_string())
+            .or_default()
+            .insert(id, sender);
+        (id, receiver)
+    }
+
+    fn unsubscribe(&mut self, name: &str, id: u64) {
+        if let Some(subscribers) = self.subscribers.get_mut(name) {
+            subscribers.remove(&id);
+            if subscribers.is_empty() {
+                self.subscribers.remove(name);
+            }
+        }
+    }
+
+    /// Ends every subscription and forgets every pod, as the shared watch has ended.
+    fn close(&mut self) {
+        self.subscribers.clear();
+        self.pods.clear();
+        self.listed = false;
+    }
+
+    /// Hands the given event of the shared watch to the subscribers of whichever pods it concerns.
+    fn dispatch(&mut self, event: watcher::Result<Event<Pod>>) {
+        let event = match event {
+            Ok(event) => event,
+            Err(err) => {
+                let err = Arc::new(err);
+                for subscribers in self.subscribers.values() {
+                    for subscriber in subscribers.values() {
+                        let _ = subscriber.send(Err(err.clone()));
+                    }
+                }
+                return;
+            }
+        };
+        match event {
+            Event::Added(pod) => {
+                self.pods.insert(pod.name(), pod.clone());
+                self.send(&pod.name(), Event::Added(pod));
+            }
+            Event::Applied(pod) => {
+                self.pods.insert(pod.name(), pod.clone());
+                self.send(&pod.name(), Event::Applied(pod));
+            }
+            Event::Deleted(pod) => {
+                self.pods.remove(&pod.name());
+                self.send(&pod.name(), Event::Deleted(pod));
+            }
+            Event::Restarted(pods) => {
+                let mut previous = std::mem::replace(
+                    &mut self.pods,
+                    pods.into_iter().map(|pod| (pod.name(), pod)).collect(),
+                );
+                let names: Vec<String> = self.subscribers.keys().cloned().collect();
+                for name in names {
+                    let current = self.pods.get(&name).cloned();
+                    let event = match (self.listed, current, previous.remove(&name)) {
+                        (false, current, _) => Event::Restarted(current.into_iter().collect()),
+                        (true, Some(current), _) => Event::Applied(current),
+                        (true, None, Some(previous)) => Event::Deleted(previous),
+                        (true, None, None) => continue,
+                    };
+                    self.send(&name, event);
+                }
+                self.listed = true;
+            }
+        }
+    }
+
+    /// Sends the given event to every subscriber of the pod of the given name.
+    fn send(&self, name: &str, event: Event<Pod>) {
+        if let Some(subscribers) = self.subscribers.get(name) {
+            for subscriber in subscribers.values() {
+                let _ = subscriber.send(Ok(event.clone()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::fixtures::{describe, labeled, pod};
+
+    fn drain(events: &mut mpsc::UnboundedReceiver<Result<Event<Pod>>>) -> Vec<String> {
+        std::iter::from_fn(|| events.try_recv().ok())
+            .map(|event| describe(&event))
+            .collect()
+    }
+
+    #[test]
+    fn routes_events_by_pod() {
+        let mut state = State::default();
+        let (_, mut a) = state.subscribe("a");
+        let (_, mut b) = state.subscribe("b");
+        state.dispatch(Ok(Event::Restarted(vec![pod("a", "Pending")])));
+        state.dispatch(Ok(Event::Applied(pod("a", "Running"))));
+        state.dispatch(Ok(Event::Added(pod("b", "Pending"))));
+        state.dispatch(Ok(Event::Applied(pod("c", "Running"))));
+        state.dispatch(Ok(Event::Deleted(pod("a", "Running"))));
+        assert_eq!(
+            drain(&mut a),
+            vec![
+                "restarted [a:Pending]",
+                "applied a:Running",
+                "deleted a:Running"
+            ]
+        );
+        assert_eq!(drain(&mut b), vec!["restarted []", "added b:Pending"]);
+    }
+
+    #[test]
+    fn closing_ends_every_subscription() {
+        let mut state = State::default();
+        let (_, mut a) = state.subscribe("a");
+        state.dispatch(Ok(Event::Restarted(vec![pod("a", "Pending")])));
+        state.close();
+        assert_eq!(drain(&mut a), vec!["restarted [a:Pending]"]);
+        assert_eq!(
+            a.try_recv().unwrap_err(),
+            mpsc::error::TryRecvError::Disconnected
+        );
+        // Nobody is told of the pod until the namespace has been listed anew.
+        let (_, mut b) = state.subscribe("a");
+        assert!(drain(&mut b).is_empty());
+        state.dispatch(Ok(Event::Restarted(vec![pod("a", "Running")])));
+        assert_eq!(drain(&mut b), vec!["restarted [a:Running]"]);
+    }
+
+    #[test]
+    fn late_subscribers_begin_with_the_pod() {
+        let mut state = State::default();
+        state.dispatch(Ok(Event::Restarted(vec![pod("a", "Pending")])));
+        state.dispatch(Ok(Event::Applied(pod("a", "Running"))));
+        let (_, mut a) = state.subscribe("a");
+        let (_, mut b) = state.subscribe("b");
+        assert_eq!(drain(&mut a), vec!["restarted [a:Running]"]);
+        assert_eq!(drain(&mut b), vec!["restarted []"]);
+    }
+
+    #[test]
+    fn relists_are_not_restarts() {
+        let mut state = State::default();
+        state.dispatch(Ok(Event::Restarted(vec![
+            pod("a", "Running"),
+            pod("b", "Running"),
+        ])));
+        let (_, mut a) = state.subscribe("a");
+        let (_, mut b) = state.subscribe("b");
+        let (_, mut c) = state.subscribe("c");
+        drain(&mut a);
+        drain(&mut b);
+        drain(&mut c);
+        state.dispatch(Ok(Event::Restarted(vec![pod("a", "Failed")])));
+        assert_eq!(drain(&mut a), vec!["applied a:Failed"]);
+        assert_eq!(drain(&mut b), vec!["deleted b:Running"]);
+        assert!(drain(&mut c).is_empty());
+    }
+
+    #[test]
+    fn informs_by_way_of_the_pod_api() {
+        tokio_test::block_on(async {
+            let pods = crate::fake::FakePods::default();
+            pods.insert(labeled(pod("a", "Pending"), &[(crate::POD_LABEL, "a")]));
+            let informer = Informer::start(Arc::new(pods.clone()), crate::OCF_NAMESPACE);
+            let mut a = informer.subscribe("a");
+            assert_eq!(describe(&a.next().await.unwrap()), "restarted [a:Pending]");
+            pods.update(crate::OCF_NAMESPACE, "a", |pod| {
+                pod.status.as_mut().unwrap().phase = Some("Running".to_string())
+            });
+            assert_eq!(describe(&a.next().await.unwrap()), "applied a:Running");
+        })
+    }
+
+    #[test]
+    fn unsubscribes() {
+        let mut state = State::default();
+        let (first, _a) = state.subscribe("a");
+        let (second, _b) = state.subscribe("a");
+        state.unsubscribe("a", first);
+        assert_eq!(state.subscribers["a"].len(), 1);
+        state.unsubscribe("a", second);
+        assert!(state.subscribers.is_empty());
+    }
+}
@@ -1,12 +1,40 @@
+pub mod api;
+pub mod audit;
 pub mod client;
+pub mod deletion;
+pub mod dependents;
+pub mod display;
 pub mod errors;
+pub mod events;
+pub mod exec;
+pub mod fake;
+#[cfg(feature = "faults")]
+pub mod faults;
+pub mod headless;
+pub mod informer;
+pub mod logsink;
+pub mod network_policy;
+pub mod node;
+pub mod options;
+pub mod permissions;
 pub mod pod;
+pub mod pull_secret;
+pub mod scheduling;
+pub mod security;
+pub mod selector;
+pub mod sidecars;
+pub mod usage;
 pub mod watcher;
 
+pub use api::PodApi;
+pub use options::PodOptions;
 pub use pod::PodExt;
 
+use deletion::{DeletionCause, DELETION_CAUSE_ANNOTATION};
 use either::Either;
-use kube::api::{DeleteParams, PostParams};
+use error::Kind;
+use futures::StreamExt;
+use kube::api::{DeleteParams, ListParams, Patch, PatchParams, PostParams};
 use kube::{Api, ResourceExt};
 use result::Result;
 
@@ -17,9 +45,28 @@ use kube::error::ErrorResponse;
 use std::collections::BTreeMap;
 use std::iter::FromIterator;
 
+/// The namespace that connector pods are deployed into unless another is requested.
 pub const OCF_NAMESPACE: &str = "ocf";
 pub const OCF_SYSTEM_NAMESPACE: &str = "ocf-system";
 
+/// The `.metadata.labels` key under which every pod created by [deploy](deploy) carries its own
+/// name, so that it may be selected by the Services and NetworkPolicies created on its behalf.
+pub const POD_LABEL: &str = "ocf.alation.com/pod";
+
+/// The `.metadata.labels` key under which every pod whose image reference is pinned to a digest
+/// (that is, `<registry>/<repository>@sha256:<hex>`) carries that digest, so that what is running
+/// may be selected for. Label values are limited to 63 characters (and may not contain a `:`), so
+/// the label is only the first 63 characters of the digest's hex, see [digest_label](digest_label).
+pub const DIGEST_LABEL: &str = "ocf.alation.com/digest";
+
+/// The `.metadata.annotations` key under which every pod whose image reference is pinned to a
+/// digest records that digest in full.
+pub const DIGEST_ANNOTATION: &str = "ocf.alation.com/digest";
+
+/// The number of names that [deploy](deploy) will try before giving up on a pod whose
+/// generated names keep colliding with existing pods.
+pub const MAX_NAME_ATTEMPTS: usize = 3;
+
 /// Returns the pod object from the Kubernetes API server that is mapped
 /// to the pod that actually executes this code. In this way, a caller with appropriate
 /// ACLs to the namespace that it itself is operating in may do a bit of reflection
@@ -31,14 +78,14 @@ pub const OCF_SYSTEM_NAMESPACE: &str = "ocf-system";
 ///
 /// ```ignore
 /// tokio_test::block_on(async {
-///     let myself = servicer().await.unwrap();
+///     let myself = servicer(&api::KubePods::new().await).await.unwrap();
 ///     assert_eq!(myself.metadata.name, tokio::fs::read_to_string("/etc/hostname").await.unwrap().trim());
 /// })
 /// ```
-async fn servicer() -> Result<Pod> {
-    let client: Api<Pod> = client::new_for_system().await;
-    Ok(client
+async fn servicer(pods: &dyn PodApi) -> Result<Pod> {
+    Ok(pods
         .get(
+            OCF_SYSTEM_NAMESPACE,
             tokio::fs::read_to_string("/etc/hostname")
                 .await
                 .expect("could not read /etc/hostname! This is extremely fatal!")
@@ -48,9 +95,17 @@ async fn servicer() -> Result<Pod> {
         .map_err(ApiError::from)?)
 }
 
-/// Deploys the given image reference to Kubernetes as a pod within the `ocf` namespace.
-/// The provided `name` will be sanitized through the [rfc1123_subdomain](names::rfc1123_subdomain)
-/// provided and then used as the `.metadata.name` of the newly created pod object.
+/// Deploys the given image reference to Kubernetes (by way of the given [PodApi](PodApi)) as a pod
+/// within the given namespace (which is [OCF_NAMESPACE](OCF_NAMESPACE) unless a tenant has been
+/// given a namespace of its own).
+/// The provided `name` is used as the prefix of a name generated by
+/// [rfc1123_subdomain_unique](rfc1123_subdomain_unique), which becomes the `.metadata.name` of the
+/// newly created pod object.
+///
+/// The given [PodOptions](PodOptions) (environment variables, etc.) are validated and rendered into
+/// the pod's spec by [pod::named](pod::named). Any PersistentVolumeClaims that the options mount are
+/// [checked](PodOptions::check_claims) to exist before the pod is created, as a pod that mounts a
+/// missing claim would otherwise sit in the `Pending` phase forever.
 ///
 /// The provided `ttl` is attached as additional metadata to the pod, but is otherwise not enacted
 /// upon within this procedure.
@@ -63,36 +118,249 @@ async fn servicer() -> Result<Pod> {
 /// * `servicer_dns`: This is cluster DNS entry of the pod that created this new pod.
 /// * `servicer_port`: This is listening port of the pod that created this new pod.
 /// * `ttl`: The `ttl` passed into this function.
-pub async fn deploy<R: AsRef<str>, N: AsRef<str>>(reference: R, name: N, ttl: u64) -> Result<Pod> {
-    let mut pod = pod::new(reference, name)?;
-    let myself = servicer().await?;
-    pod.metadata.labels = Some(BTreeMap::from_iter([
-        ("servicer".to_string(), myself.name()),
-        ("servicer_dns".to_string(), myself.dns()?),
-        ("servicer_port".to_string(), format!("{}", myself.port()?)),
-        ("ttl".to_string(), format!("{}", ttl)),
-    ]));
-    let client: Api<Pod> = client::new().await;
-    Ok(client
-        .create(&PostParams::default(), &pod)
-        .await
-        .map_err(ApiError::from)?)
+/// * [DIGEST_LABEL](DIGEST_LABEL): Should the reference be pinned to a digest, the
+///   [truncated](digest_label) digest (which is also recorded in full under the
+///   [DIGEST_ANNOTATION](DIGEST_ANNOTATION)).
+///
+/// The provided `name` (as given, before it is sanitized) and the tag of the reference (if any)
+/// are recorded as the pod's [DisplayName](display::DisplayName) under the
+/// [DISPLAY_NAME_ANNOTATION](display::DISPLAY_NAME_ANNOTATION) and
+/// [TAG_ANNOTATION](display::TAG_ANNOTATION).
+///
+/// Should the generated name collide with a pod that already exists, then one of two things
+/// happens depending on `resuffix`. If `true`, then names that are already taken are skipped
+/// before the pod is ever submitted, and should another pod take the name in the meantime then the
+/// name is regenerated and the creation is attempted again, up to
+/// [MAX_NAME_ATTEMPTS](MAX_NAME_ATTEMPTS) times. If `false`, then a
+/// [NameConflict](errors::NameConflict) describing the existing pod is returned.
+///
+/// Should [CONNECTOR_SERVICES](headless::CONNECTOR_SERVICES) be enabled, then the pod is also
+/// fronted by a [headless](headless::service) Service of the same name, whose DNS entry becomes
+/// the pod's [address](PodExt::address). The Service is owned by the pod, and so is deleted along
+/// with it.
+///
+/// Should [NETWORK_POLICIES](network_policy::NETWORK_POLICIES) be enabled, then the pod is also
+/// [isolated](network_policy::Isolation::policy) by a NetworkPolicy of the same name, which is
+/// likewise owned by the pod.
+///
+/// Should either the Service or the NetworkPolicy fail to be created, then the pod is deleted and
+/// the error returned.
+///
+/// Should `dry_run` be set, then the pod is submitted as a server-side dry-run. That is, the API
+/// server validates and admits the pod exactly as it would otherwise (running every admission
+/// webhook along the way) without persisting anything, and the would-be pod is returned. Neither
+/// its Service nor its NetworkPolicy are created.
+#[allow(clippy::too_many_arguments)]
+pub async fn deploy<S: AsRef<str>, R: AsRef<str>, N: AsRef<str>>(
+    pods: &dyn PodApi,
+    namespace: S,
+    reference: R,
+    name: N,
+    ttl: u64,
+    resuffix: bool,
+    options: &PodOptions,
+    dry_run: bool,
+) -> Result<Pod> {
+    options.validate()?;
+    options.check_claims(namespace.as_ref()).await?;
+    let myself = servicer(pods).await?;
+    let services = headless::enabled();
+    let policies = network_policy::enabled();
+    let params = PostParams {
+        dry_run,
+        ..Default::default()
+    };
+    let display =
+        display::DisplayName::new(name.as_ref(), display::reference_tag(reference.as_ref()));
+    let render = |name: &str| -> Result<Pod> {
+        let mut pod = pod::named(namespace.as_ref(), reference.as_ref(), name, options)?;
+        pod.metadata.labels = Some(BTreeMap::from_iter([
+            ("servicer".to_string(), myself.name()),
+            ("servicer_dns".to_string(), myself.dns()?),
+            ("servicer_port".to_string(), format!("{}", myself.port()?)),
+            ("ttl".to_string(), format!("{}", ttl)),
+            (POD_LABEL.to_string(), pod.name()),
+        ]));
+        display.annotate(&mut pod);
+        if let Some(digest) = pinned_digest(reference.as_ref()) {
+            pod.metadata
+                .labels
+                .get_or_insert_with(BTreeMap::new)
+                .insert(DIGEST_LABEL.to_string(), digest_label(digest));
+            pod.metadata
+                .annotations
+                .get_or_insert_with(BTreeMap::new)
+                .insert(DIGEST_ANNOTATION.to_string(), digest.to_string());
+        }
+        if services {
+            headless::prepare(&mut pod);
+        }
+        Ok(pod)
+    };
+    let pod = create(
+        pods,
+        namespace.as_ref(),
+        name.as_ref(),
+        &params,
+        resuffix,
+        render,
+    )
+    .await?;
+    if dry_run {
+        return Ok(pod);
+    }
+    if let Err(err) = provision(&pod, &myself, services, policies).await {
+        let cause = DeletionCause::IllBehaved { kind: err.kind() };
+        // The provisioning error is the more useful of the two to report.
+        let _ = delete(pods, namespace.as_ref(), pod.name(), cause).await;
+        return Err(err);
+    }
+    Ok(pod)
+}
+
+/// Returns the digest (such as `sha256:cb1f...`) that the given image reference is pinned to,
+/// should it be of the form `<registry>/<repository>@<digest>`.
+pub fn pinned_digest(reference: &str) -> Option<&str> {
+    reference
+        .rsplit_once('@')
+        .map(|(_, digest)| digest)
+        .filter(|digest| digest.contains(':'))
 }
 
-/// Delete a named resource
+/// Returns the value of the [DIGEST_LABEL](DIGEST_LABEL) for the given digest, that is, (at most)
+/// the first 63 characters of its hex. 252 bits of a SHA-256 are every bit as unique as its 256.
+pub fn digest_label(digest: &str) -> String {
+    let hex = digest.split_once(':').map_or(digest, |(_, hex)| hex);
+    hex.chars().take(63).collect()
+}
+
+/// Returns a name generated from the given prefix by [rfc1123_subdomain](names::rfc1123_subdomain)
+/// that no pod within the given namespace has yet. A generated name that is already taken is
+/// generated anew, up to [MAX_NAME_ATTEMPTS](MAX_NAME_ATTEMPTS) times, after which a
+/// [NameConflict](errors::NameConflict) describing the last pod collided with is returned.
+///
+/// Note that nothing stops another pod from taking the name between this check and the creation
+/// of the pod, which [deploy](deploy) deals with by generating a name anew.
+pub async fn rfc1123_subdomain_unique<P: AsRef<str>>(
+    prefix: P,
+    pods: &dyn PodApi,
+    namespace: &str,
+) -> Result<String> {
+    let mut attempts = 1;
+    loop {
+        let name = names::rfc1123_subdomain(prefix.as_ref());
+        let existing = match pods.get(namespace, &name).await {
+            Ok(existing) => existing,
+            Err(kube::error::Error::Api(ErrorResponse { code: 404, .. })) => return Ok(name),
+            Err(err) => return Err(ApiError::from(err).into()),
+        };
+        if attempts >= MAX_NAME_ATTEMPTS {
+            return Err(errors::NameConflict::new(name, Some(&existing)).into());
+        }
+        attempts += 1;
+    }
+}
+
+/// Creates the pod that `render` renders under a name generated from the given prefix, rendering it
+/// anew (under a freshly generated name) should the name collide with an existing pod and
+/// `resuffix` be set. See [deploy](deploy).
+async fn create<F: FnMut(&str) -> Result<Pod>>(
+    pods: &dyn PodApi,
+    namespace: &str,
+    prefix: &str,
+    params: &PostParams,
+    resuffix: bool,
+    mut render: F,
+) -> Result<Pod> {
+    let mut attempts = 1;
+    loop {
+        let name = if resuffix {
+            rfc1123_subdomain_unique(prefix, pods, namespace).await?
+        } else {
+            names::rfc1123_subdomain(prefix)
+        };
+        let pod = render(&name)?;
+        match pods.create(namespace, params, &pod).await {
+            Ok(pod) => return Ok(pod),
+            // Another pod took the name since it was checked.
+            Err(kube::error::Error::Api(ErrorResponse { ref reason, .. }))
+                if reason == "AlreadyExists" && resuffix && attempts < MAX_NAME_ATTEMPTS =>
+            {
+                attempts += 1
+            }
+            Err(kube::error::Error::Api(ErrorResponse { ref reason, .. }))
+                if reason == "AlreadyExists" =>
+            {
+                let existing = match pods.get(namespace, &pod.name()).await {
+                    Ok(existing) => Some(existing),
+                    // The existing pod went away in the meantime, there is nothing more to say about it.
+                    Err(kube::error::Error::Api(ErrorResponse { code: 404, .. })) => None,
+                    Err(err) => return Err(ApiError::from(err).into()),
+                };
+                return Err(errors::NameConflict::new(pod.name(), existing.as_ref()).into());
+            }
+            Err(err) => return Err(ApiError::from(err).into()),
+        }
+    }
+}
+
+/// Creates the dependents of a freshly deployed pod.
+async fn provision(pod: &Pod, servicer: &Pod, services: bool, policies: bool) -> Result<()> {
+    if services {
+        headless::create(pod).await?;
+    }
+    if policies {
+        network_policy::create(pod, servicer).await?;
+    }
+    Ok(())
+}
+
+/// Delete the named pod within the given namespace (by way of the given [PodApi](PodApi)).
 /// When you get a K via Left, your delete has started. When you get a Status via
 /// Right, this should be a a 2XX style confirmation that the object being gone.
 ///
+/// Before the deletion is submitted, the pod is annotated with the given [DeletionCause](DeletionCause)
+/// under [DELETION_CAUSE_ANNOTATION](DELETION_CAUSE_ANNOTATION) so that anyone observing the
+/// subsequent deletion event can tell why the pod went away. If the pod is already being
+/// deleted then the original cause is left in place.
+///
+/// The deletion is submitted with the configured [Propagation](dependents::Propagation) so that
+/// every dependent owned by the pod goes along with it. Any dependent that the pod could not own
+/// is [cleaned up](dependents::cleanup) explicitly once the deletion has been submitted.
+///
 /// 4XX and 5XX status types are returned as an Err(Box<dyn AcmError>).
-pub async fn delete<I: AsRef<str>>(id: I) -> Result<Either<Pod, Status>> {
-    let client = client::new().await;
-    Ok(client
+pub async fn delete<S: AsRef<str>, I: AsRef<str>>(
+    pods: &dyn PodApi,
+    namespace: S,
+    id: I,
+    cause: DeletionCause,
+) -> Result<Either<Pod, Status>> {
+    let namespace = namespace.as_ref();
+    let existing = match pods.get(namespace, id.as_ref()).await {
+        Ok(pod) if pod.metadata.deletion_timestamp.is_none() => {
+            let patch = serde_json::json!({
+                "metadata": {"annotations": {DELETION_CAUSE_ANNOTATION: cause.to_string()}}
+            });
+            match pods.patch(namespace, id.as_ref(), &patch).await {
+                Ok(_) | Err(kube::error::Error::Api(ErrorResponse { code: 404, .. })) => (),
+                Err(err) => return Err(ApiError::from(err).into()),
+            }
+            Some(pod)
+        }
+        // Either the deletion is already underway or the pod is gone entirely, in both cases
+        // the delete below is still idempotent.
+        Ok(pod) => Some(pod),
+        Err(kube::error::Error::Api(ErrorResponse { code: 404, .. })) => None,
+        Err(err) => return Err(ApiError::from(err).into()),
+    };
+    let deleted = pods
         .delete(
+            namespace,
             id.as_ref(),
             &DeleteParams {
                 dry_run: false,
                 grace_period_seconds: Some(60), // We return immediately, but the connector is given 60 seconds to shutdown cleanly.
-                propagation_policy: None,
+                propagation_policy: Some(dependents::Propagation::configured().policy()),
                 preconditions: None,
             },
         )
@@ -109,5 +377,222 @@ pub async fn delete<I: AsRef<str>>(id: I) -> Result<Either<Pod, Status>> {
             }
             err => Err(err),
         })
-        .map_err(ApiError::from)?)
+        .map_err(ApiError::from)?;
+    if let Some(pod) = existing {
+        dependents::cleanup(&pod).await?;
+    }
+    Ok(deleted)
+}
+
+/// Submits the deletion of the named pod within the given namespace as a server-side dry-run,
+/// returning the pod that would be deleted (or `None` should there be no such pod). Nothing is
+/// annotated, deleted, or cleaned up.
+pub async fn delete_dry_run<S: AsRef<str>, I: AsRef<str>>(
+    namespace: S,
+    id: I,
+) -> Result<Option<Pod>> {
+    let client: Api<Pod> = client::new_with_namespace(namespace.as_ref()).await;
+    let params = DeleteParams {
+        dry_run: true,
+        grace_period_seconds: Some(60),
+        propagation_policy: Some(dependents::Propagation::configured().policy()),
+        preconditions: None,
+    };
+    match client.delete(id.as_ref(), &params).await {
+        Ok(Either::Left(pod)) => Ok(Some(pod)),
+        Ok(Either::Right(_)) | Err(kube::error::Error::Api(ErrorResponse { code: 404, .. })) => {
+            Ok(None)
+        }
+        Err(err) => Err(ApiError::from(err).into()),
+    }
+}
+
+/// Blocks until the pod of the given ID (within the given namespace) is entirely gone from the API server. That is, until the
+/// pod's deletion has been observed or the pod is simply not found. A pod that is never deleted
+/// will cause this procedure to block forever, so callers SHOULD wrap it within a timeout.
+///
+/// A [delete](delete) returns as soon as the deletion has been submitted, however the pod object
+/// lingers for up to its grace period while the connector shuts down. Callers that wish to reuse
+/// the pod's name (or simply want to know that it is gone) should wait on this procedure.
+pub async fn wait_for_deletion<S: AsRef<str>, I: AsRef<str>>(namespace: S, id: I) -> Result<()> {
+    let client: Api<Pod> = client::new_with_namespace(namespace.as_ref()).await;
+    let mut events = Box::pin(watcher::watcher(
+        client,
+        ListParams::default().fields(&format!("metadata.name={}", id.as_ref())),
+    ));
+    while let Some(event) = events.next().await {
+        match event {
+            Ok(watcher::Event::Deleted(_)) => return Ok(()),
+            // The (re)list that (re)starts a watch is the current state of the world, so an
+            // empty list means that the pod is already gone.
+            Ok(watcher::Event::Restarted(pods)) if pods.is_empty() => return Ok(()),
+            Ok(_) => (),
+            // The watcher recovers on its own, we just need to not spin while it does.
+            Err(_) => tokio::time::sleep(std::time::Duration::from_secs(1)).await,
+        }
+    }
+    Ok(())
+}
+
+/// Merges the given annotations into those of the pod of the given ID (within the given namespace).
+/// A pod that is already gone is not an error, as annotations are only ever informational.
+pub async fn annotate<S: AsRef<str>, I: AsRef<str>>(
+    namespace: S,
+    id: I,
+    annotations: BTreeMap<String, String>,
+) -> Result<()> {
+    let client: Api<Pod> = client::new_with_namespace(namespace.as_ref()).await;
+    let mut patch = Pod::default();
+    patch.metadata.annotations = Some(annotations);
+    match client
+        .patch(id.as_ref(), &PatchParams::default(), &Patch::Merge(patch))
+        .await
+    {
+        Ok(_) | Err(kube::error::Error::Api(ErrorResponse { code: 404, .. })) => Ok(()),
+        Err(err) => Err(ApiError::from(err).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::FakePods;
+
+    fn named(name: &str) -> Pod {
+        let mut pod = Pod::default();
+        pod.metadata.name = Some(name.to_string());
+        pod
+    }
+
+    #[test]
+    fn resuffixes_conflicting_names() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            pods.insert(named("oracle-a"));
+            pods.insert(named("oracle-b"));
+            let mut names = vec!["oracle-a", "oracle-b", "oracle-c"].into_iter();
+            let render = |_: &str| Ok(named(names.next().unwrap()));
+            let pod = create(
+                &pods,
+                OCF_NAMESPACE,
+                "oracle",
+                &PostParams::default(),
+                true,
+                render,
+            )
+            .await
+            .unwrap();
+            assert_eq!(pod.name(), "oracle-c");
+            // Every attempt collides, so the name is given up on.
+            let render = |_: &str| Ok(named("oracle-a"));
+            let err = create(
+                &pods,
+                OCF_NAMESPACE,
+                "oracle",
+                &PostParams::default(),
+                true,
+                render,
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(err.error_code(), Some("K8S-1002"));
+        })
+    }
+
+    #[test]
+    fn generates_unique_names() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            names::seed(7);
+            let taken = names::rfc1123_subdomain("oracle");
+            pods.insert(named(&taken));
+            // The same seed generates the taken name first, which is skipped.
+            names::seed(7);
+            let name = rfc1123_subdomain_unique("oracle", &pods, OCF_NAMESPACE)
+                .await
+                .unwrap();
+            assert!(name.starts_with("oracle-"));
+            assert_ne!(name, taken);
+            // Every attempt collides, so the name is given up on.
+            let mut taken = vec![];
+            names::seed(11);
+            for _ in 0..MAX_NAME_ATTEMPTS {
+                taken.push(names::rfc1123_subdomain("oracle"));
+            }
+            for name in &taken {
+                pods.insert(named(name));
+            }
+            names::seed(11);
+            let err = rfc1123_subdomain_unique("oracle", &pods, OCF_NAMESPACE)
+                .await
+                .unwrap_err();
+            assert_eq!(err.error_code(), Some("K8S-1002"));
+        })
+    }
+
+    #[test]
+    fn reports_conflicting_names() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            pods.insert(named("oracle-a"));
+            let mut renders = 0;
+            let render = |_: &str| {
+                renders += 1;
+                Ok(named("oracle-a"))
+            };
+            let err = create(
+                &pods,
+                OCF_NAMESPACE,
+                "oracle",
+                &PostParams::default(),
+                false,
+                render,
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(err.error_code(), Some("K8S-1002"));
+            assert_eq!(renders, 1);
+        })
+    }
+
+    #[test]
+    fn deletes_with_cause() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            let mut watch = pods.watch(OCF_NAMESPACE, ListParams::default());
+            pods.insert(named("oracle"));
+            let deleted = delete(&pods, OCF_NAMESPACE, "oracle", DeletionCause::TtlExpired)
+                .await
+                .unwrap();
+            assert!(deleted.is_left());
+            assert!(pods.pod(OCF_NAMESPACE, "oracle").is_none());
+            // The cause is annotated before the deletion, so the deletion event carries it.
+            let mut last = None;
+            for _ in 0..4 {
+                last = watch.next().await;
+            }
+            match last.unwrap().unwrap() {
+                watcher::Event::Deleted(pod) => {
+                    assert_eq!(pod.deletion_cause(), Some(DeletionCause::TtlExpired))
+                }
+                _ => panic!("the last event was not the deletion"),
+            }
+            // Deleting a pod that is already gone succeeds.
+            let deleted = delete(&pods, OCF_NAMESPACE, "oracle", DeletionCause::User)
+                .await
+                .unwrap();
+            assert!(deleted.is_right());
+        })
+    }
+
+    #[test]
+    fn pins_digests() {
+        let digest = "sha256:cb1ff0854b8864a6a68ee0b5e509d4d94c50a41f96dc2749ea71dc124c89d11f";
+        let reference = format!("registry.kube-system/ocf@{}", digest);
+        assert_eq!(pinned_digest(&reference), Some(digest));
+        assert_eq!(pinned_digest("registry.kube-system/ocf:abcd1234"), None);
+        let label = digest_label(digest);
+        assert_eq!(label.len(), 63);
+        assert!(digest.ends_with(&format!("{}f", label)));
+    }
 }
@@ -0,0 +1,230 @@
+use async_trait::async_trait;
+use error::*;
+use result::Result;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+/// A LogSink is somewhere that the logs of a resource may be written to, keyed by the ID of the
+/// job that the resource ran (such as `<namespace>/<name>` of a connector pod).
+///
+/// Writing to the same key again replaces whatever was written there before.
+#[async_trait]
+pub trait LogSink: Send + Sync {
+    /// Opens the given key for writing. Nothing written is guaranteed to be kept until the
+    /// returned [LogWriter](LogWriter) is [closed](LogWriter::close).
+    async fn open(&self, key: &str) -> Result<Box<dyn LogWriter>>;
+
+    /// Returns the logs written under the given key, or `None` should there be none.
+    async fn read(&self, key: &str) -> Result<Option<Vec<u8>>>;
+}
+
+/// A LogWriter is a single, open, key of a [LogSink](LogSink).
+#[async_trait]
+pub trait LogWriter: Send {
+    async fn write(&mut self, chunk: &[u8]) -> Result<()>;
+
+    /// Flushes everything written thus far into the sink.
+    async fn close(self: Box<Self>) -> Result<()>;
+}
+
+/// A FileSink writes the logs of every key into the file `<key>.log` beneath a root directory
+/// (which may well be a mounted PersistentVolumeClaim).
+#[derive(Clone, Debug)]
+pub struct FileSink {
+    root: PathBuf,
+}
+
+impl FileSink {
+    pub fn new<P: AsRef<Path>>(root: P) -> FileSink {
+        FileSink {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the file that the logs of the given key are written into.
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.root.join(format!("{}.log", key))
+    }
+}
+
+#[async_trait]
+impl LogSink for FileSink {
+    async fn open(&self, key: &str) -> Result<Box<dyn LogWriter>> {
+        let path = self.path(key);
+        let failed = |err: std::io::Error| LogSinkFailed::new(key, err);
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await.map_err(failed)?;
+        }
+        let file = tokio::fs::File::create(&path).await.map_err(failed)?;
+        Ok(Box::new(FileWriter {
+            key: key.to_string(),
+            file: BufWriter::new(file),
+        }))
+    }
+
+    async fn read(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path(key)).await {
+            Ok(logs) => Ok(Some(logs)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(LogSinkFailed::new(key, err).into()),
+        }
+    }
+}
+
+struct FileWriter {
+    key: String,
+    file: BufWriter<tokio::fs::File>,
+}
+
+#[async_trait]
+impl LogWriter for FileWriter {
+    async fn write(&mut self, chunk: &[u8]) -> Result<()> {
+        self.file
+            .write_all(chunk)
+            .await
+            .map_err(|err| LogSinkFailed::new(&self.key, err).into())
+    }
+
+    async fn close(mut self: Box<Self>) -> Result<()> {
+        self.file
+            .flush()
+            .await
+            .map_err(|err| LogSinkFailed::new(&self.key, err).into())
+    }
+}
+
+/// A RingBufferSink keeps the last `bytes` bytes of the logs of each of the last `keys` keys
+/// in memory. Nothing survives a restart, so this is only suited to troubleshooting.
+#[derive(Clone, Debug)]
+pub struct RingBufferSink {
+    bytes: usize,
+    keys: usize,
+    buffers: Arc<Mutex<Buffers>>,
+}
+
+#[derive(Debug, Default)]
+struct Buffers {
+    logs: HashMap<String, VecDeque<u8>>,
+    order: VecDeque<String>,
+}
+
+impl RingBufferSink {
+    pub fn new(bytes: usize, keys: usize) -> RingBufferSink {
+        RingBufferSink {
+            bytes,
+            keys,
+            buffers: Arc::new(Mutex::new(Buffers::default())),
+        }
+    }
+}
+
+#[async_trait]
+impl LogSink for RingBufferSink {
+    async fn open(&self, key: &str) -> Result<Box<dyn LogWriter>> {
+        let mut buffers = self.buffers.lock().unwrap();
+        buffers.order.retain(|existing| existing != key);
+        if buffers.order.len() >= self.keys {
+            if let Some(oldest) = buffers.order.pop_front() {
+                buffers.logs.remove(&oldest);
+            }
+        }
+        buffers.order.push_back(key.to_string());
+        buffers.logs.insert(key.to_string(), VecDeque::new());
+        Ok(Box::new(RingBufferWriter {
+            key: key.to_string(),
+            sink: self.clone(),
+        }))
+    }
+
+    async fn read(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let buffers = self.buffers.lock().unwrap();
+        Ok(buffers
+            .logs
+            .get(key)
+            .map(|logs| logs.iter().copied().collect()))
+    }
+}
+
+struct RingBufferWriter {
+    key: String,
+    sink: RingBufferSink,
+}
+
+#[async_trait]
+impl LogWriter for RingBufferWriter {
+    async fn write(&mut self, chunk: &[u8]) -> Result<()> {
+        let mut buffers = self.sink.buffers.lock().unwrap();
+        // The key may have since been evicted by newer keys, in which case the write is dropped.
+        if let Some(logs) = buffers.logs.get_mut(&self.key) {
+            logs.extend(chunk);
+            if logs.len() > self.sink.bytes {
+                let excess = logs.len() - self.sink.bytes;
+                logs.drain(..excess);
+            }
+        }
+        Ok(())
+    }
+
+    async fn close(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error("Failed to write the logs of {key} into their log sink, {cause}.")]
+#[error_code("K8S-1218")]
+pub struct LogSinkFailed {
+    key: String,
+    cause: String,
+}
+
+impl LogSinkFailed {
+    pub fn new<E: ToString>(key: &str, cause: E) -> LogSinkFailed {
+        LogSinkFailed {
+            key: key.to_string(),
+            cause: cause.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_buffer() {
+        tokio_test::block_on(async {
+            let sink = RingBufferSink::new(12, 2);
+            let mut writer = sink.open("ocf/first").await.unwrap();
+            writer.write(b"first line\n").await.unwrap();
+            writer.write(b"second line\n").await.unwrap();
+            writer.close().await.unwrap();
+            assert_eq!(
+                sink.read("ocf/first").await.unwrap(),
+                Some(b"second line\n".to_vec())
+            );
+            sink.open("ocf/second").await.unwrap();
+            sink.open("ocf/third").await.unwrap();
+            assert_eq!(sink.read("ocf/first").await.unwrap(), None);
+            assert_eq!(sink.read("ocf/third").await.unwrap(), Some(vec![]));
+        })
+    }
+
+    #[test]
+    fn reopening_replaces() {
+        tokio_test::block_on(async {
+            let sink = RingBufferSink::new(64, 2);
+            let mut writer = sink.open("ocf/first").await.unwrap();
+            writer.write(b"before").await.unwrap();
+            let mut writer = sink.open("ocf/first").await.unwrap();
+            writer.write(b"after").await.unwrap();
+            assert_eq!(
+                sink.read("ocf/first").await.unwrap(),
+                Some(b"after".to_vec())
+            );
+        })
+    }
+}
@@ -0,0 +1,300 @@
+use crate::dependents;
+use crate::sidecars::CONNECTOR_PORT;
+use crate::POD_LABEL;
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::api::networking::v1::{
+    IPBlock, NetworkPolicy, NetworkPolicyEgressRule, NetworkPolicyIngressRule, NetworkPolicyPeer,
+    NetworkPolicyPort, NetworkPolicySpec,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::api::ObjectMeta;
+use kube::ResourceExt;
+use result::Result;
+use std::collections::BTreeMap;
+use std::iter::FromIterator;
+
+/// The environment variable that, when `true`, has [deploy](crate::deploy) isolate every connector
+/// pod with a NetworkPolicy of the same name. This is `false` by default.
+pub const NETWORK_POLICIES: &str = "NETWORK_POLICIES";
+
+/// The environment variable that configures the labels (a comma separated list of `<key>=<value>`)
+/// of the Alation pods, in any namespace, that may connect to connectors.
+pub const ALATION_SELECTOR: &str = "ALATION_SELECTOR";
+
+/// The environment variable that configures where connectors may connect to, given as a comma
+/// separated list of `<cidr>` or `<cidr>:<port>` destinations. For example,
+/// `10.0.0.0/8:5432,0.0.0.0/0:443`. Cluster DNS is always reachable.
+pub const EGRESS: &str = "EGRESS";
+
+/// Labels of the servicing ACM that are NOT used to select it, as they change from one
+/// rollout of the ACM to the next.
+pub const UNSTABLE_LABELS: &[&str] = &["pod-template-hash"];
+
+/// The label that Kubernetes sets upon every namespace with the name of the namespace.
+pub const NAMESPACE_NAME_LABEL: &str = "kubernetes.io/metadata.name";
+
+/// A destination that connectors may connect to. No port means every port.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Egress {
+    pub cidr: String,
+    pub port: Option<i32>,
+}
+
+/// The configured isolation of connector pods.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Isolation {
+    pub alation: BTreeMap<String, String>,
+    pub egress: Vec<Egress>,
+}
+
+/// Returns whether [NETWORK_POLICIES](NETWORK_POLICIES) are enabled.
+///
+/// This function PANICS if the environment variable is set to neither `true` nor `false`.
+/// Consumers SHOULD call this function on program startup so that such a misconfiguration is
+/// caught early.
+pub fn enabled() -> bool {
+    match std::env::var(NETWORK_POLICIES) {
+        Ok(value) if !value.trim().is_empty() => {
+            value.trim().to_lowercase().parse().unwrap_or_else(|_| {
+                panic!(
+                    "the {} environment variable was set to '{}'. It can be one of either \
+                    true or false",
+                    NETWORK_POLICIES, value
+                )
+            })
+        }
+        _ => false,
+    }
+}
+
+impl Isolation {
+    /// Returns the configured Isolation.
+    ///
+    /// This function PANICS if either environment variable is malformed. Consumers SHOULD call
+    /// this function on program startup so that such a misconfiguration is caught early.
+    pub fn configured() -> Isolation {
+        let alation = std::env::var(ALATION_SELECTOR).unwrap_or_default();
+        let egress = std::env::var(EGRESS).unwrap_or_default();
+        Isolation {
+            alation: parse_selector(&alation).unwrap_or_else(|| {
+                panic!(
+                    "the {} environment variable was set to '{}'. It must be a comma separated \
+                    list of <key>=<value> labels",
+                    ALATION_SELECTOR, alation
+                )
+            }),
+            egress: parse_egress(&egress).unwrap_or_else(|| {
+                panic!(
+                    "the {} environment variable was set to '{}'. It must be a comma separated \
+                    list of <cidr> or <cidr>:<port> destinations",
+                    EGRESS, egress
+                )
+            }),
+        }
+    }
+
+    /// Returns the NetworkPolicy that isolates the given pod. That is,
+    ///
+    /// * only the servicing ACM (and other ACMs, which may adopt the pod) and the configured Alation
+    ///   pods may connect to the connector, and only on its own ports (its gRPC port and any extra
+    ///   ports), and
+    /// * the connector may only connect to cluster DNS and the configured [EGRESS](EGRESS).
+    pub fn policy(&self, pod: &Pod, servicer: &Pod) -> NetworkPolicy {
+        let acm = NetworkPolicyPeer {
+            namespace_selector: Some(selector(BTreeMap::from_iter([(
+                NAMESPACE_NAME_LABEL.to_string(),
+                servicer
+                    .namespace()
+                    .unwrap_or_else(|| crate::OCF_SYSTEM_NAMESPACE.to_string()),
+            )]))),
+            pod_selector: Some(selector(
+                servicer
+                    .labels()
+                    .iter()
+                    .filter(|(key, _)| !UNSTABLE_LABELS.contains(&key.as_str()))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            )),
+            ip_block: None,
+        };
+        let mut from = vec![acm];
+        if !self.alation.is_empty() {
+            from.push(NetworkPolicyPeer {
+                namespace_selector: Some(LabelSelector::default()),
+                pod_selector: Some(selector(self.alation.clone())),
+                ip_block: None,
+            });
+        }
+        let dns = NetworkPolicyEgressRule {
+            to: None,
+            ports: Some(vec![port("UDP", 53), port("TCP", 53)]),
+        };
+        let egress = self.egress.iter().map(|egress| NetworkPolicyEgressRule {
+            to: Some(vec![NetworkPolicyPeer {
+                ip_block: Some(IPBlock {
+                    cidr: egress.cidr.clone(),
+                    except: None,
+                }),
+                ..Default::default()
+            }]),
+            ports: egress.port.map(|number| vec![port("TCP", number)]),
+        });
+        // Every port of the connector (its gRPC port and any extra ports) may be reached.
+        let mut ingress: Vec<NetworkPolicyPort> = pod
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.containers.first())
+            .and_then(|container| container.ports.as_ref())
+            .into_iter()
+            .flatten()
+            .map(|container| {
+                port(
+                    container.protocol.as_deref().unwrap_or("TCP"),
+                    container.container_port,
+                )
+            })
+            .collect();
+        if ingress.is_empty() {
+            ingress.push(port("TCP", CONNECTOR_PORT));
+        }
+        NetworkPolicy {
+            metadata: ObjectMeta {
+                name: Some(pod.name()),
+                namespace: pod.namespace(),
+                ..Default::default()
+            },
+            spec: Some(NetworkPolicySpec {
+                pod_selector: selector(BTreeMap::from_iter([(POD_LABEL.to_string(), pod.name())])),
+                policy_types: Some(vec!["Ingress".to_string(), "Egress".to_string()]),
+                ingress: Some(vec![NetworkPolicyIngressRule {
+                    from: Some(from),
+                    ports: Some(ingress),
+                }]),
+                egress: Some(std::iter::once(dns).chain(egress).collect()),
+            }),
+        }
+    }
+}
+
+/// Creates the NetworkPolicy that isolates the given (created) pod as one of its
+/// [dependents](dependents), so that it is deleted alongside the pod.
+pub async fn create(pod: &Pod, servicer: &Pod) -> Result<NetworkPolicy> {
+    dependents::create(pod, Isolation::configured().policy(pod, servicer)).await
+}
+
+fn selector(labels: BTreeMap<String, String>) -> LabelSelector {
+    LabelSelector {
+        match_labels: Some(labels),
+        match_expressions: None,
+    }
+}
+
+fn port(protocol: &str, number: i32) -> NetworkPolicyPort {
+    NetworkPolicyPort {
+        protocol: Some(protocol.to_string()),
+        port: Some(IntOrString::Int(number)),
+        ..Default::default()
+    }
+}
+
+fn parse_selector(value: &str) -> Option<BTreeMap<String, String>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|label| !label.is_empty())
+        .map(|label| {
+            let (key, value) = label.split_once('=')?;
+            Some((key.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+fn parse_egress(value: &str) -> Option<Vec<Egress>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|egress| !egress.is_empty())
+        .map(|egress| {
+            let (cidr, port) = match egress.split_once(':') {
+                Some((cidr, port)) => (cidr, Some(port.parse().ok().filter(|port| *port > 0)?)),
+                None => (egress, None),
+            };
+            let (address, prefix) = cidr.split_once('/')?;
+            address.parse::<std::net::IpAddr>().ok()?;
+            prefix.parse::<u8>().ok()?;
+            Some(Egress {
+                cidr: cidr.to_string(),
+                port,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::PodOptions;
+
+    #[test]
+    fn egress() {
+        assert_eq!(
+            parse_egress("10.0.0.0/8:5432, 0.0.0.0/0"),
+            Some(vec![
+                Egress {
+                    cidr: "10.0.0.0/8".to_string(),
+                    port: Some(5432),
+                },
+                Egress {
+                    cidr: "0.0.0.0/0".to_string(),
+                    port: None,
+                }
+            ])
+        );
+        assert_eq!(parse_egress(""), Some(vec![]));
+        for invalid in ["10.0.0.0", "10.0.0.0/8:http", "database/8", "10.0.0.0/8:0"] {
+            assert_eq!(parse_egress(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn policy() {
+        let pod = crate::pod::new("ocf", "oracle:1", "oracle", &PodOptions::default()).unwrap();
+        let mut servicer = Pod::default();
+        servicer.metadata.namespace = Some("ocf-system".to_string());
+        servicer.metadata.labels = Some(BTreeMap::from_iter([
+            ("app".to_string(), "acm".to_string()),
+            ("pod-template-hash".to_string(), "abcd1234".to_string()),
+        ]));
+        let isolation = Isolation {
+            alation: parse_selector("app=alation").unwrap(),
+            egress: parse_egress("10.0.0.0/8:5432").unwrap(),
+        };
+        let policy = isolation.policy(&pod, &servicer);
+        assert_eq!(policy.metadata.name, Some(pod.name()));
+        let spec = policy.spec.unwrap();
+        assert_eq!(
+            spec.pod_selector.match_labels,
+            Some(BTreeMap::from_iter([(POD_LABEL.to_string(), pod.name())]))
+        );
+        let from = spec.ingress.unwrap()[0].from.clone().unwrap();
+        assert_eq!(from.len(), 2);
+        assert_eq!(
+            from[0].pod_selector.clone().unwrap().match_labels,
+            Some(BTreeMap::from_iter([(
+                "app".to_string(),
+                "acm".to_string()
+            )]))
+        );
+        let egress = spec.egress.unwrap();
+        assert_eq!(egress.len(), 2);
+        assert_eq!(
+            egress[1].to.clone().unwrap()[0]
+                .ip_block
+                .clone()
+                .unwrap()
+                .cidr,
+            "10.0.0.0/8"
+        );
+    }
+}
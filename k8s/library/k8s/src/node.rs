@@ -0,0 +1,184 @@
+use crate::client;
+use crate::errors::ApiError;
+use crate::scheduling::Scheduling;
+use k8s_openapi::api::core::v1::Node;
+use kube::api::ListParams;
+use kube::Api;
+use result::Result;
+use std::collections::BTreeSet;
+
+/// The well-known label under which the kubelet records its node's operating system.
+pub const OS_LABEL: &str = "kubernetes.io/os";
+/// The well-known label under which the kubelet records its node's CPU architecture.
+pub const ARCH_LABEL: &str = "kubernetes.io/arch";
+
+/// Lists every node in the cluster.
+///
+/// Note that listing nodes requires a ClusterRole, as nodes are not namespaced.
+pub async fn list() -> Result<Vec<Node>> {
+    let client: Api<Node> = client::new_for_cluster().await;
+    Ok(client
+        .list(&ListParams::default())
+        .await
+        .map_err(ApiError::from)?
+        .items)
+}
+
+/// Returns the platform (`os/architecture`, e.g. `linux/amd64`) of every one of the given nodes
+/// onto which a connector pod of the given [scheduling](Scheduling) may be scheduled.
+pub fn schedulable_platforms(nodes: &[Node], scheduling: &Scheduling) -> BTreeSet<String> {
+    nodes
+        .iter()
+        .filter(|node| schedulable(node, scheduling))
+        .filter_map(platform)
+        .collect()
+}
+
+/// Returns the platform (`os/architecture`) of the given node. The well-known `kubernetes.io/os`
+/// and `kubernetes.io/arch` labels are preferred, falling back to the node's reported system info.
+pub fn platform(node: &Node) -> Option<String> {
+    let labels = node.metadata.labels.as_ref();
+    let info = node.status.as_ref().and_then(|s| s.node_info.as_ref());
+    let os = labels
+        .and_then(|labels| labels.get(OS_LABEL).cloned())
+        .or_else(|| info.map(|info| info.operating_system.clone()))?;
+    let arch = labels
+        .and_then(|labels| labels.get(ARCH_LABEL).cloned())
+        .or_else(|| info.map(|info| info.architecture.clone()))?;
+    Some(format!("{}/{}", os, arch))
+}
+
+/// Returns whether or not a connector pod of the given [scheduling](Scheduling) may be scheduled
+/// onto the given node. A node is NOT schedulable if it has been cordoned, if it lacks any of the
+/// labels of the pod's node selector, or if it carries any `NoSchedule` or `NoExecute` taint that
+/// the pod does not tolerate.
+pub fn schedulable(node: &Node, scheduling: &Scheduling) -> bool {
+    let labels = node.metadata.labels.as_ref();
+    let selected = scheduling
+        .node_selector
+        .iter()
+        .all(|(key, value)| labels.and_then(|labels| labels.get(key)) == Some(value));
+    if !selected {
+        return false;
+    }
+    let spec = match node.spec.as_ref() {
+        Some(spec) => spec,
+        None => return true,
+    };
+    if spec.unschedulable.unwrap_or(false) {
+        return false;
+    }
+    !spec.taints.iter().flatten().any(|taint| {
+        (taint.effect == "NoSchedule" || taint.effect == "NoExecute")
+            && !scheduling
+                .tolerations
+                .iter()
+                .any(|toleration| toleration.tolerates(taint))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduling::Toleration;
+    use k8s_openapi::api::core::v1::{NodeSpec, NodeStatus, NodeSystemInfo, Taint};
+
+    fn node(labels: &[(&str, &str)], arch: &str) -> Node {
+        let mut node = Node::default();
+        node.metadata.labels = Some(
+            labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        );
+        node.status = Some(NodeStatus {
+            node_info: Some(NodeSystemInfo {
+                operating_system: "linux".to_string(),
+                architecture: arch.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        node
+    }
+
+    #[test]
+    fn platform_prefers_labels() {
+        let labelled = node(&[(OS_LABEL, "linux"), (ARCH_LABEL, "arm64")], "amd64");
+        assert_eq!(platform(&labelled).unwrap(), "linux/arm64");
+        let unlabelled = node(&[], "amd64");
+        assert_eq!(platform(&unlabelled).unwrap(), "linux/amd64");
+    }
+
+    #[test]
+    fn cordoned_and_tainted_nodes_are_unschedulable() {
+        let mut n = node(&[], "amd64");
+        assert!(schedulable(&n, &Scheduling::default()));
+        n.spec = Some(NodeSpec {
+            unschedulable: Some(true),
+            ..Default::default()
+        });
+        assert!(!schedulable(&n, &Scheduling::default()));
+        n.spec = Some(NodeSpec {
+            taints: Some(vec![Taint {
+                effect: "NoSchedule".to_string(),
+                key: "node-role.kubernetes.io/master".to_string(),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        });
+        assert!(!schedulable(&n, &Scheduling::default()));
+        n.spec = Some(NodeSpec {
+            taints: Some(vec![Taint {
+                effect: "PreferNoSchedule".to_string(),
+                key: "busy".to_string(),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        });
+        assert!(schedulable(&n, &Scheduling::default()));
+    }
+
+    #[test]
+    fn tolerated_taints_are_schedulable() {
+        let mut n = node(&[], "amd64");
+        n.spec = Some(NodeSpec {
+            taints: Some(vec![Taint {
+                effect: "NoSchedule".to_string(),
+                key: "dedicated".to_string(),
+                value: Some("connectors".to_string()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        });
+        let tolerating = |value: Option<&str>, effect: Option<&str>| Scheduling {
+            tolerations: vec![Toleration {
+                key: "dedicated".to_string(),
+                value: value.map(str::to_string),
+                effect: effect.map(str::to_string),
+            }],
+            ..Default::default()
+        };
+        assert!(schedulable(
+            &n,
+            &tolerating(Some("connectors"), Some("NoSchedule"))
+        ));
+        assert!(schedulable(&n, &tolerating(None, None)));
+        assert!(!schedulable(&n, &tolerating(Some("alation"), None)));
+        assert!(!schedulable(&n, &tolerating(None, Some("NoExecute"))));
+    }
+
+    #[test]
+    fn node_selectors_must_match() {
+        let n = node(&[("pool", "connectors")], "amd64");
+        let selecting = |key: &str, value: &str| Scheduling {
+            node_selector: vec![(key.to_string(), value.to_string())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        assert!(schedulable(&n, &selecting("pool", "connectors")));
+        assert!(!schedulable(&n, &selecting("pool", "alation")));
+        assert!(!schedulable(&n, &selecting("zone", "connectors")));
+    }
+}
@@ -0,0 +1,787 @@
+use crate::errors::ApiError;
+use crate::scheduling::Scheduling;
+use crate::security::{Security, SecurityPolicy};
+use crate::sidecars;
+use error::*;
+use k8s_openapi::api::core::v1::{
+    ContainerPort, EmptyDirVolumeSource, EnvVar, EnvVarSource, PersistentVolumeClaim,
+    PersistentVolumeClaimVolumeSource, SecretKeySelector, Volume, VolumeMount,
+};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::error::ErrorResponse;
+use kube::Api;
+use result::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Environment variables that callers may never set as they are either set by the OCF itself
+/// (such as `PORT`) or would subvert the connector's runtime.
+pub const DENIED_ENV: &[&str] = &["PORT", "HOSTNAME", "PATH", "LD_PRELOAD", "LD_LIBRARY_PATH"];
+
+/// Prefixes of environment variables that callers may never set as they are reserved for
+/// Kubernetes and the OCF respectively.
+pub const DENIED_ENV_PREFIXES: &[&str] = &["KUBERNETES_", "OCF_"];
+
+/// The name of the connector's gRPC port, which callers may not give to any other port.
+pub const GRPC_PORT_NAME: &str = "grpc";
+
+/// Ports below this one are privileged, and so may not be listened on by a connector (which never
+/// runs as root).
+pub const MIN_PORT: u16 = 1024;
+
+/// Directories that callers may never mount a volume over (or within) as doing so would shadow
+/// the container's operating system or the credentials that Kubernetes mounts into it.
+pub const DENIED_MOUNT_PATHS: &[&str] = &[
+    "/bin",
+    "/dev",
+    "/etc",
+    "/lib",
+    "/proc",
+    "/sbin",
+    "/sys",
+    "/usr",
+    "/var/run/secrets",
+];
+
+/// PodOptions are the caller supplied customizations of a connector pod, given as the body of a
+/// deploy. Every field is optional, so an empty body (or `{}`) deploys the pod exactly as before.
+///
+/// ```json
+/// {
+///   "env": {"LOG_LEVEL": "debug"},
+///   "secrets": [{"name": "DB_PASSWORD", "secret": "oracle-credentials", "key": "password"}],
+///   "scratch": [{"path": "/tmp/spill", "size_limit": "2Gi"}],
+///   "claims": [{"claim": "shared-drivers", "path": "/opt/drivers", "read_only": true}],
+///   "scheduling": {"node_selector": {"pool": "connectors"}},
+///   "security": {"read_only_root_filesystem": false},
+///   "sidecars": ["fluent-bit"],
+///   "port": 50051,
+///   "ports": [{"name": "metrics", "port": 9090}]
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PodOptions {
+    /// Plain environment variables, keyed by name.
+    pub env: BTreeMap<String, String>,
+    /// Environment variables whose values are read out of Kubernetes secrets within the pod's
+    /// namespace. The values themselves never pass through the ACM.
+    pub secrets: Vec<SecretEnv>,
+    /// Size limited `emptyDir` volumes, which live and die with the pod.
+    pub scratch: Vec<ScratchVolume>,
+    /// Existing PersistentVolumeClaims within the pod's namespace to be mounted into the pod.
+    pub claims: Vec<ClaimMount>,
+    /// Constraints upon the nodes that the pod may be scheduled onto, which are added to the
+    /// [configured](Scheduling::configured) defaults.
+    pub scheduling: Scheduling,
+    /// Overrides of the pod's hardened security context, which are refused unless the
+    /// [SecurityPolicy](SecurityPolicy) allows them.
+    pub security: Security,
+    /// The names of the [configured](sidecars::SIDECARS) sidecars to run alongside the connector.
+    pub sidecars: Vec<String>,
+    /// The port that the connector serves gRPC on (and is told of via `PORT`), which is the one
+    /// that is health checked. This defaults to the [CONNECTOR_PORT](sidecars::CONNECTOR_PORT).
+    pub port: Option<u16>,
+    /// Any other ports that the connector listens on, which follow its gRPC port.
+    pub ports: Vec<ExtraPort>,
+}
+
+/// An ExtraPort is a port that the connector listens on besides its gRPC port, such as one
+/// serving metrics. Its `name` must be a valid Kubernetes port name (such as `metrics`).
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ExtraPort {
+    pub name: String,
+    pub port: u16,
+    #[serde(default)]
+    pub protocol: Protocol,
+}
+
+/// The protocol of an [ExtraPort](ExtraPort).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(rename_all = "UPPERCASE")]
+#[derive(Default)]
+pub enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+    Sctp,
+}
+
+impl Protocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Protocol::Tcp => "TCP",
+            Protocol::Udp => "UDP",
+            Protocol::Sctp => "SCTP",
+        }
+    }
+}
+
+/// A SecretEnv is an environment variable whose value is the `key` of the given `secret`.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SecretEnv {
+    pub name: String,
+    pub secret: String,
+    pub key: String,
+}
+
+/// A ScratchVolume is an `emptyDir` mounted at `path` which Kubernetes will evict the pod for
+/// filling beyond `size_limit` (a Kubernetes quantity, such as `512Mi` or `2Gi`).
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ScratchVolume {
+    pub path: String,
+    pub size_limit: String,
+}
+
+/// A ClaimMount mounts the existing PersistentVolumeClaim named `claim` at `path`.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ClaimMount {
+    pub claim: String,
+    pub path: String,
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+impl PodOptions {
+    /// Validates every environment variable against the [deny-list](DENIED_ENV) and the
+    /// naming rules of Kubernetes, returning the first violation found.
+    pub fn validate(&self) -> Result<()> {
+        let mut seen = BTreeSet::new();
+        let names = self
+            .env
+            .keys()
+            .chain(self.secrets.iter().map(|secret| &secret.name));
+        for name in names {
+            if !is_env_name(name) {
+                return Err(InvalidEnvironmentVariable { name: name.clone() }.into());
+            }
+            if is_denied(name) {
+                return Err(DeniedEnvironmentVariable { name: name.clone() }.into());
+            }
+            if !seen.insert(name) {
+                return Err(DuplicateEnvironmentVariable { name: name.clone() }.into());
+            }
+        }
+        for secret in self.secrets.iter() {
+            if secret.secret.is_empty() || secret.key.is_empty() {
+                return Err(InvalidSecretReference {
+                    name: secret.name.clone(),
+                }
+                .into());
+            }
+        }
+        let mut mounted = BTreeSet::new();
+        let paths = self
+            .scratch
+            .iter()
+            .map(|scratch| &scratch.path)
+            .chain(self.claims.iter().map(|claim| &claim.path));
+        for path in paths {
+            if !is_mount_path(path) {
+                return Err(InvalidMountPath { path: path.clone() }.into());
+            }
+            if !mounted.insert(path) {
+                return Err(DuplicateMountPath { path: path.clone() }.into());
+            }
+        }
+        for scratch in self.scratch.iter() {
+            if !is_quantity(&scratch.size_limit) {
+                return Err(InvalidSizeLimit {
+                    path: scratch.path.clone(),
+                    size_limit: scratch.size_limit.clone(),
+                }
+                .into());
+            }
+        }
+        for claim in self.claims.iter() {
+            if !is_claim_name(&claim.claim) {
+                return Err(InvalidClaimName {
+                    claim: claim.claim.clone(),
+                }
+                .into());
+            }
+        }
+        self.scheduling.validate()?;
+        SecurityPolicy::configured().validate(&self.security)?;
+        sidecars::validate(&self.sidecars)?;
+        self.validate_ports()
+    }
+
+    /// Validates that no port of the connector is privileged, that every extra port is validly
+    /// named, and that no two ports (including those of any requested sidecar) conflict.
+    fn validate_ports(&self) -> Result<()> {
+        let mut names = BTreeSet::new();
+        for extra in self.ports.iter() {
+            if !is_port_name(&extra.name) || extra.name == GRPC_PORT_NAME {
+                return Err(InvalidPortName {
+                    name: extra.name.clone(),
+                }
+                .into());
+            }
+            if !names.insert(&extra.name) {
+                return Err(PortConflict {
+                    port: extra.name.clone(),
+                    with: "another port of the same name".to_string(),
+                }
+                .into());
+            }
+        }
+        let mut claimed: BTreeMap<(i32, Protocol), String> = BTreeMap::new();
+        let ports = std::iter::once((GRPC_PORT_NAME, self.grpc_port(), Protocol::Tcp)).chain(
+            self.ports
+                .iter()
+                .map(|extra| (extra.name.as_str(), i32::from(extra.port), extra.protocol)),
+        );
+        for (name, port, protocol) in ports {
+            if port < i32::from(MIN_PORT) {
+                return Err(PrivilegedPort { port }.into());
+            }
+            if let Some(existing) = claimed.insert((port, protocol), name.to_string()) {
+                return Err(PortConflict {
+                    port: port.to_string(),
+                    with: format!("the port '{}'", existing),
+                }
+                .into());
+            }
+        }
+        for (sidecar, port) in sidecars::ports(&self.sidecars) {
+            if claimed.keys().any(|(claimed, _)| *claimed == port) {
+                return Err(PortConflict {
+                    port: port.to_string(),
+                    with: format!("the sidecar '{}'", sidecar),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// The port that the connector serves gRPC on.
+    pub fn grpc_port(&self) -> i32 {
+        self.port.map_or(sidecars::CONNECTOR_PORT, i32::from)
+    }
+
+    /// Renders the connector's gRPC port, followed by its extra ports, into a container's
+    /// `ports` spec.
+    pub fn container_ports(&self) -> Vec<ContainerPort> {
+        let grpc = ContainerPort {
+            name: Some(GRPC_PORT_NAME.to_string()),
+            container_port: self.grpc_port(),
+            protocol: Some(Protocol::Tcp.as_str().to_string()),
+            ..Default::default()
+        };
+        let extras = self.ports.iter().map(|extra| ContainerPort {
+            name: Some(extra.name.clone()),
+            container_port: i32::from(extra.port),
+            protocol: Some(extra.protocol.as_str().to_string()),
+            ..Default::default()
+        });
+        std::iter::once(grpc).chain(extras).collect()
+    }
+
+    /// Confirms that every requested [claim](ClaimMount) exists within the given namespace. Pods
+    /// that mount a missing claim are accepted by Kubernetes but are never scheduled, so this
+    /// SHOULD be checked before the pod is created.
+    pub async fn check_claims(&self, namespace: &str) -> Result<()> {
+        if self.claims.is_empty() {
+            return Ok(());
+        }
+        let client: Api<PersistentVolumeClaim> = crate::client::new_with_namespace(namespace).await;
+        for claim in self.claims.iter() {
+            match client.get(&claim.claim).await {
+                Ok(_) => (),
+                Err(kube::error::Error::Api(ErrorResponse { code: 404, .. })) => {
+                    return Err(MissingClaim {
+                        claim: claim.claim.clone(),
+                        namespace: namespace.to_string(),
+                    }
+                    .into())
+                }
+                Err(err) => return Err(ApiError::from(err).into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders the caller's environment variables into a container's `env` spec.
+    pub fn env(&self) -> Vec<EnvVar> {
+        let plain = self.env.iter().map(|(name, value)| EnvVar {
+            name: name.clone(),
+            value: Some(value.clone()),
+            value_from: None,
+        });
+        let secrets = self.secrets.iter().map(|secret| EnvVar {
+            name: secret.name.clone(),
+            value: None,
+            value_from: Some(EnvVarSource {
+                secret_key_ref: Some(SecretKeySelector {
+                    name: Some(secret.secret.clone()),
+                    key: secret.key.clone(),
+                    optional: Some(false),
+                }),
+                ..Default::default()
+            }),
+        });
+        plain.chain(secrets).collect()
+    }
+
+    /// Renders the caller's scratch volumes and claims into a pod's `volumes` spec. Scratch
+    /// volumes are named `scratch-<n>` and claims `claim-<n>`, in the order that they were given.
+    pub fn volumes(&self) -> Vec<Volume> {
+        let scratch = self.scratch.iter().enumerate().map(|(n, scratch)| Volume {
+            name: format!("scratch-{}", n),
+            empty_dir: Some(EmptyDirVolumeSource {
+                medium: None,
+                size_limit: Some(Quantity(scratch.size_limit.clone())),
+            }),
+            ..Default::default()
+        });
+        let claims = self.claims.iter().enumerate().map(|(n, claim)| Volume {
+            name: format!("claim-{}", n),
+            persistent_volume_claim: Some(PersistentVolumeClaimVolumeSource {
+                claim_name: claim.claim.clone(),
+                read_only: Some(claim.read_only),
+            }),
+            ..Default::default()
+        });
+        scratch.chain(claims).collect()
+    }
+
+    /// Renders the mounts of the [volumes](PodOptions::volumes) into a container's
+    /// `volumeMounts` spec.
+    pub fn volume_mounts(&self) -> Vec<VolumeMount> {
+        let scratch = self
+            .scratch
+            .iter()
+            .enumerate()
+            .map(|(n, scratch)| VolumeMount {
+                name: format!("scratch-{}", n),
+                mount_path: scratch.path.clone(),
+                ..Default::default()
+            });
+        let claims = self
+            .claims
+            .iter()
+            .enumerate()
+            .map(|(n, claim)| VolumeMount {
+                name: format!("claim-{}", n),
+                mount_path: claim.path.clone(),
+                read_only: Some(claim.read_only),
+                ..Default::default()
+            });
+        scratch.chain(claims).collect()
+    }
+}
+
+/// Whether the given name is a C identifier, which is what Kubernetes expects of environment
+/// variable names (and which every shell can cope with).
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn is_denied(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    DENIED_ENV.contains(&upper.as_str())
+        || DENIED_ENV_PREFIXES
+            .iter()
+            .any(|prefix| upper.starts_with(prefix))
+}
+
+/// Whether the given path is absolute, free of `.` and `..` components, and neither is, nor is
+/// within, one of the [DENIED_MOUNT_PATHS](DENIED_MOUNT_PATHS).
+fn is_mount_path(path: &str) -> bool {
+    let relative = match path.strip_prefix('/') {
+        Some(relative) if !relative.is_empty() => relative,
+        _ => return false,
+    };
+    if relative
+        .split('/')
+        .any(|component| component.is_empty() || component == "." || component == "..")
+    {
+        return false;
+    }
+    !DENIED_MOUNT_PATHS.iter().any(|denied| {
+        path == *denied
+            || path
+                .strip_prefix(denied)
+                .is_some_and(|rest| rest.starts_with('/'))
+    })
+}
+
+/// Whether the given string is a Kubernetes quantity (a decimal number followed by an optional
+/// binary or decimal SI suffix) of a non-zero size.
+fn is_quantity(quantity: &str) -> bool {
+    let suffixes = [
+        "Ki", "Mi", "Gi", "Ti", "Pi", "Ei", "k", "M", "G", "T", "P", "E", "",
+    ];
+    suffixes.iter().any(|suffix| {
+        quantity
+            .strip_suffix(suffix)
+            .and_then(|number| number.parse::<f64>().ok().filter(|_| is_decimal(number)))
+            .is_some_and(|number| number > 0.0)
+    })
+}
+
+fn is_decimal(number: &str) -> bool {
+    !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && number.matches('.').count() <= 1
+}
+
+/// Whether the given name is a valid RFC 1123 subdomain, which is what Kubernetes requires of the
+/// names of PersistentVolumeClaims.
+fn is_claim_name(name: &str) -> bool {
+    name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        })
+}
+
+/// Whether the given name is a valid Kubernetes port name (an IANA service name), being at most
+/// fifteen lowercase letters, digits, and hyphens with at least one letter, neither beginning nor
+/// ending with a hyphen nor having two in a row.
+fn is_port_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 15
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && name.chars().any(|c| c.is_ascii_lowercase())
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+        && !name.contains("--")
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "'{name}' is not a valid environment variable name. Names must begin with a letter or an \
+underscore and may only contain letters, digits, and underscores."
+)]
+#[error_code("K8S-1200")]
+pub struct InvalidEnvironmentVariable {
+    name: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The environment variable '{name}' may not be set by callers as it is reserved for either \
+Kubernetes or the OCF itself."
+)]
+#[error_code("K8S-1201")]
+pub struct DeniedEnvironmentVariable {
+    name: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error("The environment variable '{name}' was given more than once.")]
+#[error_code("K8S-1202")]
+pub struct DuplicateEnvironmentVariable {
+    name: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The environment variable '{name}' refers to a secret, however either the name of the secret \
+or the key within it is empty."
+)]
+#[error_code("K8S-1203")]
+pub struct InvalidSecretReference {
+    name: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "'{path}' may not be used as a mount path. Mount paths must be absolute, may not contain '.' \
+or '..' components, and may not shadow the container's operating system (such as '/etc') or the \
+credentials mounted by Kubernetes under '/var/run/secrets'."
+)]
+#[error_code("K8S-1204")]
+pub struct InvalidMountPath {
+    path: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error("More than one volume was requested to be mounted at '{path}'.")]
+#[error_code("K8S-1205")]
+pub struct DuplicateMountPath {
+    path: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The scratch volume at '{path}' has a size limit of '{size_limit}', which is not a positive \
+Kubernetes quantity (such as '512Mi' or '2Gi')."
+)]
+#[error_code("K8S-1206")]
+pub struct InvalidSizeLimit {
+    path: String,
+    size_limit: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error("'{claim}' is not a valid name for a PersistentVolumeClaim.")]
+#[error_code("K8S-1207")]
+pub struct InvalidClaimName {
+    claim: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::NotFound)]
+#[error(
+    "The PersistentVolumeClaim '{claim}' does not exist within the '{namespace}' namespace. \
+Claims must be created before they may be mounted into a connector."
+)]
+#[error_code("K8S-1208")]
+pub struct MissingClaim {
+    claim: String,
+    namespace: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The connector may not listen on port {port}, as ports below 1024 are privileged and \
+connectors never run as root."
+)]
+#[error_code("K8S-1223")]
+pub struct PrivilegedPort {
+    port: i32,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error("The port {port} of the connector conflicts with {with}.")]
+#[error_code("K8S-1224")]
+pub struct PortConflict {
+    port: String,
+    with: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "'{name}' is not a valid port name. Port names must be at most fifteen lowercase letters, \
+digits, and (non-consecutive, inner) hyphens with at least one letter, and 'grpc' is reserved for \
+the connector's gRPC port."
+)]
+#[error_code("K8S-1225")]
+pub struct InvalidPortName {
+    name: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(env: &[&str]) -> PodOptions {
+        PodOptions {
+            env: env
+                .iter()
+                .map(|name| (name.to_string(), "value".to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn empty_body() {
+        let options: PodOptions = serde_json::from_str("{}").unwrap();
+        assert_eq!(options, PodOptions::default());
+        assert!(options.validate().is_ok());
+        assert!(options.env().is_empty());
+    }
+
+    #[test]
+    fn deny_list() {
+        assert!(options(&["LOG_LEVEL", "_private"]).validate().is_ok());
+        for denied in [
+            "PORT",
+            "port",
+            "KUBERNETES_SERVICE_HOST",
+            "OCF_TTL",
+            "LD_PRELOAD",
+        ] {
+            let err = options(&[denied]).validate().unwrap_err();
+            assert_eq!(err.error_code(), Some("K8S-1201"), "{}", denied);
+        }
+        for invalid in ["", "1ABC", "WITH-DASH", "WITH SPACE"] {
+            let err = options(&[invalid]).validate().unwrap_err();
+            assert_eq!(err.error_code(), Some("K8S-1200"), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn secrets() {
+        let mut options = options(&["DB_PASSWORD"]);
+        options.secrets.push(SecretEnv {
+            name: "DB_PASSWORD".to_string(),
+            secret: "oracle".to_string(),
+            key: "password".to_string(),
+        });
+        assert_eq!(
+            options.validate().unwrap_err().error_code(),
+            Some("K8S-1202")
+        );
+        options.env.clear();
+        assert!(options.validate().is_ok());
+        let env = options.env();
+        let selector = env[0]
+            .value_from
+            .as_ref()
+            .unwrap()
+            .secret_key_ref
+            .as_ref()
+            .unwrap();
+        assert_eq!(env[0].name, "DB_PASSWORD");
+        assert_eq!(env[0].value, None);
+        assert_eq!(selector.name.as_deref(), Some("oracle"));
+        assert_eq!(selector.key, "password");
+        assert!(serde_json::from_str::<PodOptions>(r#"{"environment": {}}"#).is_err());
+    }
+
+    #[test]
+    fn mount_paths() {
+        for valid in ["/tmp/spill", "/opt/drivers", "/data", "/etcetera"] {
+            assert!(is_mount_path(valid), "{}", valid);
+        }
+        for invalid in [
+            "",
+            "/",
+            "tmp",
+            "/tmp/../etc",
+            "/tmp//spill",
+            "/tmp/",
+            "/etc",
+            "/etc/ssl",
+            "/var/run/secrets/kubernetes.io",
+        ] {
+            assert!(!is_mount_path(invalid), "{}", invalid);
+        }
+        let mut options = PodOptions::default();
+        for path in ["/tmp/spill", "/tmp/spill"] {
+            options.scratch.push(ScratchVolume {
+                path: path.to_string(),
+                size_limit: "1Gi".to_string(),
+            });
+        }
+        assert_eq!(
+            options.validate().unwrap_err().error_code(),
+            Some("K8S-1205")
+        );
+    }
+
+    #[test]
+    fn volumes() {
+        for valid in ["1Gi", "512Mi", "1.5G", "1000"] {
+            assert!(is_quantity(valid), "{}", valid);
+        }
+        for invalid in ["", "Gi", "0", "-1Gi", "1gb", "1e3"] {
+            assert!(!is_quantity(invalid), "{}", invalid);
+        }
+        let options: PodOptions = serde_json::from_str(
+            r#"{
+                "scratch": [{"path": "/tmp/spill", "size_limit": "2Gi"}],
+                "claims": [{"claim": "shared-drivers", "path": "/opt/drivers", "read_only": true}]
+            }"#,
+        )
+        .unwrap();
+        assert!(options.validate().is_ok());
+        let volumes = options.volumes();
+        let mounts = options.volume_mounts();
+        assert_eq!(volumes.len(), 2);
+        assert_eq!(volumes[0].name, mounts[0].name);
+        assert_eq!(volumes[1].name, mounts[1].name);
+        assert_eq!(
+            volumes[0].empty_dir.as_ref().unwrap().size_limit,
+            Some(Quantity("2Gi".to_string()))
+        );
+        assert_eq!(
+            volumes[1]
+                .persistent_volume_claim
+                .as_ref()
+                .unwrap()
+                .claim_name,
+            "shared-drivers"
+        );
+        assert_eq!(mounts[1].mount_path, "/opt/drivers");
+        assert_eq!(mounts[1].read_only, Some(true));
+        let mut options = options;
+        options.claims[0].claim = "Shared_Drivers".to_string();
+        assert_eq!(
+            options.validate().unwrap_err().error_code(),
+            Some("K8S-1207")
+        );
+    }
+
+    #[test]
+    fn ports() {
+        let options = PodOptions::default();
+        assert!(options.validate().is_ok());
+        assert_eq!(options.grpc_port(), sidecars::CONNECTOR_PORT);
+        let options: PodOptions = serde_json::from_str(
+            r#"{"port": 50051, "ports": [{"name": "metrics", "port": 9090, "protocol": "TCP"}]}"#,
+        )
+        .unwrap();
+        assert!(options.validate().is_ok());
+        let ports = options.container_ports();
+        assert_eq!(ports.len(), 2);
+        assert_eq!(ports[0].container_port, 50051);
+        assert_eq!(ports[0].name.as_deref(), Some(GRPC_PORT_NAME));
+        assert_eq!(ports[1].container_port, 9090);
+        let mut privileged = options.clone();
+        privileged.port = Some(443);
+        assert_eq!(
+            privileged.validate().unwrap_err().error_code(),
+            Some("K8S-1223")
+        );
+        let mut conflicting = options.clone();
+        conflicting.ports[0].port = 50051;
+        assert_eq!(
+            conflicting.validate().unwrap_err().error_code(),
+            Some("K8S-1224")
+        );
+        // The same port number may be served over another protocol.
+        conflicting.ports[0].protocol = Protocol::Udp;
+        assert!(conflicting.validate().is_ok());
+        for invalid in [
+            "grpc",
+            "Metrics",
+            "-metrics",
+            "a--b",
+            "1234",
+            "sixteen-letters-",
+        ] {
+            let mut named = options.clone();
+            named.ports[0].name = invalid.to_string();
+            assert_eq!(
+                named.validate().unwrap_err().error_code(),
+                Some("K8S-1225"),
+                "{}",
+                invalid
+            );
+        }
+    }
+}
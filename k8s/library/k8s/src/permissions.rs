@@ -0,0 +1,199 @@
+//! Probes the permissions that RBAC grants this process by way of
+//! [SelfSubjectAccessReviews](https://kubernetes.io/docs/reference/access-authn-authz/authorization/#checking-api-access),
+//! which any authenticated subject may create. A missing permission is thereby reported up front,
+//! by name, rather than as an opaque `403` in the middle of a deploy.
+use crate::errors::ApiError;
+use error::*;
+use k8s_openapi::api::authorization::v1::{
+    ResourceAttributes, SelfSubjectAccessReview, SelfSubjectAccessReviewSpec,
+};
+use kube::api::PostParams;
+use kube::Api;
+use result::Result;
+use serde::Serialize;
+
+/// The verbs upon pods that are required within every namespace that connectors are deployed into.
+pub const CONNECTOR_VERBS: [&str; 4] = ["create", "delete", "patch", "watch"];
+
+/// The verbs upon events that are required within every namespace that connectors are deployed
+/// into, wherein the scheduler's reasons for not scheduling a connector are [read](crate::events::of).
+pub const CONNECTOR_EVENT_VERBS: [&str; 1] = ["list"];
+
+/// The verbs upon pods that are required within the [system namespace](crate::OCF_SYSTEM_NAMESPACE),
+/// wherein a servicer reads its own pod.
+pub const SYSTEM_VERBS: [&str; 1] = ["get"];
+
+/// A Permission is a single verb upon a single resource within a single namespace, and whether it
+/// is granted.
+#[derive(Serialize, Kind, Debug, Clone, Eq, PartialEq)]
+pub struct Permission {
+    pub verb: String,
+    pub resource: String,
+    pub namespace: String,
+    pub allowed: bool,
+    /// Why the permission was (or was not) granted, should the authorizer have said.
+    pub reason: Option<String>,
+}
+
+impl Permission {
+    fn new(verb: &str, resource: &str, namespace: &str) -> Permission {
+        Permission {
+            verb: verb.to_string(),
+            resource: resource.to_string(),
+            namespace: namespace.to_string(),
+            allowed: false,
+            reason: None,
+        }
+    }
+}
+
+/// Permissions are the outcome of [reviewing](review) every required [Permission](Permission).
+#[derive(Serialize, Kind, Debug, Clone, Default)]
+pub struct Permissions {
+    pub permissions: Vec<Permission>,
+}
+
+impl Permissions {
+    /// Every permission that was not granted.
+    pub fn missing(&self) -> Vec<&Permission> {
+        self.permissions
+            .iter()
+            .filter(|permission| !permission.allowed)
+            .collect()
+    }
+
+    /// Succeeds only should every permission have been granted, otherwise a
+    /// [MissingPermissions](MissingPermissions) names each that was not.
+    pub fn check(&self) -> Result<()> {
+        let missing = self.missing();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(MissingPermissions {
+            missing: missing
+                .into_iter()
+                .map(|permission| {
+                    format!(
+                        "{} {} in '{}'",
+                        permission.verb, permission.resource, permission.namespace
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(", "),
+        }
+        .into())
+    }
+}
+
+/// Returns every permission that is required of a process that deploys connectors into the given
+/// namespaces, none of which have yet been reviewed.
+pub fn required<N: AsRef<str>>(namespaces: &[N]) -> Vec<Permission> {
+    let mut permissions: Vec<Permission> = namespaces
+        .iter()
+        .flat_map(|namespace| {
+            let pods = CONNECTOR_VERBS
+                .iter()
+                .map(move |verb| Permission::new(verb, "pods", namespace.as_ref()));
+            let events = CONNECTOR_EVENT_VERBS
+                .iter()
+                .map(move |verb| Permission::new(verb, "events", namespace.as_ref()));
+            pods.chain(events)
+        })
+        .collect();
+    permissions.extend(
+        SYSTEM_VERBS
+            .iter()
+            .map(|verb| Permission::new(verb, "pods", crate::OCF_SYSTEM_NAMESPACE)),
+    );
+    permissions
+}
+
+/// Reviews every permission [required](required) of a process that deploys connectors into the
+/// given namespaces. A review that cannot be made at all (say, the API server is unreachable) is
+/// an error, whereas a permission that is merely not granted is recorded as such.
+pub async fn review<N: AsRef<str>>(namespaces: &[N]) -> Result<Permissions> {
+    let client = kube::Client::try_default().await.map_err(ApiError::from)?;
+    let reviews: Api<SelfSubjectAccessReview> = Api::all(client);
+    let mut permissions = required(namespaces);
+    for permission in permissions.iter_mut() {
+        let request = SelfSubjectAccessReview {
+            spec: SelfSubjectAccessReviewSpec {
+                resource_attributes: Some(ResourceAttributes {
+                    verb: Some(permission.verb.clone()),
+                    resource: Some(permission.resource.clone()),
+                    namespace: Some(permission.namespace.clone()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let status = reviews
+            .create(&PostParams::default(), &request)
+            .await
+            .map_err(ApiError::from)?
+            .status
+            .unwrap_or_default();
+        permission.allowed = status.allowed && !status.denied.unwrap_or(false);
+        permission.reason = status.reason.or(status.evaluation_error);
+    }
+    Ok(Permissions { permissions })
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::ServiceUnavailable)]
+#[error(
+    "The service account of this process lacks the following permissions: {missing}. Please \
+ensure that it is bound to the 'ocf-system' ClusterRole within every tenant namespace, and to the \
+'ocf-system-meta-role' ClusterRole within the 'ocf-system' namespace (the Helm chart's rbac.yaml \
+does both)."
+)]
+#[error_code("K8S-1221")]
+pub struct MissingPermissions {
+    missing: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requires() {
+        let permissions = required(&["ocf", "tenant"]);
+        assert_eq!(
+            permissions
+                .iter()
+                .map(|p| format!("{} {} {}", p.verb, p.resource, p.namespace))
+                .collect::<Vec<String>>(),
+            vec![
+                "create pods ocf",
+                "delete pods ocf",
+                "patch pods ocf",
+                "watch pods ocf",
+                "list events ocf",
+                "create pods tenant",
+                "delete pods tenant",
+                "patch pods tenant",
+                "watch pods tenant",
+                "list events tenant",
+                "get pods ocf-system"
+            ]
+        );
+    }
+
+    #[test]
+    fn checks() {
+        let mut permissions = Permissions {
+            permissions: required(&["ocf"]),
+        };
+        for permission in permissions.permissions.iter_mut() {
+            permission.allowed = permission.verb != "patch";
+        }
+        assert_eq!(permissions.missing().len(), 1);
+        let err = permissions.check().unwrap_err();
+        assert_eq!(err.error_code(), Some("K8S-1221"));
+        assert!(err.to_string().contains("patch pods in 'ocf'"));
+        permissions.permissions[2].allowed = true;
+        assert!(permissions.check().is_ok());
+    }
+}
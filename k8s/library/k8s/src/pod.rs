@@ -1,6 +1,13 @@
+use crate::deletion::{DeletionCause, DELETION_CAUSE_ANNOTATION};
+use crate::options::PodOptions;
+use crate::pull_secret;
+use crate::scheduling::Scheduling;
+use crate::security::SecurityPolicy;
+use crate::sidecars;
 use error::*;
 use k8s_openapi::api::core::v1::{
-    ContainerState, ContainerStateTerminated, ContainerStateWaiting, Pod, PodStatus,
+    ContainerState, ContainerStateTerminated, ContainerStateWaiting, ContainerStatus, Pod,
+    PodStatus,
 };
 use result::Result;
 use serde_json;
@@ -20,15 +27,59 @@ pub struct PodSerializationError {
     source: serde_json::Error,
 }
 
-pub fn new<R: AsRef<str>, N: AsRef<str>>(reference: R, name: N) -> Result<Pod> {
+/// Returns a new connector pod of the given image reference within the given namespace. The
+/// `name` is sanitized via [rfc1123_subdomain](names::rfc1123_subdomain) and the pod is otherwise
+/// rendered by [named](named).
+pub fn new<S: AsRef<str>, R: AsRef<str>, N: AsRef<str>>(
+    namespace: S,
+    reference: R,
+    name: N,
+    options: &PodOptions,
+) -> Result<Pod> {
+    named(
+        namespace,
+        reference,
+        names::rfc1123_subdomain(name),
+        options,
+    )
+}
+
+/// Returns a new connector pod of the given image reference within the given namespace, whose
+/// `.metadata.name` is the given `name` exactly (such as one generated by
+/// [rfc1123_subdomain_unique](crate::rfc1123_subdomain_unique)).
+///
+/// The caller's [PodOptions](PodOptions) are [validated](PodOptions::validate) and then rendered
+/// into the pod's spec. The connector's gRPC port (`8080` unless the options ask for another) is
+/// the first port of its container, followed by any extra ports, and it is passed to the connector
+/// as `PORT`. Their environment variables follow the `PORT` set by the OCF, and their
+/// scratch volumes and claims are mounted into the connector's container. Their scheduling is
+/// added to the [configured](Scheduling::configured) scheduling defaults.
+///
+/// Every pod is hardened according to the [configured](SecurityPolicy::configured) SecurityPolicy,
+/// which runs the connector as a non-root user with a read-only root filesystem.
+///
+/// The [configured](pull_secret::IMAGE_PULL_SECRET) image pull secret, if any, is attached to
+/// every pod.
+///
+/// Any requested [sidecars](sidecars::SIDECARS) follow the connector's own container, which is
+/// always the first container of the pod. Sidecars are not hardened by the SecurityPolicy, as
+/// their specs are written by the operator rather than by callers.
+pub fn named<S: AsRef<str>, R: AsRef<str>, N: AsRef<str>>(
+    namespace: S,
+    reference: R,
+    name: N,
+    options: &PodOptions,
+) -> Result<Pod> {
+    options.validate()?;
+    let namespace = namespace.as_ref();
     let reference = reference.as_ref();
-    let name = names::rfc1123_subdomain(name);
-    let pod: Pod = serde_json::from_value(serde_json::json!({
+    let name = name.as_ref();
+    let mut pod: Pod = serde_json::from_value(serde_json::json!({
        "apiVersion":"v1",
        "kind":"Pod",
        "metadata":{
           "name": name,
-          "namespace": super::OCF_NAMESPACE
+          "namespace": namespace
        },
        "spec":{
           "containers":[
@@ -38,17 +89,12 @@ pub fn new<R: AsRef<str>, N: AsRef<str>>(reference: R, name: N) -> Result<Pod> {
                 "env":[
                    {
                       "name":"PORT",
-                      "value":"8080"
+                      "value":options.grpc_port().to_string()
                    }
                 ],
                 "restartPolicy":"Never",
                 "imagePullPolicy":"IfNotPresent",
-                "ports":[
-                   {
-                      "containerPort":8080,
-                      "protocol":"TCP"
-                   }
-                ]
+                "ports":options.container_ports()
              }
           ]
        }
@@ -58,12 +104,65 @@ pub fn new<R: AsRef<str>, N: AsRef<str>>(reference: R, name: N) -> Result<Pod> {
         reference: reference.to_string(),
         source,
     })?;
+    if let Some(spec) = pod.spec.as_mut() {
+        Scheduling::configured()
+            .merge(&options.scheduling)
+            .apply(spec);
+        let volumes = options.volumes();
+        if !volumes.is_empty() {
+            spec.volumes = Some(volumes);
+        }
+        if let Some(container) = spec.containers.get_mut(0) {
+            container
+                .env
+                .get_or_insert_with(Vec::new)
+                .extend(options.env());
+            let mounts = options.volume_mounts();
+            if !mounts.is_empty() {
+                container.volume_mounts = Some(mounts);
+            }
+        }
+        SecurityPolicy::configured().apply(&options.security, spec);
+        pull_secret::apply(spec);
+        sidecars::apply(&options.sidecars, spec);
+    }
     Ok(pod)
 }
 
+/// A PodPhase summarizes how far along a connector pod is. Unlike the pod's own `.status.phase`,
+/// it is judged by the connector's container (the first container of the pod, see [new](new)) and
+/// the pod's init containers alone, so that a sidecar that is running (or that has crashed) is
+/// not mistaken for the connector.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PodPhase {
+    /// The pod is yet to be scheduled, or its containers are yet to be created.
+    Pending,
+    /// An init container of the pod is yet to complete.
+    Initializing,
+    /// An init container of the pod failed, so the connector is never going to start.
+    InitFailed,
+    /// The connector's container is waiting to start (such as while its image is pulled).
+    Starting,
+    /// The connector's container is running, but the pod is not (yet) ready.
+    Running,
+    /// The connector's container is running and the pod is ready.
+    Ready,
+    /// The connector's container terminated, or is crash looping.
+    Terminated,
+    /// Kubernetes evicted the pod.
+    Evicted,
+}
+
 /// PodExt is an extension trait used to answer common questions about pods.
+///
+/// Note that [running](PodExt::running), [terminated](PodExt::terminated),
+/// [crashed](PodExt::crashed), [terminated_reason](PodExt::terminated_reason), and
+/// [terminated_message](PodExt::terminated_message) look at every container of the pod, sidecars
+/// included. Questions about the connector itself are better answered by
+/// [phase](PodExt::phase) and the per container queries.
 pub trait PodExt {
     fn dns(&self) -> Result<String>;
+    /// The port that the connector serves gRPC on, which is the first port of its container.
     fn port(&self) -> Result<i32>;
     fn address(&self) -> Result<String>;
     fn running(&self) -> bool;
@@ -73,10 +172,121 @@ pub trait PodExt {
     fn terminated_message(&self) -> Option<String>;
     fn was_err_image_pull(&self) -> bool;
     fn err_image_pull(&self) -> Result<()>;
+    fn evicted(&self) -> bool;
+    fn deletion_cause(&self) -> Option<DeletionCause>;
+    /// The state of the (regular) container of the given name.
+    fn container_state(&self, name: &str) -> Option<&ContainerState>;
+    /// Whether the (regular) container of the given name is running.
+    fn container_running(&self, name: &str) -> bool;
+    /// The state of the connector's container.
+    fn main_container_state(&self) -> Option<&ContainerState>;
+    /// The number of times that Kubernetes has restarted the connector's container.
+    fn restarts(&self) -> i32;
+    /// Whether every init container of the pod has completed successfully, which is trivially so
+    /// for a pod without any.
+    fn initialized(&self) -> bool;
+    /// Whether an init container of the pod failed (or is crash looping).
+    fn init_failed(&self) -> bool;
+    /// How the connector's container (or, should one have failed, an init container) terminated.
+    /// A container that is crash looping is described by how it last terminated.
+    fn termination(&self) -> Option<&ContainerStateTerminated>;
+    fn phase(&self) -> PodPhase;
+    /// The message that Kubernetes gave upon evicting the pod, should it have been evicted.
+    fn eviction_message(&self) -> Option<String>;
+    /// Whether the pod has been bound to a node.
+    fn scheduled(&self) -> bool;
+    /// Why the scheduler could not place the pod onto any node (such as
+    /// `0/3 nodes are available: 3 Insufficient memory.`), as reported by the pod's `PodScheduled`
+    /// condition, should the scheduler have given up on it for the time being.
+    fn unschedulable_message(&self) -> Option<String>;
+    /// Returns why the pod is never going to come up, should Kubernetes have said as much. That is,
+    /// should a container of the pod be waiting for any of...
+    ///
+    /// * `ErrImagePull` ([ErrImagePull](ErrImagePull))
+    /// * `ImagePullBackOff` ([ImagePullBackOff](ImagePullBackOff))
+    /// * `CreateContainerConfigError` ([CreateContainerConfigError](CreateContainerConfigError))
+    /// * `RunContainerError` ([RunContainerError](RunContainerError))
+    ///
+    /// or should the connector's container (or an init container) have been `OOMKilled`
+    /// ([OomKilled](OomKilled)). Each error carries the message that Kubernetes gave.
+    fn failure(&self) -> Result<()>;
+}
+
+/// Returns the status of the connector's container, which is the first container of the pod's
+/// spec. A pod without a spec is presumed to list its connector's status first.
+fn main_container_status(pod: &Pod) -> Option<&ContainerStatus> {
+    let statuses = pod.status.as_ref()?.container_statuses.as_ref()?;
+    match pod.spec.as_ref().and_then(|spec| spec.containers.first()) {
+        Some(main) => statuses.iter().find(|status| status.name == main.name),
+        None => statuses.first(),
+    }
+}
+
+fn init_container_statuses(pod: &Pod) -> &[ContainerStatus] {
+    pod.status
+        .as_ref()
+        .and_then(|status| status.init_container_statuses.as_deref())
+        .unwrap_or_default()
+}
+
+/// Whether the given (init) container failed, that is, it exited unsuccessfully or is crash
+/// looping.
+fn failed(status: &ContainerStatus) -> bool {
+    status.state.as_ref().is_some_and(|state| {
+        state
+            .terminated
+            .as_ref()
+            .is_some_and(|terminated| terminated.exit_code != 0)
+            || crash_looping(state)
+    })
+}
+
+fn crash_looping(state: &ContainerState) -> bool {
+    matches!(
+        &state.waiting,
+        Some(ContainerStateWaiting {
+            reason: Some(reason),
+            ..
+        }) if reason == "CrashLoopBackOff"
+    )
+}
+
+/// Returns the first container of the pod (init containers included) that is waiting for the
+/// given reason, alongside the message that Kubernetes gave.
+fn waiting_for<'a>(pod: &'a Pod, reason: &str) -> Option<(&'a str, String)> {
+    let statuses = pod.status.as_ref()?;
+    statuses
+        .init_container_statuses
+        .iter()
+        .chain(statuses.container_statuses.iter())
+        .flatten()
+        .find_map(|status| {
+            let waiting = status.state.as_ref()?.waiting.as_ref()?;
+            if waiting.reason.as_deref() != Some(reason) {
+                return None;
+            }
+            let message = waiting
+                .message
+                .clone()
+                .unwrap_or_else(|| "<None Given>".to_string());
+            Some((status.name.as_str(), message))
+        })
+}
+
+/// How the given container terminated, or last terminated should it be crash looping.
+fn termination_of(status: &ContainerStatus) -> Option<&ContainerStateTerminated> {
+    status
+        .state
+        .as_ref()
+        .and_then(|state| state.terminated.as_ref())
+        .or_else(|| status.last_state.as_ref()?.terminated.as_ref())
 }
 
 impl PodExt for Pod {
     fn dns(&self) -> Result<String> {
+        if let Some(dns) = crate::headless::dns(self) {
+            return Ok(dns);
+        }
         let subdomain = self
             .status
             .as_ref()
@@ -107,7 +317,7 @@ impl PodExt for Pod {
                 op: "retrieving its listening port number".to_string(),
             })?
             .containers
-            .get(0)
+            .first()
             .as_ref()
             .ok_or_else(|| PodHasNoContainers {
                 op: "retrieving its listening port number".to_string(),
@@ -117,7 +327,7 @@ impl PodExt for Pod {
             .ok_or_else(|| ContainerHasNoPorts {
                 op: "retrieving its listening port number".to_string(),
             })?
-            .get(0)
+            .first()
             .as_ref()
             .ok_or_else(|| ContainerHasNoPorts {
                 op: "retrieving its listening port number".to_string(),
@@ -333,6 +543,173 @@ impl PodExt for Pod {
             .collect();
         status.pop().unwrap_or(None)
     }
+
+    fn evicted(&self) -> bool {
+        matches!(
+            self.status.as_ref().and_then(|status| status.reason.as_ref()),
+            Some(reason) if reason.eq("Evicted")
+        )
+    }
+
+    fn deletion_cause(&self) -> Option<DeletionCause> {
+        self.metadata
+            .annotations
+            .as_ref()?
+            .get(DELETION_CAUSE_ANNOTATION)?
+            .parse()
+            .ok()
+    }
+
+    fn container_state(&self, name: &str) -> Option<&ContainerState> {
+        self.status
+            .as_ref()?
+            .container_statuses
+            .as_ref()?
+            .iter()
+            .find(|status| status.name == name)?
+            .state
+            .as_ref()
+    }
+
+    fn container_running(&self, name: &str) -> bool {
+        self.container_state(name)
+            .is_some_and(|state| state.running.is_some())
+    }
+
+    fn main_container_state(&self) -> Option<&ContainerState> {
+        main_container_status(self)?.state.as_ref()
+    }
+
+    fn restarts(&self) -> i32 {
+        main_container_status(self).map_or(0, |status| status.restart_count)
+    }
+
+    fn initialized(&self) -> bool {
+        init_container_statuses(self).iter().all(|status| {
+            matches!(
+                status.state.as_ref().and_then(|state| state.terminated.as_ref()),
+                Some(terminated) if terminated.exit_code == 0
+            )
+        })
+    }
+
+    fn init_failed(&self) -> bool {
+        init_container_statuses(self).iter().any(failed)
+    }
+
+    fn termination(&self) -> Option<&ContainerStateTerminated> {
+        match init_container_statuses(self)
+            .iter()
+            .find(|status| failed(status))
+        {
+            Some(init) => termination_of(init),
+            None => termination_of(main_container_status(self)?),
+        }
+    }
+
+    fn eviction_message(&self) -> Option<String> {
+        if !self.evicted() {
+            return None;
+        }
+        self.status.as_ref()?.message.clone()
+    }
+
+    fn scheduled(&self) -> bool {
+        self.spec
+            .as_ref()
+            .is_some_and(|spec| spec.node_name.is_some())
+    }
+
+    fn unschedulable_message(&self) -> Option<String> {
+        self.status
+            .as_ref()?
+            .conditions
+            .as_ref()?
+            .iter()
+            .find(|condition| {
+                condition.type_ == "PodScheduled"
+                    && condition.status == "False"
+                    && condition.reason.as_deref() == Some("Unschedulable")
+            })
+            .map(|condition| {
+                condition
+                    .message
+                    .clone()
+                    .unwrap_or_else(|| "Unschedulable".to_string())
+            })
+    }
+
+    fn failure(&self) -> Result<()> {
+        self.err_image_pull()?;
+        if let Some((container, message)) = waiting_for(self, "ImagePullBackOff") {
+            return Err(ImagePullBackOff {
+                container: container.to_string(),
+                message,
+            }
+            .into());
+        }
+        if let Some((container, message)) = waiting_for(self, "CreateContainerConfigError") {
+            return Err(CreateContainerConfigError {
+                container: container.to_string(),
+                message,
+            }
+            .into());
+        }
+        if let Some((container, message)) = waiting_for(self, "RunContainerError") {
+            return Err(RunContainerError {
+                container: container.to_string(),
+                message,
+            }
+            .into());
+        }
+        match self.termination() {
+            Some(termination) if termination.reason.as_deref() == Some("OOMKilled") => {
+                Err(OomKilled {
+                    message: termination
+                        .message
+                        .clone()
+                        .unwrap_or_else(|| "<None Given>".to_string()),
+                }
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn phase(&self) -> PodPhase {
+        if self.evicted() {
+            return PodPhase::Evicted;
+        }
+        if self.init_failed() {
+            return PodPhase::InitFailed;
+        }
+        let ready = self
+            .status
+            .as_ref()
+            .and_then(|status| status.conditions.as_ref())
+            .is_some_and(|conditions| {
+                conditions
+                    .iter()
+                    .any(|condition| condition.type_ == "Ready" && condition.status == "True")
+            });
+        let finished = matches!(
+            self.status
+                .as_ref()
+                .and_then(|status| status.phase.as_deref()),
+            Some("Failed") | Some("Succeeded")
+        );
+        match self.main_container_state() {
+            Some(state) if state.running.is_some() && ready => PodPhase::Ready,
+            Some(state) if state.running.is_some() => PodPhase::Running,
+            Some(state) if state.terminated.is_some() || crash_looping(state) => {
+                PodPhase::Terminated
+            }
+            _ if finished => PodPhase::Terminated,
+            _ if !self.initialized() => PodPhase::Initializing,
+            Some(state) if state.waiting.is_some() => PodPhase::Starting,
+            _ => PodPhase::Pending,
+        }
+    }
 }
 
 #[derive(Error, AcmError, HttpCode, Kind, Debug)]
@@ -341,6 +718,7 @@ impl PodExt for Pod {
 Perhaps the image doesn't exist or the connection to the registry couldn't be established?"
 )]
 #[code(error::Status::NotFound)]
+#[error_code("K8S-1000")]
 struct ErrImagePull {
     #[source]
     message: ErrImagePullCause,
@@ -353,6 +731,49 @@ struct ErrImagePullCause {
     message: String,
 }
 
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[error(
+    "The image of the '{container}' container repeatedly failed to get pulled from the configured image registry, so Kubernetes has backed off from pulling it. Perhaps the image doesn't exist or the connection to the registry couldn't be established? Kubernetes reported: {message}"
+)]
+#[code(error::Status::NotFound)]
+#[error_code("K8S-1003")]
+pub struct ImagePullBackOff {
+    container: String,
+    message: String,
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[error(
+    "The '{container}' container could not be created from its configuration. This is most often a Secret or ConfigMap that it refers to which does not exist. Kubernetes reported: {message}"
+)]
+#[code(error::Status::UnprocessableEntity)]
+#[error_code("K8S-1004")]
+pub struct CreateContainerConfigError {
+    container: String,
+    message: String,
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[error(
+    "The container runtime failed to start the '{container}' container. Kubernetes reported: {message}"
+)]
+#[code(error::Status::ServiceUnavailable)]
+#[error_code("K8S-1005")]
+pub struct RunContainerError {
+    container: String,
+    message: String,
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[error(
+    "The connector ran out of memory and was killed by Kubernetes (OOMKilled). Either reduce the memory that the job requires or deploy the connector with a larger memory limit. Kubernetes reported: {message}"
+)]
+#[code(error::Status::ServiceUnavailable)]
+#[error_code("K8S-1006")]
+pub struct OomKilled {
+    message: String,
+}
+
 #[derive(Error, AcmError, HttpCode, Kind, Debug)]
 #[code(error::Status::InternalServerError)]
 #[error(
@@ -404,8 +825,8 @@ struct PodHasNoSpec {
 {op}, however the object had no containers associated with it. This was likely a premature call to a \
 pod object that had not yet been provisioned in Kubernetes."
 )]
-struct PodHasNoContainers {
-    op: String,
+pub(crate) struct PodHasNoContainers {
+    pub(crate) op: String,
 }
 
 #[derive(Error, AcmError, HttpCode, Kind, Debug)]
@@ -425,11 +846,262 @@ mod tests {
 
     #[test]
     fn empty() {
-        new("".to_string(), "asdas").unwrap();
+        new(crate::OCF_NAMESPACE, "", "asdas", &PodOptions::default()).unwrap();
     }
 
     #[test]
     fn not_rfc1123_compliant_name() {
-        new("not a bloody chance".to_string(), "asdas").unwrap();
+        new(
+            crate::OCF_NAMESPACE,
+            "not a bloody chance",
+            "asdas",
+            &PodOptions::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn env() {
+        let mut options = PodOptions::default();
+        options
+            .env
+            .insert("LOG_LEVEL".to_string(), "debug".to_string());
+        let pod = new(crate::OCF_NAMESPACE, "oracle:1", "oracle", &options).unwrap();
+        let env = pod.spec.unwrap().containers[0].env.clone().unwrap();
+        let names: Vec<&str> = env.iter().map(|var| var.name.as_str()).collect();
+        assert_eq!(names, vec!["PORT", "LOG_LEVEL"]);
+        options.env.insert("PORT".to_string(), "80".to_string());
+        assert!(new(crate::OCF_NAMESPACE, "oracle:1", "oracle", &options).is_err());
+    }
+
+    #[test]
+    fn volumes() {
+        let pod = new(
+            crate::OCF_NAMESPACE,
+            "oracle:1",
+            "oracle",
+            &PodOptions::default(),
+        )
+        .unwrap();
+        let spec = pod.spec.unwrap();
+        assert_eq!(spec.volumes, None);
+        assert_eq!(spec.containers[0].volume_mounts, None);
+        let mut options = PodOptions::default();
+        options.scratch.push(crate::options::ScratchVolume {
+            path: "/tmp/spill".to_string(),
+            size_limit: "1Gi".to_string(),
+        });
+        let pod = new(crate::OCF_NAMESPACE, "oracle:1", "oracle", &options).unwrap();
+        let spec = pod.spec.unwrap();
+        assert_eq!(spec.volumes.unwrap()[0].name, "scratch-0");
+        let mounts = spec.containers[0].volume_mounts.clone().unwrap();
+        assert_eq!(mounts[0].name, "scratch-0");
+        assert_eq!(mounts[0].mount_path, "/tmp/spill");
+        options.scratch[0].path = "/proc/self".to_string();
+        assert!(new(crate::OCF_NAMESPACE, "oracle:1", "oracle", &options).is_err());
+    }
+
+    #[test]
+    fn security_context() {
+        let mut options = PodOptions::default();
+        let pod = new(crate::OCF_NAMESPACE, "oracle:1", "oracle", &options).unwrap();
+        let spec = pod.spec.unwrap();
+        assert_eq!(spec.security_context.unwrap().run_as_non_root, Some(true));
+        let container = spec.containers[0].security_context.clone().unwrap();
+        assert_eq!(container.read_only_root_filesystem, Some(true));
+        options.security.read_only_root_filesystem = Some(false);
+        let err = new(crate::OCF_NAMESPACE, "oracle:1", "oracle", &options).unwrap_err();
+        assert_eq!(err.error_code(), Some("K8S-1211"));
+    }
+
+    fn status_of(name: &str, state: ContainerState) -> ContainerStatus {
+        ContainerStatus {
+            name: name.to_string(),
+            state: Some(state),
+            ..Default::default()
+        }
+    }
+
+    fn running() -> ContainerState {
+        ContainerState {
+            running: Some(Default::default()),
+            ..Default::default()
+        }
+    }
+
+    fn exited(exit_code: i32) -> ContainerState {
+        ContainerState {
+            terminated: Some(ContainerStateTerminated {
+                exit_code,
+                reason: Some("Error".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn waiting(reason: &str) -> ContainerState {
+        ContainerState {
+            waiting: Some(ContainerStateWaiting {
+                reason: Some(reason.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn phases() {
+        let mut pod = new(
+            crate::OCF_NAMESPACE,
+            "oracle:1",
+            "oracle",
+            &PodOptions::default(),
+        )
+        .unwrap();
+        let main = pod.spec.as_ref().unwrap().containers[0].name.clone();
+        pod.status = Some(PodStatus::default());
+        assert_eq!(pod.phase(), PodPhase::Pending);
+        let status = pod.status.as_mut().unwrap();
+        status.init_container_statuses = Some(vec![status_of("init", running())]);
+        status.container_statuses = Some(vec![
+            status_of(&main, waiting("PodInitializing")),
+            status_of("proxy", running()),
+        ]);
+        // The running sidecar (named "proxy") is not mistaken for the connector.
+        assert!(pod.running());
+        assert!(!pod.container_running(&main));
+        assert_eq!(pod.phase(), PodPhase::Initializing);
+        let status = pod.status.as_mut().unwrap();
+        status.init_container_statuses = Some(vec![status_of("init", exited(0))]);
+        assert!(pod.initialized());
+        assert_eq!(pod.phase(), PodPhase::Starting);
+        let status = pod.status.as_mut().unwrap();
+        status.container_statuses.as_mut().unwrap()[0] = status_of(&main, running());
+        assert_eq!(pod.phase(), PodPhase::Running);
+        let status = pod.status.as_mut().unwrap();
+        status.conditions = Some(vec![k8s_openapi::api::core::v1::PodCondition {
+            type_: "Ready".to_string(),
+            status: "True".to_string(),
+            ..Default::default()
+        }]);
+        assert_eq!(pod.phase(), PodPhase::Ready);
+        // A crashed sidecar does not terminate the connector either.
+        let status = pod.status.as_mut().unwrap();
+        status.container_statuses.as_mut().unwrap()[1] =
+            status_of("proxy", waiting("CrashLoopBackOff"));
+        assert!(pod.crashed());
+        assert_eq!(pod.phase(), PodPhase::Ready);
+        let status = pod.status.as_mut().unwrap();
+        status.container_statuses.as_mut().unwrap()[0] = status_of(&main, exited(1));
+        assert_eq!(pod.phase(), PodPhase::Terminated);
+        assert_eq!(pod.termination().unwrap().exit_code, 1);
+        let status = pod.status.as_mut().unwrap();
+        status.init_container_statuses = Some(vec![status_of("init", exited(2))]);
+        assert_eq!(pod.phase(), PodPhase::InitFailed);
+        assert_eq!(pod.termination().unwrap().exit_code, 2);
+    }
+
+    #[test]
+    fn failures() {
+        let mut pod = new(
+            crate::OCF_NAMESPACE,
+            "oracle:1",
+            "oracle",
+            &PodOptions::default(),
+        )
+        .unwrap();
+        let main = pod.spec.as_ref().unwrap().containers[0].name.clone();
+        pod.status = Some(PodStatus::default());
+        assert!(pod.failure().is_ok());
+        let mut config = waiting("CreateContainerConfigError");
+        config.waiting.as_mut().unwrap().message = Some("secret \"oracle\" not found".to_string());
+        pod.status.as_mut().unwrap().container_statuses = Some(vec![status_of(&main, config)]);
+        let err = pod.failure().unwrap_err();
+        assert_eq!(err.error_code(), Some("K8S-1004"));
+        assert!(err.to_string().contains("secret \"oracle\" not found"));
+        pod.status.as_mut().unwrap().container_statuses =
+            Some(vec![status_of(&main, waiting("ImagePullBackOff"))]);
+        assert_eq!(pod.failure().unwrap_err().error_code(), Some("K8S-1003"));
+        let mut oom = status_of(&main, waiting("CrashLoopBackOff"));
+        oom.last_state = Some(ContainerState {
+            terminated: Some(ContainerStateTerminated {
+                exit_code: 137,
+                reason: Some("OOMKilled".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        oom.restart_count = 2;
+        pod.status.as_mut().unwrap().container_statuses = Some(vec![oom]);
+        assert_eq!(pod.failure().unwrap_err().error_code(), Some("K8S-1006"));
+        assert_eq!(pod.restarts(), 2);
+    }
+
+    #[test]
+    fn ports() {
+        let pod = new(
+            crate::OCF_NAMESPACE,
+            "oracle:1",
+            "oracle",
+            &PodOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(pod.port().unwrap(), crate::sidecars::CONNECTOR_PORT);
+        let options: PodOptions = serde_json::from_str(
+            r#"{"port": 50051, "ports": [{"name": "metrics", "port": 9090}]}"#,
+        )
+        .unwrap();
+        let pod = new(crate::OCF_NAMESPACE, "oracle:1", "oracle", &options).unwrap();
+        assert_eq!(pod.port().unwrap(), 50051);
+        let container = &pod.spec.as_ref().unwrap().containers[0];
+        assert_eq!(container.ports.as_ref().unwrap().len(), 2);
+        let env = container.env.as_ref().unwrap();
+        assert_eq!(env[0].name, "PORT");
+        assert_eq!(env[0].value.as_deref(), Some("50051"));
+    }
+
+    #[test]
+    fn unschedulable() {
+        let mut pod = new(
+            crate::OCF_NAMESPACE,
+            "oracle:1",
+            "oracle",
+            &PodOptions::default(),
+        )
+        .unwrap();
+        assert!(!pod.scheduled());
+        assert_eq!(pod.unschedulable_message(), None);
+        pod.status = Some(PodStatus {
+            conditions: Some(vec![k8s_openapi::api::core::v1::PodCondition {
+                type_: "PodScheduled".to_string(),
+                status: "False".to_string(),
+                reason: Some("Unschedulable".to_string()),
+                message: Some("0/3 nodes are available: 3 Insufficient memory.".to_string()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        });
+        assert_eq!(
+            pod.unschedulable_message().as_deref(),
+            Some("0/3 nodes are available: 3 Insufficient memory.")
+        );
+        pod.spec.as_mut().unwrap().node_name = Some("node".to_string());
+        assert!(pod.scheduled());
+    }
+
+    #[test]
+    fn deletion_cause() {
+        let mut pod = new(crate::OCF_NAMESPACE, "", "asdas", &PodOptions::default()).unwrap();
+        assert_eq!(pod.deletion_cause(), None);
+        pod.metadata.annotations = Some(
+            vec![(
+                DELETION_CAUSE_ANNOTATION.to_string(),
+                "ttl_expired".to_string(),
+            )]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(pod.deletion_cause(), Some(DeletionCause::TtlExpired));
     }
 }
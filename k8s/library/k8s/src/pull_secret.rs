@@ -0,0 +1,61 @@
+use crate::errors::ApiError;
+use error::*;
+use k8s_openapi::api::core::v1::{LocalObjectReference, PodSpec, Secret};
+use kube::error::ErrorResponse;
+use kube::Api;
+use result::Result;
+
+/// The environment variable that names the Secret (of type `kubernetes.io/dockerconfigjson`)
+/// that connector images are pulled with. Clusters whose nodes may pull from the registry via
+/// their own IAM roles need not set this.
+pub const IMAGE_PULL_SECRET: &str = "IMAGE_PULL_SECRET";
+
+/// Returns the name of the configured image pull secret, if any.
+pub fn configured() -> Option<String> {
+    std::env::var(IMAGE_PULL_SECRET)
+        .ok()
+        .map(|secret| secret.trim().to_string())
+        .filter(|secret| !secret.is_empty())
+}
+
+/// Attaches the configured image pull secret (if any) to the given pod spec.
+pub fn apply(spec: &mut PodSpec) {
+    if let Some(secret) = configured() {
+        spec.image_pull_secrets = Some(vec![LocalObjectReference { name: Some(secret) }]);
+    }
+}
+
+/// Confirms that the configured image pull secret (if any) exists within the given namespace.
+/// Pods that name a missing pull secret fail to pull their image, so consumers SHOULD call this
+/// on program startup for every namespace that they deploy into.
+pub async fn check(namespace: &str) -> Result<()> {
+    let secret = match configured() {
+        Some(secret) => secret,
+        None => return Ok(()),
+    };
+    let client: Api<Secret> = crate::client::new_with_namespace(namespace).await;
+    match client.get(&secret).await {
+        Ok(_) => Ok(()),
+        Err(kube::error::Error::Api(ErrorResponse { code: 404, .. })) => {
+            Err(MissingImagePullSecret {
+                secret,
+                namespace: namespace.to_string(),
+            }
+            .into())
+        }
+        Err(err) => Err(ApiError::from(err).into()),
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error(
+    "The image pull secret '{secret}' (configured by the IMAGE_PULL_SECRET environment variable) \
+does not exist within the '{namespace}' namespace. Either create the secret within that namespace \
+or unset IMAGE_PULL_SECRET if the cluster's nodes may pull connector images on their own."
+)]
+#[error_code("K8S-1214")]
+pub struct MissingImagePullSecret {
+    secret: String,
+    namespace: String,
+}
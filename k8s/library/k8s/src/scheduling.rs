@@ -0,0 +1,405 @@
+use error::*;
+use k8s_openapi::api::core::v1::{Affinity, PodAffinityTerm, PodAntiAffinity, PodSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use result::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The environment variable that configures the default node selector of every connector pod,
+/// given as a comma separated list of `<key>=<value>` labels.
+pub const NODE_SELECTOR: &str = "NODE_SELECTOR";
+
+/// The environment variable that configures the default tolerations of every connector pod,
+/// given as a comma separated list of taints in the same syntax as `kubectl taint`. That is,
+/// `<key>=<value>:<effect>` or, to tolerate every value of the key, `<key>:<effect>`. The effect
+/// may be omitted in order to tolerate every effect.
+pub const TOLERATIONS: &str = "TOLERATIONS";
+
+/// The environment variable that configures the default anti-affinity of every connector pod,
+/// given as a comma separated list of `<key>=<value>` labels.
+pub const ANTI_AFFINITY: &str = "ANTI_AFFINITY";
+
+/// The topology over which [anti-affinity](Scheduling::anti_affinity) is enforced.
+pub const ANTI_AFFINITY_TOPOLOGY: &str = "kubernetes.io/hostname";
+
+/// The effects that a taint (and thus a toleration) may have.
+pub const EFFECTS: &[&str] = &["NoSchedule", "PreferNoSchedule", "NoExecute"];
+
+/// Scheduling constrains the nodes that a connector pod may be scheduled onto, which allows
+/// connectors to be pinned to a dedicated node pool away from Alation's core services.
+///
+/// Defaults for every pod are [configured](Scheduling::configured) via the [NODE_SELECTOR](NODE_SELECTOR),
+/// [TOLERATIONS](TOLERATIONS), and [ANTI_AFFINITY](ANTI_AFFINITY) environment variables. A deploy
+/// may add to these, but never remove them.
+///
+/// ```json
+/// {
+///   "node_selector": {"pool": "connectors"},
+///   "tolerations": [{"key": "dedicated", "value": "connectors", "effect": "NoSchedule"}],
+///   "anti_affinity": {"app": "alation"}
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scheduling {
+    /// Node labels that the pod's node MUST have.
+    pub node_selector: BTreeMap<String, String>,
+    /// Taints that the pod tolerates.
+    pub tolerations: Vec<Toleration>,
+    /// Pod labels that the pod MUST NOT share a node with, regardless of their namespace.
+    pub anti_affinity: BTreeMap<String, String>,
+}
+
+/// A Toleration tolerates the taint of the given `key` and `effect` (or every effect, if absent).
+/// If a `value` is given then only taints of that value are tolerated, otherwise every value is.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Toleration {
+    pub key: String,
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub effect: Option<String>,
+}
+
+impl Scheduling {
+    /// Returns the configured defaults.
+    ///
+    /// This function PANICS if any of the environment variables are malformed. Consumers
+    /// SHOULD call this function on program startup so that such a misconfiguration is caught early.
+    pub fn configured() -> Scheduling {
+        let scheduling = Scheduling {
+            node_selector: labels_configured(NODE_SELECTOR),
+            tolerations: tolerations_configured(),
+            anti_affinity: labels_configured(ANTI_AFFINITY),
+        };
+        if let Err(err) = scheduling.validate() {
+            panic!("the configured scheduling defaults are invalid, {}", err)
+        }
+        scheduling
+    }
+
+    /// Validates every label and toleration, returning the first violation found.
+    pub fn validate(&self) -> Result<()> {
+        for (key, value) in self.node_selector.iter().chain(self.anti_affinity.iter()) {
+            if !is_label_key(key) || !is_label_value(value) {
+                return Err(InvalidSchedulingLabel {
+                    label: format!("{}={}", key, value),
+                }
+                .into());
+            }
+        }
+        for toleration in self.tolerations.iter() {
+            let valid = is_label_key(&toleration.key)
+                && toleration.value.as_deref().is_none_or(is_label_value)
+                && toleration
+                    .effect
+                    .as_deref()
+                    .is_none_or(|effect| EFFECTS.contains(&effect));
+            if !valid {
+                return Err(InvalidToleration {
+                    toleration: toleration.to_string(),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns these defaults with the given scheduling added on top of them. Should both
+    /// select the same label, then the given scheduling's value wins.
+    pub fn merge(mut self, other: &Scheduling) -> Scheduling {
+        self.node_selector.extend(other.node_selector.clone());
+        self.anti_affinity.extend(other.anti_affinity.clone());
+        for toleration in other.tolerations.iter() {
+            if !self.tolerations.contains(toleration) {
+                self.tolerations.push(toleration.clone());
+            }
+        }
+        self
+    }
+
+    /// Renders this scheduling into the given pod spec.
+    pub fn apply(&self, spec: &mut PodSpec) {
+        if !self.node_selector.is_empty() {
+            spec.node_selector = Some(self.node_selector.clone());
+        }
+        if !self.tolerations.is_empty() {
+            spec.tolerations = Some(self.tolerations.iter().map(Toleration::render).collect());
+        }
+        if !self.anti_affinity.is_empty() {
+            spec.affinity = Some(Affinity {
+                pod_anti_affinity: Some(PodAntiAffinity {
+                    required_during_scheduling_ignored_during_execution: Some(vec![
+                        PodAffinityTerm {
+                            label_selector: Some(LabelSelector {
+                                match_labels: Some(self.anti_affinity.clone()),
+                                match_expressions: None,
+                            }),
+                            // An empty selector selects every namespace, as Alation's core
+                            // services never share a namespace with connectors.
+                            namespace_selector: Some(LabelSelector::default()),
+                            namespaces: None,
+                            topology_key: ANTI_AFFINITY_TOPOLOGY.to_string(),
+                        },
+                    ]),
+                    preferred_during_scheduling_ignored_during_execution: None,
+                }),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+impl Toleration {
+    /// Returns whether this toleration tolerates the given taint, exactly as the scheduler would.
+    pub fn tolerates(&self, taint: &k8s_openapi::api::core::v1::Taint) -> bool {
+        self.key == taint.key
+            && self
+                .value
+                .as_ref()
+                .is_none_or(|value| taint.value.as_ref() == Some(value))
+            && self
+                .effect
+                .as_ref()
+                .is_none_or(|effect| *effect == taint.effect)
+    }
+
+    fn render(&self) -> k8s_openapi::api::core::v1::Toleration {
+        k8s_openapi::api::core::v1::Toleration {
+            key: Some(self.key.clone()),
+            operator: Some(
+                match self.value {
+                    Some(_) => "Equal",
+                    None => "Exists",
+                }
+                .to_string(),
+            ),
+            value: self.value.clone(),
+            effect: self.effect.clone(),
+            toleration_seconds: None,
+        }
+    }
+}
+
+impl std::fmt::Display for Toleration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.key)?;
+        if let Some(value) = self.value.as_ref() {
+            write!(f, "={}", value)?;
+        }
+        if let Some(effect) = self.effect.as_ref() {
+            write!(f, ":{}", effect)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Toleration {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        let (taint, effect) = match s.rsplit_once(':') {
+            Some((taint, effect)) => (taint, Some(effect.to_string())),
+            None => (s, None),
+        };
+        let (key, value) = match taint.split_once('=') {
+            Some((key, value)) => (key, Some(value.to_string())),
+            None => (taint, None),
+        };
+        if key.is_empty() {
+            return Err(());
+        }
+        Ok(Toleration {
+            key: key.to_string(),
+            value,
+            effect,
+        })
+    }
+}
+
+fn labels_configured(var: &str) -> BTreeMap<String, String> {
+    let value = std::env::var(var).unwrap_or_default();
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|label| !label.is_empty())
+        .map(|label| match label.split_once('=') {
+            Some((key, value)) => (key.trim().to_string(), value.trim().to_string()),
+            None => panic!(
+                "the {} environment variable was set to '{}'. It must be a comma separated \
+                list of <key>=<value> labels",
+                var, value
+            ),
+        })
+        .collect()
+}
+
+fn tolerations_configured() -> Vec<Toleration> {
+    let value = std::env::var(TOLERATIONS).unwrap_or_default();
+    value
+        .split(',')
+        .filter(|toleration| !toleration.trim().is_empty())
+        .map(|toleration| {
+            toleration.parse().unwrap_or_else(|_| {
+                panic!(
+                    "the {} environment variable was set to '{}'. It must be a comma separated \
+                    list of <key>=<value>:<effect> or <key>:<effect> taints",
+                    TOLERATIONS, value
+                )
+            })
+        })
+        .collect()
+}
+
+/// Whether the given key is a Kubernetes label key. That is, a name of at most 63 characters
+/// optionally prefixed by a DNS subdomain and a `/`.
+fn is_label_key(key: &str) -> bool {
+    let (prefix, name) = match key.split_once('/') {
+        Some((prefix, name)) => (Some(prefix), name),
+        None => (None, key),
+    };
+    let prefix_ok = prefix.is_none_or(|prefix| {
+        prefix.len() <= 253
+            && prefix.split('.').all(|label| {
+                !label.is_empty()
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            })
+    });
+    prefix_ok && !name.is_empty() && is_label_value(name)
+}
+
+/// Whether the given value is a Kubernetes label value. That is, at most 63 alphanumerics,
+/// dashes, underscores, and dots which begin and end with an alphanumeric (or is empty).
+fn is_label_value(value: &str) -> bool {
+    value.is_empty()
+        || (value.len() <= 63
+            && value.starts_with(|c: char| c.is_ascii_alphanumeric())
+            && value.ends_with(|c: char| c.is_ascii_alphanumeric())
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'))
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "'{label}' is not a valid label to schedule by. Keys must be an optionally prefixed name \
+(such as 'example.com/pool') and values may be at most 63 letters, digits, '-', '_', and '.'."
+)]
+#[error_code("K8S-1209")]
+pub struct InvalidSchedulingLabel {
+    label: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "'{toleration}' is not a valid toleration. Tolerations must have a valid label key, an \
+optional valid label value, and an optional effect of NoSchedule, PreferNoSchedule, or NoExecute."
+)]
+#[error_code("K8S-1210")]
+pub struct InvalidToleration {
+    toleration: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::iter::FromIterator;
+
+    #[test]
+    fn tolerations() {
+        let toleration: Toleration = "dedicated=connectors:NoSchedule".parse().unwrap();
+        assert_eq!(toleration.key, "dedicated");
+        assert_eq!(toleration.value.as_deref(), Some("connectors"));
+        assert_eq!(toleration.effect.as_deref(), Some("NoSchedule"));
+        assert_eq!(toleration.to_string(), "dedicated=connectors:NoSchedule");
+        assert_eq!(toleration.render().operator.as_deref(), Some("Equal"));
+        let toleration: Toleration = "example.com/gpu".parse().unwrap();
+        assert_eq!(toleration.value, None);
+        assert_eq!(toleration.effect, None);
+        assert_eq!(toleration.render().operator.as_deref(), Some("Exists"));
+        assert!(":NoSchedule".parse::<Toleration>().is_err());
+        let scheduling = Scheduling {
+            tolerations: vec!["dedicated=connectors:NoSchedul".parse().unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(
+            scheduling.validate().unwrap_err().error_code(),
+            Some("K8S-1210")
+        );
+    }
+
+    #[test]
+    fn labels() {
+        for valid in [
+            "pool",
+            "example.com/pool",
+            "node.kubernetes.io/instance-type",
+        ] {
+            assert!(is_label_key(valid), "{}", valid);
+        }
+        for invalid in ["", "/pool", "Example.com/pool", "pool/", "-pool"] {
+            assert!(!is_label_key(invalid), "{}", invalid);
+        }
+        for valid in ["", "connectors", "m5.large", "A_b-c"] {
+            assert!(is_label_value(valid), "{}", valid);
+        }
+        let long = "a".repeat(64);
+        for invalid in ["-a", "a-", "a b", long.as_str()] {
+            assert!(!is_label_value(invalid), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn merge_and_apply() {
+        let defaults = Scheduling {
+            node_selector: BTreeMap::from_iter([("pool".to_string(), "connectors".to_string())]),
+            tolerations: vec!["dedicated=connectors:NoSchedule".parse().unwrap()],
+            anti_affinity: BTreeMap::new(),
+        };
+        let requested: Scheduling = serde_json::from_str(
+            r#"{
+                "node_selector": {"zone": "a"},
+                "tolerations": [{"key": "dedicated", "value": "connectors", "effect": "NoSchedule"}],
+                "anti_affinity": {"app": "alation"}
+            }"#,
+        )
+        .unwrap();
+        assert!(requested.validate().is_ok());
+        let scheduling = defaults.merge(&requested);
+        assert_eq!(scheduling.node_selector.len(), 2);
+        assert_eq!(scheduling.tolerations.len(), 1);
+        let mut spec = PodSpec::default();
+        scheduling.apply(&mut spec);
+        assert_eq!(
+            spec.node_selector.unwrap().get("zone"),
+            Some(&"a".to_string())
+        );
+        assert_eq!(spec.tolerations.unwrap().len(), 1);
+        let term = &spec
+            .affinity
+            .unwrap()
+            .pod_anti_affinity
+            .unwrap()
+            .required_during_scheduling_ignored_during_execution
+            .unwrap()[0];
+        assert_eq!(term.topology_key, ANTI_AFFINITY_TOPOLOGY);
+        assert_eq!(
+            term.label_selector.as_ref().unwrap().match_labels,
+            Some(BTreeMap::from_iter([(
+                "app".to_string(),
+                "alation".to_string()
+            )]))
+        );
+        let mut spec = PodSpec::default();
+        Scheduling::default().apply(&mut spec);
+        assert_eq!(spec, PodSpec::default());
+    }
+}
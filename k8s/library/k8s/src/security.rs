@@ -0,0 +1,244 @@
+use error::*;
+use k8s_openapi::api::core::v1::{
+    Capabilities, Container, PodSecurityContext, PodSpec, SeccompProfile, SecurityContext,
+};
+use result::Result;
+use serde::{Deserialize, Serialize};
+
+/// The environment variable that configures the UID that connector containers run as.
+pub const RUN_AS_USER: &str = "RUN_AS_USER";
+
+/// The environment variable that, when `true`, allows deploys to [override](Security) the
+/// hardened security context of their pod. This is `false` by default.
+pub const ALLOW_SECURITY_OVERRIDES: &str = "ALLOW_SECURITY_OVERRIDES";
+
+/// The UID that connector containers run as unless [configured](RUN_AS_USER) otherwise.
+pub const DEFAULT_RUN_AS_USER: i64 = 1000;
+
+/// The seccomp profiles that a deploy may [request](Security::seccomp_profile).
+pub const SECCOMP_PROFILES: &[&str] = &["RuntimeDefault", "Unconfined"];
+
+/// The SecurityPolicy is the operator's configuration of the security context that every
+/// connector pod is hardened with. That is, connectors:
+///
+/// * run as the non-root [RUN_AS_USER](RUN_AS_USER),
+/// * have a read-only root filesystem (writable space may be had via scratch volumes),
+/// * may not escalate their privileges and have every Linux capability dropped, and
+/// * are confined by the container runtime's default seccomp profile.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SecurityPolicy {
+    pub run_as_user: i64,
+    pub allow_overrides: bool,
+}
+
+/// Security is a deploy's override of the [SecurityPolicy](SecurityPolicy). Overrides are refused
+/// unless the operator has [allowed](ALLOW_SECURITY_OVERRIDES) them.
+///
+/// ```json
+/// {"run_as_user": 0, "read_only_root_filesystem": false, "seccomp_profile": "Unconfined"}
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Security {
+    pub run_as_user: Option<i64>,
+    pub read_only_root_filesystem: Option<bool>,
+    pub seccomp_profile: Option<String>,
+}
+
+impl Default for SecurityPolicy {
+    fn default() -> Self {
+        SecurityPolicy {
+            run_as_user: DEFAULT_RUN_AS_USER,
+            allow_overrides: false,
+        }
+    }
+}
+
+impl SecurityPolicy {
+    /// Returns the configured SecurityPolicy.
+    ///
+    /// This function PANICS if either environment variable is set to an unparseable value.
+    /// Consumers SHOULD call this function on program startup so that such a misconfiguration
+    /// is caught early.
+    pub fn configured() -> SecurityPolicy {
+        let run_as_user = match std::env::var(RUN_AS_USER) {
+            Ok(value) if !value.trim().is_empty() => value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|uid| *uid > 0)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "the {} environment variable was set to '{}'. It must be a positive \
+                        integer as connectors may not run as root",
+                        RUN_AS_USER, value
+                    )
+                }),
+            _ => DEFAULT_RUN_AS_USER,
+        };
+        let allow_overrides = match std::env::var(ALLOW_SECURITY_OVERRIDES) {
+            Ok(value) if !value.trim().is_empty() => {
+                value.trim().to_lowercase().parse().unwrap_or_else(|_| {
+                    panic!(
+                        "the {} environment variable was set to '{}'. It can be one of either \
+                        true or false",
+                        ALLOW_SECURITY_OVERRIDES, value
+                    )
+                })
+            }
+            _ => false,
+        };
+        SecurityPolicy {
+            run_as_user,
+            allow_overrides,
+        }
+    }
+
+    /// Validates a deploy's overrides against this policy.
+    pub fn validate(&self, overrides: &Security) -> Result<()> {
+        if *overrides != Security::default() && !self.allow_overrides {
+            return Err(SecurityOverridesNotAllowed {}.into());
+        }
+        if let Some(profile) = overrides.seccomp_profile.as_ref() {
+            if !SECCOMP_PROFILES.contains(&profile.as_str()) {
+                return Err(InvalidSeccompProfile {
+                    profile: profile.clone(),
+                }
+                .into());
+            }
+        }
+        if overrides.run_as_user.is_some_and(|uid| uid < 0) {
+            return Err(InvalidRunAsUser {
+                uid: overrides.run_as_user.unwrap_or_default(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Renders this policy, with the given overrides, into the pod's spec and the security
+    /// context of each of its containers. The overrides are assumed to have been
+    /// [validated](SecurityPolicy::validate).
+    pub fn apply(&self, overrides: &Security, spec: &mut PodSpec) {
+        let run_as_user = overrides.run_as_user.unwrap_or(self.run_as_user);
+        let seccomp_profile = SeccompProfile {
+            localhost_profile: None,
+            type_: overrides
+                .seccomp_profile
+                .clone()
+                .unwrap_or_else(|| "RuntimeDefault".to_string()),
+        };
+        spec.security_context = Some(PodSecurityContext {
+            run_as_user: Some(run_as_user),
+            run_as_non_root: Some(run_as_user != 0),
+            seccomp_profile: Some(seccomp_profile),
+            ..Default::default()
+        });
+        for container in spec.containers.iter_mut() {
+            harden(container, overrides);
+        }
+    }
+}
+
+fn harden(container: &mut Container, overrides: &Security) {
+    container.security_context = Some(SecurityContext {
+        allow_privilege_escalation: Some(false),
+        privileged: Some(false),
+        read_only_root_filesystem: Some(overrides.read_only_root_filesystem.unwrap_or(true)),
+        capabilities: Some(Capabilities {
+            add: None,
+            drop: Some(vec!["ALL".to_string()]),
+        }),
+        ..Default::default()
+    });
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::Forbidden)]
+#[error(
+    "This deploy requested overrides to the security context of its pod, however the operator of \
+this cluster has not allowed such overrides."
+)]
+#[error_code("K8S-1211")]
+pub struct SecurityOverridesNotAllowed {}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "'{profile}' is not a supported seccomp profile. It can be one of either RuntimeDefault or \
+Unconfined."
+)]
+#[error_code("K8S-1212")]
+pub struct InvalidSeccompProfile {
+    profile: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error("{uid} is not a valid UID to run a connector as.")]
+#[error_code("K8S-1213")]
+pub struct InvalidRunAsUser {
+    uid: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> PodSpec {
+        PodSpec {
+            containers: vec![Container::default()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn hardened() {
+        let mut spec = spec();
+        SecurityPolicy::default().apply(&Security::default(), &mut spec);
+        let pod = spec.security_context.unwrap();
+        assert_eq!(pod.run_as_user, Some(DEFAULT_RUN_AS_USER));
+        assert_eq!(pod.run_as_non_root, Some(true));
+        assert_eq!(pod.seccomp_profile.unwrap().type_, "RuntimeDefault");
+        let container = spec.containers[0].security_context.clone().unwrap();
+        assert_eq!(container.read_only_root_filesystem, Some(true));
+        assert_eq!(container.allow_privilege_escalation, Some(false));
+        assert_eq!(
+            container.capabilities.unwrap().drop,
+            Some(vec!["ALL".to_string()])
+        );
+    }
+
+    #[test]
+    fn overrides() {
+        let overrides = Security {
+            run_as_user: Some(0),
+            read_only_root_filesystem: Some(false),
+            seccomp_profile: Some("Unconfined".to_string()),
+        };
+        let err = SecurityPolicy::default().validate(&overrides).unwrap_err();
+        assert_eq!(err.error_code(), Some("K8S-1211"));
+        let policy = SecurityPolicy {
+            allow_overrides: true,
+            ..Default::default()
+        };
+        assert!(policy.validate(&Security::default()).is_ok());
+        assert!(policy.validate(&overrides).is_ok());
+        let mut spec = spec();
+        policy.apply(&overrides, &mut spec);
+        let pod = spec.security_context.unwrap();
+        assert_eq!(pod.run_as_user, Some(0));
+        assert_eq!(pod.run_as_non_root, Some(false));
+        assert_eq!(pod.seccomp_profile.unwrap().type_, "Unconfined");
+        let container = spec.containers[0].security_context.clone().unwrap();
+        assert_eq!(container.read_only_root_filesystem, Some(false));
+        let invalid = Security {
+            seccomp_profile: Some("Localhost".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.validate(&invalid).unwrap_err().error_code(),
+            Some("K8S-1212")
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use crate::errors::ApiError;
+use crate::{client, POD_LABEL};
+use error::*;
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::ListParams;
+use kube::{Api, ResourceExt};
+use result::Result;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// A Selector selects connector pods by their labels.
+///
+/// A Selector is a Kubernetes [label selector](https://kubernetes.io/docs/concepts/overview/working-with-objects/labels/#label-selectors)
+/// (such as `servicer=acm-abcd12345` or `ttl in (60,120)`) that may additionally compare labels
+/// as integers via `<` and `>` (such as `execution_date<1632264721`). Kubernetes has no such
+/// operators, so comparisons are evaluated against the pods that the rest of the selector returns.
+/// A pod that lacks a compared label (or whose label is not an integer) is not selected.
+///
+/// Only connector pods (those labeled with [POD_LABEL](POD_LABEL)) are ever selected, so that not
+/// even an empty selector reaches beyond the connectors.
+///
+/// ```
+/// use k8s::selector::Selector;
+///
+/// let selector: Selector = "servicer=acm-abcd12345,execution_date<1632264721".parse().unwrap();
+/// assert_eq!(selector.labels(), "ocf.alation.com/pod,servicer=acm-abcd12345");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Selector {
+    requirements: Vec<String>,
+    comparisons: Vec<Comparison>,
+}
+
+/// A Comparison of a label, as an integer, against a bound.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Comparison {
+    label: String,
+    less_than: bool,
+    bound: i64,
+}
+
+impl Comparison {
+    fn matches(&self, pod: &Pod) -> bool {
+        pod.labels()
+            .get(&self.label)
+            .and_then(|value| value.parse::<i64>().ok())
+            .is_some_and(|value| {
+                if self.less_than {
+                    value < self.bound
+                } else {
+                    value > self.bound
+                }
+            })
+    }
+}
+
+impl Selector {
+    /// Returns the label selector that is given to Kubernetes, which is every requirement save
+    /// for the comparisons.
+    pub fn labels(&self) -> String {
+        std::iter::once(POD_LABEL.to_string())
+            .chain(self.requirements.iter().cloned())
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    /// Returns whether the given pod satisfies every comparison of this selector.
+    pub fn matches(&self, pod: &Pod) -> bool {
+        self.comparisons
+            .iter()
+            .all(|comparison| comparison.matches(pod))
+    }
+
+    /// Lists every connector pod within the given namespace that this selector selects.
+    pub async fn list<N: AsRef<str>>(&self, namespace: N) -> Result<Vec<Pod>> {
+        let client: Api<Pod> = client::new_with_namespace(namespace.as_ref()).await;
+        let pods = client
+            .list(&ListParams::default().labels(&self.labels()))
+            .await
+            .map_err(ApiError::from)?;
+        Ok(pods.into_iter().filter(|pod| self.matches(pod)).collect())
+    }
+}
+
+impl FromStr for Selector {
+    type Err = MalformedSelector;
+
+    fn from_str(selector: &str) -> std::result::Result<Self, Self::Err> {
+        let malformed = |reason: &str| MalformedSelector {
+            selector: selector.to_string(),
+            reason: reason.to_string(),
+        };
+        let mut requirements = vec![];
+        let mut comparisons = vec![];
+        for requirement in split(selector).map_err(malformed)? {
+            let comparison = requirement
+                .split_once('<')
+                .map(|(label, bound)| (label, true, bound))
+                .or_else(|| {
+                    requirement
+                        .split_once('>')
+                        .map(|(label, bound)| (label, false, bound))
+                });
+            match comparison {
+                Some((label, less_than, bound)) => {
+                    let label = label.trim();
+                    if label.is_empty() {
+                        return Err(malformed("a comparison is missing its label"));
+                    }
+                    let bound = bound
+                        .trim()
+                        .parse::<i64>()
+                        .map_err(|_| malformed("labels may only be compared against integers"))?;
+                    comparisons.push(Comparison {
+                        label: label.to_string(),
+                        less_than,
+                        bound,
+                    });
+                }
+                None => requirements.push(requirement),
+            }
+        }
+        Ok(Selector {
+            requirements,
+            comparisons,
+        })
+    }
+}
+
+impl Display for Selector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let comparisons = self.comparisons.iter().map(|comparison| {
+            format!(
+                "{}{}{}",
+                comparison.label,
+                if comparison.less_than { '<' } else { '>' },
+                comparison.bound
+            )
+        });
+        let all: Vec<String> = self
+            .requirements
+            .iter()
+            .cloned()
+            .chain(comparisons)
+            .collect();
+        write!(f, "{}", all.join(","))
+    }
+}
+
+/// Splits the given selector into its requirements, which are separated by commas that are not
+/// within the parentheses of a set (such as `ttl in (60,120)`).
+fn split(selector: &str) -> std::result::Result<Vec<String>, &'static str> {
+    let mut requirements = vec![];
+    let mut depth = 0;
+    let mut requirement = String::new();
+    for c in selector.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err("a set is closed without being opened"),
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                requirements.push(std::mem::take(&mut requirement));
+                continue;
+            }
+            _ => (),
+        }
+        requirement.push(c);
+    }
+    if depth > 0 {
+        return Err("a set is opened without being closed");
+    }
+    requirements.push(requirement);
+    Ok(requirements
+        .into_iter()
+        .map(|requirement| requirement.trim().to_string())
+        .filter(|requirement| !requirement.is_empty())
+        .collect())
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error("The selector '{selector}' is malformed, {reason}.")]
+#[error_code("K8S-1220")]
+pub struct MalformedSelector {
+    selector: String,
+    reason: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::fixtures::{labeled, pod};
+
+    #[test]
+    fn parses() {
+        let selector: Selector = "servicer=acm, ttl in (60,120), execution_date<100, b>-5"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            selector.labels(),
+            "ocf.alation.com/pod,servicer=acm,ttl in (60,120)"
+        );
+        assert_eq!(
+            selector.to_string(),
+            "servicer=acm,ttl in (60,120),execution_date<100,b>-5"
+        );
+        assert_eq!("".parse::<Selector>().unwrap().labels(), POD_LABEL);
+    }
+
+    #[test]
+    fn compares() {
+        let selector: Selector = "execution_date<100".parse().unwrap();
+        assert!(selector.matches(&labeled(
+            pod("oracle", "Running"),
+            &[("execution_date", "99")]
+        )));
+        assert!(!selector.matches(&labeled(
+            pod("oracle", "Running"),
+            &[("execution_date", "100")]
+        )));
+        assert!(!selector.matches(&labeled(
+            pod("oracle", "Running"),
+            &[("execution_date", "soon")]
+        )));
+        assert!(!selector.matches(&labeled(pod("oracle", "Running"), &[])));
+        let selector: Selector = "ttl>60".parse().unwrap();
+        assert!(selector.matches(&labeled(pod("oracle", "Running"), &[("ttl", "61")])));
+    }
+
+    #[test]
+    fn rejects() {
+        for malformed in &["ttl<soon", "<5", "ttl in (60,120", "ttl)"] {
+            assert!(malformed.parse::<Selector>().is_err(), "{}", malformed);
+        }
+    }
+}
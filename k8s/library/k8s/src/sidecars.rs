@@ -0,0 +1,165 @@
+use error::*;
+use k8s_openapi::api::core::v1::{Container, PodSpec};
+use result::Result;
+use std::collections::BTreeMap;
+
+/// The environment variable that configures the sidecars that deploys may enable, given as a JSON
+/// object of sidecar names to Kubernetes container specs. For example,
+///
+/// ```json
+/// {"fluent-bit": {"image": "fluent/fluent-bit:1.8", "args": ["-c", "/fluent-bit/etc/fluent-bit.conf"]}}
+/// ```
+///
+/// The name of each container is always that of its sidecar, regardless of any `name` given in
+/// its spec.
+pub const SIDECARS: &str = "SIDECARS";
+
+/// The port that the connector's own container listens on unless its deploy
+/// [asks](crate::options::PodOptions::port) for another, which no sidecar may claim.
+pub const CONNECTOR_PORT: i32 = 8080;
+
+/// Returns the configured sidecar templates, keyed by name.
+///
+/// This function PANICS if the environment variable is not a JSON object of valid container
+/// specs. Consumers SHOULD call this function on program startup so that such a misconfiguration
+/// is caught early.
+pub fn configured() -> BTreeMap<String, Container> {
+    match std::env::var(SIDECARS) {
+        Ok(value) if !value.trim().is_empty() => parse(&value),
+        _ => BTreeMap::new(),
+    }
+}
+
+fn parse(value: &str) -> BTreeMap<String, Container> {
+    let templates: BTreeMap<String, serde_json::Value> = serde_json::from_str(value)
+        .unwrap_or_else(|err| {
+            panic!(
+                "the {} environment variable is not a JSON object of sidecar names to container \
+                specs, {}",
+                SIDECARS, err
+            )
+        });
+    templates
+        .into_iter()
+        .map(|(name, mut template)| {
+            if let Some(template) = template.as_object_mut() {
+                template.insert("name".to_string(), serde_json::Value::from(name.clone()));
+            }
+            let container: Container = serde_json::from_value(template).unwrap_or_else(|err| {
+                panic!(
+                    "the sidecar '{}' within the {} environment variable is not a valid container \
+                    spec, {}",
+                    name, SIDECARS, err
+                )
+            });
+            let mut ports = container.ports.iter().flatten();
+            if ports.any(|port| port.container_port == CONNECTOR_PORT) {
+                panic!(
+                    "the sidecar '{}' within the {} environment variable listens on port {}, \
+                    which is reserved for the connector itself",
+                    name, SIDECARS, CONNECTOR_PORT
+                )
+            }
+            (name, container)
+        })
+        .collect()
+}
+
+/// Validates that every requested sidecar has been [configured](configured).
+pub fn validate(requested: &[String]) -> Result<()> {
+    if requested.is_empty() {
+        return Ok(());
+    }
+    let templates = configured();
+    match requested.iter().find(|name| !templates.contains_key(*name)) {
+        Some(name) => Err(UnknownSidecar {
+            sidecar: name.clone(),
+            configured: templates
+                .keys()
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(", "),
+        }
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Returns every port that the requested sidecars listen on, alongside the name of the sidecar.
+pub fn ports(requested: &[String]) -> Vec<(String, i32)> {
+    if requested.is_empty() {
+        return vec![];
+    }
+    let templates = configured();
+    requested
+        .iter()
+        .filter_map(|name| templates.get(name).map(|sidecar| (name, sidecar)))
+        .flat_map(|(name, sidecar)| {
+            sidecar
+                .ports
+                .iter()
+                .flatten()
+                .map(move |port| (name.clone(), port.container_port))
+        })
+        .collect()
+}
+
+/// Appends the requested sidecars to the given pod spec, AFTER the connector's own container so
+/// that the connector remains the first container (whose port is the one that is health checked).
+/// Sidecars that have not been configured are skipped, as are sidecars requested more than once.
+pub fn apply(requested: &[String], spec: &mut PodSpec) {
+    if requested.is_empty() {
+        return;
+    }
+    let mut templates = configured();
+    for name in requested {
+        if let Some(sidecar) = templates.remove(name) {
+            spec.containers.push(sidecar);
+        }
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The sidecar '{sidecar}' has not been configured. The configured sidecars are [{configured}]."
+)]
+#[error_code("K8S-1215")]
+pub struct UnknownSidecar {
+    sidecar: String,
+    configured: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unconfigured() {
+        assert!(validate(&[]).is_ok());
+        let err = validate(&["fluent-bit".to_string()]).unwrap_err();
+        assert_eq!(err.error_code(), Some("K8S-1215"));
+        let mut spec = PodSpec {
+            containers: vec![Container::default()],
+            ..Default::default()
+        };
+        apply(&["fluent-bit".to_string()], &mut spec);
+        assert_eq!(spec.containers.len(), 1);
+    }
+
+    #[test]
+    fn templates() {
+        let templates = parse(
+            r#"{"fluent-bit": {"name": "ignored", "image": "fluent/fluent-bit:1.8", "ports": [{"containerPort": 2020}]}}"#,
+        );
+        let sidecar = templates.get("fluent-bit").unwrap();
+        assert_eq!(sidecar.name, "fluent-bit");
+        assert_eq!(sidecar.image.as_deref(), Some("fluent/fluent-bit:1.8"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn reserved_port() {
+        parse(r#"{"proxy": {"image": "envoy", "ports": [{"containerPort": 8080}]}}"#);
+    }
+}
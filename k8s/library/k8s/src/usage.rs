@@ -0,0 +1,283 @@
+use crate::errors::ApiError;
+use error::*;
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::api::ObjectMeta;
+use kube::error::ErrorResponse;
+use kube::Api;
+use result::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::iter::FromIterator;
+
+/// The `.metadata.annotations` key under which the peak CPU usage (in millicores, such as `250m`)
+/// of a connector is recorded when it is terminated.
+pub const PEAK_CPU_ANNOTATION: &str = "ocf.alation.com/peak_cpu";
+
+/// The `.metadata.annotations` key under which the peak memory usage (in bytes) of a connector is
+/// recorded when it is terminated.
+pub const PEAK_MEMORY_ANNOTATION: &str = "ocf.alation.com/peak_memory";
+
+/// A PodMetrics is a pod's usage as served by the `metrics.k8s.io` API (typically by the cluster's
+/// metrics-server). k8s-openapi does not ship the metrics API, so it is declared here.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PodMetrics {
+    pub metadata: ObjectMeta,
+    pub timestamp: Option<String>,
+    pub window: Option<String>,
+    #[serde(default)]
+    pub containers: Vec<ContainerMetrics>,
+}
+
+/// The usage of a single container within a [PodMetrics](PodMetrics), keyed by resource
+/// (`cpu` and `memory`).
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ContainerMetrics {
+    pub name: String,
+    #[serde(default)]
+    pub usage: BTreeMap<String, Quantity>,
+}
+
+impl k8s_openapi::Resource for PodMetrics {
+    const API_VERSION: &'static str = "metrics.k8s.io/v1beta1";
+    const GROUP: &'static str = "metrics.k8s.io";
+    const KIND: &'static str = "PodMetrics";
+    const VERSION: &'static str = "v1beta1";
+    const URL_PATH_SEGMENT: &'static str = "pods";
+    type Scope = k8s_openapi::NamespaceResourceScope;
+}
+
+impl k8s_openapi::Metadata for PodMetrics {
+    type Ty = ObjectMeta;
+
+    fn metadata(&self) -> &ObjectMeta {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut ObjectMeta {
+        &mut self.metadata
+    }
+}
+
+/// The current CPU (in millicores) and memory (in bytes) usage of a connector pod, in total and
+/// per container. The `timestamp` and `window` are those of the sample, as reported by Kubernetes.
+#[derive(Serialize, Deserialize, Kind, Debug, Clone, Default, Eq, PartialEq)]
+pub struct Usage {
+    pub cpu_millicores: u64,
+    pub memory_bytes: u64,
+    pub containers: Vec<ContainerUsage>,
+    pub timestamp: Option<String>,
+    pub window: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct ContainerUsage {
+    pub name: String,
+    pub cpu_millicores: u64,
+    pub memory_bytes: u64,
+}
+
+impl From<PodMetrics> for Usage {
+    fn from(metrics: PodMetrics) -> Self {
+        let containers: Vec<ContainerUsage> = metrics
+            .containers
+            .iter()
+            .map(|container| {
+                let usage = |resource: &str| {
+                    container
+                        .usage
+                        .get(resource)
+                        .and_then(|quantity| parse_quantity(&quantity.0))
+                        .unwrap_or(0.0)
+                };
+                ContainerUsage {
+                    name: container.name.clone(),
+                    cpu_millicores: (usage("cpu") * 1000.0).ceil() as u64,
+                    memory_bytes: usage("memory").round() as u64,
+                }
+            })
+            .collect();
+        Usage {
+            cpu_millicores: containers.iter().map(|c| c.cpu_millicores).sum(),
+            memory_bytes: containers.iter().map(|c| c.memory_bytes).sum(),
+            containers,
+            timestamp: metrics.timestamp,
+            window: metrics.window,
+        }
+    }
+}
+
+/// The highest CPU and memory usage observed of a connector pod over its lifetime. Either may
+/// have been observed at a different time than the other.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Peak {
+    pub cpu_millicores: u64,
+    pub memory_bytes: u64,
+}
+
+impl Peak {
+    /// Raises this peak to include the given usage.
+    pub fn observe(&mut self, usage: &Usage) {
+        self.cpu_millicores = self.cpu_millicores.max(usage.cpu_millicores);
+        self.memory_bytes = self.memory_bytes.max(usage.memory_bytes);
+    }
+
+    /// Returns whether nothing has been observed.
+    pub fn is_empty(&self) -> bool {
+        *self == Peak::default()
+    }
+
+    /// Returns the annotations under which this peak is [recorded](record_peak).
+    pub fn annotations(&self) -> BTreeMap<String, String> {
+        BTreeMap::from_iter([
+            (
+                PEAK_CPU_ANNOTATION.to_string(),
+                format!("{}m", self.cpu_millicores),
+            ),
+            (
+                PEAK_MEMORY_ANNOTATION.to_string(),
+                self.memory_bytes.to_string(),
+            ),
+        ])
+    }
+}
+
+/// Returns the current usage of the pod of the given ID (within the given namespace).
+///
+/// Should Kubernetes have no metrics for the pod, either because the `metrics.k8s.io` API is not
+/// served by the cluster or because the pod has only just started, then a
+/// [MetricsUnavailable](MetricsUnavailable) is returned.
+pub async fn usage<S: AsRef<str>, I: AsRef<str>>(namespace: S, id: I) -> Result<Usage> {
+    let client: Api<PodMetrics> = crate::client::new_with_namespace(namespace.as_ref()).await;
+    match client.get(id.as_ref()).await {
+        Ok(metrics) => Ok(metrics.into()),
+        Err(kube::error::Error::Api(ErrorResponse { code: 404, .. }))
+        | Err(kube::error::Error::Api(ErrorResponse { code: 503, .. })) => {
+            Err(MetricsUnavailable {
+                id: id.as_ref().to_string(),
+            }
+            .into())
+        }
+        Err(err) => Err(ApiError::from(err).into()),
+    }
+}
+
+/// Annotates the pod of the given ID (within the given namespace) with the given peak usage, under
+/// [PEAK_CPU_ANNOTATION](PEAK_CPU_ANNOTATION) and [PEAK_MEMORY_ANNOTATION](PEAK_MEMORY_ANNOTATION).
+/// A pod that is already gone is not an error.
+pub async fn record_peak<S: AsRef<str>, I: AsRef<str>>(
+    namespace: S,
+    id: I,
+    peak: &Peak,
+) -> Result<()> {
+    crate::annotate(namespace, id, peak.annotations()).await
+}
+
+/// Parses a Kubernetes [Quantity](Quantity) (such as `250m`, `12345n`, `64Mi`, or `1e3`) into its
+/// value in base units (cores, bytes, etc.).
+pub fn parse_quantity(quantity: &str) -> Option<f64> {
+    let quantity = quantity.trim();
+    let split = quantity
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(quantity.len());
+    let (number, suffix) = quantity.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let multiplier = match suffix {
+        "" => 1.0,
+        "n" => 1e-9,
+        "u" => 1e-6,
+        "m" => 1e-3,
+        "k" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        "T" => 1e12,
+        "P" => 1e15,
+        "E" => 1e18,
+        "Ki" => 1024f64,
+        "Mi" => 1024f64.powi(2),
+        "Gi" => 1024f64.powi(3),
+        "Ti" => 1024f64.powi(4),
+        "Pi" => 1024f64.powi(5),
+        "Ei" => 1024f64.powi(6),
+        exponent => 10f64.powi(
+            exponent
+                .strip_prefix(|c| c == 'e' || c == 'E')?
+                .parse()
+                .ok()?,
+        ),
+    };
+    Some(number * multiplier)
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::ServiceUnavailable)]
+#[error(
+    "Kubernetes has no resource metrics for the pod {id}. Either the pod has only just started \
+(metrics are typically sampled every 15 seconds) or the cluster does not serve the metrics.k8s.io \
+API, which is usually provided by installing metrics-server."
+)]
+#[error_code("K8S-1217")]
+pub struct MetricsUnavailable {
+    id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approximately(quantity: &str, expected: f64) -> bool {
+        (parse_quantity(quantity).unwrap() - expected).abs() < 1e-9
+    }
+
+    #[test]
+    fn quantities() {
+        assert!(approximately("250m", 0.25));
+        assert!(approximately("2", 2.0));
+        assert!(approximately("64Mi", 64.0 * 1024.0 * 1024.0));
+        assert!(approximately("1k", 1000.0));
+        assert!(approximately("1e3", 1000.0));
+        assert!(approximately("1.5Gi", 1.5 * 1024.0 * 1024.0 * 1024.0));
+        assert!(approximately("12345678n", 0.012345678));
+        assert_eq!(parse_quantity("lots"), None);
+        assert_eq!(parse_quantity("1Zi"), None);
+    }
+
+    #[test]
+    fn usage_and_peak() {
+        let metrics: PodMetrics = serde_json::from_value(serde_json::json!({
+            "metadata": {"name": "oracle-abcd1234"},
+            "timestamp": "2021-09-21T22:52:01Z",
+            "window": "15s",
+            "containers": [
+                {"name": "oracle", "usage": {"cpu": "12345678n", "memory": "64Mi"}},
+                {"name": "fluent-bit", "usage": {"cpu": "1m", "memory": "1024Ki"}}
+            ]
+        }))
+        .unwrap();
+        let usage = Usage::from(metrics);
+        assert_eq!(usage.containers[0].cpu_millicores, 13);
+        assert_eq!(usage.cpu_millicores, 14);
+        assert_eq!(usage.memory_bytes, 65 * 1024 * 1024);
+        assert_eq!(usage.window.as_deref(), Some("15s"));
+        let mut peak = Peak::default();
+        assert!(peak.is_empty());
+        peak.observe(&usage);
+        peak.observe(&Usage {
+            cpu_millicores: 500,
+            memory_bytes: 1,
+            ..Default::default()
+        });
+        assert_eq!(
+            peak,
+            Peak {
+                cpu_millicores: 500,
+                memory_bytes: 65 * 1024 * 1024
+            }
+        );
+        assert_eq!(
+            peak.annotations()
+                .get(PEAK_CPU_ANNOTATION)
+                .map(String::as_str),
+            Some("500m")
+        );
+    }
+}
@@ -1,7 +1,22 @@
 //! Watches a Kubernetes Resource for changes, with error recovery
+//!
+//! Beyond the raw [watcher](watcher), this module offers
+//!
+//! * [Resumption](watcher_from) of a watch from the [resourceVersion](Watch::resource_version)
+//!   that an earlier watch got to. Bookmarks (which [ListParams](ListParams) asks for by default)
+//!   keep that resourceVersion current even while nothing being watched changes, and a
+//!   resourceVersion that has fallen out of the watch window (a `410 Gone`) is recovered from by
+//!   listing anew.
+//! * [PodEvents](PodEvent), which are the events of a pod typed by what they say of the pod's
+//!   lifecycle rather than by what happened to the object.
+//! * [Buffering](Buffered) that never holds up the producer of the events, but which coalesces
+//!   the modifications of an object that its consumer has fallen behind on.
 
+use crate::pod::PodPhase;
+use crate::PodExt;
 use derivative::Derivative;
 use futures::{stream::BoxStream, Stream, StreamExt};
+use k8s_openapi::api::core::v1::Pod;
 use kube::{
     api::{ListParams, Resource, ResourceExt, WatchEvent},
     Api,
@@ -9,7 +24,12 @@ use kube::{
 use serde::de::DeserializeOwned;
 use smallvec::SmallVec;
 use snafu::{Backtrace, ResultExt, Snafu};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
 use std::{clone::Clone, fmt::Debug};
+use tokio::task::JoinHandle;
 
 #[derive(Snafu, Debug)]
 pub enum Error {
@@ -29,6 +49,7 @@ pub enum Error {
         backtrace: Backtrace,
     },
     #[snafu(display("watch stream failed: {}", source))]
+    #[snafu(visibility = "pub(crate)")]
     WatchFailed {
         source: kube::Error,
         backtrace: Backtrace,
@@ -132,6 +153,19 @@ enum State<K: Resource + Clone> {
     },
 }
 
+impl<K: Resource + Clone> State<K> {
+    /// The resourceVersion that the watch has gotten to, if it has gotten anywhere at all.
+    fn resource_version(&self) -> Option<String> {
+        match self {
+            State::Empty => None,
+            State::InitListed { resource_version }
+            | State::Watching {
+                resource_version, ..
+            } => Some(resource_version.clone()),
+        }
+    }
+}
+
 /// Progresses the watcher a single step, returning (event, state)
 ///
 /// This function should be trampolined: if event == `None`
@@ -160,6 +194,11 @@ async fn step_trampolined<K: Resource + Clone + DeserializeOwned + Debug + Send
                         stream: stream.boxed(),
                     },
                 ),
+                // The resourceVersion has fallen out of the watch window (which is most likely when
+                // resuming from an old one), so start over and re-list.
+                Err(err @ kube::Error::Api(kube::error::ErrorResponse { code: 410, .. })) => {
+                    (Some(Err(err).context(WatchStartFailed)), State::Empty)
+                }
                 Err(err) => (
                     Some(Err(err).context(WatchStartFailed)),
                     State::InitListed { resource_version },
@@ -291,30 +330,375 @@ async fn step<K: Resource + Clone + DeserializeOwned + Debug + Send + 'static>(
 /// that we have seen on the stream. If this is successful then the stream is simply resumed from where it left off.
 /// If this fails because the resource version is no longer valid then we start over with a new stream, starting with
 /// an [`Event::Restarted`].
-// pub fn watcher<K: Resource + Clone + DeserializeOwned + Debug + Send + 'static, S>(
-//     api: Api<K>,
-//     list_params: ListParams,
-// ) -> S
-// where
-//     S: Stream<Item = Result<Event<K>>> + Send,
-// {
-//     futures::stream::unfold(
-//         (api, list_params, State::Empty),
-//         |(api, list_params, state)| async {
-//             let (event, state) = step(&api, &list_params, state).await;
-//             Some((event, (api, list_params, state)))
-//         },
-//     )
-// }
 pub fn watcher<K: Resource + Clone + DeserializeOwned + Debug + Send + 'static>(
     api: Api<K>,
     list_params: ListParams,
-) -> impl Stream<Item = Result<Event<K>>> + Send {
-    futures::stream::unfold(
-        (api, list_params, State::Empty),
+) -> Watch<K> {
+    watcher_from(api, list_params, None)
+}
+
+/// Watches a Kubernetes Resource for changes continuously, just as [watcher](watcher) does, but
+/// resumes from the given resourceVersion (such as the [resource_version](Watch::resource_version)
+/// of an earlier [Watch](Watch)) rather than beginning with a list. Only the changes since that
+/// resourceVersion are seen, and so the watch does NOT begin with an [Event::Restarted].
+///
+/// Should the resourceVersion have fallen out of the watch window, then the watch fails once with
+/// a `410 Gone` and then starts over with a list (and so with an [Event::Restarted]).
+pub fn watcher_from<K: Resource + Clone + DeserializeOwned + Debug + Send + 'static>(
+    api: Api<K>,
+    list_params: ListParams,
+    resource_version: Option<String>,
+) -> Watch<K> {
+    let state = match resource_version {
+        Some(resource_version) => State::InitListed { resource_version },
+        None => State::Empty,
+    };
+    let events = futures::stream::unfold(
+        (api, list_params, state),
         |(api, list_params, state)| async {
             let (event, state) = step(&api, &list_params, state).await;
-            Some((event, (api, list_params, state)))
+            let resource_version = state.resource_version();
+            Some(((event, resource_version), (api, list_params, state)))
         },
     )
+    .boxed();
+    Watch {
+        events,
+        resource_version: None,
+    }
+}
+
+/// A Watch is the [Stream](Stream) of a [watcher](watcher), which remembers the resourceVersion
+/// that it has gotten to so that the watch may later be [resumed](watcher_from) from there.
+pub struct Watch<K> {
+    events: BoxStream<'static, (Result<Event<K>>, Option<String>)>,
+    resource_version: Option<String>,
+}
+
+impl<K> Watch<K> {
+    /// The resourceVersion of the latest event (or bookmark) seen by this Watch. Bookmarks are
+    /// not events, so the resourceVersion of a bookmark is only seen alongside the next event.
+    pub fn resource_version(&self) -> Option<&str> {
+        self.resource_version.as_deref()
+    }
+}
+
+impl<K> Stream for Watch<K> {
+    type Item = Result<Event<K>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let watch = self.get_mut();
+        match watch.events.poll_next_unpin(cx) {
+            Poll::Ready(Some((event, resource_version))) => {
+                if resource_version.is_some() {
+                    watch.resource_version = resource_version;
+                }
+                Poll::Ready(Some(event))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// A PodEvent is an [Event](Event) of a pod typed by what it says of the pod's lifecycle.
+///
+/// A pod that was [added or modified](Event::Applied) is typed by its [phase](PodExt::phase),
+/// which is judged by the connector's container (and not by any sidecar) and by the pod's init
+/// containers.
+///
+/// * [Pending](PodEvent::Pending): The pod is yet to be scheduled.
+/// * [Scheduled](PodEvent::Scheduled): The pod has been bound to a node, but its containers are
+///   yet to be created.
+/// * [Pulling](PodEvent::Pulling): The pod is being initialized, or the connector's container is
+///   waiting to start, which is foremost the pulling of its image (or the failure thereof).
+/// * [Running](PodEvent::Running): The connector's container is running.
+/// * [Terminated](PodEvent::Terminated): The connector's container terminated (or is crash
+///   looping), an init container failed, or the pod was evicted.
+#[derive(Debug, Clone)]
+pub enum PodEvent {
+    Pending(Pod),
+    Scheduled(Pod),
+    Pulling(Pod),
+    Running(Pod),
+    Terminated(Pod),
+    /// The pod was deleted.
+    Deleted(Pod),
+    /// The watch was restarted, see [Event::Restarted].
+    Restarted(Vec<Pod>),
+}
+
+impl PodEvent {
+    /// Types the given (added or modified) pod by its current state.
+    pub fn of(pod: Pod) -> PodEvent {
+        match pod.phase() {
+            PodPhase::Evicted | PodPhase::InitFailed | PodPhase::Terminated => {
+                PodEvent::Terminated(pod)
+            }
+            PodPhase::Running | PodPhase::Ready => PodEvent::Running(pod),
+            PodPhase::Initializing | PodPhase::Starting => PodEvent::Pulling(pod),
+            // An image that cannot be pulled may be that of a sidecar.
+            PodPhase::Pending if pod.was_err_image_pull() => PodEvent::Pulling(pod),
+            PodPhase::Pending if pod.scheduled() => PodEvent::Scheduled(pod),
+            PodPhase::Pending => PodEvent::Pending(pod),
+        }
+    }
+
+    /// The pod that this event concerns, which a [Restarted](PodEvent::Restarted) has none of.
+    pub fn pod(&self) -> Option<&Pod> {
+        match self {
+            PodEvent::Pending(pod)
+            | PodEvent::Scheduled(pod)
+            | PodEvent::Pulling(pod)
+            | PodEvent::Running(pod)
+            | PodEvent::Terminated(pod)
+            | PodEvent::Deleted(pod) => Some(pod),
+            PodEvent::Restarted(_) => None,
+        }
+    }
+}
+
+impl From<Event<Pod>> for PodEvent {
+    fn from(event: Event<Pod>) -> Self {
+        match event {
+            Event::Added(pod) | Event::Applied(pod) => PodEvent::of(pod),
+            Event::Deleted(pod) => PodEvent::Deleted(pod),
+            Event::Restarted(pods) => PodEvent::Restarted(pods),
+        }
+    }
+}
+
+/// Types the events of the given stream of pod events (such as a [Watch](Watch) or an
+/// [informer](crate::informer::Subscription) subscription) as [PodEvents](PodEvent).
+pub fn pod_events<S, E>(events: S) -> impl Stream<Item = std::result::Result<PodEvent, E>>
+where
+    S: Stream<Item = std::result::Result<Event<Pod>, E>>,
+{
+    events.map(|event| event.map(PodEvent::from))
+}
+
+/// Buffered is a [Stream](Stream) of the events of another stream, which is drained as fast as
+/// it produces events no matter how slowly those events are consumed. The producer (such as an
+/// [Informer](crate::informer::Informer) serving a great many subscribers) is therefore never
+/// held up by a slow consumer.
+///
+/// Rather than growing without end, once the buffer holds `capacity` events an object that is
+/// [modified](Event::Applied) replaces the modification of the same object that is still buffered
+/// (if any), as only the latest state of an object is of any interest. Additions, deletions,
+/// restarts, and errors are never coalesced, as every one of those is of interest.
+pub struct Buffered<K, E> {
+    shared: Arc<Mutex<Buffer<K, E>>>,
+    pump: JoinHandle<()>,
+}
+
+impl<K, E> Buffered<K, E>
+where
+    K: Resource + Send + 'static,
+    E: Send + 'static,
+{
+    pub fn new<S>(events: S, capacity: usize) -> Buffered<K, E>
+    where
+        S: Stream<Item = std::result::Result<Event<K>, E>> + Send + 'static,
+    {
+        let shared = Arc::new(Mutex::new(Buffer::new(capacity)));
+        let buffer = shared.clone();
+        let pump = tokio::spawn(async move {
+            futures::pin_mut!(events);
+            while let Some(event) = events.next().await {
+                buffer.lock().unwrap().push(event);
+            }
+            buffer.lock().unwrap().close();
+        });
+        Buffered { shared, pump }
+    }
+}
+
+impl<K, E> Stream for Buffered<K, E> {
+    type Item = std::result::Result<Event<K>, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut buffer = self.shared.lock().unwrap();
+        match buffer.events.pop_front() {
+            Some(event) => Poll::Ready(Some(event)),
+            None if buffer.closed => Poll::Ready(None),
+            None => {
+                buffer.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<K, E> Drop for Buffered<K, E> {
+    fn drop(&mut self) {
+        self.pump.abort();
+    }
+}
+
+/// The events held by a [Buffered](Buffered).
+struct Buffer<K, E> {
+    events: VecDeque<std::result::Result<Event<K>, E>>,
+    capacity: usize,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+impl<K: Resource, E> Buffer<K, E> {
+    fn new(capacity: usize) -> Buffer<K, E> {
+        Buffer {
+            events: VecDeque::new(),
+            capacity,
+            closed: false,
+            waker: None,
+        }
+    }
+
+    fn push(&mut self, event: std::result::Result<Event<K>, E>) {
+        let event = match event {
+            Ok(Event::Applied(obj)) if self.events.len() >= self.capacity => {
+                match self.coalesce(obj) {
+                    Some(obj) => Ok(Event::Applied(obj)),
+                    None => return self.wake(),
+                }
+            }
+            event => event,
+        };
+        self.events.push_back(event);
+        self.wake();
+    }
+
+    /// Replaces the buffered modification of the given object with it, handing the object back
+    /// should there be none. Modifications are never coalesced across an addition or deletion of
+    /// the object, nor across a restart.
+    fn coalesce(&mut self, obj: K) -> Option<K> {
+        let key = (obj.namespace(), obj.name());
+        for buffered in self.events.iter_mut().rev() {
+            match buffered {
+                Ok(Event::Applied(other)) if (other.namespace(), other.name()) == key => {
+                    *other = obj;
+                    return None;
+                }
+                Ok(Event::Added(other)) | Ok(Event::Deleted(other))
+                    if (other.namespace(), other.name()) == key =>
+                {
+                    break
+                }
+                Ok(Event::Restarted(_)) => break,
+                _ => continue,
+            }
+        }
+        Some(obj)
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::fixtures::{describe, pod};
+    use k8s_openapi::api::core::v1::{
+        ContainerState, ContainerStateRunning, ContainerStateWaiting, ContainerStatus, PodSpec,
+    };
+
+    fn with_state(mut pod: Pod, state: ContainerState) -> Pod {
+        pod.status.as_mut().unwrap().container_statuses = Some(vec![ContainerStatus {
+            name: "connector".to_string(),
+            state: Some(state),
+            ..Default::default()
+        }]);
+        pod
+    }
+
+    #[test]
+    fn types_pod_events() {
+        let kind = |event: Event<Pod>| match PodEvent::from(event) {
+            PodEvent::Pending(_) => "pending",
+            PodEvent::Scheduled(_) => "scheduled",
+            PodEvent::Pulling(_) => "pulling",
+            PodEvent::Running(_) => "running",
+            PodEvent::Terminated(_) => "terminated",
+            PodEvent::Deleted(_) => "deleted",
+            PodEvent::Restarted(_) => "restarted",
+        };
+        let pending = pod("a", "Pending");
+        assert_eq!(kind(Event::Added(pending.clone())), "pending");
+        let mut scheduled = pending.clone();
+        scheduled.spec = Some(PodSpec {
+            node_name: Some("node".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(kind(Event::Applied(scheduled.clone())), "scheduled");
+        let waiting = ContainerState {
+            waiting: Some(ContainerStateWaiting {
+                reason: Some("ContainerCreating".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            kind(Event::Applied(with_state(scheduled.clone(), waiting))),
+            "pulling"
+        );
+        let running = ContainerState {
+            running: Some(ContainerStateRunning::default()),
+            ..Default::default()
+        };
+        assert_eq!(
+            kind(Event::Applied(with_state(pod("a", "Running"), running))),
+            "running"
+        );
+        assert_eq!(kind(Event::Applied(pod("a", "Failed"))), "terminated");
+        assert_eq!(kind(Event::Deleted(pending)), "deleted");
+        assert_eq!(kind(Event::Restarted(vec![])), "restarted");
+    }
+
+    #[test]
+    fn coalesces_modifications() {
+        let mut buffer: Buffer<Pod, ()> = Buffer::new(2);
+        buffer.push(Ok(Event::Added(pod("a", "Pending"))));
+        buffer.push(Ok(Event::Applied(pod("a", "Pending"))));
+        // The buffer is full, so modifications of the same pod are coalesced...
+        buffer.push(Ok(Event::Applied(pod("a", "Running"))));
+        buffer.push(Ok(Event::Applied(pod("b", "Running"))));
+        buffer.push(Ok(Event::Applied(pod("b", "Failed"))));
+        // ...but never across a deletion, nor are deletions and errors ever dropped.
+        buffer.push(Ok(Event::Deleted(pod("a", "Running"))));
+        buffer.push(Ok(Event::Applied(pod("a", "Failed"))));
+        buffer.push(Err(()));
+        let events: Vec<String> = buffer.events.iter().map(describe).collect();
+        assert_eq!(
+            events,
+            vec![
+                "added a:Pending",
+                "applied a:Running",
+                "applied b:Failed",
+                "deleted a:Running",
+                "applied a:Failed",
+                "error"
+            ]
+        );
+    }
+
+    #[test]
+    fn buffers() {
+        tokio_test::block_on(async {
+            let events = futures::stream::iter(vec![
+                Ok(Event::Added(pod("a", "Pending"))),
+                Ok(Event::Applied(pod("a", "Running"))),
+            ]);
+            let buffered: Buffered<Pod, ()> = Buffered::new(events, 8);
+            let events: Vec<String> = buffered.map(|event| describe(&event)).collect().await;
+            assert_eq!(events, vec!["added a:Pending", "applied a:Running"]);
+        })
+    }
 }
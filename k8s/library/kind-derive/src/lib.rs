@@ -2,73 +2,163 @@ extern crate proc_macro;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, Data, DataEnum, DataStruct, DataUnion, DeriveInput, Fields};
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DataEnum, DataStruct, DataUnion, DeriveInput,
+    Error, Fields, Ident, Index, Lit, Meta, NestedMeta, Type,
+};
 
-#[proc_macro_derive(Kind)]
+/// Derives Kind for structs and enums.
+///
+/// * A struct's kind is its name, whereas an enum's kind is `Enum::Variant`.
+/// * `#[kind(rename = "pod.v1")]` replaces the name of the type with the given name.
+/// * A generic struct's kind is followed by the kind of each of its type parameters, such as
+///   `Wrapper[Pod]`. The kind of a type parameter is taken from the first field of that type,
+///   so every type parameter MUST be the type of at least one field.
+#[proc_macro_derive(Kind, attributes(kind))]
 pub fn kind(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let name = input.ident;
-    match input.data {
-        Data::Struct(DataStruct{..}) => quote!(
-            impl Kind for #name {
-                fn kind(&self) -> String {
-                    stringify!(#name).to_string()
+    match derive(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn derive(mut input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let display = match rename(&input.attrs)? {
+        Some(rename) => rename,
+        None => name.to_string(),
+    };
+    let body = match &input.data {
+        Data::Struct(DataStruct { fields, .. }) => {
+            let params: Vec<Ident> = input
+                .generics
+                .type_params()
+                .map(|param| param.ident.clone())
+                .collect();
+            if params.is_empty() {
+                quote!(#display.to_string())
+            } else {
+                let mut kinds = vec![];
+                for param in params.iter() {
+                    let field = field_of_type(fields, param).ok_or_else(|| {
+                        Error::new(
+                            param.span(),
+                            format!(
+                                "Kind cannot be derived for {} as it has no field of type {} \
+                                 from which to take the kind of {}",
+                                name, param, param
+                            ),
+                        )
+                    })?;
+                    kinds.push(quote!(Kind::kind(&self.#field)));
+                }
+                let predicates = &mut input.generics.make_where_clause().predicates;
+                for param in params.iter() {
+                    predicates.push(parse_quote!(#param: Kind));
                 }
+                let template = format!(
+                    "{}[{}]",
+                    display.replace('{', "{{").replace('}', "}}"),
+                    vec!["{}"; params.len()].join(", ")
+                );
+                quote!(format!(#template, #(#kinds),*))
             }
-        ),
-        Data::Enum(DataEnum{ variants, ..  }) => {
-            let q = variants.iter().map(|variant| {
+        }
+        Data::Enum(DataEnum { variants, .. }) => {
+            let arms = variants.iter().map(|variant| {
                 let v = &variant.ident;
-                match variant.fields {
-                    Fields::Unnamed(_) => quote! {
-                        #name::#v(..) => concat!(stringify!(#name), stringify!(::), stringify!(#v)).to_string()
-                    },
-                    Fields::Named(_) => quote!{
-                        #name::#v{ .. } => concat!(stringify!(#name), stringify!(::), stringify!(#v)).to_string()
-                    },
-                    Fields::Unit => quote!{
-                        #name::#v => concat!(stringify!(#name), stringify!(::), stringify!(#v)).to_string()
-                    }
-                }
+                let kind = format!("{}::{}", display, v);
+                quote!(#name::#v { .. } => #kind.to_string())
             });
             quote!(
-                impl Kind for #name {
-                    fn kind(&self) -> String {
-                        match self {
-                            #(#q),*
-                        }
-                    }
+                match self {
+                    #(#arms),*
                 }
             )
         }
-        Data::Union(DataUnion {  .. }) => {
+        Data::Union(DataUnion { union_token, .. }) => {
             // Sorry, unions are more for either FFI with C code
             // or for embedded devices and that's just not our use case.
-            //
-            // At any rate, at least this is what the compiler error
-            // will look like which lets the user know how to proceed
-            // forward if they stil want this.
-            //
-            // error: proc-macro derive panicked
-            //   --> src/mod:84:18
-            //    |
-            // 84 |         #[derive(Kind)]
-            //    |                  ^^^^
-            //    |
-            //    = help: message: kind-derive does not support Unions yet. Perhaps you should try manually implementing Kind?
-            //
-            //            r#"impl Kind for MyUnion {
-            //                fn kind(&self) -> &'static str {
-            //                    ...
-            //                }
-            //            }
-            panic!(r#"kind-derive does not support Unions yet. Perhaps you should try manually implementing Kind?
+            return Err(Error::new(
+                union_token.span(),
+                format!(
+                    "kind-derive does not support unions. Perhaps you should try manually \
+                     implementing Kind?\n\nimpl Kind for {} {{\n    fn kind(&self) -> String {{\n        \
+                     ...\n    }}\n}}",
+                    name
+                ),
+            ));
+        }
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote!(
+        impl #impl_generics Kind for #name #ty_generics #where_clause {
+            fn kind(&self) -> String {
+                #body
+            }
+        }
+    ))
+}
+
+/// Extracts the name out of a `#[kind(rename = "pod.v1")]` attribute, if present.
+fn rename(attrs: &[Attribute]) -> syn::Result<Option<String>> {
+    let attr = match attrs.iter().find(|attr| attr.path.is_ident("kind")) {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+    let list = match attr.parse_meta()? {
+        Meta::List(list) => list,
+        meta => {
+            return Err(Error::new(
+                meta.span(),
+                "expected #[kind(rename = \"<NAME>\")]",
+            ))
+        }
+    };
+    let mut rename = None;
+    for nested in list.nested.iter() {
+        match nested {
+            NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("rename") => {
+                match &pair.lit {
+                    Lit::Str(lit) if !lit.value().is_empty() => rename = Some(lit.value()),
+                    lit => {
+                        return Err(Error::new(
+                            lit.span(),
+                            "the renamed kind must be a non-empty string",
+                        ))
+                    }
+                }
+            }
+            nested => {
+                return Err(Error::new(
+                    nested.span(),
+                    "unknown kind attribute, expected rename = \"<NAME>\"",
+                ))
+            }
+        }
+    }
+    Ok(rename)
+}
 
-r#"impl Kind for {} {{
-    fn kind(&self) -> &'static str {{
-        ...
-    }}
-}}"#, name)
+/// Returns the member (that is, `self.<member>`) of the first field whose type is exactly the
+/// given type parameter.
+fn field_of_type(fields: &Fields, param: &Ident) -> Option<proc_macro2::TokenStream> {
+    fields.iter().enumerate().find_map(|(index, field)| {
+        let matches = match &field.ty {
+            Type::Path(path) => path.qself.is_none() && path.path.is_ident(param),
+            _ => false,
+        };
+        if !matches {
+            return None;
         }
-    }.into()
+        Some(match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = Index::from(index);
+                quote!(#index)
+            }
+        })
+    })
 }
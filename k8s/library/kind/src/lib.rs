@@ -1,5 +1,8 @@
 use k8s_openapi::api::core::v1::Pod;
 pub use kind_derive::*;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use std::sync::Arc;
 
 /// A type that implements Kind is capable of describing itself to outside systems, typically
 /// by simply returning the name of their type.
@@ -23,11 +26,54 @@ pub use kind_derive::*;
 /// assert_eq!("MyEnum::VariantTwo", MyEnum::VariantTwo(42).kind());
 /// ```
 ///
+/// A type may present a stable kind, independent of its Rust identifier, via `#[kind(rename = "...")]`.
+/// Generic structs are followed by the kind of each of their type parameters, which is taken from
+/// the first field of that type.
+///
+/// ```
+/// use kind::Kind;
+///
+/// #[derive(Kind)]
+/// #[kind(rename = "pod.v1")]
+/// struct PodV1 {}
+///
+/// #[derive(Kind)]
+/// struct Wrapper<T: Kind> {
+///     inner: T
+/// }
+///
+/// assert_eq!("pod.v1", PodV1{}.kind());
+/// assert_eq!("Wrapper[pod.v1]", Wrapper{ inner: PodV1{} }.kind());
+/// ```
+///
 /// The Kind derivation macro does not work on Unions. If you wish, you must implement Kind
 /// on your target Union yourself.
 ///
-/// A blanket implementation exists for all [Vec<T>](std::vec::Vec) where T implements Kind for
-/// which the result is `List[T::kind()]`. If the vector is empty, then the kind is `List[]`.
+/// Blanket implementations exist for the following containers of types that implement Kind. As
+/// the kind of the contents is taken from the contents themselves, an empty container has an
+/// empty kind.
+///
+/// | Type | Kind | Empty |
+/// | --- | --- | --- |
+/// | [Vec<T>](std::vec::Vec) | `List[T]` | `List[]` |
+/// | [Option<T>](std::option::Option) | `Optional[T]` | `Optional[]` |
+/// | [Result<T, E>](std::result::Result) | `Result::Ok[T]` or `Result::Err[E]` | |
+/// | [HashMap<K, V>](std::collections::HashMap) and [BTreeMap<K, V>](std::collections::BTreeMap) | `Map[K, V]` | `Map[]` |
+/// | Tuples (of up to eight elements) | `(A, B, ...)` | |
+///
+/// References, [Box<T>](std::boxed::Box), [Rc<T>](std::rc::Rc), and [Arc<T>](std::sync::Arc)
+/// are transparent. That is, their kind is simply that of `T`.
+///
+/// ```
+/// use kind::Kind;
+/// use std::collections::BTreeMap;
+///
+/// let mut map = BTreeMap::new();
+/// map.insert("a".to_string(), vec![1u8]);
+/// assert_eq!("Map[String, List[u8]]", map.kind());
+/// assert_eq!("Optional[u8]", Some(1u8).kind());
+/// assert_eq!("(String, Optional[])", ("a".to_string(), None::<u8>).kind());
+/// ```
 pub trait Kind {
     fn kind(&self) -> String;
 }
@@ -73,11 +119,103 @@ where
         if self.is_empty() {
             "List[]".to_string()
         } else {
-            format!("List[{}]", self.get(0).unwrap().kind())
+            format!("List[{}]", self.first().unwrap().kind())
+        }
+    }
+}
+
+impl<T> Kind for Option<T>
+where
+    T: Kind,
+{
+    fn kind(&self) -> String {
+        match self {
+            Some(value) => format!("Optional[{}]", value.kind()),
+            None => "Optional[]".to_string(),
         }
     }
 }
 
+impl<T, E> Kind for Result<T, E>
+where
+    T: Kind,
+    E: Kind,
+{
+    fn kind(&self) -> String {
+        match self {
+            Ok(value) => format!("Result::Ok[{}]", value.kind()),
+            Err(err) => format!("Result::Err[{}]", err.kind()),
+        }
+    }
+}
+
+impl<K, V, S> Kind for HashMap<K, V, S>
+where
+    K: Kind,
+    V: Kind,
+{
+    fn kind(&self) -> String {
+        map_kind(self.iter().next())
+    }
+}
+
+impl<K, V> Kind for BTreeMap<K, V>
+where
+    K: Kind,
+    V: Kind,
+{
+    fn kind(&self) -> String {
+        map_kind(self.iter().next())
+    }
+}
+
+fn map_kind<K: Kind, V: Kind>(entry: Option<(&K, &V)>) -> String {
+    match entry {
+        Some((key, value)) => format!("Map[{}, {}]", key.kind(), value.kind()),
+        None => "Map[]".to_string(),
+    }
+}
+
+macro_rules! impl_kind_transparent {
+    ($($pointer:ty),*) => {
+        $(
+            impl<T> Kind for $pointer
+            where
+                T: Kind + ?Sized,
+            {
+                fn kind(&self) -> String {
+                    (**self).kind()
+                }
+            }
+        )*
+    };
+}
+
+impl_kind_transparent!(&T, &mut T, Box<T>, Rc<T>, Arc<T>);
+
+macro_rules! impl_kind_tuple {
+    ($($t:ident . $i:tt),+) => {
+        impl<$($t),+> Kind for ($($t,)+)
+        where
+            $($t: Kind),+
+        {
+            fn kind(&self) -> String {
+                let kinds: Vec<String> = vec![$(self.$i.kind()),+];
+                format!("({})", kinds.join(", "))
+            }
+        }
+    };
+}
+
+impl_kind_tuple!(A.0);
+impl_kind_tuple!(A.0, B.1);
+impl_kind_tuple!(A.0, B.1, C.2);
+impl_kind_tuple!(A.0, B.1, C.2, D.3);
+impl_kind_tuple!(A.0, B.1, C.2, D.3, E.4);
+impl_kind_tuple!(A.0, B.1, C.2, D.3, E.4, F.5);
+impl_kind_tuple!(A.0, B.1, C.2, D.3, E.4, F.5, G.6);
+impl_kind_tuple!(A.0, B.1, C.2, D.3, E.4, F.5, G.6, H.7);
+
 #[cfg(test)]
 #[allow(dead_code)]
 mod tests {
@@ -135,6 +273,108 @@ mod tests {
         assert_eq!(AnEnum::Variant { a: 1, b: 2 }.kind(), "AnEnum::Variant")
     }
 
+    #[test]
+    fn renamed_struct() {
+        #[derive(Kind)]
+        #[kind(rename = "pod.v1")]
+        struct Lol {}
+        assert_eq!(Lol {}.kind(), "pod.v1")
+    }
+
+    #[test]
+    fn renamed_enum() {
+        #[derive(Kind)]
+        #[kind(rename = "phase.v1")]
+        enum AnEnum {
+            Variant,
+        }
+        assert_eq!(AnEnum::Variant.kind(), "phase.v1::Variant")
+    }
+
+    #[test]
+    fn generic_struct() {
+        #[derive(Kind)]
+        struct Wrapper<T> {
+            inner: T,
+        }
+        assert_eq!(Wrapper { inner: 1u8 }.kind(), "Wrapper[u8]");
+        assert_eq!(
+            Wrapper {
+                inner: Wrapper { inner: () }
+            }
+            .kind(),
+            "Wrapper[Wrapper[()]]"
+        );
+    }
+
+    #[test]
+    fn generic_tuple_struct() {
+        #[derive(Kind)]
+        #[kind(rename = "pair")]
+        struct Pair<A, B>(A, B);
+        assert_eq!(Pair(1u8, "a".to_string()).kind(), "pair[u8, String]")
+    }
+
+    #[test]
+    fn generic_enum() {
+        #[derive(Kind)]
+        enum Either<L, R> {
+            Left(L),
+            Right(R),
+        }
+        assert_eq!(Either::<u8, u8>::Left(1).kind(), "Either::Left");
+        assert_eq!(Either::<u8, u8>::Right(1).kind(), "Either::Right");
+    }
+
+    #[test]
+    fn option() {
+        assert_eq!(Some(1u8).kind(), "Optional[u8]");
+        assert_eq!(None::<u8>.kind(), "Optional[]");
+    }
+
+    #[test]
+    fn result() {
+        let ok: Result<u8, String> = Ok(1);
+        let err: Result<u8, String> = Err("oops".to_string());
+        assert_eq!(ok.kind(), "Result::Ok[u8]");
+        assert_eq!(err.kind(), "Result::Err[String]");
+    }
+
+    #[test]
+    fn maps() {
+        let mut hash = HashMap::new();
+        assert_eq!(hash.kind(), "Map[]");
+        hash.insert("a".to_string(), 1u32);
+        assert_eq!(hash.kind(), "Map[String, u32]");
+        let mut btree = BTreeMap::new();
+        assert_eq!(btree.kind(), "Map[]");
+        btree.insert(1u8, vec![1u8]);
+        assert_eq!(btree.kind(), "Map[u8, List[u8]]");
+    }
+
+    #[test]
+    fn transparent() {
+        #[derive(Kind)]
+        struct Lol {}
+        let lol = &Lol {};
+        assert_eq!(lol.kind(), "Lol");
+        assert_eq!(Box::new(Lol {}).kind(), "Lol");
+        assert_eq!(Rc::new(Lol {}).kind(), "Lol");
+        assert_eq!(Arc::new(Lol {}).kind(), "Lol");
+        let boxed: Box<dyn Kind> = Box::new(Lol {});
+        assert_eq!(boxed.kind(), "Lol");
+    }
+
+    #[test]
+    fn tuples() {
+        assert_eq!((1u8,).kind(), "(u8)");
+        assert_eq!((1u8, "a".to_string()).kind(), "(u8, String)");
+        assert_eq!(
+            (1u8, 2u16, 3u32, 4u64, 5i8, 6i16, 7i32, 8i64).kind(),
+            "(u8, u16, u32, u64, i8, i16, i32, i64)"
+        );
+    }
+
     #[test]
     fn mixed_enum() {
         #[derive(Kind)]
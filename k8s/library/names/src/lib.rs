@@ -1,5 +1,12 @@
 use convert_case::{Case, Casing};
-use rand::{thread_rng, Rng};
+use error::*;
+use rand::rngs::{StdRng, ThreadRng};
+use rand::{thread_rng, Rng, RngCore, SeedableRng};
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+use std::str::FromStr;
 use uuid::Uuid;
 
 /// rfc1035_label returns a lowercase, hexadecimal encoded, UUID that is also
@@ -18,12 +25,136 @@ use uuid::Uuid;
 ///
 /// With regards to usages with Kubernetes, this is used to generate unique tags for connector images.
 pub fn rfc1035_label() -> String {
-    let mut name = uuid();
-    if !name.starts_with(char::is_alphabetic) {
-        name.remove(0);
-        name.insert(0, thread_rng().gen_range('a'..='z'))
-    }
-    name
+    with_default(NameGenerator::rfc1035_label)
+}
+
+/// is_valid_rfc1035_label returns whether the given string is a valid RFC 1035 label. That is,
+/// whether it is at most 63 characters long and matches `[a-z]([-a-z0-9]*[a-z0-9])?` (the same
+/// check that Kubernetes makes, please see [rfc1035_label](rfc1035_label)).
+pub fn is_valid_rfc1035_label<T: AsRef<str>>(label: T) -> bool {
+    let label = label.as_ref();
+    let alphanumeric = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+    label.len() <= 63
+        && label.starts_with(|c: char| c.is_ascii_lowercase())
+        && label.ends_with(alphanumeric)
+        && label.chars().all(|c| alphanumeric(c) || c == '-')
+}
+
+/// is_valid_rfc1123_subdomain returns whether the given string is a valid RFC 1123 subdomain. That
+/// is, whether it is at most 253 characters long and is a series of dot separated labels that each
+/// match `[a-z0-9]([-a-z0-9]*[a-z0-9])?` (the same check that Kubernetes makes of the names of pods
+/// and services, please see [validation.go](https://github.com/kubernetes/kubernetes/blob/f3b98a08b05257fbc3c19b52ced70ea67c546b1e/staging/src/k8s.io/apimachinery/pkg/util/validation/validation.go#L205)).
+pub fn is_valid_rfc1123_subdomain<T: AsRef<str>>(subdomain: T) -> bool {
+    let subdomain = subdomain.as_ref();
+    let alphanumeric = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+    subdomain.len() <= 253
+        && subdomain.split('.').all(|label| {
+            label.starts_with(alphanumeric)
+                && label.ends_with(alphanumeric)
+                && label.chars().all(|c| alphanumeric(c) || c == '-')
+        })
+}
+
+/// An Rfc1035Label is a string that is known to be a [valid RFC 1035 label](is_valid_rfc1035_label),
+/// such as the tag of an installed image.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Rfc1035Label(String);
+
+/// An Rfc1123Subdomain is a string that is known to be a [valid RFC 1123 subdomain](is_valid_rfc1123_subdomain),
+/// such as the name of a deployed pod.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Rfc1123Subdomain(String);
+
+macro_rules! validated {
+    ($name:ident, $valid:ident, $expected:expr) => {
+        impl $name {
+            /// Validates the given string, returning an [InvalidName](InvalidName) should it not be valid.
+            pub fn new<T: Into<String>>(name: T) -> std::result::Result<$name, InvalidName> {
+                let name = name.into();
+                if $valid(&name) {
+                    Ok($name(name))
+                } else {
+                    Err(InvalidName {
+                        name,
+                        expected: $expected,
+                    })
+                }
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            pub fn into_inner(self) -> String {
+                self.0
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = InvalidName;
+
+            fn try_from(name: String) -> std::result::Result<$name, InvalidName> {
+                $name::new(name)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = InvalidName;
+
+            fn from_str(name: &str) -> std::result::Result<$name, InvalidName> {
+                $name::new(name)
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(name: $name) -> String {
+                name.0
+            }
+        }
+    };
+}
+
+validated!(
+    Rfc1035Label,
+    is_valid_rfc1035_label,
+    "RFC 1035 label (at most 63 lowercase alphanumeric characters or '-', starting with a letter \
+and ending with an alphanumeric character)"
+);
+
+validated!(
+    Rfc1123Subdomain,
+    is_valid_rfc1123_subdomain,
+    "RFC 1123 subdomain (at most 253 lowercase alphanumeric characters, '-' or '.', starting and \
+ending with an alphanumeric character)"
+);
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug, Clone)]
+#[code(Status::UnprocessableEntity)]
+#[error("'{name}' is not a valid {expected}.")]
+#[error_code("NAMES-1000")]
+pub struct InvalidName {
+    name: String,
+    expected: &'static str,
 }
 
 const DEFAULT_IF_INVALID_SUBDOMAIN: &str = "invalid-rfc1123-connector-name";
@@ -40,7 +171,7 @@ const DEFAULT_IF_INVALID_SUBDOMAIN: &str = "invalid-rfc1123-connector-name";
 /// * 3. A lowercase, hexadecimal, UUID is suffixed to the output of #2.
 ///     * 3a. If the prefix + suffix length is less than or equal to 63, then that string is returned.
 ///     * 3b. If the prefix is too long to accommodate at least 8 bytes worth of UUID, then the
-///             prefix is truncated to 54 bytes and 8 bytes worth of UUID is suffixed and returned.
+///       prefix is truncated to 54 bytes and 8 bytes worth of UUID is suffixed and returned.
 ///     * 3c. Otherwise, the UUID is truncated such that prefix + suffix is 63 bytes long.
 ///
 /// Please see the following from [RFC 1123](https://datatracker.ietf.org/doc/html/rfc1123#section-6.1.3.5) with regard to DNS names.
@@ -62,38 +193,104 @@ const DEFAULT_IF_INVALID_SUBDOMAIN: &str = "invalid-rfc1123-connector-name";
 /// With regards to usages with Kubernetes, this is used as the name for pods and services
 /// since those names must be valid subdomains.
 pub fn rfc1123_subdomain<T: AsRef<str>>(prefix: T) -> String {
-    let mut uuid = uuid();
-    let mut prefix = prefix
-        .as_ref()
-        .chars()
-        .into_iter()
-        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
-        .collect::<String>()
-        .to_case(Case::Kebab);
-    if prefix.is_empty() {
-        prefix = DEFAULT_IF_INVALID_SUBDOMAIN.to_string();
-    }
-    // +1/9 because of the hyphen that separates {prefix}-{uuid}
-    if uuid.len() + prefix.len() < 63 {
-        // Case 3.a
-    } else if prefix.len() + 9 > 63 {
-        // Case 3.b
-        prefix.truncate(63 - 9);
-        uuid.truncate(8);
-    } else {
-        // Case 3.c
-        let ulen = 63 - 1 - prefix.len();
-        uuid.truncate(ulen);
-    }
-    // These assertions are only compiled into debug (dev/test) builds.
-    debug_assert!(prefix.len() + uuid.len() <= 63);
-    debug_assert!(uuid.len() >= 8);
-    return format!("{}-{}", prefix, uuid);
+    with_default(|generator| generator.rfc1123_subdomain(prefix))
 }
 
 /// Returns a randomly generated, lowercase, hexadecimal encoded, UUID string.
 pub fn uuid() -> String {
-    Uuid::from_u128(thread_rng().gen()).to_simple().to_string()
+    with_default(NameGenerator::uuid)
+}
+
+thread_local! {
+    /// The generator behind the free functions of this crate, of which every thread has its own.
+    static DEFAULT: RefCell<NameGenerator<Box<dyn RngCore>>> =
+        RefCell::new(NameGenerator::new(Box::new(thread_rng())));
+}
+
+fn with_default<T, F: FnOnce(&mut NameGenerator<Box<dyn RngCore>>) -> T>(f: F) -> T {
+    DEFAULT.with(|generator| f(&mut generator.borrow_mut()))
+}
+
+/// Seeds the generator behind the free functions of this crate (such as [uuid](uuid)) for the
+/// current thread, such that the names that they return thereafter are reproducible. This is
+/// intended for tests (each of which runs upon its own thread), as is the
+/// [seeded](NameGenerator::seeded) NameGenerator.
+pub fn seed(seed: u64) {
+    DEFAULT.with(|generator| {
+        *generator.borrow_mut() = NameGenerator::new(Box::new(StdRng::seed_from_u64(seed)))
+    });
+}
+
+/// A NameGenerator generates names from the RNG that it is given. The free functions of this crate
+/// are backed by a (per thread) NameGenerator of [thread_rng](rand::thread_rng), whereas tests
+/// that depend upon the names generated MAY use a [seeded](NameGenerator::seeded) NameGenerator
+/// (or [seed](seed) the default one) so that they are reproducible.
+pub struct NameGenerator<R: RngCore = ThreadRng> {
+    rng: R,
+}
+
+impl Default for NameGenerator<ThreadRng> {
+    fn default() -> Self {
+        NameGenerator::new(thread_rng())
+    }
+}
+
+impl NameGenerator<StdRng> {
+    /// Returns a NameGenerator that generates the very same names for the same seed.
+    pub fn seeded(seed: u64) -> NameGenerator<StdRng> {
+        NameGenerator::new(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl<R: RngCore> NameGenerator<R> {
+    pub fn new(rng: R) -> NameGenerator<R> {
+        NameGenerator { rng }
+    }
+
+    /// See [rfc1035_label](rfc1035_label).
+    pub fn rfc1035_label(&mut self) -> String {
+        let mut name = self.uuid();
+        if !name.starts_with(char::is_alphabetic) {
+            name.remove(0);
+            name.insert(0, self.rng.gen_range('a'..='z'))
+        }
+        name
+    }
+
+    /// See [rfc1123_subdomain](rfc1123_subdomain).
+    pub fn rfc1123_subdomain<T: AsRef<str>>(&mut self, prefix: T) -> String {
+        let mut uuid = self.uuid();
+        let mut prefix = prefix
+            .as_ref()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+            .collect::<String>()
+            .to_case(Case::Kebab);
+        if prefix.is_empty() {
+            prefix = DEFAULT_IF_INVALID_SUBDOMAIN.to_string();
+        }
+        // +1/9 because of the hyphen that separates {prefix}-{uuid}
+        if uuid.len() + prefix.len() < 63 {
+            // Case 3.a
+        } else if prefix.len() + 9 > 63 {
+            // Case 3.b
+            prefix.truncate(63 - 9);
+            uuid.truncate(8);
+        } else {
+            // Case 3.c
+            let ulen = 63 - 1 - prefix.len();
+            uuid.truncate(ulen);
+        }
+        // These assertions are only compiled into debug (dev/test) builds.
+        debug_assert!(prefix.len() + uuid.len() <= 63);
+        debug_assert!(uuid.len() >= 8);
+        format!("{}-{}", prefix, uuid)
+    }
+
+    /// See [uuid](uuid).
+    pub fn uuid(&mut self) -> String {
+        Uuid::from_u128(self.rng.gen()).to_simple().to_string()
+    }
 }
 
 #[cfg(test)]
@@ -109,11 +306,98 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_is_valid_rfc1035_label() {
+        for _ in 0..1000 {
+            assert!(is_valid_rfc1035_label(rfc1035_label()));
+        }
+        assert!(is_valid_rfc1035_label("a"));
+        assert!(is_valid_rfc1035_label("production-v2"));
+        assert!(is_valid_rfc1035_label("a".repeat(63)));
+        assert!(!is_valid_rfc1035_label("a".repeat(64)));
+        assert!(!is_valid_rfc1035_label(""));
+        assert!(!is_valid_rfc1035_label("2-production"));
+        assert!(!is_valid_rfc1035_label("production-"));
+        assert!(!is_valid_rfc1035_label("Production"));
+        assert!(!is_valid_rfc1035_label("prod.v2"));
+    }
+
+    #[test]
+    fn test_is_valid_rfc1123_subdomain() {
+        for _ in 0..1000 {
+            assert!(is_valid_rfc1123_subdomain(rfc1123_subdomain("Oracle v1.2")));
+        }
+        assert!(is_valid_rfc1123_subdomain("0"));
+        assert!(is_valid_rfc1123_subdomain("oracle-connector.ocf-system"));
+        assert!(is_valid_rfc1123_subdomain(
+            [
+                "a".repeat(63),
+                "b".repeat(63),
+                "c".repeat(63),
+                "d".repeat(61)
+            ]
+            .join(".")
+        ));
+        assert!(!is_valid_rfc1123_subdomain(
+            [
+                "a".repeat(63),
+                "b".repeat(63),
+                "c".repeat(63),
+                "d".repeat(62)
+            ]
+            .join(".")
+        ));
+        assert!(!is_valid_rfc1123_subdomain(""));
+        assert!(!is_valid_rfc1123_subdomain("oracle..connector"));
+        assert!(!is_valid_rfc1123_subdomain("-oracle"));
+        assert!(!is_valid_rfc1123_subdomain("Oracle"));
+        assert!(!is_valid_rfc1123_subdomain("oracle_connector"));
+    }
+
+    #[test]
+    fn test_newtypes() {
+        let label: Rfc1035Label = "production".parse().unwrap();
+        assert_eq!(label.as_str(), "production");
+        assert_eq!(&*label, "production");
+        let err = Rfc1035Label::new("2-production").unwrap_err();
+        assert_eq!(err.error_code(), Some("NAMES-1000"));
+        assert_eq!(err.http_code(), Status::UnprocessableEntity);
+        assert!(Rfc1123Subdomain::try_from("oracle.v2".to_string()).is_ok());
+        assert!(Rfc1123Subdomain::new("../../etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_seeded() {
+        let (mut a, mut b) = (NameGenerator::seeded(42), NameGenerator::seeded(42));
+        for _ in 0..100 {
+            assert_eq!(a.uuid(), b.uuid());
+            assert_eq!(a.rfc1035_label(), b.rfc1035_label());
+            assert_eq!(a.rfc1123_subdomain("oracle"), b.rfc1123_subdomain("oracle"));
+        }
+        assert_ne!(
+            NameGenerator::seeded(42).uuid(),
+            NameGenerator::seeded(43).uuid()
+        );
+        assert!(is_valid_rfc1035_label(
+            NameGenerator::default().rfc1035_label()
+        ));
+    }
+
+    #[test]
+    fn test_seed_default() {
+        seed(7);
+        let first = (uuid(), rfc1035_label(), rfc1123_subdomain("oracle"));
+        seed(7);
+        let second = (uuid(), rfc1035_label(), rfc1123_subdomain("oracle"));
+        assert_eq!(first, second);
+        let mut generator = NameGenerator::seeded(7);
+        assert_eq!(first.0, generator.uuid());
+    }
+
     #[test]
     fn test_complex_name() {
         let domain = rfc1123_subdomain(
-            "Alation's Oracle Connecfor (OCF:v.1.23) aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
-                .to_string(),
+            "Alation's Oracle Connecfor (OCF:v.1.23) aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
         );
         assert!(domain.starts_with("alation-s-oracle-connecfor-ocf-v-1-23"));
         assert!(domain.len() <= 63);
@@ -0,0 +1,136 @@
+//! Content negotiation for the response envelope shared by the [Response](../response/struct.Response.html)
+//! and `Box<dyn AcmError>` responders.
+//!
+//! The envelope is rendered in whichever [Format](Format) is preferred by the request's `Accept`
+//! header. Pretty printed JSON remains the default for any request that does not ask for
+//! something else.
+//!
+//! | Accept | Format |
+//! | --- | --- |
+//! | `application/yaml`, `application/x-yaml`, `text/yaml` | [Yaml](Format::Yaml) |
+//! | `application/json; pretty=false` | [CompactJson](Format::CompactJson) |
+//! | Anything else (or nothing at all) | [Json](Format::Json) |
+use rocket::http::{Accept, ContentType, MediaType};
+use rocket::request::Request;
+use serde::Serialize;
+
+/// A Format is a rendering of the response envelope that a client may ask for.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Format {
+    /// Pretty printed JSON.
+    Json,
+    /// JSON without any insignificant whitespace.
+    CompactJson,
+    /// YAML, for the sake of humans and tooling (such as kubectl plugins) that prefer it.
+    Yaml,
+}
+
+impl Format {
+    /// Returns the Format preferred by the given request.
+    pub fn negotiate(request: &Request<'_>) -> Format {
+        Format::from_accept(request.accept())
+    }
+
+    /// Returns the Format preferred by the given `Accept` header, if any.
+    pub fn from_accept(accept: Option<&Accept>) -> Format {
+        let media = match accept {
+            Some(accept) => accept.preferred().media_type(),
+            None => return Format::Json,
+        };
+        if is_yaml(media) {
+            Format::Yaml
+        } else if *media == MediaType::JSON && not_pretty(media) {
+            Format::CompactJson
+        } else {
+            Format::Json
+        }
+    }
+
+    /// The `Content-Type` of this Format.
+    pub fn content_type(self) -> ContentType {
+        match self {
+            Format::Json | Format::CompactJson => ContentType::JSON,
+            Format::Yaml => ContentType::new("application", "yaml"),
+        }
+    }
+
+    /// Renders the given value in this Format.
+    pub fn render<T: Serialize>(self, value: &T) -> String {
+        let rendered = match self {
+            Format::Json => serde_json::to_string_pretty(value).map_err(|err| err.to_string()),
+            Format::CompactJson => serde_json::to_string(value).map_err(|err| err.to_string()),
+            Format::Yaml => serde_yaml::to_string(value).map_err(|err| err.to_string()),
+        };
+        // @TODO it MIGHT be possible to fail here? No idea how. If so, can read the error here
+        // and return that instead. I just have no idea what could ever cause it.
+        rendered.unwrap_or_else(|err| panic!("failed to render as {:?}, {}", self, err))
+    }
+}
+
+fn is_yaml(media: &MediaType) -> bool {
+    [
+        MediaType::new("application", "yaml"),
+        MediaType::new("application", "x-yaml"),
+        MediaType::new("text", "yaml"),
+    ]
+    .iter()
+    .any(|yaml| yaml == media)
+}
+
+fn not_pretty(media: &MediaType) -> bool {
+    media.params().any(|(key, value)| {
+        key.as_str().eq_ignore_ascii_case("pretty") && value.eq_ignore_ascii_case("false")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn negotiate(accept: &str) -> Format {
+        Format::from_accept(Some(&accept.parse::<Accept>().unwrap()))
+    }
+
+    #[test]
+    fn defaults_to_json() {
+        assert_eq!(Format::from_accept(None), Format::Json);
+        assert_eq!(negotiate("*/*"), Format::Json);
+        assert_eq!(negotiate("application/json"), Format::Json);
+        assert_eq!(negotiate("text/html"), Format::Json);
+    }
+
+    #[test]
+    fn yaml() {
+        assert_eq!(negotiate("application/yaml"), Format::Yaml);
+        assert_eq!(negotiate("application/x-yaml"), Format::Yaml);
+        assert_eq!(negotiate("text/yaml"), Format::Yaml);
+        assert_eq!(
+            negotiate("application/json; q=0.5, text/yaml"),
+            Format::Yaml
+        );
+    }
+
+    #[test]
+    fn compact_json() {
+        assert_eq!(
+            negotiate("application/json; pretty=false"),
+            Format::CompactJson
+        );
+        assert_eq!(negotiate("application/json; pretty=true"), Format::Json);
+    }
+
+    #[test]
+    fn render() {
+        let value = json!({"payload": {"kind": "String", "object": "hi"}, "error": null});
+        let compact = Format::CompactJson.render(&value);
+        assert!(!compact.contains(' ') && !compact.contains('\n'));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&compact).unwrap(),
+            value
+        );
+        let yaml: serde_json::Value = serde_yaml::from_str(&Format::Yaml.render(&value)).unwrap();
+        assert_eq!(yaml, value);
+        assert!(Format::Json.render(&value).contains('\n'));
+    }
+}
@@ -1,11 +1,25 @@
 use result::Result;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::string::FromUtf8Error;
+use std::time::{Duration, Instant};
 use tokio::process::Command;
 
 use error::*;
 use tokio::io::AsyncWriteExt;
 
+pub use streaming::{exec_streaming, Line, Streaming};
+pub use tokio_util::sync::CancellationToken;
+
+mod streaming;
+
+/// The environment variable that configures the timeout (in seconds) of every command that is not
+/// given its own via [Options::timeout](Options::timeout). Zero disables the default timeout.
+pub const COMMAND_TIMEOUT: &str = "COMMAND_TIMEOUT";
+
+/// The default for [COMMAND_TIMEOUT](COMMAND_TIMEOUT).
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
 /// cmd runs any arbitrary system command asynchronously and returns the resulting stdout.
 /// The returned stdout is guaranteed to not have any trailing newlines or spaces.
 ///
@@ -17,13 +31,56 @@ use tokio::io::AsyncWriteExt;
 /// let temp = cmd!("mktemp").await.unwrap();
 /// let contents = cmd!("cat", &temp).await.unwrap();
 /// ```
+///
+/// Every command is killed (alongside any processes that it spawned) should it not finish within
+/// the [default timeout](COMMAND_TIMEOUT). A command may be given its own timeout, or a
+/// [CancellationToken](CancellationToken) by which it may be aborted, via [Options](Options).
+///
+/// ```ignore
+/// let options = Options::new().timeout(Duration::from_secs(60)).cancellation(token.clone());
+/// cmd!(options = options, "ctr", "images", "push", &image).await.unwrap();
+/// ```
+///
+/// Options also carry the environment variables and working directory of a command, as well as
+/// any secrets that must be kept out of its [debug string](Options::redact).
+///
+/// ```ignore
+/// let options = Options::new()
+///     .secret_env("AWS_SECRET_ACCESS_KEY", &key)
+///     .current_dir(&workspace);
+/// cmd!(options = options, "aws", "ecr", "describe-repositories").await.unwrap();
+/// ```
 #[macro_export]
 macro_rules! cmd {
+    (options=$options:expr, stdin=$stdin:expr, $command:expr $(,$args:expr)*) => {
+        {
+            let mut cmd = tokio::process::Command::new($command);
+            $(cmd.arg($args);)*
+            let mut debug_string: Vec<String> = vec![format!("{}", $command)];
+            $(
+                debug_string.push(format!("{}", $args));
+            )*
+            let debug_string: String = debug_string.join(" ");
+            os::process::exec_stdout(Some($stdin), cmd, debug_string, $options)
+        }
+    };
+    (options=$options:expr, $command:expr $(,$args:expr)*) => {
+        {
+            let mut cmd = tokio::process::Command::new($command);
+            $(cmd.arg($args);)*
+            let mut debug_string: Vec<String> = vec![format!("{}", $command)];
+            $(
+                debug_string.push(format!("{}", $args));
+            )*
+            let debug_string: String = debug_string.join(" ");
+            os::process::exec_stdout(None::<&[u8]>, cmd, debug_string, $options)
+        }
+    };
     (stdin=$stdin:expr, $command:expr) => {
         {
             let cmd = tokio::process::Command::new($command);
             let debug_string: String = format!("{}", $command);
-            os::process::exec(Some($stdin), cmd, debug_string)
+            os::process::exec_stdout(Some($stdin), cmd, debug_string, os::process::Options::default())
         }
     };
     (stdin=$stdin:expr, $command:expr $(,$args:expr)*) => {
@@ -35,14 +92,76 @@ macro_rules! cmd {
                 debug_string.push(format!("{}", $args));
             )*
             let debug_string: String = debug_string.join(" ");
-            os::process::exec(Some($stdin), cmd, debug_string)
+            os::process::exec_stdout(Some($stdin), cmd, debug_string, os::process::Options::default())
         }
     };
     ($command:expr) => {
         {
             let cmd = tokio::process::Command::new($command);
             let debug_string: String = format!("{}", $command);
-            os::process::exec(None::<&[u8]>, cmd, debug_string)
+            os::process::exec_stdout(None::<&[u8]>, cmd, debug_string, os::process::Options::default())
+        }
+    };
+    ($command:expr $(,$args:expr)*) => {
+        {
+            let mut cmd = tokio::process::Command::new($command);
+            $(cmd.arg($args);)*
+            let mut debug_string: Vec<String> = vec![format!("{}", $command)];
+            $(
+                debug_string.push(format!("{}", $args));
+            )*
+            let debug_string: String = debug_string.join(" ");
+            os::process::exec_stdout(None::<&[u8]>, cmd, debug_string, os::process::Options::default())
+        }
+    }
+}
+
+/// cmd_output runs a command just as does [cmd](crate::cmd), but returns its entire
+/// [CmdOutput](CmdOutput) (stdout, stderr, exit code, and duration) rather than just its stdout.
+/// A non-zero exit code is NOT an error, so that callers may tell one exit code from another.
+///
+/// ```ignore
+/// let output = cmd_output!("ctr", "images", "remove", &image).await.unwrap();
+/// if output.exit_code == Some(1) {
+///     warn!("{} took {:?} and failed, {}", image, output.duration, output.stderr);
+/// }
+/// ```
+#[macro_export]
+macro_rules! cmd_output {
+    (options=$options:expr, stdin=$stdin:expr, $command:expr $(,$args:expr)*) => {
+        {
+            let mut cmd = tokio::process::Command::new($command);
+            $(cmd.arg($args);)*
+            let mut debug_string: Vec<String> = vec![format!("{}", $command)];
+            $(
+                debug_string.push(format!("{}", $args));
+            )*
+            let debug_string: String = debug_string.join(" ");
+            os::process::exec_with(Some($stdin), cmd, debug_string, $options)
+        }
+    };
+    (options=$options:expr, $command:expr $(,$args:expr)*) => {
+        {
+            let mut cmd = tokio::process::Command::new($command);
+            $(cmd.arg($args);)*
+            let mut debug_string: Vec<String> = vec![format!("{}", $command)];
+            $(
+                debug_string.push(format!("{}", $args));
+            )*
+            let debug_string: String = debug_string.join(" ");
+            os::process::exec_with(None::<&[u8]>, cmd, debug_string, $options)
+        }
+    };
+    (stdin=$stdin:expr, $command:expr $(,$args:expr)*) => {
+        {
+            let mut cmd = tokio::process::Command::new($command);
+            $(cmd.arg($args);)*
+            let mut debug_string: Vec<String> = vec![format!("{}", $command)];
+            $(
+                debug_string.push(format!("{}", $args));
+            )*
+            let debug_string: String = debug_string.join(" ");
+            os::process::exec(Some($stdin), cmd, debug_string)
         }
     };
     ($command:expr $(,$args:expr)*) => {
@@ -59,11 +178,239 @@ macro_rules! cmd {
     }
 }
 
+/// The placeholder that stands in for every secret within the debug string of a command.
+pub const REDACTED: &str = "<REDACTED>";
+
+/// Options tune how a single command is run by [exec_with](exec_with).
+///
+/// Secrets given to the options (be they [environment variables](Options::secret_env) or
+/// [arguments](Options::redact)) are replaced by [REDACTED](REDACTED) wherever the command is
+/// described, be it within logs or within errors. The same goes for the options' `Debug`.
+#[derive(Clone, Default)]
+pub struct Options {
+    timeout: Option<Duration>,
+    cancellation: Option<CancellationToken>,
+    env: Vec<(String, String)>,
+    current_dir: Option<PathBuf>,
+    secrets: Vec<String>,
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options::default()
+    }
+
+    /// Kills the command should it not finish within the given timeout, rather than within the
+    /// [default timeout](COMMAND_TIMEOUT). A timeout of zero never kills the command.
+    pub fn timeout(mut self, timeout: Duration) -> Options {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Kills the command as soon as the given token is cancelled.
+    pub fn cancellation(mut self, token: CancellationToken) -> Options {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Sets an environment variable of the command (atop those inherited from this process).
+    pub fn env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Options {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Sets an environment variable of the command whose value is a secret, such as
+    /// `AWS_SECRET_ACCESS_KEY`, such that it is never described.
+    pub fn secret_env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Options {
+        let value = value.into();
+        self.secrets.push(value.clone());
+        self.env(key, value)
+    }
+
+    /// Runs the command within the given directory rather than within that of this process.
+    pub fn current_dir<P: AsRef<Path>>(mut self, dir: P) -> Options {
+        self.current_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Marks the given value as a secret, such that any argument of the command that contains it
+    /// is described with the value redacted.
+    pub fn redact<S: Into<String>>(mut self, secret: S) -> Options {
+        self.secrets.push(secret.into());
+        self
+    }
+
+    /// Applies the environment and working directory of these options to the given command,
+    /// returning its debug string prefixed by its environment and with every secret redacted.
+    fn apply(&self, cmd: &mut Command, debug_string: String) -> String {
+        for (key, value) in &self.env {
+            cmd.env(key, value);
+        }
+        if let Some(dir) = &self.current_dir {
+            cmd.current_dir(dir);
+        }
+        let debug_string = self
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .chain(std::iter::once(debug_string))
+            .collect::<Vec<String>>()
+            .join(" ");
+        self.redacted(debug_string)
+    }
+
+    /// Replaces every secret within the given text with [REDACTED](REDACTED).
+    fn redacted(&self, mut text: String) -> String {
+        for secret in self.secrets.iter().filter(|secret| !secret.is_empty()) {
+            text = text.replace(secret.as_str(), REDACTED);
+        }
+        text
+    }
+
+    /// The timeout of these options, falling back to the [default](default_timeout).
+    fn resolved_timeout(&self) -> Option<Duration> {
+        match self.timeout {
+            Some(timeout) => Some(timeout).filter(|timeout| !timeout.is_zero()),
+            None => default_timeout(),
+        }
+    }
+
+    /// Resolves once the cancellation token (if any) is cancelled.
+    async fn cancelled(&self) {
+        match self.cancellation.as_ref() {
+            Some(token) => token.cancelled().await,
+            None => std::future::pending().await,
+        }
+    }
+}
+
+impl std::fmt::Debug for Options {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Options")
+            .field("timeout", &self.timeout)
+            .field("cancellation", &self.cancellation)
+            .field(
+                "env",
+                &self
+                    .env
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .map(|variable| self.redacted(variable))
+                    .collect::<Vec<String>>(),
+            )
+            .field("current_dir", &self.current_dir)
+            .finish()
+    }
+}
+
+/// Resolves once the given timeout (if any) has elapsed.
+async fn expire(timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => tokio::time::sleep(timeout).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Has the given command lead its own process group so that anything that it spawns may be killed
+/// alongside it by way of a [ProcessGroup](ProcessGroup).
+fn lead_process_group(cmd: &mut Command) {
+    unsafe {
+        cmd.pre_exec(|| match libc::setpgid(0, 0) {
+            0 => Ok(()),
+            _ => Err(std::io::Error::last_os_error()),
+        });
+    }
+}
+
+/// Returns the timeout configured by [COMMAND_TIMEOUT](COMMAND_TIMEOUT), or `None` should it be
+/// disabled.
+///
+/// This function PANICS if the variable is set to anything other than a non-negative integer.
+pub fn default_timeout() -> Option<Duration> {
+    let timeout = match std::env::var(COMMAND_TIMEOUT) {
+        Ok(value) if !value.trim().is_empty() => {
+            Duration::from_secs(value.trim().parse().unwrap_or_else(|_| {
+                panic!(
+                    "The {} environment variable must be a non-negative integer, got '{}'",
+                    COMMAND_TIMEOUT, value
+                )
+            }))
+        }
+        _ => DEFAULT_COMMAND_TIMEOUT,
+    };
+    Some(timeout).filter(|timeout| !timeout.is_zero())
+}
+
+/// The outcome of a command that ran to completion, be it successfully or not.
+#[derive(Clone, Debug)]
+pub struct CmdOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// The command's exit code, or `None` should it have been killed by a signal.
+    pub exit_code: Option<i32>,
+    /// How long the command took, from being spawned to exiting.
+    pub duration: Duration,
+    command: String,
+}
+
+impl CmdOutput {
+    /// Whether the command exited with a zero exit code.
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// Returns the command's stdout, without any trailing newlines or spaces, or a
+    /// [CommandFailed](CommandFailed) (which includes its stderr) should it have exited
+    /// unsuccessfully.
+    pub fn into_stdout(self) -> Result<String> {
+        if !self.success() {
+            return Err(CommandFailed {
+                command: self.command,
+                status: describe(self.exit_code),
+                stderr: self.stderr,
+            }
+            .into());
+        }
+        Ok(self.stdout.trim_end().to_string())
+    }
+}
+
+/// Runs the given command with the default [Options](Options).
 pub async fn exec<S: AsRef<[u8]>>(
     stdin: Option<S>,
-    mut cmd: Command,
+    cmd: Command,
+    debug_string: String,
+) -> Result<CmdOutput> {
+    exec_with(stdin, cmd, debug_string, Options::default()).await
+}
+
+/// Runs the given command, returning only its stdout (as does [cmd](crate::cmd)). Exiting
+/// unsuccessfully is an error, see [CmdOutput::into_stdout](CmdOutput::into_stdout).
+pub async fn exec_stdout<S: AsRef<[u8]>>(
+    stdin: Option<S>,
+    cmd: Command,
     debug_string: String,
+    options: Options,
 ) -> Result<String> {
+    exec_with(stdin, cmd, debug_string, options)
+        .await?
+        .into_stdout()
+}
+
+/// Runs the given command to completion, returning its [output](CmdOutput) regardless of its exit
+/// code. Should the command time out, or should it be cancelled, then it is killed alongside
+/// every process within its process group and either a [CommandTimedOut](CommandTimedOut) or a
+/// [CommandCancelled](CommandCancelled) is returned. The same goes for a command whose future is
+/// dropped before it finishes.
+pub async fn exec_with<S: AsRef<[u8]>>(
+    stdin: Option<S>,
+    mut cmd: Command,
+    debug_string: String,
+    options: Options,
+) -> Result<CmdOutput> {
+    let debug_string = options.apply(&mut cmd, debug_string);
+    let timeout = options.resolved_timeout();
+    lead_process_group(&mut cmd);
     cmd.stdout(Stdio::piped());
     cmd.stderr(Stdio::piped());
     cmd.stdin(if stdin.is_some() {
@@ -71,6 +418,7 @@ pub async fn exec<S: AsRef<[u8]>>(
     } else {
         Stdio::null()
     });
+    let start = Instant::now();
     let mut child = cmd.spawn().map_err(|err| FailedToSpawn {
         command: debug_string.clone(),
         source: err,
@@ -84,30 +432,83 @@ pub async fn exec<S: AsRef<[u8]>>(
             .await
             .unwrap();
     };
-    let output = child.wait_with_output().await.map_err(|err| FailedToRun {
+    let mut group = ProcessGroup { leader: child.id() };
+    // Returning early drops the ProcessGroup, which kills it.
+    let output = tokio::select! {
+        output = child.wait_with_output() => output,
+        _ = expire(timeout) => {
+            return Err(CommandTimedOut {
+                command: debug_string,
+                timeout: format!("{:?}", timeout.unwrap_or_default()),
+            }
+            .into())
+        }
+        _ = options.cancelled() => return Err(CommandCancelled { command: debug_string }.into()),
+    };
+    group.leader = None;
+    let duration = start.elapsed();
+    let output = output.map_err(|err| FailedToRun {
         command: debug_string.clone(),
         source: err,
     })?;
-    if !output.status.success() {
-        let stderr_result = String::from_utf8(output.stderr.clone());
-        let stderr = stderr_result.map_err(|err| InvalidUTF8Stderr {
-            command: debug_string.clone(),
-            output: format!("{}", String::from_utf8_lossy(&output.stderr)),
-            source: err,
-        })?;
-        return Err(CommandFailed {
-            command: debug_string.clone(),
-            stderr,
-        }
-        .into());
-    }
+    let stderr_result = String::from_utf8(output.stderr.clone());
+    let stderr = stderr_result.map_err(|err| InvalidUTF8Stderr {
+        command: debug_string.clone(),
+        output: format!("{}", String::from_utf8_lossy(&output.stderr)),
+        source: err,
+    })?;
     let stdout_result = String::from_utf8(output.stdout.clone());
     let stdout = stdout_result.map_err(|err| InvalidUTF8 {
         command: debug_string.clone(),
         output: format!("{}", String::from_utf8_lossy(&output.stdout)),
         source: err,
     })?;
-    Ok(stdout.trim_end().to_string())
+    Ok(CmdOutput {
+        stdout,
+        stderr,
+        exit_code: output.status.code(),
+        duration,
+        command: debug_string,
+    })
+}
+
+/// Describes how a command exited, given its exit code.
+fn describe(exit_code: Option<i32>) -> String {
+    match exit_code {
+        Some(code) => format!("exit code {}", code),
+        None => "a signal".to_string(),
+    }
+}
+
+/// A ProcessGroup kills every process within the group of its leader upon drop, unless the
+/// leader has since exited (in which case the leader is forgotten).
+struct ProcessGroup {
+    leader: Option<u32>,
+}
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        if let Some(leader) = self.leader {
+            unsafe {
+                libc::killpg(leader as libc::pid_t, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[error(r#"The "{command}" command did not finish within {timeout} and was killed."#)]
+#[code(Status::GatewayTimeout)]
+pub struct CommandTimedOut {
+    command: String,
+    timeout: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[error(r#"The "{command}" command was cancelled and was killed before it finished."#)]
+#[code(Status::Conflict)]
+pub struct CommandCancelled {
+    command: String,
 }
 
 #[derive(Error, AcmError, Kind, HttpCode, Debug)]
@@ -155,10 +556,11 @@ struct FailedToRun {
 }
 
 #[derive(Error, AcmError, Kind, HttpCode, Debug)]
-#[error(r#"Failed to execute "{command}". Stderr was {stderr}"#)]
+#[error(r#"Failed to execute "{command}", it exited with {status}. Stderr was {stderr}"#)]
 #[code(Status::InternalServerError)]
 struct CommandFailed {
     command: String,
+    status: String,
     stderr: String,
 }
 
@@ -166,6 +568,7 @@ struct CommandFailed {
 mod tests {
 
     use crate as os;
+    use error::Kind;
 
     #[tokio::test]
     async fn ls() {
@@ -188,4 +591,80 @@ mod tests {
     async fn test_stdin() {
         assert_eq!("hello!", cmd!(stdin = "hello!", "cat").await.unwrap());
     }
+
+    #[tokio::test]
+    async fn times_out() {
+        let options = os::process::Options::new().timeout(std::time::Duration::from_millis(100));
+        let start = std::time::Instant::now();
+        let err = cmd!(options = options, "sleep", "10").await.unwrap_err();
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        assert_eq!(err.kind(), "CommandTimedOut");
+    }
+
+    #[tokio::test]
+    async fn cancels() {
+        let token = os::process::CancellationToken::new();
+        let options = os::process::Options::new().cancellation(token.clone());
+        let cancel = async {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            token.cancel();
+        };
+        let (result, _) = tokio::join!(cmd!(options = options, "sleep", "10"), cancel);
+        assert_eq!(result.unwrap_err().kind(), "CommandCancelled");
+    }
+
+    #[tokio::test]
+    async fn environment_and_directory() {
+        let options = os::process::Options::new()
+            .env("GREETING", "hello")
+            .current_dir("/");
+        assert_eq!(
+            "hello /",
+            cmd!(options = options, "sh", "-c", "echo $GREETING $(pwd)")
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn redacts_secrets() {
+        let options = os::process::Options::new()
+            .secret_env("PASSWORD", "hunter2")
+            .redact("s3cr3t");
+        let err = cmd!(
+            options = options.clone(),
+            "sh",
+            "-c",
+            "exit 1",
+            "-u",
+            "admin:s3cr3t"
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+        assert!(!err.contains("hunter2"), "{}", err);
+        assert!(!err.contains("s3cr3t"), "{}", err);
+        assert!(err.contains("PASSWORD=<REDACTED>"), "{}", err);
+        assert!(!format!("{:?}", options).contains("hunter2"));
+    }
+
+    #[tokio::test]
+    async fn output() {
+        let output = cmd_output!("sh", "-c", "echo out; echo err >&2; exit 3")
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+        assert_eq!(output.exit_code, Some(3));
+        assert!(!output.success());
+        let err = output.into_stdout().unwrap_err();
+        assert_eq!(err.kind(), "CommandFailed");
+        assert!(err.to_string().contains("exit code 3"));
+    }
+
+    #[tokio::test]
+    async fn zero_timeout_never_expires() {
+        let options = os::process::Options::new().timeout(std::time::Duration::ZERO);
+        assert_eq!("hi", cmd!(options = options, "echo", "hi").await.unwrap());
+    }
 }
@@ -0,0 +1,250 @@
+use super::*;
+use futures::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc;
+
+/// How many lines may be read ahead of the consumer of a [Streaming](Streaming) before the command
+/// is left to block on its own output.
+const BUFFERED_LINES: usize = 256;
+
+/// How many of the last lines of stderr are kept for the [CommandFailed](CommandFailed) of a
+/// streamed command.
+const STDERR_TAIL: usize = 64;
+
+/// cmd_streaming runs any arbitrary system command asynchronously and returns a
+/// [Streaming](Streaming) of every line that it writes to stdout and stderr, as it writes them.
+///
+/// The stream ends once the command exits successfully. Otherwise, the stream's final item is the
+/// error that ended the command. Should the command fail, then that error includes the last lines
+/// of its stderr.
+///
+/// ```ignore
+/// let mut lines = cmd_streaming!("ctr", "images", "push", &image);
+/// while let Some(line) = lines.next().await {
+///     debug!("{}", line?);
+/// }
+/// ```
+///
+/// Commands are given [Options](Options) just as they are with [cmd](crate::cmd), and dropping
+/// the stream kills the command alongside any processes that it spawned.
+#[macro_export]
+macro_rules! cmd_streaming {
+    (options=$options:expr, $command:expr $(,$args:expr)*) => {
+        {
+            let mut cmd = tokio::process::Command::new($command);
+            $(cmd.arg($args);)*
+            let mut debug_string: Vec<String> = vec![format!("{}", $command)];
+            $(
+                debug_string.push(format!("{}", $args));
+            )*
+            let debug_string: String = debug_string.join(" ");
+            os::process::exec_streaming(cmd, debug_string, $options)
+        }
+    };
+    ($command:expr $(,$args:expr)*) => {
+        {
+            let mut cmd = tokio::process::Command::new($command);
+            $(cmd.arg($args);)*
+            let mut debug_string: Vec<String> = vec![format!("{}", $command)];
+            $(
+                debug_string.push(format!("{}", $args));
+            )*
+            let debug_string: String = debug_string.join(" ");
+            os::process::exec_streaming(cmd, debug_string, os::process::Options::default())
+        }
+    }
+}
+
+/// A single line of a streamed command's output, without its trailing newline.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Line {
+    Stdout(String),
+    Stderr(String),
+}
+
+impl std::fmt::Display for Line {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Line::Stdout(line) | Line::Stderr(line) => write!(f, "{}", line),
+        }
+    }
+}
+
+/// Streaming is the [Stream](futures::Stream) of the output of a command started by
+/// [exec_streaming](exec_streaming).
+pub struct Streaming {
+    lines: mpsc::Receiver<Result<Line>>,
+}
+
+impl Stream for Streaming {
+    type Item = Result<Line>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.lines.poll_recv(cx)
+    }
+}
+
+/// Starts the given command, returning the [Streaming](Streaming) of its output. The command is
+/// read from in the background (so this must be called from within a Tokio runtime) and is
+/// killed alongside every process within its process group should it time out, should it be
+/// cancelled, or should the stream be dropped before the command finishes.
+///
+/// Streamed commands are never given a stdin.
+pub fn exec_streaming(cmd: Command, debug_string: String, options: Options) -> Streaming {
+    let (sender, lines) = mpsc::channel(BUFFERED_LINES);
+    tokio::spawn(async move {
+        if let Err(err) = stream(cmd, debug_string, options, &sender).await {
+            // Nobody is left to tell should the stream have been dropped.
+            let _ = sender.send(Err(err)).await;
+        }
+    });
+    Streaming { lines }
+}
+
+async fn stream(
+    mut cmd: Command,
+    debug_string: String,
+    options: Options,
+    sender: &mpsc::Sender<Result<Line>>,
+) -> Result<()> {
+    let debug_string = options.apply(&mut cmd, debug_string);
+    let timeout = options.resolved_timeout();
+    lead_process_group(&mut cmd);
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    cmd.stdin(Stdio::null());
+    let mut child = cmd.spawn().map_err(|err| FailedToSpawn {
+        command: debug_string.clone(),
+        source: err,
+    })?;
+    let mut group = ProcessGroup { leader: child.id() };
+    let failed = |err: std::io::Error| FailedToRun {
+        command: debug_string.clone(),
+        source: err,
+    };
+    let timed_out = || CommandTimedOut {
+        command: debug_string.clone(),
+        timeout: format!("{:?}", timeout.unwrap_or_default()),
+    };
+    let cancelled = || CommandCancelled {
+        command: debug_string.clone(),
+    };
+    let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap()).lines();
+    let (mut stdout_open, mut stderr_open) = (true, true);
+    let mut tail: VecDeque<String> = VecDeque::with_capacity(STDERR_TAIL);
+    let expiry = expire(timeout);
+    let cancellation = options.cancelled();
+    tokio::pin!(expiry);
+    tokio::pin!(cancellation);
+    // Returning early drops the ProcessGroup, which kills it.
+    while stdout_open || stderr_open {
+        let line = tokio::select! {
+            line = stdout.next_line(), if stdout_open => match line.map_err(failed)? {
+                Some(line) => Line::Stdout(line),
+                None => {
+                    stdout_open = false;
+                    continue;
+                }
+            },
+            line = stderr.next_line(), if stderr_open => match line.map_err(failed)? {
+                Some(line) => {
+                    if tail.len() == STDERR_TAIL {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line.clone());
+                    Line::Stderr(line)
+                }
+                None => {
+                    stderr_open = false;
+                    continue;
+                }
+            },
+            _ = &mut expiry => return Err(timed_out().into()),
+            _ = &mut cancellation => return Err(cancelled().into()),
+            _ = sender.closed() => return Ok(()),
+        };
+        if sender.send(Ok(line)).await.is_err() {
+            return Ok(());
+        }
+    }
+    let status = tokio::select! {
+        status = child.wait() => status,
+        _ = &mut expiry => return Err(timed_out().into()),
+        _ = &mut cancellation => return Err(cancelled().into()),
+    };
+    group.leader = None;
+    let status = status.map_err(failed)?;
+    if !status.success() {
+        return Err(CommandFailed {
+            command: debug_string,
+            status: describe(status.code()),
+            stderr: Vec::from(tail).join("\n"),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Line;
+    use crate as os;
+    use error::Kind;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn streams() {
+        let lines: Vec<Line> = cmd_streaming!("sh", "-c", "echo one; echo two >&2; echo three")
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        let stdout: Vec<&Line> = lines
+            .iter()
+            .filter(|line| matches!(line, Line::Stdout(_)))
+            .collect();
+        assert_eq!(
+            stdout,
+            vec![
+                &Line::Stdout("one".to_string()),
+                &Line::Stdout("three".to_string())
+            ]
+        );
+        assert!(lines.contains(&Line::Stderr("two".to_string())));
+    }
+
+    #[tokio::test]
+    async fn streams_before_exit() {
+        let mut lines = cmd_streaming!("sh", "-c", "echo early; sleep 10");
+        let start = std::time::Instant::now();
+        assert_eq!(
+            lines.next().await.unwrap().unwrap(),
+            Line::Stdout("early".to_string())
+        );
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn ends_with_failure() {
+        let results: Vec<_> = cmd_streaming!("sh", "-c", "echo oops >&2; exit 1")
+            .collect()
+            .await;
+        let err = results.last().unwrap().as_ref().unwrap_err();
+        assert_eq!(err.kind(), "CommandFailed");
+        assert!(err.to_string().contains("oops"));
+    }
+
+    #[tokio::test]
+    async fn streaming_times_out() {
+        let options = os::process::Options::new().timeout(std::time::Duration::from_millis(100));
+        let results: Vec<_> = cmd_streaming!(options = options, "sleep", "10")
+            .collect()
+            .await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_ref().unwrap_err().kind(), "CommandTimedOut");
+    }
+}
@@ -0,0 +1,304 @@
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::request::Request;
+use std::io::Write;
+
+/// The default size (in bytes) below which response bodies are not worth compressing.
+pub const DEFAULT_THRESHOLD: usize = 1024;
+
+/// Compression is a [Fairing](rocket::fairing::Fairing) which compresses response bodies with
+/// either brotli or gzip, whichever the client prefers by way of its `Accept-Encoding` header.
+/// Brotli is chosen when the client accepts both equally as it compresses JSON considerably
+/// better.
+///
+/// Bodies smaller than the configured threshold, bodies of an unknown size, and bodies that
+/// have already been encoded are left untouched, as are the responses that were
+/// [exempted](Compression::exempt). Every other response carries a `Vary: Accept-Encoding`,
+/// whether or not it was compressed.
+///
+/// ```no_run
+/// use response::Compression;
+///
+/// rocket::build().attach(Compression::default());
+/// ```
+pub struct Compression {
+    threshold: usize,
+}
+
+impl Compression {
+    /// Constructs a Compression fairing that only compresses bodies of at least `threshold` bytes.
+    pub fn new(threshold: usize) -> Compression {
+        Compression { threshold }
+    }
+
+    /// Exempts the response to the given request from compression, such that it reaches the
+    /// client exactly as its responder built it. Responders of bodies that are passed through from
+    /// elsewhere SHOULD call this.
+    pub fn exempt(request: &Request<'_>) {
+        request.local_cache(|| Exempt(true));
+    }
+}
+
+/// Whether the response to a request was [exempted](Compression::exempt) from compression.
+struct Exempt(bool);
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::new(DEFAULT_THRESHOLD)
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for Compression {
+    fn info(&self) -> Info {
+        Info {
+            name: "Response Compression",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut rocket::Response<'r>) {
+        if request.local_cache(|| Exempt(false)).0
+            || response.headers().contains("Content-Encoding")
+        {
+            return;
+        }
+        match response.body().preset_size() {
+            Some(size) if size >= self.threshold => (),
+            _ => return,
+        };
+        // Whether this body is compressed depends upon the client's Accept-Encoding, so caches must
+        // key it by that header even when it is sent as is.
+        response.adjoin_header(Header::new("Vary", "Accept-Encoding"));
+        let encoding = match request
+            .headers()
+            .get("Accept-Encoding")
+            .filter_map(Encoding::preferred)
+            .max()
+        {
+            Some(encoding) => encoding,
+            None => return,
+        };
+        let body = match response.body_mut().to_bytes().await {
+            Ok(body) => body,
+            Err(_) => return,
+        };
+        match encoding.compress(&body) {
+            Ok(compressed) => {
+                response.set_header(Header::new("Content-Encoding", encoding.name()));
+                response.set_sized_body(compressed.len(), std::io::Cursor::new(compressed));
+            }
+            // The body was already drained, so hand it back untouched.
+            Err(_) => response.set_sized_body(body.len(), std::io::Cursor::new(body)),
+        }
+    }
+}
+
+/// An Encoding is a content coding that the [Compression](Compression) fairing supports.
+/// They are ordered by preference.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+enum Encoding {
+    Gzip,
+    Brotli,
+}
+
+impl Encoding {
+    /// Returns the most preferred supported encoding within the given `Accept-Encoding` header
+    /// value. Codings are weighted by their `q` parameter, where `q=0` means "not acceptable".
+    fn preferred(header: &str) -> Option<Encoding> {
+        header
+            .split(',')
+            .filter_map(|coding| {
+                let mut parts = coding.split(';').map(str::trim);
+                let encoding = match parts.next()?.to_ascii_lowercase().as_str() {
+                    "br" => Encoding::Brotli,
+                    "gzip" | "x-gzip" => Encoding::Gzip,
+                    _ => return None,
+                };
+                let quality = parts
+                    .find_map(|param| param.strip_prefix("q="))
+                    .map(|q| q.trim().parse::<f32>().unwrap_or(0.0))
+                    .unwrap_or(1.0);
+                if quality > 0.0 {
+                    Some(((quality * 1000.0) as u32, encoding))
+                } else {
+                    None
+                }
+            })
+            .max()
+            .map(|(_, encoding)| encoding)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Brotli => "br",
+        }
+    }
+
+    fn compress(self, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            Encoding::Brotli => {
+                let mut compressed = Vec::new();
+                {
+                    // Quality 5 with a 4MB window is a good trade between speed and size for JSON.
+                    let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+                    encoder.write_all(body)?;
+                    encoder.flush()?;
+                }
+                Ok(compressed)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Response;
+    use result::Result;
+    use rocket::get;
+    use rocket::local::blocking::Client;
+    use rocket::routes;
+    use std::io::Read;
+
+    /// The routes whose bodies are compressed (or not). Rocket re-exports a URI macro for each
+    /// route, which goes unused here.
+    #[allow(unused_imports)]
+    mod handlers {
+        use super::*;
+
+        #[get("/big")]
+        pub(super) async fn big() -> Result<Response<Vec<String>>> {
+            Ok(vec!["Hello, Alation!".to_string(); 1000].into())
+        }
+
+        #[get("/passthrough")]
+        pub(super) async fn passthrough() -> Passthrough {
+            Passthrough(vec![b'a'; 4 * DEFAULT_THRESHOLD])
+        }
+
+        #[get("/small")]
+        pub(super) async fn small() -> Result<Response<String>> {
+            Ok("Hello, Alation!".to_string().into())
+        }
+    }
+
+    /// A body that is passed through as is, just as the ACM's proxied responses are.
+    struct Passthrough(Vec<u8>);
+
+    impl<'r, 'o: 'r> rocket::response::Responder<'r, 'o> for Passthrough {
+        fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'o> {
+            Compression::exempt(request);
+            self.0.respond_to(request)
+        }
+    }
+
+    fn client() -> Client {
+        Client::tracked(
+            rocket::build()
+                .mount(
+                    "/",
+                    routes![handlers::big, handlers::small, handlers::passthrough],
+                )
+                .attach(Compression::default()),
+        )
+        .unwrap()
+    }
+
+    fn fetch(
+        client: &Client,
+        uri: &'static str,
+        accept: &'static str,
+    ) -> (Option<String>, Vec<u8>) {
+        let (encoding, _, body) = fetch_varying(client, uri, accept);
+        (encoding, body)
+    }
+
+    /// Fetches the given URI, returning its `Content-Encoding` and `Vary` headers alongside its body.
+    fn fetch_varying(
+        client: &Client,
+        uri: &'static str,
+        accept: &'static str,
+    ) -> (Option<String>, Option<String>, Vec<u8>) {
+        let response = client
+            .get(uri)
+            .header(Header::new("Accept-Encoding", accept))
+            .dispatch();
+        let header = |name| response.headers().get_one(name).map(str::to_string);
+        let (encoding, vary) = (header("Content-Encoding"), header("Vary"));
+        (encoding, vary, response.into_bytes().unwrap())
+    }
+
+    #[test]
+    fn gzip() {
+        let (encoding, body) = fetch(&client(), "/big", "gzip");
+        assert_eq!(encoding.as_deref(), Some("gzip"));
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(body.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert!(decoded.contains("Hello, Alation!"));
+    }
+
+    #[test]
+    fn brotli() {
+        let (encoding, body) = fetch(&client(), "/big", "gzip, deflate, br");
+        assert_eq!(encoding.as_deref(), Some("br"));
+        let mut decoded = String::new();
+        brotli::Decompressor::new(body.as_slice(), 4096)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert!(decoded.contains("Hello, Alation!"));
+    }
+
+    #[test]
+    fn below_threshold() {
+        let (encoding, body) = fetch(&client(), "/small", "gzip");
+        assert_eq!(encoding, None);
+        assert!(String::from_utf8(body).unwrap().contains("Hello, Alation!"));
+    }
+
+    #[test]
+    fn not_accepted() {
+        let (encoding, _) = fetch(&client(), "/big", "identity");
+        assert_eq!(encoding, None);
+    }
+
+    #[test]
+    fn exempted() {
+        let (encoding, vary, body) = fetch_varying(&client(), "/passthrough", "gzip, br");
+        assert_eq!(encoding, None);
+        assert_eq!(vary, None);
+        assert_eq!(body, vec![b'a'; 4 * DEFAULT_THRESHOLD]);
+    }
+
+    #[test]
+    fn varies_by_accept_encoding() {
+        let client = client();
+        for accept in &["gzip", "br", "identity"] {
+            let (_, vary, _) = fetch_varying(&client, "/big", accept);
+            assert_eq!(vary.as_deref(), Some("Accept-Encoding"), "{}", accept);
+        }
+        let (_, vary, _) = fetch_varying(&client, "/small", "gzip");
+        assert_eq!(vary, None);
+    }
+
+    #[test]
+    fn preference() {
+        assert_eq!(Encoding::preferred("gzip"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::preferred("gzip, br"), Some(Encoding::Brotli));
+        assert_eq!(
+            Encoding::preferred("gzip;q=1.0, br;q=0.5"),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(Encoding::preferred("br;q=0, gzip"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::preferred("deflate, identity"), None);
+    }
+}
@@ -1,8 +1,17 @@
+mod compression;
+mod parsed;
+
+pub use compression::Compression;
+pub use parsed::{GenericError, ParsedResponse};
+
 use kind::Kind;
+use negotiation::Format;
+use rocket::http::{Header, Status};
 use rocket::request::Request;
 use rocket::response::Responder;
 use serde::Serialize;
-use serde_json::{json, to_string_pretty};
+use serde_json::json;
+use std::borrow::Cow;
 
 /// A Response may be constructed from any type that implements both
 /// [Serialize](serde::Serialize) and [Kind](kind::Kind).
@@ -31,6 +40,110 @@ use serde_json::{json, to_string_pretty};
 /// ```
 pub struct Response<T> {
     payload: T,
+    status: Status,
+    headers: Vec<Header<'static>>,
+    etag: Option<String>,
+    warnings: Vec<String>,
+}
+
+impl<T: Serialize + Kind> Response<T> {
+    /// Constructs a `201 Created` Response. Such a Response SHOULD also carry the
+    /// [location](Response::location) of the newly created resource.
+    ///
+    /// ```
+    /// use response::Response;
+    /// use result::Result;
+    /// use rocket::post;
+    ///
+    /// #[post("/greeting")]
+    /// async fn create() -> Result<Response<String>> {
+    ///     let greeting = "Hello, Alation!".to_string();
+    ///     Ok(Response::created(greeting).location("/greeting?id=1"))
+    /// }
+    /// ```
+    pub fn created(payload: T) -> Self {
+        Self::from(payload).status(Status::Created)
+    }
+
+    /// Constructs a `202 Accepted` Response, which is to say that the request has been accepted
+    /// but that its processing has not yet completed.
+    pub fn accepted(payload: T) -> Self {
+        Self::from(payload).status(Status::Accepted)
+    }
+}
+
+impl<T> Response<T> {
+    /// Sets the HTTP status of this Response, which is otherwise `200 OK`.
+    pub fn status(mut self, status: Status) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Attaches an arbitrary header to this Response. Attaching the same header more than once
+    /// results in a header with multiple values.
+    ///
+    /// The `Content-Type` is always that of the negotiated format and cannot be overridden.
+    pub fn header<N, V>(mut self, name: N, value: V) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+        V: Into<Cow<'static, str>>,
+    {
+        self.headers.push(Header::new(name, value));
+        self
+    }
+
+    /// Attaches a [Location](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Location)
+    /// header to this Response, typically the URI at which a created resource may be retrieved.
+    pub fn location<U: Into<String>>(self, uri: U) -> Self {
+        self.header("Location", uri.into())
+    }
+
+    /// Attaches an [ETag](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/ETag) to this
+    /// Response. The given tag should be an opaque fingerprint of the payload's state (such as a
+    /// hash) and is quoted automatically.
+    ///
+    /// A Response carrying an ETag honors the `If-None-Match` header of the incoming request.
+    /// That is, if the client already holds the current tag then a `304 Not Modified` with an
+    /// empty body is returned in lieu of the payload.
+    ///
+    /// ```
+    /// use response::Response;
+    /// use result::Result;
+    /// use rocket::get;
+    ///
+    /// #[get("/")]
+    /// async fn greet() -> Result<Response<String>> {
+    ///     let greeting = "Hello, Alation!".to_string();
+    ///     Ok(Response::from(greeting).with_etag("v1"))
+    /// }
+    /// ```
+    pub fn with_etag<E: Into<String>>(mut self, etag: E) -> Self {
+        self.etag = Some(format!("\"{}\"", etag.into()));
+        self
+    }
+
+    /// Attaches a warning to this Response. Warnings are for conditions that the caller ought
+    /// to know about (such as having deployed a deprecated image) but which did not prevent the
+    /// request from succeeding.
+    ///
+    /// Warnings are serialized as a `warnings` list alongside the payload. The list is omitted
+    /// entirely from responses that carry no warnings.
+    ///
+    /// ```
+    /// use response::Response;
+    /// use result::Result;
+    /// use rocket::get;
+    ///
+    /// #[get("/")]
+    /// async fn greet() -> Result<Response<String>> {
+    ///     let greeting = "Hello, Alation!".to_string();
+    ///     Ok(Response::from(greeting).with_warning("this greeting is deprecated"))
+    /// }
+    /// ```
+    pub fn with_warning<W: Into<String>>(mut self, warning: W) -> Self {
+        self.warnings.push(warning.into());
+        self
+    }
 }
 
 /// A Response may be constructed from any type that implements both
@@ -38,62 +151,159 @@ pub struct Response<T> {
 /// this blanket implementation.
 impl<T: Serialize + Kind> From<T> for Response<T> {
     fn from(payload: T) -> Self {
-        Self { payload }
+        Self {
+            payload,
+            status: Status::Ok,
+            headers: vec![],
+            etag: None,
+            warnings: vec![],
+        }
     }
 }
 
 /// The [Responder](rocket::response::Responder) implementation for a [Response](crate::Response)
 /// does three things:
 ///
-/// 1. Sets the content type to JSON.
-/// 2. Sets the HTTP status to 200 (OK).
+/// 1. Sets the content type to that [negotiated](negotiation::Format::negotiate) via the
+///    request's `Accept` header. That is, YAML, compact JSON, or (by default) pretty printed JSON.
+/// 2. Sets the HTTP status to that of the Response (200 OK unless otherwise [set](Response::status)).
 /// 3. Serializes the aggregated data and sends the resulting bytes over the wire.
 ///
+/// Any [headers](Response::header) are sent as given. Any [warnings](Response::with_warning) are included as a `warnings` list.
+///
+/// If an [ETag](Response::with_etag) is attached then it is sent as the `ETag` header, and a
+/// request whose `If-None-Match` names that tag receives a `304 Not Modified` with no body.
+///
+/// `HEAD` requests against `GET` routes are dispatched to the `GET` handler by Rocket, which
+/// strips the body afterwards. As the body is sized, the client still receives the same
+/// `Content-Length`, `ETag`, and (potentially) `304` that the equivalent `GET` would have.
+///
 /// The resulting serialization is the following schema.
 ///
 /// ```ignore
 /// {
 ///     "payload": {<object>},
-///     "error": null
+///     "error": null,
+///     "warnings": [<string>, ...] // Only if there are any.
 /// }
 /// ```
 impl<'r, 'o: 'r, T: Serialize + Kind> Responder<'r, 'o> for Response<T> {
-    fn respond_to(self, _: &'r Request<'_>) -> rocket::response::Result<'o> {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'o> {
         let mut response = rocket::Response::build();
-        response.header(rocket::http::ContentType::JSON);
-        response.status(rocket::http::Status::Ok);
-        let json = json!({
+        for header in self.headers {
+            response.header_adjoin(header);
+        }
+        if let Some(etag) = self.etag.as_ref() {
+            // The same payload may be rendered in any number of formats.
+            response.header(Header::new("Vary", "Accept"));
+            response.header(Header::new("ETag", etag.clone()));
+            let fresh = request
+                .headers()
+                .get("If-None-Match")
+                .any(|candidates| if_none_match(candidates, etag));
+            if fresh {
+                response.status(Status::NotModified);
+                return Ok(response.finalize());
+            }
+        }
+        let format = Format::negotiate(request);
+        response.header(format.content_type());
+        response.status(self.status);
+        let mut json = json!({
             "payload": {
                 "kind": self.payload.kind(),
                 "object": self.payload
             },
             "error": null,
         });
-        // @TODO it MIGHT be possible to fail here? No idea how. If so, can read the error here
-        // and return that instead. I just have no idea what could ever cause it.
-        let json =
-            to_string_pretty(&json).unwrap_or_else(|_| panic!("failed to pretty print {}", json));
-        response.sized_body(json.len(), std::io::Cursor::new(json));
+        if !self.warnings.is_empty() {
+            json["warnings"] = json!(self.warnings);
+        }
+        let body = format.render(&json);
+        response.sized_body(body.len(), std::io::Cursor::new(body));
         Ok(response.finalize())
     }
 }
 
+/// Returns whether the given `If-None-Match` header value names the given (quoted) ETag.
+/// The header may be a comma separated list of tags, any of which may be weak (`W/"..."`),
+/// or the wildcard `*`.
+fn if_none_match(candidates: &str, etag: &str) -> bool {
+    candidates.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use error::{httpcode, AcmError, Error, HttpCode, StringError};
     use result::Result;
     use rocket::get;
     use rocket::local::blocking::Client;
     use rocket::routes;
 
-    #[get("/")]
-    async fn greet() -> Result<Response<String>> {
-        Ok("Hello, Alation!".to_string().into())
+    /// The routes that respond with the envelopes under test. Rocket re-exports a URI macro for
+    /// each route, which goes unused here.
+    #[allow(unused_imports)]
+    mod handlers {
+        use super::*;
+
+        #[get("/")]
+        pub(super) async fn greet() -> Result<Response<String>> {
+            Ok("Hello, Alation!".to_string().into())
+        }
+
+        #[get("/")]
+        pub(super) async fn get_pod() -> Result<Response<Pod>> {
+            Ok(Pod {
+                name: "Bob".to_string(),
+                metadata: Metadata {
+                    number: 1,
+                    bool: true,
+                    arr: vec!["this".to_string(), "and".to_string(), "that".to_string()],
+                },
+            }
+            .into())
+        }
+
+        #[get("/")]
+        pub(super) async fn missing() -> Result<Response<Pod>> {
+            Err(Missing {
+                cause: "it was never there".into(),
+            }
+            .into())
+        }
+
+        #[get("/")]
+        pub(super) async fn tagged() -> Result<Response<String>> {
+            Ok(Response::from("Hello, Alation!".to_string()).with_etag("abc123"))
+        }
+
+        #[get("/")]
+        pub(super) async fn warned() -> Result<Response<String>> {
+            Ok(Response::from("Hello, Alation!".to_string())
+                .with_warning("first")
+                .with_warning("second"))
+        }
+
+        #[get("/")]
+        pub(super) async fn created() -> Result<Response<String>> {
+            Ok(Response::created("Hello, Alation!".to_string())
+                .location("/greeting?id=1")
+                .header("X-Greeting", "hello")
+                .header("X-Greeting", "hi"))
+        }
+
+        #[get("/")]
+        pub(super) async fn accepted() -> Result<Response<String>> {
+            Ok(Response::accepted("Hello, Alation!".to_string()))
+        }
     }
 
     #[test]
     fn test_string() {
-        let client = Client::tracked(rocket::build().mount("/", routes![greet])).unwrap();
+        let client = Client::tracked(rocket::build().mount("/", routes![handlers::greet])).unwrap();
         let response = client.get("/").dispatch();
         assert_eq!(response.status(), rocket::http::Status::Ok);
         let got: serde_json::Value = response.into_json().unwrap();
@@ -120,22 +330,10 @@ mod tests {
         arr: Vec<String>,
     }
 
-    #[get("/")]
-    async fn get_pod() -> Result<Response<Pod>> {
-        Ok(Pod {
-            name: "Bob".to_string(),
-            metadata: Metadata {
-                number: 1,
-                bool: true,
-                arr: vec!["this".to_string(), "and".to_string(), "that".to_string()],
-            },
-        }
-        .into())
-    }
-
     #[test]
     fn test_struct() {
-        let client = Client::tracked(rocket::build().mount("/", routes![get_pod])).unwrap();
+        let client =
+            Client::tracked(rocket::build().mount("/", routes![handlers::get_pod])).unwrap();
         let response = client.get("/").dispatch();
         assert_eq!(response.status(), rocket::http::Status::Ok);
         let got: serde_json::Value = response.into_json().unwrap();
@@ -155,4 +353,191 @@ mod tests {
         });
         assert_eq!(got, want)
     }
+
+    #[test]
+    fn parse_payload() {
+        let client =
+            Client::tracked(rocket::build().mount("/", routes![handlers::get_pod])).unwrap();
+        let response = client.get("/").dispatch();
+        let status = response.status().code;
+        let body = response.into_bytes().unwrap();
+        let got: serde_json::Value = ParsedResponse::from_slice(status, &body)
+            .unwrap()
+            .into_result()
+            .unwrap();
+        assert_eq!(got["name"], "Bob");
+        assert_eq!(got["metadata"]["arr"][2], "that");
+    }
+
+    #[derive(Error, AcmError, Kind, HttpCode, Debug)]
+    #[error("Couldn't find it")]
+    #[code(rocket::http::Status::NotFound)]
+    #[error_code("TEST-0001")]
+    struct Missing {
+        #[source]
+        cause: StringError,
+    }
+
+    #[test]
+    fn parse_error() {
+        let client =
+            Client::tracked(rocket::build().mount("/", routes![handlers::missing])).unwrap();
+        let response = client.get("/").dispatch();
+        let status = response.status().code;
+        let body = response.into_bytes().unwrap();
+        let err = ParsedResponse::<serde_json::Value>::from_slice(status, &body)
+            .unwrap()
+            .into_result()
+            .unwrap_err();
+        assert_eq!(err.kind(), "Missing");
+        assert_eq!(err.error_code(), Some("TEST-0001"));
+        assert_eq!(err.http_code(), rocket::http::Status::NotFound);
+        assert_eq!(format!("{}", err), "Couldn't find it");
+        // Re-serializing the parsed error results in the original JSON.
+        let original: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let boxed: Box<dyn AcmError> = err.into();
+        assert_eq!(serde_json::json!(boxed), original["error"]);
+    }
+
+    #[test]
+    fn parse_neither() {
+        let body = r#"{"payload": null, "error": null}"#;
+        assert!(ParsedResponse::<()>::from_slice(200, body.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn etag() {
+        let client =
+            Client::tracked(rocket::build().mount("/", routes![handlers::tagged])).unwrap();
+        let response = client.get("/").dispatch();
+        assert_eq!(response.status(), rocket::http::Status::Ok);
+        assert_eq!(response.headers().get_one("ETag"), Some("\"abc123\""));
+        let got: String = ParsedResponse::from_slice(200, &response.into_bytes().unwrap())
+            .unwrap()
+            .into_result()
+            .unwrap();
+        assert_eq!(got, "Hello, Alation!");
+    }
+
+    #[test]
+    fn not_modified() {
+        let client =
+            Client::tracked(rocket::build().mount("/", routes![handlers::tagged])).unwrap();
+        for candidates in &["\"abc123\"", "W/\"abc123\"", "\"nope\", \"abc123\"", "*"] {
+            let response = client
+                .get("/")
+                .header(Header::new("If-None-Match", *candidates))
+                .dispatch();
+            assert_eq!(response.status(), rocket::http::Status::NotModified);
+            assert_eq!(response.headers().get_one("ETag"), Some("\"abc123\""));
+            assert!(response.into_bytes().unwrap_or_default().is_empty());
+        }
+        let response = client
+            .get("/")
+            .header(Header::new("If-None-Match", "\"stale\""))
+            .dispatch();
+        assert_eq!(response.status(), rocket::http::Status::Ok);
+    }
+
+    #[test]
+    fn warnings() {
+        let client =
+            Client::tracked(rocket::build().mount("/", routes![handlers::warned])).unwrap();
+        let got: serde_json::Value = client.get("/").dispatch().into_json().unwrap();
+        assert_eq!(got["warnings"], serde_json::json!(["first", "second"]));
+        assert_eq!(got["payload"]["object"], "Hello, Alation!");
+    }
+
+    #[test]
+    fn no_warnings() {
+        let client = Client::tracked(rocket::build().mount("/", routes![handlers::greet])).unwrap();
+        let got: serde_json::Value = client.get("/").dispatch().into_json().unwrap();
+        assert!(got.get("warnings").is_none());
+    }
+
+    #[test]
+    fn status_and_headers() {
+        let client =
+            Client::tracked(rocket::build().mount("/", routes![handlers::created])).unwrap();
+        let response = client.get("/").dispatch();
+        assert_eq!(response.status(), rocket::http::Status::Created);
+        assert_eq!(
+            response.headers().get_one("Location"),
+            Some("/greeting?id=1")
+        );
+        assert_eq!(
+            response.headers().get("X-Greeting").collect::<Vec<_>>(),
+            vec!["hello", "hi"]
+        );
+        assert_eq!(
+            response.content_type(),
+            Some(rocket::http::ContentType::JSON)
+        );
+        let got: String = ParsedResponse::from_slice(201, &response.into_bytes().unwrap())
+            .unwrap()
+            .into_result()
+            .unwrap();
+        assert_eq!(got, "Hello, Alation!");
+    }
+
+    #[test]
+    fn accepted_status() {
+        let client =
+            Client::tracked(rocket::build().mount("/", routes![handlers::accepted])).unwrap();
+        let response = client.get("/").dispatch();
+        assert_eq!(response.status(), rocket::http::Status::Accepted);
+    }
+
+    fn accepting<'c>(
+        client: &'c Client,
+        accept: &str,
+    ) -> rocket::local::blocking::LocalResponse<'c> {
+        client
+            .get("/")
+            .header(Header::new("Accept", accept.to_string()))
+            .dispatch()
+    }
+
+    #[test]
+    fn yaml() {
+        let client =
+            Client::tracked(rocket::build().mount("/", routes![handlers::get_pod])).unwrap();
+        let response = accepting(&client, "application/yaml");
+        assert_eq!(
+            response.content_type(),
+            Some(rocket::http::ContentType::new("application", "yaml"))
+        );
+        let got: serde_json::Value =
+            serde_yaml::from_str(&response.into_string().unwrap()).unwrap();
+        assert_eq!(got["payload"]["kind"], "Pod");
+        assert_eq!(got["payload"]["object"]["metadata"]["arr"][1], "and");
+    }
+
+    #[test]
+    fn compact_json() {
+        let client =
+            Client::tracked(rocket::build().mount("/", routes![handlers::get_pod])).unwrap();
+        let response = accepting(&client, "application/json; pretty=false");
+        assert_eq!(
+            response.content_type(),
+            Some(rocket::http::ContentType::JSON)
+        );
+        let body = response.into_string().unwrap();
+        assert!(!body.contains('\n'));
+        let got: serde_json::Value = ParsedResponse::from_slice(200, body.as_bytes())
+            .unwrap()
+            .into_result()
+            .unwrap();
+        assert_eq!(got["name"], "Bob");
+    }
+
+    #[test]
+    fn head() {
+        let client =
+            Client::tracked(rocket::build().mount("/", routes![handlers::tagged])).unwrap();
+        let response = client.head("/").dispatch();
+        assert_eq!(response.status(), rocket::http::Status::Ok);
+        assert_eq!(response.headers().get_one("ETag"), Some("\"abc123\""));
+        assert!(response.into_bytes().unwrap_or_default().is_empty());
+    }
 }
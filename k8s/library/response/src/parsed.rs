@@ -0,0 +1,170 @@
+use error::{AcmError, HttpCode, Kind, Status, StringError};
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer};
+use std::fmt::{Display, Formatter};
+
+/// A ParsedResponse is the deserialization side of the [Response](crate::Response) and
+/// `Box<dyn AcmError>` envelope. It allows Rust clients of the ACM and AIM to consume the
+/// envelope without hand rolling their own serde structures.
+///
+/// A successful response is parsed into [Payload](ParsedResponse::Payload) and carries the
+/// deserialized `object`, whereas a failure is parsed into [Error](ParsedResponse::Error) and
+/// carries a [GenericError](GenericError).
+///
+/// ```
+/// use response::ParsedResponse;
+///
+/// let body = r#"{"payload": {"kind": "String", "object": "Hello, Alation!"}, "error": null}"#;
+/// let parsed: ParsedResponse<String> = ParsedResponse::from_slice(200, body.as_bytes()).unwrap();
+/// assert_eq!("Hello, Alation!", parsed.into_result().unwrap());
+/// ```
+#[derive(Debug)]
+pub enum ParsedResponse<T> {
+    Payload(T),
+    Error(GenericError),
+}
+
+impl<T: DeserializeOwned> ParsedResponse<T> {
+    /// Parses the given response body. The provided HTTP `status` is that which was returned
+    /// alongside the body and is attached to any [GenericError](GenericError) so that it
+    /// reports the same [HttpCode](error::HttpCode) as the original error did.
+    pub fn from_slice(status: u16, body: &[u8]) -> serde_json::Result<ParsedResponse<T>> {
+        Ok(match serde_json::from_slice(body)? {
+            ParsedResponse::Error(error) => ParsedResponse::Error(error.with_status(status)),
+            payload => payload,
+        })
+    }
+}
+
+impl<T> ParsedResponse<T> {
+    /// Converts this ParsedResponse into a standard library [Result](std::result::Result).
+    pub fn into_result(self) -> std::result::Result<T, GenericError> {
+        match self {
+            ParsedResponse::Payload(payload) => Ok(payload),
+            ParsedResponse::Error(error) => Err(error),
+        }
+    }
+}
+
+/// A ParsedResponse may be directly converted into a [Result](result::Result) in which the
+/// error is boxed into a `Box<dyn AcmError>`.
+impl<T> From<ParsedResponse<T>> for std::result::Result<T, Box<dyn AcmError>> {
+    fn from(parsed: ParsedResponse<T>) -> Self {
+        parsed.into_result().map_err(|err| err.into())
+    }
+}
+
+#[derive(Deserialize)]
+struct Envelope<T> {
+    payload: Option<Object<T>>,
+    error: Option<GenericError>,
+}
+
+#[derive(Deserialize)]
+struct Object<T> {
+    #[allow(dead_code)]
+    kind: String,
+    object: T,
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for ParsedResponse<T> {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let envelope: Envelope<T> = Envelope::deserialize(deserializer)?;
+        match (envelope.payload, envelope.error) {
+            (_, Some(error)) => Ok(ParsedResponse::Error(error)),
+            (Some(payload), None) => Ok(ParsedResponse::Payload(payload.object)),
+            (None, None) => Err(D::Error::custom(
+                "the response envelope contained neither a payload nor an error",
+            )),
+        }
+    }
+}
+
+/// A GenericError is the deserialized form of any `Box<dyn AcmError>` that was serialized
+/// by a remote ACM component. It is itself an [AcmError](error::AcmError) whose
+/// [kind](error::Kind), [error code](error::AcmError::error_code), message, and cause are
+/// those of the original error. As such, re-serializing a GenericError results in the same
+/// JSON that was originally received.
+#[derive(Deserialize, Debug)]
+#[serde(from = "RawGenericError")]
+pub struct GenericError {
+    kind: String,
+    error_code: Option<String>,
+    message: String,
+    cause: Option<StringError>,
+    status: Option<u16>,
+}
+
+impl GenericError {
+    /// Attaches the HTTP status that this error was received with.
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// The original error's message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+#[derive(Deserialize)]
+struct RawGenericError {
+    kind: String,
+    #[serde(default)]
+    error_code: Option<String>,
+    message: String,
+    #[serde(default)]
+    cause: Option<String>,
+}
+
+impl From<RawGenericError> for GenericError {
+    fn from(raw: RawGenericError) -> Self {
+        GenericError {
+            kind: raw.kind,
+            error_code: raw.error_code,
+            message: raw.message,
+            cause: raw.cause.map(StringError::from),
+            status: None,
+        }
+    }
+}
+
+impl Display for GenericError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for GenericError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.cause
+            .as_ref()
+            .map(|cause| cause as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// The HTTP code of a GenericError is that which was attached via [with_status](GenericError::with_status).
+/// If no status was attached (or the status is not a valid HTTP status) then a 500 is assumed.
+impl HttpCode for GenericError {
+    fn http_code(&self) -> Status {
+        self.status
+            .and_then(Status::from_code)
+            .unwrap_or(Status::InternalServerError)
+    }
+}
+
+impl Kind for GenericError {
+    fn kind(&self) -> String {
+        self.kind.clone()
+    }
+}
+
+impl AcmError for GenericError {
+    fn error_code(&self) -> Option<&str> {
+        self.error_code.as_deref()
+    }
+}
@@ -0,0 +1,342 @@
+//! Shared retry policies for every coroutine that retries against a shared dependency (most
+//! notably, the Kubernetes API server).
+//!
+//! Every retrying site names the [Policy](Policy) that it retries under (`k8s-api`,
+//! `grpc-health`, `containerd-cleanup`, or `webhook`) rather than instantiating its own backoff, so that
+//! each class of operation behaves the same everywhere and may be tuned by operators via the
+//! [RETRY_POLICIES](RETRY_POLICIES) environment variable.
+//!
+//! A plain [ExponentialBackoff](backoff::ExponentialBackoff) is only lightly randomized, so
+//! when a dependency blips every one of, say, 1500 event watchers fails at the same instant and
+//! then retries in near lockstep, knocking the recovering dependency right back over. The
+//! [Backoff](Backoff) handed out by each [Policy](Policy) addresses this in two ways.
+//!
+//! 1. [Full jitter](https://aws.amazon.com/blogs/architecture/exponential-backoff-and-jitter/).
+//!    Each pause is drawn uniformly from zero up to the exponential ceiling, spreading retries
+//!    across the entire window rather than clustering them around its end.
+//! 2. A [Budget](Budget) (a token bucket) shared by the entire process. Every retry of a budgeted
+//!    policy withdraws a token, and once the bucket runs dry each retry is additionally delayed
+//!    until a token would have been refilled for it. Recovery storms are thus smoothed to the
+//!    refill rate. Polling the health of freshly started connectors (which is expected to fail
+//!    until they are up) draws from a [pool](Pool) of its own, so that it cannot starve retries
+//!    against the Kubernetes API server.
+//!
+//! Most sites need nothing more than [retry_async](retry_async).
+//!
+//! ```ignore
+//! let pod = retry::retry_async(Policy::K8sApi, || client.get(&name)).await?;
+//! ```
+//!
+//! Sites that must interleave their own work between attempts may drive the
+//! [Backoff](Backoff) themselves.
+//!
+//! ```ignore
+//! use backoff::backoff::Backoff;
+//!
+//! let mut backoff = Policy::K8sApi.backoff();
+//! loop {
+//!     match attempt().await {
+//!         Ok(ok) => return Ok(ok),
+//!         Err(err) => match backoff.next_backoff() {
+//!             Some(pause) => tokio::time::sleep(pause).await,
+//!             None => return Err(err),
+//!         },
+//!     }
+//! }
+//! ```
+mod policy;
+
+pub use policy::{Policy, Pool, Settings, RETRY_POLICIES};
+
+use backoff::backoff::Backoff as _;
+use backoff::ExponentialBackoff;
+use rand::{thread_rng, Rng};
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+#[macro_use]
+extern crate lazy_static;
+
+/// The number of tokens in each global [Budget](Budget) should it not be [configured](configure).
+pub const DEFAULT_CAPACITY: u32 = 100;
+/// The number of tokens refilled into each global [Budget](Budget) per second should it not be
+/// [configured](configure).
+pub const DEFAULT_REFILL: u32 = 20;
+
+static BUDGET: OnceLock<Budget> = OnceLock::new();
+static HEALTH_BUDGET: OnceLock<Budget> = OnceLock::new();
+
+/// Fills the global [Budgets](Budget) (that of every [pool](Pool)) with `capacity` tokens apiece,
+/// refilled at `refill` tokens per second, and forces the evaluation of the
+/// [RETRY_POLICIES](RETRY_POLICIES) configuration so that a misconfiguration panics on startup
+/// rather than upon the first retry.
+///
+/// This MUST be called upon startup, before the first retry, as the budgets cannot be changed once
+/// they have been drawn from.
+pub fn configure(capacity: u32, refill: u32) {
+    for budget in &[&BUDGET, &HEALTH_BUDGET] {
+        if budget.set(Budget::new(capacity, refill)).is_err() {
+            panic!("The retry budget was configured after it had already been drawn from");
+        }
+    }
+    policy::configure();
+}
+
+/// Runs the given operation until it succeeds, retrying it under the given [Policy](Policy) for
+/// as long as the policy allows. The last error is returned should the policy give up.
+pub async fn retry_async<T, E, F, Fut>(policy: Policy, op: F) -> std::result::Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = std::result::Result<T, E>>,
+{
+    retry_with(policy.backoff(), op).await
+}
+
+async fn retry_with<T, E, F, Fut>(mut backoff: Backoff, mut op: F) -> std::result::Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = std::result::Result<T, E>>,
+{
+    loop {
+        match op().await {
+            Ok(ok) => return Ok(ok),
+            Err(err) => match backoff.next_backoff() {
+                Some(pause) => tokio::time::sleep(pause).await,
+                None => return Err(err),
+            },
+        }
+    }
+}
+
+/// Returns the process wide [Budget](Budget) of the [shared](Pool::Shared) pool.
+pub fn budget() -> &'static Budget {
+    BUDGET.get_or_init(|| Budget::new(DEFAULT_CAPACITY, DEFAULT_REFILL))
+}
+
+/// Returns the process wide [Budget](Budget) of the [health](Pool::Health) pool.
+pub fn health_budget() -> &'static Budget {
+    HEALTH_BUDGET.get_or_init(|| Budget::new(DEFAULT_CAPACITY, DEFAULT_REFILL))
+}
+
+/// A Backoff wraps an [ExponentialBackoff](ExponentialBackoff), which merely provides the
+/// ceiling of each pause, with full jitter and (optionally) a shared [Budget](Budget).
+pub struct Backoff {
+    exponential: ExponentialBackoff,
+    budget: Option<&'static Budget>,
+}
+
+impl Backoff {
+    fn new(mut exponential: ExponentialBackoff, budget: Option<&'static Budget>) -> Backoff {
+        // We apply our own (full) jitter.
+        exponential.randomization_factor = 0.0;
+        Backoff {
+            exponential,
+            budget,
+        }
+    }
+
+    /// The time elapsed since this backoff was created or last [reset](backoff::backoff::Backoff::reset).
+    pub fn get_elapsed_time(&self) -> Duration {
+        self.exponential.get_elapsed_time()
+    }
+
+    /// How long after its creation (or last [reset](backoff::backoff::Backoff::reset)) that this
+    /// backoff gives up. `None` never gives up.
+    pub fn max_elapsed_time(&self) -> Option<Duration> {
+        self.exponential.max_elapsed_time
+    }
+}
+
+impl backoff::backoff::Backoff for Backoff {
+    fn reset(&mut self) {
+        self.exponential.reset()
+    }
+
+    fn next_backoff(&mut self) -> Option<Duration> {
+        let ceiling = self.exponential.next_backoff()?;
+        let debt = self.budget.map(Budget::withdraw).unwrap_or(Duration::ZERO);
+        Some(jitter(ceiling) + debt)
+    }
+}
+
+/// Draws a pause uniformly from `[0, ceiling]`.
+fn jitter(ceiling: Duration) -> Duration {
+    ceiling.mul_f64(thread_rng().gen_range(0.0..=1.0))
+}
+
+/// A Budget is a token bucket of retries.
+///
+/// Withdrawals never fail. Instead, once the bucket is empty, each withdrawal goes into debt and
+/// is told how long it must wait until the bucket would have refilled enough to cover it.
+///
+/// The debt is capped at a full bucket (`capacity` tokens), such that no withdrawal ever waits
+/// longer than it takes to refill the bucket from empty. Otherwise, a storm of thousands of
+/// retries would push every later retry back by minutes (or hours) long after the storm is over.
+pub struct Budget {
+    capacity: f64,
+    refill: f64,
+    state: Mutex<State>,
+}
+
+struct State {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Budget {
+    /// Constructs a full bucket of `capacity` tokens which refills at `refill` tokens per second.
+    pub fn new(capacity: u32, refill: u32) -> Budget {
+        Budget {
+            capacity: capacity as f64,
+            refill: refill as f64,
+            state: Mutex::new(State {
+                tokens: capacity as f64,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Withdraws a single token, returning how long the caller must wait (in addition to its
+    /// own backoff) before retrying. This is zero so long as the bucket is not empty.
+    pub fn withdraw(&self) -> Duration {
+        self.withdraw_at(Instant::now())
+    }
+
+    /// The number of tokens currently available, which is negative while the bucket is in debt.
+    pub fn available(&self) -> f64 {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state, Instant::now());
+        state.tokens
+    }
+
+    fn withdraw_at(&self, now: Instant) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state, now);
+        state.tokens = (state.tokens - 1.0).max(-self.capacity);
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / self.refill)
+        }
+    }
+
+    fn refill(&self, state: &mut State, now: Instant) {
+        let elapsed = now.saturating_duration_since(state.updated).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.refill).min(self.capacity);
+        state.updated = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter_is_bounded() {
+        let ceiling = Duration::from_millis(500);
+        for _ in 0..1000 {
+            assert!(jitter(ceiling) <= ceiling);
+        }
+    }
+
+    #[test]
+    fn jitter_spreads() {
+        let ceiling = Duration::from_secs(10);
+        let pauses: Vec<Duration> = (0..100).map(|_| jitter(ceiling)).collect();
+        assert!(pauses.iter().any(|pause| *pause < ceiling / 2));
+        assert!(pauses.iter().any(|pause| *pause > ceiling / 2));
+    }
+
+    #[test]
+    fn budget_is_free_until_empty() {
+        let budget = Budget::new(3, 1);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(budget.withdraw_at(now), Duration::ZERO);
+        }
+        assert_eq!(budget.withdraw_at(now), Duration::from_secs(1));
+        assert_eq!(budget.withdraw_at(now), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn budget_refills() {
+        let budget = Budget::new(2, 2);
+        let now = Instant::now();
+        budget.withdraw_at(now);
+        budget.withdraw_at(now);
+        assert_eq!(budget.withdraw_at(now), Duration::from_millis(500));
+        // Paying off the debt of one token and then refilling one more.
+        let later = now + Duration::from_secs(1);
+        assert_eq!(budget.withdraw_at(later), Duration::ZERO);
+    }
+
+    #[test]
+    fn budget_does_not_overfill() {
+        let budget = Budget::new(1, 4);
+        let later = Instant::now() + Duration::from_secs(60);
+        assert_eq!(budget.withdraw_at(later), Duration::ZERO);
+        assert_eq!(budget.withdraw_at(later), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn budget_debt_is_capped() {
+        let budget = Budget::new(2, 1);
+        let now = Instant::now();
+        for _ in 0..100 {
+            budget.withdraw_at(now);
+        }
+        assert_eq!(budget.withdraw_at(now), Duration::from_secs(2));
+        // Once the debt is paid off, the bucket is empty rather than still owing for the storm.
+        let later = now + Duration::from_secs(2);
+        assert_eq!(budget.withdraw_at(later), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn backoff_draws_from_budget() {
+        lazy_static! {
+            static ref EMPTY: Budget = Budget::new(1, 1);
+        }
+        EMPTY.withdraw();
+        let mut backoff = Backoff::new(ExponentialBackoff::default(), Some(&*EMPTY));
+        // The budget is in debt so even the luckiest jitter must wait for a refill.
+        assert!(backoff.next_backoff().unwrap() > Duration::from_millis(500));
+    }
+
+    #[test]
+    fn retries_until_success() {
+        let mut attempts = 0;
+        let result: std::result::Result<u32, &str> =
+            tokio_test::block_on(retry_with(quick(Some(Duration::from_secs(60))), || {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    if attempt < 3 {
+                        Err("not yet")
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            }));
+        assert_eq!(result, Ok(3));
+    }
+
+    #[test]
+    fn gives_up() {
+        let result: std::result::Result<(), &str> = tokio_test::block_on(retry_with(
+            quick(Some(Duration::from_millis(20))),
+            || async { Err("never") },
+        ));
+        assert_eq!(result, Err("never"));
+    }
+
+    fn quick(max_elapsed_time: Option<Duration>) -> Backoff {
+        Settings {
+            initial_interval: Duration::from_millis(1),
+            max_interval: Duration::from_millis(2),
+            max_elapsed_time,
+            budget: None,
+        }
+        .backoff()
+    }
+}
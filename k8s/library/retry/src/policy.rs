@@ -0,0 +1,281 @@
+use crate::{budget, health_budget, Backoff, Budget};
+use backoff::ExponentialBackoff;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// The environment variable that tunes the named [Policies](Policy), given as a comma separated
+/// list of `<policy>=<initial interval ms>:<max interval ms>:<max elapsed seconds>` entries, such
+/// as `k8s-api=500:30000:600,grpc-health=250:2000:60`. A max elapsed time of zero retries forever.
+///
+/// Policies that are not listed keep their defaults.
+pub const RETRY_POLICIES: &str = "RETRY_POLICIES";
+
+lazy_static! {
+    static ref POLICIES: HashMap<Policy, Settings> = configured();
+}
+
+/// A Policy names the manner in which a particular class of operation is retried, such that every
+/// site that retries the same class of operation behaves the same, and such that operators may
+/// tune each class via [RETRY_POLICIES](RETRY_POLICIES).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Policy {
+    /// Calls to the Kubernetes API server (such as an event watcher re-establishing its watch).
+    /// These draw from the [shared](Pool::Shared) pool.
+    K8sApi,
+    /// Polling a freshly started connector's gRPC health check. These draw from the
+    /// [health](Pool::Health) pool, and the max elapsed time is the connector's entire allowance
+    /// to come online.
+    GrpcHealth,
+    /// Removing the temporary images and namespaces that an install leaves within containerd.
+    ContainerdCleanup,
+    /// Delivering a notification to an outbound webhook (such as those told of pod lifecycle
+    /// transitions). The receiver is outside of the cluster, so these do not draw from the
+    /// [Budget](crate::Budget).
+    Webhook,
+}
+
+impl Policy {
+    /// Every named policy.
+    pub const ALL: [Policy; 4] = [
+        Policy::K8sApi,
+        Policy::GrpcHealth,
+        Policy::ContainerdCleanup,
+        Policy::Webhook,
+    ];
+
+    /// The name under which this policy is configured within [RETRY_POLICIES](RETRY_POLICIES).
+    pub fn name(&self) -> &'static str {
+        match self {
+            Policy::K8sApi => "k8s-api",
+            Policy::GrpcHealth => "grpc-health",
+            Policy::ContainerdCleanup => "containerd-cleanup",
+            Policy::Webhook => "webhook",
+        }
+    }
+
+    /// Returns this policy's settings, as [configured](RETRY_POLICIES).
+    pub fn settings(&self) -> Settings {
+        POLICIES[self]
+    }
+
+    /// Returns a fresh, fully jittered, exponential backoff of this policy.
+    pub fn backoff(&self) -> Backoff {
+        self.settings().backoff()
+    }
+
+    fn default_settings(&self) -> Settings {
+        match self {
+            Policy::K8sApi | Policy::ContainerdCleanup => Settings {
+                initial_interval: Duration::from_millis(500),
+                max_interval: Duration::from_secs(60),
+                max_elapsed_time: Some(Duration::from_secs(15 * 60)),
+                budget: if *self == Policy::K8sApi {
+                    Some(Pool::Shared)
+                } else {
+                    None
+                },
+            },
+            Policy::GrpcHealth => Settings {
+                initial_interval: Duration::from_millis(500),
+                max_interval: Duration::from_secs(60),
+                max_elapsed_time: Some(Duration::from_secs(30)),
+                budget: Some(Pool::Health),
+            },
+            Policy::Webhook => Settings {
+                initial_interval: Duration::from_secs(1),
+                max_interval: Duration::from_secs(60),
+                max_elapsed_time: Some(Duration::from_secs(10 * 60)),
+                budget: None,
+            },
+        }
+    }
+}
+
+impl Display for Policy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A Pool names one of the process wide [Budgets](crate::Budget) that retries draw from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Pool {
+    /// The [budget](crate::budget) of retries against the shared dependencies of the process,
+    /// such as the Kubernetes API server.
+    Shared,
+    /// The [budget](crate::health_budget) of health checks of freshly started connectors, which
+    /// fail as a matter of course until the connector is up.
+    Health,
+}
+
+impl Pool {
+    /// Returns the budget of this pool.
+    pub fn budget(&self) -> &'static Budget {
+        match self {
+            Pool::Shared => budget(),
+            Pool::Health => health_budget(),
+        }
+    }
+}
+
+/// The parameters of a [Policy](Policy).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Settings {
+    /// The ceiling of the first pause.
+    pub initial_interval: Duration,
+    /// The greatest ceiling of any one pause.
+    pub max_interval: Duration,
+    /// How long after its first failure that the operation is given up on. `None` never gives up.
+    pub max_elapsed_time: Option<Duration>,
+    /// The [pool](Pool) whose process wide [Budget](crate::Budget) retries draw from, if any.
+    pub budget: Option<Pool>,
+}
+
+impl Settings {
+    /// Returns a fresh, fully jittered, exponential backoff of these settings.
+    pub fn backoff(&self) -> Backoff {
+        let exponential = ExponentialBackoff {
+            initial_interval: self.initial_interval,
+            current_interval: self.initial_interval,
+            max_interval: self.max_interval,
+            max_elapsed_time: self.max_elapsed_time,
+            ..Default::default()
+        };
+        Backoff::new(exponential, self.budget.as_ref().map(Pool::budget))
+    }
+}
+
+/// Forces the evaluation of the [RETRY_POLICIES](RETRY_POLICIES) configuration.
+pub(crate) fn configure() {
+    lazy_static::initialize(&POLICIES);
+}
+
+/// Constructs the settings of every policy, as tuned by [RETRY_POLICIES](RETRY_POLICIES).
+///
+/// This function PANICS if the variable is malformed.
+fn configured() -> HashMap<Policy, Settings> {
+    let mut policies: HashMap<Policy, Settings> = Policy::ALL
+        .iter()
+        .map(|policy| (*policy, policy.default_settings()))
+        .collect();
+    if let Ok(tuned) = std::env::var(RETRY_POLICIES) {
+        let tuned = parse(&tuned, &policies).unwrap_or_else(|err| {
+            panic!(
+                "The {} environment variable is malformed, {}. Got '{}'",
+                RETRY_POLICIES, err, tuned
+            )
+        });
+        policies.extend(tuned);
+    }
+    policies
+}
+
+/// Parses a [RETRY_POLICIES](RETRY_POLICIES) configuration atop the given defaults.
+fn parse(
+    tuned: &str,
+    defaults: &HashMap<Policy, Settings>,
+) -> std::result::Result<HashMap<Policy, Settings>, String> {
+    let malformed = |entry: &str| {
+        format!(
+            "'{}' is not of the form <policy>=<initial interval ms>:<max interval ms>:<max elapsed seconds>",
+            entry
+        )
+    };
+    let mut parsed = HashMap::new();
+    for entry in tuned.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (name, settings) = entry.split_once('=').ok_or_else(|| malformed(entry))?;
+        let policy = Policy::ALL
+            .iter()
+            .find(|policy| policy.name() == name.trim())
+            .ok_or_else(|| {
+                format!(
+                    "'{}' is not a policy. Policies are {}",
+                    name.trim(),
+                    Policy::ALL
+                        .iter()
+                        .map(Policy::name)
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                )
+            })?;
+        let numbers = settings
+            .split(':')
+            .map(|number| number.trim().parse::<u64>())
+            .collect::<std::result::Result<Vec<u64>, _>>()
+            .map_err(|_| malformed(entry))?;
+        let (initial, max, elapsed) = match numbers.as_slice() {
+            [initial, max, elapsed] => (*initial, *max, *elapsed),
+            _ => return Err(malformed(entry)),
+        };
+        if initial == 0 || max < initial {
+            return Err(format!(
+                "the initial interval of '{}' must be positive and no greater than its max interval",
+                entry
+            ));
+        }
+        parsed.insert(
+            *policy,
+            Settings {
+                initial_interval: Duration::from_millis(initial),
+                max_interval: Duration::from_millis(max),
+                max_elapsed_time: match elapsed {
+                    0 => None,
+                    elapsed => Some(Duration::from_secs(elapsed)),
+                },
+                budget: defaults[policy].budget,
+            },
+        );
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> HashMap<Policy, Settings> {
+        Policy::ALL
+            .iter()
+            .map(|policy| (*policy, policy.default_settings()))
+            .collect()
+    }
+
+    #[test]
+    fn parses() {
+        let parsed = parse(
+            "k8s-api=250:2000:60, containerd-cleanup=1000:1000:0",
+            &defaults(),
+        )
+        .unwrap();
+        assert_eq!(
+            parsed[&Policy::K8sApi],
+            Settings {
+                initial_interval: Duration::from_millis(250),
+                max_interval: Duration::from_secs(2),
+                max_elapsed_time: Some(Duration::from_secs(60)),
+                budget: Some(Pool::Shared),
+            }
+        );
+        assert_eq!(parsed[&Policy::ContainerdCleanup].max_elapsed_time, None);
+        assert_eq!(parsed[&Policy::ContainerdCleanup].budget, None);
+        assert!(!parsed.contains_key(&Policy::GrpcHealth));
+        assert_eq!(defaults()[&Policy::GrpcHealth].budget, Some(Pool::Health));
+        assert_eq!(defaults()[&Policy::Webhook].budget, None);
+        assert!(parse("", &defaults()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects() {
+        for malformed in &[
+            "k8s-api",
+            "k8s-api=1:2",
+            "k8s-api=a:2:3",
+            "k8s-api=0:2:3",
+            "k8s-api=3:2:1",
+            "etcd=1:2:3",
+        ] {
+            assert!(parse(malformed, &defaults()).is_err(), "{}", malformed);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! A [Secret](Secret) is a string (such as a password or an access key) that must never find its
+//! way into logs, error messages, or responses.
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::env::VarError;
+use std::ffi::OsStr;
+use std::fmt::{Debug, Display, Formatter};
+use zeroize::Zeroize;
+
+/// What a [Secret](Secret) is formatted (and serialized) as in place of the underlying secret.
+pub const REDACTED: &str = "<REDACTED>";
+
+/// A `Secret` obfuscates an underlying string from being accidentally printed to any logs.
+///
+/// Any attempt to format a `Secret` using the either the [Display](Display)("{}") or [Debug](Debug)
+/// ("{:?}") directives will result in the string "<REDACTED>" rather than the underlying secret.
+/// The same goes for serializing a `Secret`, unless the field is explicitly annotated with
+/// `#[serde(serialize_with = "secret::expose")]`.
+///
+/// Original secret may be retrieved by either requesting a reference to a [String](String)/[str](str)
+/// or by explicitly calling [raw_secret](Secret::raw_secret).
+///
+/// The underlying secret is zeroed out of memory as soon as the `Secret` is dropped.
+///
+/// ```
+/// use secret::Secret;
+///
+/// let password = Secret::from("please don't log this");
+/// let log_entry = format!("my password is {}!", password);
+/// assert_eq!("my password is <REDACTED>!", log_entry);
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret {
+    secret: String,
+}
+
+impl Secret {
+    pub fn raw_secret(&self) -> &str {
+        self.as_ref()
+    }
+
+    /// Reads the secret held by the given environment variable. A variable that is present, albeit
+    /// empty, is taken to be absent.
+    pub fn from_env(var: &str) -> Result<Secret, VarError> {
+        match std::env::var(var)? {
+            secret if secret.is_empty() => Err(VarError::NotPresent),
+            secret => Ok(Secret::from(secret)),
+        }
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+impl Display for Secret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret::from)
+    }
+}
+
+/// Serializes the underlying secret itself, for the rare field that must actually be sent.
+///
+/// ```ignore
+/// #[derive(Serialize)]
+/// struct Login {
+///     #[serde(serialize_with = "secret::expose")]
+///     password: Secret,
+/// }
+/// ```
+pub fn expose<S: Serializer>(secret: &Secret, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(secret.raw_secret())
+}
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Self {
+        Self { secret }
+    }
+}
+
+impl From<&String> for Secret {
+    fn from(secret: &String) -> Self {
+        Self {
+            secret: secret.clone(),
+        }
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(secret: &str) -> Self {
+        Self::from(secret.to_string())
+    }
+}
+
+impl AsRef<str> for Secret {
+    fn as_ref(&self) -> &str {
+        self.secret.as_str()
+    }
+}
+
+impl AsRef<String> for Secret {
+    fn as_ref(&self) -> &String {
+        &self.secret
+    }
+}
+
+impl AsRef<OsStr> for Secret {
+    fn as_ref(&self) -> &OsStr {
+        self.secret.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_display() {
+        let password = Secret::from("please don't log this");
+        let log_entry = format!("my password is {}!", password);
+        assert_eq!("my password is <REDACTED>!", log_entry);
+    }
+
+    #[test]
+    fn test_secret_debug() {
+        let password = Secret::from("please don't log this");
+        let log_entry = format!("my password is {:?}!", password);
+        assert_eq!("my password is <REDACTED>!", log_entry);
+    }
+
+    #[test]
+    fn test_secret_serialize() {
+        let password = Secret::from("please don't log this");
+        assert_eq!("\"<REDACTED>\"", serde_json::to_string(&password).unwrap());
+        let mut serializer = serde_json::Serializer::new(vec![]);
+        expose(&password, &mut serializer).unwrap();
+        assert_eq!(
+            "\"please don't log this\"",
+            String::from_utf8(serializer.into_inner()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_secret_deserialize() {
+        let password: Secret = serde_json::from_str("\"hunter2\"").unwrap();
+        assert_eq!("hunter2", password.raw_secret());
+    }
+
+    #[test]
+    fn test_secret_from_env() {
+        std::env::set_var("SECRET_TEST_PRESENT", "hunter2");
+        std::env::set_var("SECRET_TEST_EMPTY", "");
+        assert_eq!(
+            "hunter2",
+            Secret::from_env("SECRET_TEST_PRESENT")
+                .unwrap()
+                .raw_secret()
+        );
+        assert!(Secret::from_env("SECRET_TEST_EMPTY").is_err());
+        assert!(Secret::from_env("SECRET_TEST_ABSENT").is_err());
+    }
+}
@@ -0,0 +1,350 @@
+use crate::{styled, theme, Role, Style};
+use std::fmt::{Display, Formatter};
+
+/// The default number of characters within the bar of a [Progress](Progress).
+pub const DEFAULT_PROGRESS_WIDTH: usize = 20;
+
+/// The spaces between two columns of a [Table](Table).
+const GUTTER: &str = "  ";
+
+/// A Cell is a single piece of text within a [Table](Table), alongside the style that it is
+/// painted with.
+#[derive(Clone, Debug)]
+pub struct Cell {
+    text: String,
+    style: Style,
+}
+
+impl Cell {
+    pub fn new<T: ToString>(text: T) -> Cell {
+        Cell {
+            text: text.to_string(),
+            style: Style::new(),
+        }
+    }
+
+    /// Paints this cell with the given style.
+    pub fn style(mut self, style: Style) -> Cell {
+        self.style = style;
+        self
+    }
+
+    /// Paints this cell with the style that the global [Theme](crate::Theme) gives to the given
+    /// role.
+    pub fn role(self, role: Role) -> Cell {
+        let style = theme().style(role);
+        self.style(style)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Cell::new(text)
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::new(text)
+    }
+}
+
+/// A Table renders rows of [Cells](Cell) beneath a bold header, with every column padded to the
+/// width of its widest cell. Padding is measured on the text alone, so styled cells line up just
+/// as well as plain ones.
+///
+/// ```ignore
+/// let table = Table::new(vec!["POD", "PHASE"])
+///     .row(vec![Cell::new(&pod).role(Role::Highlight), Cell::new("Running")]);
+/// info!("Connectors\n{}", table);
+/// ```
+///
+/// Rows may be shorter than the header, in which case their trailing columns are left empty.
+#[derive(Clone, Debug)]
+pub struct Table {
+    header: Vec<Cell>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    pub fn new<I, C>(header: I) -> Table
+    where
+        I: IntoIterator<Item = C>,
+        C: Into<Cell>,
+    {
+        Table {
+            header: header
+                .into_iter()
+                .map(|cell| {
+                    let cell = cell.into();
+                    let style = cell.style.bold();
+                    cell.style(style)
+                })
+                .collect(),
+            rows: vec![],
+        }
+    }
+
+    /// Appends the given row to this table.
+    pub fn row<I, C>(mut self, cells: I) -> Table
+    where
+        I: IntoIterator<Item = C>,
+        C: Into<Cell>,
+    {
+        self.push(cells);
+        self
+    }
+
+    /// Appends the given row to this table.
+    pub fn push<I, C>(&mut self, cells: I)
+    where
+        I: IntoIterator<Item = C>,
+        C: Into<Cell>,
+    {
+        self.rows.push(cells.into_iter().map(Into::into).collect());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// The width of every column, being the width of its widest cell.
+    fn widths(&self) -> Vec<usize> {
+        let columns = self
+            .rows
+            .iter()
+            .map(Vec::len)
+            .chain(std::iter::once(self.header.len()))
+            .max()
+            .unwrap_or_default();
+        (0..columns)
+            .map(|column| {
+                std::iter::once(&self.header)
+                    .chain(self.rows.iter())
+                    .filter_map(|row| row.get(column))
+                    .map(|cell| cell.text.chars().count())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+}
+
+impl Display for Table {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let widths = self.widths();
+        let empty = Cell::new("");
+        for (index, row) in std::iter::once(&self.header)
+            .chain(self.rows.iter())
+            .enumerate()
+        {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            // Rows span only as many columns as they have cells, whereas the header spans them all.
+            let columns = if index == 0 { widths.len() } else { row.len() };
+            for (column, width) in widths.iter().enumerate().take(columns) {
+                let cell = row.get(column).unwrap_or(&empty);
+                write!(f, "{}", styled(cell.style, cell.text.as_str()))?;
+                // The last cell is never padded, lest lines end in whitespace.
+                if column + 1 < columns {
+                    let padding = width - cell.text.chars().count();
+                    write!(f, "{}{}", " ".repeat(padding), GUTTER)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Progress renders how far along a long running operation (such as an image push) is, as in
+/// `[########------------] 40% (2/5)`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Progress {
+    done: u64,
+    total: u64,
+    width: usize,
+}
+
+impl Progress {
+    /// The progress of having done `done` out of `total` units of work. Anything done beyond the
+    /// total is counted as the total.
+    pub fn new(done: u64, total: u64) -> Progress {
+        Progress {
+            done: done.min(total),
+            total,
+            width: DEFAULT_PROGRESS_WIDTH,
+        }
+    }
+
+    /// Sets the number of characters within the bar.
+    pub fn width(mut self, width: usize) -> Progress {
+        self.width = width;
+        self
+    }
+
+    /// The fraction of the work that is done. Nothing to do at all counts as done.
+    pub fn fraction(&self) -> f64 {
+        match self.total {
+            0 => 1.0,
+            total => self.done as f64 / total as f64,
+        }
+    }
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let filled = (self.fraction() * self.width as f64).floor() as usize;
+        write!(
+            f,
+            "[{}{}] {}% ({}/{})",
+            styled(theme().style(Role::Info), "#".repeat(filled)),
+            "-".repeat(self.width - filled),
+            (self.fraction() * 100.0).floor() as u64,
+            self.done,
+            self.total
+        )
+    }
+}
+
+/// A Checklist renders the outcome of a series of checks (such as those of a preflight) as a
+/// [Table](Table) of `PASS` and `FAIL` verdicts, followed by a tally.
+///
+/// ```ignore
+/// let mut checklist = Checklist::default();
+/// checklist.check("Kubernetes API", Ok::<_, String>("reachable"));
+/// checklist.check("RBAC", Err::<String, _>("cannot delete pods"));
+/// println!("{}", checklist);
+/// std::process::exit(if checklist.passed() { 0 } else { 1 });
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Checklist {
+    checks: Vec<(String, bool, String)>,
+}
+
+impl Checklist {
+    /// Records the outcome of the named check, which details either what was found or what went
+    /// wrong.
+    pub fn check<N, D, E>(&mut self, name: N, outcome: Result<D, E>)
+    where
+        N: ToString,
+        D: ToString,
+        E: ToString,
+    {
+        let (passed, detail) = match outcome {
+            Ok(detail) => (true, detail.to_string()),
+            Err(err) => (false, err.to_string()),
+        };
+        self.checks.push((name.to_string(), passed, detail));
+    }
+
+    /// Whether every check passed. A checklist of no checks at all has passed.
+    pub fn passed(&self) -> bool {
+        self.failures() == 0
+    }
+
+    /// The number of checks that failed.
+    pub fn failures(&self) -> usize {
+        self.checks.iter().filter(|(_, passed, _)| !passed).count()
+    }
+}
+
+impl Display for Checklist {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut table = Table::new(vec!["RESULT", "CHECK", "DETAIL"]);
+        for (name, passed, detail) in &self.checks {
+            let verdict = match passed {
+                true => Cell::new("PASS").role(Role::Info),
+                false => Cell::new("FAIL").role(Role::Error),
+            };
+            table.push(vec![verdict, Cell::new(name), Cell::new(detail)]);
+        }
+        let failures = self.failures();
+        let tally = format!(
+            "{} passed, {} failed",
+            self.checks.len() - failures,
+            failures
+        );
+        let role = if failures == 0 {
+            Role::Info
+        } else {
+            Role::Error
+        };
+        write!(f, "{}\n\n{}", table, styled(theme().style(role), tally))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Strips every ANSI escape sequence from the given text, as whether anything is painted
+    /// depends upon the global color mode.
+    fn plain(text: String) -> String {
+        let mut plain = String::new();
+        let mut escaped = false;
+        for c in text.chars() {
+            match (escaped, c) {
+                (false, '\u{1b}') => escaped = true,
+                (false, c) => plain.push(c),
+                (true, 'm') => escaped = false,
+                (true, _) => {}
+            }
+        }
+        plain
+    }
+
+    #[test]
+    fn aligns() {
+        let table = Table::new(vec!["POD", "PHASE", "AGE"])
+            .row(vec![
+                Cell::new("ocf/tennis").role(Role::Highlight),
+                Cell::new("Running"),
+                Cell::new("5m"),
+            ])
+            .row(vec!["ocf/a", "Pending"]);
+        assert_eq!(
+            plain(table.to_string()),
+            "POD         PHASE    AGE\n\
+             ocf/tennis  Running  5m\n\
+             ocf/a       Pending"
+        );
+    }
+
+    #[test]
+    fn checks() {
+        let mut checklist = Checklist::default();
+        assert!(checklist.passed());
+        checklist.check(
+            "containerd",
+            Ok::<_, String>("/run/containerd/containerd.sock"),
+        );
+        checklist.check("registry", Err::<String, _>("connection refused"));
+        assert!(!checklist.passed());
+        assert_eq!(checklist.failures(), 1);
+        assert_eq!(
+            plain(checklist.to_string()),
+            "RESULT  CHECK       DETAIL\n\
+             PASS    containerd  /run/containerd/containerd.sock\n\
+             FAIL    registry    connection refused\n\
+             \n\
+             1 passed, 1 failed"
+        );
+    }
+
+    #[test]
+    fn progresses() {
+        assert_eq!(
+            plain(Progress::new(2, 5).width(10).to_string()),
+            "[####------] 40% (2/5)"
+        );
+        assert_eq!(
+            plain(Progress::new(7, 5).width(4).to_string()),
+            "[####] 100% (5/5)"
+        );
+        assert_eq!(
+            plain(Progress::new(0, 0).width(2).to_string()),
+            "[##] 100% (0/0)"
+        );
+    }
+}
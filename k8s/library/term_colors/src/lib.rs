@@ -1,14 +1,133 @@
 //! term_colors is a collection of convenience functions for coloring terminal output.
+//!
+//! Whether anything is colored at all is decided by the global [ColorMode](ColorMode). Should
+//! colors be disabled then every function here returns its input as is, free of any ANSI escape
+//! sequences (which would otherwise pollute collectors that expect plain, or JSON, logs).
+
+#[macro_use]
+extern crate lazy_static;
+
+pub mod layout;
+mod theme;
+
+pub use ansi_term::{Color, Style};
+pub use theme::*;
 
 use ansi_term::ANSIGenericString;
 use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The environment variable that configures the [ColorMode](ColorMode) of a service, being one
+/// of `auto`, `always`, or `never`. This defaults to `auto`.
+pub const COLOR_MODE: &str = "COLOR_MODE";
+
+/// The environment variable that, when set to anything other than an empty string, disables
+/// colors while in [ColorMode::Auto](ColorMode::Auto). See <https://no-color.org>.
+pub const NO_COLOR: &str = "NO_COLOR";
+
+static MODE: AtomicU8 = AtomicU8::new(ColorMode::Auto as u8);
+
+lazy_static! {
+    static ref AUTO: bool = std::env::var(NO_COLOR).unwrap_or_default().is_empty()
+        && unsafe { libc::isatty(libc::STDERR_FILENO) } == 1;
+}
+
+/// ColorMode decides whether terminal output is colored.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ColorMode {
+    /// Colors are used unless [NO_COLOR](NO_COLOR) is set or stderr (where logs are written) is
+    /// not a terminal.
+    Auto = 0,
+    Always = 1,
+    Never = 2,
+}
+
+impl ColorMode {
+    /// Returns the mode configured by [COLOR_MODE](COLOR_MODE).
+    ///
+    /// This function PANICS if the variable is set to anything other than a valid mode.
+    pub fn configured() -> ColorMode {
+        match std::env::var(COLOR_MODE) {
+            Ok(mode) if !mode.trim().is_empty() => mode.parse().unwrap_or_else(|err| {
+                panic!(
+                    "The {} environment variable is malformed, {}",
+                    COLOR_MODE, err
+                )
+            }),
+            _ => ColorMode::Auto,
+        }
+    }
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.trim().to_lowercase().as_str() {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(format!("'{}' is not one of auto, always, or never", mode)),
+        }
+    }
+}
+
+impl Display for ColorMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorMode::Auto => write!(f, "auto"),
+            ColorMode::Always => write!(f, "always"),
+            ColorMode::Never => write!(f, "never"),
+        }
+    }
+}
+
+/// Sets the global [ColorMode](ColorMode).
+pub fn set_color_mode(mode: ColorMode) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+/// Returns the global [ColorMode](ColorMode).
+pub fn color_mode() -> ColorMode {
+    match MODE.load(Ordering::Relaxed) {
+        1 => ColorMode::Always,
+        2 => ColorMode::Never,
+        _ => ColorMode::Auto,
+    }
+}
+
+/// Whether terminal output is currently colored, as decided by the global
+/// [ColorMode](ColorMode).
+pub fn enabled() -> bool {
+    match color_mode() {
+        ColorMode::Auto => *AUTO,
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+    }
+}
+
+/// Paints the given input with the given style (which may use any of the 256 colors, or any RGB
+/// color, of [Color](Color)), or with no style at all should colors be [disabled](enabled).
+pub fn styled<'a, I, S: 'a + ToOwned + ?Sized>(style: Style, input: I) -> ANSIGenericString<'a, S>
+where
+    I: Into<Cow<'a, S>>,
+    <S as ToOwned>::Owned: std::fmt::Debug,
+{
+    if enabled() {
+        style.paint(input)
+    } else {
+        Style::new().paint(input)
+    }
+}
 
 pub fn bold<'a, I, S: 'a + ToOwned + ?Sized>(input: I) -> ANSIGenericString<'a, S>
 where
     I: Into<Cow<'a, S>>,
     <S as ToOwned>::Owned: std::fmt::Debug,
 {
-    ansi_term::Style::new().bold().paint(input)
+    styled(Style::new().bold(), input)
 }
 
 pub fn cyan<'a, I, S: 'a + ToOwned + ?Sized>(input: I) -> ANSIGenericString<'a, S>
@@ -16,7 +135,7 @@ where
     I: Into<Cow<'a, S>>,
     <S as ToOwned>::Owned: std::fmt::Debug,
 {
-    ansi_term::Color::Cyan.paint(input)
+    styled(Color::Cyan.normal(), input)
 }
 
 pub fn red<'a, I, S: 'a + ToOwned + ?Sized>(input: I) -> ANSIGenericString<'a, S>
@@ -24,7 +143,7 @@ where
     I: Into<Cow<'a, S>>,
     <S as ToOwned>::Owned: std::fmt::Debug,
 {
-    ansi_term::Color::Red.paint(input)
+    styled(Color::Red.normal(), input)
 }
 
 pub fn green<'a, I, S: 'a + ToOwned + ?Sized>(input: I) -> ANSIGenericString<'a, S>
@@ -32,7 +151,7 @@ where
     I: Into<Cow<'a, S>>,
     <S as ToOwned>::Owned: std::fmt::Debug,
 {
-    ansi_term::Color::Green.paint(input)
+    styled(Color::Green.normal(), input)
 }
 
 pub fn blue<'a, I, S: 'a + ToOwned + ?Sized>(input: I) -> ANSIGenericString<'a, S>
@@ -40,7 +159,7 @@ where
     I: Into<Cow<'a, S>>,
     <S as ToOwned>::Owned: std::fmt::Debug,
 {
-    ansi_term::Color::Blue.paint(input)
+    styled(Color::Blue.normal(), input)
 }
 
 pub fn purple<'a, I, S: 'a + ToOwned + ?Sized>(input: I) -> ANSIGenericString<'a, S>
@@ -48,7 +167,7 @@ where
     I: Into<Cow<'a, S>>,
     <S as ToOwned>::Owned: std::fmt::Debug,
 {
-    ansi_term::Color::Purple.paint(input)
+    styled(Color::Purple.normal(), input)
 }
 
 pub fn orange<'a, I, S: 'a + ToOwned + ?Sized>(input: I) -> ANSIGenericString<'a, S>
@@ -56,5 +175,50 @@ where
     I: Into<Cow<'a, S>>,
     <S as ToOwned>::Owned: std::fmt::Debug,
 {
-    ansi_term::Color::RGB(243, 113, 33).paint(input)
+    styled(Color::RGB(243, 113, 33).normal(), input)
+}
+
+/// Paints the given input with one of the 256 colors of the terminal's palette.
+pub fn fixed<'a, I, S: 'a + ToOwned + ?Sized>(color: u8, input: I) -> ANSIGenericString<'a, S>
+where
+    I: Into<Cow<'a, S>>,
+    <S as ToOwned>::Owned: std::fmt::Debug,
+{
+    styled(Color::Fixed(color).normal(), input)
+}
+
+/// Paints the given input with an arbitrary (truecolor) RGB color, as does [orange](orange).
+pub fn rgb<'a, I, S: 'a + ToOwned + ?Sized>(
+    (r, g, b): (u8, u8, u8),
+    input: I,
+) -> ANSIGenericString<'a, S>
+where
+    I: Into<Cow<'a, S>>,
+    <S as ToOwned>::Owned: std::fmt::Debug,
+{
+    styled(Color::RGB(r, g, b).normal(), input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes() {
+        set_color_mode(ColorMode::Always);
+        assert_eq!(cyan("pod").to_string(), "\u{1b}[36mpod\u{1b}[0m");
+        set_color_mode(ColorMode::Never);
+        assert_eq!(cyan("pod").to_string(), "pod");
+        assert_eq!(bold("pod").to_string(), "pod");
+        assert_eq!(orange("pod").to_string(), "pod");
+        set_color_mode(ColorMode::Auto);
+        assert_eq!(color_mode(), ColorMode::Auto);
+    }
+
+    #[test]
+    fn parses() {
+        assert_eq!("Never".parse(), Ok(ColorMode::Never));
+        assert_eq!(" always ".parse(), Ok(ColorMode::Always));
+        assert!("sometimes".parse::<ColorMode>().is_err());
+    }
 }
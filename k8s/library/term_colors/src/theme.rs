@@ -0,0 +1,201 @@
+use crate::{styled, Color, Style};
+use ansi_term::ANSIGenericString;
+use std::borrow::Cow;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+/// The environment variable that configures the [Theme](Theme) of a service, being one of `dark`,
+/// `light`, or `high-contrast`. This defaults to `dark`.
+pub const COLOR_THEME: &str = "COLOR_THEME";
+
+lazy_static! {
+    static ref THEME: RwLock<Theme> = RwLock::new(Theme::dark());
+}
+
+/// A Role is the meaning of a piece of output, which the current [Theme](Theme) maps to a style.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Role {
+    /// Something going as expected, such as a pod becoming `Running`.
+    Info,
+    /// Something worth a second look.
+    Warn,
+    /// Something having gone wrong, such as a pod becoming `Terminated`.
+    Error,
+    /// Something worth drawing the eye to, such as the name of the pod being logged about.
+    Highlight,
+    /// An opaque identifier, such as a UUID or a digest.
+    Identifier,
+}
+
+/// A Theme maps every [Role](Role) to a [Style](Style), so that services log by meaning (say,
+/// `highlight(pod)`) rather than by hard-coded color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    pub info: Style,
+    pub warn: Style,
+    pub error: Style,
+    pub highlight: Style,
+    pub identifier: Style,
+}
+
+impl Theme {
+    /// The default theme, suited to terminals with a dark background.
+    pub fn dark() -> Theme {
+        Theme {
+            info: Color::Green.normal(),
+            warn: Color::Yellow.normal(),
+            error: Color::Red.normal(),
+            highlight: Color::Cyan.normal(),
+            identifier: Color::Purple.normal(),
+        }
+    }
+
+    /// A theme suited to terminals with a light background, on which yellow and cyan wash out.
+    pub fn light() -> Theme {
+        Theme {
+            info: Color::Fixed(28).normal(),
+            warn: Color::Fixed(130).normal(),
+            error: Color::Fixed(124).normal(),
+            highlight: Color::Blue.normal(),
+            identifier: Color::Fixed(90).normal(),
+        }
+    }
+
+    /// A theme of bold, bright, colors for the sake of legibility.
+    pub fn high_contrast() -> Theme {
+        Theme {
+            info: Color::Fixed(10).bold(),
+            warn: Color::Fixed(11).bold(),
+            error: Color::Fixed(9).bold(),
+            highlight: Color::Fixed(14).bold(),
+            identifier: Color::Fixed(15).bold().underline(),
+        }
+    }
+
+    /// Returns the theme configured by [COLOR_THEME](COLOR_THEME).
+    ///
+    /// This function PANICS if the variable is set to anything other than a built-in theme.
+    pub fn configured() -> Theme {
+        match std::env::var(COLOR_THEME) {
+            Ok(theme) if !theme.trim().is_empty() => theme.parse().unwrap_or_else(|err| {
+                panic!(
+                    "The {} environment variable is malformed, {}",
+                    COLOR_THEME, err
+                )
+            }),
+            _ => Theme::dark(),
+        }
+    }
+
+    /// Returns the style of the given role within this theme.
+    pub fn style(&self, role: Role) -> Style {
+        match role {
+            Role::Info => self.info,
+            Role::Warn => self.warn,
+            Role::Error => self.error,
+            Role::Highlight => self.highlight,
+            Role::Identifier => self.identifier,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(theme: &str) -> Result<Self, Self::Err> {
+        match theme.trim().to_lowercase().as_str() {
+            "dark" => Ok(Theme::dark()),
+            "light" => Ok(Theme::light()),
+            "high-contrast" => Ok(Theme::high_contrast()),
+            _ => Err(format!(
+                "'{}' is not one of dark, light, or high-contrast",
+                theme
+            )),
+        }
+    }
+}
+
+/// Sets the global [Theme](Theme).
+pub fn set_theme(theme: Theme) {
+    *THEME.write().unwrap() = theme;
+}
+
+/// Returns the global [Theme](Theme).
+pub fn theme() -> Theme {
+    *THEME.read().unwrap()
+}
+
+/// Paints the given input in the style that the global [Theme](Theme) gives to the given role.
+pub fn role<'a, I, S: 'a + ToOwned + ?Sized>(role: Role, input: I) -> ANSIGenericString<'a, S>
+where
+    I: Into<Cow<'a, S>>,
+    <S as ToOwned>::Owned: std::fmt::Debug,
+{
+    styled(theme().style(role), input)
+}
+
+pub fn info<'a, I, S: 'a + ToOwned + ?Sized>(input: I) -> ANSIGenericString<'a, S>
+where
+    I: Into<Cow<'a, S>>,
+    <S as ToOwned>::Owned: std::fmt::Debug,
+{
+    role(Role::Info, input)
+}
+
+pub fn warn<'a, I, S: 'a + ToOwned + ?Sized>(input: I) -> ANSIGenericString<'a, S>
+where
+    I: Into<Cow<'a, S>>,
+    <S as ToOwned>::Owned: std::fmt::Debug,
+{
+    role(Role::Warn, input)
+}
+
+pub fn error<'a, I, S: 'a + ToOwned + ?Sized>(input: I) -> ANSIGenericString<'a, S>
+where
+    I: Into<Cow<'a, S>>,
+    <S as ToOwned>::Owned: std::fmt::Debug,
+{
+    role(Role::Error, input)
+}
+
+pub fn highlight<'a, I, S: 'a + ToOwned + ?Sized>(input: I) -> ANSIGenericString<'a, S>
+where
+    I: Into<Cow<'a, S>>,
+    <S as ToOwned>::Owned: std::fmt::Debug,
+{
+    role(Role::Highlight, input)
+}
+
+pub fn identifier<'a, I, S: 'a + ToOwned + ?Sized>(input: I) -> ANSIGenericString<'a, S>
+where
+    I: Into<Cow<'a, S>>,
+    <S as ToOwned>::Owned: std::fmt::Debug,
+{
+    role(Role::Identifier, input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses() {
+        assert_eq!("light".parse(), Ok(Theme::light()));
+        assert_eq!(" High-Contrast ".parse(), Ok(Theme::high_contrast()));
+        assert!("solarized".parse::<Theme>().is_err());
+    }
+
+    #[test]
+    fn styles() {
+        let theme = Theme::dark();
+        assert_eq!(theme.style(Role::Highlight), Color::Cyan.normal());
+        assert_eq!(theme.style(Role::Error), Color::Red.normal());
+        assert!(Theme::high_contrast().style(Role::Warn).is_bold);
+    }
+}
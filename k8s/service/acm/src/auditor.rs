@@ -0,0 +1,73 @@
+//! The auditor keeps the ACM's [audit trail](audit) of every deploy, delete, and refresh.
+//!
+//! Which sink the trail is written into is configured by the [AUDIT_SINK](audit::AUDIT_SINK)
+//! environment variable. The `file` sink defaults to the `audit.log` of the ACM's
+//! [store](store), while the `events` sink attaches a Kubernetes Event to every pod operated upon.
+use crate::store;
+use audit::{Action, Actor, Auditor, Entry};
+use k8s::deletion::DeletionCause;
+use result::Result;
+
+/// The number of entries returned by the `/audit` endpoint unless another limit is requested.
+pub const DEFAULT_AUDIT_LIMIT: usize = 100;
+
+/// The actor of the deletions that the ACM makes of its own accord, such as garbage collections.
+pub const ACM_ACTOR: &str = "acm";
+
+const AUDIT_FILE: &str = "audit.log";
+
+lazy_static! {
+    static ref AUDITOR: Auditor = Auditor::configured(
+        store::directory().join(AUDIT_FILE),
+        Some(k8s::audit::EventSink::boxed)
+    );
+}
+
+/// Forces the evaluation of the [AUDIT_SINK](audit::AUDIT_SINK) configuration.
+pub fn configure() {
+    lazy_static::initialize(&AUDITOR);
+}
+
+/// Records the given action, made by the given actor upon the given target, before handing its
+/// result back.
+pub async fn record<T>(
+    actor: &Actor,
+    action: Action,
+    target: String,
+    result: Result<T>,
+) -> Result<T> {
+    AUDITOR
+        .record(Entry::new(actor, action, target, &result))
+        .await;
+    result
+}
+
+/// Records the deletion of the given target, made by the given actor for the given cause, before
+/// handing its result back.
+pub async fn record_deletion<T>(
+    actor: &Actor,
+    target: String,
+    cause: &DeletionCause,
+    result: Result<T>,
+) -> Result<T> {
+    AUDITOR
+        .record(Entry::new(actor, Action::Delete, target, &result).with_cause(cause))
+        .await;
+    result
+}
+
+/// Returns (at most) the given number of the most recent audit entries, oldest first.
+pub fn recent(limit: usize) -> Vec<Entry> {
+    AUDITOR.recent(limit)
+}
+
+/// Returns the `<namespace>/<name>` that a request targets, before its namespace is
+/// [resolved](crate::tenancy::namespace) (which may well fail, and be audited all the same).
+pub fn target(namespace: &Option<String>, name: &str) -> String {
+    let namespace = namespace
+        .as_deref()
+        .map(str::trim)
+        .filter(|namespace| !namespace.is_empty())
+        .unwrap_or(k8s::OCF_NAMESPACE);
+    format!("{}/{}", namespace, name)
+}
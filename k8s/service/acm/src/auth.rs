@@ -0,0 +1,91 @@
+use error::*;
+use rocket::request::{FromRequest, Outcome, Request};
+
+/// The environment variable that configures the bearer token of the operator scope. Endpoints
+/// guarded by an [Operator](Operator) are disabled entirely unless this is set. It is loaded
+/// alongside the rest of the ACM's [configuration](crate::env).
+pub const OPERATOR_TOKEN: &str = "OPERATOR_TOKEN";
+
+/// An Operator is a request guard that admits only requests bearing the operator's token within
+/// their `Authorization: Bearer <token>` header. It guards endpoints that are meant for the people
+/// operating the cluster (such as [exec](crate::exec())) rather than for Alation itself.
+///
+/// Requests without the token fail with a `401 Unauthorized`, which is rendered by the
+/// [unauthorized](unauthorized) catcher. Should no token be configured at all, then every
+/// request fails with a `403 Forbidden`, which is rendered by the [forbidden](forbidden) catcher.
+pub struct Operator;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Operator {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let token = match crate::env::config().operator_token.as_ref() {
+            Some(token) => token.raw_secret(),
+            None => return Outcome::Failure((rocket::http::Status::Forbidden, ())),
+        };
+        if bears_token(request, token) {
+            Outcome::Success(Operator)
+        } else {
+            warn!(
+                "Refused an unauthorized request to {}",
+                term_colors::highlight(request.uri().to_string())
+            );
+            Outcome::Failure((rocket::http::Status::Unauthorized, ()))
+        }
+    }
+}
+
+/// Returns whether the given request bears the operator's token, which it never does should no
+/// token be configured.
+pub fn is_operator(request: &Request<'_>) -> bool {
+    crate::env::config()
+        .operator_token
+        .as_ref()
+        .is_some_and(|token| bears_token(request, token.raw_secret()))
+}
+
+/// Returns whether the given request bears the given token within its `Authorization` header.
+fn bears_token(request: &Request<'_>, token: &str) -> bool {
+    request
+        .headers()
+        .get_one("Authorization")
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(str::trim)
+        .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()))
+}
+
+/// Compares the two tokens in time that depends only upon their lengths, so that a client cannot
+/// discover the token one byte at a time.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Renders an [Unauthorized](Unauthorized) error.
+#[catch(401)]
+pub fn unauthorized() -> Box<dyn AcmError> {
+    Unauthorized {}.into()
+}
+
+/// Renders an [OperatorScopeDisabled](OperatorScopeDisabled) error.
+#[catch(403)]
+pub fn forbidden() -> Box<dyn AcmError> {
+    OperatorScopeDisabled {}.into()
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::Unauthorized)]
+#[error(
+    "This endpoint requires the operator's token, given as an 'Authorization: Bearer <token>' header."
+)]
+#[error_code("ACM-2200")]
+pub struct Unauthorized {}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::Forbidden)]
+#[error(
+    "This endpoint is only available to operators, however no operator token has been configured \
+via the OPERATOR_TOKEN environment variable."
+)]
+#[error_code("ACM-2201")]
+pub struct OperatorScopeDisabled {}
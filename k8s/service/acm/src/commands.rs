@@ -0,0 +1,29 @@
+use config::acm::normalize;
+use error::*;
+use result::Result;
+
+/// Resolves the requested command into its arguments. The command MUST be exactly one of the
+/// [allowed](config::acm::AcmConfig::exec_commands) commands (modulo whitespace), otherwise a
+/// [CommandNotAllowed](CommandNotAllowed) is returned. Commands are never given to a shell.
+pub fn command(requested: &str) -> Result<Vec<String>> {
+    let requested = normalize(requested);
+    let allowed = &crate::env::config().exec_commands;
+    if allowed.contains(&requested) {
+        Ok(requested.split(' ').map(str::to_string).collect())
+    } else {
+        Err(CommandNotAllowed {
+            command: requested,
+            allowed: allowed.iter().cloned().collect::<Vec<String>>().join(", "),
+        }
+        .into())
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::Forbidden)]
+#[error("The command '{command}' may not be run within connectors. The allowed commands are [{allowed}].")]
+#[error_code("ACM-2300")]
+pub struct CommandNotAllowed {
+    command: String,
+    allowed: String,
+}
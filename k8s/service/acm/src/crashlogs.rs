@@ -0,0 +1,216 @@
+use crate::podmanager::PodId;
+use crate::store;
+use error::*;
+use k8s::client::Logs;
+use k8s::logsink::{FileSink, LogSink, RingBufferSink};
+use k8s::PodApi;
+use result::Result;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use term_colors::*;
+
+/// The most bytes of a crashed connector's logs that are read to find their last lines, so that
+/// a connector that dies in the middle of an enormous line does not bloat its error.
+const TAIL_BYTES: usize = 16 * 1024;
+
+/// The maximum number of crash logs that are kept. Once exceeded, the oldest are deleted first.
+pub const MAXIMUM_CRASH_LOGS: usize = 512;
+
+/// The maximum amount of time that a capture may take. The waiting client is not told of the
+/// crash until the capture is done, so it must not take long.
+pub const CAPTURE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CRASH_LOGS_DIRECTORY: &str = "crashlogs";
+
+/// A CrashLog is the captured tail of the logs of a connector that crashed.
+#[derive(Serialize, Kind, Debug)]
+pub struct CrashLog {
+    /// The pod (as `<namespace>/<name>`).
+    pub pod: String,
+    /// The Unix timestamp of when the logs were captured.
+    pub captured_at: i64,
+    pub logs: String,
+}
+
+/// Captures the tail of the logs of the given pod's connector into the ACM's [store](store), from
+/// which they may be [read](read) long after the pod is gone. This MUST be called before the pod
+/// is deleted. Should `previous` be set, then the logs are those of the previous (crashed)
+/// instance of the connector, which is what is wanted of a connector that has since restarted.
+///
+/// Failures are logged rather than returned as the pod is being torn down regardless.
+pub async fn capture(pods: &dyn PodApi, pod: &PodId, previous: bool) {
+    if captured_bytes() == 0 {
+        return;
+    }
+    match tokio::time::timeout(CAPTURE_TIMEOUT, try_capture(pods, pod, previous)).await {
+        Ok(Ok(bytes)) => debug!(
+            "Captured {} bytes of the logs of crashed pod {}",
+            bytes,
+            highlight(pod.to_string())
+        ),
+        Ok(Err(err)) => warn!(
+            "Failed to capture the logs of crashed pod {}, {}",
+            highlight(pod.to_string()),
+            err
+        ),
+        Err(_) => warn!(
+            "Gave up capturing the logs of crashed pod {} after {:?}",
+            highlight(pod.to_string()),
+            CAPTURE_TIMEOUT
+        ),
+    }
+    prune().await;
+}
+
+/// Returns the last [CRASH_LOG_LINES](config::acm::AcmConfig::crash_log_lines) lines of the logs
+/// of the given pod's connector, such that the panic (or stack trace) that it died with may be
+/// handed straight to its waiting client. As with [capture](capture), this MUST be called before
+/// the pod is deleted, and should `previous` be set, then the logs are those of the previous
+/// (crashed) instance.
+///
+/// `None` is returned should the connector have logged nothing, or should its logs not be had.
+pub async fn tail(pods: &dyn PodApi, pod: &PodId, previous: bool) -> Option<String> {
+    if tail_lines() == 0 {
+        return None;
+    }
+    match tokio::time::timeout(CAPTURE_TIMEOUT, try_tail(pods, pod, previous)).await {
+        Ok(Ok(tail)) => Some(tail).filter(|tail| !tail.is_empty()),
+        Ok(Err(err)) => {
+            warn!(
+                "Failed to read the logs of crashed pod {}, {}",
+                highlight(pod.to_string()),
+                err
+            );
+            None
+        }
+        Err(_) => {
+            warn!(
+                "Gave up reading the logs of crashed pod {} after {:?}",
+                highlight(pod.to_string()),
+                CAPTURE_TIMEOUT
+            );
+            None
+        }
+    }
+}
+
+/// Returns the captured logs of the given pod, or a [CrashLogNotFound](CrashLogNotFound) should
+/// none have been captured (or should they have since been pruned).
+pub async fn read(pod: &PodId) -> Result<CrashLog> {
+    let path = path(pod);
+    let not_found = || CrashLogNotFound {
+        id: pod.to_string(),
+    };
+    let logs = tokio::fs::read(&path).await.map_err(|_| not_found())?;
+    let captured_at = tokio::fs::metadata(&path)
+        .await
+        .and_then(|metadata| metadata.modified())
+        .map_err(|_| not_found())?;
+    Ok(CrashLog {
+        pod: pod.to_string(),
+        captured_at: unix(captured_at),
+        logs: String::from_utf8_lossy(&logs).into_owned(),
+    })
+}
+
+async fn try_capture(pods: &dyn PodApi, pod: &PodId, previous: bool) -> Result<usize> {
+    let resource = pods
+        .get(&pod.namespace, &pod.name)
+        .await
+        .map_err(k8s::errors::ApiError::from)?;
+    pods.tail_into(
+        &resource,
+        &sink(),
+        &pod.to_string(),
+        captured_bytes(),
+        previous,
+    )
+    .await
+}
+
+async fn try_tail(pods: &dyn PodApi, pod: &PodId, previous: bool) -> Result<String> {
+    let resource = pods
+        .get(&pod.namespace, &pod.name)
+        .await
+        .map_err(k8s::errors::ApiError::from)?;
+    let key = pod.to_string();
+    let sink = RingBufferSink::new(TAIL_BYTES, 1);
+    pods.tail_into(&resource, &sink, &key, TAIL_BYTES, previous)
+        .await?;
+    let logs = sink.read(&key).await?.unwrap_or_default();
+    let logs = String::from_utf8_lossy(&logs);
+    let lines: Vec<&str> = logs.lines().collect();
+    Ok(lines[lines.len().saturating_sub(tail_lines())..].join("\n"))
+}
+
+/// Deletes the oldest crash logs beyond [MAXIMUM_CRASH_LOGS](MAXIMUM_CRASH_LOGS).
+async fn prune() {
+    let mut logs = vec![];
+    let mut namespaces = match tokio::fs::read_dir(directory()).await {
+        Ok(namespaces) => namespaces,
+        Err(_) => return,
+    };
+    while let Ok(Some(namespace)) = namespaces.next_entry().await {
+        let mut entries = match tokio::fs::read_dir(namespace.path()).await {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if let Ok(modified) = entry.metadata().await.and_then(|m| m.modified()) {
+                logs.push((modified, entry.path()));
+            }
+        }
+    }
+    if logs.len() <= MAXIMUM_CRASH_LOGS {
+        return;
+    }
+    logs.sort();
+    for (_, path) in logs.iter().take(logs.len() - MAXIMUM_CRASH_LOGS) {
+        if let Err(err) = tokio::fs::remove_file(path).await {
+            warn!("Failed to prune the crash log {:?}, {}", path, err);
+        }
+    }
+}
+
+fn directory() -> PathBuf {
+    store::directory().join(CRASH_LOGS_DIRECTORY)
+}
+
+/// Crash logs are kept beneath the store, keyed by `<namespace>/<name>`.
+fn sink() -> FileSink {
+    FileSink::new(directory())
+}
+
+fn path(pod: &PodId) -> PathBuf {
+    sink().path(&pod.to_string())
+}
+
+fn unix(time: SystemTime) -> i64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|since| since.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// The [number of bytes](config::acm::AcmConfig::crash_log_bytes) of a crashed connector's logs
+/// that are captured.
+fn captured_bytes() -> usize {
+    crate::env::config().crash_log_bytes
+}
+
+/// The [number of lines](config::acm::AcmConfig::crash_log_lines) of a crashed connector's logs
+/// that are given to its waiting client.
+fn tail_lines() -> usize {
+    crate::env::config().crash_log_lines
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::NotFound)]
+#[error(
+    "No logs were captured for the pod {id}. Logs are only captured of connectors that crashed, \
+and only the most recent captures are kept."
+)]
+#[error_code("ACM-2500")]
+pub struct CrashLogNotFound {
+    id: String,
+}
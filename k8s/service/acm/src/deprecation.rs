@@ -0,0 +1,75 @@
+use chrono::TimeZone;
+use client_sdk::{Deprecation, Inspection};
+use error::*;
+use result::Result;
+
+/// Checks the given (inspected) image for a deprecation.
+///
+/// A deprecated image yields a warning for the caller of the deploy. Once the deprecation's
+/// sunset has passed, however, an [ImageSunset](ImageSunset) error is returned instead unless
+/// [REJECT_AFTER_SUNSET](config::acm::AcmConfig::reject_after_sunset) is disabled.
+pub fn check(inspection: &Inspection) -> Result<Option<String>> {
+    let deprecation = match inspection.deprecation.as_ref() {
+        Some(deprecation) => deprecation,
+        None => return Ok(None),
+    };
+    let sunset = match deprecation.sunset {
+        Some(sunset) => sunset,
+        None => {
+            return Ok(Some(format!(
+                "The image {} is deprecated. {}",
+                deprecation.tag, deprecation.message
+            )))
+        }
+    };
+    if chrono::Utc::now().timestamp() < sunset {
+        return Ok(Some(format!(
+            "The image {} is deprecated and will no longer be deployable after {}. {}",
+            deprecation.tag,
+            date(sunset),
+            deprecation.message
+        )));
+    }
+    if crate::env::config().reject_after_sunset {
+        return Err(ImageSunset::new(deprecation).into());
+    }
+    Ok(Some(format!(
+        "The image {} is deprecated and was due to be retired at {}. {}",
+        deprecation.tag,
+        date(sunset),
+        deprecation.message
+    )))
+}
+
+/// Renders the given Unix timestamp as an RFC 3339 date, falling back to the raw timestamp
+/// should it be out of range.
+fn date(timestamp: i64) -> String {
+    chrono::Utc
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|date| date.to_rfc3339())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::Gone)]
+#[error(
+    "The image {tag} was deprecated and retired at {sunset}, so it has not been deployed. \
+{message} Please deploy a supported build of the connector instead."
+)]
+#[error_code("ACM-1800")]
+pub struct ImageSunset {
+    tag: String,
+    sunset: String,
+    message: String,
+}
+
+impl ImageSunset {
+    fn new(deprecation: &Deprecation) -> ImageSunset {
+        ImageSunset {
+            tag: deprecation.tag.clone(),
+            sunset: deprecation.sunset.map(date).unwrap_or_default(),
+            message: deprecation.message.clone(),
+        }
+    }
+}
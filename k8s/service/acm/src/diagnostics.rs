@@ -0,0 +1,102 @@
+//! The `--preflight` mode of the ACM, which validates the environment that the ACM is about to run
+//! within rather than running within it. Every check is made (none stops short upon the failure of
+//! another, unless it depends upon it) and printed as a [Checklist](term_colors::layout::Checklist),
+//! after which the ACM exits non-zero should any check have failed. This makes for a ready made
+//! init container.
+//!
+//! Checked are that the Kubernetes API server is reachable, that pods may be read within every
+//! tenant namespace (and within the system namespace), that RBAC grants every
+//! [permission](k8s::permissions) that the ACM requires, and that the configured image pull secret
+//! (if any) exists within every tenant namespace.
+use crate::tenancy;
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::ListParams;
+use kube::Api;
+use term_colors::layout::Checklist;
+
+/// The command line flag that switches the ACM into its preflight mode.
+pub const PREFLIGHT: &str = "--preflight";
+
+/// Whether the ACM was asked to run its preflight checks.
+pub fn requested() -> bool {
+    std::env::args().any(|arg| arg == PREFLIGHT)
+}
+
+/// Runs every preflight check, prints the report, and returns the code that the process ought to
+/// exit with.
+pub async fn run() -> i32 {
+    let checklist = checks().await;
+    println!("{}", checklist);
+    if checklist.passed() {
+        0
+    } else {
+        1
+    }
+}
+
+async fn checks() -> Checklist {
+    let mut checklist = Checklist::default();
+    let client = match kube::Client::try_default().await {
+        Ok(client) => client,
+        Err(err) => {
+            checklist.check(
+                "Kubernetes configuration",
+                Err::<String, _>(format!("no usable configuration was found, {}", err)),
+            );
+            return checklist;
+        }
+    };
+    let reachable = client
+        .apiserver_version()
+        .await
+        .map(|version| format!("reachable, running {}", version.git_version));
+    let unreachable = reachable.is_err();
+    checklist.check("Kubernetes API", reachable);
+    if unreachable {
+        // Every check that follows asks the API server, so they would merely fail alike.
+        return checklist;
+    }
+    let mut namespaces = tenancy::namespaces();
+    namespaces.push(k8s::OCF_SYSTEM_NAMESPACE.to_string());
+    for namespace in &namespaces {
+        let pods: Api<Pod> = Api::namespaced(client.clone(), namespace);
+        checklist.check(
+            format!("Read pods in '{}'", namespace),
+            pods.list(&ListParams::default().limit(1))
+                .await
+                .map(|_| "permitted"),
+        );
+    }
+    match k8s::permissions::review(&tenancy::namespaces()).await {
+        Ok(permissions) => {
+            for permission in permissions.permissions {
+                let name = format!(
+                    "RBAC {} {} in '{}'",
+                    permission.verb, permission.resource, permission.namespace
+                );
+                let reason = permission.reason.unwrap_or_default();
+                checklist.check(
+                    name,
+                    match permission.allowed {
+                        true => Ok(reason),
+                        false if reason.is_empty() => Err("not permitted".to_string()),
+                        false => Err(format!("not permitted, {}", reason)),
+                    },
+                );
+            }
+        }
+        Err(err) => checklist.check("RBAC", Err::<String, _>(err)),
+    }
+    for namespace in tenancy::namespaces() {
+        checklist.check(
+            format!("Image pull secret in '{}'", namespace),
+            k8s::pull_secret::check(&namespace).await.map(|_| {
+                match k8s::pull_secret::configured() {
+                    Some(secret) => format!("'{}' exists", secret),
+                    None => "none configured".to_string(),
+                }
+            }),
+        );
+    }
+    checklist
+}
@@ -0,0 +1,59 @@
+//! Dry-runs of deploys and deletes, which tell the caller (typically upgrade tooling running its
+//! pre-flight checks) whether the operation would succeed without creating or deleting anything.
+//!
+//! A dry-run goes through every check that the real operation does, and then submits the
+//! operation to the Kubernetes API server as a server-side dry-run, such that the pod is validated
+//! and admitted (by every admission webhook within the cluster, too) exactly as it would be
+//! otherwise. Dry-runs are not [audited](crate::auditor) as they change nothing.
+use crate::pinning::Image;
+use crate::podmanager::PodId;
+use crate::{podmanager, preflight};
+use k8s_openapi::api::core::v1::Pod;
+use response::Response;
+use result::Result;
+
+/// Dry-runs a deploy of the given tag, returning the pod that would have been created alongside
+/// the warnings (such as a deprecation) that the deploy would have been met with.
+///
+/// Beyond the checks of a real deploy, the tag is [looked up](preflight::lookup) within the AIM,
+/// and so a tag that is not installed fails the dry-run with an
+/// [ImageNotInstalled](preflight::ImageNotInstalled). An image that is [pinned](Image::pinned) to
+/// a digest was already found within the AIM while being resolved.
+pub async fn deploy(
+    namespace: &str,
+    image: &Image,
+    name: &str,
+    ttl: u64,
+    resuffix: bool,
+    options: &k8s::PodOptions,
+) -> Result<Response<Pod>> {
+    // The admission is released as soon as it is dropped, which is merely to check that this
+    // ACM would have room for the pod right now.
+    podmanager::admission::admit()?;
+    if !image.pinned() {
+        preflight::lookup(&image.tag).await?;
+    }
+    let warnings = preflight::check(&image.tag, options).await?;
+    let pods = podmanager::pods().await;
+    let pod = k8s::deploy(
+        &*pods,
+        namespace,
+        &image.reference,
+        name,
+        ttl,
+        resuffix,
+        options,
+        true,
+    )
+    .await?;
+    Ok(warnings
+        .into_iter()
+        .fold(Response::from(pod), Response::with_warning))
+}
+
+/// Dry-runs the deletion of the pod of the given ID, returning the pod that would have been
+/// deleted. As a real deletion is idempotent, a pod that does not exist is not an error, but is
+/// rather met with an empty payload.
+pub async fn delete(id: &PodId) -> Result<Response<Option<Pod>>> {
+    Ok(k8s::delete_dry_run(&id.namespace, &id.name).await?.into())
+}
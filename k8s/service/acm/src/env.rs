@@ -0,0 +1,24 @@
+//! The settings of the ACM itself, as loaded (and validated) by the [config](config) library.
+//!
+//! Settings that belong to a particular module (say, the [rate limits](crate::ratelimit)) are
+//! still configured by that module, although they too may be given within the
+//! [configuration file](config::CONFIG_FILE).
+use config::acm::AcmConfig;
+
+lazy_static! {
+    static ref CONFIG: AcmConfig = AcmConfig::configured();
+}
+
+/// Loads (and validates) the configuration of the ACM, PANICKING with every problem that it has.
+///
+/// This MUST be called upon startup before anything else is configured, as it exports the
+/// settings of the configuration file into the environment for the sake of every other module.
+pub fn configure() {
+    lazy_static::initialize(&CONFIG);
+}
+
+/// Returns the configuration of the ACM, as reported by the [config](crate::configuration())
+/// endpoint.
+pub fn config() -> &'static AcmConfig {
+    &CONFIG
+}
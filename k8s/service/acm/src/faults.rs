@@ -0,0 +1,78 @@
+//! Fault injection for chaos testing the ACM. This module only exists should the ACM be built with
+//! the `faults` feature, which MUST NOT be enabled in production builds.
+//!
+//! Faults are switched on and off by an operator via the [faults](crate::put_faults()) endpoint,
+//! and are injected into the [PodApi](k8s::PodApi) that every PodManager is handed (see
+//! [FaultyPods](k8s::faults::FaultyPods)) as well as into the health checks of connectors. This
+//! makes reachable the error paths that a healthy cluster never takes, such as a
+//! `KubernetesUnresponsive` (`ACM-1104`, by way of API errors) and an
+//! `UnexpectedCloseOfEventStream` (`ACM-1105`, by way of closed watches).
+//!
+//! Only the operations made through the PodApi are faulted, anything else that the ACM asks of the
+//! API server (such as listing pods by selector) is not.
+use error::*;
+use k8s::faults::{Faults, FaultyPods};
+use k8s::PodApi;
+use result::Result;
+use rocket::serde::json::{self, Json};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use term_colors::*;
+
+lazy_static! {
+    static ref FAULTS: Arc<RwLock<Faults>> = Arc::new(RwLock::new(Faults::default()));
+}
+
+/// Wraps the given PodApi such that the faults currently switched on are injected into it.
+pub fn inject(pods: Arc<dyn PodApi>) -> Arc<dyn PodApi> {
+    Arc::new(FaultyPods::new(pods, FAULTS.clone()))
+}
+
+/// Returns the faults that are currently switched on.
+pub fn current() -> Faults {
+    FAULTS.read().unwrap().clone()
+}
+
+/// Replaces the faults that are currently switched on with the given ones.
+pub fn set(faults: Faults) -> Faults {
+    warn!(
+        "Injecting the faults {}",
+        highlight(format!("{:?}", faults))
+    );
+    *FAULTS.write().unwrap() = faults.clone();
+    faults
+}
+
+/// Stalls for the [health check delay](k8s::faults::Faults::health_check_delay), should one be
+/// switched on.
+pub async fn stall_health_check() {
+    if let Some(delay) = current().health_check_delay {
+        tokio::time::sleep(Duration::from_secs(delay)).await;
+    }
+}
+
+/// Takes the [Faults](k8s::faults::Faults) out of the body of a request. A body that is not valid
+/// Faults is a [MalformedFaults](MalformedFaults).
+pub fn from_body(body: std::result::Result<Json<Faults>, json::Error<'_>>) -> Result<Faults> {
+    match body {
+        Ok(Json(faults)) => Ok(faults),
+        Err(err) => Err(MalformedFaults {
+            cause: match err {
+                json::Error::Io(err) => err.to_string(),
+                json::Error::Parse(_, err) => err.to_string(),
+            },
+        }
+        .into()),
+    }
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The body could not be read as faults, {cause}. The body must be a JSON object with any of \
+the 'api_errors', 'close_watches', 'containerd_errors', and 'health_check_delay' fields."
+)]
+#[error_code("ACM-3000")]
+pub struct MalformedFaults {
+    cause: String,
+}
@@ -0,0 +1,96 @@
+//! HMAC-SHA256 ([RFC 2104](https://datatracker.ietf.org/doc/html/rfc2104)), with which the ACM
+//! signs its [lifecycle notifications](crate::podmanager::notifications) and its
+//! [keep-alive tickets](crate::podmanager::tickets).
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+/// The block size of SHA-256, which the HMAC key is padded to.
+const BLOCK_SIZE: usize = 64;
+
+/// Returns the hex encoded HMAC-SHA256 of the given message under the given key.
+pub fn sign(key: &[u8], message: &[u8]) -> String {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.iter().map(|k| k ^ byte).collect::<Vec<u8>>();
+    let inner = Sha256::new().chain(pad(0x36)).chain(message).finalize();
+    let outer = Sha256::new().chain(pad(0x5c)).chain(inner).finalize();
+    outer.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+/// Returns whether the given hex encoded signature is that of the given message under the given
+/// key. The comparison takes the same time no matter where the signatures differ, so as not to
+/// hand a forger the signature one byte at a time.
+pub fn verify(key: &[u8], message: &[u8], signature: &str) -> bool {
+    let expected = sign(key, message);
+    expected.len() == signature.len()
+        && expected
+            .bytes()
+            .zip(signature.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The test cases of [RFC 4231](https://datatracker.ietf.org/doc/html/rfc4231#section-4), save
+    /// for the fifth (which truncates its output).
+    #[test]
+    fn rfc_4231() {
+        let cases: [(Vec<u8>, &[u8], &str); 6] = [
+            (
+                vec![0x0b; 20],
+                b"Hi There",
+                "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            ),
+            (
+                b"Jefe".to_vec(),
+                b"what do ya want for nothing?",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                vec![0xaa; 20],
+                &[0xdd; 50],
+                "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+            ),
+            (
+                (0x01..=0x19).collect(),
+                &[0xcd; 50],
+                "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+            ),
+            (
+                vec![0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+            (
+                vec![0xaa; 131],
+                b"This is a test using a larger than block-size key and a larger than block-size \
+data. The key needs to be hashed before being used by the HMAC algorithm.",
+                "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+            ),
+        ];
+        for (key, message, signature) in cases.iter() {
+            assert_eq!(sign(key, message), *signature);
+            assert!(verify(key, message, signature));
+        }
+    }
+
+    #[test]
+    fn verification() {
+        let signature = sign(b"key", b"message");
+        assert!(verify(b"key", b"message", &signature));
+        assert!(!verify(b"key", b"massage", &signature));
+        assert!(!verify(b"yek", b"message", &signature));
+        assert!(!verify(b"key", b"message", &signature[1..]));
+        assert!(!verify(b"key", b"message", ""));
+    }
+}
@@ -0,0 +1,337 @@
+//! Idempotency keys make deploys safe to retry. A client that retries a deploy (say, after its
+//! connection to the ACM dropped before the response arrived) under the same
+//! [Idempotency-Key](IDEMPOTENCY_KEY_HEADER) is answered with the pod of the original deploy
+//! rather than deploying a duplicate pod.
+//!
+//! Keys are remembered in memory for the [idempotency window](config::acm::AcmTunables::idempotency_window)
+//! and are scoped to the [Actor](audit::Actor) that made the deploy, so that two clients who happen
+//! to choose the same key never see one another's pods. Only successful deploys are remembered. A
+//! replay of a deploy that failed deploys afresh.
+use crate::podmanager::tickets;
+use audit::Actor;
+use error::*;
+use k8s_openapi::api::core::v1::Pod;
+use kube::ResourceExt;
+use response::Response;
+use result::Result;
+use rocket::request::{FromRequest, Outcome, Request};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// The header under which clients give the idempotency key of a deploy.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// The header that marks a response as the replay of an earlier deploy.
+pub const REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+/// The maximum length of an idempotency key.
+pub const MAXIMUM_KEY_LENGTH: usize = 255;
+
+/// The slot of every idempotency key, by actor and key.
+type Slots = HashMap<(String, String), Arc<Mutex<Option<Remembered>>>>;
+
+lazy_static! {
+    static ref KEYS: std::sync::Mutex<Slots> = std::sync::Mutex::new(HashMap::new());
+}
+
+/// An IdempotencyKey is a request guard that reads the [Idempotency-Key](IDEMPOTENCY_KEY_HEADER)
+/// of a request, should it have one. It never fails.
+pub struct IdempotencyKey(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IdempotencyKey {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(IdempotencyKey(
+            request
+                .headers()
+                .get_one(IDEMPOTENCY_KEY_HEADER)
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(String::from),
+        ))
+    }
+}
+
+/// A Deployment is the outcome of a successful deploy, which is what replays are answered with.
+#[derive(Clone, Debug)]
+pub struct Deployment {
+    pub pod: Pod,
+    pub warnings: Vec<String>,
+    /// The pod's first [ticket](crate::podmanager::tickets), which a replay hands out once more.
+    pub ticket: String,
+}
+
+impl Deployment {
+    /// Renders this deployment as the `202 Accepted` of a deploy, which bears the pod's ticket
+    /// within its [TICKET_HEADER](tickets::TICKET_HEADER) and whose `Location` names the pod's
+    /// wait endpoint.
+    pub fn into_response(self) -> Response<Pod> {
+        let location = format!(
+            "/wait?ticket={}&namespace={}",
+            self.ticket,
+            self.pod
+                .namespace()
+                .unwrap_or_else(|| k8s::OCF_NAMESPACE.to_string())
+        );
+        self.warnings
+            .into_iter()
+            .fold(Response::accepted(self.pod), Response::with_warning)
+            .location(location)
+            .header(tickets::TICKET_HEADER, self.ticket)
+    }
+}
+
+/// What is remembered of a deploy, being the request that it was made with (so that a key reused
+/// for a different request can be refused) and its outcome.
+#[derive(Debug)]
+struct Remembered {
+    request: String,
+    deployment: Deployment,
+    at: Instant,
+}
+
+/// A Claim is what is to be done of a deploy that was given an idempotency key.
+#[allow(clippy::large_enum_variant)]
+pub enum Claim {
+    /// The deploy is a replay, and so is to be answered with the original deployment.
+    Replay(Deployment),
+    /// The deploy is to be made, after which its deployment is to be [remembered](Slot::remember).
+    /// There is no slot should the deploy not have been given a key.
+    Fresh(Option<Slot>),
+}
+
+/// A Slot is the exclusive right to make the deploy of an idempotency key. Replays of the key
+/// wait for the slot to be released, such that concurrent replays never deploy concurrently.
+pub struct Slot {
+    request: String,
+    guard: OwnedMutexGuard<Option<Remembered>>,
+}
+
+impl Slot {
+    /// Remembers the given deployment under this slot's key for the
+    /// [idempotency window](config::acm::AcmTunables::idempotency_window).
+    pub fn remember(mut self, deployment: &Deployment) {
+        *self.guard = Some(Remembered {
+            request: self.request,
+            deployment: deployment.clone(),
+            at: Instant::now(),
+        });
+    }
+}
+
+/// Claims the given key on behalf of the given actor for a deploy of the given request (being
+/// any description of the deploy's parameters). A request that was not given a key is always
+/// [Fresh](Claim::Fresh).
+///
+/// Should the key have been used within the window for a different request, then an
+/// [IdempotencyKeyReused](IdempotencyKeyReused) is returned.
+pub async fn claim(key: IdempotencyKey, actor: &Actor, request: String) -> Result<Claim> {
+    let key = match key.0 {
+        None => return Ok(Claim::Fresh(None)),
+        Some(key) if key.len() > MAXIMUM_KEY_LENGTH => {
+            return Err(MalformedIdempotencyKey { length: key.len() }.into())
+        }
+        Some(key) => key,
+    };
+    let window = window();
+    let cell = {
+        let mut keys = KEYS.lock().unwrap();
+        forget_expired(&mut keys, window);
+        keys.entry((actor.to_string(), key.clone()))
+            .or_insert_with(|| Arc::new(Mutex::new(None)))
+            .clone()
+    };
+    let guard = cell.lock_owned().await;
+    match guard.as_ref() {
+        Some(remembered) if remembered.at.elapsed() < window => {
+            if remembered.request == request {
+                info!(
+                    "Replaying the deploy of {} for {} under the idempotency key {}",
+                    remembered.deployment.pod.name(),
+                    actor,
+                    key
+                );
+                Ok(Claim::Replay(remembered.deployment.clone()))
+            } else {
+                Err(IdempotencyKeyReused {
+                    key,
+                    original: remembered.request.clone(),
+                }
+                .into())
+            }
+        }
+        _ => Ok(Claim::Fresh(Some(Slot { request, guard }))),
+    }
+}
+
+/// Returns the configured [idempotency window](config::acm::AcmTunables::idempotency_window).
+fn window() -> Duration {
+    Duration::from_secs(crate::reload::tunables().idempotency_window)
+}
+
+/// Forgets every key whose deploy has outlived the window, as well as every key whose deploy
+/// failed. Keys that are being claimed, or whose deploys are still underway, are kept.
+fn forget_expired(
+    keys: &mut HashMap<(String, String), Arc<Mutex<Option<Remembered>>>>,
+    window: Duration,
+) {
+    // A key whose cell is held by anyone other than this map is being claimed (or deployed) as
+    // we speak.
+    keys.retain(|_, cell| {
+        Arc::strong_count(cell) > 1
+            || matches!(
+                cell.try_lock().as_deref(),
+                Ok(Some(remembered)) if remembered.at.elapsed() < window
+            )
+    });
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::UnprocessableEntity)]
+#[error(
+    "The idempotency key {key} was already used for a different deploy ({original}). Please use \
+a fresh key for every distinct deploy."
+)]
+#[error_code("ACM-2700")]
+pub struct IdempotencyKeyReused {
+    key: String,
+    original: String,
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The Idempotency-Key header may be at most 255 characters long, got one of {length} characters."
+)]
+#[error_code("ACM-2701")]
+pub struct MalformedIdempotencyKey {
+    length: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deployment(name: &str) -> Deployment {
+        let mut pod = Pod::default();
+        pod.metadata.name = Some(name.to_string());
+        Deployment {
+            pod,
+            warnings: vec![],
+            ticket: "ticket".to_string(),
+        }
+    }
+
+    fn key(key: &str) -> IdempotencyKey {
+        IdempotencyKey(Some(key.to_string()))
+    }
+
+    async fn deploy(key: IdempotencyKey, actor: &Actor, request: &str, name: &str) -> Claim {
+        match claim(key, actor, request.to_string()).await.unwrap() {
+            Claim::Fresh(Some(slot)) => {
+                slot.remember(&deployment(name));
+                Claim::Fresh(None)
+            }
+            claim => claim,
+        }
+    }
+
+    #[test]
+    fn keyless_deploys_are_fresh() {
+        tokio_test::block_on(async {
+            let actor = Actor::from("keyless");
+            let claim = claim(IdempotencyKey(None), &actor, "request".to_string()).await;
+            assert!(matches!(claim.unwrap(), Claim::Fresh(None)));
+        });
+    }
+
+    #[test]
+    fn replays() {
+        tokio_test::block_on(async {
+            let actor = Actor::from("replays");
+            let first = deploy(key("replays"), &actor, "request", "first").await;
+            assert!(matches!(first, Claim::Fresh(None)));
+            match deploy(key("replays"), &actor, "request", "second").await {
+                Claim::Replay(deployment) => assert_eq!(deployment.pod.name(), "first"),
+                Claim::Fresh(_) => panic!("expected a replay"),
+            }
+        });
+    }
+
+    #[test]
+    fn refuses_reuse() {
+        tokio_test::block_on(async {
+            let actor = Actor::from("refuses_reuse");
+            deploy(key("refuses_reuse"), &actor, "request", "first").await;
+            let err = claim(key("refuses_reuse"), &actor, "other".to_string())
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(err.error_code(), Some("ACM-2700"));
+        });
+    }
+
+    #[test]
+    fn scopes_keys_to_actors() {
+        tokio_test::block_on(async {
+            deploy(key("scoped"), &Actor::from("scoped-a"), "request", "a").await;
+            let claim = deploy(key("scoped"), &Actor::from("scoped-b"), "request", "b").await;
+            assert!(matches!(claim, Claim::Fresh(None)));
+        });
+    }
+
+    #[test]
+    fn forgets_failed_deploys() {
+        tokio_test::block_on(async {
+            let actor = Actor::from("forgets_failed_deploys");
+            let claim = claim(key("failed"), &actor, "request".to_string()).await;
+            assert!(matches!(claim.unwrap(), Claim::Fresh(Some(_))));
+            // The slot was dropped without remembering a deployment, as of a failed deploy.
+            let claim = deploy(key("failed"), &actor, "other", "retry").await;
+            assert!(matches!(claim, Claim::Fresh(None)));
+        });
+    }
+
+    #[test]
+    fn rejects_long_keys() {
+        tokio_test::block_on(async {
+            let long = "k".repeat(MAXIMUM_KEY_LENGTH + 1);
+            let err = claim(key(&long), &Actor::from("long"), "request".to_string())
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(err.error_code(), Some("ACM-2701"));
+        });
+    }
+
+    #[test]
+    fn forgets_expired_keys() {
+        let remembered = |at: Instant| {
+            Arc::new(Mutex::new(Some(Remembered {
+                request: "request".to_string(),
+                deployment: deployment("pod"),
+                at,
+            })))
+        };
+        let slot = |key: &str| ("actor".to_string(), key.to_string());
+        let mut keys = Slots::new();
+        keys.insert(slot("recent"), remembered(Instant::now()));
+        keys.insert(
+            slot("expired"),
+            remembered(Instant::now() - Duration::from_secs(60)),
+        );
+        keys.insert(slot("failed"), Arc::new(Mutex::new(None)));
+        let claimed = Arc::new(Mutex::new(None));
+        keys.insert(slot("claimed"), claimed.clone());
+        forget_expired(&mut keys, Duration::from_secs(30));
+        let mut kept: Vec<_> = keys.keys().map(|(_, key)| key.as_str()).collect();
+        kept.sort_unstable();
+        assert_eq!(kept, vec!["claimed", "recent"]);
+        drop(claimed);
+    }
+}
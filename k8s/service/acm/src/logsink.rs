@@ -0,0 +1,144 @@
+//! The log sink is where the ACM archives the complete logs of every connector that it runs,
+//! keyed by the job's ID (`<namespace>/<name>` of its pod), such that they are retained long
+//! after the pod itself is gone.
+//!
+//! Which sink is used is configured by [LOG_SINK](config::acm::AcmConfig::log_sink). Archiving
+//! is disabled unless it is set.
+use async_trait::async_trait;
+use aws_sdk_s3::{ByteStream, Client};
+use k8s::logsink::{FileSink, LogSink, LogSinkFailed, LogWriter, RingBufferSink};
+use result::Result;
+use tokio::sync::OnceCell;
+
+/// The maximum number of jobs whose logs are kept by the `memory` sink. Once exceeded, the logs of
+/// the oldest job are forgotten first.
+pub const MAXIMUM_MEMORY_JOBS: usize = 256;
+
+lazy_static! {
+    static ref SINK: Option<Box<dyn LogSink>> = configured();
+}
+
+/// Constructs the [configured](config::acm::AcmConfig::log_sink) sink.
+pub fn configure() {
+    lazy_static::initialize(&SINK);
+}
+
+/// Returns the configured sink, or `None` should archiving be disabled.
+pub fn sink() -> Option<&'static dyn LogSink> {
+    SINK.as_deref()
+}
+
+fn configured() -> Option<Box<dyn LogSink>> {
+    let config = crate::env::config();
+    match config.log_sink.as_str() {
+        "file" => Some(Box::new(FileSink::new(config.log_sink_path.clone()))),
+        "s3" => Some(Box::new(S3Sink {
+            bucket: config
+                .log_sink_bucket
+                .clone()
+                .expect("The LOG_SINK_BUCKET setting is mandatory when using the S3 log sink"),
+            prefix: config.log_sink_prefix.clone(),
+        })),
+        "memory" => Some(Box::new(RingBufferSink::new(
+            config.log_sink_bytes,
+            MAXIMUM_MEMORY_JOBS,
+        ))),
+        _ => None,
+    }
+}
+
+lazy_static! {
+    static ref CLIENT: OnceCell<Client> = OnceCell::new();
+}
+
+/// Returns the shared [AWS S3 client](aws_sdk_s3::Client), which is configured from the
+/// environment the first time that it is requested.
+async fn client() -> &'static Client {
+    CLIENT
+        .get_or_init(|| async { Client::new(&aws_config::load_from_env().await) })
+        .await
+}
+
+/// An S3Sink writes the logs of every key into the object `<prefix><key>.log` within a bucket.
+///
+/// S3 objects cannot be appended to, so the logs are held in memory until the writer is closed
+/// and are then uploaded in a single PutObject.
+struct S3Sink {
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Sink {
+    fn object(&self, key: &str) -> String {
+        format!("{}{}.log", self.prefix, key)
+    }
+}
+
+#[async_trait]
+impl LogSink for S3Sink {
+    async fn open(&self, key: &str) -> Result<Box<dyn LogWriter>> {
+        Ok(Box::new(S3Writer {
+            key: key.to_string(),
+            bucket: self.bucket.clone(),
+            object: self.object(key),
+            logs: vec![],
+        }))
+    }
+
+    async fn read(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let output = match client()
+            .await
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.object(key))
+            .send()
+            .await
+        {
+            Ok(output) => output,
+            Err(aws_sdk_s3::SdkError::ServiceError { err, .. }) if err.is_no_such_key() => {
+                return Ok(None)
+            }
+            Err(err) => return Err(LogSinkFailed::new(key, err).into()),
+        };
+        let logs = output
+            .body
+            .collect()
+            .await
+            .map_err(|err| LogSinkFailed::new(key, err))?;
+        Ok(Some(logs.into_bytes().to_vec()))
+    }
+}
+
+struct S3Writer {
+    key: String,
+    bucket: String,
+    object: String,
+    logs: Vec<u8>,
+}
+
+#[async_trait]
+impl LogWriter for S3Writer {
+    async fn write(&mut self, chunk: &[u8]) -> Result<()> {
+        self.logs.extend_from_slice(chunk);
+        Ok(())
+    }
+
+    async fn close(self: Box<Self>) -> Result<()> {
+        let S3Writer {
+            key,
+            bucket,
+            object,
+            logs,
+        } = *self;
+        client()
+            .await
+            .put_object()
+            .bucket(bucket)
+            .key(object)
+            .body(ByteStream::from(logs))
+            .send()
+            .await
+            .map_err(|err| LogSinkFailed::new(&key, err))?;
+        Ok(())
+    }
+}
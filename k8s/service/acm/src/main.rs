@@ -12,15 +12,60 @@ extern crate jemallocator;
 #[global_allocator]
 static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
 
+pub mod auditor;
+pub mod auth;
+pub mod commands;
+pub mod crashlogs;
+pub mod deprecation;
+pub mod diagnostics;
+pub mod dry_run;
+pub mod env;
+#[cfg(feature = "faults")]
+pub mod faults;
+pub mod hmac;
+pub mod idempotency;
+pub mod logsink;
+pub mod metrics;
+pub mod options;
+pub mod pinning;
+pub mod platform;
 pub mod podmanager;
+pub mod pool;
+pub mod preflight;
+pub mod proxy;
+pub mod ratelimit;
+pub mod reload;
+pub mod restarts;
+pub mod store;
+pub mod tenancy;
+pub mod ttl;
 
+use crate::auth::Operator;
+use crate::crashlogs::CrashLog;
+use crate::idempotency::{Claim, Deployment, IdempotencyKey};
+use crate::podmanager::deletions::BulkDeletionReport;
 use crate::podmanager::garbage_collector::KeepAliveTicket;
-use crate::podmanager::{garbage_collector, PodManager, PodTicket};
+use crate::podmanager::gc_report::GcReport;
+use crate::podmanager::history::EventHistory;
+use crate::podmanager::notifications::{self, DeadLetter};
+use crate::podmanager::refreshes::BatchRefreshReport;
+use crate::podmanager::registry::ManagedPods;
+use crate::podmanager::tasks::TaskReport;
+use crate::podmanager::{PodId, PodManager, PodTicket};
+use crate::proxy::{Forward, Proxied};
+use crate::ratelimit::Quota;
+use audit::{Action, Actor, Entry};
+use config::acm::AcmConfig;
+use k8s::deletion::DeletionCause;
+use k8s::display::DisplayName;
+use k8s::selector::Selector;
 use k8s_openapi::api::core::v1::Pod;
 use kube::ResourceExt;
 use response::Response;
 use result::Result;
-use term_colors::*;
+use rocket::data::Data;
+use rocket::serde::json::{self, Json};
+use std::time::Duration;
 
 #[macro_use]
 extern crate rocket;
@@ -32,14 +77,22 @@ extern crate lazy_static;
 /// provided name as a prefix to the new pod (AFTER it has been sanitized via [rfc1123_subdomain](names::rfc1123_subdomain)).
 ///
 /// An optional TTL may be provided which is the number of seconds that the pod is allowed to live
-/// without a call to [refresh](self::refresh()). If no TTL is provided, then the
-/// [default TTL](podmanager::garbage_collector::DEFAULT_TTL) is used.
+/// without a call to [refresh](self::refresh()). If no TTL is provided, then the caller's
+/// [default TTL](config::acm::AcmTunables::default_ttls) is used, or else the
+/// [default TTL](config::acm::AcmTunables::default_ttl) of every caller. A TTL that is outside of
+/// the ACM's [bounds](ttl) is refused with a [TtlOutOfBounds](ttl::TtlOutOfBounds).
 ///
 /// The pod object returned by this endpoint is NOT ready for consumption. It has NOT been
 /// provisioned by Kubernetes. It does NOT have an IP address. The result returned by this
 /// endpoint is merely the PROMISE that the pod will eventually be provisioned. Client MUST
 /// make a call to the [wait](self::wait()) endpoint before attempting any communication with
-/// the request pod.
+/// the request pod. As such, this endpoint responds with a `202 Accepted` whose `Location`
+/// header names the pod's [wait](self::wait()) endpoint.
+///
+/// The response bears the pod's first [keep-alive ticket](podmanager::tickets) within its
+/// `X-OCF-Ticket` header. The ticket is what the pod is then waited on, refreshed, and deleted
+/// with, so that only the client that deployed a pod (or whomever it hands the ticket to) may do
+/// so. A deploy that is replayed under the same idempotency key is answered with the same ticket.
 ///
 /// The garbage collection timeout does NOT begin immediately upon calling this endpoint. However,
 /// it DOES begin immediately upon the pods actual creation in Kubernetes. However, sane clients
@@ -47,8 +100,105 @@ extern crate lazy_static;
 /// collector's timeout for you on your behalf such that you are guaranteed to have full session
 /// available to you once the pod has been confirmed to be fully functional.
 ///
+/// Before the pod is created, the tag is [validated](preflight::validate) against the AIM. Should it
+/// not be installed, then the AIM's `404` TagNotFound is returned immediately rather than a pod
+/// that fails minutes later with an `ErrImagePull`. This may be disabled via
+/// [VALIDATE_TAGS](config::acm::AcmConfig::validate_tags).
+///
+/// Before the pod is created, the image's platforms (as reported by the AIM) are checked against
+/// the platforms of the cluster's schedulable nodes. If the image cannot run on any of them, then
+/// a [NoMatchingNodeArchitecture](platform::NoMatchingNodeArchitecture) error is returned rather
+/// than a pod that would sit in the `Pending` phase forever.
+///
+/// Should the image have been [deprecated](deprecation) via the AIM, then the deprecation is passed
+/// along as a `warnings` entry in the response. Once the deprecation's sunset has passed, an
+/// [ImageSunset](deprecation::ImageSunset) error is returned instead (unless the ACM is configured
+/// via [REJECT_AFTER_SUNSET](config::acm::AcmConfig::reject_after_sunset) to only ever warn).
+///
+/// Should the generated name collide with an existing pod, then the name is regenerated by default.
+/// Clients that would rather be told about the existing pod may pass `resuffix=false`, in which
+/// case a [NameConflict](k8s::errors::NameConflict) describing the existing pod is returned.
+///
+/// The pod is deployed into the `ocf` namespace unless another `namespace` is given, which MUST
+/// be one of the namespaces that tenants are [allowed](config::acm::AcmConfig::tenant_namespaces) to deploy into.
+/// Otherwise, a [NamespaceNotAllowed](tenancy::NamespaceNotAllowed) is returned. Every other endpoint
+/// that refers to the pod MUST then be given the same `namespace`.
+///
+/// Should this ACM already be managing its [maximum](config::acm::AcmConfig::max_pod_managers)
+/// number of pods, then the deploy is refused up front with a `503 Service Unavailable` and a
+/// [TooManyPodManagers](podmanager::admission::TooManyPodManagers) error.
+///
+/// Clients that may retry a deploy (say, after a network error) SHOULD give it an
+/// [Idempotency-Key](idempotency::IDEMPOTENCY_KEY_HEADER) header. A deploy replayed under the same
+/// key within the [idempotency window](config::acm::AcmTunables::idempotency_window) is answered
+/// with the pod of the original deploy (marked by an `Idempotent-Replayed: true` header) rather
+/// than deploying a duplicate pod. Reusing a key for a different deploy (one that differs in any
+/// parameter, its options included) is refused with an
+/// [IdempotencyKeyReused](idempotency::IdempotencyKeyReused).
+///
+/// Deploys are subject to the ACM's [rate limits](ratelimit::RATE_LIMITS), as is every other
+/// endpoint save for [metrics](self::scrape()). A client that exceeds them is turned away with a
+/// `429 Too Many Requests` and a [RateLimited](ratelimit::RateLimited) error.
+///
+/// The body of the deploy MAY be a JSON [PodOptions](k8s::PodOptions) object giving environment
+/// variables, and references to the keys of Kubernetes secrets within the pod's namespace, to be
+/// injected into the connector's container. Names reserved for Kubernetes or the OCF (such as `PORT`)
+/// are [refused](k8s::options::DENIED_ENV). The body may also request size limited scratch space
+/// and the mounting of existing PersistentVolumeClaims, each of which is checked to exist before
+/// the pod is created. Finally, the body may constrain the nodes that the pod is scheduled onto
+/// (via a node selector, tolerations, and anti-affinity), in addition to the ACM's
+/// [configured](k8s::scheduling::Scheduling::configured) defaults.
+///
+/// Every connector runs as a non-root user with a read-only root filesystem, no capabilities, and
+/// the runtime's default seccomp profile. A deploy may only override these (via `security` in the
+/// body) if the operator has [allowed](k8s::security::ALLOW_SECURITY_OVERRIDES) it, otherwise a
+/// [SecurityOverridesNotAllowed](k8s::security::SecurityOverridesNotAllowed) is returned.
+///
+/// The body may also enable any of the operator's [configured](k8s::sidecars::SIDECARS) sidecars
+/// (such as a log forwarder) by name. The connector remains the pod's first container, and it is
+/// still the connector's port that [wait](self::wait()) health checks. A body that cannot be read
+/// as such is rejected with a [MalformedPodOptions](options::MalformedPodOptions) error, while no
+/// body at all deploys the pod exactly as before.
+///
+/// The connector serves gRPC on port `8080` (as told by its `PORT` environment variable) unless
+/// the body asks for another `port`, and the body may list extra `ports` (such as one serving
+/// metrics) that the connector listens on as well. Privileged ports (below `1024`) are refused
+/// with a [PrivilegedPort](k8s::options::PrivilegedPort) error, and ports that clash with one
+/// another (or with those of a requested sidecar) with a [PortConflict](k8s::options::PortConflict).
+///
+/// Rather than a `tag`, a deploy may name its image by `digest` (such as `sha256:cb1f...`), in
+/// which case the pod references the image by that digest rather than by a mutable tag, and
+/// carries it under its [DIGEST_LABEL](k8s::DIGEST_LABEL). The digest is [resolved](pinning)
+/// through the AIM to a tag that is installed under it (failing with a
+/// [DigestNotInstalled](pinning::DigestNotInstalled) should there be none), against which the
+/// checks above are run. Given both, the tag must be installed under the digest, otherwise a
+/// [DigestMismatch](pinning::DigestMismatch) is returned. Deploys by digest are never handed a
+/// pooled pod, as pooled pods reference their image by tag.
+///
+/// Should the tag have a [warm pool](pool), then a deploy into the default namespace without a
+/// body is handed one of the pool's idle pods (whose name is not prefixed by the given name, but
+/// rather by [pooled](pool::POOLED_NAME)) rather than waiting on a cold start. Deploys fall back to
+/// deploying a fresh pod should the pool be empty.
+///
+/// A connector that restarts is deleted as [rebooted](podmanager::history::Lifecycle::Rebooted)
+/// by default. A deploy may instead allow up to `restarts` automatic restarts (at most
+/// [MAX_RESTARTS](restarts::MAX_RESTARTS), otherwise a [TooManyRestarts](restarts::TooManyRestarts)
+/// is returned), after each of which the connector is health checked afresh. Every restart is
+/// recorded within the pod's [events](self::events()).
+///
+/// Should `dry_run=true` be given, then nothing is created. Rather, the tag is looked up within
+/// the AIM (failing with an [ImageNotInstalled](preflight::ImageNotInstalled) should it not be
+/// installed), every check above is made, and the rendered pod is submitted to Kubernetes as a
+/// server-side dry-run. The would-be pod is returned with a `200 OK` (and without a `Location`).
+/// See [dry_run](dry_run) for details.
+///
 /// ```text
 /// curl -X POST http://acm.ocf-system/deploy?tag=abcd1234&SuperCoolConnector&ttl=150
+/// curl -X POST http://acm.ocf-system/deploy?tag=abcd1234&name=SuperCoolConnector \
+///     -d '{"env": {"LOG_LEVEL": "debug"}, "secrets": [{"name": "DB_PASSWORD", "secret": "oracle", "key": "password"}]}'
+/// curl -X POST "http://acm.ocf-system/deploy?tag=abcd1234&name=SuperCoolConnector&dry_run=true"
+/// curl -X POST "http://acm.ocf-system/deploy?tag=abcd1234&name=SuperCoolConnector&restarts=3"
+/// curl -X POST "http://acm.ocf-system/deploy?digest=sha256:cb1ff0854b8864a6a68ee0b5e509d4d94c50a41f96dc2749ea71dc124c89d11f&name=SuperCoolConnector"
 /// ```
 ///
 /// ```text
@@ -57,15 +207,120 @@ extern crate lazy_static;
 /// pod.wait()
 /// print(pod.address())
 /// ```
-#[post("/deploy?<tag>&<name>&<ttl>")]
-pub async fn deploy(tag: String, name: String, ttl: Option<u64>) -> Result<Response<Pod>> {
-    let registry = std::env::var("REGISTRY").unwrap_or_else(|_| "registry.kurl".to_string());
-    let repository = std::env::var("REPOSITORY").unwrap_or_else(|_| "ocf".to_string());
-    let reference = format!("{}/{}:{}", registry, repository, tag);
-    let ttl = ttl.unwrap_or(garbage_collector::DEFAULT_TTL);
-    let pod = k8s::deploy(reference, name, ttl).await?;
-    podmanager::PodManager::new_podmanager(pod.name(), ttl).await;
-    Ok(pod.into())
+#[allow(clippy::too_many_arguments)]
+#[post(
+    "/deploy?<tag>&<digest>&<name>&<ttl>&<resuffix>&<namespace>&<dry_run>&<restarts>",
+    data = "<options>"
+)]
+pub async fn deploy(
+    tag: Option<String>,
+    digest: Option<String>,
+    name: String,
+    ttl: Option<u64>,
+    resuffix: Option<bool>,
+    namespace: Option<String>,
+    dry_run: Option<bool>,
+    restarts: Option<u32>,
+    options: std::result::Result<Json<k8s::PodOptions>, json::Error<'_>>,
+    actor: Actor,
+    idempotency_key: IdempotencyKey,
+    _quota: Quota,
+) -> Result<Response<Pod>> {
+    if dry_run.unwrap_or(false) {
+        let namespace = tenancy::namespace(namespace)?;
+        let options = options::from_body(options)?;
+        let ttl = ttl::resolve(ttl, &actor)?;
+        restarts::resolve(restarts)?;
+        let image = pinning::resolve(tag, digest).await?;
+        return dry_run::deploy(
+            &namespace,
+            &image,
+            &name,
+            ttl,
+            resuffix.unwrap_or(true),
+            &options,
+        )
+        .await;
+    }
+    let target = auditor::target(&namespace, &name);
+    let deployed = async {
+        let namespace = tenancy::namespace(namespace)?;
+        let options = options::from_body(options)?;
+        let ttl = ttl::resolve(ttl, &actor)?;
+        let restarts = restarts::resolve(restarts)?;
+        // Every parameter of the deploy is part of the request, so that a retry which changed any
+        // one of them is refused rather than answered with the pod of another deploy.
+        let request = format!(
+            "{} as {} within {} for {} seconds with {} restarts, resuffix={} and options {}",
+            pinning::describe(tag.as_deref(), digest.as_deref()),
+            name,
+            namespace,
+            ttl,
+            restarts,
+            resuffix.unwrap_or(true),
+            serde_json::to_string(&options).unwrap_or_default()
+        );
+        let slot = match idempotency::claim(idempotency_key, &actor, request).await? {
+            Claim::Replay(deployment) => {
+                return Ok(deployment
+                    .into_response()
+                    .header(idempotency::REPLAYED_HEADER, "true"))
+            }
+            Claim::Fresh(slot) => slot,
+        };
+        let admission = podmanager::admission::admit()?;
+        let image = pinning::resolve(tag, digest).await?;
+        // A pinned image was already found within the AIM while being resolved.
+        if !image.pinned() {
+            preflight::validate(&image.tag).await?;
+        }
+        let warnings = preflight::check(&image.tag, &options).await?;
+        let pods = podmanager::pods().await;
+        let pooled = if image.pinned() {
+            None
+        } else {
+            pool::claim(&*pods, &image.tag, &name, &namespace, &options, ttl).await
+        };
+        let pod = match pooled {
+            Some(pod) => pod,
+            None => {
+                k8s::deploy(
+                    &*pods,
+                    &namespace,
+                    &image.reference,
+                    &name,
+                    ttl,
+                    resuffix.unwrap_or(true),
+                    &options,
+                    false,
+                )
+                .await?
+            }
+        };
+        // The tag of a pinned reference is not recorded upon the pod, but is still known here.
+        let display = DisplayName::new(&name, Some(&image.tag));
+        podmanager::PodManager::new_podmanager(
+            pods,
+            PodId::new(&namespace, pod.name()),
+            ttl,
+            admission,
+            display,
+            restarts,
+        )
+        .await;
+        let ticket = podmanager::tickets::issue_first(&pod.name(), ttl);
+        let deployment = Deployment {
+            pod,
+            warnings,
+            ticket,
+        };
+        if let Some(slot) = slot {
+            slot.remember(&deployment);
+        }
+        Ok(deployment.into_response())
+    }
+    .await;
+    auditor::record(&actor, Action::Deploy, target, deployed).await
 }
 
 /// A GET to the wait endpoint blocks INDEFINITELY until either the pod requested by [deploy](self::deploy())
@@ -84,13 +339,23 @@ pub async fn deploy(tag: String, name: String, ttl: Option<u64>) -> Result<Respo
 ///
 /// Of course, it is possible that sometime AFTER this call completes that the pod
 /// crashes or becomes unresponsive, but at the very least the caller is guaranteed that
-/// the pod has entered a reasonable state of execution.
+/// the pod has entered a reasonable state of execution. Should the ACM be configured to probe
+/// connectors that are online (see `HEALTH_PROBE_INTERVAL`), then a connector that stops answering
+/// is deleted, and any wait thereafter returns its `ConnectorBecameUnresponsive` (ACM-1305).
+///
+/// The result of the wait is retained for as long as the pod is managed, so a client that lost its
+/// connection mid-wait may simply wait again. Any number of clients may wait on the same pod at
+/// once, and they all receive the same result.
+///
+/// The pod is waited on by its ticket, as issued by its [deploy](self::deploy()) (or by any later
+/// wait or refresh), rather than by its name. A ticket is refused exactly as a
+/// [refresh](self::refresh()) would refuse it.
 ///
 /// Upon completion of this request the garbage collector timeout associated with this pod
-/// will be automatically refreshed on the caller's behalf.
+/// will be automatically refreshed on the caller's behalf, and the refreshed ticket is returned.
 ///
 /// ```text
-/// curl -X GET http://acm.ocf-system/wait?id=super-cool-connector-abcd12345
+/// curl -X GET http://acm.ocf-system/wait?ticket=super-cool-connector-abcd12345
 /// ```
 ///
 /// ```text
@@ -99,21 +364,34 @@ pub async fn deploy(tag: String, name: String, ttl: Option<u64>) -> Result<Respo
 /// pod.wait()
 /// print(pod.address())
 /// ```
-#[get("/wait?<id>")]
-pub async fn wait(id: String) -> Result<Response<PodTicket>> {
-    let lock = PodManager::get(&id).await?;
-    let mut manager = lock.lock().await;
-    let pod = manager.wait().await?;
-    let ticket = manager.refresh().await?;
+#[get("/wait?<ticket>&<namespace>")]
+pub async fn wait(
+    ticket: String,
+    namespace: Option<String>,
+    _quota: Quota,
+) -> Result<Response<PodTicket>> {
+    let manager = PodManager::get_by_ticket(tenancy::namespace(namespace)?, &ticket).await?;
+    let wait = manager.lock().await.wait();
+    let pod = wait.await?;
+    let ticket = manager.lock().await.refresh(None).await?;
     Ok(PodTicket { pod, ticket }.into())
 }
 
 /// A POST to refresh resets the countdown timer for the associated ticket in the garbage collector.
 /// The value used for the TTL is the (optional) value that was given to the call to
-/// [deploy](self::deploy()) which created the pod that this ticket is for.
+/// [deploy](self::deploy()) which created the pod that this ticket is for, unless a new `ttl` is
+/// given, which replaces it for this and every later refresh. Such a TTL is held to the same
+/// [bounds](ttl) as that of a deploy.
+///
+/// Should the ACM have any [ticket signing keys](config::acm::AcmConfig::ticket_signing_keys),
+/// then tickets are signed and MUST be given exactly as the most recent [deploy](self::deploy()),
+/// [wait](self::wait()), or refresh returned them. A ticket that the ACM did not sign is refused with a
+/// [ForgedTicket](podmanager::tickets::ForgedTicket), and one that has expired with an
+/// [ExpiredTicket](podmanager::tickets::ExpiredTicket).
 ///
 /// ```text
 /// curl -X POST http://acm.ocf-system/refresh?ticket=super-cool-connector-abcd12345
+/// curl -X POST "http://acm.ocf-system/refresh?ticket=super-cool-connector-abcd12345&ttl=600"
 /// ```
 ///
 /// ```text
@@ -124,22 +402,111 @@ pub async fn wait(id: String) -> Result<Response<PodTicket>> {
 /// pod.refresh()
 /// pod.refresh()
 /// ```
-#[post("/refresh?<ticket>")]
-pub async fn refresh(ticket: String) -> Result<Response<KeepAliveTicket>> {
-    Ok(PodManager::get(&ticket)
-        .await?
-        .lock()
+#[post("/refresh?<ticket>&<namespace>&<ttl>")]
+pub async fn refresh(
+    ticket: String,
+    namespace: Option<String>,
+    ttl: Option<u64>,
+    actor: Actor,
+    _quota: Quota,
+) -> Result<Response<KeepAliveTicket>> {
+    let target = auditor::target(&namespace, podmanager::tickets::describe(&ticket));
+    let refreshed = async {
+        let namespace = tenancy::namespace(namespace)?;
+        let ttl = ttl.map(ttl::check).transpose()?;
+        Ok(PodManager::get_by_ticket(namespace, &ticket)
+            .await?
+            .lock()
+            .await
+            .refresh(ttl)
+            .await?
+            .into())
+    }
+    .await;
+    auditor::record(&actor, Action::Refresh, target, refreshed).await
+}
+
+/// A POST to the batch refresh endpoint [refreshes](self::refresh()) every ticket within its body
+/// (a JSON list of tickets, all within the given namespace) at once, returning each refreshed
+/// ticket, or the error that its refresh was met with, keyed by the ticket. Clients that hold many
+/// pods SHOULD refresh them here rather than one request at a time.
+///
+/// Every ticket is refreshed (and audited) exactly as a single refresh would be, concurrently. The
+/// failure to refresh one ticket does not stop the others from being refreshed. A body that is not
+/// a list of tickets is refused with a [MalformedBatch](podmanager::refreshes::MalformedBatch),
+/// and a batch of more than [MAXIMUM_BATCH_SIZE](podmanager::refreshes::MAXIMUM_BATCH_SIZE)
+/// tickets with a [BatchTooLarge](podmanager::refreshes::BatchTooLarge).
+///
+/// ```text
+/// curl -X POST http://acm.ocf-system/refresh/batch \
+///     -d '["super-cool-connector-abcd12345", "another-connector-efgh67890"]'
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "BatchRefreshReport",
+///     "object": {
+///       "namespace": "ocf",
+///       "refreshed": 1,
+///       "results": {
+///         "another-connector-efgh67890": {
+///           "ticket": null,
+///           "error": {
+///             "kind": "PodWasDeleted",
+///             "error_code": "ACM-1002",
+///             "message": "The pod ocf/another-connector-efgh67890 has been deleted...",
+///             "cause": null
+///           }
+///         },
+///         "super-cool-connector-abcd12345": {
+///           "ticket": {"ticket": "super-cool-connector-abcd12345", "execution_date": 1632266521},
+///           "error": null
+///         }
+///       }
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[post("/refresh/batch?<namespace>", data = "<tickets>")]
+pub async fn refresh_batch(
+    namespace: Option<String>,
+    tickets: std::result::Result<Json<Vec<String>>, json::Error<'_>>,
+    actor: Actor,
+    _quota: Quota,
+) -> Result<Response<BatchRefreshReport>> {
+    let namespace = tenancy::namespace(namespace)?;
+    let tickets = podmanager::refreshes::from_body(tickets)?;
+    Ok(podmanager::refreshes::batch(&namespace, tickets, &actor)
         .await
-        .refresh()
-        .await?
         .into())
 }
 
 /// A DELETE to the delete endpoint destroys the pod in Kubernetes. This endpoint is idempotent,
 /// meaning that clients may make as many calls to this endpoint as they like.
 ///
+/// Deletion goes through the pod's [PodManager](podmanager::PodManager::delete), meaning that any
+/// concurrent call to [wait](self::wait()) resolves immediately with a `PodDeleted` error and that
+/// the garbage collector is stopped before the pod is deleted in Kubernetes.
+///
+/// The pod is annotated with a [DeletionCause::User](k8s::deletion::DeletionCause::User) before
+/// it is deleted, which distinguishes it from pods deleted by the garbage collector. The same
+/// cause is carried by the deletion's audit entry and by the `deleted` notification sent to the
+/// [lifecycle webhooks](podmanager::notifications).
+///
+/// The pod is deleted by its ticket (as returned by its [deploy](self::deploy()) or by its most
+/// recent [wait](self::wait()) or [refresh](self::refresh())) rather than by its name, so that only the owner of a pod may
+/// delete it. A ticket is refused exactly as a refresh would refuse it.
+///
+/// The payload of a deletion is empty. Should `dry_run=true` be given, then nothing is deleted.
+/// Rather, the deletion is submitted to Kubernetes as a server-side dry-run, and the pod that
+/// would have been deleted (if any) is returned as the payload.
+///
 /// ```text
-/// curl -X DELETE http://acm.ocf-system/delete?id=super-cool-connector-abcd12345
+/// curl -X DELETE http://acm.ocf-system/delete?ticket=super-cool-connector-abcd12345
+/// curl -X DELETE "http://acm.ocf-system/delete?ticket=super-cool-connector-abcd12345&dry_run=true"
 /// ```
 ///
 /// ```text
@@ -150,20 +517,834 @@ pub async fn refresh(ticket: String) -> Result<Response<KeepAliveTicket>> {
 /// pod.delete()
 /// pod.delete()
 /// ```
-#[delete("/delete?<id>")]
-pub async fn delete(id: String) -> Result<Response<()>> {
-    match k8s::delete(id.as_str()).await? {
-        either::Left(pod) => info!("Deleting pod {}", cyan(pod.name())),
-        either::Right(_) => info!("Pod {} was already deleted", cyan(id)),
+#[delete("/delete?<ticket>&<namespace>&<dry_run>")]
+pub async fn delete(
+    ticket: String,
+    namespace: Option<String>,
+    dry_run: Option<bool>,
+    actor: Actor,
+    _quota: Quota,
+) -> Result<Response<Option<Pod>>> {
+    if dry_run.unwrap_or(false) {
+        let id = PodId::parse(
+            tenancy::namespace(namespace)?,
+            podmanager::tickets::redeem(&ticket)?,
+        )?;
+        return dry_run::delete(&id).await;
+    }
+    let target = auditor::target(&namespace, podmanager::tickets::describe(&ticket));
+    let deleted = async {
+        let id = PodId::parse(
+            tenancy::namespace(namespace)?,
+            podmanager::tickets::redeem(&ticket)?,
+        )?;
+        let pods = podmanager::pods().await;
+        PodManager::delete(&*pods, &id).await?;
+        Ok(None.into())
+    }
+    .await;
+    auditor::record_deletion(&actor, target, &DeletionCause::User, deleted).await
+}
+
+/// A DELETE to the bulk delete endpoint deletes every connector pod within the given namespace
+/// (`ocf` by default) that the given [selector](k8s::selector::Selector) selects, returning the
+/// outcome of each deletion. This is meant for emergency cleanups (say, of every pod left behind
+/// by an ACM that has since gone away) and so requires the [operator's token](auth::OPERATOR_TOKEN).
+///
+/// The selector is a Kubernetes label selector that may also compare labels as integers via `<`
+/// and `>`, such as `servicer=acm-7d9f8b6c4-x2x9q` or `execution_date<1632264721`. Only connector
+/// pods are ever selected, no matter the selector. A selector that cannot be parsed is refused
+/// with a [MalformedSelector](k8s::selector::MalformedSelector).
+///
+/// Every pod is deleted exactly as [delete](self::delete()) would, which tears down its
+/// [PodManager](podmanager::PodManager) as well. The failure to delete one pod does not stop the
+/// others from being deleted.
+///
+/// ```text
+/// curl -X DELETE -H "Authorization: Bearer $OPERATOR_TOKEN" \
+///     "http://acm.ocf-system/delete/bulk?selector=execution_date%3C1632264721"
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "BulkDeletionReport",
+///     "object": {
+///       "namespace": "ocf",
+///       "selector": "execution_date<1632264721",
+///       "matched": 2,
+///       "deleted": 2,
+///       "results": [
+///         {"pod": "super-cool-connector-abcd12345", "deleted": true, "error": null},
+///         {"pod": "another-connector-efgh67890", "deleted": true, "error": null}
+///       ]
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[delete("/delete/bulk?<selector>&<namespace>")]
+pub async fn delete_bulk(
+    selector: String,
+    namespace: Option<String>,
+    actor: Actor,
+    _operator: Operator,
+    _quota: Quota,
+) -> Result<Response<BulkDeletionReport>> {
+    let target = auditor::target(&namespace, &selector);
+    let deleted = async {
+        let namespace = tenancy::namespace(namespace)?;
+        let selector: Selector = selector.parse()?;
+        let pods = podmanager::pods().await;
+        Ok(podmanager::deletions::bulk(&*pods, &namespace, &selector)
+            .await?
+            .into())
     }
+    .await;
+    auditor::record_deletion(&actor, target, &DeletionCause::User, deleted).await
+}
+
+/// A GET to the wait-delete endpoint blocks until the pod of the given ID is entirely gone from
+/// the Kubernetes API server. A call to [delete](self::delete()) returns as soon as the deletion
+/// has been submitted, however the pod lingers for up to its grace period while the connector
+/// shuts down. Clients that wish to immediately redeploy under the same name SHOULD wait here first.
+///
+/// This endpoint is idempotent, and returns immediately if the pod is already gone. An optional
+/// `timeout` (in seconds) may be given, which otherwise defaults to
+/// [DEFAULT_DELETION_TIMEOUT](podmanager::deletions::DEFAULT_DELETION_TIMEOUT). Should the pod
+/// still be present after the timeout, then a [DeletionTimeout](podmanager::deletions::DeletionTimeout)
+/// is returned.
+///
+/// ```text
+/// curl -X GET http://acm.ocf-system/wait-delete?id=super-cool-connector-abcd12345&timeout=120
+/// ```
+///
+/// ```text
+/// client = Client()
+/// pod = client.deploy(connector)
+/// pod.wait()
+/// pod.delete()
+/// pod.wait_delete()
+/// ```
+#[get("/wait-delete?<id>&<timeout>&<namespace>")]
+pub async fn wait_delete(
+    id: String,
+    timeout: Option<u64>,
+    namespace: Option<String>,
+    _quota: Quota,
+) -> Result<Response<()>> {
+    let id = PodId::parse(tenancy::namespace(namespace)?, id)?;
+    let timeout = timeout.unwrap_or(podmanager::deletions::DEFAULT_DELETION_TIMEOUT);
+    podmanager::deletions::wait(&id, Duration::from_secs(timeout)).await?;
     Ok(().into())
 }
 
+/// A GET to the events endpoint returns the timestamped lifecycle transitions (`added`, `running`,
+/// `health_check_passed`, `rebooted`, `deleted`, etc.) of the pod of the given ID, oldest first,
+/// as observed by its [event watcher](podmanager::event_watcher). Each pod's history is bounded to
+/// its most recent [MAXIMUM_TRANSITIONS](podmanager::history::MAXIMUM_TRANSITIONS) transitions.
+///
+/// Histories outlive the PodManagers that record them (up to
+/// [MAXIMUM_RETIRED_HISTORIES](podmanager::history::MAXIMUM_RETIRED_HISTORIES) of them) so that a
+/// pod may be troubleshot after it is gone, however they do not outlive this ACM unless they are
+/// also [persisted](config::acm::AcmConfig::persist_event_history) within the pod's annotations. A
+/// pod with no remembered history is met with a [HistoryNotFound](podmanager::history::HistoryNotFound).
+///
+/// ```text
+/// curl -X GET http://acm.ocf-system/events?id=super-cool-connector-abcd12345
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "EventHistory",
+///     "object": {
+///       "pod": "ocf/super-cool-connector-abcd12345",
+///       "retired": true,
+///       "restarts": 0,
+///       "transitions": [
+///         {"at": 1632264721, "event": "added", "detail": null},
+///         {"at": 1632264725, "event": "running", "detail": null},
+///         {"at": 1632264726, "event": "health_check_passed", "detail": null},
+///         {"at": 1632266521, "event": "deleted", "detail": "ttl_expired"}
+///       ]
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/events?<id>&<namespace>")]
+pub async fn events(
+    id: String,
+    namespace: Option<String>,
+    _quota: Quota,
+) -> Result<Response<EventHistory>> {
+    let id = PodId::parse(tenancy::namespace(namespace)?, id)?;
+    Ok(podmanager::history::of(&id).await?.into())
+}
+
+/// A GET to the pods endpoint lists the name of every pod within the given namespace that this
+/// ACM is managing (that is, for which it holds a PodManager), sorted by name. Pods managed by
+/// other ACMs are not listed. Alongside, every pod's [DisplayName](DisplayName) gives the name and
+/// tag that it was deployed as.
+///
+/// ```text
+/// curl -X GET http://acm.ocf-system/pods
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "ManagedPods",
+///     "object": {
+///       "namespace": "ocf",
+///       "pods": ["super-cool-connector-abcd12345", "super-cool-connector-efgh67890"],
+///       "display_names": {
+///         "super-cool-connector-abcd12345": {"name": "SuperCoolConnector", "tag": "abcd1234"},
+///         "super-cool-connector-efgh67890": {"name": "Super Cool Connector", "tag": "efgh5678"}
+///       }
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/pods?<namespace>")]
+pub async fn pods(namespace: Option<String>, _quota: Quota) -> Result<Response<ManagedPods>> {
+    let namespace = tenancy::namespace(namespace)?;
+    Ok(podmanager::registry::list(&namespace).await.into())
+}
+
+/// A GET to the crash logs endpoint returns the last
+/// [CRASH_LOG_BYTES](config::acm::AcmConfig::crash_log_bytes) of the logs of the pod of the given
+/// ID, as captured just before the pod was deleted for having crashed (or rebooted). This is the
+/// evidence referred to by the errors that such pods are reported with, and it remains available
+/// long after the pod itself is gone (up to the most recent
+/// [MAXIMUM_CRASH_LOGS](crashlogs::MAXIMUM_CRASH_LOGS) captures). A pod whose logs were never
+/// captured is met with a [CrashLogNotFound](crashlogs::CrashLogNotFound).
+///
+/// ```text
+/// curl -X GET http://acm.ocf-system/crashlogs?id=super-cool-connector-abcd12345
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "CrashLog",
+///     "object": {
+///       "pod": "ocf/super-cool-connector-abcd12345",
+///       "captured_at": 1632264730,
+///       "logs": "...\npanicked at 'called `Option::unwrap()` on a `None` value'\n"
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/crashlogs?<id>&<namespace>")]
+pub async fn crash_logs(
+    id: String,
+    namespace: Option<String>,
+    _quota: Quota,
+) -> Result<Response<CrashLog>> {
+    let id = PodId::parse(tenancy::namespace(namespace)?, id)?;
+    Ok(crashlogs::read(&id).await?.into())
+}
+
+/// A GET to the usage endpoint returns the current CPU (in millicores) and memory (in bytes) usage
+/// of the pod of the given ID, both in total and per container, as reported by the cluster's
+/// `metrics.k8s.io` API. The pod MUST be managed by this ACM.
+///
+/// Should the cluster have no metrics for the pod (the pod has only just started, or the cluster
+/// does not run metrics-server) then a [MetricsUnavailable](k8s::usage::MetricsUnavailable) is
+/// returned.
+///
+/// The usage of every running pod is also [sampled](config::acm::AcmConfig::usage_sample_interval) for
+/// as long as it is managed, and its peak is recorded within the pod's
+/// [annotations](k8s::usage::PEAK_CPU_ANNOTATION) when it is terminated.
+///
+/// ```text
+/// curl -X GET http://acm.ocf-system/usage?id=super-cool-connector-abcd12345
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "Usage",
+///     "object": {
+///       "cpu_millicores": 13,
+///       "memory_bytes": 67108864,
+///       "containers": [
+///         {"name": "super-cool-connector", "cpu_millicores": 13, "memory_bytes": 67108864}
+///       ],
+///       "timestamp": "2021-09-21T22:52:01Z",
+///       "window": "15s"
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/usage?<id>&<namespace>")]
+pub async fn usage(
+    id: String,
+    namespace: Option<String>,
+    _quota: Quota,
+) -> Result<Response<k8s::usage::Usage>> {
+    let id = PodId::parse(tenancy::namespace(namespace)?, id)?;
+    PodManager::get(&id).await?;
+    Ok(k8s::usage::usage(&id.namespace, &id.name).await?.into())
+}
+
+/// A POST to the exec endpoint runs a short, [allow-listed](config::acm::AcmConfig::exec_commands) command
+/// (such as `env` or `cat /proc/meminfo`) within the connector's container of the pod of the given
+/// ID, returning its captured stdout and stderr (each truncated to
+/// [MAXIMUM_OUTPUT](k8s::exec::MAXIMUM_OUTPUT) bytes) and its exit code. The pod MUST be managed
+/// by this ACM.
+///
+/// This endpoint is for debugging by operators, and so is [guarded](auth::Operator) by the
+/// operator's token.
+///
+/// ```text
+/// curl -X POST -H "Authorization: Bearer $OPERATOR_TOKEN" \
+///     "http://acm.ocf-system/exec?id=super-cool-connector-abcd12345&command=cat%20/proc/meminfo"
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "ExecOutput",
+///     "object": {
+///       "stdout": "MemTotal:       16302084 kB\n...",
+///       "stderr": "",
+///       "exit_code": 0,
+///       "truncated": false
+///     }
+///   }
+/// }
+/// ```
+#[post("/exec?<id>&<command>&<namespace>")]
+pub async fn exec(
+    id: String,
+    command: String,
+    namespace: Option<String>,
+    _operator: Operator,
+    _quota: Quota,
+) -> Result<Response<k8s::exec::ExecOutput>> {
+    let id = PodId::parse(tenancy::namespace(namespace)?, id)?;
+    let command = commands::command(&command)?;
+    PodManager::get(&id).await?;
+    Ok(k8s::exec::exec(&id.namespace, &id.name, command)
+        .await?
+        .into())
+}
+
+/// A GET to the tasks endpoint returns a snapshot of every coroutine currently running on behalf
+/// of a PodManager (event watchers, garbage collectors, server health checks, etc.) alongside when
+/// each started and when each last made progress.
+///
+/// A coroutine is flagged as `orphaned` if the pod that it is working on behalf of no longer has a
+/// PodManager. Once this ACM has gone entirely idle, this endpoint MUST eventually report zero
+/// tasks. Any that remain are leaks. PodManagers that are still held long after their pod was
+/// deleted are counted as `leaked_pod_managers` (and are logged as such).
+///
+/// ```text
+/// curl -X GET http://acm.ocf-system/admin/tasks
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "TaskReport",
+///     "object": {
+///       "pod_managers": 1,
+///       "admitted_pod_managers": 1,
+///       "max_pod_managers": 2000,
+///       "leaked_pod_managers": 0,
+///       "counts": {"event_watcher": 1, "garbage_collector": 1, "reaper": 1},
+///       "tasks": [
+///         {
+///           "name": "event_watcher",
+///           "pod": "ocf/super-cool-connector-abcd12345",
+///           "started_at": 1632264721,
+///           "last_heartbeat": 1632264730,
+///           "orphaned": false
+///         },
+///         ...
+///       ]
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/admin/tasks")]
+pub async fn tasks(_quota: Quota) -> Response<TaskReport> {
+    podmanager::tasks::report().await.into()
+}
+
+/// A GET to the GC report endpoint returns the record of every garbage collector that exited
+/// within the given window (inclusive Unix timestamps, either of which may be omitted) alongside
+/// a tally of how each pod came to an end. That is, whether the pod was lost to its TTL expiring,
+/// was terminated by some other means (most commonly an explicit deletion), or was deleted
+/// because its event watcher shut down.
+///
+/// Records are kept in the ACM's [store](store) and thus outlive both the pods that they
+/// describe and (given a persistent volume) the ACM itself.
+///
+/// ```text
+/// curl -X GET "http://acm.ocf-system/gc/report?from=1632264000&to=1632350400"
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "GcReport",
+///     "object": {
+///       "from": 1632264000,
+///       "to": 1632350400,
+///       "outcomes": {"ttl_expired": 1, "terminated": 12},
+///       "executions": [
+///         {
+///           "namespace": "ocf",
+///           "pod": "super-cool-connector-abcd12345",
+///           "ttl": 1800,
+///           "outcome": "ttl_expired",
+///           "scheduled_at": 1632266521,
+///           "executed_at": 1632266521,
+///           "refreshes": 3,
+///           "deleted": true,
+///           "cause": null
+///         },
+///         ...
+///       ]
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/gc/report?<from>&<to>")]
+pub async fn gc_report(
+    from: Option<i64>,
+    to: Option<i64>,
+    _quota: Quota,
+) -> Result<Response<GcReport>> {
+    Ok(podmanager::gc_report::report(from, to).await?.into())
+}
+
+/// A GET to the metrics endpoint returns this ACM's metrics in the Prometheus text exposition
+/// format. This includes the number of PodManagers (alongside how many are admitted and the
+/// maximum allowed) and the number of running (and orphaned)
+/// PodManager coroutines as reported by the [tasks](self::tasks()) endpoint.
+///
+/// ```text
+/// curl -X GET http://acm.ocf-system/metrics
+/// ```
+#[get("/metrics")]
+pub async fn scrape() -> metrics::Metrics {
+    metrics::render().await
+}
+
+/// A GET to the config endpoint returns the configuration of this ACM, with the operator's
+/// token redacted. As it is meant for the people operating the cluster, it requires the
+/// [operator's token](auth::OPERATOR_TOKEN).
+///
+/// ```text
+/// curl -X GET -H "Authorization: Bearer $OPERATOR_TOKEN" http://acm.ocf-system/config
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "AcmConfig",
+///     "object": {
+///       "registry": "registry.kurl",
+///       "repository": "ocf",
+///       "store_path": "/var/lib/acm",
+///       "operator_token": "<REDACTED>",
+///       "lifecycle_webhooks": ["https://scheduler.alation.example.com/ocf"],
+///       "lifecycle_webhook_secret": "<REDACTED>",
+///       "aim": "http://aim.ocf-system",
+///       "validate_tags": true
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/config")]
+pub async fn configuration(_operator: Operator, _quota: Quota) -> Response<AcmConfig> {
+    env::config().clone().into()
+}
+
+/// A GET to the audit endpoint returns (at most) the `limit` most recent entries of this ACM's
+/// [audit trail](auditor), oldest first. That is, every deploy, delete, and refresh alongside
+/// who made it, when, and how it turned out. The limit defaults to
+/// [DEFAULT_AUDIT_LIMIT](auditor::DEFAULT_AUDIT_LIMIT), and only as many entries
+/// as are configured by [AUDIT_RETAINED](audit::AUDIT_RETAINED) are ever kept in memory. As it is
+/// meant for the people operating the cluster, it requires the
+/// [operator's token](auth::OPERATOR_TOKEN).
+///
+/// ```text
+/// curl -X GET -H "Authorization: Bearer $OPERATOR_TOKEN" "http://acm.ocf-system/audit?limit=10"
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "List[Entry]",
+///     "object": [
+///       {
+///         "at": 1632264721,
+///         "actor": "alation-instance-1",
+///         "action": "deploy",
+///         "target": "ocf/super-cool-connector",
+///         "outcome": "succeeded"
+///       },
+///       ...
+///     ]
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/audit?<limit>")]
+pub async fn audit_trail(
+    limit: Option<usize>,
+    _operator: Operator,
+    _quota: Quota,
+) -> Response<Vec<Entry>> {
+    auditor::recent(limit.unwrap_or(auditor::DEFAULT_AUDIT_LIMIT)).into()
+}
+
+/// A GET to the dead letters endpoint returns every [lifecycle notification](notifications) that
+/// could not be delivered to its webhook, even after being retried, oldest first. As it is meant
+/// for the people operating the cluster, it requires the [operator's token](auth::OPERATOR_TOKEN).
+///
+/// ```text
+/// curl -X GET -H "Authorization: Bearer $OPERATOR_TOKEN" http://acm.ocf-system/webhooks/dead_letters
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "List[DeadLetter]",
+///     "object": [
+///       {
+///         "webhook": "https://scheduler.alation.example.com/ocf",
+///         "failed_at": 1632265321,
+///         "error": "the webhook responded with 503 Service Unavailable",
+///         "notification": {
+///           "id": "9f86d081884c7d65",
+///           "transition": "ready",
+///           "namespace": "ocf",
+///           "pod": "super-cool-connector-abcd12345",
+///           "at": 1632264721,
+///           "cause": null,
+///           "detail": null
+///         }
+///       },
+///       ...
+///     ]
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/webhooks/dead_letters")]
+pub async fn dead_letters(_operator: Operator, _quota: Quota) -> Result<Response<Vec<DeadLetter>>> {
+    Ok(notifications::dead_letters().await?.into())
+}
+
+/// A GET to the status endpoint returns the status of this ACM's configuration. That is, the
+/// generation of its [tunables](reload) (which is incremented every time that they are reloaded),
+/// when they were last reloaded, why the latest reload was rejected (should it have been), and
+/// the tunables themselves.
+///
+/// ```text
+/// curl -X GET http://acm.ocf-system/status
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "Status",
+///     "object": {
+///       "generation": 2,
+///       "reloaded_at": 1632264721,
+///       "rejected": null,
+///       "tunables": {
+///         "default_ttl": 1800,
+///         "default_ttls": {"alation-scheduler": 300},
+///         "min_ttl": 60,
+///         "max_ttl": 86400,
+///         "health_check_timeout": 30,
+///         "health_check_mode": "strict",
+///         "health_check_service": "",
+///         "health_probe_interval": 15,
+///         "health_probe_failures": 3,
+///         "scheduling_timeout": 300,
+///         "rate_limits": "deploy=2000:20",
+///         "log_filter": "info,acm=debug",
+///         "idempotency_window": 600,
+///         "max_lifetime": 86400,
+///         "gc_grace_period": 60
+///       }
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/status")]
+pub async fn status(_quota: Quota) -> Response<reload::Status> {
+    reload::status().into()
+}
+
+/// A GET to the readiness endpoint reviews every permission that RBAC must grant this ACM (see
+/// [permissions](k8s::permissions)), returning them all should every one be granted. Should any be
+/// missing, then this endpoint fails with a `503` naming each of them, which takes this ACM out of
+/// its Service until its RBAC is mended.
+///
+/// ```text
+/// curl -X GET http://acm.ocf-system/ready
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "Permissions",
+///     "object": {
+///       "permissions": [
+///         {
+///           "verb": "create",
+///           "resource": "pods",
+///           "namespace": "ocf",
+///           "allowed": true,
+///           "reason": "RBAC: allowed by RoleBinding \"ocf-system/ocf\" of ClusterRole \"ocf-system\" to ServiceAccount \"ocf-system/ocf-system\""
+///         },
+///         ...
+///       ]
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/ready")]
+pub async fn ready() -> Result<Response<k8s::permissions::Permissions>> {
+    Ok(permissions().await?.into())
+}
+
+/// Reviews every permission required within the tenant namespaces, failing should any be missing.
+async fn permissions() -> Result<k8s::permissions::Permissions> {
+    let permissions = k8s::permissions::review(&tenancy::namespaces()).await?;
+    permissions.check()?;
+    Ok(permissions)
+}
+
+/// A GET to the faults endpoint returns the faults that are currently being injected into this
+/// ACM. This endpoint only exists should the ACM have been built with the `faults` feature (see
+/// [faults](faults)), and requires the [operator's token](auth::OPERATOR_TOKEN).
+///
+/// ```text
+/// curl -X GET -H "Authorization: Bearer $OPERATOR_TOKEN" http://acm.ocf-system/debug/faults
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "Faults",
+///     "object": {
+///       "api_errors": false,
+///       "close_watches": true,
+///       "containerd_errors": false,
+///       "health_check_delay": null
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[cfg(feature = "faults")]
+#[get("/debug/faults")]
+pub async fn get_faults(_operator: Operator, _quota: Quota) -> Response<k8s::faults::Faults> {
+    faults::current().into()
+}
+
+/// A PUT to the faults endpoint replaces the faults that are being injected into this ACM with
+/// those of the body, returning them. Any fault that the body leaves out is switched off, so an
+/// empty object switches off every fault. This endpoint only exists should the ACM have been built
+/// with the `faults` feature (see [faults](faults)), and requires the
+/// [operator's token](auth::OPERATOR_TOKEN).
+///
+/// * `api_errors` fails every request made of the API server on behalf of a pod with a `503`.
+/// * `close_watches` ends every watch of pods.
+/// * `containerd_errors` reports every connector container that has yet to run as having failed
+///     to pull its image.
+/// * `health_check_delay` stalls every health check of a connector by the given number of seconds.
+///
+/// ```text
+/// curl -X PUT -H "Authorization: Bearer $OPERATOR_TOKEN" -d '{"close_watches": true}' \
+///     http://acm.ocf-system/debug/faults
+/// ```
+#[cfg(feature = "faults")]
+#[put("/debug/faults", data = "<faults>")]
+pub async fn put_faults(
+    faults: std::result::Result<Json<k8s::faults::Faults>, json::Error<'_>>,
+    _operator: Operator,
+    _quota: Quota,
+) -> Result<Response<k8s::faults::Faults>> {
+    Ok(faults::set(faults::from_body(faults)?).into())
+}
+
+/// Requests to the proxy endpoints are forwarded on to the connector of the pod of the given ID,
+/// relative to the `/proxy/<id>` prefix, on behalf of the owner of the pod. That is, every request
+/// MUST bear the pod's keep-alive ticket (exactly as the most recent [wait](self::wait()) or
+/// [refresh](self::refresh()) returned it) within its [X-OCF-Ticket](proxy::TICKET_HEADER) header.
+/// The connector's response is returned as it is, rather than within the usual JSON envelope.
+///
+/// The proxy supports plain HTTP as well as gRPC-web, though not native gRPC (see
+/// [proxy](proxy)). It is only mounted should the ACM be configured with `PROXY=true`, in which
+/// case Alation needs only to reach the ACM rather than every connector pod.
+///
+/// ```text
+/// curl -X GET http://acm.ocf-system/proxy/super-cool-connector-abcd12345/metadata?schema=public \
+///     -H "X-OCF-Ticket: super-cool-connector-abcd12345"
+/// curl -X POST http://acm.ocf-system/proxy/super-cool-connector-abcd12345/ocf.Connector/Query \
+///     -H "X-OCF-Ticket: super-cool-connector-abcd12345" \
+///     -H "Content-Type: application/grpc-web+proto" \
+///     --data-binary @request.bin
+/// ```
+#[get("/proxy/<id>/<_..>?<namespace>")]
+pub async fn proxy_get(
+    id: String,
+    namespace: Option<String>,
+    forward: Forward,
+    _quota: Quota,
+) -> Result<Proxied> {
+    let id = PodId::parse(tenancy::namespace(namespace)?, id)?;
+    proxy::forward(id, forward, None).await
+}
+
+/// See [proxy_get](self::proxy_get()).
+#[post("/proxy/<id>/<_..>?<namespace>", data = "<body>")]
+pub async fn proxy_post(
+    id: String,
+    namespace: Option<String>,
+    forward: Forward,
+    body: Data<'_>,
+    _quota: Quota,
+) -> Result<Proxied> {
+    let id = PodId::parse(tenancy::namespace(namespace)?, id)?;
+    proxy::forward(id, forward, Some(body)).await
+}
+
+/// See [proxy_get](self::proxy_get()).
+#[put("/proxy/<id>/<_..>?<namespace>", data = "<body>")]
+pub async fn proxy_put(
+    id: String,
+    namespace: Option<String>,
+    forward: Forward,
+    body: Data<'_>,
+    _quota: Quota,
+) -> Result<Proxied> {
+    let id = PodId::parse(tenancy::namespace(namespace)?, id)?;
+    proxy::forward(id, forward, Some(body)).await
+}
+
+/// See [proxy_get](self::proxy_get()).
+#[patch("/proxy/<id>/<_..>?<namespace>", data = "<body>")]
+pub async fn proxy_patch(
+    id: String,
+    namespace: Option<String>,
+    forward: Forward,
+    body: Data<'_>,
+    _quota: Quota,
+) -> Result<Proxied> {
+    let id = PodId::parse(tenancy::namespace(namespace)?, id)?;
+    proxy::forward(id, forward, Some(body)).await
+}
+
+/// See [proxy_get](self::proxy_get()).
+#[delete("/proxy/<id>/<_..>?<namespace>", data = "<body>")]
+pub async fn proxy_delete(
+    id: String,
+    namespace: Option<String>,
+    forward: Forward,
+    body: Data<'_>,
+    _quota: Quota,
+) -> Result<Proxied> {
+    let id = PodId::parse(tenancy::namespace(namespace)?, id)?;
+    proxy::forward(id, forward, Some(body)).await
+}
+
 #[tokio::main]
 async fn main() {
-    // Sets the logger to use terminal colors.
-    std::env::set_var("RUST_LOG_STYLE", "always");
-    env_logger::init();
+    // Validates every setting up front. Doing so also exports those given within the
+    // configuration file into the environment, where every other module reads them.
+    env::configure();
+    // Colors both our own output and the logger's as configured (or not at all).
+    term_colors::set_color_mode(term_colors::ColorMode::configured());
+    term_colors::set_theme(term_colors::Theme::configured());
+    std::env::set_var(
+        "RUST_LOG_STYLE",
+        if term_colors::enabled() {
+            "always"
+        } else {
+            "never"
+        },
+    );
+    // Logs through the configured log filter, which is reloaded alongside the other tunables.
+    reload::configure();
+    // Fail fast on a misconfigured deletion propagation rather than upon the first deletion.
+    k8s::dependents::Propagation::configured();
+    // Likewise for the retry budget shared by every PodManager, and the retry policies.
+    retry::configure(
+        env::config().retry_budget_capacity,
+        env::config().retry_budget_refill,
+    );
+    // And the rate limits of each endpoint.
+    ratelimit::configure();
+    // And the sweep for PodManagers that outlive their pods.
+    podmanager::registry::configure();
+    // And the sink that the logs of every connector are archived into.
+    logsink::configure();
+    // And the default scheduling constraints of connector pods.
+    k8s::scheduling::Scheduling::configured();
+    // And the security context that connector pods are hardened with.
+    k8s::security::SecurityPolicy::configured();
+    // And the templates of the sidecars that deploys may enable.
+    k8s::sidecars::configured();
+    // And whether connector pods are fronted by headless Services.
+    k8s::headless::enabled();
+    // And whether (and how) connector pods are isolated by NetworkPolicies.
+    k8s::network_policy::enabled();
+    k8s::network_policy::Isolation::configured();
+    // And the sink that the audit trail is written into.
+    auditor::configure();
+    // Having been asked only to check the environment, we check it rather than panic over it.
+    if diagnostics::requested() {
+        std::process::exit(diagnostics::run().await);
+    }
+    // And the image pull secret, which MUST exist within every namespace that we deploy into.
+    for namespace in tenancy::namespaces() {
+        if let Err(err) = k8s::pull_secret::check(&namespace).await {
+            panic!("{}", err);
+        }
+    }
+    // And the permissions that RBAC grants us, lest they surface as opaque 403s mid-deploy.
+    if let Err(err) = permissions().await {
+        panic!("{}", err);
+    }
+    // Reloads the tunables upon a SIGHUP, or whenever the configuration file changes.
+    reload::watch();
+    // Keeps the warm pools (if any) topped up.
+    pool::configure();
     let config = rocket::Config {
         // If you leave it to the default then it will choose
         // 127.0.0.1 which will not be reachable whe running
@@ -171,8 +1352,50 @@ async fn main() {
         address: "0.0.0.0".parse().unwrap(),
         ..Default::default()
     };
+    let mut routes = routes![
+        deploy,
+        wait,
+        delete,
+        delete_bulk,
+        wait_delete,
+        events,
+        pods,
+        crash_logs,
+        usage,
+        exec,
+        refresh,
+        refresh_batch,
+        gc_report,
+        tasks,
+        scrape,
+        configuration,
+        status,
+        ready,
+        audit_trail,
+        dead_letters
+    ];
+    #[cfg(feature = "faults")]
+    routes.extend(routes![get_faults, put_faults]);
+    if env::config().proxy {
+        routes.extend(routes![
+            proxy_get,
+            proxy_post,
+            proxy_put,
+            proxy_patch,
+            proxy_delete
+        ]);
+    }
     rocket::custom(config)
-        .mount("/", routes![deploy, wait, delete, refresh])
+        .mount("/", routes)
+        .register(
+            "/",
+            catchers![
+                ratelimit::too_many_requests,
+                auth::unauthorized,
+                auth::forbidden
+            ],
+        )
+        .attach(response::Compression::default())
         .launch()
         .await
         .unwrap();
@@ -0,0 +1,140 @@
+use crate::podmanager::notifications;
+use crate::podmanager::tasks;
+use crate::ratelimit;
+use crate::reload;
+use rocket::http::ContentType;
+use std::fmt::{Display, Write};
+
+/// The content type of the Prometheus text exposition format.
+const PROMETHEUS_TEXT: &str = "text/plain; version=0.0.4";
+
+/// Metrics is a responder for the [Prometheus text exposition format](https://prometheus.io/docs/instrumenting/exposition_formats/).
+///
+/// Unlike every other endpoint in the ACM, metrics are NOT wrapped within the [response envelope](response::Response)
+/// as scrapers expect the exposition format verbatim.
+#[derive(Responder)]
+pub struct Metrics {
+    body: String,
+    content_type: ContentType,
+}
+
+/// Renders every metric currently tracked by this ACM.
+pub async fn render() -> Metrics {
+    let report = tasks::report().await;
+    let mut body = String::new();
+    gauge(
+        &mut body,
+        "acm_pod_managers",
+        "The number of PodManagers currently held by this ACM.",
+        vec![(String::new(), report.pod_managers)],
+    );
+    gauge(
+        &mut body,
+        "acm_admitted_pod_managers",
+        "The number of PodManagers admitted by this ACM, including those being created or torn down.",
+        vec![(String::new(), report.admitted_pod_managers)],
+    );
+    gauge(
+        &mut body,
+        "acm_max_pod_managers",
+        "The maximum number of PodManagers that this ACM may hold before refusing deploys.",
+        vec![(String::new(), report.max_pod_managers)],
+    );
+    gauge(
+        &mut body,
+        "acm_leaked_pod_managers",
+        "The number of PodManagers still held long after their pod was deleted.",
+        vec![(String::new(), report.leaked_pod_managers)],
+    );
+    gauge(
+        &mut body,
+        "acm_tasks",
+        "The number of running PodManager coroutines, by name.",
+        report
+            .counts
+            .iter()
+            .map(|(name, count)| (format!("name=\"{}\"", name), *count))
+            .collect(),
+    );
+    gauge(
+        &mut body,
+        "acm_orphaned_tasks",
+        "The number of running PodManager coroutines whose pod no longer has a PodManager.",
+        vec![(String::new(), report.orphaned())],
+    );
+    gauge(
+        &mut body,
+        "acm_retry_budget_tokens",
+        "The number of retries left in each budget shared by every PodManager, by pool. Negative \
+        while retries are being delayed until the budget refills.",
+        vec![
+            (
+                "pool=\"shared\"".to_string(),
+                retry::budget().available().floor() as i64,
+            ),
+            (
+                "pool=\"health\"".to_string(),
+                retry::health_budget().available().floor() as i64,
+            ),
+        ],
+    );
+    counter(
+        &mut body,
+        "acm_throttled_requests_total",
+        "The number of requests turned away for exceeding their endpoint's rate limit, by endpoint.",
+        ratelimit::throttled()
+            .into_iter()
+            .map(|(endpoint, count)| (format!("endpoint=\"{}\"", endpoint), count))
+            .collect(),
+    );
+    gauge(
+        &mut body,
+        "acm_config_generation",
+        "The generation of this ACM's tunables, which is incremented every time that they are reloaded.",
+        vec![(String::new(), reload::status().generation)],
+    );
+    let (delivered, dead_lettered) = notifications::deliveries();
+    counter(
+        &mut body,
+        "acm_lifecycle_notifications_total",
+        "The number of lifecycle notifications that were delivered to, or given up on delivering \
+        to, a lifecycle webhook, by outcome.",
+        vec![
+            ("outcome=\"delivered\"".to_string(), delivered),
+            ("outcome=\"dead_lettered\"".to_string(), dead_lettered),
+        ],
+    );
+    Metrics {
+        body,
+        content_type: ContentType::parse_flexible(PROMETHEUS_TEXT).unwrap_or(ContentType::Plain),
+    }
+}
+
+/// Writes a single gauge, along with its HELP and TYPE metadata, to the given buffer.
+/// Each sample is a pairing of its (possibly empty) label set and its value.
+fn gauge<T: Display>(out: &mut String, name: &str, help: &str, samples: Vec<(String, T)>) {
+    metric(out, name, "gauge", help, samples)
+}
+
+/// Writes a single counter in the same manner as a [gauge](gauge).
+fn counter<T: Display>(out: &mut String, name: &str, help: &str, samples: Vec<(String, T)>) {
+    metric(out, name, "counter", help, samples)
+}
+
+fn metric<T: Display>(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    samples: Vec<(String, T)>,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for (labels, value) in samples {
+        if labels.is_empty() {
+            let _ = writeln!(out, "{} {}", name, value);
+        } else {
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+        }
+    }
+}
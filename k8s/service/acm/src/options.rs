@@ -0,0 +1,35 @@
+use error::*;
+use k8s::PodOptions;
+use result::Result;
+use rocket::serde::json::{self, Json};
+
+/// Takes the [PodOptions](k8s::PodOptions) out of the body of a deploy. Deploys without a body
+/// at all are given the default options, which deploys the pod exactly as it always has been.
+/// A body that is not valid PodOptions is a [MalformedPodOptions](MalformedPodOptions).
+pub fn from_body(
+    body: std::result::Result<Json<PodOptions>, json::Error<'_>>,
+) -> Result<PodOptions> {
+    match body {
+        Ok(Json(options)) => Ok(options),
+        Err(json::Error::Parse(body, _)) if body.trim().is_empty() => Ok(PodOptions::default()),
+        Err(err) => Err(MalformedPodOptions {
+            cause: match err {
+                json::Error::Io(err) => err.to_string(),
+                json::Error::Parse(_, err) => err.to_string(),
+            },
+        }
+        .into()),
+    }
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The body of the deploy could not be read as the options of a pod, {cause}. The body, if \
+given, must be a JSON object with any of the 'env', 'secrets', 'scratch', 'claims', 'scheduling', \
+'security', and 'sidecars' fields."
+)]
+#[error_code("ACM-2100")]
+pub struct MalformedPodOptions {
+    cause: String,
+}
@@ -0,0 +1,171 @@
+//! Digest-pinned deploys. A deploy names its image by either a tag, a digest, or both. Tags are
+//! mutable (an alias such as `production` is [promoted](client_sdk::Client::promote) from one
+//! image to the next), whereas a digest always refers to the very same bytes.
+//!
+//! A deploy by digest is resolved through the AIM to a tag that is installed under that digest
+//! (against which the [pre-flight checks](crate::preflight) are run), while the pod itself
+//! references the image by its digest. The pod thereby carries the digest under its
+//! [DIGEST_LABEL](k8s::DIGEST_LABEL), so that what is running is cryptographically identifiable.
+use crate::env;
+use crate::preflight::aim;
+use error::*;
+use names::Rfc1035Label;
+use result::Result;
+
+/// An Image is what a deploy resolved its image to.
+#[derive(Debug, Clone)]
+pub struct Image {
+    /// The tag that the image is installed under.
+    pub tag: String,
+    /// The reference that the pod pulls the image by, which is pinned to the digest should the
+    /// deploy have named one.
+    pub reference: String,
+    /// The digest that the deploy named, if any.
+    pub digest: Option<String>,
+}
+
+impl Image {
+    /// Whether the pod's reference is pinned to a digest.
+    pub fn pinned(&self) -> bool {
+        self.digest.is_some()
+    }
+}
+
+/// Describes the image that a deploy names, such as `abcd1234`, `sha256:cb1f...`, or (given both)
+/// `abcd1234@sha256:cb1f...`.
+pub fn describe(tag: Option<&str>, digest: Option<&str>) -> String {
+    match (tag, digest) {
+        (Some(tag), Some(digest)) => format!("{}@{}", tag, digest),
+        (Some(tag), None) => tag.to_string(),
+        (None, Some(digest)) => digest.to_string(),
+        (None, None) => String::new(),
+    }
+}
+
+/// Resolves the image that a deploy names. A tag must be a valid
+/// [RFC 1035 label](names::is_valid_rfc1035_label) (as every tag that the AIM installs is),
+/// otherwise an [InvalidName](names::InvalidName) is returned.
+///
+/// * A tag alone is referenced as is, without asking the AIM (just as deploys always have).
+/// * A digest alone is resolved through the AIM to a tag that is installed under it, failing with
+///   a [DigestNotInstalled](DigestNotInstalled) should there be none.
+/// * A tag and a digest are checked against each other through the AIM, failing with a
+///   [DigestMismatch](DigestMismatch) should the tag not (or no longer) be of the digest.
+///
+/// Unlike the [pre-flight checks](crate::preflight::check), an AIM that cannot be reached fails a
+/// deploy by digest, as there would be no tag to check the image by.
+pub async fn resolve(tag: Option<String>, digest: Option<String>) -> Result<Image> {
+    let tag = match tag {
+        Some(tag) => Some(Rfc1035Label::new(tag)?.into_inner()),
+        None => None,
+    };
+    let digest = match digest {
+        Some(digest) => Some(validate(digest)?),
+        None => None,
+    };
+    let tag = match (tag, digest.as_ref()) {
+        (Some(tag), None) => tag,
+        (Some(tag), Some(digest)) => {
+            let image = aim().get(&tag).await?;
+            if &image.digest != digest {
+                return Err(DigestMismatch {
+                    tag,
+                    digest: digest.clone(),
+                    installed: image.digest,
+                }
+                .into());
+            }
+            tag
+        }
+        (None, Some(digest)) => installed_under(digest).await?,
+        (None, None) => return Err(NoImageNamed {}.into()),
+    };
+    let (registry, repository) = (&env::config().registry, &env::config().repository);
+    let reference = match digest.as_ref() {
+        Some(digest) => format!("{}/{}@{}", registry, repository, digest),
+        None => format!("{}/{}:{}", registry, repository, tag),
+    };
+    Ok(Image {
+        tag,
+        reference,
+        digest,
+    })
+}
+
+/// Returns the (alphabetically first) tag that is installed under the given digest.
+async fn installed_under(digest: &str) -> Result<String> {
+    let mut tags: Vec<String> = aim()
+        .list()
+        .await?
+        .into_iter()
+        .filter(|image| image.digest == digest)
+        .map(|image| image.tag)
+        .collect();
+    tags.sort();
+    tags.into_iter().next().ok_or_else(|| {
+        DigestNotInstalled {
+            digest: digest.to_string(),
+        }
+        .into()
+    })
+}
+
+/// Returns the given digest should it be a well formed (lowercase) SHA-256 digest, otherwise a
+/// [MalformedDigest](MalformedDigest).
+fn validate(digest: String) -> Result<String> {
+    let well_formed = match digest.strip_prefix("sha256:") {
+        Some(hex) => {
+            hex.len() == 64
+                && hex
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+        }
+        None => false,
+    };
+    if well_formed {
+        Ok(digest)
+    } else {
+        Err(MalformedDigest { digest }.into())
+    }
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The digest '{digest}' is malformed. A digest takes the form of 'sha256:' followed by 64 \
+lowercase hexadecimal characters."
+)]
+#[error_code("ACM-3100")]
+pub struct MalformedDigest {
+    digest: String,
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::NotFound)]
+#[error(
+    "No image is installed under the digest {digest}. Please install the connector via the AIM \
+before deploying it."
+)]
+#[error_code("ACM-3101")]
+pub struct DigestNotInstalled {
+    digest: String,
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::Conflict)]
+#[error(
+    "The tag {tag} is installed under the digest {installed} rather than {digest}. The tag may \
+have been promoted to another image since the digest was resolved."
+)]
+#[error_code("ACM-3102")]
+pub struct DigestMismatch {
+    tag: String,
+    digest: String,
+    installed: String,
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::BadRequest)]
+#[error("A deploy must name its image by either a 'tag', a 'digest', or both.")]
+#[error_code("ACM-3103")]
+pub struct NoImageNamed {}
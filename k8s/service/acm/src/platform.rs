@@ -0,0 +1,90 @@
+use client_sdk::Inspection;
+use error::*;
+use k8s::scheduling::Scheduling;
+use k8s_openapi::api::core::v1::Node;
+use result::Result;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use term_colors::*;
+use tokio::sync::Mutex;
+
+/// How long the cluster's nodes are remembered for. Nodes come and go far less often than
+/// connectors are deployed, so a burst of deploys need not list every node for each of them.
+const NODES_TTL: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    static ref NODES: Mutex<Option<(Instant, Arc<Vec<Node>>)>> = Mutex::new(None);
+}
+
+/// Verifies that the given (inspected) image can actually run on at least one node in the cluster
+/// onto which a pod of the given [scheduling](k8s::scheduling::Scheduling) may be scheduled (that
+/// is, one that matches its node selector and whose taints it tolerates). Without this check, an image built for (say) `linux/arm64` deployed onto
+/// an all `linux/amd64` cluster simply sits in the `Pending` phase forever with a scheduler
+/// message that is inscrutable to anyone that is not a Kubernetes administrator.
+///
+/// This check is best effort. Should either the image's platforms or the nodes' platforms be
+/// unavailable, then a warning is logged and the deploy is allowed to proceed.
+pub async fn check<T: AsRef<str>>(
+    tag: T,
+    inspection: &Inspection,
+    scheduling: &Scheduling,
+) -> Result<()> {
+    let image: BTreeSet<String> = inspection
+        .platforms
+        .iter()
+        .map(|platform| format!("{}/{}", platform.os, platform.architecture))
+        .collect();
+    if image.is_empty() {
+        return Ok(());
+    }
+    let cluster = match nodes().await {
+        Ok(nodes) => k8s::node::schedulable_platforms(&nodes, scheduling),
+        Err(err) => {
+            warn!(
+                "Skipping the platform check for {}, the cluster's nodes could not be listed. {}",
+                highlight(tag.as_ref()),
+                err
+            );
+            return Ok(());
+        }
+    };
+    if cluster.is_empty() || !image.is_disjoint(&cluster) {
+        return Ok(());
+    }
+    Err(NoMatchingNodeArchitecture {
+        tag: tag.as_ref().to_string(),
+        image: image.into_iter().collect::<Vec<_>>().join(", "),
+        cluster: cluster.into_iter().collect::<Vec<_>>().join(", "),
+    }
+    .into())
+}
+
+/// Returns the cluster's nodes, which are listed at most once per [NODES_TTL](NODES_TTL). Deploys
+/// that arrive while the nodes are being listed wait on that listing rather than making their own.
+async fn nodes() -> Result<Arc<Vec<Node>>> {
+    let mut cached = NODES.lock().await;
+    if let Some((listed, nodes)) = cached.as_ref() {
+        if listed.elapsed() < NODES_TTL {
+            return Ok(nodes.clone());
+        }
+    }
+    let nodes = Arc::new(k8s::node::list().await?);
+    *cached = Some((Instant::now(), nodes.clone()));
+    Ok(nodes)
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::UnprocessableEntity)]
+#[error(
+    "The image {tag} was built for the platform(s) [{image}], however this cluster can only \
+schedule connectors onto nodes of the platform(s) [{cluster}]. The connector would never be \
+scheduled, so it has not been deployed. Please rebuild the connector for one of the cluster's \
+platforms (for example, via `docker buildx build --platform {cluster}`)."
+)]
+#[error_code("ACM-1500")]
+pub struct NoMatchingNodeArchitecture {
+    tag: String,
+    image: String,
+    cluster: String,
+}
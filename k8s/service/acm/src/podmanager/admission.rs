@@ -0,0 +1,69 @@
+use error::*;
+use result::Result;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static ADMITTED: AtomicUsize = AtomicUsize::new(0);
+
+/// The maximum number of PodManagers that this ACM may hold at once, as configured by
+/// [MAX_POD_MANAGERS](config::acm::AcmConfig::max_pod_managers).
+pub fn maximum() -> usize {
+    crate::env::config().max_pod_managers
+}
+
+/// The number of PodManagers currently admitted. This includes PodManagers that are still being
+/// created (that is, their pod is being deployed) as well as those that are being torn down.
+pub fn admitted() -> usize {
+    ADMITTED.load(Ordering::SeqCst)
+}
+
+/// Admits a single new PodManager, or fails with a [TooManyPodManagers](TooManyPodManagers)
+/// should this ACM already hold its [maximum](maximum).
+///
+/// Every PodManager holds an event watcher (and its connection to the Kubernetes API server),
+/// a garbage collector, and a handful of other coroutines, so an unbounded number of them can
+/// exhaust both this ACM's memory and the API server. Admission SHOULD therefore be sought
+/// before the pod is deployed so that a refused deploy never leaves a pod behind.
+pub fn admit() -> Result<Admission> {
+    let maximum = maximum();
+    match ADMITTED.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |admitted| {
+        if admitted < maximum {
+            Some(admitted + 1)
+        } else {
+            None
+        }
+    }) {
+        Ok(_) => Ok(Admission { _private: () }),
+        Err(admitted) => {
+            warn!(
+                "Refused a new PodManager as this ACM already holds {} of its maximum of {}",
+                admitted, maximum
+            );
+            Err(TooManyPodManagers { maximum }.into())
+        }
+    }
+}
+
+/// An Admission is a reserved slot for a single PodManager. The slot is released when the
+/// Admission is dropped, which (once it has been handed to
+/// [new_podmanager](super::PodManager::new_podmanager)) is when the PodManager is torn down.
+pub struct Admission {
+    _private: (),
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        ADMITTED.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::ServiceUnavailable)]
+#[error(
+    "This ACM (Alation Connector Manager) is already managing its maximum of {maximum} pods and \
+cannot deploy another until some are deleted or expire. Please try again later. If this limit \
+is routinely reached, then it may be raised via the ACM's MAX_POD_MANAGERS configuration."
+)]
+#[error_code("ACM-1003")]
+pub struct TooManyPodManagers {
+    maximum: usize,
+}
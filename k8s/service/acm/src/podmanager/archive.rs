@@ -0,0 +1,37 @@
+use super::tasks::Task;
+use super::PodId;
+use crate::logsink;
+use k8s::client::Logs;
+use k8s::PodApi;
+use k8s_openapi::api::core::v1::Pod;
+use std::sync::Arc;
+use term_colors::*;
+
+/// Starts archiving the logs of the given running pod into the configured
+/// [log sink](crate::logsink), unless archiving has been disabled. The logs are followed until
+/// the connector exits (or its pod is deleted), at which point they are flushed into the sink
+/// under the pod's ID.
+pub fn start(pods: Arc<dyn PodApi>, pod: &PodId, resource: &Pod) {
+    let sink = match logsink::sink() {
+        Some(sink) => sink,
+        None => return,
+    };
+    let task = Task::register("log_archiver", pod);
+    let pod = pod.clone();
+    let resource = resource.clone();
+    tokio::spawn(async move {
+        let _task = task;
+        match pods.stream_into(&resource, sink, &pod.to_string()).await {
+            Ok(bytes) => debug!(
+                "Archived {} bytes of the logs of pod {}",
+                bytes,
+                highlight(pod.to_string())
+            ),
+            Err(err) => warn!(
+                "Failed to archive the logs of pod {}, {}",
+                highlight(pod.to_string()),
+                err
+            ),
+        }
+    });
+}
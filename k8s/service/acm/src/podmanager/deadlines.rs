@@ -0,0 +1,138 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+use tokio::time::Instant;
+
+/// Deadlines is the schedule by which the single [garbage collection](super::garbage_collector)
+/// daemon knows which of its pods is due next. It is a min-heap of deadlines in which at most one
+/// deadline per key is live at any given time.
+///
+/// Rescheduling or cancelling a key does not search the heap. Rather, the key's live deadline is
+/// kept alongside the heap, and any entry that no longer matches it is discarded once it reaches
+/// the top. The heap is rebuilt should such stale entries come to outnumber the live ones.
+pub struct Deadlines<K> {
+    heap: BinaryHeap<Reverse<(Instant, K)>>,
+    live: HashMap<K, Instant>,
+}
+
+impl<K: Clone + Eq + Hash + Ord> Default for Deadlines<K> {
+    fn default() -> Self {
+        Deadlines {
+            heap: BinaryHeap::new(),
+            live: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash + Ord> Deadlines<K> {
+    /// Schedules the given key for the given instant, replacing any deadline that it already had.
+    pub fn schedule(&mut self, key: K, at: Instant) {
+        self.live.insert(key.clone(), at);
+        self.heap.push(Reverse((at, key)));
+        if self.heap.len() > 2 * self.live.len() + 64 {
+            self.heap = self
+                .live
+                .iter()
+                .map(|(key, at)| Reverse((*at, key.clone())))
+                .collect();
+        }
+    }
+
+    /// Cancels the deadline of the given key, if it has one.
+    pub fn cancel(&mut self, key: &K) {
+        self.live.remove(key);
+    }
+
+    /// Returns the earliest live deadline, if there is one.
+    pub fn earliest(&mut self) -> Option<Instant> {
+        self.discard_stale();
+        self.heap.peek().map(|Reverse((at, _))| *at)
+    }
+
+    /// Removes and returns a key whose deadline is at or before the given instant, if there is one.
+    /// A key that is returned no longer has a deadline.
+    pub fn pop_expired(&mut self, now: Instant) -> Option<K> {
+        self.discard_stale();
+        match self.heap.peek() {
+            Some(Reverse((at, _))) if *at <= now => (),
+            _ => return None,
+        };
+        let Reverse((_, key)) = self.heap.pop()?;
+        self.live.remove(&key);
+        Some(key)
+    }
+
+    /// Pops every entry off of the top of the heap that is no longer the live deadline of its key.
+    fn discard_stale(&mut self) {
+        while let Some(Reverse((at, key))) = self.heap.peek() {
+            if self.live.get(key) == Some(at) {
+                return;
+            }
+            self.heap.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn pops_in_order() {
+        let now = Instant::now();
+        let mut deadlines = Deadlines::default();
+        deadlines.schedule("b", now + Duration::from_secs(2));
+        deadlines.schedule("a", now + Duration::from_secs(1));
+        deadlines.schedule("c", now + Duration::from_secs(3));
+        assert_eq!(deadlines.earliest(), Some(now + Duration::from_secs(1)));
+        assert_eq!(deadlines.pop_expired(now), None);
+        let later = now + Duration::from_secs(2);
+        assert_eq!(deadlines.pop_expired(later), Some("a"));
+        assert_eq!(deadlines.pop_expired(later), Some("b"));
+        assert_eq!(deadlines.pop_expired(later), None);
+        assert_eq!(deadlines.earliest(), Some(now + Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn reschedules() {
+        let now = Instant::now();
+        let mut deadlines = Deadlines::default();
+        deadlines.schedule("a", now + Duration::from_secs(1));
+        deadlines.schedule("a", now + Duration::from_secs(5));
+        assert_eq!(deadlines.earliest(), Some(now + Duration::from_secs(5)));
+        assert_eq!(deadlines.pop_expired(now + Duration::from_secs(1)), None);
+        assert_eq!(
+            deadlines.pop_expired(now + Duration::from_secs(5)),
+            Some("a")
+        );
+        assert_eq!(deadlines.earliest(), None);
+    }
+
+    #[test]
+    fn cancels() {
+        let now = Instant::now();
+        let mut deadlines = Deadlines::default();
+        deadlines.schedule("a", now);
+        deadlines.schedule("b", now + Duration::from_secs(1));
+        deadlines.cancel(&"a");
+        deadlines.cancel(&"absent");
+        assert_eq!(deadlines.earliest(), Some(now + Duration::from_secs(1)));
+        assert_eq!(
+            deadlines.pop_expired(now + Duration::from_secs(1)),
+            Some("b")
+        );
+        assert_eq!(deadlines.pop_expired(now + Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn compacts_stale_entries() {
+        let now = Instant::now();
+        let mut deadlines = Deadlines::default();
+        for seconds in 0..1000 {
+            deadlines.schedule("a", now + Duration::from_secs(seconds));
+        }
+        assert!(deadlines.heap.len() <= 2 * deadlines.live.len() + 64);
+        assert_eq!(deadlines.earliest(), Some(now + Duration::from_secs(999)));
+    }
+}
@@ -0,0 +1,211 @@
+use super::{PodId, PodManager};
+use audit::Actor;
+use error::*;
+use futures::StreamExt;
+use k8s::deletion::DeletionCause;
+use k8s::selector::Selector;
+use k8s::PodApi;
+use k8s_openapi::api::core::v1::Pod;
+use kube::ResourceExt;
+use result::Result;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use term_colors::*;
+use tokio::sync::RwLock;
+
+/// The default number of seconds that [wait](wait) blocks for. This is the deletion grace period
+/// given to every connector (60 seconds) with some headroom for the API server to catch up.
+pub const DEFAULT_DELETION_TIMEOUT: u64 = 90;
+
+/// The maximum number of deletion causes that are remembered. Once exceeded, the oldest
+/// record is forgotten first.
+pub const MAXIMUM_REMEMBERED_DELETIONS: usize = 4096;
+
+/// The maximum number of pods that a [bulk](bulk) deletion deletes at once, which spares the
+/// Kubernetes API server a burst of thousands of deletions.
+pub const BULK_DELETION_CONCURRENCY: usize = 16;
+
+lazy_static! {
+    static ref DELETIONS: RwLock<Deletions> = RwLock::new(Deletions::default());
+}
+
+/// `Deletions` remembers why recently deleted pods went away so that clients that show up
+/// AFTER their PodManager has already been torn down (say, a late call to `/wait`) are told
+/// why their pod is gone rather than simply being told that it could not be found.
+#[derive(Default)]
+struct Deletions {
+    causes: HashMap<PodId, DeletionCause>,
+    /// The moment that each cause was recorded.
+    recorded_at: HashMap<PodId, Instant>,
+    order: VecDeque<PodId>,
+}
+
+/// Records the cause of the given pod's deletion. Recording a second cause for the
+/// same pod is a no-op as the first observed cause is the authoritative one.
+pub async fn record(pod: &PodId, cause: DeletionCause) {
+    let mut deletions = DELETIONS.write().await;
+    if deletions.causes.contains_key(pod) {
+        return;
+    }
+    if deletions.order.len() >= MAXIMUM_REMEMBERED_DELETIONS {
+        if let Some(oldest) = deletions.order.pop_front() {
+            deletions.causes.remove(&oldest);
+            deletions.recorded_at.remove(&oldest);
+        }
+    }
+    deletions.order.push_back(pod.clone());
+    deletions.causes.insert(pod.clone(), cause);
+    deletions.recorded_at.insert(pod.clone(), Instant::now());
+}
+
+/// Returns the recorded cause of the given pod's deletion, if one is remembered.
+pub async fn cause_of(pod: &PodId) -> Option<DeletionCause> {
+    DELETIONS.read().await.causes.get(pod).cloned()
+}
+
+/// Returns the moment at which the given pod's deletion was recorded, if it is remembered.
+pub async fn deleted_at(pod: &PodId) -> Option<Instant> {
+    DELETIONS.read().await.recorded_at.get(pod).cloned()
+}
+
+/// Records the given cause and then submits a request to Kubernetes to delete the pod
+/// (annotated with that same cause).
+///
+/// Every deletion that the ACM makes of its own accord (that is, for any cause other than
+/// [User](DeletionCause::User), whose deletions are audited by the delete endpoint) is
+/// [audited](crate::auditor) as having been made by the [ACM](crate::auditor::ACM_ACTOR).
+///
+/// Failures are logged rather than returned as the callers of this procedure (the
+/// event watcher and the garbage collector) have nobody left to report them to. The
+/// returned flag merely says whether the deletion was successfully submitted.
+pub async fn delete(pods: &dyn PodApi, pod: &PodId, cause: DeletionCause) -> bool {
+    record(pod, cause.clone()).await;
+    let mut deleted = k8s::delete(pods, &pod.namespace, &pod.name, cause.clone()).await;
+    if cause != DeletionCause::User {
+        let actor = Actor::from(crate::auditor::ACM_ACTOR);
+        deleted = crate::auditor::record_deletion(&actor, pod.to_string(), &cause, deleted).await;
+    }
+    match deleted {
+        Ok(_) => {
+            debug!(
+                "Deletion of pod {} submitted with cause {}",
+                highlight(pod.to_string()),
+                cause
+            );
+            true
+        }
+        Err(err) => {
+            error!(
+                "Failed to delete pod {} (cause {}), {}",
+                highlight(pod.to_string()),
+                cause,
+                err
+            );
+            false
+        }
+    }
+}
+
+/// Blocks until the given pod is entirely gone from Kubernetes, or until the given timeout
+/// elapses, in which case a [DeletionTimeout](DeletionTimeout) is returned.
+pub async fn wait(pod: &PodId, timeout: Duration) -> Result<()> {
+    match tokio::time::timeout(timeout, k8s::wait_for_deletion(&pod.namespace, &pod.name)).await {
+        Ok(result) => result,
+        Err(_) => Err(DeletionTimeout {
+            id: pod.to_string(),
+            timeout: format!("{:?}", timeout),
+        }
+        .into()),
+    }
+}
+
+/// Deletes every dependent resource that the given (already deleted) pod could not own.
+///
+/// Pods deleted via [delete](delete) have already had this done on their behalf, however pods
+/// may also be deleted out from underneath us (by an operator or by Kubernetes itself), in which
+/// case the event watcher is the only one left to clean up after them.
+pub async fn cleanup(deleted: &Pod) {
+    if let Err(err) = k8s::dependents::cleanup(deleted).await {
+        error!(
+            "Failed to clean up the dependents of pod {}, {}",
+            highlight(deleted.name()),
+            err
+        )
+    }
+}
+
+/// A BulkDeletion is the outcome of the deletion of a single pod within a [bulk](bulk) deletion.
+#[derive(Serialize, Kind, Debug)]
+pub struct BulkDeletion {
+    pub pod: String,
+    pub deleted: bool,
+    /// Why the pod could not be deleted, should it not have been.
+    pub error: Option<String>,
+}
+
+/// A BulkDeletionReport is the outcome of a [bulk](bulk) deletion.
+#[derive(Serialize, Kind, Debug)]
+pub struct BulkDeletionReport {
+    pub namespace: String,
+    pub selector: String,
+    /// The number of pods that the selector selected.
+    pub matched: usize,
+    /// The number of pods whose deletion was submitted.
+    pub deleted: usize,
+    pub results: Vec<BulkDeletion>,
+}
+
+/// Deletes every connector pod within the given namespace that the given selector selects.
+///
+/// Each deletion goes through the pod's [PodManager](PodManager::delete) (should this ACM hold
+/// one), so the PodManager is torn down along with its pod, exactly as if each pod had been
+/// deleted one at a time. The failure to delete one pod does not stop the others from being
+/// deleted, and is instead reported among the results.
+pub async fn bulk(
+    pods: &dyn PodApi,
+    namespace: &str,
+    selector: &Selector,
+) -> Result<BulkDeletionReport> {
+    let selected = selector.list(namespace).await?;
+    let matched = selected.len();
+    warn!(
+        "Deleting the {} pods within {} selected by {}",
+        matched,
+        highlight(namespace),
+        highlight(selector.to_string())
+    );
+    let results: Vec<BulkDeletion> = futures::stream::iter(selected)
+        .map(|pod| async move {
+            let id = PodId::new(namespace, pod.name());
+            let deleted = PodManager::delete(pods, &id).await;
+            BulkDeletion {
+                pod: id.name,
+                deleted: deleted.is_ok(),
+                error: deleted.err().map(|err| err.to_string()),
+            }
+        })
+        .buffer_unordered(BULK_DELETION_CONCURRENCY)
+        .collect()
+        .await;
+    Ok(BulkDeletionReport {
+        namespace: namespace.to_string(),
+        selector: selector.to_string(),
+        matched,
+        deleted: results.iter().filter(|result| result.deleted).count(),
+        results,
+    })
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::GatewayTimeout)]
+#[error(
+    "The pod {id} was still present in Kubernetes after waiting {timeout} for it to be deleted. \
+Perhaps the pod was never deleted to begin with, or perhaps the connector is taking its time to \
+shut down? Either way, you may wait again."
+)]
+#[error_code("ACM-1600")]
+pub struct DeletionTimeout {
+    id: String,
+    timeout: String,
+}
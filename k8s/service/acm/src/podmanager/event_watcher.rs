@@ -1,17 +1,60 @@
+use super::archive;
+use super::deletions;
+use super::history::{History, Lifecycle};
+use super::notifications::{self, Transition};
+use super::recorder::{self, Reason};
 use super::server_check;
+use super::tasks::Task;
+use super::usage::UsageSampler;
+use super::PodId;
 
+use crate::crashlogs;
 use crate::podmanager::external_handle::PodManagerLowerHandle;
-use backoff::{backoff::Backoff, ExponentialBackoff};
+use backoff::backoff::Backoff;
 use error::*;
-use futures_util::{pin_mut, select, FutureExt, StreamExt, TryStreamExt};
-use k8s::{client, PodExt};
+use futures_util::{pin_mut, select, FutureExt, TryStream, TryStreamExt};
+use k8s::deletion::DeletionCause;
+use k8s::informer::Informer;
+use k8s::pod::PodPhase;
+use k8s::watcher::{Buffered, PodEvent};
+use k8s::{PodApi, PodExt};
 use k8s_openapi::api::core::v1::Pod;
-use kube::api::{DeleteParams, ListParams};
-use kube::Api;
 use result::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 use term_colors::*;
+use tokio::sync::{mpsc, oneshot};
 use tokio::task::JoinHandle;
 
+lazy_static! {
+    /// The shared [Informer](Informer) of every namespace that pods have been watched within.
+    static ref INFORMERS: std::sync::Mutex<HashMap<String, Informer>> =
+        std::sync::Mutex::new(HashMap::new());
+}
+
+/// Returns the shared [Informer](Informer) of the given namespace, starting it should this be the
+/// first pod to be watched within the namespace.
+///
+/// Every event watcher subscribes to its pod by way of its namespace's Informer, so the ACM holds
+/// a single watch connection to the API server per namespace rather than one per pod.
+fn informer(pods: &Arc<dyn PodApi>, namespace: &str) -> Informer {
+    INFORMERS
+        .lock()
+        .unwrap()
+        .entry(namespace.to_string())
+        .or_insert_with(|| Informer::start(pods.clone(), namespace))
+        .clone()
+}
+
+/// How many events of its pod an event watcher buffers before it coalesces the modifications of
+/// the pod that it has yet to look at, see [Buffered](Buffered).
+const EVENT_BUFFER: usize = 16;
+
+/// The message of the [PodRebooted](Reason::PodRebooted) Event.
+const REBOOTED: &str =
+    "The connector restarted, so the pod was deleted rather than left to crash loop";
+
 /// An EventWatcher is a facade that may be used to communicate into
 /// a running daemon that has registered itself as a listener with
 /// the K8s API server for a given pod and is continually observing
@@ -21,32 +64,114 @@ pub struct EventWatcher {}
 impl EventWatcher {
     /// In order to instantiate an EventWatcher it requires.
     ///
-    ///     1. The ID of the pod. This MUST be the name of the pod in K8s
-    ///         as it is used to retrieve an event stream over that pod.
-    ///     2. The sender end of a channel of [GcStatus](GcStatus). The receiving end
+    ///     1. The [PodApi](k8s::PodApi) through which the pod is watched, logged, and deleted.
+    ///     2. The [PodId](super::PodId) of the pod. This MUST be the namespace and name of the
+    ///         pod in K8s as it is used to retrieve an event stream over that pod.
+    ///     3. The sender end of a channel of [GcStatus](GcStatus). The receiving end
     ///         of this channel MUST be given to garbage collector that pairs with this EventWatcher.
-    ///     3. A PodManagerLowerHandle. This serves as the communication and synchronization
+    ///     4. A PodManagerLowerHandle. This serves as the communication and synchronization
     ///         channel to external clients that may access results via the paired PodManagerUpperHandle.
-    pub fn new_watcher<P: AsRef<str>>(
-        pod_id: P,
+    ///     5. The [History](super::history::History) into which every lifecycle transition of the
+    ///         pod is recorded.
+    ///     6. The number of times that the connector may be [restarted](crate::restarts) before
+    ///         it is declared to have failed.
+    ///
+    /// A [Terminator](Terminator) that may be used to request the deletion of the pod is returned
+    /// alongside the daemon's coroutine.
+    pub fn new_watcher(
+        pods: Arc<dyn PodApi>,
+        pod_id: PodId,
         status: tokio::sync::mpsc::Sender<GcStatus>,
         lower: PodManagerLowerHandle,
-    ) -> JoinHandle<()> {
+        history: History,
+        restarts: u32,
+    ) -> (Terminator, JoinHandle<()>) {
+        let (delete_sender, delete_requests) = mpsc::channel(1);
         let event_watcher_daemon = EventWatcherDaemon {
-            pod_id: pod_id.as_ref().to_string(),
+            pods,
+            pod_id,
             gc_status_signal: status,
             pod_manager_handle: lower,
+            delete_requests,
+            usage: None,
+            health: None,
+            history,
+            restarts: Restarts {
+                allowed: restarts,
+                ..Default::default()
+            },
         };
-        tokio::spawn(event_watcher_daemon.watch())
+        (
+            Terminator {
+                sender: delete_sender,
+            },
+            tokio::spawn(event_watcher_daemon.watch()),
+        )
+    }
+}
+
+/// A Terminator is a facade that may be used to ask a running event watcher to tear down its pod.
+///
+/// Terminators require no lock upon the PodManager, and an in-flight wait is resolved by the
+/// deletion.
+#[derive(Clone)]
+pub struct Terminator {
+    sender: mpsc::Sender<DeleteRequest>,
+}
+
+impl Terminator {
+    /// Requests that the event watcher delete its pod on behalf of a user. This procedure returns
+    /// once the deletion has been submitted to Kubernetes.
+    ///
+    /// `false` is returned if the event watcher has already exited, in which case the pod is either
+    /// already gone or is being torn down for some other reason.
+    pub async fn delete(&self) -> bool {
+        let (tx, rx) = oneshot::channel();
+        match self.sender.send(tx).await {
+            Ok(()) => rx.await.is_ok(),
+            Err(_) => false,
+        }
     }
 }
 
+/// A DeleteRequest is the channel on which an event watcher acknowledges that it has
+/// submitted the deletion requested by a [Terminator](Terminator).
+type DeleteRequest = oneshot::Sender<()>;
+
 /// An EventWatcherDaemon is a simple holder of data for the ongoing coroutine that is the
 /// actual daemon fired up via [watch](EventWatcherDaemon::watch).
 struct EventWatcherDaemon {
-    pod_id: String,
+    pods: Arc<dyn PodApi>,
+    pod_id: PodId,
     gc_status_signal: tokio::sync::mpsc::Sender<GcStatus>,
     pod_manager_handle: PodManagerLowerHandle,
+    delete_requests: mpsc::Receiver<DeleteRequest>,
+    /// Samples the pod's usage from the moment that it is running.
+    usage: Option<UsageSampler>,
+    /// Watches the health of the connector once it is online, should the ACM be configured to.
+    health: Option<server_check::HealthWatch>,
+    history: History,
+    restarts: Restarts,
+}
+
+/// Restarts counts the restarts of the connector against those that its deploy allows.
+#[derive(Default)]
+struct Restarts {
+    allowed: u32,
+    used: u32,
+    /// The restart count of the connector's container as of the latest restart that was counted.
+    seen: i32,
+}
+
+/// A Restart is what an event of the pod says of the restarts of its connector.
+enum Restart {
+    /// Nothing, which is all that is ever said of a pod that allows no restarts.
+    None,
+    /// The connector went down (or came back up) within the restarts that its deploy allows, so it
+    /// is to be waited upon and health checked afresh.
+    Allowed,
+    /// The connector restarted more often than its deploy allows.
+    Exhausted,
 }
 
 impl EventWatcherDaemon {
@@ -55,177 +180,478 @@ impl EventWatcherDaemon {
     /// @TODO do a full writeup of everything we discussed, including swimlanes, and
     /// include and explanation of the comms channels setup between this, the GC, and
     /// the health checker.
-    async fn watch(self) {
-        let mut backoff = ExponentialBackoff::default();
-        let client: Api<Pod> = client::new().await;
-        let mut client = k8s::watcher::watcher(
-            client,
-            ListParams::default().fields(&format!("metadata.name={}", self.pod_id)),
-        )
-        .boxed();
+    async fn watch(mut self) {
+        let task = Task::register("event_watcher", &self.pod_id);
+        let mut backoff = retry::Policy::K8sApi.backoff();
+        let subscription =
+            informer(&self.pods, &self.pod_id.namespace).subscribe(&self.pod_id.name);
+        let mut client = k8s::watcher::pod_events(Buffered::new(subscription, EVENT_BUFFER));
         let mut pod = Pod::default();
+        let mut added = false;
         let start = tokio::time::Instant::now();
-        ////////////////////////////////////////////////////////////////////////////
-        // Phase 1
-        ////////////////////////////////////////////////////////////////////////////
-        loop {
-            let next = client.try_next().await;
-            let event = match next {
-                Err(err) => match backoff.next_backoff() {
-                    Some(duration) => {
-                        warn!("Failure from the K8s API, {:?}", err);
-                        tokio::time::sleep(duration).await;
-                        continue;
+        // A pod that the scheduler cannot place onto any node (for a lack of memory, say) would
+        // otherwise sit in Pending forever, so it is only given so long to be scheduled.
+        let scheduling_timeout = Duration::from_secs(crate::reload::tunables().scheduling_timeout);
+        let mut scheduled = false;
+        // Whether the connector has come online before, should it have since been restarted.
+        let mut online = false;
+        // A connector that its deploy allows to restart goes through phases 1 and 2 afresh upon
+        // every restart.
+        'lifecycle: loop {
+            ////////////////////////////////////////////////////////////////////////
+            // Phase 1
+            ////////////////////////////////////////////////////////////////////////
+            loop {
+                let interrupt = if scheduled {
+                    self.next_event(&mut client).await
+                } else {
+                    match tokio::time::timeout_at(
+                        start + scheduling_timeout,
+                        self.next_event(&mut client),
+                    )
+                    .await
+                    {
+                        Ok(interrupt) => interrupt,
+                        Err(_) => {
+                            self.unschedulable(scheduling_timeout).await;
+                            return;
+                        }
+                    }
+                };
+                let next = match interrupt {
+                    Interrupt::Event(next) => next,
+                    Interrupt::Delete(request) => {
+                        self.delete_on_request(request).await;
+                        return;
                     }
+                    // The health of the connector is only watched in phase 3, and the watch is
+                    // stopped before a restarted connector returns to phase 1.
+                    Interrupt::Unhealthy(_) => continue,
+                };
+                task.heartbeat();
+                let event = match next {
+                    Err(err) => match backoff.next_backoff() {
+                        Some(duration) => {
+                            warn!("Failure from the K8s API, {:?}", err);
+                            tokio::time::sleep(duration).await;
+                            continue;
+                        }
+                        None => {
+                            error!("Too many failures from the K8s API, {:?}", err);
+                            self.terminate(KubernetesUnresponsive {
+                                elapsed: format!("{:?}", backoff.get_elapsed_time()),
+                            })
+                            .await;
+                            return;
+                        }
+                    },
+                    Ok(event) => event,
+                };
+                backoff.reset();
+                let event = match event {
                     None => {
-                        error!("Too many failures from the K8s API, {:?}", err);
-                        self.terminate(KubernetesUnresponsive {
-                            elapsed: format!("{:?}", backoff.get_elapsed_time()),
-                        })
-                        .await;
+                        // The stream is done? Kubernetes will never produce events
+                        // again for this pod. I'm not entirely certain why this would
+                        // happen, but it certainly seems like a terminal condition.
+                        error!(
+                            "Kubernetes has permanently closed the event stream for pod {} while the \
+                        Event Watcher was in phase 1",
+                            highlight(self.pod_id.to_string())
+                        );
+                        self.terminate(UnexpectedCloseOfEventStream {}).await;
                         return;
                     }
-                },
-                Ok(event) => event,
-            };
-            backoff.reset();
-            let event = match event {
-                None => {
-                    // The stream is done? Kubernetes will never produce events
-                    // again for this pod. I'm not entirely certain why this would
-                    // happen, but it certainly seems like a terminal condition.
-                    error!(
-                        "Kubernetes has permanently closed the event stream for pod {} while the \
-                    Event Watcher was in phase 1",
-                        cyan(&self.pod_id)
-                    );
-                    self.terminate(UnexpectedCloseOfEventStream {}).await;
-                    return;
+                    Some(event) => event,
+                };
+                scheduled = scheduled
+                    || match &event {
+                        PodEvent::Pending(_) => false,
+                        PodEvent::Restarted(pods) => pods.iter().any(|pod| pod.scheduled()),
+                        _ => true,
+                    };
+                let p = match event {
+                    PodEvent::Pending(_) | PodEvent::Scheduled(_) => {
+                        // This is pretty much the very first event that
+                        // occurs when you submit the deploy request to K8s.
+                        if !added {
+                            trace!(
+                                "Pod {} was added to the Kubernetes deployment queue",
+                                highlight(self.pod_id.to_string())
+                            );
+                            self.history.record(Lifecycle::Added, None).await;
+                            added = true;
+                        }
+                        continue;
+                    }
+                    PodEvent::Deleted(deleted) => {
+                        // Yeah, this can happen if a client makes a call to
+                        // `delete` before the pod even starts.
+                        debug!(
+                            "Pod {} was deleted from Kubernetes before it was ever deployed",
+                            highlight(self.pod_id.to_string())
+                        );
+                        self.report_deletion(&deleted).await;
+                        return;
+                    }
+                    PodEvent::Restarted(_) => {
+                        // A "started" event gets reported as a "restart" event
+                        // as well. Kind of confusing, yeah, but *shrug*.
+                        //
+                        // Note that "started" is NOT the same as running!
+                        // We need to wait for the pod to be fully running!
+                        trace!(
+                            "Pod {} entered started/restarted state",
+                            highlight(self.pod_id.to_string())
+                        );
+                        continue;
+                    }
+                    PodEvent::Pulling(p) if p.failure().is_ok() => continue,
+                    PodEvent::Pulling(p) | PodEvent::Running(p) | PodEvent::Terminated(p) => p,
+                };
+                match self.judge_restart(&p).await {
+                    Restart::Exhausted => {
+                        self.reboot().await;
+                        return;
+                    }
+                    // Kubernetes is left to restart the connector, as its deploy allows.
+                    Restart::Allowed if p.phase() == PodPhase::Terminated => continue,
+                    _ => (),
                 }
-                Some(event) => event,
-            };
-            let p = match event {
-                k8s::watcher::Event::Added(_) => {
-                    // This is pretty much the very first event that
-                    // occurs when you submit the deploy request to K8s.
+                if p.evicted() {
+                    self.history.record(Lifecycle::Evicted, None).await;
+                    self.terminate_with_cause(PodEvicted::of(&p).into(), DeletionCause::Eviction)
+                        .await;
+                    return;
+                } else if matches!(p.phase(), PodPhase::Running | PodPhase::Ready) {
+                    // Only the connector's container is gated on, as a sidecar may well be running
+                    // long before (or crash long after) the connector.
+                    pod = p;
+                    // A connector that was restarted has already been handed to the garbage
+                    // collector.
+                    if !online {
+                        match self
+                            .gc_status_signal
+                            .send(GcStatus::Running(Box::new(pod.clone())))
+                            .await
+                        {
+                            Ok(_) => trace!(
+                                "Garbage collector received {} signal for {}",
+                                info("Running"),
+                                highlight(self.pod_id.to_string())
+                            ),
+                            Err(err) => {
+                                let result = GarbageCollectorUnresponsive {
+                                    pod: self.pod_id.to_string(),
+                                };
+                                error!("{}, {:?}", result, err);
+                                self.terminate(result).await;
+                                return;
+                            }
+                        };
+                    }
+                    info!(
+                        "Pod {} entered the {} phase in {}",
+                        highlight(self.pod_id.to_string()),
+                        info("Running"),
+                        orange(format!("{:?}", start.elapsed()))
+                    );
                     trace!(
-                        "Pod {} was added to the Kubernetes deployment queue",
-                        cyan(&self.pod_id)
+                        "State of pod {} upon entering running phase was: {:?}",
+                        highlight(self.pod_id.to_string()),
+                        pod
+                    );
+                    self.history.record(Lifecycle::Running, None).await;
+                    if !online {
+                        self.usage = UsageSampler::start(&self.pod_id);
+                        archive::start(self.pods.clone(), &self.pod_id, &pod);
+                        online = true;
+                    }
+                    break;
+                } else if matches!(p.phase(), PodPhase::Terminated | PodPhase::InitFailed) {
+                    let termination = p.termination();
+                    let message = termination
+                        .and_then(|termination| termination.message.clone())
+                        .unwrap_or_else(|| "<None Given>".to_string());
+                    let reason = termination
+                        .and_then(|termination| termination.reason.clone())
+                        .unwrap_or_else(|| "<None Given>".to_string());
+                    info!(
+                        "Pod {} entered the {} phase in {}",
+                        highlight(self.pod_id.to_string()),
+                        error("Terminated"),
+                        orange(format!("{:?}", start.elapsed()))
                     );
-                    continue;
-                }
-                k8s::watcher::Event::Deleted(_) => {
-                    // Yeah, this can happen if a client makes a call to
-                    // `delete` before the pod even starts.
                     debug!(
-                        "Pod {} was deleted from Kubernetes before it was ever deployed",
-                        cyan(&self.pod_id)
+                        "Pod {} termination message: {}, reason: {}",
+                        highlight(self.pod_id.to_string()),
+                        message,
+                        reason
+                    );
+                    trace!(
+                        "The state of pod {} upon termination phase was: {:?}",
+                        highlight(self.pod_id.to_string()),
+                        pod
                     );
-                    self.terminate(PodDeleted {}).await;
+                    let detail = format!("reason: {}, message: {}", reason, message);
+                    self.history
+                        .record(Lifecycle::Crashed, Some(detail.clone()))
+                        .await;
+                    // A connector in CrashLoopBackOff has already been restarted, so it is the
+                    // previous instance whose logs tell of the crash.
+                    crashlogs::capture(&*self.pods, &self.pod_id, p.crashed()).await;
+                    // Kubernetes may know better than a generic crash, such as the connector having
+                    // been OOMKilled.
+                    let err = match p.failure() {
+                        Err(err) => err,
+                        Ok(()) => {
+                            // The last of its logs most likely tell of the panic that it died
+                            // with. Lacking any, Kubernetes' own account of the termination is all
+                            // that there is to go on.
+                            let logs =
+                                crashlogs::tail(&*self.pods, &self.pod_id, p.crashed()).await;
+                            match logs {
+                                Some(logs) => PodCrashed {
+                                    cause: Some(logs.into()),
+                                }
+                                .into(),
+                                None => PodTerminatedBeforeStart { message, reason }.into(),
+                            }
+                        }
+                    };
+                    let cause = DeletionCause::IllBehaved { kind: err.kind() };
+                    notifications::notify(
+                        &self.pod_id,
+                        Transition::Crashed,
+                        Some(&cause),
+                        Some(&detail),
+                    );
+                    self.terminate_with_cause(err, cause).await;
                     return;
-                }
-                k8s::watcher::Event::Restarted(_) => {
-                    // A "started" event gets reported as a "restart" event
-                    // as well. Kind of confusing, yeah, but *shrug*.
-                    //
-                    // Note that "started" is NOT the same as running!
-                    // We need to wait for the pod to be fully running!
-                    trace!("Pod {} entered started/restarted state", cyan(&self.pod_id));
+                } else if let Err(err) = p.failure() {
+                    // The pod is waiting upon something that is never going to happen, such as the
+                    // pull of an image that does not exist or a Secret that is missing.
+                    let lifecycle = match err.kind().as_str() {
+                        "ErrImagePull" | "ImagePullBackOff" => Lifecycle::ImagePullFailed,
+                        _ => Lifecycle::FailedToStart,
+                    };
+                    self.history.record(lifecycle, Some(err.to_string())).await;
+                    self.terminate(err).await;
+                    return;
+                } else {
                     continue;
                 }
-                k8s::watcher::Event::Applied(pod) => pod,
+            }
+            ////////////////////////////////////////////////////////////////////////
+            // Phase 2
+            ////////////////////////////////////////////////////////////////////////
+            let (check, outcome) = match server_check::ServerCheck::new(&pod) {
+                Ok((check, outcome)) => (check, outcome),
+                Err(err) => {
+                    self.terminate(err).await;
+                    return;
+                }
             };
-            if p.running() {
-                pod = p;
-                match self
-                    .gc_status_signal
-                    .send(GcStatus::Running(Box::new(pod.clone())))
-                    .await
-                {
-                    Ok(_) => trace!(
-                        "Garbage collector received {} signal for {}",
-                        green("Running"),
-                        cyan(&self.pod_id)
-                    ),
-                    Err(err) => {
-                        let result = GarbageCollectorUnresponsive {
-                            pod: self.pod_id.clone(),
-                        };
-                        error!("{}, {:?}", result, err);
-                        self.terminate(result).await;
-                        return;
+            let outcome = outcome.fuse();
+            pin_mut!(outcome);
+            loop {
+                let event: Phase2Event = {
+                    let next_event = client.try_next().fuse();
+                    let delete = next_delete_request(&mut self.delete_requests).fuse();
+                    pin_mut!(next_event, delete);
+                    select! {
+                        event = next_event => Phase2Event::K8s(event),
+                        status = outcome => Phase2Event::HealthCheck(status),
+                        request = delete => Phase2Event::Delete(request),
                     }
                 };
-                info!(
-                    "Pod {} entered the {} phase in {}",
-                    cyan(&self.pod_id),
-                    green("Running"),
-                    orange(format!("{:?}", start.elapsed()))
-                );
-                trace!(
-                    "State of pod {} upon entering running phase was: {:?}",
-                    cyan(&self.pod_id),
-                    pod
-                );
-                break;
-            } else if p.terminated() || p.crashed() {
-                let message = pod
-                    .terminated_message()
-                    .unwrap_or_else(|| "<None Given>".to_string());
-                let reason = pod
-                    .terminated_reason()
-                    .unwrap_or_else(|| "<None Given>".to_string());
-                info!(
-                    "Pod {} entered the {} phase in {}",
-                    cyan(&self.pod_id),
-                    red("Terminated"),
-                    orange(format!("{:?}", start.elapsed()))
-                );
-                debug!(
-                    "Pod {} termination message: {}, reason: {}",
-                    cyan(&self.pod_id),
-                    message,
-                    reason
-                );
-                trace!(
-                    "The state of pod {} upon termination phase was: {:?}",
-                    cyan(&self.pod_id),
-                    pod
-                );
-                self.terminate(PodCrashed {}).await;
-                return;
-            } else if p.was_err_image_pull() {
-                self.terminate(
-                    p.err_image_pull()
-                        .expect_err("unsafe call to PodExt::err_image_pull"),
-                )
-                .await;
-                return;
-            } else {
-                continue;
-            }
-        }
-        ////////////////////////////////////////////////////////////////////////////
-        // Phase 2
-        ////////////////////////////////////////////////////////////////////////////
-        let (check, outcome) = match server_check::ServerCheck::new(&pod) {
-            Ok((check, outcome)) => (check, outcome),
-            Err(err) => {
-                self.terminate(err).await;
-                return;
+                task.heartbeat();
+                match event {
+                    Phase2Event::Delete(request) => {
+                        check.kill().await;
+                        self.delete_on_request(request).await;
+                        return;
+                    }
+                    Phase2Event::K8s(event) => match event {
+                        Err(err) => match backoff.next_backoff() {
+                            Some(duration) => {
+                                warn!("Failure from the K8s API, {:?}", err);
+                                tokio::time::sleep(duration).await;
+                                continue;
+                            }
+                            None => {
+                                // The API server has been busted for 15 minutes straight.
+                                error!("Too many failures from the K8s API, {:?}", err);
+                                check.kill().await;
+                                self.terminate(KubernetesUnresponsive {
+                                    elapsed: format!("{:?}", backoff.get_elapsed_time()),
+                                })
+                                .await;
+                                return;
+                            }
+                        },
+                        Ok(Some(PodEvent::Deleted(deleted))) => {
+                            // This can easily happen if a client calls the delete
+                            // endpoint before calling on the wait endpoint.
+                            check.kill().await;
+                            self.report_deletion(&deleted).await;
+                            return;
+                        }
+                        Ok(Some(PodEvent::Terminated(p))) if p.evicted() => {
+                            check.kill().await;
+                            self.history.record(Lifecycle::Evicted, None).await;
+                            self.terminate_with_cause(
+                                PodEvicted::of(&p).into(),
+                                DeletionCause::Eviction,
+                            )
+                            .await;
+                            return;
+                        }
+                        Ok(Some(PodEvent::Restarted(_))) => {
+                            // It got restarted? We're not going to tolerate a boot cycle here.
+                            check.kill().await;
+                            self.reboot().await;
+                            return;
+                        }
+                        Ok(None) => {
+                            // The stream is done? Kubernetes will never produce events
+                            // again for this pod. I'm not entirely certain why this would
+                            // happen, but it certainly seems like a terminal condition.
+                            check.kill().await;
+                            error!(
+                                "Kubernetes has permanent closed the event stream for pod {} \
+                            while the Event Watcher was in phase 1",
+                                highlight(self.pod_id.to_string())
+                            );
+                            self.terminate(UnexpectedCloseOfEventStream {}).await;
+                            return;
+                        }
+                        // Reset the backoff in-case we had some failures because obviously
+                        // now we're back online with the API server.
+                        Ok(Some(event)) => {
+                            backoff.reset();
+                            if let Some(p) = event.pod() {
+                                match self.judge_restart(p).await {
+                                    Restart::None => (),
+                                    Restart::Allowed => {
+                                        check.kill().await;
+                                        continue 'lifecycle;
+                                    }
+                                    Restart::Exhausted => {
+                                        check.kill().await;
+                                        self.reboot().await;
+                                        return;
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    Phase2Event::HealthCheck(server_status) => match server_status {
+                        Err(recv_error) => {
+                            // This means that the server status coroutine dropped its sender.
+                            // The connector may-or-may not be running, but our current state
+                            // cannot be trusted as this is a severe violation of the state
+                            // machine.
+                            error!(
+                                "Server status coroutine dropped its sender! {:?}",
+                                recv_error
+                            );
+                            check.join().await;
+                            self.terminate(HealthCheckDroppedItsChannel {}).await;
+                            return;
+                        }
+                        Ok(Err(err)) => {
+                            // The server health check has reported that it considers the
+                            // the pod to be ill-behaved, and as such should be terminated.
+                            check.join().await;
+                            self.history
+                                .record(Lifecycle::HealthCheckFailed, Some(err.to_string()))
+                                .await;
+                            recorder::record(&self.pod_id, Reason::HealthCheckFailed, &err).await;
+                            self.terminate(err).await;
+                            return;
+                        }
+                        Ok(Ok(())) => {
+                            // The server health check has reported that it considers the
+                            // the pod to be alive and responsive.
+                            check.join().await;
+                            self.history
+                                .record(Lifecycle::HealthCheckPassed, None)
+                                .await;
+                            notifications::notify(
+                                &self.pod_id,
+                                Transition::Ready,
+                                None,
+                                None::<String>,
+                            );
+                            // Inform the upstream waiting client that their pod is ready.
+                            match self.send_result(Ok(pod.clone())).await {
+                                Ok(()) => (),
+                                Err(err) => {
+                                    error!(
+                                        "The server health check returned a response of a \
+                                    successful start. However, the upstream channel that communicates \
+                                    those results back to clients appears to have been closed early. \
+                                    Since we cannot communicate back to the client, there is nothing \
+                                    for us to do but show down the pod. {:?}",
+                                        err
+                                    );
+                                    self.kill_gc().await;
+                                    self.kill_pod(DeletionCause::IllBehaved {
+                                        kind: SendChannelClose {}.kind(),
+                                    })
+                                    .await;
+                                    return;
+                                }
+                            }
+                            break;
+                        }
+                    },
+                }
             }
-        };
-        let outcome = outcome.fuse();
-        pin_mut!(outcome);
-        loop {
-            let next_event = client.try_next().fuse();
-            pin_mut!(next_event);
-            let event: Phase2Event = select! {
-                event = next_event => Phase2Event::K8s(event),
-                status = outcome => Phase2Event::HealthCheck(status),
+            ////////////////////////////////////////////////////////////////////////
+            // Phase 3
+            ////////////////////////////////////////////////////////////////////////
+            info!(
+                "Pod {} completed its health check and came fully online in {}",
+                highlight(self.pod_id.to_string()),
+                orange(format!("{:?}", start.elapsed()))
+            );
+            self.health = match server_check::HealthWatch::new(&pod) {
+                Ok(health) => health,
+                Err(err) => {
+                    self.terminate(err).await;
+                    return;
+                }
             };
-            match event {
-                Phase2Event::K8s(event) => match event {
+            loop {
+                let next = match self.next_event(&mut client).await {
+                    Interrupt::Event(next) => next,
+                    Interrupt::Delete(request) => {
+                        self.delete_on_request(request).await;
+                        return;
+                    }
+                    Interrupt::Unhealthy(err) => {
+                        // The connector was online, but has since either told its health watch
+                        // that it is no longer serving or stopped answering its health probes.
+                        let detail = err.to_string();
+                        self.history
+                            .record(Lifecycle::Unresponsive, Some(detail.clone()))
+                            .await;
+                        recorder::record(&self.pod_id, Reason::ConnectorUnresponsive, &detail)
+                            .await;
+                        let cause = DeletionCause::IllBehaved { kind: err.kind() };
+                        notifications::notify(
+                            &self.pod_id,
+                            Transition::Unresponsive,
+                            Some(&cause),
+                            Some(&detail),
+                        );
+                        self.terminate_with_cause(err, cause).await;
+                        return;
+                    }
+                };
+                task.heartbeat();
+                let event = match next {
                     Err(err) => match backoff.next_backoff() {
                         Some(duration) => {
                             warn!("Failure from the K8s API, {:?}", err);
@@ -233,9 +659,7 @@ impl EventWatcherDaemon {
                             continue;
                         }
                         None => {
-                            // The API server has been busted for 15 minutes straight.
                             error!("Too many failures from the K8s API, {:?}", err);
-                            check.kill().await;
                             self.terminate(KubernetesUnresponsive {
                                 elapsed: format!("{:?}", backoff.get_elapsed_time()),
                             })
@@ -243,153 +667,280 @@ impl EventWatcherDaemon {
                             return;
                         }
                     },
-                    Ok(Some(k8s::watcher::Event::Deleted(_))) => {
-                        // This can easily happen if a client calls the delete
-                        // endpoint before calling on the wait endpoint.
-                        check.kill().await;
-                        self.terminate(PodDeleted {}).await;
-                        return;
-                    }
-                    Ok(Some(k8s::watcher::Event::Restarted(_))) => {
-                        // It got restarted? We're not going to tolerate a boot cycle here.
-                        check.kill().await;
-                        self.terminate(PodRebooted {}).await;
-                        return;
-                    }
-                    Ok(None) => {
+                    Ok(event) => event,
+                };
+                backoff.reset();
+                let event = match event {
+                    None => {
                         // The stream is done? Kubernetes will never produce events
                         // again for this pod. I'm not entirely certain why this would
                         // happen, but it certainly seems like a terminal condition.
-                        check.kill().await;
                         error!(
                             "Kubernetes has permanent closed the event stream for pod {} \
-                        while the Event Watcher was in phase 1",
-                            cyan(&self.pod_id)
+                        while the Event Watcher was in phase 3",
+                            highlight(self.pod_id.to_string())
                         );
                         self.terminate(UnexpectedCloseOfEventStream {}).await;
                         return;
                     }
-                    // Reset the backoff in-case we had some failures because obviously
-                    // now we're back online with the API server.
-                    Ok(Some(_)) => backoff.reset(),
-                },
-                Phase2Event::HealthCheck(server_status) => match server_status {
-                    Err(recv_error) => {
-                        // This means that the server status coroutine dropped its sender.
-                        // The connector may-or-may not be running, but our current state
-                        // cannot be trusted as this is a severe violation of the state
-                        // machine.
-                        error!(
-                            "Server status coroutine dropped its sender! {:?}",
-                            recv_error
-                        );
-                        check.join().await;
-                        self.terminate(HealthCheckDroppedItsChannel {}).await;
+                    Some(event) => event,
+                };
+                match event {
+                    PodEvent::Deleted(deleted) => {
+                        // Cool, the client appears to be done with the pod
+                        // and it has been deleted. There is nothing left
+                        // for us to do but record why, clean up after it, and shutdown
+                        // the garbage collector.
+                        let cause = deleted.deletion_cause();
+                        self.history
+                            .record(Lifecycle::Deleted, cause.as_ref().map(|c| c.to_string()))
+                            .await;
+                        if let Some(cause) = cause {
+                            deletions::record(&self.pod_id, cause).await;
+                        }
+                        deletions::cleanup(&deleted).await;
+                        self.kill_gc().await;
                         return;
                     }
-                    Ok(Err(err)) => {
-                        // The server health check has reported that it considers the
-                        // the pod to be ill-behaved, and as such should be terminated.
-                        check.join().await;
-                        self.terminate(err).await;
+                    PodEvent::Terminated(p) if p.evicted() => {
+                        // Kubernetes evicted the pod out from underneath us. The pod object
+                        // lingers in a failed state, so we clean it up ourselves.
+                        self.history.record(Lifecycle::Evicted, None).await;
+                        self.kill_gc().await;
+                        self.kill_pod(DeletionCause::Eviction).await;
                         return;
                     }
-                    Ok(Ok(())) => {
-                        // The server health check has reported that it considers the
-                        // the pod to be alive and responsive.
-                        check.join().await;
-                        // Inform the upstream waiting client that their pod is ready.
-                        match self.send_result(Ok(pod.clone())).await {
-                            Ok(()) => (),
-                            Err(err) => {
-                                error!(
-                                    "The server health check returned a response of a \
-                                successful start. However, the upstream channel that communicates \
-                                those results back to clients appears to have been closed early. \
-                                Since we cannot communicate back to the client, there is nothing \
-                                for us to do but show down the pod. {:?}",
-                                    err
-                                );
-                                self.kill_gc().await;
-                                self.kill_pod().await;
-                                return;
+                    event
+                        if event
+                            .pod()
+                            .is_some_and(|p| p.metadata.deletion_timestamp.is_some()) =>
+                    {
+                        // The pod is being deleted by someone else (most commonly the garbage
+                        // collector), so this is our last chance to record its peak usage.
+                        if self.usage.is_some() {
+                            self.record_peak().await;
+                            self.usage = None;
+                        }
+                    }
+                    PodEvent::Restarted(_) => {
+                        // It got restarted? We're not going to tolerate a boot cycle here.
+                        self.reboot().await;
+                        return;
+                    }
+                    // We are not particularly interested in other events that may
+                    // occur during the rest of its lifecycle, save for a restart of the
+                    // connector that its deploy allows.
+                    event => {
+                        if let Some(p) = event.pod() {
+                            match self.judge_restart(p).await {
+                                Restart::None => (),
+                                Restart::Allowed => {
+                                    self.health = None;
+                                    continue 'lifecycle;
+                                }
+                                Restart::Exhausted => {
+                                    self.reboot().await;
+                                    return;
+                                }
                             }
                         }
-                        break;
                     }
-                },
+                };
             }
         }
-        ////////////////////////////////////////////////////////////////////////////
-        // Phase 3
-        ////////////////////////////////////////////////////////////////////////////
-        info!(
-            "Pod {} completed its health check and came fully online in {}",
-            cyan(&self.pod_id),
-            orange(format!("{:?}", start.elapsed()))
+    }
+
+    /// Awaits the next event from Kubernetes. Should a [Terminator](Terminator) request the
+    /// deletion of the pod (or the [health watch](server_check::HealthWatch) find the connector
+    /// unhealthy) in the meantime, then that is returned instead.
+    async fn next_event<S>(
+        &mut self,
+        stream: &mut S,
+    ) -> Interrupt<std::result::Result<Option<S::Ok>, S::Error>>
+    where
+        S: TryStream + Unpin,
+    {
+        let next = stream.try_next().fuse();
+        let delete = next_delete_request(&mut self.delete_requests).fuse();
+        let unhealthy = next_unhealthy(&mut self.health).fuse();
+        pin_mut!(next, delete, unhealthy);
+        select! {
+            event = next => Interrupt::Event(event),
+            request = delete => Interrupt::Delete(request),
+            err = unhealthy => Interrupt::Unhealthy(err),
+        }
+    }
+
+    /// Tears down the pod on behalf of a [Terminator](Terminator).
+    ///
+    /// Any waiting upstream client is immediately told that the pod was deleted by the user,
+    /// the garbage collector is shut down (so that it cannot race any refresh), and only then is
+    /// the pod deleted in Kubernetes. The requester is notified once the deletion has been submitted.
+    async fn delete_on_request(&self, request: DeleteRequest) {
+        debug!(
+            "Event watcher for pod {} received a deletion request",
+            highlight(self.pod_id.to_string())
         );
-        loop {
-            let next = client.try_next().await;
-            let event = match next {
-                Err(err) => match backoff.next_backoff() {
-                    Some(duration) => {
-                        warn!("Failure from the K8s API, {:?}", err);
-                        tokio::time::sleep(duration).await;
-                        continue;
-                    }
-                    None => {
-                        error!("Too many failures from the K8s API, {:?}", err);
-                        self.terminate(KubernetesUnresponsive {
-                            elapsed: format!("{:?}", backoff.get_elapsed_time()),
-                        })
-                        .await;
-                        return;
-                    }
-                },
-                Ok(event) => event,
-            };
-            backoff.reset();
-            let event = match event {
-                None => {
-                    // The stream is done? Kubernetes will never produce events
-                    // again for this pod. I'm not entirely certain why this would
-                    // happen, but it certainly seems like a terminal condition.
-                    error!(
-                        "Kubernetes has permanent closed the event stream for pod {} \
-                    while the Event Watcher was in phase 3",
-                        cyan(&self.pod_id)
-                    );
-                    self.terminate(UnexpectedCloseOfEventStream {}).await;
-                    return;
-                }
-                Some(event) => event,
-            };
-            match event {
-                k8s::watcher::Event::Deleted(_) => {
-                    // Cool, the client appears to be done with the pod
-                    // and it has been deleted. There is nothing left
-                    // for us to do but shutdown the garbage collector.
-                    self.kill_gc().await;
-                    return;
-                }
-                k8s::watcher::Event::Restarted(_) => {
-                    // It got restarted? We're not going to tolerate a boot cycle here.
-                    self.terminate(PodRebooted {}).await;
-                    return;
-                }
-                // We are not particularly interested in other events that may
-                // occur during the rest of its lifecycle.
-                _ => (),
-            };
+        let cause = DeletionCause::User;
+        deletions::record(&self.pod_id, cause.clone()).await;
+        let _ = self
+            .send_result(Err(PodDeleted {
+                cause: cause.to_string(),
+            }
+            .into()))
+            .await;
+        self.kill_gc().await;
+        self.kill_pod(cause).await;
+        let _ = request.send(());
+    }
+
+    /// Judges the given (current) state of the pod by the restarts that its deploy allows,
+    /// recording every restart of the connector that is allowed. Pods that allow no restarts are
+    /// left to the rest of the event watcher, which tolerates none.
+    async fn judge_restart(&mut self, pod: &Pod) -> Restart {
+        if self.restarts.allowed == 0 {
+            return Restart::None;
         }
+        if pod.restarts() > self.restarts.seen {
+            self.restarts.seen = pod.restarts();
+            self.restarts.used += 1;
+            if self.restarts.used > self.restarts.allowed {
+                return Restart::Exhausted;
+            }
+            let detail = format!(
+                "restart {} of {}",
+                self.restarts.used, self.restarts.allowed
+            );
+            info!(
+                "The connector of pod {} restarted ({})",
+                highlight(self.pod_id.to_string()),
+                detail
+            );
+            self.history
+                .record(Lifecycle::Restarted, Some(detail.clone()))
+                .await;
+            recorder::record(&self.pod_id, Reason::PodRestarted, &detail).await;
+            return Restart::Allowed;
+        }
+        if pod.phase() == PodPhase::Terminated && self.restarts.used < self.restarts.allowed {
+            // Kubernetes is yet to restart the connector.
+            return Restart::Allowed;
+        }
+        Restart::None
+    }
+
+    /// Tears down a pod whose connector restarted more often than its deploy allows.
+    async fn reboot(&self) {
+        self.history.record(Lifecycle::Rebooted, None).await;
+        recorder::record(&self.pod_id, Reason::PodRebooted, REBOOTED).await;
+        let err = PodRebooted {};
+        let cause = DeletionCause::IllBehaved { kind: err.kind() };
+        notifications::notify(
+            &self.pod_id,
+            Transition::Crashed,
+            Some(&cause),
+            Some(REBOOTED),
+        );
+        crashlogs::capture(&*self.pods, &self.pod_id, true).await;
+        self.terminate_with_cause(err.into(), cause).await;
+    }
+
+    /// Tears down a pod that was not scheduled onto any node within the given timeout, telling
+    /// any waiting upstream client why the scheduler could not place it.
+    ///
+    /// The scheduler's reason is taken from its most recent `FailedScheduling` Event of the pod,
+    /// or else from the pod's `PodScheduled` condition.
+    async fn unschedulable(&self, timeout: Duration) {
+        let events = match k8s::events::of(&self.pod_id.namespace, &self.pod_id.name).await {
+            Ok(events) => events,
+            Err(err) => {
+                warn!(
+                    "Failed to list the Events of unschedulable pod {}, {}",
+                    highlight(self.pod_id.to_string()),
+                    err
+                );
+                vec![]
+            }
+        };
+        let event = events
+            .into_iter()
+            .rev()
+            .find(|event| event.reason.as_deref() == Some("FailedScheduling"))
+            .and_then(|event| event.message);
+        let condition = match self
+            .pods
+            .get(&self.pod_id.namespace, &self.pod_id.name)
+            .await
+        {
+            Ok(pod) => pod.unschedulable_message(),
+            Err(err) => {
+                warn!(
+                    "Failed to get unschedulable pod {}, {}",
+                    highlight(self.pod_id.to_string()),
+                    err
+                );
+                None
+            }
+        };
+        let reason = event
+            .or(condition)
+            .unwrap_or_else(|| "<None Given>".to_string());
+        info!(
+            "Pod {} could not be scheduled within {:?}, {}",
+            highlight(self.pod_id.to_string()),
+            timeout,
+            reason
+        );
+        self.history
+            .record(Lifecycle::Unschedulable, Some(reason.clone()))
+            .await;
+        self.terminate(PodUnschedulable {
+            timeout: format!("{:?}", timeout),
+            reason,
+        })
+        .await;
     }
 
     /// Sends the final result to any waiting upstream client, kills the garbage collector,
     /// and tears down the pod being monitored.
+    ///
+    /// The pod is deleted as being [ill-behaved](DeletionCause::IllBehaved) by way of
+    /// the [kind](kind::Kind) of the given error.
     async fn terminate<T: Into<Box<dyn AcmError>>>(&self, err: T) {
-        let _ = self.send_result(Err(err.into())).await;
+        let err = err.into();
+        let cause = DeletionCause::IllBehaved { kind: err.kind() };
+        self.terminate_with_cause(err, cause).await;
+    }
+
+    /// Sends the final result to any waiting upstream client, kills the garbage collector,
+    /// and tears down the pod being monitored with the given cause.
+    async fn terminate_with_cause(&self, err: Box<dyn AcmError>, cause: DeletionCause) {
+        let _ = self.send_result(Err(err)).await;
+        self.kill_gc().await;
+        self.kill_pod(cause).await;
+    }
+
+    /// Sends a [PodDeleted](PodDeleted) result to any waiting upstream client and kills the
+    /// garbage collector. The pod itself is already gone, so the cause recorded on the deleted
+    /// pod object is what is reported. Any dependents left behind by the pod are cleaned up.
+    async fn report_deletion(&self, deleted: &Pod) {
+        let cause = deleted.deletion_cause();
+        self.history
+            .record(Lifecycle::Deleted, cause.as_ref().map(|c| c.to_string()))
+            .await;
+        if let Some(cause) = cause.as_ref() {
+            deletions::record(&self.pod_id, cause.clone()).await;
+        }
+        deletions::cleanup(deleted).await;
+        let _ = self
+            .send_result(Err(PodDeleted {
+                cause: cause
+                    .map(|cause| cause.to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+            }
+            .into()))
+            .await;
         self.kill_gc().await;
-        self.kill_pod().await;
     }
 
     /// Sends a shutdown signal the garbage collector. It is NOT fatal call this procedure
@@ -401,16 +952,42 @@ impl EventWatcherDaemon {
                 "The event watcher for pod {} sent a shutdown \
             signal to its garbage collector, however the garbage collector appears to have shut \
             itself down earlier than expected. {:?}",
-                cyan(&self.pod_id),
+                highlight(self.pod_id.to_string()),
                 err
             ),
         }
     }
 
-    /// Submits a request to Kubernetes to destroy the pod being monitored.
-    async fn kill_pod(&self) {
-        let client: Api<Pod> = client::new().await;
-        let _ = client.delete(&self.pod_id, &DeleteParams::default()).await;
+    /// Submits a request to Kubernetes to destroy the pod being monitored. The pod's peak usage
+    /// (if any was sampled) is recorded within its annotations first.
+    async fn kill_pod(&self, cause: DeletionCause) {
+        self.history
+            .record(Lifecycle::Deleted, Some(cause.to_string()))
+            .await;
+        self.record_peak().await;
+        deletions::delete(&*self.pods, &self.pod_id, cause).await;
+    }
+
+    /// Records the peak usage sampled by the [UsageSampler](UsageSampler) within the pod's
+    /// annotations so that it outlives the ACM's interest in the pod. Failures are merely logged.
+    async fn record_peak(&self) {
+        let peak = match self.usage.as_ref().map(UsageSampler::peak) {
+            Some(peak) if !peak.is_empty() => peak,
+            _ => return,
+        };
+        match k8s::usage::record_peak(&self.pod_id.namespace, &self.pod_id.name, &peak).await {
+            Ok(()) => debug!(
+                "Pod {} peaked at {}m CPU and {} bytes of memory",
+                highlight(self.pod_id.to_string()),
+                peak.cpu_millicores,
+                peak.memory_bytes
+            ),
+            Err(err) => warn!(
+                "Failed to record the peak usage of pod {}, {}",
+                highlight(self.pod_id.to_string()),
+                err
+            ),
+        }
     }
 
     /// Sends the provided to result back upstream to any client that may be waiting.
@@ -422,6 +999,33 @@ impl EventWatcherDaemon {
     }
 }
 
+/// Returns the next [DeleteRequest](DeleteRequest) sent by a [Terminator](Terminator). Should every
+/// Terminator have been dropped, then no such request can ever arrive and this future never resolves.
+async fn next_delete_request(requests: &mut mpsc::Receiver<DeleteRequest>) -> DeleteRequest {
+    match requests.recv().await {
+        Some(request) => request,
+        None => futures::future::pending().await,
+    }
+}
+
+/// Resolves once the connector is found unhealthy by the given
+/// [health watch](server_check::HealthWatch). Should there be no such watch, then this future
+/// never resolves.
+async fn next_unhealthy(health: &mut Option<server_check::HealthWatch>) -> Box<dyn AcmError> {
+    match health {
+        Some(health) => health.unhealthy().await,
+        None => futures::future::pending().await,
+    }
+}
+
+/// An Interrupt is either the next event that was being awaited, or a request to
+/// delete the pod or a report of the connector becoming unhealthy that arrived first.
+enum Interrupt<T> {
+    Event(T),
+    Delete(DeleteRequest),
+    Unhealthy(Box<dyn AcmError>),
+}
+
 #[derive(Clone, Debug)]
 /// A GcStatus us a simple binary status that may be sent to
 /// the garbage collector to communicate start/shutdown signals.
@@ -433,19 +1037,28 @@ pub enum GcStatus {
 #[derive(Error, AcmError, HttpCode, Kind, Debug)]
 #[error("")]
 #[code(error::Status::InternalServerError)]
+#[error_code("ACM-1100")]
 struct SendChannelClose {}
 
 #[derive(Error, AcmError, HttpCode, Kind, Debug)]
 #[error(
     "The connector has crashed. Please review its logs for additional debugging information \
-and report any finding to the connector's development team for further analysis."
+(the last lines of which are given as the cause of this error, and more of which are kept by the \
+ACM and may be retrieved from its /crashlogs endpoint) and report any finding to the connector's \
+development team for further analysis."
 )]
 #[code(error::Status::ServiceUnavailable)]
-struct PodCrashed {}
+#[error_code("ACM-1101")]
+struct PodCrashed {
+    #[source]
+    cause: Option<StringError>,
+}
 
+#[allow(clippy::large_enum_variant)]
 enum Phase2Event {
-    K8s(std::result::Result<Option<k8s::watcher::Event<Pod>>, k8s::watcher::Error>),
+    K8s(std::result::Result<Option<PodEvent>, k8s::informer::Error>),
     HealthCheck(std::result::Result<Result<()>, tokio::sync::oneshot::error::RecvError>),
+    Delete(DeleteRequest),
 }
 
 #[derive(Error, AcmError, HttpCode, Kind, Debug)]
@@ -455,6 +1068,7 @@ enum Phase2Event {
 (perhaps it crashed immediately). The (optional) reason given by Kubernetes was '{reason}' \
 and the (optional) message given was '{message}'."
 )]
+#[error_code("ACM-1102")]
 struct PodTerminatedBeforeStart {
     message: String,
     reason: String,
@@ -467,6 +1081,7 @@ struct PodTerminatedBeforeStart {
 For the sake of safety, the pod for this job has been deleted. Please try this operation again, \
 but please also report this as a bug to the Alation OCF development and infrastructure team."
 )]
+#[error_code("ACM-1103")]
 struct GarbageCollectorUnresponsive {
     pod: String,
 }
@@ -477,6 +1092,7 @@ struct GarbageCollectorUnresponsive {
     "The Kubernetes API server has failed to reconnect for this job's event stream for over \
 {elapsed}. The cluster appears to be too unhealthy to reasonably continue at this time."
 )]
+#[error_code("ACM-1104")]
 struct KubernetesUnresponsive {
     elapsed: String,
 }
@@ -487,24 +1103,52 @@ struct KubernetesUnresponsive {
     "The Kubernetes API server has prematurely, and permanently, close the event stream for this \
 job's pod. The job may work if simply re-ran, however this may be indicative of an unhealthy cluster."
 )]
+#[error_code("ACM-1105")]
 struct UnexpectedCloseOfEventStream {}
 
 #[derive(Error, AcmError, HttpCode, Kind, Debug)]
 #[code(error::Status::ServiceUnavailable)]
 #[error(
-"The pod for this job was deleted. If this not expected, then perhaps Alation timed out and the \
-pod was garbage collected. Or perhaps another component has deleted the pod for some reason."
+"The pod for this job was deleted (cause: {cause}). If this not expected, then perhaps Alation \
+timed out and the pod was garbage collected. Or perhaps another component has deleted the pod for \
+some reason."
+)]
+#[error_code("ACM-1106")]
+struct PodDeleted {
+    cause: String,
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(error::Status::ServiceUnavailable)]
+#[error(
+"The pod for this job was evicted by Kubernetes. This typically occurs when the node that it was \
+scheduled on comes under resource pressure. The job may succeed if simply re-ran. Kubernetes \
+reported: {message}"
 )]
-struct PodDeleted {}
+#[error_code("ACM-1107")]
+struct PodEvicted {
+    message: String,
+}
+
+impl PodEvicted {
+    fn of(pod: &Pod) -> PodEvicted {
+        PodEvicted {
+            message: pod
+                .eviction_message()
+                .unwrap_or_else(|| "<None Given>".to_string()),
+        }
+    }
+}
 
 #[derive(Error, AcmError, HttpCode, Kind, Debug)]
 #[code(error::Status::ServiceUnavailable)]
 #[error(
 "The pod for this job appears to have been rebooted. This may occur if the pod crashed and was \
 restarted automatically. However, OCF has no tolerance for \"crashy\' connectors, and as such it \
-has been deleted. Please gather logs for this connector and report the issue to the connector's \
-development team."
+has been deleted. The last of its logs are kept by the ACM and may be retrieved from its /crashlogs \
+endpoint. Please report the issue to the connector's development team."
 )]
+#[error_code("ACM-1108")]
 struct PodRebooted {}
 
 #[derive(Error, AcmError, HttpCode, Kind, Debug)]
@@ -514,4 +1158,175 @@ struct PodRebooted {}
 signal. This job may succeed if re-attempted, however this bug should be reported to the Alation \
 OCF development and infrastructure team."
 )]
+#[error_code("ACM-1109")]
 struct HealthCheckDroppedItsChannel {}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(error::Status::ServiceUnavailable)]
+#[error(
+"The pod for this job could not be scheduled onto any node of the cluster within {timeout}, and so \
+it has been deleted. This typically occurs when the cluster lacks the memory or CPU that the \
+connector requests, and the job may succeed once resources free up. Kubernetes reported: {reason}"
+)]
+#[error_code("ACM-1110")]
+struct PodUnschedulable {
+    timeout: String,
+    reason: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::podmanager::external_handle::PodManagerUpperHandle;
+    use crate::podmanager::history;
+    use k8s::fake::FakePods;
+    use k8s_openapi::api::core::v1::{
+        Container, ContainerState, ContainerStateWaiting, ContainerStatus, PodSpec, PodStatus,
+    };
+    use kube::api::DeleteParams;
+
+    /// A scheduled, albeit not yet started, connector pod. Every test watches its pod within a
+    /// namespace of its own, as the [INFORMERS](INFORMERS) are shared.
+    fn pod(id: &PodId) -> Pod {
+        let mut pod = Pod::default();
+        pod.metadata.namespace = Some(id.namespace.clone());
+        pod.metadata.name = Some(id.name.clone());
+        pod.metadata.labels = Some(
+            vec![(k8s::POD_LABEL.to_string(), id.name.clone())]
+                .into_iter()
+                .collect(),
+        );
+        pod.spec = Some(PodSpec {
+            containers: vec![Container {
+                name: "connector".to_string(),
+                ..Default::default()
+            }],
+            node_name: Some("node".to_string()),
+            ..Default::default()
+        });
+        pod
+    }
+
+    struct Watched {
+        terminator: Terminator,
+        daemon: JoinHandle<()>,
+        gc: mpsc::Receiver<GcStatus>,
+        upper: PodManagerUpperHandle,
+    }
+
+    /// Watches the given pod, returning once the event watcher is known to be subscribed to it.
+    async fn watch(pods: &FakePods, id: &PodId) -> Watched {
+        pods.insert(pod(id));
+        let (status, gc) = mpsc::channel(1);
+        let (upper, lower) = PodManagerUpperHandle::new();
+        let history = history::open(id).await;
+        let (terminator, daemon) = EventWatcher::new_watcher(
+            Arc::new(pods.clone()),
+            id.clone(),
+            status,
+            lower,
+            history,
+            0,
+        );
+        // Until the informer has listed the namespace, the pod's events go unseen.
+        while !recorded(id, Lifecycle::Added).await {
+            pods.update(&id.namespace, &id.name, |_| ());
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        Watched {
+            terminator,
+            daemon,
+            gc,
+            upper,
+        }
+    }
+
+    async fn recorded(id: &PodId, lifecycle: Lifecycle) -> bool {
+        history::of(id)
+            .await
+            .unwrap()
+            .transitions
+            .iter()
+            .any(|transition| transition.event == lifecycle)
+    }
+
+    #[test]
+    fn reports_deletions_before_start() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            let id = PodId::new("reports-deletions-before-start", "oracle");
+            let mut watched = watch(&pods, &id).await;
+            pods.delete(&id.namespace, &id.name, &DeleteParams::default())
+                .await
+                .unwrap();
+            let err = watched.upper.wait().await.unwrap_err();
+            assert_eq!(err.error_code(), Some("ACM-1106"));
+            assert!(matches!(
+                watched.gc.recv().await,
+                Some(GcStatus::Terminated)
+            ));
+            watched.daemon.await.unwrap();
+            assert!(recorded(&id, Lifecycle::Deleted).await);
+        });
+    }
+
+    #[test]
+    fn deletes_on_request() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            let id = PodId::new("deletes-on-request", "oracle");
+            let mut watched = watch(&pods, &id).await;
+            assert!(watched.terminator.delete().await);
+            assert!(pods.pod(&id.namespace, &id.name).is_none());
+            assert_eq!(deletions::cause_of(&id).await, Some(DeletionCause::User));
+            let err = watched.upper.wait().await.unwrap_err();
+            assert_eq!(err.error_code(), Some("ACM-1106"));
+            assert!(matches!(
+                watched.gc.recv().await,
+                Some(GcStatus::Terminated)
+            ));
+            watched.daemon.await.unwrap();
+            assert!(!watched.terminator.delete().await);
+        });
+    }
+
+    #[test]
+    fn terminates_failed_image_pulls() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            let id = PodId::new("terminates-failed-image-pulls", "oracle");
+            let mut watched = watch(&pods, &id).await;
+            pods.update(&id.namespace, &id.name, |pod| {
+                pod.status = Some(PodStatus {
+                    container_statuses: Some(vec![ContainerStatus {
+                        name: "connector".to_string(),
+                        state: Some(ContainerState {
+                            waiting: Some(ContainerStateWaiting {
+                                reason: Some("ImagePullBackOff".to_string()),
+                                message: Some("manifest unknown".to_string()),
+                            }),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }]),
+                    ..Default::default()
+                })
+            });
+            let err = watched.upper.wait().await.unwrap_err();
+            assert_eq!(err.kind(), "ImagePullBackOff");
+            assert!(matches!(
+                watched.gc.recv().await,
+                Some(GcStatus::Terminated)
+            ));
+            watched.daemon.await.unwrap();
+            assert!(pods.pod(&id.namespace, &id.name).is_none());
+            assert!(recorded(&id, Lifecycle::ImagePullFailed).await);
+            assert_eq!(
+                deletions::cause_of(&id).await,
+                Some(DeletionCause::IllBehaved {
+                    kind: "ImagePullBackOff".to_string()
+                })
+            );
+        });
+    }
+}
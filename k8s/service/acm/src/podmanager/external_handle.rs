@@ -1,94 +1,127 @@
 use error::*;
-use futures_util::{pin_mut, select, FutureExt};
 use k8s_openapi::api::core::v1::Pod;
-use rocket::tokio::task::JoinHandle;
-use std::sync::Arc;
+use tokio::sync::watch;
 
+/// The result of waiting on a pod, as retained by a [PodManagerUpperHandle](PodManagerUpperHandle).
+pub type WaitResult = std::result::Result<Pod, WaitError>;
+
+/// A PodManagerUpperHandle is the end of the wait result channel that external clients
+/// [wait](PodManagerUpperHandle::wait) on.
+///
+/// The wait result is retained for as long as the pod is managed (that is, until the pod is
+/// deleted and its PodManager is torn down) rather than being handed to the first waiter alone.
+/// Any number of waiters may wait at once, and they are all woken by the same result. Should the
+/// event watcher report a later result (say, that the pod crashed after having become active) then
+/// that result replaces the earlier one for every later wait.
+#[derive(Clone)]
 pub struct PodManagerUpperHandle {
-    barrier: Arc<tokio::sync::Barrier>,
-    result: tokio::sync::mpsc::Receiver<result::Result<Pod>>,
-    phantom: Option<result::Result<Pod>>,
+    result: watch::Receiver<Option<WaitResult>>,
+}
+
+/// A PodManagerLowerHandle is the end of the wait result channel that the
+/// [event watcher](super::event_watcher) reports the pod's wait result into.
+pub struct PodManagerLowerHandle {
+    result: watch::Sender<Option<WaitResult>>,
 }
 
 impl PodManagerLowerHandle {
+    /// Reports the given result to every current and future waiter.
     pub async fn send(
         &self,
         value: result::Result<Pod>,
-    ) -> std::result::Result<(), tokio::sync::mpsc::error::SendError<result::Result<Pod>>> {
-        self.result.send(value).await
+    ) -> std::result::Result<(), watch::error::SendError<Option<WaitResult>>> {
+        self.result.send(Some(value.map_err(WaitError::from)))
     }
 }
 
 impl PodManagerUpperHandle {
-    pub fn new() -> (PodManagerUpperHandle, PodManagerLowerHandle, JoinHandle<()>) {
-        let barrier = Arc::new(tokio::sync::Barrier::new(2));
-        let (tx1, mut rx1) = tokio::sync::mpsc::channel(1);
-        let (tx2, rx2) = tokio::sync::mpsc::channel(1);
-        let shim_barrier = barrier.clone();
-        let handle_shim = tokio::spawn(async move {
-            let result = match rx1.recv().await {
-                None => {
-                    let err = InboundResultChannelDropped {}.into();
-                    error!("{}", err);
-                    Err(err)
-                }
-                Some(result) => result,
-            };
-            let patience = tokio::time::Duration::from_secs(60);
-            let patience = tokio::time::sleep(patience).fuse();
-            let barrier = shim_barrier.wait().fuse();
-            pin_mut!(patience, barrier);
-            select! {
-                _ = patience => {
-                    return;
-                },
-                _ = barrier => ()
+    pub fn new() -> (PodManagerUpperHandle, PodManagerLowerHandle) {
+        let (tx, rx) = watch::channel(None);
+        let upper = PodManagerUpperHandle { result: rx };
+        let lower = PodManagerLowerHandle { result: tx };
+        (upper, lower)
+    }
+
+    /// Waits for the event watcher to report the pod's result, returning immediately should it
+    /// have already done so.
+    pub async fn wait(mut self) -> result::Result<Pod> {
+        loop {
+            let result = self.result.borrow().clone();
+            if let Some(result) = result {
+                return result.map_err(Into::into);
             }
-            match tx2.send(result).await {
-                Ok(()) => trace!("Successfully communicated pod result to the calling client"),
-                Err(err) => error!("{}, {:?}", OutboundResultChannelDropped {}, err),
+            if self.result.changed().await.is_err() {
+                // The event watcher exited, although it may have reported a result on its way out.
+                let result = self.result.borrow().clone();
+                return match result {
+                    Some(result) => result.map_err(Into::into),
+                    None => Err(InboundResultChannelDropped {}.into()),
+                };
             }
-        });
-        let upper = PodManagerUpperHandle {
-            barrier,
-            result: rx2,
-            phantom: None,
-        };
-        let lower = PodManagerLowerHandle { result: tx1 };
-        (upper, lower, handle_shim)
+        }
     }
+}
+
+/// A WaitError is a copy of the error that a pod's wait resolved with. Errors themselves may only
+/// be handed out once, whereas the copy may be handed to every one of the pod's waiters. It is
+/// indistinguishable from the original error to the client.
+#[derive(Clone, Debug)]
+pub struct WaitError {
+    kind: String,
+    error_code: Option<String>,
+    message: String,
+    cause: Option<WaitErrorCause>,
+    status: Status,
+}
 
-    pub async fn wait(&mut self) -> result::Result<Pod> {
-        match self.phantom.as_ref() {
-            None => (),
-            Some(Ok(pod)) => return Ok(pod.clone()),
-            Some(Err(_)) => return Err(PhantomError {}.into()),
+impl From<Box<dyn AcmError>> for WaitError {
+    fn from(err: Box<dyn AcmError>) -> Self {
+        WaitError {
+            kind: err.kind(),
+            error_code: err.error_code().map(str::to_string),
+            message: err.to_string(),
+            cause: err.source().map(|cause| WaitErrorCause(cause.to_string())),
+            status: err.http_code(),
         }
-        self.barrier.wait().await;
-        let result = match self.result.recv().await {
-            Some(result) => result,
-            None => Err(InboundResultChannelDropped {}.into()),
-        };
-        match result.as_ref() {
-            Ok(pod) => {
-                self.phantom = Some(Ok(pod.clone()));
-            }
-            Err(_) => {
-                self.phantom = Some(Err(PhantomError {}.into()));
-            }
-        };
-        result
     }
 }
 
-#[derive(Error, AcmError, Kind, HttpCode, Debug)]
-#[code(Status::InternalServerError)]
-#[error(
-    "In internal datastructure was deallocated before a result was ever placed into. \
-This is a severe state machine violation from within the ACM (Alation Connector Manager). \
-Please try this operation again, but please also report this as a bug to Alation."
-)]
-pub struct InboundResultChannelDropped {}
+impl std::fmt::Display for WaitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for WaitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.cause
+            .as_ref()
+            .map(|cause| cause as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl HttpCode for WaitError {
+    fn http_code(&self) -> Status {
+        self.status
+    }
+}
+
+impl Kind for WaitError {
+    fn kind(&self) -> String {
+        self.kind.clone()
+    }
+}
+
+impl AcmError for WaitError {
+    fn error_code(&self) -> Option<&str> {
+        self.error_code.as_deref()
+    }
+}
+
+/// The (formatted) cause of a [WaitError](WaitError).
+#[derive(Error, Clone, Debug)]
+#[error("{0}")]
+struct WaitErrorCause(String);
 
 #[derive(Error, AcmError, Kind, HttpCode, Debug)]
 #[code(Status::InternalServerError)]
@@ -97,13 +130,5 @@ pub struct InboundResultChannelDropped {}
 This is a severe state machine violation from within the ACM (Alation Connector Manager). \
 Please try this operation again, but please also report this as a bug to Alation."
 )]
-pub struct OutboundResultChannelDropped {}
-
-#[derive(Error, AcmError, HttpCode, Kind, Debug)]
-#[error("")]
-#[code(Status::BadRequest)]
-struct PhantomError {}
-
-pub struct PodManagerLowerHandle {
-    result: tokio::sync::mpsc::Sender<result::Result<Pod>>,
-}
+#[error_code("ACM-1400")]
+pub struct InboundResultChannelDropped {}
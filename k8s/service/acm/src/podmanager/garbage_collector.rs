@@ -1,26 +1,34 @@
+use super::deadlines::Deadlines;
+use super::deletions;
 use super::event_watcher::GcStatus;
+use super::gc_report::{self, GcExecution, GcOutcome};
+use super::notifications::{self, Transition};
+use super::recorder::{self, Reason};
+use super::tasks::Task;
+use super::tickets;
+use super::PodId;
 use chrono::DateTime;
 use chrono::Utc;
 use error::*;
-use futures::FutureExt;
+use futures::stream::{BoxStream, SelectAll};
+use futures::{FutureExt, StreamExt};
 use futures_util::{pin_mut, select};
-use k8s::client;
+use k8s::deletion::DeletionCause;
+use k8s::PodApi;
 use k8s_openapi::api::core::v1::Pod;
 use kind::Kind;
-use kube::api::{DeleteParams, Patch, PatchParams};
-use kube::Api;
 use result::Result;
 use serde::Serialize;
-use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
-use std::iter::FromIterator;
 use std::ops::Add;
+use std::sync::Arc;
 use term_colors::*;
-use tokio::sync::mpsc;
-use tokio::sync::oneshot::{channel, Sender};
-use tokio::task::JoinHandle;
+use tokio::sync::{mpsc, oneshot, watch};
 
-pub const DEFAULT_TTL: u64 = 60 * 30;
+/// The label that marks a pod whose TTL has expired, and which will be garbage collected once
+/// its [grace period](config::acm::AcmTunables::gc_grace_period) is over unless it is refreshed.
+pub const GC_IMMINENT_LABEL: &str = "gc_imminent";
 
 /// A `KeepAliveTicket` is issued to client programs who lease out pods. It encodes two pieces
 /// of information intended for client consumption:
@@ -31,15 +39,17 @@ pub const DEFAULT_TTL: u64 = 60 * 30;
 pub struct KeepAliveTicket {
     /// `ticket` is the unique identifier for this `KeepAliveTicket`
     ///
-    /// It is currently simply the name of the pod that it is tied
-    /// to, however this should not be taken to be a guarantee of
-    /// future behavior.
+    /// It is [signed](super::tickets) should the ACM have any signing
+    /// keys, and is otherwise simply the name of the pod that it is
+    /// tied to. Either way, clients must treat it as opaque.
     ticket: String,
     /// `execution_date` is the Unix timestamp of the exact moment
     /// when the `KeepAliveTicket` becomes invalid and deletion of
     /// the backing pod will commence.
     ///
-    /// There is no grace period.
+    /// Should a [grace period](config::acm::AcmTunables::gc_grace_period) be configured, then the
+    /// pod is rather labeled as [gc_imminent](GC_IMMINENT_LABEL) at this moment, and is deleted
+    /// once the grace period is over unless it is refreshed in the meantime.
     execution_date: i64,
     // Anything annotated with #[serde(skip)] will NOT
     // be serialized into the JSON returned to the client
@@ -67,7 +77,7 @@ impl KeepAliveTicket {
         let execution_date = then.timestamp();
         let execution_instant =
             tokio::time::Instant::now().add(tokio::time::Duration::from_secs(ttl));
-        let ticket = pod.as_ref().to_string();
+        let ticket = tickets::issue(pod.as_ref(), execution_date);
         KeepAliveTicket {
             ticket,
             execution_date,
@@ -76,26 +86,6 @@ impl KeepAliveTicket {
             execution_instant,
         }
     }
-
-    /// Puts the running couroutine to sleep until the moment that `execution_instant` is reached.
-    pub async fn sleep(self) {
-        tokio::time::sleep_until(self.execution_instant).await;
-    }
-
-    /// Returns a (Patch<Pod>)[use kube::api::Patch] object that may be used to update
-    /// a given pod with am accurate `.metadata.labels.execution_date`.
-    ///
-    /// This is especially useful for recording this information into Kubernetes itself
-    /// so that disaster recovery may happen (for example, if this ACM dies then another
-    /// instance of the ACM could reconstruct a PodManager using this information).
-    fn pod_patch(&self) -> Patch<Pod> {
-        let mut patch = Pod::default();
-        patch.metadata.labels = Some(BTreeMap::from_iter([(
-            "execution_date".to_string(),
-            format!("{}", self.execution_date),
-        )]));
-        Patch::Merge(patch)
-    }
 }
 
 /// The logging display implementation of a `KeepAliveTicket`. This dictates how to format
@@ -112,8 +102,26 @@ impl Display for KeepAliveTicket {
     }
 }
 
+lazy_static! {
+    /// The single daemon that garbage collects every pod, which is started by the first
+    /// [GarbageCollector](GarbageCollector) to be created.
+    static ref DAEMON: mpsc::UnboundedSender<Registration> = {
+        let (sender, registrations) = mpsc::unbounded_channel();
+        tokio::spawn(GarbageCollectorDaemon::default().run(registrations));
+        sender
+    };
+}
+
+/// Finished resolves once the garbage collection of a pod has come to an end, including any
+/// deletion of the pod that the garbage collector made on its way out.
+pub type Finished = oneshot::Receiver<()>;
+
 /// A `GarbageCollector` is a facade over the long-running daemon that is tracking the garbage
 /// collection status of a particular pod.
+///
+/// There is but one daemon, which tracks the execution date of every pod within a single
+/// [schedule](Deadlines) rather than each pod having a coroutine of its own that sleeps until its
+/// execution date.
 pub struct GarbageCollector {
     refresh_sender: mpsc::Sender<RefreshRequest>,
 }
@@ -121,45 +129,63 @@ pub struct GarbageCollector {
 impl GarbageCollector {
     /// A new garbage collector takes in:
     ///
-    /// 1. A receiver channel of [GcStatus](super::event_watcher::GcStatus)es. This channel servers
-    ///     as the sole means of communication between the event watcher thread and the garbage
-    ///     collector thread. The two signals that the event watcher may send to the GC are
-    ///     [GcStatus::Running](super::event_watcher::GcStatus::Running) and [GcStatus::Terminated](super::event_watcher::GcStatus::Terminated).
-    ///     These statuses are used the GC as go-ahead and shutdown signals.
-    /// 2. The name of the pod being managed by this garbage collector.
-    /// 3. The `ttl` interval for this garbage collector.
+    /// 1. The [PodApi](k8s::PodApi) through which the pod is patched and deleted.
+    /// 2. A receiver channel of [GcStatus](super::event_watcher::GcStatus)es. This channel servers
+    ///    as the sole means of communication between the event watcher thread and the garbage
+    ///    collector thread. The two signals that the event watcher may send to the GC are
+    ///    [GcStatus::Running](super::event_watcher::GcStatus::Running) and [GcStatus::Terminated](super::event_watcher::GcStatus::Terminated).
+    ///    These statuses are used the GC as go-ahead and shutdown signals.
+    /// 3. The [PodId](super::PodId) of the pod being managed by this garbage collector.
+    /// 4. The `ttl` interval for this garbage collector.
     ///
-    /// A tuple of a `GarbageCollector` and a [JoinHandle<()>](tokio::task::JoinHandle) are returned.
+    /// A tuple of a `GarbageCollector` and a [Finished](Finished) are returned.
     ///
-    /// The `GarbageCollector` object is a facade into the running coroutin that is the actual
-    /// garbage collector. It has a single method, [refresh](GarbageCollector::refresh), which may
-    /// be used to reset the GC's execution date and retrieve a new [KeepAliveTicket](KeepAliveTicket).
+    /// The `GarbageCollector` object is a facade into the daemon that is the actual garbage
+    /// collector. It has a single method, [refresh](GarbageCollector::refresh), which may be used
+    /// to reset the GC's execution date and retrieve a new [KeepAliveTicket](KeepAliveTicket).
     ///
-    /// The return [JoinHandle<()>](tokio::task::JoinHandle) is the actual running coroutine that is
-    /// the garbage collector. `await`ing on this handle will block indefinitely until the
-    /// garbage collector exists.
+    /// `await`ing on the returned [Finished](Finished) will block indefinitely until the garbage
+    /// collection of the pod has come to an end.
     pub fn new(
+        pods: Arc<dyn PodApi>,
         status: mpsc::Receiver<GcStatus>,
-        pod: String,
+        pod: PodId,
         ttl: u64,
-    ) -> (GarbageCollector, JoinHandle<()>) {
+    ) -> (GarbageCollector, Finished) {
         let (refresh_sender, refresh_receiver) = mpsc::channel(1);
-        let gc = GarbageCollector { refresh_sender };
-        let gcd = GarbageCollectorDaemon {
-            refresh_receiver,
+        let (finished, done) = oneshot::channel();
+        let registration = Registration {
+            pods,
+            pod,
+            ttl,
             status,
+            refreshes: refresh_receiver,
+            finished,
         };
-        (gc, tokio::spawn(gcd.gc(pod, ttl)))
+        if let Err(err) = DAEMON.send(registration) {
+            error!(
+                "The garbage collection daemon has shut down, so {} will never be garbage collected",
+                highlight(err.0.pod.to_string())
+            );
+        }
+        (GarbageCollector { refresh_sender }, done)
     }
 
-    /// Retrieves a refreshed [KeepAliveTicket](KeepAliveTicket).
+    /// Retrieves a refreshed [KeepAliveTicket](KeepAliveTicket). Should a `ttl` be given, then it
+    /// replaces the garbage collector's TTL for this and every subsequent refresh.
+    ///
+    /// A refresh that arrives before the pod is running is answered once it is running.
     ///
     /// An [error](RefreshChannelClosed) will be returned in the extremely unlikely, although
     /// technically possible, event that the garbage collector has proceeded with a shutdown
     /// sequence at the exact same time that a client has requested a refresh.
-    pub async fn refresh(&self) -> Result<KeepAliveTicket> {
-        let (tx, rx) = channel();
-        match self.refresh_sender.send(tx).await {
+    pub async fn refresh(&self, ttl: Option<u64>) -> Result<KeepAliveTicket> {
+        let (reply, rx) = oneshot::channel();
+        match self
+            .refresh_sender
+            .send(RefreshRequest { ttl, reply })
+            .await
+        {
             Ok(()) => (),
             Err(_) => return Err(RefreshChannelClosed {}.into()),
         };
@@ -173,155 +199,721 @@ impl GarbageCollector {
 #[derive(Error, AcmError, HttpCode, Kind, Debug)]
 #[code(Status::ServiceUnavailable)]
 #[error("This pod appears to have already been shutdown or garbage collected.")]
+#[error_code("ACM-1200")]
 pub struct RefreshChannelClosed {}
 
-struct GarbageCollectorDaemon {
-    refresh_receiver: mpsc::Receiver<RefreshRequest>,
+/// A Registration hands a new pod over to the [daemon](GarbageCollectorDaemon).
+struct Registration {
+    pods: Arc<dyn PodApi>,
+    pod: PodId,
+    ttl: u64,
     status: mpsc::Receiver<GcStatus>,
+    refreshes: mpsc::Receiver<RefreshRequest>,
+    finished: oneshot::Sender<()>,
+}
+
+/// A Message is anything that is sent to the daemon on behalf of a single pod.
+enum Message {
+    Refresh(RefreshRequest),
+    /// A signal from the pod's event watcher, or `None` should the event watcher have shut down.
+    Status(Option<GcStatus>),
 }
 
+/// The daemon wakes up for whichever of these occurs first.
 enum GcEvent {
-    RefreshRequest(Option<RefreshRequest>),
-    ExecutionDateReached,
-    PodEvent(Option<GcStatus>),
+    Registration(Option<Registration>),
+    Message(Option<(u64, Message)>),
+    DeadlineReached,
+}
+
+/// A Collection is the daemon's record of the garbage collection of a single pod.
+///
+/// Collections are keyed by a serial number rather than by their [PodId](PodId), such that any
+/// straggling messages on behalf of an earlier pod of the same name cannot reach a later one.
+struct Collection {
+    pods: Arc<dyn PodApi>,
+    pod: PodId,
+    ttl: u64,
+    /// Refreshes that arrived before the pod was running, which are answered once it is.
+    queued: Vec<RefreshRequest>,
+    /// The countdown to the pod's execution date, which begins once the pod is running.
+    countdown: Option<Countdown>,
+    /// The labels that the pod ought to bear, which are written by a [labeler](labeler) of the
+    /// pod's own once its countdown begins.
+    labels: Option<watch::Sender<Labels>>,
+    finished: oneshot::Sender<()>,
+    /// The pod's registration with the [task registry](super::tasks), which lasts for as long as
+    /// its garbage collection does (just as it did when every pod had a coroutine of its own).
+    task: Task,
+}
+
+/// A Countdown is the garbage collection schedule of a running pod.
+struct Countdown {
+    keep_alive: KeepAliveTicket,
+    refreshes: u64,
+    /// The instant at which a pod whose timeout was reached is deleted, should it be within its
+    /// grace period.
+    imminent: Option<tokio::time::Instant>,
+    /// The instant at which the pod outlives the maximum lifetime, should there be one.
+    death: Option<tokio::time::Instant>,
+    max_lifetime: Option<u64>,
+}
+
+impl Countdown {
+    /// Returns the instant at which the daemon next has to attend to the pod.
+    fn deadline(&self) -> tokio::time::Instant {
+        let timeout = self.imminent.unwrap_or(self.keep_alive.execution_instant);
+        match self.death {
+            Some(death) => death.min(timeout),
+            None => timeout,
+        }
+    }
+}
+
+/// The GarbageCollectorDaemon is the single coroutine that garbage collects every pod.
+///
+/// The daemon itself never waits on Kubernetes (or anything else that may be slow). Rather, every
+/// deletion, Event, and notification on behalf of a pod is made within a short lived coroutine of
+/// its own, and every label is written by the pod's [labeler](labeler), so that a slow API server
+/// cannot hold up the garbage collection of every other pod.
+#[derive(Default)]
+struct GarbageCollectorDaemon {
+    collections: HashMap<u64, Collection>,
+    deadlines: Deadlines<u64>,
+    inbox: SelectAll<BoxStream<'static, (u64, Message)>>,
+    serial: u64,
 }
 
 impl GarbageCollectorDaemon {
-    async fn gc(mut self, pod: String, ttl: u64) {
-        /////////////////////////////////////////////////////////////////////////////////
-        // Phase 1: Begin listening for an event received from the event watcher.
-        //          At this point, the GC countdown has not begun because the pod
-        //          has not even been provisioned yet.
+    async fn run(mut self, mut registrations: mpsc::UnboundedReceiver<Registration>) {
+        loop {
+            let event = {
+                let deadline = self.deadlines.earliest();
+                let timeout = async move {
+                    match deadline {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => futures::future::pending().await,
+                    }
+                }
+                .fuse();
+                let registration = registrations.recv().fuse();
+                let inbox = &mut self.inbox;
+                let message = async move {
+                    // An empty inbox is an immediately exhausted stream rather than a quiet one.
+                    if inbox.is_empty() {
+                        futures::future::pending().await
+                    } else {
+                        inbox.next().await
+                    }
+                }
+                .fuse();
+                pin_mut!(timeout, registration, message);
+                // This right here is the magical select statement which chooses whichever event
+                // occurs first.
+                select! {
+                    registration = registration => GcEvent::Registration(registration),
+                    message = message => GcEvent::Message(message),
+                    _ = timeout => GcEvent::DeadlineReached,
+                }
+            };
+            match event {
+                GcEvent::Registration(None) => return,
+                GcEvent::Registration(Some(registration)) => self.register(registration),
+                GcEvent::Message(None) => (),
+                GcEvent::Message(Some((serial, message))) => self.receive(serial, message),
+                GcEvent::DeadlineReached => {
+                    let now = tokio::time::Instant::now();
+                    while let Some(serial) = self.deadlines.pop_expired(now) {
+                        self.expire(serial);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Begins the garbage collection of a new pod. At this point, the GC countdown has not begun
+    /// because the pod has not even been provisioned yet.
+    fn register(&mut self, registration: Registration) {
+        let Registration {
+            pods,
+            pod,
+            ttl,
+            status,
+            refreshes,
+            finished,
+        } = registration;
         debug!(
             "GC waiting for go head to begin countdown for {}",
-            cyan(&pod)
+            highlight(pod.to_string())
         );
-        match self.status.recv().await {
-            None => {
-                // This is probably a bug should this occur. The event watcher shutdown
-                // before ever giving a signal to the GC.
-                warn!(
-                    "GC received a signal that the event watcher for {} prematurely shutdown",
-                    cyan(&pod)
+        let serial = self.serial;
+        self.serial += 1;
+        // The event watcher's signals end with a `None` once the event watcher has gone away.
+        let statuses = futures::stream::unfold(Some(status), |status| async move {
+            let mut status = status?;
+            match status.recv().await {
+                Some(signal) => Some((Message::Status(Some(signal)), Some(status))),
+                None => Some((Message::Status(None), None)),
+            }
+        });
+        let refreshes = futures::stream::unfold(refreshes, |mut refreshes| async move {
+            let refresh = refreshes.recv().await?;
+            Some((Message::Refresh(refresh), refreshes))
+        });
+        self.inbox.push(
+            futures::stream::select(statuses, refreshes)
+                .map(move |message| (serial, message))
+                .boxed(),
+        );
+        self.collections.insert(
+            serial,
+            Collection {
+                task: Task::register("garbage_collector", &pod),
+                pods,
+                pod,
+                ttl,
+                queued: vec![],
+                countdown: None,
+                labels: None,
+                finished,
+            },
+        );
+    }
+
+    /// Handles a message on behalf of the pod of the given serial number. Messages for a pod whose
+    /// garbage collection has already come to an end are dropped, which any refresh among them
+    /// sees as a [RefreshChannelClosed](RefreshChannelClosed).
+    fn receive(&mut self, serial: u64, message: Message) {
+        let collection = match self.collections.get_mut(&serial) {
+            Some(collection) => collection,
+            None => return,
+        };
+        collection.task.heartbeat();
+        match (message, collection.countdown.is_some()) {
+            (Message::Refresh(refresh), false) => collection.queued.push(refresh),
+            (Message::Refresh(refresh), true) => {
+                collection.refresh(refresh);
+                let deadline = collection.countdown.as_ref().unwrap().deadline();
+                self.deadlines.schedule(serial, deadline);
+            }
+            (Message::Status(Some(GcStatus::Running(running))), false) => {
+                // Yay! The pod is running!
+                debug!(
+                    "GC received {} signal for {}, beginning routine",
+                    stringify!(Status::Running),
+                    highlight(collection.pod.to_string())
+                );
+                collection.start(&running);
+                let deadline = collection.countdown.as_ref().unwrap().deadline();
+                self.deadlines.schedule(serial, deadline);
+            }
+            (Message::Status(Some(GcStatus::Running(_))), true) => {
+                // Neat? We shouldn't be receiving such superfluous signals, but it's
+                // not an error or nothing. It's just not useful.
+                debug!(
+                    "Garbage collector received running signal for {} in mid-operation",
+                    highlight(collection.pod.to_string())
                 );
-                return;
             }
-            Some(GcStatus::Terminated) => {
+            (Message::Status(Some(GcStatus::Terminated)), false) => {
                 // The pod has shutdown before it ever even started. This'll happen for
                 // instant crashes, bad images, etc.
                 debug!(
                     "GC received {} signal for {}, shutting down",
                     stringify!(Status::Terminated),
-                    cyan(&pod)
+                    highlight(collection.pod.to_string())
                 );
-                return;
+                let collection = self.retire(serial);
+                let _ = collection.finished.send(());
             }
-            Some(GcStatus::Running(_)) => {
-                // Yay! The pod is running!
+            (Message::Status(Some(GcStatus::Terminated)), true) => {
+                // The pod has been deleted. Most commonly this is due to a client
+                // explicitly deleting the pod through the ACM's API.
                 debug!(
-                    "GC received {} signal for {}, beginning routine",
-                    stringify!(Status::Running),
-                    cyan(&pod)
+                    "Garbage collector received termination signal for {}",
+                    highlight(collection.pod.to_string())
+                );
+                let collection = self.retire(serial);
+                tokio::spawn(async move {
+                    let pod = &collection.pod;
+                    gc_report::record(GcExecution {
+                        cause: deletions::cause_of(pod)
+                            .await
+                            .map(|cause| cause.to_string()),
+                        ..collection.execution(GcOutcome::Terminated)
+                    })
+                    .await;
+                    let _ = collection.finished.send(());
+                });
+            }
+            (Message::Status(None), false) => {
+                // This is probably a bug should this occur. The event watcher shutdown
+                // before ever giving a signal to the GC.
+                warn!(
+                    "GC received a signal that the event watcher for {} prematurely shutdown",
+                    highlight(collection.pod.to_string())
                 );
+                let collection = self.retire(serial);
+                let _ = collection.finished.send(());
             }
+            (Message::Status(None), true) => {
+                // The event listener went down without sending us a signal. This NOT
+                // what it is suppose to do, but just to be safe let's assume that it completely
+                // crashed and burned and now we need to be the ones to clean the pod up.
+                warn!(
+                    "The event listener for pod {} has shutdown",
+                    highlight(collection.pod.to_string())
+                );
+                let collection = self.retire(serial);
+                tokio::spawn(async move {
+                    let pod = &collection.pod;
+                    let message =
+                        "The pod's event watcher shut down unexpectedly, so the pod was deleted";
+                    let cause = DeletionCause::IllBehaved {
+                        kind: "EventWatcherShutdown".to_string(),
+                    };
+                    recorder::record(pod, Reason::GarbageCollected, message).await;
+                    notifications::notify(
+                        pod,
+                        Transition::GarbageCollected,
+                        Some(&cause),
+                        Some(message),
+                    );
+                    let deleted = deletions::delete(&*collection.pods, pod, cause).await;
+                    gc_report::record(GcExecution {
+                        deleted: Some(deleted),
+                        ..collection.execution(GcOutcome::EventWatcherShutdown)
+                    })
+                    .await;
+                    let _ = collection.finished.send(());
+                });
+            }
+        }
+    }
+
+    /// Handles the deadline of the pod of the given serial number. That is, the pod has either
+    /// outlived the maximum lifetime, gone unrefreshed for its TTL, or reached the end of its
+    /// grace period.
+    fn expire(&mut self, serial: u64) {
+        let collection = match self.collections.get_mut(&serial) {
+            Some(collection) => collection,
+            None => return,
+        };
+        collection.task.heartbeat();
+        let countdown = match collection.countdown.as_mut() {
+            Some(countdown) => countdown,
+            None => return,
+        };
+        let now = tokio::time::Instant::now();
+        if countdown.death.is_some_and(|death| death <= now) {
+            warn!(
+                "{} has outlived the maximum lifetime, having been refreshed {} times",
+                highlight(collection.pod.to_string()),
+                countdown.refreshes
+            );
+            let message = format!(
+                "The pod outlived the maximum lifetime of {} seconds, so it was deleted",
+                countdown.max_lifetime.unwrap_or_default()
+            );
+            let collection = self.retire(serial);
+            tokio::spawn(async move {
+                let pod = &collection.pod;
+                let cause = DeletionCause::MaxLifetimeExceeded;
+                recorder::record(pod, Reason::GarbageCollected, &message).await;
+                notifications::notify(
+                    pod,
+                    Transition::GarbageCollected,
+                    Some(&cause),
+                    Some(message),
+                );
+                let deleted = deletions::delete(&*collection.pods, pod, cause).await;
+                gc_report::record(GcExecution {
+                    deleted: Some(deleted),
+                    ..collection.execution(GcOutcome::MaxLifetimeExceeded)
+                })
+                .await;
+                let _ = collection.finished.send(());
+            });
+            return;
+        }
+        let grace = crate::reload::tunables().gc_grace_period;
+        if let (None, Some(grace)) = (countdown.imminent, grace) {
+            // The timeout has been reached, but the pod gets a warning first.
+            warn!(
+                "Garbage collection timeout reached for {}, which will be deleted in {} seconds \
+                unless it is refreshed",
+                highlight(collection.pod.to_string()),
+                grace
+            );
+            countdown.imminent = Some(now.add(tokio::time::Duration::from_secs(grace)));
+            let deadline = countdown.deadline();
+            self.deadlines.schedule(serial, deadline);
+            let message = format!(
+                "The pod went unrefreshed for its TTL of {} seconds, so it will be deleted in {} \
+                seconds unless it is refreshed",
+                collection.ttl, grace
+            );
+            collection.relabel(|labels| labels.imminent = true);
+            let pod = collection.pod.clone();
+            tokio::spawn(async move {
+                recorder::record(&pod, Reason::GcImminent, &message).await;
+                notifications::notify(&pod, Transition::GcImminent, None, Some(message));
+            });
+            return;
+        }
+        // The timeout (and any grace period) has been reached! Kill it!
+        warn!(
+            "Garbage collection timeout reached for {}",
+            highlight(collection.pod.to_string())
+        );
+        let message = format!(
+            "The pod went unrefreshed for its TTL of {} seconds, so it was deleted",
+            collection.ttl
+        );
+        let collection = self.retire(serial);
+        tokio::spawn(async move {
+            let pod = &collection.pod;
+            let cause = DeletionCause::TtlExpired;
+            recorder::record(pod, Reason::GarbageCollected, &message).await;
+            notifications::notify(
+                pod,
+                Transition::GarbageCollected,
+                Some(&cause),
+                Some(message),
+            );
+            let deleted = deletions::delete(&*collection.pods, pod, cause).await;
+            gc_report::record(GcExecution {
+                deleted: Some(deleted),
+                ..collection.execution(GcOutcome::TtlExpired)
+            })
+            .await;
+            let _ = collection.finished.send(());
+        });
+    }
+
+    /// Brings the garbage collection of the pod of the given serial number to an end, returning
+    /// its collection. Its [Finished](Finished) is yet to be resolved.
+    fn retire(&mut self, serial: u64) -> Collection {
+        self.deadlines.cancel(&serial);
+        self.collections
+            .remove(&serial)
+            .expect("a pod's collection was retired twice")
+    }
+}
+
+impl Collection {
+    /// Begins the countdown of the given (now running) pod, and answers any refresh that arrived
+    /// before it was running.
+    fn start(&mut self, running: &Pod) {
+        let max_lifetime = crate::reload::tunables().max_lifetime;
+        let countdown = Countdown {
+            keep_alive: KeepAliveTicket::new(&self.pod.name, self.ttl),
+            refreshes: 0,
+            imminent: None,
+            death: max_lifetime.map(|lifetime| death(running, lifetime)),
+            max_lifetime,
         };
-        /////////////////////////////////////////////////////////////////////////////////
-        // Phase 2: Instantiate a client and prepare a ticket for countdown.
-        //          In this phase, we listen on three events:
-        //
-        //              1. The timeout has been reached, in which case the pod is destroyed.
-        //              2. The event watcher signals that the pod has exited or been deleted,
-        //                  in which case the GC simply exits.
-        //              3. A refresh request has come in.
-        let client: Api<Pod> = client::new().await;
-        let mut keep_alive = KeepAliveTicket::new(&pod, ttl);
         info!(
             "Garbage collection for {} has been schedule. {}",
-            cyan(&pod),
-            keep_alive
+            highlight(self.pod.to_string()),
+            countdown.keep_alive
         );
-        client
-            .patch(&pod, &PatchParams::default(), &keep_alive.pod_patch())
-            .await
-            .unwrap();
-        loop {
-            let timeout = keep_alive.clone().sleep().fuse();
-            let refresh_request = self.refresh_receiver.recv().fuse();
-            let status_change = self.status.recv().fuse();
-            pin_mut!(timeout, refresh_request, status_change);
-            // This right here is the magical select statement which chooses whichever event
-            // occurs first.
-            let event = select! {
-                refresh = refresh_request => GcEvent::RefreshRequest(refresh),
-                _ = timeout => GcEvent::ExecutionDateReached,
-                status = status_change => GcEvent::PodEvent(status)
-            };
-            drop(timeout);
-            match event {
-                GcEvent::RefreshRequest(None) => {
-                    // This would be a pretty bad bug should it ever occur. Unfortunately, by
-                    // definition, it can't be communicated back to the caller because the
-                    // comms channel was dropped early.
-                    error!(
-                        "A garbage collection refresh request was sent for {}, \
-                    however its return channel was immediately dropped before a refreshed \
-                    ticket could be generated. Please review the GarbageCollector::refresh \
-                    method as this is a serious state machine violation.",
-                        cyan(&pod)
-                    );
-                }
-                GcEvent::RefreshRequest(Some(refresh)) => {
-                    // A new refresh request came in.
-                    keep_alive = KeepAliveTicket::new(&pod, ttl);
-                    match refresh.send(keep_alive.clone()) {
-                        Ok(()) => (),
-                        Err(_) => error!("Failed to send a refresh ticket over a GC channel"),
-                    };
-                    client
-                        .patch(&pod, &PatchParams::default(), &keep_alive.pod_patch())
-                        .await
-                        .unwrap();
-                    info!(
-                        "Garbage collection for {} has been refreshed. {}",
-                        cyan(&pod),
-                        keep_alive
-                    );
-                }
-                GcEvent::PodEvent(None) => {
-                    // The event listener went down without sending us a signal. This NOT
-                    // what it is suppose to do, but just to be safe let's assume that it completely
-                    // crashed and burned and now we need to be the ones to clean the pod up.
-                    warn!("The event listener for pod {} has shutdown", cyan(&pod));
-                    client.delete(&pod, &DeleteParams::default()).await.unwrap();
-                    return;
-                }
-                GcEvent::PodEvent(Some(GcStatus::Running(_))) => {
-                    // Neat? We shouldn't be receiving such superfluous signals, but it's
-                    // not an error or nothing. It's just not useful.
-                    debug!(
-                        "Garbage collector received running signal for {} in mid-operation",
-                        cyan(&pod)
-                    );
-                }
-                GcEvent::PodEvent(Some(GcStatus::Terminated)) => {
-                    // The pod has been deleted. Most commonly this is due to a client
-                    // explicitly deleting the pod through the ACM's API.
-                    debug!(
-                        "Garbage collector received termination signal for {}",
-                        cyan(&pod)
-                    );
-                    return;
-                }
-                GcEvent::ExecutionDateReached => {
-                    // The timeout has been reached! Kill it!
-                    warn!("Garbage collection timeout reached for {}", cyan(&pod));
-                    client.delete(&pod, &DeleteParams::default()).await.unwrap();
-                    return;
+        let (labels, written) = watch::channel(Labels {
+            execution_date: countdown.keep_alive.execution_date,
+            imminent: false,
+        });
+        tokio::spawn(labeler(self.pods.clone(), self.pod.clone(), written));
+        self.labels = Some(labels);
+        self.countdown = Some(countdown);
+        for refresh in std::mem::take(&mut self.queued) {
+            self.refresh(refresh);
+        }
+    }
+
+    /// Refreshes the countdown of the pod, which also spares a pod whose timeout was reached
+    /// but whose grace period is not yet over.
+    fn refresh(&mut self, refresh: RefreshRequest) {
+        let countdown = match self.countdown.as_mut() {
+            Some(countdown) => countdown,
+            None => return,
+        };
+        self.ttl = refresh.ttl.unwrap_or(self.ttl);
+        countdown.keep_alive = KeepAliveTicket::new(&self.pod.name, self.ttl);
+        countdown.refreshes += 1;
+        match refresh.reply.send(countdown.keep_alive.clone()) {
+            Ok(()) => (),
+            Err(_) => error!("Failed to send a refresh ticket over a GC channel"),
+        };
+        info!(
+            "Garbage collection for {} has been refreshed. {}",
+            highlight(self.pod.to_string()),
+            countdown.keep_alive
+        );
+        let spared = countdown.imminent.take().is_some();
+        if spared {
+            info!(
+                "{} was refreshed within its grace period and has been spared",
+                highlight(self.pod.to_string())
+            );
+        }
+        let execution_date = countdown.keep_alive.execution_date;
+        self.relabel(|labels| {
+            labels.execution_date = execution_date;
+            labels.imminent = false;
+        });
+    }
+
+    /// Changes the labels that the pod ought to bear, which its [labeler](labeler) writes in
+    /// the order that they were changed.
+    fn relabel<F: FnOnce(&mut Labels)>(&self, change: F) {
+        if let Some(labels) = self.labels.as_ref() {
+            let mut changed = *labels.borrow();
+            change(&mut changed);
+            let _ = labels.send(changed);
+        }
+    }
+
+    /// Describes a garbage collector that is exiting with the given outcome, as of right now.
+    fn execution(&self, outcome: GcOutcome) -> GcExecution {
+        let (scheduled_at, refreshes) = match self.countdown.as_ref() {
+            Some(countdown) => (countdown.keep_alive.execution_date, countdown.refreshes),
+            None => (chrono::Utc::now().timestamp(), 0),
+        };
+        GcExecution {
+            namespace: self.pod.namespace.clone(),
+            pod: self.pod.name.clone(),
+            ttl: self.ttl,
+            outcome,
+            scheduled_at,
+            executed_at: chrono::Utc::now().timestamp(),
+            refreshes,
+            deleted: None,
+            cause: None,
+        }
+    }
+}
+
+/// Labels are the labels that the garbage collector keeps upon a running pod.
+///
+/// The execution date is recorded into Kubernetes itself so that disaster recovery may happen (for
+/// example, if this ACM dies then another instance of the ACM could reconstruct a PodManager using
+/// this information), whereas the [gc_imminent](GC_IMMINENT_LABEL) label is merely a courtesy to
+/// the pod's connector.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Labels {
+    execution_date: i64,
+    imminent: bool,
+}
+
+impl Labels {
+    /// Returns a JSON merge patch that sets every one of these labels at once.
+    fn patch(&self) -> serde_json::Value {
+        serde_json::json!({
+            "metadata": {
+                "labels": {
+                    "execution_date": format!("{}", self.execution_date),
+                    GC_IMMINENT_LABEL: if self.imminent { Some("true") } else { None }
                 }
-            };
+            }
+        })
+    }
+}
+
+/// Writes the labels of the given pod, one patch at a time, for as long as its garbage collection
+/// lasts. Labels that change while a patch is underway are coalesced, so the pod always ends up
+/// bearing the latest of them. Failures are logged.
+async fn labeler(pods: Arc<dyn PodApi>, pod: PodId, mut labels: watch::Receiver<Labels>) {
+    loop {
+        let latest = *labels.borrow_and_update();
+        if let Err(err) = pods.patch(&pod.namespace, &pod.name, &latest.patch()).await {
+            warn!(
+                "Failed to label {} with its execution date of {} and {}={}, {}",
+                highlight(pod.to_string()),
+                latest.execution_date,
+                GC_IMMINENT_LABEL,
+                latest.imminent,
+                err
+            );
+        }
+        if labels.changed().await.is_err() {
+            return;
         }
     }
 }
 
-/// A RefreshRequest is channel on which a PodManager's daemon may return a new ticket
-type RefreshRequest = Sender<KeepAliveTicket>;
+/// Returns the instant at which the given pod outlives the given lifetime (in seconds). A pod's
+/// age is counted from its creation, so a pod that is adopted by a restarted ACM does not have its
+/// lifetime begin anew.
+fn death(pod: &Pod, lifetime: u64) -> tokio::time::Instant {
+    let age = pod
+        .metadata
+        .creation_timestamp
+        .as_ref()
+        .map(|created| (chrono::Utc::now() - created.0).num_seconds().max(0) as u64)
+        .unwrap_or_default();
+    tokio::time::Instant::now().add(tokio::time::Duration::from_secs(
+        lifetime.saturating_sub(age),
+    ))
+}
+
+/// A RefreshRequest asks a PodManager's daemon for a new ticket, which is returned over its
+/// `reply` channel. Should it carry a `ttl`, then that replaces the daemon's TTL.
+struct RefreshRequest {
+    ttl: Option<u64>,
+    reply: oneshot::Sender<KeepAliveTicket>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s::fake::FakePods;
+    use kube::ResourceExt;
+
+    struct Collected {
+        gc: GarbageCollector,
+        finished: Finished,
+        status: mpsc::Sender<GcStatus>,
+        /// The daemon shuts down once its registrations are dropped.
+        _registrations: mpsc::UnboundedSender<Registration>,
+    }
+
+    /// Garbage collects the given pod within a daemon of the test's own, as the shared
+    /// [DAEMON](DAEMON) outlives the runtime of whichever test happens to start it.
+    fn collect(pods: &FakePods, pod: &PodId, ttl: u64) -> Collected {
+        let mut resource = Pod::default();
+        resource.metadata.namespace = Some(pod.namespace.clone());
+        resource.metadata.name = Some(pod.name.clone());
+        pods.insert(resource);
+        let (registrations, daemon) = mpsc::unbounded_channel();
+        tokio::spawn(GarbageCollectorDaemon::default().run(daemon));
+        let (status, statuses) = mpsc::channel(1);
+        let (refresh_sender, refreshes) = mpsc::channel(1);
+        let (finished, done) = oneshot::channel();
+        let _ = registrations.send(Registration {
+            pods: Arc::new(pods.clone()),
+            pod: pod.clone(),
+            ttl,
+            status: statuses,
+            refreshes,
+            finished,
+        });
+        Collected {
+            gc: GarbageCollector { refresh_sender },
+            finished: done,
+            status,
+            _registrations: registrations,
+        }
+    }
+
+    /// Waits for the given pod to carry the given label.
+    async fn labeled(pods: &FakePods, pod: &PodId, label: &str) -> String {
+        loop {
+            let value = pods
+                .pod(&pod.namespace, &pod.name)
+                .and_then(|pod| pod.labels().get(label).cloned());
+            if let Some(value) = value {
+                return value;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    }
+
+    #[test]
+    fn refreshes_once_running() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            let pod = PodId::new("gc", "refreshes-once-running");
+            let Collected {
+                gc,
+                finished,
+                status,
+                _registrations,
+            } = collect(&pods, &pod, 3600);
+            let gc = Arc::new(gc);
+            // A refresh that arrives before the pod is running is answered once it is.
+            let queued = tokio::spawn({
+                let gc = gc.clone();
+                async move { gc.refresh(Some(7200)).await }
+            });
+            tokio::task::yield_now().await;
+            let running = pods.pod(&pod.namespace, &pod.name).unwrap();
+            status
+                .send(GcStatus::Running(Box::new(running)))
+                .await
+                .unwrap();
+            let ticket = queued.await.unwrap().unwrap();
+            assert!(ticket.execution_date >= chrono::Utc::now().timestamp() + 7199);
+            // The TTL of the queued refresh replaces the original for every later refresh.
+            let ticket = gc.refresh(None).await.unwrap();
+            assert!(ticket.execution_date >= chrono::Utc::now().timestamp() + 7199);
+            let execution_date = labeled(&pods, &pod, "execution_date").await;
+            assert!(execution_date.parse::<i64>().unwrap() > 0);
+            status.send(GcStatus::Terminated).await.unwrap();
+            finished.await.unwrap();
+            assert!(gc.refresh(None).await.is_err());
+            assert!(pods.pod(&pod.namespace, &pod.name).is_some());
+        });
+    }
+
+    #[test]
+    fn labels_in_order() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            let pod = PodId::new("gc", "labels-in-order");
+            let mut resource = Pod::default();
+            resource.metadata.namespace = Some(pod.namespace.clone());
+            resource.metadata.name = Some(pod.name.clone());
+            pods.insert(resource);
+            let (labels, written) = watch::channel(Labels {
+                execution_date: 1,
+                imminent: false,
+            });
+            let writing = tokio::spawn(labeler(Arc::new(pods.clone()), pod.clone(), written));
+            labels
+                .send(Labels {
+                    execution_date: 1,
+                    imminent: true,
+                })
+                .unwrap();
+            // A refresh that lands right after the grace period began spares the pod.
+            labels
+                .send(Labels {
+                    execution_date: 2,
+                    imminent: false,
+                })
+                .unwrap();
+            drop(labels);
+            writing.await.unwrap();
+            let pod = pods.pod(&pod.namespace, &pod.name).unwrap();
+            assert_eq!(pod.labels().get("execution_date").unwrap(), "2");
+            assert!(!pod.labels().contains_key(GC_IMMINENT_LABEL));
+        });
+    }
+
+    #[test]
+    fn shuts_down_before_running() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            let pod = PodId::new("gc", "shuts-down-before-running");
+            let collected = collect(&pods, &pod, 3600);
+            collected.status.send(GcStatus::Terminated).await.unwrap();
+            collected.finished.await.unwrap();
+            assert!(collected.gc.refresh(None).await.is_err());
+            assert!(pods.pod(&pod.namespace, &pod.name).is_some());
+        });
+    }
+
+    #[test]
+    fn shuts_down_with_the_event_watcher() {
+        tokio_test::block_on(async {
+            let pods = FakePods::default();
+            let pod = PodId::new("gc", "shuts-down-with-the-event-watcher");
+            let collected = collect(&pods, &pod, 3600);
+            // The event watcher went away before the pod ever ran, so there is nothing to delete.
+            drop(collected.status);
+            collected.finished.await.unwrap();
+            assert!(collected.gc.refresh(None).await.is_err());
+            assert!(pods.pod(&pod.namespace, &pod.name).is_some());
+        });
+    }
+}
@@ -0,0 +1,100 @@
+use crate::store;
+use kind::Kind;
+use result::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The [Store](crate::store) collection in which every [GcExecution](GcExecution) is kept.
+const COLLECTION: &str = "gc_executions";
+
+/// A GcOutcome is the reason that a garbage collector stopped watching over its pod.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(rename_all = "snake_case")]
+pub enum GcOutcome {
+    /// The pod went unrefreshed for its entire TTL and so the garbage collector deleted it.
+    TtlExpired,
+    /// The pod outlived the maximum lifetime and so the garbage collector deleted it, no matter
+    /// how recently it had been refreshed.
+    MaxLifetimeExceeded,
+    /// The pod went away by some other means (most commonly, an explicit deletion by a client).
+    Terminated,
+    /// The event watcher shut down unexpectedly and so the garbage collector deleted the pod.
+    EventWatcherShutdown,
+}
+
+/// A GcExecution is the record of a single garbage collector's lifetime, from the moment that
+/// its countdown began until the moment that it exited.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GcExecution {
+    /// The namespace of the pod. Executions recorded before tenants could be given namespaces
+    /// of their own were all within the [default](k8s::OCF_NAMESPACE).
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    pub pod: String,
+    pub ttl: u64,
+    pub outcome: GcOutcome,
+    /// The Unix timestamp at which the pod was (most recently) scheduled to be collected.
+    pub scheduled_at: i64,
+    /// The Unix timestamp at which the garbage collector actually exited.
+    pub executed_at: i64,
+    /// The number of times that the pod's TTL was refreshed.
+    pub refreshes: u64,
+    /// Whether the garbage collector's own deletion of the pod was successfully submitted.
+    /// This is `None` if the garbage collector did not delete the pod itself.
+    pub deleted: Option<bool>,
+    /// The recorded [DeletionCause](k8s::deletion::DeletionCause) of a pod that was
+    /// [Terminated](GcOutcome::Terminated), if one is known.
+    pub cause: Option<String>,
+}
+
+/// A GcReport is every [GcExecution](GcExecution) within a window of time alongside a tally
+/// of their outcomes.
+#[derive(Serialize, Kind, Debug)]
+pub struct GcReport {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub outcomes: BTreeMap<GcOutcome, usize>,
+    pub executions: Vec<GcExecution>,
+}
+
+/// Records the given execution into the [Store](crate::store). Failures are logged rather than
+/// returned as the garbage collector has nobody left to report them to.
+pub async fn record(execution: GcExecution) {
+    if let Err(err) = store::append(COLLECTION, &execution).await {
+        error!(
+            "Failed to record the garbage collection of {}/{}, {}",
+            execution.namespace, execution.pod, err
+        );
+    }
+}
+
+/// Returns every recorded execution that exited within `[from, to]` (Unix timestamps, where
+/// either bound may be omitted).
+pub async fn report(from: Option<i64>, to: Option<i64>) -> Result<GcReport> {
+    let executions: Vec<GcExecution> = store::scan(COLLECTION, |execution: &GcExecution| {
+        within(execution.executed_at, from, to)
+    })
+    .await?;
+    Ok(GcReport {
+        from,
+        to,
+        outcomes: tally(&executions),
+        executions,
+    })
+}
+
+fn default_namespace() -> String {
+    k8s::OCF_NAMESPACE.to_string()
+}
+
+fn within(timestamp: i64, from: Option<i64>, to: Option<i64>) -> bool {
+    from.is_none_or(|from| timestamp >= from) && to.is_none_or(|to| timestamp <= to)
+}
+
+fn tally(executions: &[GcExecution]) -> BTreeMap<GcOutcome, usize> {
+    let mut outcomes = BTreeMap::new();
+    for execution in executions {
+        *outcomes.entry(execution.outcome).or_insert(0) += 1;
+    }
+    outcomes
+}
@@ -0,0 +1,219 @@
+use super::PodId;
+use error::*;
+use result::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::iter::FromIterator;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use term_colors::*;
+use tokio::sync::RwLock;
+
+/// The maximum number of transitions that are remembered of any one pod. Once exceeded, the
+/// oldest transition is forgotten first.
+pub const MAXIMUM_TRANSITIONS: usize = 64;
+
+/// The maximum number of histories that are remembered of pods whose PodManager has been torn
+/// down. Once exceeded, the oldest history is forgotten first.
+pub const MAXIMUM_RETIRED_HISTORIES: usize = 1024;
+
+/// The `.metadata.annotations` key under which a pod's history is
+/// [persisted](config::acm::AcmConfig::persist_event_history).
+pub const HISTORY_ANNOTATION: &str = "ocf.alation.com/history";
+
+lazy_static! {
+    static ref HISTORIES: RwLock<Histories> = RwLock::new(Histories::default());
+}
+
+/// A Lifecycle is a transition in the life of a managed pod, as observed by its
+/// [event watcher](super::event_watcher).
+#[derive(Serialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Lifecycle {
+    /// Kubernetes accepted the pod and queued it for scheduling.
+    Added,
+    /// The pod could not be scheduled onto any node within the
+    /// [scheduling timeout](config::acm::AcmTunables::scheduling_timeout).
+    Unschedulable,
+    /// The pod entered its `Running` phase.
+    Running,
+    /// The connector responded to its health check.
+    HealthCheckPassed,
+    /// The connector did not respond to its health check in time.
+    HealthCheckFailed,
+    /// The connector exited before it ever came online.
+    Crashed,
+    /// The connector's image could not be pulled.
+    ImagePullFailed,
+    /// Kubernetes could not create or start the connector's container (such as for a missing
+    /// Secret).
+    FailedToStart,
+    /// Kubernetes evicted the pod.
+    Evicted,
+    /// The connector restarted more often than its deploy allowed (which, by default, is never).
+    Rebooted,
+    /// The connector restarted, as its deploy allowed it to.
+    Restarted,
+    /// The connector stopped passing its health probes (or reported itself unhealthy) after it
+    /// came online.
+    Unresponsive,
+    /// The pod was deleted (or its deletion was submitted).
+    Deleted,
+}
+
+/// A Transition is a single, timestamped, [Lifecycle](Lifecycle) transition of a pod alongside
+/// any detail that was known about it (an error message, a deletion cause, etc.).
+#[derive(Serialize, Clone, Debug)]
+pub struct Transition {
+    /// The Unix timestamp of when the transition was observed.
+    pub at: i64,
+    pub event: Lifecycle,
+    pub detail: Option<String>,
+}
+
+/// An EventHistory is the view of every remembered transition of a single pod, oldest first.
+#[derive(Serialize, Kind, Debug)]
+pub struct EventHistory {
+    /// The pod (as `<namespace>/<name>`).
+    pub pod: String,
+    /// Whether the pod's PodManager has been torn down, in which case no more transitions
+    /// will be recorded.
+    pub retired: bool,
+    /// The number of times that the connector was [restarted](Lifecycle::Restarted), which is
+    /// counted even once the transitions themselves have been forgotten.
+    pub restarts: usize,
+    pub transitions: Vec<Transition>,
+}
+
+/// A History is a handle onto the bounded record of a single pod's transitions. Handles are cheap
+/// to clone and every clone records into the same history.
+#[derive(Clone)]
+pub struct History {
+    pod: PodId,
+    transitions: Arc<Mutex<VecDeque<Transition>>>,
+    restarts: Arc<AtomicUsize>,
+}
+
+impl History {
+    /// Records the given transition, forgetting the oldest transition should there now be more
+    /// than [MAXIMUM_TRANSITIONS](MAXIMUM_TRANSITIONS).
+    pub async fn record(&self, event: Lifecycle, detail: Option<String>) {
+        if event == Lifecycle::Restarted {
+            self.restarts.fetch_add(1, Ordering::SeqCst);
+        }
+        let transitions = {
+            let mut transitions = self.transitions.lock().unwrap();
+            if transitions.len() >= MAXIMUM_TRANSITIONS {
+                transitions.pop_front();
+            }
+            transitions.push_back(Transition {
+                at: chrono::Utc::now().timestamp(),
+                event,
+                detail,
+            });
+            transitions.iter().cloned().collect::<Vec<Transition>>()
+        };
+        if crate::env::config().persist_event_history {
+            self.persist(&transitions).await;
+        }
+    }
+
+    fn transitions(&self) -> Vec<Transition> {
+        self.transitions.lock().unwrap().iter().cloned().collect()
+    }
+
+    async fn persist(&self, transitions: &[Transition]) {
+        let history = match serde_json::to_string(transitions) {
+            Ok(history) => history,
+            Err(err) => {
+                error!(
+                    "Failed to serialize the history of pod {}, {}",
+                    highlight(self.pod.to_string()),
+                    err
+                );
+                return;
+            }
+        };
+        let annotations = BTreeMap::from_iter([(HISTORY_ANNOTATION.to_string(), history)]);
+        if let Err(err) = k8s::annotate(&self.pod.namespace, &self.pod.name, annotations).await {
+            warn!(
+                "Failed to persist the history of pod {}, {}",
+                highlight(self.pod.to_string()),
+                err
+            );
+        }
+    }
+}
+
+/// `Histories` holds the history of every pod that currently has a PodManager, as well as those of
+/// recently retired PodManagers so that a pod may still be troubleshot after it is gone.
+#[derive(Default)]
+struct Histories {
+    live: HashMap<PodId, History>,
+    retired: HashMap<PodId, History>,
+    order: VecDeque<PodId>,
+}
+
+/// Opens a new, empty, history for the given pod.
+pub async fn open(pod: &PodId) -> History {
+    let history = History {
+        pod: pod.clone(),
+        transitions: Arc::new(Mutex::new(VecDeque::new())),
+        restarts: Arc::new(AtomicUsize::new(0)),
+    };
+    let mut histories = HISTORIES.write().await;
+    histories.retired.remove(pod);
+    histories.order.retain(|retired| retired != pod);
+    histories.live.insert(pod.clone(), history.clone());
+    history
+}
+
+/// Retires the history of the given pod once its PodManager has been torn down. Retired histories
+/// are remembered until [MAXIMUM_RETIRED_HISTORIES](MAXIMUM_RETIRED_HISTORIES) newer ones are.
+pub async fn retire(pod: &PodId) {
+    let mut histories = HISTORIES.write().await;
+    let history = match histories.live.remove(pod) {
+        Some(history) => history,
+        None => return,
+    };
+    if histories.order.len() >= MAXIMUM_RETIRED_HISTORIES {
+        if let Some(oldest) = histories.order.pop_front() {
+            histories.retired.remove(&oldest);
+        }
+    }
+    histories.order.push_back(pod.clone());
+    histories.retired.insert(pod.clone(), history);
+}
+
+/// Returns the history of the given pod, or a [HistoryNotFound](HistoryNotFound) should none
+/// be remembered.
+pub async fn of(pod: &PodId) -> Result<EventHistory> {
+    let histories = HISTORIES.read().await;
+    let (history, retired) = match (histories.live.get(pod), histories.retired.get(pod)) {
+        (Some(history), _) => (history, false),
+        (None, Some(history)) => (history, true),
+        (None, None) => {
+            return Err(HistoryNotFound {
+                id: pod.to_string(),
+            }
+            .into())
+        }
+    };
+    Ok(EventHistory {
+        pod: pod.to_string(),
+        retired,
+        restarts: history.restarts.load(Ordering::SeqCst),
+        transitions: history.transitions(),
+    })
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::NotFound)]
+#[error(
+    "No event history is held for the pod {id}. Either this ACM (Alation Connector Manager) never \
+managed the pod, or the pod was torn down long enough ago that its history has been forgotten."
+)]
+#[error_code("ACM-2400")]
+pub struct HistoryNotFound {
+    id: String,
+}
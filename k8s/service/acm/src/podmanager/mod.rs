@@ -1,26 +1,113 @@
+use admission::Admission;
 use error::*;
-use event_watcher::EventWatcher;
+use event_watcher::{EventWatcher, Terminator};
 use external_handle::PodManagerUpperHandle;
 use garbage_collector::GarbageCollector;
 use garbage_collector::KeepAliveTicket;
+use k8s::deletion::DeletionCause;
+use k8s::display::DisplayName;
+use k8s::PodApi;
 use k8s_openapi::api::core::v1::Pod;
+use kube::ResourceExt;
+use notifications::Transition;
 use result::Result;
 use serde::Serialize;
-use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::sync::Arc;
+use tasks::Task;
 use term_colors::*;
 use tokio::join;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::Mutex;
 
+pub mod admission;
 pub mod adoption;
+pub mod archive;
+pub mod deadlines;
+pub mod deletions;
 pub mod event_watcher;
 pub mod external_handle;
 pub mod garbage_collector;
+pub mod gc_report;
+pub mod history;
+pub mod notifications;
+pub mod recorder;
+pub mod refreshes;
+pub mod registry;
 pub mod server_check;
+pub mod tasks;
+pub mod tickets;
+pub mod usage;
 
 lazy_static! {
-    static ref POD_MANAGER_CACHE: RwLock<HashMap<String, Arc<Mutex<PodManager>>>> =
-        RwLock::new(HashMap::new());
+    static ref POD_MANAGERS: registry::Registry = registry::Registry::default();
+}
+
+/// Returns the [PodApi](k8s::PodApi) through which every PodManager (and everything else that
+/// deploys or deletes connector pods) reaches the API server. Should the ACM have been built with
+/// the `faults` feature, then the [faults](crate::faults) switched on by an operator are injected
+/// into it.
+pub async fn pods() -> Arc<dyn PodApi> {
+    let pods = k8s::client::pods().await;
+    #[cfg(feature = "faults")]
+    let pods = crate::faults::inject(pods);
+    pods
+}
+
+/// A PodId is the namespace qualified name of a managed pod.
+///
+/// Pods of the same name may exist within different tenant [namespaces](crate::tenancy), so every
+/// PodManager (as well as the record of [recent deletions](deletions)) is keyed by PodId rather
+/// than by the pod's name alone.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct PodId {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl PodId {
+    pub fn new<N: Into<String>, P: Into<String>>(namespace: N, name: P) -> PodId {
+        PodId {
+            namespace: namespace.into(),
+            name: name.into(),
+        }
+    }
+
+    /// Returns the PodId of the pod of the given name (as given by a client), which must be a valid
+    /// [RFC 1123 subdomain](names::is_valid_rfc1123_subdomain) (as every pod's name is), otherwise
+    /// an [InvalidName](names::InvalidName) is returned.
+    pub fn parse<N: Into<String>, P: Into<String>>(namespace: N, name: P) -> Result<PodId> {
+        let name = names::Rfc1123Subdomain::new(name)?;
+        Ok(PodId::new(namespace, name))
+    }
+
+    /// Returns the PodId of the given pod, which is presumed to be within the
+    /// [default](k8s::OCF_NAMESPACE) namespace should it not name one.
+    pub fn of(pod: &Pod) -> PodId {
+        PodId {
+            namespace: pod
+                .namespace()
+                .unwrap_or_else(|| k8s::OCF_NAMESPACE.to_string()),
+            name: pod.name(),
+        }
+    }
+}
+
+/// A PodId is displayed as `<namespace>/<name>`, as per `kubectl`.
+impl Display for PodId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.namespace, self.name)
+    }
+}
+
+/// A ManagedPod is an entry in the [registry](registry::Registry) of PodManagers.
+///
+/// The [Terminator](event_watcher::Terminator) for the pod is kept alongside (rather than within)
+/// the PodManager so that a [delete](PodManager::delete) requires no lock upon the PodManager.
+#[derive(Clone)]
+struct ManagedPod {
+    manager: Arc<Mutex<PodManager>>,
+    terminator: Terminator,
+    display: DisplayName,
 }
 
 /// A PodManager holds two handles - one into the [garbage collection](GarbageCollector) daemon for a give pod
@@ -32,27 +119,72 @@ lazy_static! {
 ///
 /// 1. They may [wait](PodManager::wait) for a pod to become active.
 /// 2. They may [refresh](PodManager::refresh) the time-to-live for a given pod.
+/// 3. They may [delete](PodManager::delete) the pod.
 pub struct PodManager {
+    id: PodId,
     gc_handle: GarbageCollector,
     event_watcher_handle: PodManagerUpperHandle,
 }
 
 impl PodManager {
     /// Retrieves the PodManager at the given ID should it exist. Should the PodManager
-    /// not exist, then an Err([PodManagerNotFound](PodManagerNotFound)) is returned.
+    /// not exist, then an Err([PodManagerNotFound](PodManagerNotFound)) is returned. However,
+    /// if the pod is known to have been recently deleted, then an Err([PodWasDeleted](PodWasDeleted))
+    /// describing the [cause](k8s::deletion::DeletionCause) of the deletion is returned instead
+    /// (or an Err([MaxLifetimeExceeded](MaxLifetimeExceeded)) should the pod have outlived the
+    /// maximum lifetime).
+    pub async fn get(id: &PodId) -> Result<Arc<Mutex<PodManager>>> {
+        let manager = POD_MANAGERS.get(id).await.map(|managed| managed.manager);
+        match manager {
+            Some(manager) => Ok(manager),
+            None => match deletions::cause_of(id).await {
+                Some(cause) => Err(was_deleted(id, cause)),
+                None => Err(PodManagerNotFound { id: id.to_string() }.into()),
+            },
+        }
+    }
+
+    /// Retrieves the PodManager of the pod that the given ticket (within the given namespace) was
+    /// issued for, exactly as [get](PodManager::get) does. However, should the ticket not have been
+    /// issued by this ACM, or should it have expired, then the ticket is [refused](tickets::redeem).
+    pub async fn get_by_ticket<N: Into<String>>(
+        namespace: N,
+        ticket: &str,
+    ) -> Result<Arc<Mutex<PodManager>>> {
+        PodManager::get(&PodId::new(namespace, tickets::redeem(ticket)?)).await
+    }
+
+    /// Deletes the pod at the given ID on behalf of a user. This procedure is idempotent.
+    ///
+    /// Should this ACM be managing the pod, then the deletion goes through the pod's
+    /// [event watcher](event_watcher::Terminator). Any in-flight [wait](PodManager::wait) is
+    /// resolved immediately with a PodDeleted error and the garbage collector is shut down BEFORE
+    /// the pod is deleted in Kubernetes, which prevents a concurrent refresh from racing the deletion.
+    ///
+    /// Otherwise (the pod is unknown to this ACM or its event watcher has already exited) the pod
+    /// is simply deleted in Kubernetes directly (by way of the given [PodApi](k8s::PodApi)).
     ///
-    pub async fn get<T: AsRef<str>>(id: T) -> Result<Arc<Mutex<PodManager>>> {
-        POD_MANAGER_CACHE
-            .read()
-            .await
-            .get(id.as_ref())
-            .cloned()
-            .ok_or_else(|| {
-                PodManagerNotFound {
-                    id: id.as_ref().to_string(),
-                }
-                .into()
-            })
+    /// Either way, the lifecycle webhooks are [notified](notifications) that the pod was
+    /// [Deleted](Transition::Deleted).
+    pub async fn delete(pods: &dyn PodApi, id: &PodId) -> Result<()> {
+        let cause = DeletionCause::User;
+        let terminator = POD_MANAGERS.get(id).await.map(|managed| managed.terminator);
+        if let Some(terminator) = terminator {
+            if terminator.delete().await {
+                info!("Deleting pod {}", highlight(id.to_string()));
+                notifications::notify(id, Transition::Deleted, Some(&cause), None::<String>);
+                return Ok(());
+            }
+        }
+        match k8s::delete(pods, &id.namespace, &id.name, cause.clone()).await? {
+            either::Left(_) => {
+                deletions::record(id, cause.clone()).await;
+                info!("Deleting pod {}", highlight(id.to_string()));
+                notifications::notify(id, Transition::Deleted, Some(&cause), None::<String>);
+            }
+            either::Right(_) => info!("Pod {} was already deleted", highlight(id.to_string())),
+        }
+        Ok(())
     }
 
     /// Instantiates a new PodManager. The PodManager that is created is NOT returned by this
@@ -61,30 +193,54 @@ impl PodManager {
     ///
     /// The `ttl` provided will be used as the initial value for the TTL in the garbage collector
     /// that will be spun up to back this new PodManager. If no specific TTL is desired, then
-    /// one may use the [DEFAULT_TTL](garbage_collector::DEFAULT_TTL) defined in the garbage
-    /// collector module.
-    pub async fn new_podmanager<T: AsRef<str>>(id: T, ttl: u64) {
+    /// one may use the [default TTL](config::acm::AcmTunables::default_ttl) of the ACM's
+    /// [tunables](crate::reload::tunables).
+    ///
+    /// Every PodManager occupies the slot reserved by the given [Admission](admission::admit),
+    /// which is released only once all of its coroutines have shut down. As such, the
+    /// [maximum](admission::maximum) number of PodManagers bounds every PodManager that this
+    /// ACM holds, including those still being torn down.
+    ///
+    /// Every operation that the PodManager makes upon its pod goes through the given
+    /// [PodApi](k8s::PodApi). The connector may be [restarted](crate::restarts) up to `restarts`
+    /// times before it is declared to have failed.
+    pub async fn new_podmanager(
+        pods: Arc<dyn PodApi>,
+        pod: PodId,
+        ttl: u64,
+        admission: Admission,
+        display: DisplayName,
+        restarts: u32,
+    ) {
         // @TODO the object graph here could use some cleanup. The design pattern is
         // ALMOST consistent across the whole multiple components that comprise a Podmanager,
         // but not quite.
-        let pod = id.as_ref().to_string();
-        // pm_to_ew_send/recv is a pair of pseudo channels that are used for an external client
-        // to reach through a PodManager and retrieve a "wait" result from the EventWatcher.
-        // The returned "shim" is simply a coroutine that spinning that is maintaining this
-        // communicate channel between the two objects. As such, a reference to it needs to be
-        // held onto and eventually "joined" on to make sure that all PodManager coroutines
-        // shutdown everytime.
-        let (pm_to_ew_send, pm_to_ew_recv, shim) = PodManagerUpperHandle::new();
+        // pm_to_ew_send/recv is the channel pair that is used for an external client to reach
+        // through a PodManager and retrieve a "wait" result from the EventWatcher. The result is
+        // retained within the channel for as long as the PodManager lives.
+        let (pm_to_ew_send, pm_to_ew_recv) = PodManagerUpperHandle::new();
         // ew_to_gc_send/recv is the channel pair used for the EventWatcher to communicate to
         // the GarbageCollector. The EventWatcher gets the sending end of the channel and the
         // GarbageCollector gets the receiving end.
         let (ew_to_gc_send, ew_to_gc_recv) = tokio::sync::mpsc::channel(100);
+        // Every transition that the EventWatcher observes is recorded into the pod's history,
+        // which outlives the PodManager so that the pod may be troubleshot after it is gone.
+        let history = history::open(&pod).await;
         // Lets get our EventWatcher. This is a coroutine that needs to be eventually joined.
-        let watcher_handle = EventWatcher::new_watcher(pod.clone(), ew_to_gc_send, pm_to_ew_recv);
-        // Lets get our GarbageCollector. The "gc" is a facade into the actual garbage collector
-        // while the "gc_handle" is a coroutine that needs to be eventually joined.
-        let (gc, gc_handle) = GarbageCollector::new(ew_to_gc_recv, pod.clone(), ttl);
+        let (terminator, watcher_handle) = EventWatcher::new_watcher(
+            pods.clone(),
+            pod.clone(),
+            ew_to_gc_send,
+            pm_to_ew_recv,
+            history,
+            restarts,
+        );
+        // Lets get our GarbageCollector. The "gc" is a facade into the single garbage collection
+        // daemon while "gc_finished" resolves once the pod's garbage collection has come to an
+        // end, and so needs to be eventually joined.
+        let (gc, gc_finished) = GarbageCollector::new(pods, ew_to_gc_recv, pod.clone(), ttl);
         let manager = PodManager {
+            id: pod.clone(),
             gc_handle: gc,
             event_watcher_handle: pm_to_ew_send,
         };
@@ -97,37 +253,58 @@ impl PodManager {
         // very useful for easily identifying whether or not all coroutines are successfully
         // shutting down and getting cleaned up. That is to say, once this ACM has gone entirely
         // idle, then this log entry MUST report that the number pod managers present eventually
-        // winds down to zero. Otherwise, their is likely a rouge runtime somewhere.
+        // winds down to zero. Otherwise, their is likely a rouge runtime somewhere. Every
+        // coroutine registers itself with the [task registry](tasks), so such a runtime may
+        // also be found directly via the `/admin/tasks` endpoint.
         tokio::spawn(async move {
             let pod = p;
-            let (_, _, _) = join!(watcher_handle, gc_handle, shim);
-            let left_alive = {
-                let mut managers = POD_MANAGER_CACHE.write().await;
-                managers.remove(&pod);
-                managers.len()
-            };
+            let _task = Task::register("reaper", &pod);
+            let (_, _) = join!(watcher_handle, gc_finished);
+            let left_alive = POD_MANAGERS.remove(&pod).await;
+            history::retire(&pod).await;
+            drop(admission);
             debug!(
                 "PodManager for {} has been successfully cleaned up, {} are still alive",
-                cyan(&pod),
+                highlight(pod.to_string()),
                 left_alive
             );
         });
-        POD_MANAGER_CACHE
-            .write()
-            .await
-            .insert(pod.clone(), Arc::new(Mutex::new(manager)));
+        POD_MANAGERS
+            .insert(
+                pod.clone(),
+                ManagedPod {
+                    manager: Arc::new(Mutex::new(manager)),
+                    terminator,
+                    display,
+                },
+            )
+            .await;
     }
 
-    /// Refreshes the TTL in the garbage collector for the pod managed by this PodManager.
+    /// Refreshes the TTL in the garbage collector for the pod managed by this PodManager, replacing
+    /// the pod's TTL with the given one (if any).
     ///
-    /// This is a straight passthroughs to [GarbageCollector::refresh](GarbageCollector::refresh).
-    pub async fn refresh(&self) -> Result<KeepAliveTicket> {
-        self.gc_handle.refresh().await
+    /// This is a passthrough to [GarbageCollector::refresh](GarbageCollector::refresh). However,
+    /// should the garbage collector have already shut down because the pod was deleted, then a
+    /// [PodWasDeleted](PodWasDeleted) (or a [MaxLifetimeExceeded](MaxLifetimeExceeded)) is
+    /// returned rather than the garbage collector's own error.
+    pub async fn refresh(&self, ttl: Option<u64>) -> Result<KeepAliveTicket> {
+        match self.gc_handle.refresh(ttl).await {
+            Ok(ticket) => Ok(ticket),
+            Err(err) => match deletions::cause_of(&self.id).await {
+                Some(cause) => Err(was_deleted(&self.id, cause)),
+                None => Err(err),
+            },
+        }
     }
 
     /// Waits for the pod to either become active or to be considered "ill-behaved".
-    pub async fn wait(&mut self) -> Result<Pod> {
-        self.event_watcher_handle.wait().await
+    ///
+    /// The returned future does not borrow the PodManager, so the PodManager's lock need not be
+    /// held for the duration of the wait. Any number of clients may wait on the same pod at once,
+    /// and waiting on a pod whose wait has already resolved returns the very same result.
+    pub fn wait(&self) -> impl std::future::Future<Output = Result<Pod>> {
+        self.event_watcher_handle.clone().wait()
     }
 }
 
@@ -135,15 +312,57 @@ impl PodManager {
 #[code(Status::NotFound)]
 #[error(
     "The pod manager for {id} could not be found. If an error occurred in the requested pod \
-then the caller has one hour to consume the message before the record is dropped. This message \
-may also only be consumed once. Alternatively, the calling client may have been configured for \
-the incorrect ACM (Alation Connection Manager) that was not in possession of the requested \
-pod manager."
+then the caller has one hour to consume the message before the record is dropped. \
+Alternatively, the calling client may have been configured for the incorrect ACM (Alation \
+Connection Manager) that was not in possession of the requested pod manager."
 )]
+#[error_code("ACM-1001")]
 pub struct PodManagerNotFound {
     id: String,
 }
 
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::Gone)]
+#[error(
+    "The pod {id} has been deleted and its pod manager has been torn down. The recorded cause \
+of the deletion was '{cause}'. A cause of 'user' means that a client explicitly deleted the pod, \
+'ttl_expired' means that the pod was garbage collected because it was not refreshed in time, \
+'max_lifetime_exceeded' means that the pod was garbage collected because it outlived the maximum \
+lifetime, \
+'ill_behaved{{..}}' means that the ACM (Alation Connector Manager) deleted the pod for the given \
+reason, and 'eviction' means that Kubernetes evicted the pod."
+)]
+#[error_code("ACM-1002")]
+pub struct PodWasDeleted {
+    id: String,
+    cause: String,
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::Gone)]
+#[error(
+    "The pod {id} outlived the maximum lifetime that the ACM (Alation Connector Manager) allows \
+of any connector, and so it was garbage collected regardless of its TTL having been refreshed. \
+Please deploy a new pod in its place."
+)]
+#[error_code("ACM-1004")]
+pub struct MaxLifetimeExceeded {
+    id: String,
+}
+
+/// Describes the deletion of the given pod for the given cause to a client who is still
+/// asking after the pod.
+fn was_deleted(id: &PodId, cause: DeletionCause) -> Box<dyn AcmError> {
+    match cause {
+        DeletionCause::MaxLifetimeExceeded => MaxLifetimeExceeded { id: id.to_string() }.into(),
+        cause => PodWasDeleted {
+            id: id.to_string(),
+            cause: cause.to_string(),
+        }
+        .into(),
+    }
+}
+
 /// A PodTicket is the simple combination of a pod strucutre as returned by
 /// the Kubernetes API server and a [KeepAliveTicker](garbage_collector::KeepAliveTicket).
 #[derive(Serialize, Kind)]
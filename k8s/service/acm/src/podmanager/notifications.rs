@@ -0,0 +1,216 @@
+//! Notifications tell the configured
+//! [lifecycle webhooks](config::acm::AcmConfig::lifecycle_webhooks) of the lifecycle transitions
+//! of managed pods (their becoming ready, crashing, being about to be garbage collected, being
+//! garbage collected, becoming unresponsive, and being deleted by a user), so that the likes of
+//! Alation's job scheduler may react to them rather than poll the ACM. Every transition that ends
+//! with the pod being deleted carries the [cause](k8s::deletion::DeletionCause) of its deletion.
+//!
+//! Every notification is POSTed as JSON and signed with an HMAC-SHA256 of its body, keyed by the
+//! [LIFECYCLE_WEBHOOK_SECRET](config::acm::AcmConfig::lifecycle_webhook_secret), within the
+//! [SIGNATURE_HEADER](SIGNATURE_HEADER). Deliveries are retried under the
+//! [webhook](retry::Policy::Webhook) policy, and those that are given up on are appended to the
+//! dead letter collection of the ACM's [store](crate::store) rather than lost.
+use super::PodId;
+use crate::{hmac, store};
+use k8s::deletion::DeletionCause;
+use kind::Kind;
+use result::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use term_colors::*;
+
+/// The header that bears the signature of a notification, as `sha256=<hex encoded HMAC>`.
+pub const SIGNATURE_HEADER: &str = "X-OCF-Signature";
+
+/// The header that bears the [Transition](Transition) of a notification.
+pub const TRANSITION_HEADER: &str = "X-OCF-Transition";
+
+/// The [Store](crate::store) collection in which undeliverable notifications are kept.
+const DEAD_LETTERS: &str = "webhook_dead_letters";
+
+/// The maximum amount of time that a single delivery attempt may take.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    static ref CLIENT: reqwest::Client = reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .expect("failed to build the lifecycle webhook client");
+}
+
+static DELIVERED: AtomicU64 = AtomicU64::new(0);
+static DEAD_LETTERED: AtomicU64 = AtomicU64::new(0);
+
+/// A Transition is a change in the lifecycle of a managed pod that webhooks are notified of.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Transition {
+    /// The pod passed its server health check and its connector is ready to serve.
+    Ready,
+    /// The pod's connector crashed (or restarted), and so the pod was deleted.
+    Crashed,
+    /// The pod's TTL expired, and so the garbage collector will delete it at the end of the
+    /// [grace period](config::acm::AcmTunables::gc_grace_period) unless it is refreshed.
+    GcImminent,
+    /// The garbage collector deleted the pod.
+    GarbageCollected,
+    /// The pod's connector stopped passing its health probes (or reported itself unhealthy) after
+    /// it came online, and so the pod was deleted.
+    Unresponsive,
+    /// A user deleted the pod.
+    Deleted,
+}
+
+impl Display for Transition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transition::Ready => write!(f, "ready"),
+            Transition::Crashed => write!(f, "crashed"),
+            Transition::GcImminent => write!(f, "gc_imminent"),
+            Transition::GarbageCollected => write!(f, "garbage_collected"),
+            Transition::Unresponsive => write!(f, "unresponsive"),
+            Transition::Deleted => write!(f, "deleted"),
+        }
+    }
+}
+
+/// A Notification is the body POSTed to every lifecycle webhook.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Notification {
+    /// Identifies the notification, which is the same across every retry of its delivery so that
+    /// receivers may discard duplicates.
+    pub id: String,
+    pub transition: Transition,
+    pub namespace: String,
+    pub pod: String,
+    /// The Unix timestamp of the transition.
+    pub at: i64,
+    /// Why the pod was deleted, should the transition have deleted it.
+    pub cause: Option<DeletionCause>,
+    /// Further detail of the transition (such as the reason that a connector crashed), if any.
+    pub detail: Option<String>,
+}
+
+/// A DeadLetter is a notification that could not be delivered to a webhook within the allowance
+/// of the [webhook](retry::Policy::Webhook) retry policy.
+#[derive(Serialize, Deserialize, Kind, Clone, Debug)]
+pub struct DeadLetter {
+    pub webhook: String,
+    /// The Unix timestamp at which delivery was given up on.
+    pub failed_at: i64,
+    /// The failure of the last delivery attempt.
+    pub error: String,
+    pub notification: Notification,
+}
+
+/// Notifies every lifecycle webhook of the given transition of the given pod, which deleted the pod
+/// for the given cause (if any).
+///
+/// Deliveries are made in the background, so this returns immediately and never fails.
+pub fn notify<D: ToString>(
+    pod: &PodId,
+    transition: Transition,
+    cause: Option<&DeletionCause>,
+    detail: Option<D>,
+) {
+    let webhooks = &crate::env::config().lifecycle_webhooks;
+    if webhooks.is_empty() {
+        return;
+    }
+    let notification = Notification {
+        id: format!("{:016x}", rand::random::<u64>()),
+        transition,
+        namespace: pod.namespace.clone(),
+        pod: pod.name.clone(),
+        at: chrono::Utc::now().timestamp(),
+        cause: cause.cloned(),
+        detail: detail.map(|detail| detail.to_string()),
+    };
+    for webhook in webhooks {
+        tokio::spawn(deliver(webhook.clone(), notification.clone()));
+    }
+}
+
+/// Returns every dead letter, in the order in which they were given up on.
+pub async fn dead_letters() -> Result<Vec<DeadLetter>> {
+    store::scan(DEAD_LETTERS, |_: &DeadLetter| true).await
+}
+
+/// Returns the number of notifications that were delivered, and that were dead lettered, since
+/// startup.
+pub fn deliveries() -> (u64, u64) {
+    (
+        DELIVERED.load(Ordering::Relaxed),
+        DEAD_LETTERED.load(Ordering::Relaxed),
+    )
+}
+
+/// Delivers the given notification to the given webhook, retrying under the
+/// [webhook](retry::Policy::Webhook) policy before dead lettering it.
+async fn deliver(webhook: String, notification: Notification) {
+    let body = match serde_json::to_vec(&notification) {
+        Ok(body) => body,
+        Err(err) => {
+            error!("Failed to serialize a lifecycle notification, {}", err);
+            return;
+        }
+    };
+    let signature = match &crate::env::config().lifecycle_webhook_secret {
+        Some(secret) => hmac::sign(secret.raw_secret().trim().as_bytes(), &body),
+        None => return,
+    };
+    let attempt = || async {
+        let response = CLIENT
+            .post(&webhook)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+            .header(TRANSITION_HEADER, notification.transition.to_string())
+            .body(body.clone())
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(format!("the webhook responded with {}", status))
+        }
+    };
+    match retry::retry_async(retry::Policy::Webhook, attempt).await {
+        Ok(()) => {
+            DELIVERED.fetch_add(1, Ordering::Relaxed);
+            debug!(
+                "Notified {} that pod {}/{} is {}",
+                highlight(webhook.clone()),
+                notification.namespace,
+                notification.pod,
+                notification.transition
+            );
+        }
+        Err(err) => {
+            DEAD_LETTERED.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "Gave up notifying {} that pod {}/{} is {}, {}",
+                highlight(webhook.clone()),
+                notification.namespace,
+                notification.pod,
+                notification.transition,
+                err
+            );
+            let letter = DeadLetter {
+                webhook,
+                failed_at: chrono::Utc::now().timestamp(),
+                error: err,
+                notification,
+            };
+            if let Err(err) = store::append(DEAD_LETTERS, &letter).await {
+                error!(
+                    "Failed to record an undeliverable lifecycle notification, {}",
+                    err
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! The recorder posts Kubernetes Events upon connector pods for the decisions that the ACM makes
+//! about them (such as deleting them), so that those decisions show up within
+//! `kubectl describe pod` rather than only within the ACM's own logs.
+use super::PodId;
+use k8s::events::{EventType, Recorder};
+
+/// The component that the ACM's Events are reported by.
+const COMPONENT: &str = "acm";
+
+lazy_static! {
+    static ref RECORDER: Recorder = Recorder::new(COMPONENT);
+}
+
+/// The reason of an Event, as shown by `kubectl describe pod`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Reason {
+    /// The garbage collector will delete the pod once its grace period is over.
+    GcImminent,
+    /// The garbage collector deleted the pod.
+    GarbageCollected,
+    /// The pod failed its server health check, and so was deleted as ill-behaved.
+    HealthCheckFailed,
+    /// The pod's container restarted, and so was deleted rather than left to crash loop.
+    PodRebooted,
+    /// The pod's container restarted, as its deploy allowed it to.
+    PodRestarted,
+    /// The pod's connector became unresponsive after it came online, and so was deleted as
+    /// ill-behaved.
+    ConnectorUnresponsive,
+}
+
+impl Reason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Reason::GcImminent => "GcImminent",
+            Reason::GarbageCollected => "GarbageCollected",
+            Reason::HealthCheckFailed => "HealthCheckFailed",
+            Reason::PodRebooted => "PodRebooted",
+            Reason::PodRestarted => "PodRestarted",
+            Reason::ConnectorUnresponsive => "ConnectorUnresponsive",
+        }
+    }
+
+    fn event_type(&self) -> EventType {
+        match self {
+            Reason::GarbageCollected => EventType::Normal,
+            Reason::GcImminent
+            | Reason::HealthCheckFailed
+            | Reason::PodRebooted
+            | Reason::PodRestarted
+            | Reason::ConnectorUnresponsive => EventType::Warning,
+        }
+    }
+}
+
+/// Posts an Event of the given reason upon the given pod. Failing to post it is logged rather than
+/// returned, as the decision that it describes has been made either way.
+pub async fn record<M: ToString>(pod: &PodId, reason: Reason, message: M) {
+    if let Err(err) = RECORDER
+        .publish(
+            &pod.namespace,
+            &pod.name,
+            reason.event_type(),
+            reason.as_str(),
+            message,
+        )
+        .await
+    {
+        warn!("{}", err);
+    }
+}
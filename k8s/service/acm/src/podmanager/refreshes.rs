@@ -0,0 +1,143 @@
+//! Batch refreshes, which let a client that holds many pods (such as an Alation instance keeping
+//! its entire fleet of connectors alive) refresh all of their tickets in a single request rather
+//! than in one request per pod.
+use super::garbage_collector::KeepAliveTicket;
+use super::{tickets, PodManager};
+use crate::auditor;
+use audit::{Action, Actor};
+use error::*;
+use futures::StreamExt;
+use kind::Kind;
+use result::Result;
+use rocket::serde::json::{self, Json};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use term_colors::*;
+
+/// The maximum number of tickets that may be refreshed within a single [batch](batch).
+pub const MAXIMUM_BATCH_SIZE: usize = 1000;
+
+/// The maximum number of tickets that a [batch](batch) refreshes at once.
+pub const BATCH_REFRESH_CONCURRENCY: usize = 64;
+
+/// A BatchRefresh is the outcome of the refresh of a single ticket within a [batch](batch).
+/// Exactly one of `ticket` and `error` is present.
+#[derive(Serialize)]
+pub struct BatchRefresh {
+    /// The refreshed ticket, should the refresh have succeeded.
+    pub ticket: Option<KeepAliveTicket>,
+    /// Why the ticket could not be refreshed, should it not have been.
+    pub error: Option<Box<dyn AcmError>>,
+}
+
+/// A BatchRefreshReport is the outcome of a [batch](batch) refresh.
+#[derive(Serialize, Kind)]
+pub struct BatchRefreshReport {
+    pub namespace: String,
+    /// The number of tickets that were successfully refreshed.
+    pub refreshed: usize,
+    /// The outcome of every ticket, keyed by the ticket.
+    pub results: BTreeMap<String, BatchRefresh>,
+}
+
+/// Takes the tickets out of the body of a batch refresh, which must be a JSON list of tickets.
+/// A body that is not such a list is a [MalformedBatch](MalformedBatch), while a list of more than
+/// [MAXIMUM_BATCH_SIZE](MAXIMUM_BATCH_SIZE) tickets is a [BatchTooLarge](BatchTooLarge).
+pub fn from_body(
+    body: std::result::Result<Json<Vec<String>>, json::Error<'_>>,
+) -> Result<Vec<String>> {
+    let tickets = match body {
+        Ok(Json(tickets)) => tickets,
+        Err(err) => {
+            return Err(MalformedBatch {
+                cause: match err {
+                    json::Error::Io(err) => err.to_string(),
+                    json::Error::Parse(_, err) => err.to_string(),
+                },
+            }
+            .into())
+        }
+    };
+    if tickets.len() > MAXIMUM_BATCH_SIZE {
+        return Err(BatchTooLarge {
+            size: tickets.len(),
+        }
+        .into());
+    }
+    Ok(tickets)
+}
+
+/// Refreshes every one of the given tickets within the given namespace on behalf of the given
+/// actor.
+///
+/// Each refresh is exactly that of a [single](PodManager::refresh) refresh, and is
+/// [audited](crate::auditor) as one. The failure to refresh one ticket does not stop the others
+/// from being refreshed, and is instead reported among the results. A ticket that is given more
+/// than once is refreshed only once.
+pub async fn batch(namespace: &str, tickets: Vec<String>, actor: &Actor) -> BatchRefreshReport {
+    let tickets: BTreeSet<String> = tickets.into_iter().collect();
+    debug!(
+        "Refreshing a batch of {} tickets within {} for {}",
+        tickets.len(),
+        highlight(namespace),
+        actor
+    );
+    let results: BTreeMap<String, BatchRefresh> = futures::stream::iter(tickets)
+        .map(|ticket| async move {
+            let target = format!("{}/{}", namespace, tickets::describe(&ticket));
+            let refreshed = async {
+                PodManager::get_by_ticket(namespace, &ticket)
+                    .await?
+                    .lock()
+                    .await
+                    .refresh(None)
+                    .await
+            }
+            .await;
+            let refreshed = auditor::record(actor, Action::Refresh, target, refreshed).await;
+            let result = match refreshed {
+                Ok(ticket) => BatchRefresh {
+                    ticket: Some(ticket),
+                    error: None,
+                },
+                Err(err) => BatchRefresh {
+                    ticket: None,
+                    error: Some(err),
+                },
+            };
+            (ticket, result)
+        })
+        .buffer_unordered(BATCH_REFRESH_CONCURRENCY)
+        .collect()
+        .await;
+    BatchRefreshReport {
+        namespace: namespace.to_string(),
+        refreshed: results
+            .values()
+            .filter(|result| result.ticket.is_some())
+            .count(),
+        results,
+    }
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The body of the batch refresh could not be read as a list of tickets, {cause}. The body must \
+be a JSON list of the tickets to refresh, such as [\"super-cool-connector-abcd12345\"]."
+)]
+#[error_code("ACM-2800")]
+pub struct MalformedBatch {
+    cause: String,
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::PayloadTooLarge)]
+#[error(
+    "A batch refresh may refresh at most 1000 tickets at once, got {size} tickets. Please split \
+the batch into several."
+)]
+#[error_code("ACM-2801")]
+pub struct BatchTooLarge {
+    size: usize,
+}
@@ -0,0 +1,174 @@
+//! The Registry holds every [PodManager](super::PodManager) of this ACM, keyed by
+//! [PodId](PodId).
+//!
+//! Every wait, refresh, and delete looks its PodManager up within the registry, so the registry is
+//! split into [SHARDS](SHARDS) independently locked shards so that a burst of deploys (each of
+//! which writes to the registry) does not hold up every other request. The registry is bounded
+//! by [admission](super::admission), which is sought before any PodManager is registered.
+//!
+//! A PodManager is removed from the registry once every one of its coroutines has exited, which
+//! should happen shortly after its pod is deleted. The registry [sweeps](configure) for
+//! PodManagers that linger for longer than the [LEAK_DEADLINE](LEAK_DEADLINE) after their pod
+//! was deleted, as such a PodManager has a coroutine that is never going to exit.
+use super::{deletions, ManagedPod, PodId};
+use k8s::display::DisplayName;
+use kind::Kind;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use term_colors::*;
+use tokio::sync::RwLock;
+
+/// The number of independently locked shards that the registry is split into.
+pub const SHARDS: usize = 16;
+
+/// How long a PodManager may linger after its pod was deleted before it is reported as leaked.
+pub const LEAK_DEADLINE: Duration = Duration::from_secs(300);
+
+/// How often the registry is swept for leaked PodManagers.
+const LEAK_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+pub struct Registry {
+    shards: Vec<RwLock<HashMap<PodId, ManagedPod>>>,
+    len: AtomicUsize,
+    /// Every PodManager that has been reported as leaked, which is forgotten once the PodManager
+    /// is finally removed.
+    leaked: std::sync::Mutex<HashSet<PodId>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry {
+            shards: (0..SHARDS).map(|_| RwLock::new(HashMap::new())).collect(),
+            len: AtomicUsize::new(0),
+            leaked: std::sync::Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+impl Registry {
+    fn shard(&self, id: &PodId) -> &RwLock<HashMap<PodId, ManagedPod>> {
+        let mut hasher = DefaultHasher::new();
+        id.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    /// Returns the entry of the given pod, should it be registered.
+    pub(super) async fn get(&self, id: &PodId) -> Option<ManagedPod> {
+        self.shard(id).read().await.get(id).cloned()
+    }
+
+    /// Registers the given pod, replacing any entry that it already had.
+    pub(super) async fn insert(&self, id: PodId, managed: ManagedPod) {
+        if self.shard(&id).write().await.insert(id, managed).is_none() {
+            self.len.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Removes the given pod, returning the number of PodManagers that are still registered.
+    pub(super) async fn remove(&self, id: &PodId) -> usize {
+        if self.shard(id).write().await.remove(id).is_some() {
+            self.len.fetch_sub(1, Ordering::SeqCst);
+        }
+        if self.leaked.lock().unwrap().remove(id) {
+            warn!(
+                "The leaked PodManager for {} was finally cleaned up",
+                highlight(id.to_string())
+            );
+        }
+        self.len()
+    }
+
+    /// The number of registered PodManagers.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::SeqCst)
+    }
+
+    /// Whether there are no registered PodManagers at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of registered PodManagers that have been reported as leaked.
+    pub fn leaked(&self) -> usize {
+        self.leaked.lock().unwrap().len()
+    }
+
+    /// Returns the ID of every registered PodManager, sorted. Each shard is locked only for as
+    /// long as it takes to copy its IDs, so this is a snapshot that no other request waits on.
+    pub async fn ids(&self) -> Vec<PodId> {
+        let mut ids = Vec::with_capacity(self.len());
+        for shard in self.shards.iter() {
+            ids.extend(shard.read().await.keys().cloned());
+        }
+        ids.sort();
+        ids
+    }
+
+    /// Reports every PodManager that has lingered for longer than the
+    /// [LEAK_DEADLINE](LEAK_DEADLINE) after its pod was deleted. Each is reported only once.
+    async fn sweep(&self) {
+        for id in self.ids().await {
+            let deleted_at = match deletions::deleted_at(&id).await {
+                Some(deleted_at) => deleted_at,
+                None => continue,
+            };
+            let leaked = deleted_at.elapsed() >= LEAK_DEADLINE
+                && self.leaked.lock().unwrap().insert(id.clone());
+            if !leaked {
+                continue;
+            }
+            error!(
+                "The PodManager for {} is still registered {:?} after its pod was deleted, so at \
+                least one of its coroutines has not exited. Please review /admin/tasks for the \
+                coroutines of this pod.",
+                highlight(id.to_string()),
+                deleted_at.elapsed()
+            );
+        }
+    }
+}
+
+/// Spawns the coroutine that periodically sweeps the [registry](super::POD_MANAGERS) for leaked
+/// PodManagers.
+pub fn configure() {
+    tokio::spawn(async {
+        loop {
+            tokio::time::sleep(LEAK_SWEEP_INTERVAL).await;
+            super::POD_MANAGERS.sweep().await;
+        }
+    });
+}
+
+/// ManagedPods lists the pods that this ACM manages within a namespace, alongside the
+/// [DisplayName](DisplayName) that each was deployed as.
+#[derive(Serialize, Kind)]
+pub struct ManagedPods {
+    pub namespace: String,
+    pub pods: Vec<String>,
+    pub display_names: BTreeMap<String, DisplayName>,
+}
+
+/// Lists the pods that this ACM manages within the given namespace.
+pub async fn list(namespace: &str) -> ManagedPods {
+    let registry = &*super::POD_MANAGERS;
+    let mut display_names = BTreeMap::new();
+    for shard in registry.shards.iter() {
+        display_names.extend(
+            shard
+                .read()
+                .await
+                .iter()
+                .filter(|(id, _)| id.namespace == namespace)
+                .map(|(id, managed)| (id.name.clone(), managed.display.clone())),
+        );
+    }
+    ManagedPods {
+        namespace: namespace.to_string(),
+        pods: display_names.keys().cloned().collect(),
+        display_names,
+    }
+}
@@ -1,19 +1,24 @@
+use super::tasks::Task;
+use super::PodId;
 use backoff::backoff::Backoff;
+use config::acm::HealthCheckMode;
 use error::*;
 use futures::FutureExt;
 use futures_util::{pin_mut, select};
 use k8s::PodExt;
 use k8s_openapi::api::core::v1::Pod;
+use kube::ResourceExt;
 use result::Result;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
 use term_colors::*;
 use tokio::sync::oneshot::{channel, Receiver, Sender};
 use tokio::task::JoinHandle;
+use tonic::transport::Channel;
 use tonic::transport::Endpoint;
+use tonic_health::proto::health_check_response::ServingStatus;
 use tonic_health::proto::health_client::HealthClient;
-
-/// The maximum amount of time (in seconds) that well spend polling for the target
-/// pod's gRPC server to become active.
-pub const MAXIMUM_POLLING_TIME: u64 = 30;
+use tonic_health::proto::HealthCheckRequest;
 
 /// A ServerCheck acts as a facade into the running coroutine that is polling for the newly
 /// created connector pod gRPC endpoint.
@@ -30,7 +35,13 @@ impl ServerCheck {
             .map_err(|err| GrpcEndpointParsdeError { uri, source: err })?;
         let (sigint, sigint_rx) = channel();
         let (result_tx, result) = channel();
-        let handle = tokio::spawn(Self::check(endpoint, sigint_rx, result_tx));
+        let task = Task::register("server_check", &PodId::of(pod));
+        // Pods fronted by a headless Service are only resolvable once the Service has endpoints.
+        let service = pod
+            .annotations()
+            .get(k8s::headless::SERVICE_ANNOTATION)
+            .map(|service| (PodId::of(pod).namespace, service.clone()));
+        let handle = tokio::spawn(Self::check(endpoint, service, sigint_rx, result_tx, task));
         Ok((ServerCheck { sigint, handle }, result))
     }
 
@@ -41,13 +52,12 @@ impl ServerCheck {
     /// between a killed pod and a pod whose gRPC server hasn't come up yet, it needs to be told
     /// to just die when such an event occurs.
     pub async fn kill(self) {
-        match self.sigint.send(()) {
-            Err(err) => warn!(
+        if let Err(err) = self.sigint.send(()) {
+            warn!(
                 "The server health check coroutine appears have to shut itself \
-            down earlier than expected. {:?}",
+        down earlier than expected. {:?}",
                 err
-            ),
-            Ok(()) => (),
+            )
         };
         match self.handle.await {
             Ok(()) => (),
@@ -77,42 +87,84 @@ impl ServerCheck {
 
     /// Continuously polls the target gRPC endpoint following a strategy of exponential backoff.
     ///
-    /// In order to be considered active, a gRPC endpoint must only RESPOND to a request. It does
-    /// not need to respond with a SUCCESS. That is to say, this procedure is making a call into
-    /// the standard [gRPC health check](https://github.com/grpc/grpc/blob/master/doc/health-checking.md)
-    /// protocol. It does not yet REQUIRE that the target gRPC server actually implement the protocol
-    /// (it is fine if the server responds with "method not found"), however it does require
-    /// that connection can be established and that a response can be received at all.
+    /// By default (that is, in the [lenient](HealthCheckMode::Lenient) health check mode), a gRPC
+    /// endpoint must only accept a connection in order to be considered active. It does not need
+    /// to implement the standard
+    /// [gRPC health check](https://github.com/grpc/grpc/blob/master/doc/health-checking.md)
+    /// protocol at all. In the [strict](HealthCheckMode::Strict) (and
+    /// [watch](HealthCheckMode::Watch)) health check modes, the endpoint must additionally answer
+    /// a `Check` of the configured [service](config::acm::AcmTunables::health_check_service) with
+    /// `SERVING`. Any other answer (including "method not found") is retried until the endpoint
+    /// runs out of time, at which point it is reported as [NotServing](NotServing).
+    ///
+    /// The MAXIMUM time that the gRPC endpoint has to become active is the health check timeout
+    /// (thirty seconds by default), or the maximum elapsed time of the
+    /// [grpc-health](retry::Policy::GrpcHealth) retry policy should that be shorter, at which
+    /// point the pod will be considered ill-behaved. The timeout is one of the ACM's
+    /// [tunables](crate::reload) and is read anew for every check.
     ///
-    /// The MAXIMUM time that the gRPC endpoint has to become active is thirty seconds, at which
-    /// point the pod will be considered ill-behaved.
-    async fn check(endpoint: Endpoint, sigint: Receiver<()>, output: Sender<Result<()>>) {
+    /// Should the pod be fronted by a headless Service (given as its namespace and name), then
+    /// polling only begins once the Service has endpoints. The Service is likewise given the
+    /// health check timeout to get them.
+    async fn check(
+        endpoint: Endpoint,
+        service: Option<(String, String)>,
+        sigint: Receiver<()>,
+        output: Sender<Result<()>>,
+        task: Task,
+    ) {
         let mut latest_error = None;
-        let mut b = backoff::ExponentialBackoff {
-            max_elapsed_time: Some(std::time::Duration::from_secs(MAXIMUM_POLLING_TIME)),
-            ..Default::default()
-        };
+        let tunables = crate::reload::tunables();
+        let timeout = Duration::from_secs(tunables.health_check_timeout);
+        let strict = tunables.health_check_mode.strict();
+        let health_service = tunables.health_check_service.clone();
         let sigint = sigint.fuse();
         pin_mut!(sigint);
+        if let Some((namespace, service)) = service {
+            let endpoints = k8s::headless::wait_for_endpoints(namespace, service).fuse();
+            let patience = tokio::time::sleep(timeout).fuse();
+            pin_mut!(endpoints, patience);
+            select! {
+                _ = endpoints => (),
+                _ = patience => {
+                    output.send(Err(NotReady {}.into())).unwrap();
+                    return;
+                }
+                _ = sigint => {
+                    trace!("Server health check thread for {} received signal to shutdown \
+                    while awaiting its service's endpoints", highlight(format!("{}", endpoint.uri())));
+                    return;
+                }
+            };
+        }
+        let mut b = retry::Policy::GrpcHealth.backoff();
+        let allowance = b
+            .max_elapsed_time()
+            .map_or(timeout, |max_elapsed_time| max_elapsed_time.min(timeout));
         loop {
             match b.next_backoff() {
                 None => {
-                    output
-                        .send(Err(TooManyFailures {
-                            uri: format!("{}", endpoint.uri()),
-                            // This unwrap works ONLY because the only
-                            // `continue` in this loop is immediately
-                            // after assigning it a value. If a new
-                            // continue is ever added or the extant
-                            // continue moved, then this unwrap
-                            // becomes unsafe.
-                            source: latest_error.unwrap(),
+                    let uri = format!("{}", endpoint.uri());
+                    // This unwrap works ONLY because the only
+                    // `continue` in this loop is immediately
+                    // after assigning it a value. If a new
+                    // continue is ever added or the extant
+                    // continue moved, then this unwrap
+                    // becomes unsafe.
+                    let err: Box<dyn AcmError> = match latest_error.unwrap() {
+                        Failure::Connect(source) => TooManyFailures { uri, source }.into(),
+                        Failure::NotServing(status) => NotServing {
+                            uri,
+                            service: health_service,
+                            status,
                         }
-                        .into()))
-                        .unwrap();
+                        .into(),
+                    };
+                    output.send(Err(err)).unwrap();
                     return;
                 }
                 Some(duration) => {
+                    task.heartbeat();
                     let wait = tokio::time::sleep(duration).fuse();
                     pin_mut!(wait);
                     // Wait for either the next period in our exponential backoff
@@ -121,7 +173,7 @@ impl ServerCheck {
                         _ = wait => (),
                         _ = sigint => {
                             trace!("Server health check thread for {} received signal to shutdown \
-                            while awaiting backoff timer", cyan(format!("{}", endpoint.uri())));
+                            while awaiting backoff timer", highlight(format!("{}", endpoint.uri())));
                             return;
                         }
                     };
@@ -129,11 +181,18 @@ impl ServerCheck {
                     //
                     // In order to protect ourselves from a slow loris attack
                     // (https://en.wikipedia.org/wiki/Slowloris_(computer_security))
-                    // we will compute the maximum allowable time (thirty seconds) minus how long
+                    // we will compute the maximum allowable time (the health check timeout, or
+                    // the max elapsed time of the grpc-health retry policy should that be
+                    // shorter, thirty seconds by default) minus how long
                     // we have waited thus far and assert that the connection MUST be established
                     // and responded to us before our "patience" runs out.
-                    let connection = HealthClient::connect(endpoint.clone()).fuse();
-                    let patience = tokio::time::Duration::from_secs(MAXIMUM_POLLING_TIME)
+                    let connection = async {
+                        #[cfg(feature = "faults")]
+                        crate::faults::stall_health_check().await;
+                        attempt(&endpoint, strict, &health_service).await
+                    }
+                    .fuse();
+                    let patience = allowance
                         .checked_sub(b.get_elapsed_time())
                         .unwrap_or_else(|| tokio::time::Duration::from_secs(0));
                     let patience = tokio::time::sleep(patience).fuse();
@@ -151,14 +210,15 @@ impl ServerCheck {
                         _ = sigint => {
                             trace!("Server health check thread for {} received signal to \
                             shutdown while awaiting server connection",
-                                cyan(format!("{}", endpoint.uri())));
+                                highlight(format!("{}", endpoint.uri())));
                             return;
                         }
                     };
                     // Alright! We got a result from the connection. But result could still
-                    // something like "connection refused", meaning that the server is not up yet.
+                    // something like "connection refused", meaning that the server is not up yet,
+                    // or (should the check be strict) a status other than SERVING.
                     //
-                    // So if we got an "Ok" then successfully established a connection!
+                    // So if we got an "Ok" then the server is healthy!
                     // But if we got an "Err" then we should record what the error was and try
                     // again after the next backoff period.
                     match conn {
@@ -168,8 +228,8 @@ impl ServerCheck {
                         }
                         Err(err) => {
                             debug!(
-                                "Could not connect to {}, {:?}",
-                                cyan(format!("{}", endpoint.uri())),
+                                "Could not health check {}, {:?}",
+                                highlight(format!("{}", endpoint.uri())),
                                 err
                             );
                             latest_error = Some(err);
@@ -182,9 +242,284 @@ impl ServerCheck {
     }
 }
 
+/// A HealthWatch acts as a facade into the running coroutine that monitors the health of a
+/// connector that has come online. The connector is `Watch`ed (see
+/// [HealthCheckMode::Watch](HealthCheckMode::Watch)) and/or periodically probed (see
+/// [health_probe_interval](config::acm::AcmTunables::health_probe_interval)) as the ACM is
+/// configured to.
+///
+/// The coroutine is aborted once its HealthWatch is dropped.
+pub struct HealthWatch {
+    unhealthy: Option<Receiver<Box<dyn AcmError>>>,
+    handle: JoinHandle<()>,
+}
+
+/// How a connector that is online is probed.
+struct Probe {
+    interval: Duration,
+    timeout: Duration,
+    /// The number of consecutive probes that may fail before the connector is unresponsive.
+    failures: u64,
+    strict: bool,
+}
+
+impl HealthWatch {
+    /// Begins monitoring the health of the connector of the given pod, should the ACM be
+    /// configured to either [watch](HealthCheckMode::Watch) or probe connectors at all.
+    pub fn new(pod: &Pod) -> Result<Option<HealthWatch>> {
+        let tunables = crate::reload::tunables();
+        let watch = tunables.health_check_mode == HealthCheckMode::Watch;
+        let probe = tunables.health_probe_interval.map(|interval| Probe {
+            interval: Duration::from_secs(interval),
+            timeout: Duration::from_secs(tunables.health_check_timeout),
+            failures: tunables.health_probe_failures,
+            strict: tunables.health_check_mode.strict(),
+        });
+        if !watch && probe.is_none() {
+            return Ok(None);
+        }
+        let uri = format!("http://{}", pod.address()?);
+        let endpoint: Endpoint = uri
+            .parse()
+            .map_err(|err| GrpcEndpointParsdeError { uri, source: err })?;
+        let (unhealthy, result) = channel();
+        let task = Task::register("health_watch", &PodId::of(pod));
+        let handle = tokio::spawn(Self::monitor(
+            endpoint,
+            tunables.health_check_service.clone(),
+            watch,
+            probe,
+            unhealthy,
+            task,
+        ));
+        Ok(Some(HealthWatch {
+            unhealthy: Some(result),
+            handle,
+        }))
+    }
+
+    /// Resolves once the connector has been found unhealthy, with either a
+    /// [BecameUnhealthy](BecameUnhealthy) or a
+    /// [ConnectorBecameUnresponsive](ConnectorBecameUnresponsive) describing why.
+    ///
+    /// Should monitoring end without the connector ever having been found unhealthy (say, because
+    /// the connector went down altogether, which its event watcher hears of by other means, or
+    /// because it does not implement `Watch`), then this never resolves.
+    pub async fn unhealthy(&mut self) -> Box<dyn AcmError> {
+        if let Some(result) = self.unhealthy.as_mut() {
+            if let Ok(err) = result.await {
+                self.unhealthy = None;
+                return err;
+            }
+            self.unhealthy = None;
+        }
+        futures::future::pending().await
+    }
+
+    /// Watches and/or probes the given service of the given endpoint, sending the reason that it
+    /// was found unhealthy (whichever comes first) on the given channel.
+    async fn monitor(
+        endpoint: Endpoint,
+        service: String,
+        watch: bool,
+        probe: Option<Probe>,
+        unhealthy: Sender<Box<dyn AcmError>>,
+        task: Task,
+    ) {
+        let watching = async {
+            if watch {
+                if let Some(err) = Self::watch(&endpoint, &service, &task).await {
+                    return err;
+                }
+            }
+            futures::future::pending().await
+        }
+        .fuse();
+        let probing = async {
+            match probe {
+                Some(probe) => Self::probe(&endpoint, &service, probe, &task).await,
+                None => futures::future::pending().await,
+            }
+        }
+        .fuse();
+        pin_mut!(watching, probing);
+        let err = select! {
+            err = watching => err,
+            err = probing => err,
+        };
+        let _ = unhealthy.send(err);
+    }
+
+    /// `Watch`es the given service of the given endpoint, returning a
+    /// [BecameUnhealthy](BecameUnhealthy) should it ever report a status other than `SERVING`.
+    async fn watch(endpoint: &Endpoint, service: &str, task: &Task) -> Option<Box<dyn AcmError>> {
+        let uri = format!("{}", endpoint.uri());
+        let mut client = match HealthClient::connect(endpoint.clone()).await {
+            Ok(client) => client,
+            Err(err) => {
+                debug!(
+                    "Could not connect to {} to watch its health, {:?}",
+                    highlight(uri),
+                    err
+                );
+                return None;
+            }
+        };
+        let request = HealthCheckRequest {
+            service: service.to_string(),
+        };
+        let mut statuses = match client.watch(request).await {
+            Ok(statuses) => statuses.into_inner(),
+            Err(err) => {
+                warn!(
+                    "Could not watch the health of {}, {:?}",
+                    highlight(uri),
+                    err
+                );
+                return None;
+            }
+        };
+        loop {
+            task.heartbeat();
+            match statuses.message().await {
+                Ok(Some(response)) => match response.status() {
+                    ServingStatus::Serving => continue,
+                    status => {
+                        return Some(
+                            BecameUnhealthy {
+                                uri,
+                                service: service.to_string(),
+                                status: format!("{:?}", status),
+                            }
+                            .into(),
+                        )
+                    }
+                },
+                Ok(None) => {
+                    debug!("{} ended the watch of its health", highlight(uri));
+                    return None;
+                }
+                Err(err) => {
+                    debug!(
+                        "The watch of the health of {} failed, {:?}",
+                        highlight(uri),
+                        err
+                    );
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Probes the given service of the given endpoint once every interval, returning a
+    /// [ConnectorBecameUnresponsive](ConnectorBecameUnresponsive) once it has failed as many
+    /// consecutive probes as it may. A probe that is not answered within the health check timeout
+    /// is failed.
+    async fn probe(
+        endpoint: &Endpoint,
+        service: &str,
+        probe: Probe,
+        task: &Task,
+    ) -> Box<dyn AcmError> {
+        let uri = format!("{}", endpoint.uri());
+        let mut failures = 0;
+        loop {
+            tokio::time::sleep(probe.interval).await;
+            task.heartbeat();
+            let failure =
+                match tokio::time::timeout(probe.timeout, attempt(endpoint, probe.strict, service))
+                    .await
+                {
+                    Ok(Ok(())) => {
+                        failures = 0;
+                        continue;
+                    }
+                    Ok(Err(failure)) => failure.to_string(),
+                    Err(_) => format!("no answer within {:?}", probe.timeout),
+                };
+            failures += 1;
+            debug!(
+                "{} failed {} consecutive health probes, {}",
+                highlight(uri.clone()),
+                failures,
+                failure
+            );
+            if failures >= probe.failures {
+                return ConnectorBecameUnresponsive {
+                    uri,
+                    failures,
+                    cause: failure.into(),
+                }
+                .into();
+            }
+        }
+    }
+}
+
+impl Drop for HealthWatch {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Why a single attempt at a server health check failed.
+#[derive(Debug)]
+enum Failure {
+    /// No connection could be established at all.
+    Connect(tonic::transport::Error),
+    /// The server answered a strict `Check` with the given status (or error) rather than SERVING.
+    NotServing(String),
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Connect(err) => write!(f, "{}", err),
+            Failure::NotServing(status) => write!(f, "{}", status),
+        }
+    }
+}
+
+/// Makes a single attempt at health checking the given endpoint, which must accept a connection
+/// and (should the check be strict) answer a `Check` of the given service with SERVING.
+async fn attempt(
+    endpoint: &Endpoint,
+    strict: bool,
+    service: &str,
+) -> std::result::Result<(), Failure> {
+    let mut client = HealthClient::connect(endpoint.clone())
+        .await
+        .map_err(Failure::Connect)?;
+    if strict {
+        serving(&mut client, service)
+            .await
+            .map_err(Failure::NotServing)?;
+    }
+    Ok(())
+}
+
+/// Performs a `Check` of the given service, returning the status (or error) that was received in
+/// its place should it not be SERVING.
+async fn serving(
+    client: &mut HealthClient<Channel>,
+    service: &str,
+) -> std::result::Result<(), String> {
+    let response = client
+        .check(HealthCheckRequest {
+            service: service.to_string(),
+        })
+        .await
+        .map_err(|status| status.to_string())?;
+    match response.into_inner().status() {
+        ServingStatus::Serving => Ok(()),
+        status => Err(format!("{:?}", status)),
+    }
+}
+
 #[derive(Error, AcmError, Kind, Debug, HttpCode)]
 #[error("")]
 #[code(Status::ServiceUnavailable)]
+#[error_code("ACM-1300")]
 pub struct NotReady {}
 
 #[derive(Error, AcmError, Kind, Debug, HttpCode)]
@@ -193,6 +528,7 @@ pub struct NotReady {}
 ({uri}) for its server health check"
 )]
 #[code(Status::ServiceUnavailable)]
+#[error_code("ACM-1301")]
 pub struct TooManyFailures {
     uri: String,
     #[source]
@@ -205,8 +541,51 @@ pub struct TooManyFailures {
 ({uri}) for its server health check"
 )]
 #[code(Status::ServiceUnavailable)]
+#[error_code("ACM-1302")]
 pub struct GrpcEndpointParsdeError {
     uri: String,
     #[source]
     source: k8s_openapi::http::uri::InvalidUri,
 }
+
+#[derive(Error, AcmError, Kind, Debug, HttpCode)]
+#[error(
+    "The requested pod ({uri}) never reported its '{service}' service as SERVING to its gRPC \
+health check. The latest answer that it gave was '{status}'. The ACM requires connectors to \
+implement the gRPC health checking protocol (grpc.health.v1.Health) should its HEALTH_CHECK_MODE \
+be strict or watch."
+)]
+#[code(Status::ServiceUnavailable)]
+#[error_code("ACM-1303")]
+pub struct NotServing {
+    uri: String,
+    service: String,
+    status: String,
+}
+
+#[derive(Error, AcmError, Kind, Debug, HttpCode)]
+#[error(
+    "The connector ({uri}) reported its '{service}' service as {status} rather than SERVING \
+after it came online, and so it was deleted."
+)]
+#[code(Status::ServiceUnavailable)]
+#[error_code("ACM-1304")]
+pub struct BecameUnhealthy {
+    uri: String,
+    service: String,
+    status: String,
+}
+
+#[derive(Error, AcmError, Kind, Debug, HttpCode)]
+#[error(
+    "The connector ({uri}) failed {failures} consecutive health probes after it came online, and \
+so it was deleted as unresponsive. The latest failure is given as the cause of this error."
+)]
+#[code(Status::ServiceUnavailable)]
+#[error_code("ACM-1305")]
+pub struct ConnectorBecameUnresponsive {
+    uri: String,
+    failures: u64,
+    #[source]
+    cause: StringError,
+}
@@ -0,0 +1,137 @@
+use super::{admission, PodId, POD_MANAGERS};
+use error::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref TASKS: Mutex<HashMap<u64, TaskRecord>> = Mutex::new(HashMap::new());
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A Task is the registration of a running PodManager coroutine (the event watcher, the garbage
+/// collector, the server health check, etc.) with the task registry.
+///
+/// Coroutines register themselves upon starting and are automatically deregistered when
+/// their Task is dropped. As such, a coroutine that never exits is a coroutine whose Task
+/// lingers in the registry, which turns the question of "is there a rogue runtime somewhere?"
+/// into something that may be answered by way of [report](report) rather than by trawling logs.
+pub struct Task {
+    id: u64,
+}
+
+impl Task {
+    /// Registers a new Task of the given name on behalf of the given pod.
+    pub fn register(name: &'static str, pod: &PodId) -> Task {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let now = chrono::Utc::now().timestamp();
+        TASKS.lock().unwrap().insert(
+            id,
+            TaskRecord {
+                name,
+                pod: pod.to_string(),
+                started_at: now,
+                last_heartbeat: now,
+                orphaned: false,
+            },
+        );
+        Task { id }
+    }
+
+    /// Records that the coroutine has made progress. Coroutines SHOULD heartbeat every time
+    /// that they wake up to handle an event.
+    pub fn heartbeat(&self) {
+        if let Some(record) = TASKS.lock().unwrap().get_mut(&self.id) {
+            record.last_heartbeat = chrono::Utc::now().timestamp();
+        }
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        TASKS.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// A TaskRecord is the view of a single running coroutine.
+#[derive(Serialize, Kind, Clone, Debug)]
+pub struct TaskRecord {
+    /// The kind of coroutine (e.g. `event_watcher` or `garbage_collector`).
+    pub name: &'static str,
+    /// The pod (as `<namespace>/<name>`) that this coroutine is working on behalf of.
+    pub pod: String,
+    /// The Unix timestamp of when the coroutine started.
+    pub started_at: i64,
+    /// The Unix timestamp of when the coroutine last made progress.
+    pub last_heartbeat: i64,
+    /// Whether or not the pod that this coroutine is working on behalf of no longer has a
+    /// PodManager. Coroutines may be momentarily orphaned while their PodManager is being created
+    /// or torn down, however a coroutine that remains orphaned is a leak.
+    pub orphaned: bool,
+}
+
+/// A TaskReport is a snapshot of the task registry.
+#[derive(Serialize, Kind, Debug)]
+pub struct TaskReport {
+    /// The number of PodManagers currently held by this ACM.
+    pub pod_managers: usize,
+    /// The number of PodManagers currently [admitted](super::admission::admitted), which also
+    /// counts those whose pods are still being deployed or whose coroutines are shutting down.
+    pub admitted_pod_managers: usize,
+    /// The [maximum](super::admission::maximum) number of PodManagers that this ACM may hold.
+    pub max_pod_managers: usize,
+    /// The number of PodManagers that are still held long after their pod was deleted, and so
+    /// have [leaked](super::registry::LEAK_DEADLINE).
+    pub leaked_pod_managers: usize,
+    /// The number of running coroutines, keyed by their name.
+    pub counts: BTreeMap<&'static str, usize>,
+    /// Every running coroutine, oldest first.
+    pub tasks: Vec<TaskRecord>,
+}
+
+impl TaskReport {
+    /// The number of running coroutines that are [orphaned](TaskRecord::orphaned).
+    pub fn orphaned(&self) -> usize {
+        self.tasks.iter().filter(|task| task.orphaned).count()
+    }
+}
+
+/// Takes a snapshot of every registered coroutine.
+pub async fn report() -> TaskReport {
+    let managed: HashSet<String> = POD_MANAGERS
+        .ids()
+        .await
+        .iter()
+        .map(PodId::to_string)
+        .collect();
+    let mut tasks: Vec<TaskRecord> = TASKS
+        .lock()
+        .unwrap()
+        .values()
+        .cloned()
+        .map(|mut record| {
+            record.orphaned = !managed.contains(&record.pod);
+            record
+        })
+        .collect();
+    tasks.sort_by(|a, b| {
+        a.started_at
+            .cmp(&b.started_at)
+            .then_with(|| a.pod.cmp(&b.pod))
+            .then_with(|| a.name.cmp(b.name))
+    });
+    let mut counts = BTreeMap::new();
+    for task in tasks.iter() {
+        *counts.entry(task.name).or_insert(0) += 1;
+    }
+    TaskReport {
+        pod_managers: managed.len(),
+        admitted_pod_managers: admission::admitted(),
+        max_pod_managers: admission::maximum(),
+        leaked_pod_managers: POD_MANAGERS.leaked(),
+        counts,
+        tasks,
+    }
+}
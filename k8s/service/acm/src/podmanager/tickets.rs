@@ -0,0 +1,223 @@
+//! Keep-alive tickets are what clients wait on, refresh, and delete their pods with. A pod's first
+//! ticket is issued by its [deploy](crate::deploy()), and every later one by a wait or refresh
+//! that was given the one before. Should the ACM have any
+//! [ticket signing keys](config::acm::AcmConfig::ticket_signing_keys), then every ticket that it
+//! issues is signed, such that a client who merely knows (or guesses) the name of somebody else's
+//! pod cannot keep it alive (or delete it) on their behalf.
+//!
+//! A signed ticket reads `<pod>.<execution date>.<nonce>.<signature>`, where the signature is the
+//! HMAC-SHA256 of everything before it. Clients MUST nonetheless treat tickets as opaque. Without
+//! any signing keys, a ticket is merely the name of its pod.
+use crate::{env, hmac};
+use error::*;
+use result::Result;
+
+/// The header that bears a pod's ticket, both within the response to a deploy and within
+/// requests [proxied](crate::proxy) to the pod's connector.
+pub const TICKET_HEADER: &str = "X-OCF-Ticket";
+
+/// Issues a ticket for the given pod that is good until the given execution date (a Unix
+/// timestamp).
+pub fn issue(pod: &str, execution_date: i64) -> String {
+    sign(&env::config().ticket_signing_keys, pod, execution_date)
+}
+
+/// Issues the first ticket of the given pod, being one that is deployed with the given TTL. The
+/// countdown of a pod only begins once it is running, so this ticket is good for the TTL beyond
+/// the longest that the pod may take to be [scheduled](config::acm::AcmTunables::scheduling_timeout)
+/// and to pass its [health check](config::acm::AcmTunables::health_check_timeout).
+pub fn issue_first(pod: &str, ttl: u64) -> String {
+    let tunables = crate::reload::tunables();
+    let startup = tunables.scheduling_timeout + tunables.health_check_timeout;
+    issue(pod, chrono::Utc::now().timestamp() + (ttl + startup) as i64)
+}
+
+/// Returns the name of the pod that the given ticket was issued for.
+///
+/// A ticket that was not signed by any of the signing keys is a [ForgedTicket](ForgedTicket),
+/// while one that is past its execution date (and any
+/// [grace period](config::acm::AcmTunables::gc_grace_period) thereafter) is an
+/// [ExpiredTicket](ExpiredTicket). Every ticket is simply the name of its pod should there be no
+/// signing keys.
+pub fn redeem(ticket: &str) -> Result<String> {
+    let grace = crate::reload::tunables()
+        .gc_grace_period
+        .unwrap_or_default() as i64;
+    verify(
+        &env::config().ticket_signing_keys,
+        grace,
+        chrono::Utc::now().timestamp(),
+        ticket,
+    )
+}
+
+/// Returns the name of the pod that the given ticket claims to be for, WITHOUT verifying it. This
+/// is only fit for describing the ticket (say, within the audit trail) without disclosing it.
+pub fn describe(ticket: &str) -> &str {
+    claimed(!env::config().ticket_signing_keys.is_empty(), ticket)
+}
+
+/// Signs a ticket for the given pod with the first of the given keys. Later keys are only ever
+/// verified against, such that a key may be rotated out without refusing the tickets that it
+/// has already signed.
+fn sign<K: AsRef<str>>(keys: &[K], pod: &str, execution_date: i64) -> String {
+    match keys.first() {
+        None => pod.to_string(),
+        Some(key) => {
+            let claims = format!("{}.{}.{:016x}", pod, execution_date, rand::random::<u64>());
+            let signature = hmac::sign(key.as_ref().as_bytes(), claims.as_bytes());
+            format!("{}.{}", claims, signature)
+        }
+    }
+}
+
+/// Verifies the given ticket against every one of the given keys as of `now` (a Unix timestamp),
+/// returning the name of its pod.
+fn verify<K: AsRef<str>>(keys: &[K], grace: i64, now: i64, ticket: &str) -> Result<String> {
+    if keys.is_empty() {
+        return Ok(ticket.to_string());
+    }
+    let (claims, signature) = ticket.rsplit_once('.').ok_or(ForgedTicket {})?;
+    let signed = keys
+        .iter()
+        .any(|key| hmac::verify(key.as_ref().as_bytes(), claims.as_bytes(), signature));
+    if !signed {
+        return Err(ForgedTicket {}.into());
+    }
+    // The pod's name comes first, as the only one of the claims that may itself have a dot.
+    let mut claims = claims.rsplitn(3, '.').skip(1);
+    let execution_date = claims.next().and_then(|date| date.parse::<i64>().ok());
+    let (execution_date, pod) = match (execution_date, claims.next()) {
+        (Some(execution_date), Some(pod)) => (execution_date, pod),
+        _ => return Err(ForgedTicket {}.into()),
+    };
+    if now > execution_date + grace {
+        return Err(ExpiredTicket {
+            pod: pod.to_string(),
+            execution_date,
+        }
+        .into());
+    }
+    Ok(pod.to_string())
+}
+
+/// Returns the name of the pod that the given ticket claims to be for, which is the ticket itself
+/// should tickets not be signed.
+fn claimed(signed: bool, ticket: &str) -> &str {
+    if !signed {
+        return ticket;
+    }
+    ticket
+        .rsplitn(4, '.')
+        .nth(3)
+        .unwrap_or("<malformed ticket>")
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::Forbidden)]
+#[error(
+    "The given ticket was not issued by this ACM (Alation Connector Manager). Tickets must be \
+given exactly as they were returned by the deploy (or the most recent wait or refresh) of their pod."
+)]
+#[error_code("ACM-1201")]
+pub struct ForgedTicket {}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::Forbidden)]
+#[error(
+    "The given ticket for {pod} expired at {execution_date} (Unix). Please refresh the pod with \
+the ticket returned by its most recent refresh."
+)]
+#[error_code("ACM-1202")]
+pub struct ExpiredTicket {
+    pod: String,
+    execution_date: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1632266521;
+
+    #[test]
+    fn unsigned() {
+        let keys: [&str; 0] = [];
+        assert_eq!(sign(&keys, "oracle-abcd1234", NOW), "oracle-abcd1234");
+        assert_eq!(
+            verify(&keys, 0, NOW, "oracle-abcd1234").unwrap(),
+            "oracle-abcd1234"
+        );
+        assert_eq!(claimed(false, "oracle-abcd1234"), "oracle-abcd1234");
+    }
+
+    #[test]
+    fn issued_and_redeemed() {
+        let ticket = sign(&["key"], "oracle-abcd1234", NOW);
+        assert!(ticket.starts_with("oracle-abcd1234."));
+        assert_eq!(
+            verify(&["key"], 0, NOW, &ticket).unwrap(),
+            "oracle-abcd1234"
+        );
+        assert_eq!(claimed(true, &ticket), "oracle-abcd1234");
+    }
+
+    #[test]
+    fn pod_names_with_dots() {
+        let ticket = sign(&["key"], "oracle.v1.2.3-abcd1234", NOW);
+        assert_eq!(
+            verify(&["key"], 0, NOW, &ticket).unwrap(),
+            "oracle.v1.2.3-abcd1234"
+        );
+        assert_eq!(claimed(true, &ticket), "oracle.v1.2.3-abcd1234");
+    }
+
+    #[test]
+    fn forged() {
+        let ticket = sign(&["key"], "oracle-abcd1234", NOW);
+        let forged =
+            |ticket: &str| verify(&["key"], 0, NOW, ticket).unwrap_err().kind() == "ForgedTicket";
+        // Signed with a key that the ACM does not hold.
+        assert!(forged(&sign(&["another"], "oracle-abcd1234", NOW)));
+        // Somebody else's pod, under the signature of this one.
+        assert!(forged(&ticket.replacen("oracle", "mysql", 1)));
+        // A later execution date, under the signature of the original.
+        assert!(forged(&ticket.replacen(
+            &NOW.to_string(),
+            &(NOW + 3600).to_string(),
+            1
+        )));
+        // A signature that has been tampered with, or that is missing entirely.
+        let (claims, _) = ticket.rsplit_once('.').unwrap();
+        assert!(forged(&format!("{}.{}", claims, "0".repeat(64))));
+        assert!(forged(claims));
+        assert!(forged("oracle-abcd1234"));
+        // Claims that are correctly signed, but which are not those of a ticket.
+        let signature = hmac::sign(b"key", b"oracle-abcd1234");
+        assert!(forged(&format!("oracle-abcd1234.{}", signature)));
+        assert_eq!(claimed(true, "oracle-abcd1234"), "<malformed ticket>");
+    }
+
+    #[test]
+    fn expired() {
+        let ticket = sign(&["key"], "oracle-abcd1234", NOW);
+        assert!(verify(&["key"], 0, NOW, &ticket).is_ok());
+        let err = verify(&["key"], 0, NOW + 1, &ticket).unwrap_err();
+        assert_eq!(err.kind(), "ExpiredTicket");
+        // The grace period extends the life of the ticket along with that of the pod.
+        assert!(verify(&["key"], 60, NOW + 60, &ticket).is_ok());
+        assert!(verify(&["key"], 60, NOW + 61, &ticket).is_err());
+    }
+
+    #[test]
+    fn rotated_keys() {
+        let old = sign(&["old"], "oracle-abcd1234", NOW);
+        // Once rotated in, the new key signs every ticket while the old key is still honored.
+        let new = sign(&["new", "old"], "oracle-abcd1234", NOW);
+        assert!(verify(&["new"], 0, NOW, &new).is_ok());
+        assert!(verify(&["new", "old"], 0, NOW, &old).is_ok());
+        // Once rotated out, the old key's tickets are forgeries.
+        let err = verify(&["new"], 0, NOW, &old).unwrap_err();
+        assert_eq!(err.kind(), "ForgedTicket");
+    }
+}
@@ -0,0 +1,73 @@
+use super::tasks::Task;
+use super::PodId;
+use futures::FutureExt;
+use futures_util::{pin_mut, select};
+use k8s::usage::Peak;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use term_colors::*;
+use tokio::sync::oneshot::{channel, Receiver, Sender};
+
+/// A UsageSampler acts as a facade into the running coroutine that periodically samples the usage
+/// of a running connector pod, keeping track of the peak.
+///
+/// The coroutine shuts down as soon as its UsageSampler is dropped.
+pub struct UsageSampler {
+    peak: Arc<Mutex<Peak>>,
+    _sigint: Sender<()>,
+}
+
+impl UsageSampler {
+    /// Starts sampling the usage of the given pod every
+    /// [USAGE_SAMPLE_INTERVAL](config::acm::AcmConfig::usage_sample_interval) seconds, unless
+    /// sampling has been disabled.
+    pub fn start(pod: &PodId) -> Option<UsageSampler> {
+        if interval() == 0 {
+            return None;
+        }
+        let peak = Arc::new(Mutex::new(Peak::default()));
+        let (sigint, sigint_rx) = channel();
+        let task = Task::register("usage_sampler", pod);
+        tokio::spawn(Self::sample(pod.clone(), peak.clone(), sigint_rx, task));
+        Some(UsageSampler {
+            peak,
+            _sigint: sigint,
+        })
+    }
+
+    /// Returns the peak usage sampled so far.
+    pub fn peak(&self) -> Peak {
+        *self.peak.lock().unwrap()
+    }
+
+    async fn sample(pod: PodId, peak: Arc<Mutex<Peak>>, sigint: Receiver<()>, task: Task) {
+        let sigint = sigint.fuse();
+        pin_mut!(sigint);
+        let mut interval = tokio::time::interval(Duration::from_secs(interval()));
+        loop {
+            {
+                let tick = interval.tick().fuse();
+                pin_mut!(tick);
+                select! {
+                    // Either a signal or (far more likely) the UsageSampler was dropped.
+                    _ = sigint => return,
+                    _ = tick => ()
+                }
+            }
+            task.heartbeat();
+            match k8s::usage::usage(&pod.namespace, &pod.name).await {
+                Ok(usage) => peak.lock().unwrap().observe(&usage),
+                // Metrics lag a pod's start, and the cluster may not serve them at all.
+                Err(err) => trace!(
+                    "Failed to sample the usage of pod {}, {}",
+                    highlight(pod.to_string()),
+                    err
+                ),
+            }
+        }
+    }
+}
+
+fn interval() -> u64 {
+    crate::env::config().usage_sample_interval
+}
@@ -0,0 +1,255 @@
+//! The warm pool keeps idle, pre-warmed, connector pods running for each of the
+//! [configured](config::acm::AcmConfig::warm_pools) tags, such that a deploy of a pooled tag is
+//! handed a pod that has already pulled its image and booted rather than waiting on a cold start.
+//!
+//! Pooled pods live within the default [namespace](k8s::OCF_NAMESPACE) and are labeled with the
+//! [POOL_LABEL](POOL_LABEL) (whose value is their tag). They are not managed by a
+//! [PodManager](crate::podmanager::PodManager) until they are handed out, upon which the label is
+//! removed. As pooled pods are deployed without any [options](k8s::PodOptions), only deploys into
+//! the default namespace without any options are served from the pool. The pool is replenished in
+//! the background, and pooled pods that have sat idle for longer than the
+//! [maximum idle time](config::acm::AcmConfig::warm_pool_max_idle) are replaced with fresh ones.
+//!
+//! Pools are kept in memory. The pooled pods of an ACM that has since gone away may be cleaned up
+//! by [bulk deleting](crate::delete_bulk()) with the `ocf.alation.com/pool` selector.
+use crate::env;
+use crate::podmanager::deletions;
+use crate::podmanager::PodId;
+use k8s::deletion::DeletionCause;
+use k8s::display::DISPLAY_NAME_ANNOTATION;
+use k8s::{PodApi, PodOptions};
+use k8s_openapi::api::core::v1::Pod;
+use kube::ResourceExt;
+use result::Result;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use term_colors::*;
+use tokio::sync::Notify;
+
+/// The label that marks a pooled pod, whose value is the pod's tag.
+pub const POOL_LABEL: &str = "ocf.alation.com/pool";
+
+/// The name that pooled pods are deployed under (with a random suffix).
+pub const POOLED_NAME: &str = "pooled";
+
+/// How often the pools are replenished, should nothing hand out a pooled pod in the meantime.
+const REPLENISH_INTERVAL: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    static ref POOLS: Mutex<HashMap<String, VecDeque<Pooled>>> = Mutex::new(HashMap::new());
+    static ref REPLENISH: Notify = Notify::new();
+}
+
+/// A Pooled pod is the name of an idle pod alongside the moment that it joined the pool.
+struct Pooled {
+    name: String,
+    since: Instant,
+}
+
+/// Spawns the coroutine that replenishes the pools, should there be any.
+pub fn configure() {
+    if env::config().warm_pools.is_empty() {
+        return;
+    }
+    info!(
+        "Keeping warm pools of {}",
+        highlight(
+            env::config()
+                .warm_pools
+                .iter()
+                .map(|(tag, size)| format!("{}={}", tag, size))
+                .collect::<Vec<String>>()
+                .join(",")
+        )
+    );
+    tokio::spawn(async {
+        let pods = crate::podmanager::pods().await;
+        loop {
+            replenish(&*pods).await;
+            // Whichever comes first, the interval or a pooled pod being handed out.
+            let _ = tokio::time::timeout(REPLENISH_INTERVAL, REPLENISH.notified()).await;
+        }
+    });
+}
+
+/// Hands out a running pod of the given tag out of the pool, should a deploy into the given
+/// namespace with the given options be eligible for one and should there be one. The pod is
+/// relabeled with the given TTL (and annotated with the given [display name](k8s::display)) and
+/// is no longer pooled.
+///
+/// Pooled pods that are still starting up are skipped over, while those that have stopped running
+/// are deleted.
+pub async fn claim(
+    pods: &dyn PodApi,
+    tag: &str,
+    name: &str,
+    namespace: &str,
+    options: &PodOptions,
+    ttl: u64,
+) -> Option<Pod> {
+    if namespace != k8s::OCF_NAMESPACE || *options != PodOptions::default() {
+        return None;
+    }
+    let candidates: Vec<String> = POOLS
+        .lock()
+        .unwrap()
+        .get(tag)?
+        .iter()
+        .map(|pooled| pooled.name.clone())
+        .collect();
+    for pooled in candidates {
+        let pod = match pods.get(k8s::OCF_NAMESPACE, &pooled).await {
+            Ok(pod) => pod,
+            Err(err) => {
+                warn!(
+                    "The pooled pod {} could not be retrieved, {}",
+                    highlight(pooled.clone()),
+                    err
+                );
+                forget(tag, &pooled);
+                continue;
+            }
+        };
+        match pod
+            .status
+            .as_ref()
+            .and_then(|status| status.phase.as_deref())
+        {
+            Some("Running") => (),
+            Some("Pending") | None => continue,
+            Some(phase) => {
+                warn!(
+                    "The pooled pod {} is {} rather than running, deleting it",
+                    highlight(pooled.clone()),
+                    phase
+                );
+                if forget(tag, &pooled) {
+                    let cause = DeletionCause::IllBehaved {
+                        kind: "PooledPodNotRunning".to_string(),
+                    };
+                    deletions::delete(pods, &PodId::new(k8s::OCF_NAMESPACE, &pooled), cause).await;
+                }
+                continue;
+            }
+        }
+        // Another deploy may have claimed the very same pod in the meantime.
+        if !forget(tag, &pooled) {
+            continue;
+        }
+        REPLENISH.notify_one();
+        let patch = serde_json::json!({
+            "metadata": {
+                "labels": {POOL_LABEL: null, "ttl": ttl.to_string()},
+                "annotations": {DISPLAY_NAME_ANNOTATION: name}
+            }
+        });
+        match pods.patch(k8s::OCF_NAMESPACE, &pooled, &patch).await {
+            Ok(pod) => {
+                info!(
+                    "Handed out the pooled pod {} of {}",
+                    highlight(pooled),
+                    highlight(tag)
+                );
+                return Some(pod);
+            }
+            Err(err) => {
+                warn!(
+                    "The pooled pod {} could not be handed out, deleting it. {}",
+                    highlight(pooled.clone()),
+                    err
+                );
+                let cause = DeletionCause::IllBehaved {
+                    kind: "PooledPodUnclaimable".to_string(),
+                };
+                deletions::delete(pods, &PodId::new(k8s::OCF_NAMESPACE, pooled), cause).await;
+            }
+        }
+    }
+    None
+}
+
+/// Removes the given pod from the pool of the given tag, returning whether it was pooled.
+fn forget(tag: &str, name: &str) -> bool {
+    let mut pools = POOLS.lock().unwrap();
+    let pool = match pools.get_mut(tag) {
+        Some(pool) => pool,
+        None => return false,
+    };
+    match pool.iter().position(|pooled| pooled.name == name) {
+        Some(index) => pool.remove(index).is_some(),
+        None => false,
+    }
+}
+
+/// Replaces every pooled pod that has sat idle for too long, and then deploys however many pods
+/// each pool is short of.
+async fn replenish(pods: &dyn PodApi) {
+    let config = env::config();
+    let max_idle = Duration::from_secs(config.warm_pool_max_idle);
+    for (tag, size) in &config.warm_pools {
+        let (stale, fresh): (VecDeque<Pooled>, VecDeque<Pooled>) = POOLS
+            .lock()
+            .unwrap()
+            .remove(tag)
+            .unwrap_or_default()
+            .into_iter()
+            .partition(|pooled| pooled.since.elapsed() > max_idle);
+        let missing = size.saturating_sub(fresh.len());
+        POOLS.lock().unwrap().insert(tag.clone(), fresh);
+        for pooled in stale {
+            debug!(
+                "The pooled pod {} of {} has sat idle for too long, replacing it",
+                highlight(pooled.name.clone()),
+                highlight(tag.clone())
+            );
+            let id = PodId::new(k8s::OCF_NAMESPACE, pooled.name);
+            deletions::delete(pods, &id, DeletionCause::TtlExpired).await;
+        }
+        for _ in 0..missing {
+            match deploy(pods, tag).await {
+                Ok(name) => POOLS
+                    .lock()
+                    .unwrap()
+                    .entry(tag.clone())
+                    .or_default()
+                    .push_back(Pooled {
+                        name,
+                        since: Instant::now(),
+                    }),
+                Err(err) => {
+                    warn!(
+                        "Failed to replenish the warm pool of {}, {}",
+                        highlight(tag.clone()),
+                        err
+                    );
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Deploys a pod of the given tag into the pool, returning its name.
+async fn deploy(pods: &dyn PodApi, tag: &str) -> Result<String> {
+    let config = env::config();
+    let reference = format!("{}/{}:{}", config.registry, config.repository, tag);
+    let pod = k8s::deploy(
+        pods,
+        k8s::OCF_NAMESPACE,
+        reference,
+        POOLED_NAME,
+        config.warm_pool_max_idle,
+        true,
+        &PodOptions::default(),
+        false,
+    )
+    .await?;
+    let patch = serde_json::json!({
+        "metadata": {"labels": {POOL_LABEL: tag}}
+    });
+    pods.patch(k8s::OCF_NAMESPACE, &pod.name(), &patch)
+        .await
+        .map_err(k8s::errors::ApiError::from)?;
+    Ok(pod.name())
+}
@@ -0,0 +1,105 @@
+//! The checks that are run against an image before it is deployed. Each check is handed the
+//! AIM's [Inspection](client_sdk::Inspection) of the image, which is retrieved only once.
+use crate::{deprecation, platform};
+use client_sdk::{Client, ClientError, Lookup};
+use error::*;
+use k8s::scheduling::Scheduling;
+use k8s::PodOptions;
+use result::Result;
+use std::time::Duration;
+use term_colors::*;
+
+/// How long any one call to the AIM is retried for. Pre-flight calls sit in the path of every
+/// deploy, so an ailing AIM should cost a deploy a few seconds rather than the SDK's
+/// [default](client_sdk::DEFAULT_RETRY_BUDGET) half a minute.
+const AIM_RETRY_BUDGET: Duration = Duration::from_secs(3);
+
+lazy_static! {
+    static ref AIM: Client = Client::new(client_sdk::DEFAULT_ACM, crate::env::config().aim.clone())
+        .with_retry_budget(AIM_RETRY_BUDGET);
+}
+
+/// Returns the client of the [configured](config::acm::AcmConfig::aim) AIM with which every call
+/// to the AIM in the path of a deploy is made.
+pub fn aim() -> &'static Client {
+    &AIM
+}
+
+/// Asks the AIM whether the given tag is installed, failing with the AIM's own `404` TagNotFound
+/// should it not be. A typo'd tag is thereby refused up front rather than surfacing minutes later
+/// as an `ErrImagePull`.
+///
+/// This is skipped altogether unless [enabled](config::acm::AcmConfig::validate_tags). Should the
+/// AIM be unreachable (or fail in any other way), then a warning is logged and the deploy is
+/// allowed to proceed unvalidated, just as with the [pre-flight checks](check).
+pub async fn validate<T: AsRef<str>>(tag: T) -> Result<()> {
+    if !crate::env::config().validate_tags {
+        return Ok(());
+    }
+    match aim().get(tag.as_ref()).await {
+        Ok(_) => Ok(()),
+        Err(err @ ClientError::NotFound(_)) => Err(err.into()),
+        Err(err) => {
+            warn!(
+                "Skipping the validation of {}, the AIM could not be asked about it. {}",
+                highlight(tag.as_ref()),
+                err
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Runs every pre-flight check against the given tag as it would be deployed with the given
+/// options, returning the warnings (if any) that ought to be passed along to the caller of the
+/// deploy.
+///
+/// Should the AIM be unable to inspect the image, then a warning is logged and the deploy is
+/// allowed to proceed unchecked.
+pub async fn check<T: AsRef<str>>(tag: T, options: &PodOptions) -> Result<Vec<String>> {
+    let inspection = match aim().inspect(tag.as_ref()).await {
+        Ok(inspection) => inspection,
+        Err(err) => {
+            warn!(
+                "Skipping the pre-flight checks for {}, the AIM could not inspect it. {}",
+                highlight(tag.as_ref()),
+                err
+            );
+            return Ok(vec![]);
+        }
+    };
+    let scheduling = Scheduling::configured().merge(&options.scheduling);
+    platform::check(tag.as_ref(), &inspection, &scheduling).await?;
+    Ok(deprecation::check(&inspection)?.into_iter().collect())
+}
+
+/// Looks up the given tag within the AIM, failing with an [ImageNotInstalled](ImageNotInstalled)
+/// should it not be installed.
+///
+/// Unlike [check](check), an AIM that cannot be reached fails the lookup rather than being
+/// skipped, as the lookup is only made by a [dry-run](crate::dry_run) whose entire purpose is to
+/// tell the caller whether the deploy would succeed.
+pub async fn lookup<T: AsRef<str>>(tag: T) -> Result<Lookup> {
+    let lookup = aim().lookup(tag.as_ref()).await?;
+    if lookup.installed {
+        Ok(lookup)
+    } else {
+        Err(ImageNotInstalled {
+            tag: lookup.tag,
+            reference: lookup.reference,
+        }
+        .into())
+    }
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::NotFound)]
+#[error(
+    "The tag {tag} is not installed, so {reference} cannot be pulled. Please install the \
+connector via the AIM before deploying it."
+)]
+#[error_code("ACM-2600")]
+pub struct ImageNotInstalled {
+    tag: String,
+    reference: String,
+}
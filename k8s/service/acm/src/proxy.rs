@@ -0,0 +1,295 @@
+//! The proxy forwards HTTP (and gRPC-web) requests made to the ACM on to the connectors that it
+//! manages, so that Alation needs only to reach the ACM rather than every connector pod (which in
+//! turn lets connector pods be [isolated](k8s::network_policy) to the ACM alone). It is only
+//! mounted should the ACM be [configured](config::acm::AcmConfig::proxy) to, which it may only be
+//! alongside [ticket signing keys](config::acm::AcmConfig::ticket_signing_keys).
+//!
+//! A request to `/proxy/<id>/<path>` is forwarded to `<path>` of the connector of the pod of the
+//! given ID, so long as it bears the pod's keep-alive ticket within its [TICKET_HEADER](TICKET_HEADER).
+//! That is, only the owner of a pod may call it. The request's method, headers (save for those
+//! that are [hop-by-hop](HOP_BY_HOP) and the ticket itself), query (save for the ACM's own
+//! `namespace`) and body are forwarded as they are, as is the connector's response.
+//!
+//! gRPC-web is plain HTTP/1.1 (its trailers being carried within the body), and so is proxied
+//! like any other request. Native gRPC, which relies upon HTTP/2 trailers, is not. Both requests
+//! and responses are buffered in full, so a server streaming gRPC-web call is answered all at
+//! once as the stream ends.
+use crate::podmanager::{tickets, PodId, PodManager};
+use error::*;
+use k8s::PodExt;
+use result::Result;
+use rocket::data::{ByteUnit, Data};
+use rocket::http::Method;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::Responder;
+use std::io::Cursor;
+use std::time::Duration;
+use term_colors::*;
+
+pub use crate::podmanager::tickets::TICKET_HEADER;
+
+/// The largest body that may be forwarded to a connector.
+pub const MAXIMUM_BODY: ByteUnit = ByteUnit::Mebibyte(16);
+
+/// The headers that describe a single connection rather than the request (or response) itself,
+/// and so are never forwarded.
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "content-length",
+];
+
+/// The maximum amount of time that connecting to a connector may take.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    static ref CLIENT: reqwest::Client = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("failed to build the proxy client");
+}
+
+/// A Forward is a request guard that captures everything about a request to the proxy that is to
+/// be forwarded on to its connector.
+pub struct Forward {
+    method: reqwest::Method,
+    /// The path (and query) of the request, relative to the connector.
+    target: String,
+    headers: Vec<(String, String)>,
+    ticket: Option<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Forward {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Forward::of(request))
+    }
+}
+
+impl Forward {
+    fn of(request: &Request<'_>) -> Forward {
+        // The path is given exactly as it was requested (percent encoding and all), minus the
+        // leading /proxy/<id>.
+        let path = request
+            .uri()
+            .path()
+            .as_str()
+            .trim_start_matches('/')
+            .splitn(3, '/')
+            .nth(2)
+            .unwrap_or("");
+        let query = request
+            .uri()
+            .query()
+            .map(|query| {
+                query
+                    .as_str()
+                    .split('&')
+                    .filter(|pair| !pair.is_empty() && !pair.starts_with("namespace="))
+                    .collect::<Vec<_>>()
+                    .join("&")
+            })
+            .filter(|query| !query.is_empty());
+        let target = match query {
+            Some(query) => format!("/{}?{}", path, query),
+            None => format!("/{}", path),
+        };
+        let headers = request
+            .headers()
+            .iter()
+            .filter(|header| forwarded(header.name().as_str()))
+            .filter(|header| !header.name().as_str().eq_ignore_ascii_case(TICKET_HEADER))
+            .map(|header| (header.name().to_string(), header.value().to_string()))
+            .collect();
+        Forward {
+            method: method(request.method()),
+            target,
+            headers,
+            ticket: request.headers().get_one(TICKET_HEADER).map(str::to_string),
+        }
+    }
+}
+
+/// Forwards the given request (and its body) to the connector of the pod of the given ID, on
+/// behalf of the owner of the pod's ticket.
+///
+/// A request without a ticket is refused with a [MissingTicket](MissingTicket), and one whose
+/// ticket is for another pod with a [NotTicketOwner](NotTicketOwner). Forged and expired tickets
+/// are refused just as a [refresh](crate::refresh()) would refuse them. A request to a pod that
+/// has yet to come online waits for it to, as a [wait](crate::wait()) would.
+pub async fn forward(id: PodId, request: Forward, body: Option<Data<'_>>) -> Result<Proxied> {
+    let ticket = request.ticket.as_deref().ok_or(MissingTicket {})?;
+    if tickets::redeem(ticket)? != id.name {
+        return Err(NotTicketOwner {
+            pod: id.to_string(),
+        }
+        .into());
+    }
+    let manager = PodManager::get(&id).await?;
+    let wait = manager.lock().await.wait();
+    let pod = wait.await?;
+    let body = match body {
+        Some(body) => read(body).await?,
+        None => vec![],
+    };
+    let url = format!("http://{}{}", pod.address()?, request.target);
+    debug!(
+        "Proxying {} {} to {}",
+        request.method,
+        highlight(request.target.as_str()),
+        highlight(id.to_string())
+    );
+    let mut outgoing = CLIENT.request(request.method, &url).body(body);
+    for (name, value) in request.headers {
+        outgoing = outgoing.header(name, value);
+    }
+    let unreachable = |err: reqwest::Error| ConnectorUnreachable {
+        pod: id.to_string(),
+        cause: err.to_string().into(),
+    };
+    let response = outgoing.send().await.map_err(unreachable)?;
+    let status = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| forwarded(name.as_str()))
+        .filter_map(|(name, value)| {
+            value
+                .to_str()
+                .ok()
+                .map(|value| (name.to_string(), value.to_string()))
+        })
+        .collect();
+    let body = response.bytes().await.map_err(unreachable)?.to_vec();
+    Ok(Proxied {
+        status,
+        headers,
+        body,
+    })
+}
+
+/// Reads the given body in full, refusing one that is larger than [MAXIMUM_BODY](MAXIMUM_BODY)
+/// with a [BodyTooLarge](BodyTooLarge).
+async fn read(body: Data<'_>) -> Result<Vec<u8>> {
+    let body = body
+        .open(MAXIMUM_BODY)
+        .into_bytes()
+        .await
+        .map_err(|err| UnreadableBody {
+            cause: err.to_string().into(),
+        })?;
+    if !body.is_complete() {
+        return Err(BodyTooLarge {
+            limit: MAXIMUM_BODY.to_string(),
+        }
+        .into());
+    }
+    Ok(body.into_inner())
+}
+
+/// Returns whether the header of the given name is forwarded, which is to say whether it is not
+/// [hop-by-hop](HOP_BY_HOP).
+fn forwarded(name: &str) -> bool {
+    !HOP_BY_HOP
+        .iter()
+        .any(|hop_by_hop| name.eq_ignore_ascii_case(hop_by_hop))
+}
+
+/// Returns the given method as understood by the proxy's client.
+fn method(method: Method) -> reqwest::Method {
+    match method {
+        Method::Get => reqwest::Method::GET,
+        Method::Put => reqwest::Method::PUT,
+        Method::Post => reqwest::Method::POST,
+        Method::Delete => reqwest::Method::DELETE,
+        Method::Options => reqwest::Method::OPTIONS,
+        Method::Head => reqwest::Method::HEAD,
+        Method::Trace => reqwest::Method::TRACE,
+        Method::Connect => reqwest::Method::CONNECT,
+        Method::Patch => reqwest::Method::PATCH,
+    }
+}
+
+/// Proxied is the response of a connector to a [forwarded](forward) request.
+///
+/// Unlike every other endpoint in the ACM, proxied responses are NOT wrapped within the
+/// [response envelope](response::Response), as they are the connector's rather than the ACM's.
+/// For the same reason they are [exempt](response::Compression::exempt) from compression.
+pub struct Proxied {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for Proxied {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'o> {
+        response::Compression::exempt(request);
+        let mut response = rocket::Response::build();
+        response.status(rocket::http::Status::new(self.status));
+        for (name, value) in self.headers {
+            response.raw_header_adjoin(name, value);
+        }
+        response.sized_body(self.body.len(), Cursor::new(self.body));
+        Ok(response.finalize())
+    }
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::Unauthorized)]
+#[error(
+    "Requests proxied to a connector must bear the keep-alive ticket of its pod within their \
+X-OCF-Ticket header, exactly as the most recent wait or refresh returned it."
+)]
+#[error_code("ACM-3300")]
+pub struct MissingTicket {}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::Forbidden)]
+#[error(
+    "The given ticket is not for the pod {pod}. Only the owner of a pod (that is, the holder of \
+its ticket) may call its connector through the ACM (Alation Connector Manager)."
+)]
+#[error_code("ACM-3301")]
+pub struct NotTicketOwner {
+    pod: String,
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::PayloadTooLarge)]
+#[error("The ACM (Alation Connector Manager) proxies bodies of no more than {limit}.")]
+#[error_code("ACM-3302")]
+pub struct BodyTooLarge {
+    limit: String,
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::BadRequest)]
+#[error("The body of the request to be proxied could not be read.")]
+#[error_code("ACM-3303")]
+pub struct UnreadableBody {
+    #[source]
+    cause: StringError,
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::BadGateway)]
+#[error(
+    "The connector of the pod {pod} could not be reached (or did not answer) on behalf of the \
+proxied request."
+)]
+#[error_code("ACM-3304")]
+pub struct ConnectorUnreachable {
+    pod: String,
+    #[source]
+    cause: StringError,
+}
@@ -0,0 +1,364 @@
+use error::*;
+use rocket::http::Header;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::Responder;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use term_colors::layout::{Cell, Table};
+use term_colors::Role;
+
+/// The environment variable that configures the per endpoint rate limits. It is a comma
+/// separated list of `<endpoint>=<burst>:<refill>` entries, where `<endpoint>` is the name of the
+/// endpoint's handler (e.g. `deploy` or `wait_delete`), `<burst>` is the number of requests that
+/// a single client may make in a burst, and `<refill>` is the number of requests per second that
+/// the client regains thereafter. For example, `deploy=100:2,refresh=1000:50`.
+///
+/// Endpoints that are not listed are not rate limited. This is one of the ACM's
+/// [tunables](crate::reload), and so the limits may be changed without restarting the ACM.
+pub const RATE_LIMITS: &str = "RATE_LIMITS";
+
+/// The number of buckets beyond which buckets that have completely refilled are forgotten. A
+/// full bucket is indistinguishable from one that was never created, so forgetting it is free.
+const MAXIMUM_TRACKED_BUCKETS: usize = 10_000;
+
+lazy_static! {
+    static ref LIMITER: Limiter = Limiter::configured();
+}
+
+/// Forces the evaluation of the [RATE_LIMITS](RATE_LIMITS) configuration. This SHOULD be called
+/// on program startup so that a misconfiguration panics immediately rather than on the first request.
+pub fn configure() {
+    lazy_static::initialize(&LIMITER);
+}
+
+/// Replaces the rate limits of every endpoint. Clients keep the tokens that they had, save for
+/// any beyond the new burst.
+pub fn reconfigure(limits: Limits) {
+    log_limits(&limits.0);
+    *LIMITER.limits.write().unwrap() = limits.0;
+}
+
+/// Returns the number of requests that have been throttled since startup, by endpoint.
+pub fn throttled() -> BTreeMap<String, u64> {
+    LIMITER.throttled.lock().unwrap().clone()
+}
+
+/// A Quota is a request guard that admits a request so long as its client has not exceeded the
+/// [rate limit](RATE_LIMITS) of the requested endpoint.
+///
+/// Every client is given its own token bucket per endpoint, which is why a single runaway Alation
+/// instance hammering `/deploy` is throttled without affecting any other client (nor any other
+/// endpoint). Clients are told apart by who they can be [proven](client) to be, rather than by
+/// who they claim to be, lest a client dodge its limit by naming itself anew with every request.
+/// Throttled requests fail with a `429 Too Many Requests`, which is rendered by the
+/// [too_many_requests](too_many_requests) catcher.
+pub struct Quota;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Quota {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let endpoint = match request.route().and_then(|route| route.name.as_deref()) {
+            Some(endpoint) => endpoint,
+            None => return Outcome::Success(Quota),
+        };
+        let client = client(request);
+        match LIMITER.withdraw(endpoint, &client, Instant::now()) {
+            Ok(()) => Outcome::Success(Quota),
+            Err(retry_after) => {
+                warn!(
+                    "Throttled a request to {} from {}",
+                    term_colors::highlight(endpoint),
+                    term_colors::highlight(client.as_str())
+                );
+                // Stashed away for the sake of the catcher, which cannot otherwise know.
+                request.local_cache(|| RetryAfter(retry_after));
+                Outcome::Failure((rocket::http::Status::TooManyRequests, ()))
+            }
+        }
+    }
+}
+
+/// Returns the key of the bucket of the client that made the given request. Requests that bear the
+/// [operator's token](crate::auth::Operator) share the operator's bucket, while every other
+/// request is keyed by the address that it was received from. The `X-Client-Id` and `X-Real-IP`
+/// headers are ignored, as they are whatever the client says they are.
+fn client(request: &Request<'_>) -> String {
+    if crate::auth::is_operator(request) {
+        return "operator".to_string();
+    }
+    request
+        .remote()
+        .map(|remote| remote.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// The number of seconds that a throttled client is advised to wait.
+struct RetryAfter(u64);
+
+/// Renders a [RateLimited](RateLimited) error alongside a `Retry-After` header.
+#[catch(429)]
+pub fn too_many_requests(request: &Request<'_>) -> Throttled {
+    Throttled {
+        retry_after: request.local_cache(|| RetryAfter(1)).0,
+    }
+}
+
+/// A Throttled is the response given to a request that was turned away by its [Quota](Quota).
+pub struct Throttled {
+    retry_after: u64,
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for Throttled {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'o> {
+        let err: Box<dyn AcmError> = RateLimited {
+            retry_after: self.retry_after,
+        }
+        .into();
+        let mut response = err.respond_to(request)?;
+        response.set_header(Header::new("Retry-After", self.retry_after.to_string()));
+        Ok(response)
+    }
+}
+
+/// Limits are the rate limits of every endpoint, as parsed out of a [RATE_LIMITS](RATE_LIMITS)
+/// configuration.
+pub struct Limits(HashMap<String, Limit>);
+
+impl FromStr for Limits {
+    type Err = String;
+
+    fn from_str(limits: &str) -> std::result::Result<Self, Self::Err> {
+        parse(limits).map(Limits)
+    }
+}
+
+/// A Limit is the configured token bucket of a single endpoint.
+#[derive(Debug, Clone, Copy)]
+struct Limit {
+    burst: f64,
+    refill: f64,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: Limit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.refill).min(limit.burst);
+        self.updated = now;
+    }
+}
+
+struct Limiter {
+    limits: RwLock<HashMap<String, Limit>>,
+    buckets: Mutex<HashMap<(String, String), Bucket>>,
+    throttled: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Limiter {
+    /// Constructs the Limiter configured by the [RATE_LIMITS](RATE_LIMITS) tunable.
+    ///
+    /// This function PANICS if the tunable is malformed.
+    fn configured() -> Limiter {
+        let limits = crate::reload::tunables().rate_limits.clone();
+        let limits = parse(&limits).unwrap_or_else(|err| {
+            panic!(
+                "The {} setting is malformed, {}. Got '{}'",
+                RATE_LIMITS, err, limits
+            )
+        });
+        log_limits(&limits);
+        Limiter {
+            limits: RwLock::new(limits),
+            buckets: Mutex::new(HashMap::new()),
+            throttled: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Withdraws a single token from the given client's bucket for the given endpoint. Should the
+    /// bucket be empty, then the number of seconds until a token is refilled is returned instead.
+    fn withdraw(&self, endpoint: &str, client: &str, now: Instant) -> std::result::Result<(), u64> {
+        let limits = self.limits.read().unwrap();
+        let limit = match limits.get(endpoint) {
+            Some(limit) => *limit,
+            None => return Ok(()),
+        };
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAXIMUM_TRACKED_BUCKETS {
+            buckets.retain(|(endpoint, _), bucket| match limits.get(endpoint) {
+                Some(limit) => {
+                    bucket.refill(*limit, now);
+                    bucket.tokens < limit.burst
+                }
+                None => false,
+            });
+        }
+        let bucket = buckets
+            .entry((endpoint.to_string(), client.to_string()))
+            .or_insert(Bucket {
+                tokens: limit.burst,
+                updated: now,
+            });
+        bucket.refill(limit, now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        let wait = Duration::from_secs_f64((1.0 - bucket.tokens) / limit.refill);
+        drop(buckets);
+        drop(limits);
+        *self
+            .throttled
+            .lock()
+            .unwrap()
+            .entry(endpoint.to_string())
+            .or_default() += 1;
+        Err(wait.as_secs_f64().ceil().max(1.0) as u64)
+    }
+}
+
+/// Logs the given rate limits as a table, should there be any.
+fn log_limits(limits: &HashMap<String, Limit>) {
+    if limits.is_empty() {
+        return;
+    }
+    let mut table = Table::new(vec!["ENDPOINT", "BURST", "REFILL PER SECOND"]);
+    for (endpoint, limit) in limits.iter().collect::<BTreeMap<_, _>>() {
+        table.push(vec![
+            Cell::new(endpoint).role(Role::Highlight),
+            Cell::new(limit.burst),
+            Cell::new(limit.refill),
+        ]);
+    }
+    info!("Rate limiting the following endpoints\n{}", table);
+}
+
+/// Parses a [RATE_LIMITS](RATE_LIMITS) configuration.
+fn parse(limits: &str) -> std::result::Result<HashMap<String, Limit>, String> {
+    let mut parsed = HashMap::new();
+    for entry in limits.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (endpoint, limit) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("'{}' is not of the form <endpoint>=<burst>:<refill>", entry))?;
+        let (burst, refill) = limit
+            .split_once(':')
+            .ok_or_else(|| format!("'{}' is not of the form <endpoint>=<burst>:<refill>", entry))?;
+        let burst = match burst.trim().parse::<u32>() {
+            Ok(burst) if burst > 0 => burst as f64,
+            _ => {
+                return Err(format!(
+                    "the burst of '{}' must be a positive integer",
+                    entry
+                ))
+            }
+        };
+        let refill = match refill.trim().parse::<f64>() {
+            Ok(refill) if refill > 0.0 && refill.is_finite() => refill,
+            _ => {
+                return Err(format!(
+                    "the refill of '{}' must be a positive number",
+                    entry
+                ))
+            }
+        };
+        parsed.insert(endpoint.trim().to_string(), Limit { burst, refill });
+    }
+    Ok(parsed)
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::TooManyRequests)]
+#[error(
+    "This client has exceeded the rate limit of this ACM endpoint. Please try again in \
+{retry_after} seconds. If this client legitimately requires more throughput, then the limit may \
+be raised via the ACM's RATE_LIMITS configuration."
+)]
+#[error_code("ACM-1900")]
+pub struct RateLimited {
+    retry_after: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(limits: &str) -> Limiter {
+        Limiter {
+            limits: RwLock::new(parse(limits).unwrap()),
+            buckets: Mutex::new(HashMap::new()),
+            throttled: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    #[test]
+    fn parses() {
+        let limits = parse("deploy=100:2, refresh = 1000:0.5,").unwrap();
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits["deploy"].burst, 100.0);
+        assert_eq!(limits["deploy"].refill, 2.0);
+        assert_eq!(limits["refresh"].burst, 1000.0);
+        assert_eq!(limits["refresh"].refill, 0.5);
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects() {
+        for malformed in &[
+            "deploy",
+            "deploy=100",
+            "deploy=0:2",
+            "deploy=-1:2",
+            "deploy=100:0",
+            "deploy=100:inf",
+            "deploy=lots:2",
+        ] {
+            assert!(parse(malformed).is_err(), "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn withdraws_until_empty() {
+        let limiter = limiter("deploy=2:1");
+        let now = Instant::now();
+        assert_eq!(limiter.withdraw("deploy", "10.0.0.1", now), Ok(()));
+        assert_eq!(limiter.withdraw("deploy", "10.0.0.1", now), Ok(()));
+        assert_eq!(limiter.withdraw("deploy", "10.0.0.1", now), Err(1));
+        assert_eq!(throttled_of(&limiter, "deploy"), 1);
+        // Every other client, and every other endpoint, is unaffected.
+        assert_eq!(limiter.withdraw("deploy", "10.0.0.2", now), Ok(()));
+        assert_eq!(limiter.withdraw("refresh", "10.0.0.1", now), Ok(()));
+    }
+
+    #[test]
+    fn refills() {
+        let limiter = limiter("deploy=1:0.25");
+        let now = Instant::now();
+        assert_eq!(limiter.withdraw("deploy", "10.0.0.1", now), Ok(()));
+        // A quarter of a token a second leaves four seconds to wait.
+        assert_eq!(limiter.withdraw("deploy", "10.0.0.1", now), Err(4));
+        let later = now + Duration::from_secs(2);
+        assert_eq!(limiter.withdraw("deploy", "10.0.0.1", later), Err(2));
+        let refilled = now + Duration::from_secs(4);
+        assert_eq!(limiter.withdraw("deploy", "10.0.0.1", refilled), Ok(()));
+    }
+
+    #[test]
+    fn never_overfills() {
+        let limiter = limiter("deploy=2:10");
+        let later = Instant::now() + Duration::from_secs(60);
+        assert_eq!(limiter.withdraw("deploy", "10.0.0.1", later), Ok(()));
+        assert_eq!(limiter.withdraw("deploy", "10.0.0.1", later), Ok(()));
+        assert_eq!(limiter.withdraw("deploy", "10.0.0.1", later), Err(1));
+    }
+
+    fn throttled_of(limiter: &Limiter, endpoint: &str) -> u64 {
+        limiter.throttled.lock().unwrap()[endpoint]
+    }
+}
@@ -0,0 +1,238 @@
+//! The tunables of the ACM are those of its settings that may be changed while it runs, namely
+//! the [default TTL](AcmTunables::default_ttl) (and those of
+//! [particular clients](AcmTunables::default_ttls)), the [TTL bounds](AcmTunables::min_ttl), the
+//! [health check timeout](AcmTunables::health_check_timeout) (and
+//! [mode](AcmTunables::health_check_mode) and [service](AcmTunables::health_check_service)), the
+//! [health probes](AcmTunables::health_probe_interval) of connectors that are online, the
+//! [scheduling timeout](AcmTunables::scheduling_timeout), the
+//! [idempotency window](AcmTunables::idempotency_window), the
+//! [maximum lifetime](AcmTunables::max_lifetime), the
+//! [garbage collection grace period](AcmTunables::gc_grace_period), the
+//! [rate limits](crate::ratelimit::RATE_LIMITS), and the [log filter](AcmTunables::log_filter).
+//!
+//! Tunables are reloaded out of the [configuration file](config::CONFIG_FILE) whenever the ACM
+//! receives a `SIGHUP` or the file changes, so that operators need not restart the ACM (and
+//! strand every connector that it manages) to change them. A reload is all or nothing. Should any
+//! tunable be invalid, then the reload is rejected and every tunable keeps its current value.
+//!
+//! Every reload that is applied increments the configuration's generation, which is reported by
+//! the [status](crate::status()) endpoint.
+use crate::ratelimit::{self, Limits};
+use config::acm::AcmTunables;
+use config::Invalid;
+use futures::FutureExt;
+use futures_util::{pin_mut, select};
+use kind::Kind;
+use log::{Log, Metadata, Record};
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use term_colors::*;
+use tokio::signal::unix::{signal, SignalKind};
+
+/// How often the [configuration file](config::CONFIG_FILE) is checked for changes.
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    static ref STATE: RwLock<State> = RwLock::new(State::configured());
+    static ref LOGGER: Logger = Logger(RwLock::new(logger(&tunables().log_filter)));
+}
+
+/// Loads the tunables of the ACM and installs the logger that writes through the configured log
+/// filter. This MUST be called upon startup in place of initializing any other logger.
+///
+/// This function PANICS, describing every problem, should the tunables be invalid.
+pub fn configure() {
+    lazy_static::initialize(&STATE);
+    log::set_logger(&*LOGGER)
+        .map(|()| log::set_max_level(LOGGER.0.read().unwrap().filter()))
+        .unwrap_or_else(|err| panic!("A logger was already installed, {}", err));
+}
+
+/// Returns the tunables of the ACM as of the latest reload.
+pub fn tunables() -> Arc<AcmTunables> {
+    STATE.read().unwrap().tunables.clone()
+}
+
+/// Returns the [status](Status) of the ACM's configuration.
+pub fn status() -> Status {
+    let state = STATE.read().unwrap();
+    Status {
+        generation: state.generation,
+        reloaded_at: state.reloaded_at,
+        rejected: state.rejected.clone(),
+        tunables: state.tunables.as_ref().clone(),
+    }
+}
+
+/// Spawns the coroutine that reloads the tunables of the ACM whenever it receives a `SIGHUP` or
+/// the [configuration file](config::CONFIG_FILE) changes.
+pub fn watch() {
+    tokio::spawn(async move {
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => Some(hangups),
+            Err(err) => {
+                warn!(
+                    "Could not listen for SIGHUP, the configuration will only be reloaded \
+                    when its file changes. {}",
+                    err
+                );
+                None
+            }
+        };
+        let mut last = contents().await;
+        loop {
+            let hangup = {
+                let tick = tokio::time::sleep(CONFIG_POLL_INTERVAL).fuse();
+                let hangup = async {
+                    match hangups.as_mut() {
+                        Some(hangups) => hangups.recv().await,
+                        None => futures::future::pending().await,
+                    }
+                }
+                .fuse();
+                pin_mut!(tick, hangup);
+                select! {
+                    _ = tick => None,
+                    hangup = hangup => Some(hangup),
+                }
+            };
+            match hangup {
+                Some(Some(())) => info!("Received a SIGHUP, reloading the configuration"),
+                // The signal stream closed, leaving only the file to watch.
+                Some(None) => {
+                    hangups = None;
+                    continue;
+                }
+                None => {
+                    let latest = contents().await;
+                    if latest == last {
+                        continue;
+                    }
+                    last = latest;
+                    info!("The configuration file changed, reloading the configuration");
+                }
+            }
+            if let Err(err) = reload() {
+                warn!("Rejected the reloaded configuration, {}", err);
+            }
+        }
+    });
+}
+
+/// Reloads the tunables of the ACM, returning the generation of the configuration that is now in
+/// effect. Should any tunable be invalid, then none are changed.
+pub fn reload() -> Result<u64, Invalid> {
+    let reloaded = config::reload(|source| {
+        let tunables = AcmTunables::load(source)?;
+        let limits = tunables
+            .rate_limits
+            .parse::<Limits>()
+            .map_err(|err| Invalid {
+                problems: vec![format!(
+                    "The {} setting is malformed, {}",
+                    ratelimit::RATE_LIMITS,
+                    err
+                )],
+            })?;
+        Ok((tunables, limits))
+    });
+    let (tunables, limits) = match reloaded {
+        Ok(reloaded) => reloaded,
+        Err(err) => {
+            STATE.write().unwrap().rejected = Some(err.to_string());
+            return Err(err);
+        }
+    };
+    // Everything is valid, so from here on out everything is applied.
+    ratelimit::reconfigure(limits);
+    LOGGER.replace(&tunables.log_filter);
+    let generation = {
+        let mut state = STATE.write().unwrap();
+        state.tunables = Arc::new(tunables);
+        state.generation += 1;
+        state.reloaded_at = chrono::Utc::now().timestamp();
+        state.rejected = None;
+        state.generation
+    };
+    info!(
+        "Reloaded the configuration, which is now at generation {}",
+        highlight(generation.to_string())
+    );
+    Ok(generation)
+}
+
+/// Reads the configuration file, should there be one.
+async fn contents() -> Option<Vec<u8>> {
+    let path = std::env::var(config::CONFIG_FILE).ok()?;
+    tokio::fs::read(path.trim()).await.ok()
+}
+
+/// Status is the state of the ACM's configuration as reported by the [status](crate::status())
+/// endpoint.
+#[derive(Serialize, Kind, Clone)]
+pub struct Status {
+    /// The number of times that the configuration has been applied, starting at one for the
+    /// configuration that the ACM started with.
+    pub generation: u64,
+    /// The Unix timestamp at which the current generation was applied.
+    pub reloaded_at: i64,
+    /// Why the latest reload was rejected, should it have been.
+    pub rejected: Option<String>,
+    /// The tunables as of the current generation.
+    pub tunables: AcmTunables,
+}
+
+struct State {
+    tunables: Arc<AcmTunables>,
+    generation: u64,
+    reloaded_at: i64,
+    rejected: Option<String>,
+}
+
+impl State {
+    fn configured() -> State {
+        State {
+            tunables: Arc::new(AcmTunables::configured()),
+            generation: 1,
+            reloaded_at: chrono::Utc::now().timestamp(),
+            rejected: None,
+        }
+    }
+}
+
+/// Logger writes every record through the [env_logger](env_logger) that was built out of the
+/// current log filter, which is swapped out whenever the filter is reloaded.
+struct Logger(RwLock<env_logger::Logger>);
+
+impl Logger {
+    fn replace(&self, filter: &str) {
+        let logger = logger(filter);
+        log::set_max_level(logger.filter());
+        *self.0.write().unwrap() = logger;
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.0.read().unwrap().enabled(metadata)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        self.0.read().unwrap().log(record)
+    }
+
+    fn flush(&self) {
+        self.0.read().unwrap().flush()
+    }
+}
+
+/// Builds the logger for the given filter, written in the style chosen by `RUST_LOG_STYLE`.
+fn logger(filter: &str) -> env_logger::Logger {
+    let mut builder = env_logger::Builder::new();
+    builder.parse_filters(filter);
+    if let Ok(style) = std::env::var("RUST_LOG_STYLE") {
+        builder.parse_write_style(&style);
+    }
+    builder.build()
+}
@@ -0,0 +1,52 @@
+//! The restart policy of connectors. By default, OCF has no tolerance for a connector that
+//! restarts, and so deletes it as [rebooted](crate::podmanager::history::Lifecycle::Rebooted).
+//! A deploy may instead opt into up to [MAX_RESTARTS](MAX_RESTARTS) automatic restarts, each of
+//! which is followed by a fresh health check of the connector before it is considered online
+//! again. Only once the connector restarts more often than that is it declared to have failed.
+use error::*;
+use result::Result;
+
+/// The most automatic restarts that a deploy may ask for.
+pub const MAX_RESTARTS: u32 = 5;
+
+/// Returns the number of restarts that a deploy which asked for the given number is allowed,
+/// which is none should it not have asked. A number beyond [MAX_RESTARTS](MAX_RESTARTS) is
+/// refused with a [TooManyRestarts](TooManyRestarts).
+pub fn resolve(requested: Option<u32>) -> Result<u32> {
+    match requested {
+        Some(restarts) if restarts > MAX_RESTARTS => Err(TooManyRestarts {
+            restarts,
+            max: MAX_RESTARTS,
+        }
+        .into()),
+        Some(restarts) => Ok(restarts),
+        None => Ok(0),
+    }
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::UnprocessableEntity)]
+#[error(
+    "The deploy asked for its connector to be restarted up to {restarts} times, however the ACM \
+(Alation Connector Manager) allows no more than {max}. A connector that restarts more often than \
+that is better fixed than restarted."
+)]
+#[error_code("ACM-3200")]
+pub struct TooManyRestarts {
+    restarts: u32,
+    max: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves() {
+        assert_eq!(resolve(None).unwrap(), 0);
+        assert_eq!(resolve(Some(0)).unwrap(), 0);
+        assert_eq!(resolve(Some(MAX_RESTARTS)).unwrap(), MAX_RESTARTS);
+        let err = resolve(Some(MAX_RESTARTS + 1)).unwrap_err();
+        assert_eq!(err.error_code(), Some("ACM-3200"));
+    }
+}
@@ -0,0 +1,108 @@
+//! The Store is the ACM's durable record of things that are worth remembering after the pods
+//! that they are about are long gone (such as the outcome of every garbage collection).
+//!
+//! Each collection is an append-only file of newline delimited JSON records within the
+//! directory configured by the [STORE_PATH](STORE_PATH) environment variable. Whether that
+//! directory outlives the ACM's pod is a matter of the volume mounted there.
+use error::*;
+use result::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::Mutex;
+
+/// The environment variable that configures the directory in which the Store is kept.
+pub const STORE_PATH: &str = "STORE_PATH";
+
+lazy_static! {
+    /// Serializes writers so that concurrent appends never interleave within a line.
+    static ref WRITER: Mutex<()> = Mutex::new(());
+}
+
+/// Appends the given record to the given collection.
+pub async fn append<T: Serialize>(collection: &str, record: &T) -> Result<()> {
+    let path = path(collection);
+    let mut line = serde_json::to_vec(record).map_err(|err| StoreError::new(collection, err))?;
+    line.push(b'\n');
+    let _guard = WRITER.lock().await;
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|err| StoreError::new(collection, err))?;
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await
+        .map_err(|err| StoreError::new(collection, err))?;
+    file.write_all(&line)
+        .await
+        .map_err(|err| StoreError::new(collection, err))?;
+    Ok(())
+}
+
+/// Returns every record within the given collection that satisfies the given filter, in the
+/// order in which they were appended. A collection that has never been appended to is empty.
+///
+/// Lines that cannot be parsed (say, a line that was torn by a crash mid-append) are skipped.
+pub async fn scan<T, F>(collection: &str, filter: F) -> Result<Vec<T>>
+where
+    T: DeserializeOwned,
+    F: Fn(&T) -> bool,
+{
+    let file = match tokio::fs::File::open(path(collection)).await {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(StoreError::new(collection, err).into()),
+    };
+    let mut lines = BufReader::new(file).lines();
+    let mut records = vec![];
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|err| StoreError::new(collection, err))?
+    {
+        match serde_json::from_str::<T>(&line) {
+            Ok(record) if filter(&record) => records.push(record),
+            Ok(_) => (),
+            Err(err) => warn!(
+                "Skipping a malformed record in the {} store, {}",
+                collection, err
+            ),
+        }
+    }
+    Ok(records)
+}
+
+/// Returns the directory in which the Store is kept. Records that are not suited to a collection
+/// (such as [crash logs](crate::crashlogs)) may be kept beneath it as well.
+pub fn directory() -> PathBuf {
+    crate::env::config().store_path.clone()
+}
+
+fn path(collection: &str) -> PathBuf {
+    directory().join(format!("{}.jsonl", collection))
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::InternalServerError)]
+#[error(
+    "Failed to access the {collection} records in the ACM's store, {cause}. Please check that \
+the volume mounted at the STORE_PATH is writable and has space available."
+)]
+#[error_code("ACM-1700")]
+pub struct StoreError {
+    collection: String,
+    cause: String,
+}
+
+impl StoreError {
+    pub(crate) fn new<E: ToString>(collection: &str, err: E) -> StoreError {
+        StoreError {
+            collection: collection.to_string(),
+            cause: err.to_string(),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use error::*;
+use result::Result;
+use std::collections::BTreeSet;
+
+lazy_static! {
+    static ref ALLOWED: BTreeSet<String> = allowed_configured();
+}
+
+/// Resolves the namespace requested by a client. No namespace at all means the
+/// [default](k8s::OCF_NAMESPACE), while any other namespace MUST be within the allow-list
+/// configured by [TENANT_NAMESPACES](config::acm::AcmConfig::tenant_namespaces) or else a
+/// [NamespaceNotAllowed](NamespaceNotAllowed) is returned.
+pub fn namespace(requested: Option<String>) -> Result<String> {
+    let requested = match requested {
+        Some(requested) if !requested.trim().is_empty() => requested.trim().to_string(),
+        _ => return Ok(k8s::OCF_NAMESPACE.to_string()),
+    };
+    if ALLOWED.contains(&requested) {
+        Ok(requested)
+    } else {
+        Err(NamespaceNotAllowed {
+            namespace: requested,
+            allowed: ALLOWED.iter().cloned().collect::<Vec<String>>().join(", "),
+        }
+        .into())
+    }
+}
+
+/// Returns every namespace that connectors may be deployed into, the [default](k8s::OCF_NAMESPACE)
+/// namespace first.
+pub fn namespaces() -> Vec<String> {
+    std::iter::once(k8s::OCF_NAMESPACE.to_string())
+        .chain(ALLOWED.iter().cloned())
+        .collect()
+}
+
+/// The [tenant namespaces](config::acm::AcmConfig::tenant_namespaces) alongside the
+/// [default](k8s::OCF_NAMESPACE) namespace.
+fn allowed_configured() -> BTreeSet<String> {
+    let mut allowed = crate::env::config().tenant_namespaces.clone();
+    allowed.insert(k8s::OCF_NAMESPACE.to_string());
+    allowed
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::Forbidden)]
+#[error(
+    "The namespace '{namespace}' is not one that this ACM (Alation Connector Manager) may manage \
+connectors in. The allowed namespaces are {allowed}. Additional namespaces may be allowed via the \
+ACM's TENANT_NAMESPACES configuration."
+)]
+#[error_code("ACM-2000")]
+pub struct NamespaceNotAllowed {
+    namespace: String,
+    allowed: String,
+}
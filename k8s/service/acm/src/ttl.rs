@@ -0,0 +1,96 @@
+//! The TTL policy of the ACM, which bounds the TTLs that clients may ask for (via the
+//! [MIN_TTL](config::acm::AcmTunables::min_ttl) and the [MAX_TTL](config::acm::AcmTunables::max_ttl))
+//! and chooses the TTL of those who do not ask for one (via the
+//! [DEFAULT_TTLS](config::acm::AcmTunables::default_ttls) of particular clients, or else the
+//! [DEFAULT_TTL](config::acm::AcmTunables::default_ttl)).
+use crate::reload;
+use audit::Actor;
+use config::acm::AcmTunables;
+use error::*;
+use result::Result;
+
+/// Returns the TTL of a deploy by the given actor that asked for the given TTL, if any. A TTL
+/// that is out of bounds is refused with a [TtlOutOfBounds](TtlOutOfBounds).
+pub fn resolve(requested: Option<u64>, actor: &Actor) -> Result<u64> {
+    resolve_with(&reload::tunables(), requested, actor)
+}
+
+/// Returns the given TTL should it be within bounds, or else a [TtlOutOfBounds](TtlOutOfBounds).
+pub fn check(ttl: u64) -> Result<u64> {
+    check_with(&reload::tunables(), ttl)
+}
+
+fn resolve_with(tunables: &AcmTunables, requested: Option<u64>, actor: &Actor) -> Result<u64> {
+    match requested {
+        Some(ttl) => check_with(tunables, ttl),
+        None => Ok(tunables.default_ttl_for(actor.as_str())),
+    }
+}
+
+fn check_with(tunables: &AcmTunables, ttl: u64) -> Result<u64> {
+    if tunables.permits_ttl(ttl) {
+        return Ok(ttl);
+    }
+    let bound = |bound: Option<u64>| bound.map_or("none".to_string(), |bound| bound.to_string());
+    Err(TtlOutOfBounds {
+        ttl,
+        min: bound(tunables.min_ttl),
+        max: bound(tunables.max_ttl),
+    }
+    .into())
+}
+
+#[derive(Error, AcmError, HttpCode, Kind, Debug)]
+#[code(Status::UnprocessableEntity)]
+#[error(
+    "The requested TTL of {ttl} seconds is out of the bounds that the ACM (Alation Connector \
+Manager) allows, being a minimum of {min} and a maximum of {max} seconds. Please ask for a TTL \
+within them, or for none at all to be given the default."
+)]
+#[error_code("ACM-2900")]
+pub struct TtlOutOfBounds {
+    ttl: u64,
+    min: String,
+    max: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Source;
+
+    fn tunables() -> AcmTunables {
+        AcmTunables {
+            default_ttl: 1800,
+            default_ttls: vec![("etl".to_string(), 7200)].into_iter().collect(),
+            min_ttl: Some(60),
+            max_ttl: Some(86400),
+            ..AcmTunables::load(&Source::default()).unwrap()
+        }
+    }
+
+    #[test]
+    fn resolves_defaults() {
+        let tunables = tunables();
+        let resolve = |actor: &str| resolve_with(&tunables, None, &Actor::from(actor)).unwrap();
+        assert_eq!(resolve("etl"), 7200);
+        assert_eq!(resolve("anonymous"), 1800);
+    }
+
+    #[test]
+    fn checks_bounds() {
+        let tunables = tunables();
+        let actor = Actor::from("anonymous");
+        assert_eq!(resolve_with(&tunables, Some(60), &actor).unwrap(), 60);
+        assert_eq!(resolve_with(&tunables, Some(86400), &actor).unwrap(), 86400);
+        let err = resolve_with(&tunables, Some(59), &actor).unwrap_err();
+        assert_eq!(err.error_code(), Some("ACM-2900"));
+        assert!(check_with(&tunables, 86401).is_err());
+        let unbounded = AcmTunables {
+            min_ttl: None,
+            max_ttl: None,
+            ..tunables
+        };
+        assert_eq!(check_with(&unbounded, 1).unwrap(), 1);
+    }
+}
@@ -0,0 +1,599 @@
+use crate::env;
+use crate::registry::{Image, Step};
+use error::*;
+use names::{InvalidName, Rfc1123Subdomain};
+use result::Result;
+use rocket::http::Header;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::Responder;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use term_colors::layout::Progress;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio_util::sync::CancellationToken;
+
+/// The maximum number of finished installs whose final status is remembered for the
+/// sake of [status](status) and [progress](progress). Once exceeded, the oldest record
+/// is forgotten first.
+pub const MAXIMUM_REMEMBERED_INSTALLS: usize = 1024;
+
+lazy_static::lazy_static! {
+    static ref ADMISSION: Admission = Admission {
+        permits: Semaphore::new(env::max_concurrent_installs()),
+        max_queued: env::max_queued_installs(),
+        state: Mutex::new(State::default()),
+    };
+}
+
+/// Admission control for installs.
+///
+/// Every install streams up to 10 gigabytes to disk before pushing it through containerd, so
+/// allowing an unbounded number of them to run at once can exhaust both. Instead, at most
+/// [max_concurrent_installs](env::max_concurrent_installs) installs run at once, while up to
+/// [max_queued_installs](env::max_queued_installs) more wait their turn in FIFO order. Anything
+/// beyond that is turned away with a `429 Too Many Requests` and a `Retry-After` header.
+///
+/// Admission is granted by way of a [Ticket](Ticket) request guard. As request guards are
+/// evaluated before the data guard, a queued install does not begin uploading until it has been
+/// admitted, and a rejected install is never uploaded at all.
+struct Admission {
+    permits: Semaphore,
+    max_queued: usize,
+    state: Mutex<State>,
+}
+
+/// Forces the evaluation of admission control's configuration. As with
+/// [Implementation::configure](crate::registry::Implementation::configure), this SHOULD be called
+/// on program startup so that a misconfiguration panics immediately rather than on the first install.
+pub fn configure() {
+    lazy_static::initialize(&ADMISSION);
+}
+
+/// Returns the current status of the install with the given ID. Installs that are queued,
+/// in progress, or among the [most recently finished](MAXIMUM_REMEMBERED_INSTALLS) are known.
+pub fn status<I: AsRef<str>>(id: I) -> Result<InstallStatus> {
+    ADMISSION
+        .state
+        .lock()
+        .unwrap()
+        .status(id.as_ref())
+        .ok_or_else(|| {
+            InstallNotFound {
+                id: id.as_ref().to_string(),
+            }
+            .into()
+        })
+}
+
+/// Returns the detailed progress of the install with the given ID. Whereas [status](status) only
+/// reports whether or not an install is underway, progress reports which [phase](Phase) of the
+/// install pipeline is currently running and how many bytes are involved.
+pub fn progress<I: AsRef<str>>(id: I) -> Result<InstallProgress> {
+    ADMISSION
+        .state
+        .lock()
+        .unwrap()
+        .progress(id.as_ref())
+        .ok_or_else(|| {
+            InstallNotFound {
+                id: id.as_ref().to_string(),
+            }
+            .into()
+        })
+}
+
+/// Cancels the install with the given ID, returning its progress. An install that is underway is
+/// aborted (killing any command that it is running) and fails with an
+/// [InstallCancelled](InstallCancelled). Cancelling an install that is queued or that has already
+/// finished has no effect.
+pub fn cancel<I: AsRef<str>>(id: I) -> Result<InstallProgress> {
+    let state = ADMISSION.state.lock().unwrap();
+    if let Some(token) = state.cancellations.get(id.as_ref()) {
+        info!("Cancelling install {}", term_colors::highlight(id.as_ref()));
+        token.cancel();
+    }
+    state.progress(id.as_ref()).ok_or_else(|| {
+        InstallNotFound {
+            id: id.as_ref().to_string(),
+        }
+        .into()
+    })
+}
+
+/// Waits, in FIFO order, for the install with the given ID to be admitted. If the queue is
+/// already full then `None` is returned immediately.
+async fn admit(id: String) -> Option<Ticket> {
+    if !ADMISSION
+        .state
+        .lock()
+        .unwrap()
+        .enqueue(&id, ADMISSION.max_queued)
+    {
+        warn!(
+            "Install {} was rejected as the install queue is full",
+            term_colors::highlight(id.as_str())
+        );
+        return None;
+    }
+    // Should the client disconnect while queued, then this future is dropped and
+    // the Dequeue guard removes the install from the queue.
+    let dequeue = Dequeue { id: &id };
+    let permit = ADMISSION
+        .permits
+        .acquire()
+        .await
+        .expect("the install semaphore is never closed");
+    std::mem::forget(dequeue);
+    let cancellation = CancellationToken::new();
+    ADMISSION
+        .state
+        .lock()
+        .unwrap()
+        .start(&id, cancellation.clone());
+    info!(
+        "Install {} was admitted",
+        term_colors::highlight(id.as_str())
+    );
+    Some(Ticket {
+        id,
+        _permit: permit,
+        cancellation,
+        finished: false,
+    })
+}
+
+/// A Ticket is proof of admission for an install. The install's slot is held for as long as
+/// the Ticket lives and is released upon drop.
+pub struct Ticket {
+    id: String,
+    _permit: SemaphorePermit<'static>,
+    cancellation: CancellationToken,
+    finished: bool,
+}
+
+impl Ticket {
+    /// The ID of the install that this Ticket admits.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The token by which the install may be [cancelled](cancel).
+    pub fn cancellation(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    /// Records that the install's upload has completed and was of the given size.
+    pub fn uploaded(&self, bytes: u64) {
+        ADMISSION.state.lock().unwrap().uploaded(&self.id, bytes);
+    }
+
+    /// Records that the install has moved onto the given [Step](Step) of the install pipeline.
+    pub fn step(&self, step: Step) {
+        debug!(
+            "Install {} is {:?} {}",
+            term_colors::highlight(self.id.as_str()),
+            step,
+            Progress::new(step as u64, Step::ALL.len() as u64)
+        );
+        ADMISSION.state.lock().unwrap().step(&self.id, step);
+    }
+
+    /// Records the outcome of the install for the sake of [status](status).
+    pub fn finish(mut self, result: &Result<Image>) {
+        let phase = match result {
+            Ok(image) => Phase::Installed {
+                image: image.clone(),
+            },
+            Err(err) => Phase::Failed {
+                error: err.to_string(),
+            },
+        };
+        ADMISSION.state.lock().unwrap().finish(&self.id, phase);
+        self.finished = true;
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        if !self.finished {
+            ADMISSION.state.lock().unwrap().finish(
+                &self.id,
+                Phase::Failed {
+                    error: "The install was interrupted before it completed".to_string(),
+                },
+            );
+        }
+    }
+}
+
+/// A Ticket is acquired by way of the optional `id` query parameter. Clients that would like to
+/// poll the [status](status) of their install while it is queued SHOULD choose their own ID (for
+/// example, a UUID). Otherwise, a random ID is assigned.
+///
+/// Should the queue be full, then the request fails with a `429 Too Many Requests`, which is
+/// rendered by the [too_many_requests](too_many_requests) catcher.
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Ticket {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let id = match request.query_value::<String>("id").and_then(|id| id.ok()) {
+            Some(id) => match Rfc1123Subdomain::new(id) {
+                Ok(id) => id.into_inner(),
+                Err(err) => {
+                    request.local_cache(|| Some(err));
+                    return Outcome::Failure((rocket::http::Status::UnprocessableEntity, ()));
+                }
+            },
+            None => names::rfc1035_label(),
+        };
+        match admit(id).await {
+            Some(ticket) => Outcome::Success(ticket),
+            None => Outcome::Failure((rocket::http::Status::TooManyRequests, ())),
+        }
+    }
+}
+
+/// Renders a [QueueFull](QueueFull) error alongside a `Retry-After` header.
+#[catch(429)]
+pub fn too_many_requests() -> Throttled {
+    Throttled {
+        retry_after: env::install_retry_after(),
+    }
+}
+
+/// Renders the [InvalidName](names::InvalidName) of an install whose ID is not a valid
+/// [RFC 1123 subdomain](names::is_valid_rfc1123_subdomain). Any other request that Rocket itself
+/// found to be unprocessable is rendered as an [UnprocessableRequest](UnprocessableRequest).
+#[catch(422)]
+pub fn unprocessable(request: &Request<'_>) -> Box<dyn AcmError> {
+    match request.local_cache(|| None::<InvalidName>) {
+        Some(err) => err.clone().into(),
+        None => UnprocessableRequest {}.into(),
+    }
+}
+
+/// A Throttled is the response given to an install that was turned away because the queue was full.
+pub struct Throttled {
+    retry_after: u64,
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for Throttled {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'o> {
+        let err: Box<dyn AcmError> = QueueFull {
+            retry_after: self.retry_after,
+        }
+        .into();
+        let mut response = err.respond_to(request)?;
+        response.set_header(Header::new("Retry-After", self.retry_after.to_string()));
+        Ok(response)
+    }
+}
+
+/// Removes an install from the queue should it be dropped before admission.
+struct Dequeue<'a> {
+    id: &'a str,
+}
+
+impl Drop for Dequeue<'_> {
+    fn drop(&mut self) {
+        ADMISSION.state.lock().unwrap().abandon(self.id);
+    }
+}
+
+/// An InstallStatus is the current state of an install as seen by [status](status).
+#[derive(Serialize, Kind, Clone, Debug)]
+pub struct InstallStatus {
+    id: String,
+    #[serde(flatten)]
+    state: InstallState,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum InstallState {
+    /// The install is waiting for admission. A position of `1` is next in line.
+    Queued {
+        position: usize,
+    },
+    Installing,
+    Installed {
+        image: Image,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+/// An InstallProgress is the current state of an install as seen by [progress](progress).
+#[derive(Serialize, Kind, Clone, Debug)]
+pub struct InstallProgress {
+    id: String,
+    #[serde(flatten)]
+    phase: Phase,
+    /// The size of the uploaded image. This is not known until the upload completes.
+    bytes: Option<u64>,
+    /// The number of bytes pushed to the registry. containerd does not report its push
+    /// progress in a machine readable form, so this remains zero until the push completes.
+    bytes_pushed: u64,
+}
+
+/// A Phase is a step of the install pipeline, serialized under the `phase` key.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum Phase {
+    /// The install is waiting for admission. A position of `1` is next in line.
+    Queued {
+        position: usize,
+    },
+    /// The install has been admitted and the image is being uploaded to the AIM.
+    Uploading,
+    /// The image is being imported into containerd.
+    Importing,
+    /// The image is being retagged into the configured repository.
+    Retagging,
+    /// The image is being pushed to the configured registry.
+    Pushing,
+    Installed {
+        image: Image,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+impl From<Step> for Phase {
+    fn from(step: Step) -> Self {
+        match step {
+            Step::Importing => Phase::Importing,
+            Step::Retagging => Phase::Retagging,
+            Step::Pushing => Phase::Pushing,
+        }
+    }
+}
+
+struct Record {
+    phase: Phase,
+    bytes: Option<u64>,
+    bytes_pushed: u64,
+}
+
+impl Record {
+    fn new(phase: Phase) -> Record {
+        Record {
+            phase,
+            bytes: None,
+            bytes_pushed: 0,
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    queue: VecDeque<String>,
+    records: HashMap<String, Record>,
+    finished: VecDeque<String>,
+    cancellations: HashMap<String, CancellationToken>,
+}
+
+impl State {
+    /// Adds the given install to the back of the queue, returning `false` if the queue is full.
+    fn enqueue(&mut self, id: &str, max_queued: usize) -> bool {
+        if self.queue.len() >= max_queued {
+            return false;
+        }
+        self.queue.push_back(id.to_string());
+        self.records
+            .insert(id.to_string(), Record::new(Phase::Queued { position: 0 }));
+        true
+    }
+
+    fn start(&mut self, id: &str, cancellation: CancellationToken) {
+        self.queue.retain(|queued| queued != id);
+        self.cancellations.insert(id.to_string(), cancellation);
+        self.records
+            .insert(id.to_string(), Record::new(Phase::Uploading));
+    }
+
+    fn uploaded(&mut self, id: &str, bytes: u64) {
+        if let Some(record) = self.records.get_mut(id) {
+            record.bytes = Some(bytes);
+        }
+    }
+
+    fn step(&mut self, id: &str, step: Step) {
+        if let Some(record) = self.records.get_mut(id) {
+            record.phase = step.into();
+        }
+    }
+
+    fn abandon(&mut self, id: &str) {
+        self.queue.retain(|queued| queued != id);
+        self.records.remove(id);
+    }
+
+    fn finish(&mut self, id: &str, phase: Phase) {
+        self.cancellations.remove(id);
+        if self.finished.len() >= MAXIMUM_REMEMBERED_INSTALLS {
+            if let Some(oldest) = self.finished.pop_front() {
+                self.records.remove(&oldest);
+            }
+        }
+        self.finished.push_back(id.to_string());
+        let record = self
+            .records
+            .entry(id.to_string())
+            .or_insert_with(|| Record::new(Phase::Uploading));
+        if let Phase::Installed { .. } = phase {
+            record.bytes_pushed = record.bytes.unwrap_or_default();
+        }
+        record.phase = phase;
+    }
+
+    fn progress(&self, id: &str) -> Option<InstallProgress> {
+        let record = self.records.get(id)?;
+        let phase = match record.phase {
+            Phase::Queued { .. } => Phase::Queued {
+                position: self.queue.iter().position(|queued| queued == id)? + 1,
+            },
+            ref phase => phase.clone(),
+        };
+        Some(InstallProgress {
+            id: id.to_string(),
+            phase,
+            bytes: record.bytes,
+            bytes_pushed: record.bytes_pushed,
+        })
+    }
+
+    fn status(&self, id: &str) -> Option<InstallStatus> {
+        let state = match self.progress(id)?.phase {
+            Phase::Queued { position } => InstallState::Queued { position },
+            Phase::Uploading | Phase::Importing | Phase::Retagging | Phase::Pushing => {
+                InstallState::Installing
+            }
+            Phase::Installed { image } => InstallState::Installed { image },
+            Phase::Failed { error } => InstallState::Failed { error },
+        };
+        Some(InstallStatus {
+            id: id.to_string(),
+            state,
+        })
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::TooManyRequests)]
+#[error(
+    "The AIM is already installing as many images as it is configured to and its install queue \
+is full. Please try again in {retry_after} seconds."
+)]
+#[error_code("AIM-1300")]
+pub struct QueueFull {
+    retry_after: u64,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::NotFound)]
+#[error(
+    "No install with the ID '{id}' is known to this AIM. Either no such install was ever \
+submitted, or it finished long enough ago that its record has been dropped."
+)]
+#[error_code("AIM-1301")]
+pub struct InstallNotFound {
+    id: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::UnprocessableEntity)]
+#[error("The request could not be processed, as one of its parameters (or its body) is malformed.")]
+#[error_code("AIM-1303")]
+pub struct UnprocessableRequest {}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::Conflict)]
+#[error("The install was cancelled before it completed.")]
+#[error_code("AIM-1302")]
+pub struct InstallCancelled {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(state: &State, id: &str) -> Option<usize> {
+        match state.status(id)?.state {
+            InstallState::Queued { position } => Some(position),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn fifo_positions() {
+        let mut state = State::default();
+        assert!(state.enqueue("a", 2));
+        assert!(state.enqueue("b", 2));
+        assert!(!state.enqueue("c", 2));
+        assert_eq!(position(&state, "a"), Some(1));
+        assert_eq!(position(&state, "b"), Some(2));
+        state.start("a", CancellationToken::new());
+        assert!(matches!(
+            state.status("a").unwrap().state,
+            InstallState::Installing
+        ));
+        assert_eq!(position(&state, "b"), Some(1));
+        assert!(state.enqueue("c", 2));
+        state.abandon("b");
+        assert!(state.status("b").is_none());
+        assert_eq!(position(&state, "c"), Some(1));
+    }
+
+    #[test]
+    fn finished_installs_are_bounded() {
+        let mut state = State::default();
+        for i in 0..=MAXIMUM_REMEMBERED_INSTALLS {
+            state.finish(
+                &i.to_string(),
+                Phase::Failed {
+                    error: "".to_string(),
+                },
+            );
+        }
+        assert!(state.status("0").is_none());
+        assert!(state
+            .status(&MAXIMUM_REMEMBERED_INSTALLS.to_string())
+            .is_some());
+    }
+
+    #[test]
+    fn progress_through_phases() {
+        let mut state = State::default();
+        assert!(state.enqueue("a", 1));
+        state.start("a", CancellationToken::new());
+        assert!(matches!(
+            state.progress("a").unwrap().phase,
+            Phase::Uploading
+        ));
+        state.uploaded("a", 42);
+        state.step("a", Step::Pushing);
+        let progress = state.progress("a").unwrap();
+        assert!(matches!(progress.phase, Phase::Pushing));
+        assert_eq!(progress.bytes, Some(42));
+        assert_eq!(progress.bytes_pushed, 0);
+        assert!(matches!(
+            state.status("a").unwrap().state,
+            InstallState::Installing
+        ));
+        state.finish(
+            "a",
+            Phase::Installed {
+                image: Image {
+                    tag: "tag".to_string(),
+                    digest: "sha256:digest".to_string(),
+                    labels: Default::default(),
+                },
+            },
+        );
+        assert_eq!(state.progress("a").unwrap().bytes_pushed, 42);
+        let json = serde_json::to_value(state.progress("a").unwrap()).unwrap();
+        assert_eq!(json["phase"], "installed");
+        assert_eq!(json["image"]["tag"], "tag");
+    }
+
+    #[test]
+    fn finishing_forgets_the_cancellation() {
+        let mut state = State::default();
+        assert!(state.enqueue("a", 1));
+        let cancellation = CancellationToken::new();
+        state.start("a", cancellation.clone());
+        state.cancellations["a"].cancel();
+        assert!(cancellation.is_cancelled());
+        state.finish(
+            "a",
+            Phase::Failed {
+                error: "".to_string(),
+            },
+        );
+        assert!(state.cancellations.is_empty());
+    }
+}
@@ -0,0 +1,43 @@
+//! The auditor keeps the AIM's [audit trail](audit) of every install and uninstall.
+//!
+//! Which sink the trail is written into is configured by the [AUDIT_SINK](audit::AUDIT_SINK)
+//! environment variable. The `file` sink defaults to `audit.log` alongside the AIM's
+//! [record of deprecations](crate::env::deprecations_path). The AIM cannot post Kubernetes
+//! Events, and so the `events` sink is refused.
+use crate::env;
+use audit::{Action, Actor, Auditor, Entry};
+use result::Result;
+
+/// The number of entries returned by the `/audit` endpoint unless another limit is requested.
+pub const DEFAULT_AUDIT_LIMIT: usize = 100;
+
+const AUDIT_FILE: &str = "audit.log";
+
+lazy_static::lazy_static! {
+    static ref AUDITOR: Auditor =
+        Auditor::configured(env::deprecations_path().with_file_name(AUDIT_FILE), None);
+}
+
+/// Forces the evaluation of the [AUDIT_SINK](audit::AUDIT_SINK) configuration.
+pub fn configure() {
+    lazy_static::initialize(&AUDITOR);
+}
+
+/// Records the given action, made by the given actor upon the given target, before handing its
+/// result back.
+pub async fn record<T>(
+    actor: &Actor,
+    action: Action,
+    target: String,
+    result: Result<T>,
+) -> Result<T> {
+    AUDITOR
+        .record(Entry::new(actor, action, target, &result))
+        .await;
+    result
+}
+
+/// Returns (at most) the given number of the most recent audit entries, oldest first.
+pub fn recent(limit: usize) -> Vec<Entry> {
+    AUDITOR.recent(limit)
+}
@@ -0,0 +1,158 @@
+//! Checksum validation of uploaded images. A client of [install](crate::install) may give the
+//! SHA-256 that it expects of the uploaded file, either via the [CHECKSUM_HEADER](CHECKSUM_HEADER)
+//! or the `sha256` query parameter. The staged upload is then hashed before anything is imported
+//! into containerd, such that an upload that was corrupted along the way (which, for an image of
+//! several gigabytes, is not unheard of) is refused with a [ChecksumMismatch](ChecksumMismatch)
+//! rather than failing mysteriously (or worse, succeeding) further down the pipeline.
+use error::*;
+use result::Result;
+use rocket::request::{FromRequest, Outcome, Request};
+use sha2::Digest;
+use std::io::Read;
+use std::path::Path;
+
+/// The header by which a client MAY give the SHA-256 that it expects of its upload.
+pub const CHECKSUM_HEADER: &str = "X-Checksum-Sha256";
+
+/// The size of each read of the staged upload while it is being hashed.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// A Checksum is the SHA-256 (if any) that a client expects of its upload, as given by either
+/// the [CHECKSUM_HEADER](CHECKSUM_HEADER) or (should the header be absent) the `sha256` query
+/// parameter. It may be given as bare hex or prefixed with `sha256:`, in either case.
+pub struct Checksum(Option<String>);
+
+impl Checksum {
+    /// Hashes the file at the given path and compares it against this Checksum, if any. The file
+    /// is read in chunks, so it is never held in memory as a whole.
+    pub async fn verify<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let expected = match self.0.as_ref() {
+            Some(expected) => normalize(expected)?,
+            None => return Ok(()),
+        };
+        let path = path.as_ref().to_path_buf();
+        let actual = tokio::task::spawn_blocking(move || hash(&path))
+            .await
+            .map_err(|err| ChecksumUnreadable {
+                cause: err.to_string(),
+            })?
+            .map_err(|err| ChecksumUnreadable {
+                cause: err.to_string(),
+            })?;
+        if actual != expected {
+            return Err(ChecksumMismatch { expected, actual }.into());
+        }
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Checksum {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let checksum = request
+            .headers()
+            .get_one(CHECKSUM_HEADER)
+            .map(str::to_string)
+            .or_else(|| {
+                request
+                    .query_value::<String>("sha256")
+                    .and_then(|checksum| checksum.ok())
+            });
+        Outcome::Success(Checksum(checksum))
+    }
+}
+
+/// Returns the lowercase hex of the given checksum, which must be a SHA-256 (optionally prefixed
+/// by `sha256:`), otherwise a [MalformedChecksum](MalformedChecksum).
+fn normalize(checksum: &str) -> Result<String> {
+    let trimmed = checksum.trim();
+    let hex = match trimmed.split_once(':') {
+        Some((algorithm, hex)) if algorithm.eq_ignore_ascii_case("sha256") => hex,
+        Some(_) => "",
+        None => trimmed,
+    };
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(MalformedChecksum {
+            checksum: checksum.to_string(),
+        }
+        .into());
+    }
+    Ok(hex.to_ascii_lowercase())
+}
+
+/// Returns the lowercase hex of the SHA-256 of the file at the given path.
+fn hash(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = sha2::Sha256::new();
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        match file.read(&mut buffer)? {
+            0 => break,
+            read => hasher.update(&buffer[..read]),
+        }
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The checksum '{checksum}' is malformed. A checksum is the SHA-256 of the uploaded image, that \
+is 64 hexadecimal characters (optionally prefixed by 'sha256:')."
+)]
+#[error_code("AIM-1800")]
+pub struct MalformedChecksum {
+    checksum: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::UnprocessableEntity)]
+#[error(
+    "The uploaded image has a SHA-256 of {actual} rather than the expected {expected}, so it was \
+likely corrupted (or truncated) along the way. Please upload the image again."
+)]
+#[error_code("AIM-1801")]
+pub struct ChecksumMismatch {
+    expected: String,
+    actual: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error("Failed to read back the uploaded image in order to verify its checksum, {cause}")]
+#[error_code("AIM-1802")]
+pub struct ChecksumUnreadable {
+    cause: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SHA-256 of `hello world`.
+    const HELLO: &str = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+    #[test]
+    fn normalizes() {
+        assert_eq!(normalize(HELLO).unwrap(), HELLO);
+        assert_eq!(
+            normalize(&format!("SHA256:{}", HELLO.to_uppercase())).unwrap(),
+            HELLO
+        );
+        for malformed in &["", "abc", &format!("md5:{}", HELLO), &format!("{}0", HELLO)] {
+            let err = normalize(malformed).unwrap_err();
+            assert_eq!(err.error_code(), Some("AIM-1800"));
+        }
+    }
+
+    #[test]
+    fn hashes() {
+        let path = std::env::temp_dir().join(names::uuid());
+        std::fs::write(&path, "hello world").unwrap();
+        assert_eq!(hash(&path).unwrap(), HELLO);
+        std::fs::remove_file(&path).unwrap();
+        assert!(hash(&path).is_err());
+    }
+}
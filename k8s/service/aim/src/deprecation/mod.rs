@@ -0,0 +1,123 @@
+use crate::env;
+use crate::registry;
+use error::*;
+use result::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+
+lazy_static::lazy_static! {
+    /// Serializes every read-modify-write of the deprecations file.
+    static ref LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// A Deprecation marks an installed image as slated for retirement.
+///
+/// Deprecations are reported alongside the image's [Inspection](registry::Inspection), which
+/// is how the ACM learns of them before deploying the image. Until the `sunset` passes the ACM
+/// merely warns the caller, after which it may (depending on its configuration) refuse to deploy
+/// the image altogether.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Kind)]
+pub struct Deprecation {
+    pub tag: String,
+    /// A human readable explanation, such as which build replaces this one.
+    pub message: String,
+    /// The Unix timestamp after which the image SHOULD no longer be deployed. A deprecation
+    /// without a sunset is purely advisory.
+    pub sunset: Option<i64>,
+    /// The Unix timestamp at which the image was deprecated.
+    pub deprecated_at: i64,
+}
+
+/// Marks the given tag as deprecated, replacing any previous deprecation of the same tag.
+/// If no such tag is installed, then a [TagNotFound](registry::TagNotFound) is returned.
+pub async fn deprecate(tag: String, message: String, sunset: Option<i64>) -> Result<Deprecation> {
+    let image = registry::get(tag).await?;
+    let deprecation = Deprecation {
+        tag: image.tag,
+        message,
+        sunset,
+        deprecated_at: now(),
+    };
+    let _guard = LOCK.lock().await;
+    let mut deprecations = load().await?;
+    deprecations.insert(deprecation.tag.clone(), deprecation.clone());
+    save(&deprecations).await?;
+    Ok(deprecation)
+}
+
+/// Lifts the deprecation of the given tag. Lifting a deprecation that does not exist succeeds.
+pub async fn undeprecate<T: AsRef<str>>(tag: T) -> Result<()> {
+    let _guard = LOCK.lock().await;
+    let mut deprecations = load().await?;
+    if deprecations.remove(tag.as_ref()).is_some() {
+        save(&deprecations).await?;
+    }
+    Ok(())
+}
+
+/// Returns the deprecation of the given tag, if it has been deprecated.
+pub async fn get<T: AsRef<str>>(tag: T) -> Result<Option<Deprecation>> {
+    let _guard = LOCK.lock().await;
+    Ok(load().await?.remove(tag.as_ref()))
+}
+
+async fn load() -> Result<BTreeMap<String, Deprecation>> {
+    let path = env::deprecations_path();
+    let contents = match tokio::fs::read(&path).await {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(err) => return Err(DeprecationStoreError::new(&path, err).into()),
+    };
+    Ok(serde_json::from_slice(&contents).map_err(|err| DeprecationStoreError::new(&path, err))?)
+}
+
+/// Writes the given deprecations to a sibling file before renaming it over the original so
+/// that a crash mid-write never leaves a torn file behind.
+async fn save(deprecations: &BTreeMap<String, Deprecation>) -> Result<()> {
+    let path = env::deprecations_path();
+    let contents = serde_json::to_vec_pretty(deprecations)
+        .map_err(|err| DeprecationStoreError::new(&path, err))?;
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|err| DeprecationStoreError::new(&path, err))?;
+    }
+    let staged = path.with_extension("json.tmp");
+    tokio::fs::write(&staged, contents)
+        .await
+        .map_err(|err| DeprecationStoreError::new(&path, err))?;
+    tokio::fs::rename(&staged, &path)
+        .await
+        .map_err(|err| DeprecationStoreError::new(&path, err))?;
+    Ok(())
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[error(
+    "Failed to access the image deprecations kept at {path}, {cause}. Please check that the \
+volume mounted for the DEPRECATIONS_PATH is writable and has space available."
+)]
+#[code(Status::InternalServerError)]
+#[error_code("AIM-1500")]
+pub struct DeprecationStoreError {
+    path: String,
+    cause: String,
+}
+
+impl DeprecationStoreError {
+    fn new<E: ToString>(path: &std::path::Path, err: E) -> DeprecationStoreError {
+        DeprecationStoreError {
+            path: path.display().to_string(),
+            cause: err.to_string(),
+        }
+    }
+}
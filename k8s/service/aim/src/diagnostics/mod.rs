@@ -0,0 +1,37 @@
+//! The `--preflight` mode of the AIM, which validates the environment that the AIM is about to run
+//! within rather than running within it. Every check is made and printed as a
+//! [Checklist](term_colors::layout::Checklist), after which the AIM exits non-zero should any
+//! check have failed. This makes for a ready made init container.
+//!
+//! Checked are that containerd answers at its [address](env::containerd_address), that the
+//! configured [registry](env::registry) is reachable, and that credentials to push to it may be
+//! had. Unlike [configure](registry::Implementation::configure), nothing is bootstrapped.
+use crate::env;
+use crate::registry;
+use term_colors::layout::Checklist;
+
+/// The command line flag that switches the AIM into its preflight mode.
+pub const PREFLIGHT: &str = "--preflight";
+
+/// Whether the AIM was asked to run its preflight checks.
+pub fn requested() -> bool {
+    std::env::args().any(|arg| arg == PREFLIGHT)
+}
+
+/// Runs every preflight check, prints the report, and returns the code that the process ought to
+/// exit with.
+pub async fn run() -> i32 {
+    let mut checklist = Checklist::default();
+    checklist.check("containerd", registry::containerd::ping().await);
+    checklist.check(
+        format!("Registry ({})", env::implementation()),
+        registry::ping().await,
+    );
+    checklist.check("Registry credentials", registry::check_credentials().await);
+    println!("{}", checklist);
+    if checklist.passed() {
+        0
+    } else {
+        1
+    }
+}
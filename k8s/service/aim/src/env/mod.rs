@@ -1,6 +1,24 @@
-use std::env::VarError;
-use std::ffi::OsStr;
-use std::fmt::{Debug, Display, Formatter};
+use config::aim::AimConfig;
+use secret::Secret;
+use std::path::PathBuf;
+
+lazy_static::lazy_static! {
+    static ref CONFIG: AimConfig = AimConfig::configured();
+}
+
+/// Loads (and validates) the configuration of the AIM, PANICKING with every problem that it has.
+/// Every environment variable of this module may instead be given within the
+/// [configuration file](config::CONFIG_FILE).
+///
+/// Consumers SHOULD call this function upon startup, before any other function of this module.
+pub fn configure() {
+    lazy_static::initialize(&CONFIG);
+}
+
+/// Returns the configuration of the AIM, as reported by the `/config` endpoint.
+pub fn config() -> &'static AimConfig {
+    &CONFIG
+}
 
 /// The registry configured under the `REGISTRY` environment variable. If no such environment
 /// variable is set, then this function defaults to `registry.kube-system` (which is the
@@ -11,14 +29,14 @@ use std::fmt::{Debug, Display, Formatter};
 ///
 /// A valid example may be `248135293344.dkr.ecr.us-east-2.amazonaws.com`.
 pub fn registry() -> String {
-    std::env::var("REGISTRY").unwrap_or_else(|_| String::from("registry.kube-system"))
+    CONFIG.registry.clone()
 }
 
 /// The repository configured under the `REPOSITORY` environment variable. If no such environment
 /// variable is set, then this function defaults to `ocf` (which is the repository used
 /// for local Minikube development).
 pub fn repository() -> String {
-    std::env::var("REPOSITORY").unwrap_or_else(|_| String::from("ocf"))
+    CONFIG.repository.clone()
 }
 
 /// The registry implementation configured under the `IMPLEMENTATION` environment variable. If no
@@ -29,7 +47,28 @@ pub fn repository() -> String {
 /// * `ECR`
 /// * `Minikube` (for development and testing ONLY!)
 pub fn implementation() -> String {
-    std::env::var("IMPLEMENTATION").unwrap_or_else(|_| String::from("Minikube"))
+    CONFIG.implementation.clone()
+}
+
+/// The address of containerd's gRPC socket configured under the `CONTAINERD_ADDRESS` environment
+/// variable. If no such environment variable is set, then this function defaults to
+/// `/run/containerd/containerd.sock` (which is where the containerd sidecar listens).
+pub fn containerd_address() -> String {
+    CONFIG.containerd_address.clone()
+}
+
+/// The file in which [image deprecations](crate::deprecation) are kept, configured under the
+/// `DEPRECATIONS_PATH` environment variable. If no such environment variable is set, then this
+/// function defaults to `/var/lib/aim/deprecations.json`.
+pub fn deprecations_path() -> PathBuf {
+    CONFIG.deprecations_path.clone()
+}
+
+/// The file in which the [metadata](crate::metadata) of installed images is cached, configured
+/// under the `METADATA_PATH` environment variable. If no such environment variable is set, then
+/// this function defaults to `/var/lib/aim/metadata.json`.
+pub fn metadata_path() -> PathBuf {
+    CONFIG.metadata_path.clone()
 }
 
 /// The AWS region configured under the `AWS_REGION` environment variable. This is the AWS region
@@ -42,11 +81,9 @@ pub fn implementation() -> String {
 /// The `AWS_REGION` environment variable is MANDATORY when the configured
 /// (implementation)[implementation] is `ECR`.
 pub fn aws_region() -> String {
-    std::env::var("AWS_REGION")
-        .and_then(map_empty_to_error)
-        .expect(
-            "The AWS_REGION environment variable is mandatory when using the ECR implementation",
-        )
+    CONFIG.aws_region.clone().expect(
+        "The AWS_REGION environment variable is mandatory when using the ECR implementation",
+    )
 }
 
 /// The AWS access key ID configured under the `AWS_ACCESS_KEY_ID` environment variable. This
@@ -60,7 +97,7 @@ pub fn aws_region() -> String {
 /// The `AWS_ACCESS_KEY_ID` environment variable is MANDATORY when the configured
 /// (implementation)[implementation] is `ECR`.
 pub fn aws_access_key_id() -> String {
-    std::env::var("AWS_ACCESS_KEY_ID").and_then(map_empty_to_error).expect(
+    CONFIG.aws_access_key_id.clone().expect(
         "The AWS_ACCESS_KEY_ID environment variable is mandatory when using the ECR implementation",
     )
 }
@@ -76,9 +113,9 @@ pub fn aws_access_key_id() -> String {
 /// The `AWS_SECRET_ACCESS_KEY` environment variable is MANDATORY when the configured
 /// (implementation)[implementation] is `ECR`.
 pub fn aws_secret_access_key() -> Secret {
-    std::env::var("AWS_SECRET_ACCESS_KEY").and_then(map_empty_to_error).expect(
+    CONFIG.aws_secret_access_key.clone().expect(
         "The AWS_SECRET_ACCESS_KEY environment variable is mandatory when using the ECR implementation",
-    ).into()
+    )
 }
 
 /// The AWS IAM user configured under the `AWS_USERNAME` environment variable.
@@ -92,111 +129,170 @@ pub fn aws_secret_access_key() -> Secret {
 /// The `AWS_USERNAME` environment variable is MANDATORY when the configured
 /// (implementation)[implementation] is `ECR`.
 pub fn aws_username() -> String {
-    std::env::var("AWS_USERNAME")
-        .and_then(map_empty_to_error)
-        .expect(
-            "The AWS_USERNAME environment variable is mandatory when using the ECR implementation",
-        )
+    CONFIG.aws_username.clone().expect(
+        "The AWS_USERNAME environment variable is mandatory when using the ECR implementation",
+    )
+}
+
+/// The maximum number of images kept in the ECR repository, configured under the `ECR_MAX_IMAGES`
+/// environment variable. If no such environment variable is set, then no such limit is enforced.
+///
+/// A variable that is set to anything other than a positive integer fails [configure](configure).
+pub fn ecr_max_images() -> Option<usize> {
+    CONFIG.ecr_max_images
 }
 
-/// If an environment variable is technically present, albeit empty, then we would like to
-/// take that to mean that it doesn't actually exist.
-fn map_empty_to_error(var: String) -> std::result::Result<String, VarError> {
-    if var.is_empty() {
-        Err(VarError::NotPresent)
-    } else {
-        Ok(var)
-    }
+/// The maximum number of days that an image is kept in the ECR repository after being pushed,
+/// configured under the `ECR_MAX_IMAGE_AGE_DAYS` environment variable. If no such environment
+/// variable is set, then no such limit is enforced.
+///
+/// A variable that is set to anything other than a positive integer fails [configure](configure).
+pub fn ecr_max_image_age_days() -> Option<usize> {
+    CONFIG.ecr_max_image_age_days
 }
 
-/// A `Secret` obfuscates an underlying string from being accidentally printed to any logs.
+/// The maximum number of images that may be installed concurrently, configured under the
+/// `MAX_CONCURRENT_INSTALLS` environment variable. If no such environment variable is set, then
+/// this function defaults to `2`.
 ///
-/// Any attempt to format a `Secret` using the either the [Display](Display)("{}") or [Debug](Debug)
-/// ("{:?}") directives will result in the string "<REDACTED>" rather than the underlying secret.
+/// Every install streams up to 10 gigabytes to disk and then through containerd, so this value
+/// SHOULD be sized against the disk available to the AIM.
 ///
-/// Original secret may be retrieved by either requesting a reference to a [String](String)/[str](str)
-/// or by explicitly calling [raw_secret](Secret::raw_secret).  
+/// A variable that is set to anything other than a positive integer fails [configure](configure).
+pub fn max_concurrent_installs() -> usize {
+    CONFIG.max_concurrent_installs
+}
+
+/// The maximum number of installs that may wait for admission (in FIFO order) while
+/// [max_concurrent_installs](max_concurrent_installs) are already in progress, configured under
+/// the `MAX_QUEUED_INSTALLS` environment variable. If no such environment variable is set, then
+/// this function defaults to `8`. Installs beyond this limit are rejected with a `429 Too Many Requests`.
 ///
-/// ```
-/// let password = Secret::from("please don't log this");
-/// let log_entry = format!("my password is {}!", password);
-/// assert_eq!("my password is <REDACTED>!", log_entry);
-/// ```
-pub struct Secret {
-    secret: String,
+/// A variable that is set to anything other than a non-negative integer fails [configure](configure).
+pub fn max_queued_installs() -> usize {
+    CONFIG.max_queued_installs
 }
 
-impl Secret {
-    pub fn raw_secret(&self) -> &str {
-        self.as_ref()
-    }
+/// The number of seconds that a rejected install is advised to wait before retrying (via the
+/// `Retry-After` header), configured under the `INSTALL_RETRY_AFTER` environment variable. If no
+/// such environment variable is set, then this function defaults to `30`.
+///
+/// A variable that is set to anything other than a positive integer fails [configure](configure).
+pub fn install_retry_after() -> u64 {
+    CONFIG.install_retry_after
 }
 
-impl Display for Secret {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str("<REDACTED>")
-    }
+/// The number of bytes that the configured repository may hold, configured under the
+/// `REGISTRY_QUOTA` environment variable. Once the repository's [usage](crate::usage) reaches this
+/// quota, new installs are rejected until images are uninstalled. If no such environment variable
+/// is set, then no quota is enforced.
+///
+/// A variable that is set to anything other than a positive integer fails [configure](configure).
+pub fn registry_quota() -> Option<u64> {
+    CONFIG.registry_quota.map(|quota| quota as u64)
+}
+
+/// The scanner that uploaded images are run through before they are imported, configured under
+/// the `SCANNER` environment variable. If no such environment variable is set, then this function
+/// defaults to `none`. See [scan](crate::scan) for each of the scanners.
+///
+/// Valid scanners are:
+/// * `none`
+/// * `clamd`
+/// * `webhook`
+pub fn scanner() -> String {
+    CONFIG.scanner.to_lowercase()
 }
 
-impl Debug for Secret {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str("<REDACTED>")
-    }
+/// The unix socket of the clamd that uploaded images are scanned by, configured under the
+/// `CLAMD_SOCKET` environment variable. If no such environment variable is set, then this function
+/// defaults to `/var/run/clamav/clamd.ctl`. Only used when the [scanner](scanner) is `clamd`.
+pub fn clamd_socket() -> PathBuf {
+    CONFIG.clamd_socket.clone()
 }
 
-impl From<String> for Secret {
-    fn from(secret: String) -> Self {
-        Self { secret }
-    }
+/// The URL that uploaded images are POSTed to for scanning, configured under the `SCAN_WEBHOOK`
+/// environment variable.
+///
+/// The `SCAN_WEBHOOK` environment variable is MANDATORY when the configured [scanner](scanner) is
+/// `webhook`.
+pub fn scan_webhook() -> String {
+    CONFIG
+        .scan_webhook
+        .clone()
+        .expect("The SCAN_WEBHOOK environment variable is mandatory when using the webhook scanner")
 }
 
-impl From<&String> for Secret {
-    fn from(secret: &String) -> Self {
-        Self {
-            secret: secret.clone(),
-        }
-    }
+/// The directory into which uploads that were flagged by the [scanner](scanner) are moved,
+/// configured under the `QUARANTINE_PATH` environment variable. If no such environment variable is
+/// set, then this function defaults to `/var/lib/aim/quarantine`.
+pub fn quarantine_path() -> PathBuf {
+    CONFIG.quarantine_path.clone()
 }
 
-impl From<&str> for Secret {
-    fn from(secret: &str) -> Self {
-        Self::from(secret.to_string())
-    }
+/// Where uploads are spooled between being received and being imported, configured under the
+/// `SPOOL` environment variable. If no such environment variable is set, then this function
+/// defaults to `disk`. See [spool](crate::spool) for each of the spools.
+///
+/// Valid spools are:
+/// * `disk`
+/// * `s3`
+pub fn spool() -> String {
+    CONFIG.spool.to_lowercase()
 }
 
-impl AsRef<str> for Secret {
-    fn as_ref(&self) -> &str {
-        self.secret.as_str()
-    }
+/// The directory that uploads are received into (and, for the `disk` [spool](spool), held within),
+/// configured under the `SPOOL_PATH` environment variable. If no such environment variable is set,
+/// then this function defaults to `aim-spool` within the system's temporary directory. This MAY be
+/// a mounted PersistentVolumeClaim, such that uploads are not limited by the pod's ephemeral disk.
+///
+/// Everything within this directory is considered abandoned (and removed) upon startup, so it
+/// MUST NOT be shared with anything else.
+pub fn spool_path() -> PathBuf {
+    CONFIG.spool_path.clone()
 }
 
-impl AsRef<String> for Secret {
-    fn as_ref(&self) -> &String {
-        &self.secret
-    }
+/// The S3 bucket that uploads are held within, configured under the `SPOOL_BUCKET` environment
+/// variable. The bucket is reached with the same AWS credentials (and region) as ECR.
+///
+/// The `SPOOL_BUCKET` environment variable is MANDATORY when the configured [spool](spool) is `s3`.
+pub fn spool_bucket() -> String {
+    CONFIG
+        .spool_bucket
+        .clone()
+        .expect("The SPOOL_BUCKET environment variable is mandatory when using the S3 spool")
 }
 
-impl AsRef<OsStr> for Secret {
-    fn as_ref(&self) -> &OsStr {
-        self.secret.as_ref()
-    }
+/// The prefix of the keys of the uploads held within the [spool bucket](spool_bucket), configured
+/// under the `SPOOL_PREFIX` environment variable. If no such environment variable is set, then
+/// this function defaults to `aim/spool`.
+pub fn spool_prefix() -> String {
+    CONFIG.spool_prefix.trim_matches('/').to_string()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// The number of bytes that MUST remain free within the [spool path](spool_path) once an upload
+/// has been received, configured under the `SPOOL_MIN_FREE` environment variable. If no such
+/// environment variable is set, then this function defaults to `1073741824` (1 GiB).
+///
+/// A variable that is set to anything other than a non-negative integer fails [configure](configure).
+pub fn spool_min_free() -> u64 {
+    CONFIG.spool_min_free
+}
 
-    #[test]
-    fn test_secret_display() {
-        let password = Secret::from("please don't log this");
-        let log_entry = format!("my password is {}!", password);
-        assert_eq!("my password is <REDACTED>!", log_entry);
-    }
+/// The number of retries within the [budget](retry::Budget) shared by every budgeted retry,
+/// configured under the `RETRY_BUDGET_CAPACITY` environment variable. If no such environment
+/// variable is set, then this function defaults to `100`.
+///
+/// A variable that is set to anything other than a positive integer fails [configure](configure).
+pub fn retry_budget_capacity() -> u32 {
+    CONFIG.retry_budget_capacity
+}
 
-    #[test]
-    fn test_secret_debug() {
-        let password = Secret::from("please don't log this");
-        let log_entry = format!("my password is {:?}!", password);
-        assert_eq!("my password is <REDACTED>!", log_entry);
-    }
+/// The number of retries refilled into the [budget](retry::Budget) per second, configured under
+/// the `RETRY_BUDGET_REFILL` environment variable. If no such environment variable is set, then
+/// this function defaults to `20`.
+///
+/// A variable that is set to anything other than a positive integer fails [configure](configure).
+pub fn retry_budget_refill() -> u32 {
+    CONFIG.retry_budget_refill
 }
@@ -1,16 +1,38 @@
+mod admission;
+mod auditor;
+mod checksum;
+mod deprecation;
+mod diagnostics;
 mod env;
+mod metadata;
 mod registry;
+mod scan;
+mod spool;
+mod usage;
 
-use crate::registry::Image;
+use crate::admission::{InstallProgress, InstallStatus, Ticket};
+use crate::checksum::Checksum;
+use crate::deprecation::Deprecation;
+use crate::registry::{Image, Inspection, Lookup, MalformedRemoteInstall, RemoteInstall};
+use crate::spool::Preflight;
+use crate::usage::Usage;
+use audit::{Action, Actor, Entry};
+use config::aim::AimConfig;
+use names::{Rfc1035Label, Rfc1123Subdomain};
 use response::Response;
 use result::Result;
 use rocket::data::{ByteUnit, Limits};
 use rocket::fs::TempFile;
+use rocket::serde::json::{self, Json};
+use std::future::Future;
 
 #[macro_use]
 extern crate rocket;
 
 #[macro_use]
+extern crate lazy_static;
+
+#[cfg_attr(feature = "ctr", macro_use)]
 extern crate os;
 
 const MAX_UPLOAD_SIZE: ByteUnit = ByteUnit::Gigabyte(10);
@@ -49,9 +71,285 @@ const MAX_UPLOAD_SIZE: ByteUnit = ByteUnit::Gigabyte(10);
 ///   "error": null
 /// }
 /// ```
-#[post("/install", data = "<image>")]
-async fn install(image: TempFile<'_>) -> Result<Response<Image>> {
-    Ok(registry::import(image).await?.into())
+///
+/// Installs are subject to [admission control](admission). At most
+/// [max_concurrent_installs](env::max_concurrent_installs) run at once while up to
+/// [max_queued_installs](env::max_queued_installs) more wait their turn. A queued install does not
+/// begin uploading until it is admitted. Should the queue be full, then the install is rejected with
+/// a `429 Too Many Requests` and a `Retry-After` header.
+///
+/// The optional `id` query parameter names the install so that its progress may be polled
+/// via [/install/status](install_status) while it is queued or in progress. The ID must be a valid
+/// [RFC 1123 subdomain](names::is_valid_rfc1123_subdomain), otherwise the install is rejected with
+/// a `422 Unprocessable Entity` before it is queued.
+///
+/// ```text
+/// # BASH curl example
+/// curl -X POST --data-binary @oracle.img "http://aim.ocf-system/install?id=my-install"
+/// ```
+///
+/// Should a [registry quota](env::registry_quota) be configured, then an install is refused with a
+/// `507 Insufficient Storage` once the registry's [usage](usage) has reached it.
+///
+/// Clients SHOULD give the SHA-256 of the image, via either the
+/// [X-Checksum-Sha256](checksum::CHECKSUM_HEADER) header or the `sha256` query parameter, in which
+/// case the upload is [verified](checksum) against it before it is imported. An upload that was
+/// corrupted along the way is refused with a `422 Unprocessable Entity` and a
+/// [ChecksumMismatch](checksum::ChecksumMismatch).
+///
+/// ```text
+/// # BASH curl example
+/// curl -X POST --data-binary @oracle.img -H "X-Checksum-Sha256: $(sha256sum oracle.img | cut -d' ' -f1)" \
+///     "http://aim.ocf-system/install?id=my-install"
+/// ```
+///
+/// Should a [scanner](scan) be configured, then the upload is scanned for malware before it is
+/// imported. A flagged upload is quarantined and its install fails with a
+/// [MalwareDetected](scan::MalwareDetected), while an upload that could not be scanned at all fails
+/// with a [ScanFailed](scan::ScanFailed).
+///
+/// Uploads are received into the [spool](spool). An upload whose `Content-Length` would not fit
+/// within the spool is refused with a `507 Insufficient Storage` before it is received.
+///
+/// Pushing a large image may take longer than a client is willing to hold an HTTP request open.
+/// If `detach=true` is given, then this endpoint returns a `202 Accepted` carrying the install's
+/// [InstallProgress](admission::InstallProgress) as soon as the upload completes, while the
+/// import, retag, and push continue in the background. The install may then be polled via
+/// [/install/progress](install_progress) until it reaches either the `installed` or `failed` phase.
+///
+/// ```text
+/// # BASH curl example
+/// curl -X POST --data-binary @oracle.img "http://aim.ocf-system/install?id=my-install&detach=true"
+/// ```
+#[post("/install?<detach>", data = "<image>")]
+async fn install(
+    ticket: Ticket,
+    _preflight: Preflight,
+    mut image: TempFile<'_>,
+    detach: Option<bool>,
+    checksum: Checksum,
+    actor: Actor,
+) -> Result<Installation> {
+    ticket.uploaded(image.len());
+    let id = ticket.id().to_string();
+    if let Err(err) = usage::check_quota().await {
+        return auditor::record(&actor, Action::Install, id, Err(err)).await;
+    }
+    let staged = match registry::stage(&mut image).await {
+        Ok(staged) => staged,
+        Err(err) => return auditor::record(&actor, Action::Install, id, Err(err)).await,
+    };
+    if let Err(err) = checksum.verify(staged.path()).await {
+        return auditor::record(&actor, Action::Install, id, Err(err)).await;
+    }
+    let spooled = match spool::hold(staged.path().to_path_buf()).await {
+        Ok(spooled) => spooled,
+        Err(err) => return auditor::record(&actor, Action::Install, id, Err(err)).await,
+    };
+    let target = id.clone();
+    let job = async move {
+        let result = match spooled.fetch().await {
+            Ok(path) => match scan::scan(&target, &path).await {
+                Ok(()) => {
+                    registry::import(&path, ticket.cancellation(), |step| ticket.step(step)).await
+                }
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err),
+        };
+        spooled.release().await;
+        // The staged upload is held by the job so that, should the client go away and take the
+        // job with it part way through, the upload is still removed.
+        drop(staged);
+        settle(ticket, actor, target, result).await
+    };
+    run(id, detach, job).await
+}
+
+/// Installs the image at the given reference within a remote registry (such as a vendor's own)
+/// into this AIM's configured image registry. Rather than being uploaded, the image is pulled
+/// straight into containerd (every platform of it) before undergoing the same sanitization as an
+/// [uploaded](install) image, which spares multi-gigabyte round trips through the client.
+///
+/// The body is a JSON object naming the `reference` of the image, alongside the `username` and
+/// `password` to pull it with, should the remote registry require any. A reference without a
+/// registry refers to Docker Hub, and one without a tag refers to `latest`. A malformed body
+/// (or reference) is rejected with a `400 Bad Request` before the install is begun.
+///
+/// ```text
+/// # BASH curl example
+/// curl -X POST -H "Content-Type: application/json" \
+///     -d '{"reference": "public.ecr.aws/alation/oracle:1.2.0"}' \
+///     "http://aim.ocf-system/install/remote?id=my-install"
+/// ```
+///
+/// Remote installs are otherwise the same as [uploaded](install) ones. That is, they are subject
+/// to the same admission control and registry quota, report their progress via
+/// [/install/progress](install_progress) (pulling is reported as the `importing` phase), may be
+/// cancelled, and may be detached via `detach=true`.
+#[post("/install/remote?<detach>", data = "<remote>")]
+async fn install_remote(
+    ticket: Ticket,
+    remote: std::result::Result<Json<RemoteInstall>, json::Error<'_>>,
+    detach: Option<bool>,
+    actor: Actor,
+) -> Result<Installation> {
+    let id = ticket.id().to_string();
+    let remote = match remote {
+        Ok(Json(remote)) => remote.remote(),
+        Err(err) => Err(MalformedRemoteInstall {
+            cause: match err {
+                json::Error::Io(err) => err.to_string(),
+                json::Error::Parse(_, err) => err.to_string(),
+            },
+        }
+        .into()),
+    };
+    let remote = match remote {
+        Ok(remote) => remote,
+        Err(err) => return auditor::record(&actor, Action::Install, id, Err(err)).await,
+    };
+    if let Err(err) = usage::check_quota().await {
+        return auditor::record(&actor, Action::Install, id, Err(err)).await;
+    }
+    info!(
+        "Install {} is pulling {}",
+        term_colors::highlight(id.as_str()),
+        term_colors::highlight(remote.to_string())
+    );
+    let target = id.clone();
+    let job = async move {
+        let result = registry::pull(&remote, ticket.cancellation(), |step| ticket.step(step)).await;
+        settle(ticket, actor, target, result).await
+    };
+    run(id, detach, job).await
+}
+
+/// Finishes the install of the given ticket with the given result, caching the metadata of the
+/// installed image and recording the install within the audit trail.
+async fn settle(
+    ticket: Ticket,
+    actor: Actor,
+    target: String,
+    mut result: Result<Image>,
+) -> Result<Image> {
+    // The metadata is merely cached ahead of time, so failing to do so fails nothing.
+    if let Ok(image) = result.as_mut() {
+        match metadata::extract(image).await {
+            Ok(config) => image.labels = config.connector_labels(),
+            Err(err) => warn!("Failed to extract the metadata of {}, {}", image.tag, err),
+        }
+    }
+    ticket.finish(&result);
+    auditor::record(&actor, Action::Install, target, result).await
+}
+
+/// Runs the given install job, either in the background (should the install be detached) or while
+/// the client waits.
+async fn run<J>(id: String, detach: Option<bool>, job: J) -> Result<Installation>
+where
+    J: Future<Output = Result<Image>> + Send + 'static,
+{
+    if detach.unwrap_or(false) {
+        tokio::spawn(job);
+        let location = format!("/install/progress?id={}", id);
+        Ok(Installation::Detached(
+            Response::accepted(admission::progress(id)?).location(location),
+        ))
+    } else {
+        let image = job.await?;
+        let location = format!("/get?tag={}", image.tag);
+        Ok(Installation::Installed(
+            Response::created(image).location(location),
+        ))
+    }
+}
+
+/// An Installation is the result of an [install](install). A detached install responds with a
+/// `202 Accepted` carrying its [progress](admission::InstallProgress) while every other install
+/// responds with a `201 Created` carrying the installed [Image](Image). Either way, the `Location`
+/// header names the endpoint at which the install (or image) may be found thereafter.
+#[derive(Responder)]
+enum Installation {
+    Installed(Response<Image>),
+    Detached(Response<InstallProgress>),
+}
+
+/// Returns the detailed progress of the install with the given `id`. The `phase` of an install
+/// moves through `queued`, `uploading`, `importing`, `retagging`, and `pushing` before ending in
+/// either `installed` (alongside the installed image) or `failed` (alongside the error). If no
+/// such install is known, then an [InstallNotFound](admission::InstallNotFound) error is returned.
+///
+/// ```text
+/// # BASH curl example
+/// curl "http://aim.ocf-system/install/progress?id=my-install"
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "InstallProgress",
+///     "object": {
+///       "id": "my-install",
+///       "phase": "pushing",
+///       "bytes": 20132659,
+///       "bytes_pushed": 0
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/install/progress?<id>")]
+async fn install_progress(id: String) -> Result<Response<InstallProgress>> {
+    let id = Rfc1123Subdomain::new(id)?;
+    Ok(admission::progress(id)?.into())
+}
+
+/// Returns the status of the install with the given `id`. Queued installs report their
+/// position in the queue (`1` being next in line) while finished installs report either the
+/// installed image or the error that they failed with. If no such install is known, then an
+/// [InstallNotFound](admission::InstallNotFound) error is returned.
+///
+/// ```text
+/// # BASH curl example
+/// curl "http://aim.ocf-system/install/status?id=my-install"
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "InstallStatus",
+///     "object": {
+///       "id": "my-install",
+///       "state": "queued",
+///       "position": 2
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/install/status?<id>")]
+async fn install_status(id: String) -> Result<Response<InstallStatus>> {
+    let id = Rfc1123Subdomain::new(id)?;
+    Ok(admission::status(id)?.into())
+}
+
+/// Cancels the install with the given `id`, returning its progress. An install that is underway is
+/// aborted (killing any command that it is running) and fails with an
+/// [InstallCancelled](admission::InstallCancelled), while an install that is queued or that has
+/// already finished is left as it is. If no such install is known, then an
+/// [InstallNotFound](admission::InstallNotFound) error is returned.
+///
+/// ```text
+/// # BASH curl example
+/// curl -X DELETE "http://aim.ocf-system/install?id=my-install"
+/// ```
+#[delete("/install?<id>")]
+async fn cancel_install(id: String) -> Result<Response<InstallProgress>> {
+    let id = Rfc1123Subdomain::new(id)?;
+    Ok(admission::cancel(id)?.into())
 }
 
 /// Deletes the given tag from the configured image registry. If the tag is not found, then
@@ -67,18 +365,162 @@ async fn install(image: TempFile<'_>) -> Result<Response<Image>> {
 /// In Minikube, however, the deletion of a tag WILL result in the deletion of the backing digest.
 /// Meaning that in development settings, if the same image is installed multiple times, then the
 /// deletion of one tag will result in the deletion of all other tags backed by the same digest.
+///
+/// Any [deprecation](deprecate) of the tag is lifted along with it.
 #[delete("/uninstall?<tag>")]
-async fn uninstall(tag: String) -> Result<Response<()>> {
-    Ok(registry::uninstall(tag).await?.into())
+async fn uninstall(tag: String, actor: Actor) -> Result<Response<()>> {
+    let uninstalled = async {
+        Rfc1035Label::new(tag.as_str())?;
+        registry::uninstall(tag.clone()).await?;
+        Ok(deprecation::undeprecate(tag.clone()).await?.into())
+    }
+    .await;
+    auditor::record(&actor, Action::Uninstall, tag, uninstalled).await
+}
+
+/// Marks the given tag as deprecated with the given message and an optional sunset (a Unix
+/// timestamp). Deprecating a tag that is already deprecated replaces its deprecation. If no such
+/// tag exists in the registry, then a [TagNotFound](registry::TagNotFound) error is returned.
+///
+/// The deprecation is reported by [inspect](inspect), and thus by the ACM when the image is
+/// deployed. Deploying a deprecated image succeeds with a warning until its sunset, after which
+/// the ACM refuses to deploy it (unless the ACM is configured to only ever warn).
+///
+/// ```text
+/// # BASH curl example
+/// curl -X POST "http://aim.ocf-system/deprecate?tag=n6f7748462d94a093610de86808febbd&message=Superseded%20by%20v2&sunset=1640995200"
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "Deprecation",
+///     "object": {
+///       "tag": "n6f7748462d94a093610de86808febbd",
+///       "message": "Superseded by v2",
+///       "sunset": 1640995200,
+///       "deprecated_at": 1636329600
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[post("/deprecate?<tag>&<message>&<sunset>")]
+async fn deprecate(
+    tag: String,
+    message: String,
+    sunset: Option<i64>,
+) -> Result<Response<Deprecation>> {
+    let tag = Rfc1035Label::new(tag)?;
+    Ok(deprecation::deprecate(tag.into(), message, sunset)
+        .await?
+        .into())
+}
+
+/// Lifts the [deprecation](deprecate) of the given tag. If the tag is not deprecated, then this
+/// endpoint silently succeeds.
+///
+/// ```text
+/// # BASH curl example
+/// curl -X DELETE "http://aim.ocf-system/deprecate?tag=n6f7748462d94a093610de86808febbd"
+/// ```
+#[delete("/deprecate?<tag>")]
+async fn undeprecate(tag: String) -> Result<Response<()>> {
+    let tag = Rfc1035Label::new(tag)?;
+    Ok(deprecation::undeprecate(tag).await?.into())
+}
+
+/// Promotes the given tag by tagging the very same digest with the given alias (say, `production`)
+/// as well, without uploading the image anew. This makes for a staging to production flow wherein
+/// connectors are deployed by a stable, human-readable alias that is moved from one vetted tag to
+/// the next.
+///
+/// The alias must be a valid RFC 1035 label. Promoting a tag to an alias that it already carries
+/// succeeds, whereas an alias that refers to another digest is an
+/// [AliasConflict](registry::AliasConflict), and must be [uninstalled](uninstall) first. If no
+/// such tag exists in the registry, then a [TagNotFound](registry::TagNotFound) error is returned.
+///
+/// ```text
+/// # BASH curl example
+/// curl -X POST "http://aim.ocf-system/promote?tag=n6f7748462d94a093610de86808febbd&alias=production"
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "Image",
+///     "object": {
+///       "tag": "production",
+///       "digest": "sha256:cb1ff0854b8864a6a68ee0b5e509d4d94c50a41f96dc2749ea71dc124c89d11f"
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[post("/promote?<tag>&<alias>")]
+async fn promote(tag: String, alias: String, actor: Actor) -> Result<Response<Image>> {
+    let target = format!("{} -> {}", tag, alias);
+    let promoted = async {
+        let tag = Rfc1035Label::new(tag)?;
+        Ok(registry::promote(tag.into(), alias).await?.into())
+    }
+    .await;
+    auditor::record(&actor, Action::Promote, target, promoted).await
+}
+
+/// Returns every tag that refers to the same digest as the given tag (the given tag included),
+/// that is, every alias that it has been [promoted](promote) to. If no such tag exists in the
+/// registry, then a [TagNotFound](registry::TagNotFound) error is returned.
+///
+/// ```text
+/// # BASH curl example
+/// curl -X GET "http://aim.ocf-system/aliases?tag=n6f7748462d94a093610de86808febbd"
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "List[Image]",
+///     "object": [
+///       {
+///         "tag": "n6f7748462d94a093610de86808febbd",
+///         "digest": "sha256:cb1ff0854b8864a6a68ee0b5e509d4d94c50a41f96dc2749ea71dc124c89d11f"
+///       },
+///       {
+///         "tag": "production",
+///         "digest": "sha256:cb1ff0854b8864a6a68ee0b5e509d4d94c50a41f96dc2749ea71dc124c89d11f"
+///       }
+///     ]
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/aliases?<tag>")]
+async fn aliases(tag: String) -> Result<Response<Vec<Image>>> {
+    let tag = Rfc1035Label::new(tag)?;
+    Ok(registry::aliases(tag.into()).await?.into())
 }
 
 /// Returns a list of image objects that is all unique `tag:digest` pairs installed to the registry.
+/// Every image carries the [labels](registry::ConnectorLabels) (that is, the connector's `name`,
+/// `version`, and `vendor`) that it was built with, should it have any.
 ///
 /// ```text
 /// # BASH curl example
 /// curl http://aim.ocf-system/list
 /// ```
 ///
+/// The list may be filtered by any of the `name`, `version`, and `vendor` labels, each of which
+/// must match exactly. An image without the label never matches a filter upon it.
+///
+/// ```text
+/// # BASH curl example
+/// curl "http://aim.ocf-system/list?name=oracle&vendor=Alation"
+/// ```
+///
 /// ```text
 /// # Python client exmaple
 /// client = Client()
@@ -93,7 +535,8 @@ async fn uninstall(tag: String) -> Result<Response<()>> {
 ///     "object": [
 ///       {
 ///         "tag": "n6f7748462d94a093610de86808febbd",
-///         "digest": "sha256:cb1ff0854b8864a6a68ee0b5e509d4d94c50a41f96dc2749ea71dc124c89d11f"
+///         "digest": "sha256:cb1ff0854b8864a6a68ee0b5e509d4d94c50a41f96dc2749ea71dc124c89d11f",
+///         "labels": {"name": "oracle", "version": "1.2.0", "vendor": "Alation"}
 ///       },
 ///       {
 ///         "tag": "p70f18eef60727fb2f9105d78e1e9af2",
@@ -104,9 +547,27 @@ async fn uninstall(tag: String) -> Result<Response<()>> {
 ///   "error": null
 /// }
 /// ```
-#[get("/list")]
-async fn list() -> Result<Response<Vec<Image>>> {
-    Ok(registry::list().await?.into())
+///
+/// The response carries an `ETag` derived from the [state of the registry](registry::etag).
+/// Pollers SHOULD send it back as `If-None-Match`, in which case an unchanged registry results
+/// in an empty `304 Not Modified`. `HEAD` is also supported.
+#[get("/list?<name>&<version>&<vendor>")]
+async fn list(
+    name: Option<String>,
+    version: Option<String>,
+    vendor: Option<String>,
+) -> Result<Response<Vec<Image>>> {
+    let images: Vec<Image> = metadata::label(registry::list().await?)
+        .await?
+        .into_iter()
+        .filter(|image| {
+            image
+                .labels
+                .matches(name.as_deref(), version.as_deref(), vendor.as_deref())
+        })
+        .collect();
+    let etag = registry::etag(&images.iter().collect::<Vec<_>>());
+    Ok(Response::from(images).with_etag(etag))
 }
 
 /// Returns a single `tag:digest` object for the given tag. If no such tag exists in the
@@ -136,23 +597,263 @@ async fn list() -> Result<Response<Vec<Image>>> {
 ///   "error": null
 /// }
 /// ```
+///
+/// As with [list](list), the response carries an `ETag` and honors `If-None-Match` and `HEAD`.
 #[get("/get?<tag>")]
 async fn get(tag: String) -> Result<Response<Image>> {
-    Ok(registry::get(tag).await?.into())
+    let tag = Rfc1035Label::new(tag)?;
+    let image = metadata::label(vec![registry::get(tag.into()).await?])
+        .await?
+        .remove(0);
+    let etag = registry::etag(&[&image]);
+    Ok(Response::from(image).with_etag(etag))
+}
+
+/// Returns the [Inspection](registry::Inspection) of the given tag, which includes every
+/// platform (that is, `os/architecture`) that the image may be run on, its
+/// [deprecation](deprecate) (if any), and its [config](registry::ImageConfig). The config (its
+/// entrypoint, exposed ports, environment, labels, layers, size, and when it was built) is
+/// extracted upon install and [cached](metadata) thereafter, so that a caller may, say, confirm
+/// that a connector exposes port `8080` before deploying it. If no such tag exists in the
+/// registry, then a [TagNotFound](registry::TagNotFound) error is returned.
+///
+/// ```text
+/// # BASH curl example
+/// curl http://aim.ocf-system/inspect?tag=n6f7748462d94a093610de86808febbd
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "Inspection",
+///     "object": {
+///       "tag": "n6f7748462d94a093610de86808febbd",
+///       "digest": "sha256:cb1ff0854b8864a6a68ee0b5e509d4d94c50a41f96dc2749ea71dc124c89d11f",
+///       "platforms": [
+///         {"os": "linux", "architecture": "amd64"}
+///       ],
+///       "config": {
+///         "entrypoint": ["/bin/connector"],
+///         "cmd": [],
+///         "exposed_ports": ["8080/tcp"],
+///         "env": ["PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin"],
+///         "labels": {"org.opencontainers.image.title": "oracle"},
+///         "layers": 7,
+///         "size": 312456789,
+///         "created": "2021-09-21T22:12:01.000000000Z"
+///       }
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/inspect?<tag>")]
+async fn inspect(tag: String) -> Result<Response<Inspection>> {
+    let tag = Rfc1035Label::new(tag)?;
+    Ok(registry::inspect(tag.into()).await?.into())
+}
+
+/// Returns the [Lookup](registry::Lookup) of the given tag, which is whether it is installed in the
+/// registry (and if so, its digest) alongside the image reference that it is pulled by. Unlike
+/// [get](get), a tag that is not installed is NOT an error. This is what the ACM consults when
+/// asked for a dry-run of a deploy.
+///
+/// ```text
+/// # BASH curl example
+/// curl http://aim.ocf-system/lookup?tag=n6f7748462d94a093610de86808febbd
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "Lookup",
+///     "object": {
+///       "tag": "n6f7748462d94a093610de86808febbd",
+///       "reference": "registry.kurl/ocf:n6f7748462d94a093610de86808febbd",
+///       "installed": true,
+///       "digest": "sha256:cb1ff0854b8864a6a68ee0b5e509d4d94c50a41f96dc2749ea71dc124c89d11f"
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/lookup?<tag>")]
+async fn lookup(tag: String) -> Result<Response<Lookup>> {
+    let tag = Rfc1035Label::new(tag)?;
+    Ok(registry::lookup(tag.into()).await?.into())
+}
+
+/// Returns the configuration of this AIM, with every secret within it redacted.
+///
+/// ```text
+/// curl -X GET http://aim.ocf-system/config
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "AimConfig",
+///     "object": {
+///       "registry": "248135293344.dkr.ecr.us-east-2.amazonaws.com",
+///       "repository": "ocf",
+///       "implementation": "ECR",
+///       "aws_secret_access_key": "<REDACTED>",
+///       ...
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/config")]
+async fn configuration() -> Response<AimConfig> {
+    env::config().clone().into()
+}
+
+/// Returns the disk usage of this AIM, in bytes. That is, the size of the whole repository (blobs
+/// shared between tags are counted once), the configured [registry quota](env::registry_quota)
+/// (if any), the size of every tag (largest first), the size of containerd's content store, and
+/// the size of the uploads staged within the temporary directory.
+///
+/// The size of every tag is found by inspecting its manifest, so this endpoint is about as
+/// expensive as an [inspect](inspect) of every installed tag.
+///
+/// ```text
+/// # BASH curl example
+/// curl http://aim.ocf-system/usage
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "Usage",
+///     "object": {
+///       "registry": 1073741824,
+///       "quota": 10737418240,
+///       "tags": [
+///         {
+///           "tag": "n6f7748462d94a093610de86808febbd",
+///           "digest": "sha256:cb1ff0854b8864a6a68ee0b5e509d4d94c50a41f96dc2749ea71dc124c89d11f",
+///           "size": 1073741824
+///         }
+///       ],
+///       "containerd": 0,
+///       "temp_dir": 0
+///     }
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/usage")]
+async fn disk_usage() -> Result<Response<Usage>> {
+    Ok(usage::usage().await?.into())
+}
+
+/// Returns (at most) the `limit` most recent entries of this AIM's [audit trail](auditor), oldest
+/// first. That is, every install and uninstall alongside who made it, when, and how it turned
+/// out. The limit defaults to [DEFAULT_AUDIT_LIMIT](auditor::DEFAULT_AUDIT_LIMIT), and only as many
+/// entries as are configured by [AUDIT_RETAINED](audit::AUDIT_RETAINED) are ever kept in memory.
+///
+/// Installs are identified by their `id` and uninstalls by their tag. Detached installs are
+/// recorded once they finish, rather than once they are accepted.
+///
+/// ```text
+/// curl -X GET "http://aim.ocf-system/audit?limit=10"
+/// ```
+///
+/// ```text
+/// // Example JSON return structure.
+/// {
+///   "payload": {
+///     "kind": "List[Entry]",
+///     "object": [
+///       {
+///         "at": 1632264721,
+///         "actor": "alation-instance-1",
+///         "action": "uninstall",
+///         "target": "n6f7748462d94a093610de86808febbd",
+///         "outcome": "succeeded"
+///       },
+///       ...
+///     ]
+///   },
+///   "error": null
+/// }
+/// ```
+#[get("/audit?<limit>")]
+async fn audit_trail(limit: Option<usize>) -> Response<Vec<Entry>> {
+    auditor::recent(limit.unwrap_or(auditor::DEFAULT_AUDIT_LIMIT)).into()
 }
 
 #[tokio::main]
 async fn main() {
-    std::env::set_var("RUST_LOG_STYLE", "always");
+    // Validates every setting up front. Doing so also exports those given within the
+    // configuration file into the environment, where our libraries (and the AWS SDK) read them.
+    env::configure();
+    // Colors both our own output and the logger's as configured (or not at all).
+    term_colors::set_color_mode(term_colors::ColorMode::configured());
+    term_colors::set_theme(term_colors::Theme::configured());
+    std::env::set_var(
+        "RUST_LOG_STYLE",
+        if term_colors::enabled() {
+            "always"
+        } else {
+            "never"
+        },
+    );
     env_logger::init();
+    // Having been asked only to check the environment, we check it rather than bootstrap it.
+    if diagnostics::requested() {
+        std::process::exit(diagnostics::run().await);
+    }
     registry::Implementation::configure();
+    admission::configure();
+    retry::configure(env::retry_budget_capacity(), env::retry_budget_refill());
+    os::process::default_timeout();
+    auditor::configure();
+    spool::cleanup().await;
     let config = rocket::Config {
         address: "0.0.0.0".parse().expect("it to parse"),
         limits: Limits::default().limit("file", MAX_UPLOAD_SIZE),
+        // Uploads are received straight into the spool, so that staging them is a mere rename.
+        temp_dir: env::spool_path(),
         ..Default::default()
     };
     rocket::custom(config)
-        .mount("/", routes![install, uninstall, list, get])
+        .mount(
+            "/",
+            routes![
+                install,
+                install_remote,
+                install_status,
+                install_progress,
+                cancel_install,
+                uninstall,
+                deprecate,
+                undeprecate,
+                promote,
+                aliases,
+                list,
+                get,
+                inspect,
+                lookup,
+                configuration,
+                disk_usage,
+                audit_trail
+            ],
+        )
+        .register(
+            "/",
+            catchers![
+                admission::too_many_requests,
+                admission::unprocessable,
+                spool::insufficient_storage
+            ],
+        )
+        .attach(response::Compression::default())
         .launch()
         .await
         .unwrap();
@@ -0,0 +1,122 @@
+//! The metadata of installed images, that is the [ImageConfig](ImageConfig) read out of each
+//! image's config blob. Reading it requires a round trip (or two) to the registry, so it is
+//! extracted once upon install and cached by digest within the [metadata file](env::metadata_path)
+//! thereafter. Images installed before the cache existed (or whose entry was lost alongside an
+//! unclaimed volume) are extracted upon their first [inspection](crate::registry::inspect) (or
+//! [listing](label)).
+//!
+//! Among the metadata are the image's [ConnectorLabels](registry::ConnectorLabels), which give
+//! the catalog a human-meaningful name for an image rather than its UUID tag.
+use crate::env;
+use crate::registry::{self, Image, ImageConfig};
+use error::*;
+use result::Result;
+use std::collections::BTreeMap;
+use tokio::sync::Mutex;
+
+lazy_static::lazy_static! {
+    /// Serializes every read-modify-write of the metadata file.
+    static ref LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Returns the metadata of the given image, extracting (and caching) it should it not already be
+/// cached. Should the tag have vanished from the registry in the meantime, then a
+/// [TagNotFound](registry::TagNotFound) is returned.
+pub async fn get(image: &Image) -> Result<ImageConfig> {
+    if let Some(config) = cached(&image.digest).await? {
+        return Ok(config);
+    }
+    extract(image).await
+}
+
+/// Extracts the metadata of the given (just installed) image out of the registry and caches it.
+pub async fn extract(image: &Image) -> Result<ImageConfig> {
+    let config = registry::config(&image.tag).await?;
+    let _guard = LOCK.lock().await;
+    let mut metadata = load().await?;
+    metadata.insert(image.digest.clone(), config.clone());
+    save(&metadata).await?;
+    Ok(config)
+}
+
+/// Attaches the [ConnectorLabels](registry::ConnectorLabels) of every one of the given images to
+/// it, extracting the metadata of any image that is not already cached. An image whose metadata
+/// cannot be extracted is left without labels rather than failing the lot.
+pub async fn label(mut images: Vec<Image>) -> Result<Vec<Image>> {
+    let cached = {
+        let _guard = LOCK.lock().await;
+        load().await?
+    };
+    for image in images.iter_mut() {
+        let config = match cached.get(&image.digest) {
+            Some(config) => config.clone(),
+            None => match extract(image).await {
+                Ok(config) => config,
+                Err(err) => {
+                    warn!("Failed to extract the metadata of {}, {}", image.tag, err);
+                    continue;
+                }
+            },
+        };
+        image.labels = config.connector_labels();
+    }
+    Ok(images)
+}
+
+/// Returns the cached metadata of the given digest, if any.
+pub async fn cached(digest: &str) -> Result<Option<ImageConfig>> {
+    let _guard = LOCK.lock().await;
+    Ok(load().await?.remove(digest))
+}
+
+async fn load() -> Result<BTreeMap<String, ImageConfig>> {
+    let path = env::metadata_path();
+    let contents = match tokio::fs::read(&path).await {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(err) => return Err(MetadataStoreError::new(&path, err).into()),
+    };
+    Ok(serde_json::from_slice(&contents).map_err(|err| MetadataStoreError::new(&path, err))?)
+}
+
+/// Writes the given metadata to a sibling file before renaming it over the original so that a
+/// crash mid-write never leaves a torn file behind.
+async fn save(metadata: &BTreeMap<String, ImageConfig>) -> Result<()> {
+    let path = env::metadata_path();
+    let contents =
+        serde_json::to_vec_pretty(metadata).map_err(|err| MetadataStoreError::new(&path, err))?;
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|err| MetadataStoreError::new(&path, err))?;
+    }
+    let staged = path.with_extension("json.tmp");
+    tokio::fs::write(&staged, contents)
+        .await
+        .map_err(|err| MetadataStoreError::new(&path, err))?;
+    tokio::fs::rename(&staged, &path)
+        .await
+        .map_err(|err| MetadataStoreError::new(&path, err))?;
+    Ok(())
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[error(
+    "Failed to access the image metadata cached at {path}, {cause}. Please check that the \
+volume mounted for the METADATA_PATH is writable and has space available."
+)]
+#[code(Status::InternalServerError)]
+#[error_code("AIM-1700")]
+pub struct MetadataStoreError {
+    path: String,
+    cause: String,
+}
+
+impl MetadataStoreError {
+    fn new<E: ToString>(path: &std::path::Path, err: E) -> MetadataStoreError {
+        MetadataStoreError {
+            path: path.display().to_string(),
+            cause: err.to_string(),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use error::*;
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// The first two bytes of every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The first four bytes of every zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Both POSIX (`ustar\0`) and GNU (`ustar `) tar headers carry this magic at [TAR_MAGIC_OFFSET](TAR_MAGIC_OFFSET).
+const TAR_MAGIC: [u8; 5] = *b"ustar";
+const TAR_MAGIC_OFFSET: u64 = 257;
+
+/// The Compression that an uploaded tarball was found to be wrapped in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+/// The Layout of the image within an uploaded tarball.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Layout {
+    /// An [OCI image layout](https://github.com/opencontainers/image-spec/blob/main/image-layout.md),
+    /// which has an `index.json` at its root.
+    Oci,
+    /// The output of `docker save`, which has a `manifest.json` at its root.
+    DockerArchive,
+}
+
+/// A Format is what an uploaded image was sniffed to be.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Format {
+    pub compression: Compression,
+    pub layout: Layout,
+}
+
+/// A Normalized is an uploaded image as an uncompressed tarball, which is what both
+/// `ctr images import` and the [gRPC import](super::grpc::Containerd::import) are handed.
+///
+/// Should the upload have been compressed, then it is decompressed into a sibling file which is
+/// removed upon drop. Otherwise, the upload itself is used as is (and left alone upon drop).
+#[derive(Debug)]
+pub struct Normalized {
+    pub path: PathBuf,
+    pub format: Format,
+    decompressed: bool,
+}
+
+impl Normalized {
+    /// Sniffs the format of the given upload, decompressing it should it be gzip or zstd
+    /// compressed. Anything other than a (possibly compressed) tarball of an OCI image layout or
+    /// of a `docker save` is an [UnsupportedImageFormat](UnsupportedImageFormat).
+    ///
+    /// As an upload is only [preflighted](crate::spool::preflight) by its compressed length, its
+    /// decompression is capped at the [headroom](crate::spool::headroom) of the spool.
+    ///
+    /// This procedure does blocking IO and SHOULD be ran via [spawn_blocking](tokio::task::spawn_blocking).
+    pub fn new(upload: &Path) -> Result<Normalized, UnsupportedImageFormat> {
+        let dir = upload.parent().unwrap_or_else(|| Path::new("."));
+        let limit = crate::spool::headroom(dir).unwrap_or(u64::MAX);
+        Normalized::within(upload, limit)
+    }
+
+    /// Normalizes the given upload just as [new](Normalized::new) does, refusing one that
+    /// decompresses to more than the given number of bytes.
+    fn within(upload: &Path, limit: u64) -> Result<Normalized, UnsupportedImageFormat> {
+        let compression = compression(upload)?;
+        let (path, decompressed) = match compression {
+            Compression::None => (upload.to_path_buf(), false),
+            _ => {
+                let path = upload.with_extension("decompressed.tar");
+                decompress(upload, &path, compression, limit).inspect_err(|_err| {
+                    let _ = std::fs::remove_file(&path);
+                })?;
+                (path, true)
+            }
+        };
+        // Constructed before the layout is known so that a decompressed tarball of an unsupported
+        // layout is still removed.
+        let mut normalized = Normalized {
+            path,
+            format: Format {
+                compression,
+                layout: Layout::Oci,
+            },
+            decompressed,
+        };
+        normalized.format.layout = layout(&normalized.path, compression)?;
+        Ok(normalized)
+    }
+}
+
+impl Drop for Normalized {
+    fn drop(&mut self) {
+        if self.decompressed {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Sniffs the compression of the given file by its magic bytes. An uncompressed file must be a
+/// tarball.
+pub fn compression(path: &Path) -> Result<Compression, UnsupportedImageFormat> {
+    let mut file = File::open(path).map_err(UnsupportedImageFormat::io)?;
+    let mut magic = [0u8; 4];
+    let read = file.read(&mut magic).map_err(UnsupportedImageFormat::io)?;
+    if read >= GZIP_MAGIC.len() && magic[..GZIP_MAGIC.len()] == GZIP_MAGIC {
+        return Ok(Compression::Gzip);
+    }
+    if read == ZSTD_MAGIC.len() && magic == ZSTD_MAGIC {
+        return Ok(Compression::Zstd);
+    }
+    let mut magic = [0u8; 5];
+    file.seek(SeekFrom::Start(TAR_MAGIC_OFFSET))
+        .map_err(UnsupportedImageFormat::io)?;
+    if file.read_exact(&mut magic).is_ok() && magic == TAR_MAGIC {
+        Ok(Compression::None)
+    } else {
+        Err(UnsupportedImageFormat::from(
+            "it is neither a tarball nor a gzip or zstd compressed tarball",
+        ))
+    }
+}
+
+fn decompress(
+    from: &Path,
+    to: &Path,
+    compression: Compression,
+    limit: u64,
+) -> Result<(), UnsupportedImageFormat> {
+    let file = File::open(from).map_err(UnsupportedImageFormat::io)?;
+    let mut out = File::create(to).map_err(UnsupportedImageFormat::io)?;
+    // One byte beyond the limit is read so that an upload of exactly the limit is not refused.
+    let cap = limit.saturating_add(1);
+    let copied = match compression {
+        Compression::Gzip => std::io::copy(&mut GzDecoder::new(file).take(cap), &mut out),
+        Compression::Zstd => zstd::stream::read::Decoder::new(file)
+            .and_then(|decoder| std::io::copy(&mut decoder.take(cap), &mut out)),
+        Compression::None => return Ok(()),
+    };
+    let copied = copied.map_err(|err| {
+        UnsupportedImageFormat::from(format!(
+            "it could not be decompressed as {:?}, {}",
+            compression, err
+        ))
+    })?;
+    if copied > limit {
+        return Err(UnsupportedImageFormat::from(format!(
+            "it decompresses to more than the {} bytes that the spool has room for",
+            limit
+        )));
+    }
+    Ok(())
+}
+
+/// Sniffs the layout of the given (uncompressed) tarball by the files found at its root.
+fn layout(path: &Path, compression: Compression) -> Result<Layout, UnsupportedImageFormat> {
+    let file = File::open(path).map_err(UnsupportedImageFormat::io)?;
+    let mut archive = tar::Archive::new(file);
+    let (mut oci, mut docker) = (false, false);
+    for entry in archive.entries().map_err(UnsupportedImageFormat::io)? {
+        let entry = entry.map_err(UnsupportedImageFormat::io)?;
+        let entry = entry.path().map_err(UnsupportedImageFormat::io)?;
+        let name = entry.strip_prefix(".").unwrap_or(&entry);
+        oci |= name == Path::new("index.json");
+        docker |= name == Path::new("manifest.json");
+    }
+    match (oci, docker) {
+        // `docker save` (as of Docker 25) writes both, in which case either may be imported.
+        (true, _) => Ok(Layout::Oci),
+        (false, true) => Ok(Layout::DockerArchive),
+        (false, false) => Err(UnsupportedImageFormat::from(format!(
+            "{} has neither an index.json (OCI image layout) nor a manifest.json (docker save) at its root",
+            match compression {
+                Compression::None => "the tarball".to_string(),
+                compression => format!("the {:?} compressed tarball", compression),
+            }
+        ))),
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::UnsupportedMediaType)]
+#[error(
+    "The uploaded image is of an unsupported format, {reason}. We accept a tarball (optionally gzip \
+or zstd compressed) of either an OCI image layout or the output of `docker save`."
+)]
+#[error_code("AIM-1411")]
+pub struct UnsupportedImageFormat {
+    reason: String,
+}
+
+impl UnsupportedImageFormat {
+    fn io(err: std::io::Error) -> UnsupportedImageFormat {
+        UnsupportedImageFormat::from(err.to_string())
+    }
+}
+
+impl<T: Into<String>> From<T> for UnsupportedImageFormat {
+    fn from(reason: T) -> Self {
+        UnsupportedImageFormat {
+            reason: reason.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    struct TestFile(PathBuf);
+
+    impl TestFile {
+        fn new(contents: &[u8]) -> TestFile {
+            let path = std::env::temp_dir().join(format!("{}.tar", names::uuid()));
+            std::fs::write(&path, contents).unwrap();
+            TestFile(path)
+        }
+    }
+
+    impl Drop for TestFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn tarball(name: &str) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        let contents = b"{}";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, name, &contents[..])
+            .unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn plain() {
+        let oci = TestFile::new(&tarball("index.json"));
+        let normalized = Normalized::new(&oci.0).unwrap();
+        assert_eq!(normalized.path, oci.0);
+        assert_eq!(
+            normalized.format,
+            Format {
+                compression: Compression::None,
+                layout: Layout::Oci
+            }
+        );
+        let docker = TestFile::new(&tarball("./manifest.json"));
+        let normalized = Normalized::new(&docker.0).unwrap();
+        assert_eq!(normalized.format.layout, Layout::DockerArchive);
+    }
+
+    #[test]
+    fn gzip() {
+        let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(&tarball("manifest.json")).unwrap();
+        let upload = TestFile::new(&encoder.finish().unwrap());
+        let normalized = Normalized::new(&upload.0).unwrap();
+        assert_eq!(normalized.format.compression, Compression::Gzip);
+        assert_eq!(normalized.format.layout, Layout::DockerArchive);
+        assert_ne!(normalized.path, upload.0);
+        assert_eq!(compression(&normalized.path).unwrap(), Compression::None);
+        let decompressed = normalized.path.clone();
+        drop(normalized);
+        assert!(!decompressed.exists());
+    }
+
+    #[test]
+    fn zstd() {
+        let compressed = zstd::stream::encode_all(&tarball("index.json")[..], 0).unwrap();
+        let upload = TestFile::new(&compressed);
+        let normalized = Normalized::new(&upload.0).unwrap();
+        assert_eq!(normalized.format.compression, Compression::Zstd);
+        assert_eq!(normalized.format.layout, Layout::Oci);
+    }
+
+    #[test]
+    fn capped() {
+        let tarball = tarball("index.json");
+        let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(&tarball).unwrap();
+        let upload = TestFile::new(&encoder.finish().unwrap());
+        let limit = tarball.len() as u64;
+        assert!(Normalized::within(&upload.0, limit).is_ok());
+        let err = Normalized::within(&upload.0, limit - 1).unwrap_err();
+        assert_eq!(err.error_code(), Some("AIM-1411"));
+        assert!(!upload.0.with_extension("decompressed.tar").exists());
+        let compressed = zstd::stream::encode_all(&tarball[..], 0).unwrap();
+        let upload = TestFile::new(&compressed);
+        assert!(Normalized::within(&upload.0, limit - 1).is_err());
+        assert!(!upload.0.with_extension("decompressed.tar").exists());
+    }
+
+    #[test]
+    fn unsupported() {
+        let zip = TestFile::new(b"PK\x03\x04 not an image");
+        let err = Normalized::new(&zip.0).unwrap_err();
+        assert_eq!(err.error_code(), Some("AIM-1411"));
+        let neither = TestFile::new(&tarball("etc/passwd"));
+        assert!(Normalized::new(&neither.0).is_err());
+        let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(b"not a tarball").unwrap();
+        let upload = TestFile::new(&encoder.finish().unwrap());
+        assert!(Normalized::new(&upload.0).is_err());
+        assert!(!upload.0.with_extension("decompressed.tar").exists());
+    }
+}
@@ -0,0 +1,388 @@
+use error::*;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+
+pub const DOCKER_MANIFEST: &str = "application/vnd.docker.distribution.manifest.v2+json";
+pub const DOCKER_CONFIG: &str = "application/vnd.docker.container.image.v1+json";
+pub const DOCKER_LAYER: &str = "application/vnd.docker.image.rootfs.diff.tar";
+pub const DOCKER_LAYER_GZIP: &str = "application/vnd.docker.image.rootfs.diff.tar.gzip";
+
+/// The annotations (in order of preference) that may name an image within an OCI image layout.
+const NAME_ANNOTATIONS: [&str; 2] = [
+    "io.containerd.image.name",
+    "org.opencontainers.image.ref.name",
+];
+
+/// The name given to an image whose archive does not name it. The name is only ever used
+/// within the install's own (unique) namespace and is replaced upon [retagging](super::super::retag::Retag).
+pub const UNNAMED: &str = "ocf/import:latest";
+
+/// The name of the Docker v2 manifest that is synthesized for a `docker save` archive.
+const SYNTHESIZED_MANIFEST: &str = "ocf-manifest.v2.json";
+
+/// The first two bytes of every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A Descriptor is the OCI [content descriptor](https://github.com/opencontainers/image-spec/blob/main/descriptor.md),
+/// which is also the shape in which every manifest refers to its children.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Descriptor {
+    pub media_type: String,
+    pub digest: String,
+    pub size: i64,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub annotations: HashMap<String, String>,
+}
+
+/// A Blob is a single file within an unpacked archive that is destined for the content store.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Blob {
+    pub path: PathBuf,
+    pub digest: String,
+    pub size: i64,
+}
+
+/// An Archive is an unpacked image archive, ready to be written into containerd.
+#[derive(Debug)]
+pub struct Archive {
+    /// The name of the image, as given by the archive itself.
+    pub name: String,
+    /// The descriptor of the image's root manifest (or index).
+    pub target: Descriptor,
+    /// Every blob that must be written to the content store before the image may be created.
+    pub blobs: Vec<Blob>,
+}
+
+/// Unpacks the given (possibly gzipped) tarball into the given directory and then [reads](read)
+/// the image found within.
+///
+/// This procedure does blocking IO and SHOULD be ran via [spawn_blocking](tokio::task::spawn_blocking).
+pub fn unpack(archive: &Path, dir: &Path) -> Result<Archive, MalformedArchive> {
+    let mut file = File::open(archive).map_err(MalformedArchive::io)?;
+    let mut magic = [0u8; 2];
+    let gzipped = file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC;
+    file.seek(SeekFrom::Start(0))
+        .map_err(MalformedArchive::io)?;
+    if gzipped {
+        tar::Archive::new(GzDecoder::new(file)).unpack(dir)
+    } else {
+        tar::Archive::new(file).unpack(dir)
+    }
+    .map_err(MalformedArchive::io)?;
+    read(dir)
+}
+
+/// Reads the image found within the given unpacked archive. Both the
+/// [OCI image layout](https://github.com/opencontainers/image-spec/blob/main/image-layout.md)
+/// and the format produced by `docker save` are supported, as they are by `ctr images import`.
+///
+/// Exactly one image is expected to be within the archive.
+pub fn read(dir: &Path) -> Result<Archive, MalformedArchive> {
+    if dir.join("index.json").is_file() {
+        oci(dir)
+    } else if dir.join("manifest.json").is_file() {
+        docker(dir)
+    } else {
+        Err(MalformedArchive::from(
+            "the archive has neither an index.json (OCI) nor a manifest.json (docker save)",
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+struct Index {
+    manifests: Vec<Descriptor>,
+}
+
+fn oci(dir: &Path) -> Result<Archive, MalformedArchive> {
+    let index: Index = json(&dir.join("index.json"))?;
+    let target = match index.manifests.as_slice() {
+        [target] => target.clone(),
+        [] => return Err("the archive's index.json does not list any images".into()),
+        _ => return Err("the archive's index.json lists more than one image".into()),
+    };
+    let name = NAME_ANNOTATIONS
+        .iter()
+        .find_map(|annotation| target.annotations.get(*annotation))
+        .cloned()
+        .unwrap_or_else(|| UNNAMED.to_string());
+    let mut blobs = vec![];
+    for algorithm in std::fs::read_dir(dir.join("blobs")).map_err(MalformedArchive::io)? {
+        let algorithm = algorithm.map_err(MalformedArchive::io)?;
+        for blob in std::fs::read_dir(algorithm.path()).map_err(MalformedArchive::io)? {
+            let blob = blob.map_err(MalformedArchive::io)?;
+            blobs.push(Blob {
+                digest: format!(
+                    "{}:{}",
+                    algorithm.file_name().to_string_lossy(),
+                    blob.file_name().to_string_lossy()
+                ),
+                size: blob.metadata().map_err(MalformedArchive::io)?.len() as i64,
+                path: blob.path(),
+            });
+        }
+    }
+    Ok(Archive {
+        name,
+        target,
+        blobs,
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DockerEntry {
+    config: String,
+    #[serde(default)]
+    repo_tags: Option<Vec<String>>,
+    layers: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DockerManifest {
+    schema_version: u8,
+    media_type: &'static str,
+    config: Descriptor,
+    layers: Vec<Descriptor>,
+}
+
+/// A `docker save` archive has no manifest of its own, so a Docker v2 manifest is synthesized
+/// from its manifest.json (as `ctr images import` does).
+fn docker(dir: &Path) -> Result<Archive, MalformedArchive> {
+    let entries: Vec<DockerEntry> = json(&dir.join("manifest.json"))?;
+    let entry = match entries.as_slice() {
+        [entry] => entry,
+        [] => return Err("the archive's manifest.json does not list any images".into()),
+        _ => return Err("the archive's manifest.json lists more than one image".into()),
+    };
+    let mut blobs = vec![blob(&within(dir, &entry.config)?)?];
+    let config = descriptor(DOCKER_CONFIG, &blobs[0]);
+    let mut layers = vec![];
+    for layer in entry.layers.iter() {
+        let path = within(dir, layer)?;
+        let blob = blob(&path)?;
+        let media_type = if gzipped(&path)? {
+            DOCKER_LAYER_GZIP
+        } else {
+            DOCKER_LAYER
+        };
+        layers.push(descriptor(media_type, &blob));
+        blobs.push(blob);
+    }
+    let manifest = serde_json::to_vec(&DockerManifest {
+        schema_version: 2,
+        media_type: DOCKER_MANIFEST,
+        config,
+        layers,
+    })
+    .map_err(|err| MalformedArchive::from(err.to_string()))?;
+    let path = dir.join(SYNTHESIZED_MANIFEST);
+    std::fs::write(&path, &manifest).map_err(MalformedArchive::io)?;
+    let manifest = blob(&path)?;
+    let target = descriptor(DOCKER_MANIFEST, &manifest);
+    blobs.push(manifest);
+    Ok(Archive {
+        name: entry
+            .repo_tags
+            .as_ref()
+            .and_then(|tags| tags.first())
+            .cloned()
+            .unwrap_or_else(|| UNNAMED.to_string()),
+        target,
+        blobs,
+    })
+}
+
+fn json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, MalformedArchive> {
+    let raw = std::fs::read(path).map_err(MalformedArchive::io)?;
+    serde_json::from_slice(&raw).map_err(|err| {
+        MalformedArchive::from(format!(
+            "{} could not be parsed, {}",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            err
+        ))
+    })
+}
+
+/// Joins the given path (as referred to from within the archive) onto the archive's directory,
+/// refusing any path that would escape it.
+fn within(dir: &Path, relative: &str) -> Result<PathBuf, MalformedArchive> {
+    let relative = Path::new(relative);
+    if relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        Ok(dir.join(relative))
+    } else {
+        Err(format!(
+            "the archive refers to a path outside of itself ({:?})",
+            relative
+        )
+        .into())
+    }
+}
+
+/// Hashes the file at the given path.
+fn blob(path: &Path) -> Result<Blob, MalformedArchive> {
+    let mut file = File::open(path).map_err(MalformedArchive::io)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 20];
+    let mut size = 0;
+    loop {
+        match file.read(&mut buffer).map_err(MalformedArchive::io)? {
+            0 => break,
+            read => {
+                hasher.update(&buffer[..read]);
+                size += read as i64;
+            }
+        }
+    }
+    Ok(Blob {
+        path: path.to_path_buf(),
+        digest: format!("sha256:{:x}", hasher.finalize()),
+        size,
+    })
+}
+
+fn gzipped(path: &Path) -> Result<bool, MalformedArchive> {
+    let mut magic = [0u8; 2];
+    let mut file = File::open(path).map_err(MalformedArchive::io)?;
+    Ok(file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC)
+}
+
+fn descriptor(media_type: &str, blob: &Blob) -> Descriptor {
+    Descriptor {
+        media_type: media_type.to_string(),
+        digest: blob.digest.clone(),
+        size: blob.size,
+        annotations: HashMap::new(),
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The uploaded image could not be imported as {reason}. We expected a (possibly compressed) \
+tarball of either an OCI image layout or the output of `docker save` containing exactly one image."
+)]
+#[error_code("AIM-1402")]
+pub struct MalformedArchive {
+    reason: String,
+}
+
+impl MalformedArchive {
+    fn io(err: std::io::Error) -> MalformedArchive {
+        MalformedArchive::from(err.to_string())
+    }
+}
+
+impl<T: Into<String>> From<T> for MalformedArchive {
+    fn from(reason: T) -> Self {
+        MalformedArchive {
+            reason: reason.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OCI_INDEX: &str = "application/vnd.oci.image.index.v1+json";
+
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new() -> TestDir {
+            let dir = std::env::temp_dir().join(names::uuid());
+            std::fs::create_dir_all(&dir).unwrap();
+            TestDir(dir)
+        }
+
+        fn write<C: AsRef<[u8]>>(&self, path: &str, contents: C) {
+            let path = self.0.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn docker_save() {
+        let dir = TestDir::new();
+        dir.write(
+            "manifest.json",
+            r#"[{"Config": "abcd.json", "RepoTags": ["test/tennis:latest"], "Layers": ["1234/layer.tar"]}]"#,
+        );
+        dir.write("abcd.json", r#"{"os": "linux", "architecture": "amd64"}"#);
+        dir.write("1234/layer.tar", "not really a tarball");
+        let archive = read(&dir.0).unwrap();
+        assert_eq!(archive.name, "test/tennis:latest");
+        assert_eq!(archive.target.media_type, DOCKER_MANIFEST);
+        assert_eq!(archive.blobs.len(), 3);
+        assert_eq!(archive.blobs[2].digest, archive.target.digest);
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&archive.blobs[2].path).unwrap()).unwrap();
+        assert_eq!(
+            manifest["config"]["digest"],
+            archive.blobs[0].digest.as_str()
+        );
+        assert_eq!(manifest["layers"][0]["mediaType"], DOCKER_LAYER);
+        assert_eq!(manifest["layers"][0]["size"], 20);
+    }
+
+    #[test]
+    fn oci_layout() {
+        let dir = TestDir::new();
+        dir.write(
+            "index.json",
+            r#"{"schemaVersion": 2, "manifests": [{
+                "mediaType": "application/vnd.oci.image.index.v1+json",
+                "digest": "sha256:aaaa",
+                "size": 4,
+                "annotations": {"org.opencontainers.image.ref.name": "test/tennis:1.0"}
+            }]}"#,
+        );
+        dir.write("blobs/sha256/aaaa", "{}{}");
+        dir.write("blobs/sha256/bbbb", "{}");
+        let archive = read(&dir.0).unwrap();
+        assert_eq!(archive.name, "test/tennis:1.0");
+        assert_eq!(archive.target.media_type, OCI_INDEX);
+        let mut digests: Vec<&str> = archive.blobs.iter().map(|b| b.digest.as_str()).collect();
+        digests.sort_unstable();
+        assert_eq!(digests, vec!["sha256:aaaa", "sha256:bbbb"]);
+    }
+
+    #[test]
+    fn exactly_one_image() {
+        let dir = TestDir::new();
+        dir.write(
+            "manifest.json",
+            r#"[{"Config": "a.json", "Layers": []}, {"Config": "b.json", "Layers": []}]"#,
+        );
+        assert!(read(&dir.0).is_err());
+    }
+
+    #[test]
+    fn refuses_to_escape() {
+        let dir = TestDir::new();
+        dir.write(
+            "manifest.json",
+            r#"[{"Config": "../../etc/passwd", "Layers": []}]"#,
+        );
+        assert!(read(&dir.0).is_err());
+        assert!(read(&dir.0.join("missing")).is_err());
+    }
+}
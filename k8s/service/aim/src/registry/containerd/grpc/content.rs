@@ -0,0 +1,127 @@
+use super::archive::Blob;
+use super::{rpc, Containerd};
+use containerd_client::services::v1::content_client::ContentClient;
+use containerd_client::services::v1::{ReadContentRequest, WriteAction, WriteContentRequest};
+use error::*;
+use result::Result;
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+
+/// The size of each chunk that is streamed into the content store.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Writes the given blob into the content store, under the install's lease. Blobs that are
+/// already present in the content store are skipped.
+pub async fn write(containerd: &Containerd, blob: &Blob) -> Result<()> {
+    let file = File::open(&blob.path).await.map_err(|err| BlobUnreadable {
+        digest: blob.digest.clone(),
+        cause: err.to_string(),
+    })?;
+    let (reference, total, expected) = (
+        format!("ocf-{}", blob.digest),
+        blob.size,
+        blob.digest.clone(),
+    );
+    let requests = futures::stream::unfold(Some((file, 0)), move |state| {
+        let (reference, expected) = (reference.clone(), expected.clone());
+        async move {
+            let (mut file, offset) = state?;
+            let mut data = vec![0u8; CHUNK_SIZE];
+            // A read failure ends the stream without a commit, which is reported below.
+            let read = read_full(&mut file, &mut data).await.ok()?;
+            let action = match read {
+                0 => WriteAction::Commit,
+                _ => WriteAction::Write,
+            };
+            let request = WriteContentRequest {
+                action: action as i32,
+                r#ref: reference,
+                total,
+                expected,
+                offset,
+                data: data[..read].to_vec(),
+                ..Default::default()
+            };
+            match read {
+                0 => Some((request, None)),
+                _ => Some((request, Some((file, offset + read as i64)))),
+            }
+        }
+    });
+    let mut client = ContentClient::new(containerd.channel());
+    let mut responses = match client.write(containerd.leased(requests)).await {
+        Ok(responses) => responses.into_inner(),
+        Err(status) if status.code() == tonic::Code::AlreadyExists => return Ok(()),
+        Err(status) => return Err(rpc("content.Write", status).into()),
+    };
+    let mut committed = None;
+    loop {
+        match responses.message().await {
+            Ok(Some(response)) => committed = Some(response.digest),
+            Ok(None) => break,
+            Err(status) if status.code() == tonic::Code::AlreadyExists => return Ok(()),
+            Err(status) => return Err(rpc("content.Write", status).into()),
+        }
+    }
+    match committed {
+        Some(digest) if digest == blob.digest => Ok(()),
+        _ => Err(BlobUnreadable {
+            digest: blob.digest.clone(),
+            cause: "the upload ended before it could be committed".to_string(),
+        }
+        .into()),
+    }
+}
+
+/// Reads (at most) `size` bytes of the given blob from the content store, starting at `offset`.
+pub async fn read(
+    containerd: &Containerd,
+    digest: &str,
+    offset: i64,
+    size: i64,
+) -> Result<Vec<u8>> {
+    let mut client = ContentClient::new(containerd.channel());
+    let mut responses = client
+        .read(containerd.request(ReadContentRequest {
+            digest: digest.to_string(),
+            offset,
+            size,
+        }))
+        .await
+        .map_err(|status| rpc("content.Read", status))?
+        .into_inner();
+    let mut data = Vec::with_capacity(size as usize);
+    while let Some(response) = responses
+        .message()
+        .await
+        .map_err(|status| rpc("content.Read", status))?
+    {
+        data.extend(response.data);
+    }
+    Ok(data)
+}
+
+/// Fills the given buffer from the given file, returning fewer bytes than the buffer's length
+/// only upon reaching the end of the file.
+async fn read_full(file: &mut File, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..]).await? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error(
+    "Failed to write the blob {digest} into containerd's content store, {cause}. The uploaded \
+image may have been modified on disk while it was being imported. Perhaps try installing it again?"
+)]
+#[error_code("AIM-1403")]
+struct BlobUnreadable {
+    digest: String,
+    cause: String,
+}
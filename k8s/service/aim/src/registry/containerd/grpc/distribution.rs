@@ -0,0 +1,488 @@
+use super::archive::{Blob, Descriptor, MalformedArchive};
+use super::{content, Containerd};
+use crate::registry::manifest::ManifestSerdeError;
+use error::*;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use reqwest::{Method, RequestBuilder, StatusCode};
+use result::Result;
+use secret::Secret;
+use serde::Deserialize;
+use sha2::Digest;
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+
+/// The size of each chunk that is uploaded to the registry. ECR requires that every chunk
+/// (other than the last) be at least 5 megabytes.
+const UPLOAD_CHUNK_SIZE: i64 = 8 << 20;
+
+/// The media types of every manifest (and index) that we are prepared to pull.
+const MANIFEST_MEDIA_TYPES: &str = "application/vnd.docker.distribution.manifest.list.v2+json, \
+application/vnd.oci.image.index.v1+json, \
+application/vnd.docker.distribution.manifest.v2+json, \
+application/vnd.oci.image.manifest.v1+json";
+
+/// containerd neither pushes nor pulls images itself (`ctr images push` and `ctr images pull` are
+/// entirely client side), so a Registry is a minimal client of the
+/// [OCI distribution API](https://github.com/opencontainers/distribution-spec/blob/main/spec.md#push)
+/// that pushes images straight out of the content store, and pulls them straight into it.
+pub struct Registry {
+    http: reqwest::Client,
+    base: String,
+    repository: String,
+    authorization: Option<Secret>,
+}
+
+/// The token handed out by a registry's token server. Registries differ as to which field they
+/// fill in, so both are accepted.
+#[derive(Deserialize)]
+struct Token {
+    #[serde(default)]
+    token: Option<String>,
+    #[serde(default)]
+    access_token: Option<String>,
+}
+
+/// The children of either a manifest or an index.
+#[derive(Deserialize)]
+struct Children {
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+    #[serde(default)]
+    config: Option<Descriptor>,
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+}
+
+impl Registry {
+    /// Constructs a client for the given `registry` (a bare host, such as `registry.kube-system`)
+    /// and repository. Credentials, if any, are in the form of `<username>:<password>`.
+    pub fn new(
+        registry: &str,
+        repository: &str,
+        credentials: Option<Secret>,
+        plain_http: bool,
+    ) -> Registry {
+        Registry {
+            http: reqwest::Client::new(),
+            base: format!(
+                "{}://{}",
+                if plain_http { "http" } else { "https" },
+                registry
+            ),
+            repository: repository.to_string(),
+            authorization: credentials.map(|credentials| {
+                Secret::from(format!(
+                    "Basic {}",
+                    base64::encode(credentials.raw_secret())
+                ))
+            }),
+        }
+    }
+
+    /// Pushes the given manifest (or index) along with all of its children, depth first, naming
+    /// it with the given reference (either a tag or, for the children of an index, a digest).
+    pub fn push<'a>(
+        &'a self,
+        containerd: &'a Containerd,
+        manifest: Descriptor,
+        reference: String,
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            let raw = content::read(containerd, &manifest.digest, 0, 0).await?;
+            let children: Children = serde_json::from_slice(&raw).map_err(|err| {
+                MalformedArchive::from(format!(
+                    "the manifest {} could not be parsed, {}",
+                    manifest.digest, err
+                ))
+            })?;
+            for child in children.manifests {
+                let digest = child.digest.clone();
+                self.push(containerd, child, digest).await?;
+            }
+            for blob in children.config.into_iter().chain(children.layers) {
+                self.upload(containerd, &blob).await?;
+            }
+            self.put_manifest(&reference, &manifest.media_type, raw)
+                .await
+        }
+        .boxed()
+    }
+
+    /// Exchanges this Registry's credentials (if any) for a bearer token that permits pulling
+    /// from its repository, should the registry ask for one. Registries such as Docker Hub and
+    /// GHCR refuse even anonymous pulls without such a token, whereas others accept basic
+    /// authentication as is.
+    pub async fn authenticate(mut self) -> Result<Registry> {
+        let url = format!("{}/v2/", self.base);
+        let response = self
+            .send("GET /v2/", self.request(Method::GET, &url))
+            .await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(self);
+        }
+        let challenge = response
+            .headers()
+            .get("WWW-Authenticate")
+            .and_then(|challenge| challenge.to_str().ok())
+            .and_then(bearer_challenge);
+        let (realm, service) = match challenge {
+            Some(challenge) => challenge,
+            None => return Ok(self),
+        };
+        let mut query = vec![("scope", format!("repository:{}:pull", self.repository))];
+        if let Some(service) = service {
+            query.push(("service", service));
+        }
+        let request = self.request(Method::GET, &realm).query(&query);
+        let response = self.send("GET token", request).await?;
+        if !response.status().is_success() {
+            return Err(rejected("GET token", response).await);
+        }
+        let status = response.status().as_u16();
+        let token: Token = response.json().await.map_err(|err| RegistryRequestFailed {
+            operation: "GET token",
+            status,
+            body: err.to_string(),
+        })?;
+        let token = token
+            .token
+            .or(token.access_token)
+            .ok_or(RegistryRequestFailed {
+                operation: "GET token",
+                status,
+                body: "the response did not include a token".to_string(),
+            })?;
+        self.authorization = Some(Secret::from(format!("Bearer {}", token)));
+        Ok(self)
+    }
+
+    /// Pulls the manifest (or index) of the given reference (either a tag or a digest) along with
+    /// all of its children, depth first, into the content store, returning its descriptor. Every
+    /// platform of an index is pulled so that the image may be pushed onward as a whole.
+    ///
+    /// Blobs are downloaded into the given directory before being written into the content store,
+    /// which verifies that each matches its digest.
+    pub fn pull<'a>(
+        &'a self,
+        containerd: &'a Containerd,
+        reference: String,
+        dir: &'a Path,
+    ) -> BoxFuture<'a, Result<Descriptor>> {
+        async move {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(|err| DownloadFailed {
+                    digest: reference.clone(),
+                    cause: err.to_string(),
+                })?;
+            let url = format!(
+                "{}/v2/{}/manifests/{}",
+                self.base, self.repository, reference
+            );
+            let request = self
+                .request(Method::GET, &url)
+                .header("Accept", MANIFEST_MEDIA_TYPES);
+            let response = self.send("GET manifest", request).await?;
+            if response.status() != StatusCode::OK {
+                return Err(rejected("GET manifest", response).await);
+            }
+            let media_type = response
+                .headers()
+                .get("Content-Type")
+                .and_then(|media_type| media_type.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            let raw = response.bytes().await.map_err(|err| RegistryUnreachable {
+                operation: "GET manifest",
+                cause: err.to_string(),
+            })?;
+            let digest = format!("sha256:{:x}", sha2::Sha256::digest(&raw));
+            if reference.starts_with("sha256:") && reference != digest {
+                return Err(DownloadFailed {
+                    digest: reference,
+                    cause: format!("the registry served a manifest of {} instead", digest),
+                }
+                .into());
+            }
+            let children: Children =
+                serde_json::from_slice(&raw).map_err(ManifestSerdeError::from)?;
+            for child in children.manifests {
+                self.pull(containerd, child.digest, dir).await?;
+            }
+            for blob in children.config.into_iter().chain(children.layers) {
+                self.download(containerd, &blob, dir).await?;
+            }
+            let manifest = Blob {
+                path: dir.join(digest.trim_start_matches("sha256:")),
+                digest: digest.clone(),
+                size: raw.len() as i64,
+            };
+            tokio::fs::write(&manifest.path, &raw)
+                .await
+                .map_err(|err| DownloadFailed {
+                    digest: digest.clone(),
+                    cause: err.to_string(),
+                })?;
+            content::write(containerd, &manifest).await?;
+            Ok(Descriptor {
+                media_type,
+                digest,
+                size: manifest.size,
+                annotations: Default::default(),
+            })
+        }
+        .boxed()
+    }
+
+    /// Downloads the given blob into the given directory and then writes it into the content store.
+    async fn download(&self, containerd: &Containerd, blob: &Descriptor, dir: &Path) -> Result<()> {
+        let url = format!("{}/v2/{}/blobs/{}", self.base, self.repository, blob.digest);
+        let mut response = self
+            .send("GET blob", self.request(Method::GET, &url))
+            .await?;
+        if response.status() != StatusCode::OK {
+            return Err(rejected("GET blob", response).await);
+        }
+        let failed = |cause: String| DownloadFailed {
+            digest: blob.digest.clone(),
+            cause,
+        };
+        let path = dir.join(blob.digest.trim_start_matches("sha256:"));
+        let mut file = tokio::fs::File::create(&path)
+            .await
+            .map_err(|err| failed(err.to_string()))?;
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|err| failed(err.to_string()))?
+        {
+            file.write_all(&chunk)
+                .await
+                .map_err(|err| failed(err.to_string()))?;
+        }
+        file.flush().await.map_err(|err| failed(err.to_string()))?;
+        let downloaded = Blob {
+            path,
+            digest: blob.digest.clone(),
+            size: blob.size,
+        };
+        let written = content::write(containerd, &downloaded).await;
+        // The content store has its own copy now (or never will), so ours is of no further use.
+        let _ = tokio::fs::remove_file(&downloaded.path).await;
+        written
+    }
+
+    /// Uploads the given blob from the content store, in chunks. Blobs that the registry already
+    /// has are skipped.
+    async fn upload(&self, containerd: &Containerd, blob: &Descriptor) -> Result<()> {
+        let url = format!("{}/v2/{}/blobs/{}", self.base, self.repository, blob.digest);
+        let response = self
+            .send("HEAD blob", self.request(Method::HEAD, &url))
+            .await?;
+        match response.status() {
+            StatusCode::OK => return Ok(()),
+            StatusCode::NOT_FOUND => (),
+            _ => return Err(rejected("HEAD blob", response).await),
+        }
+        let url = format!("{}/v2/{}/blobs/uploads/", self.base, self.repository);
+        let mut location = self
+            .location("POST upload", self.request(Method::POST, &url))
+            .await?;
+        let mut offset = 0;
+        while offset < blob.size {
+            let chunk = content::read(containerd, &blob.digest, offset, UPLOAD_CHUNK_SIZE).await?;
+            let end = offset + chunk.len() as i64 - 1;
+            let request = self
+                .request(Method::PATCH, &location)
+                .header("Content-Type", "application/octet-stream")
+                .header("Content-Range", format!("{}-{}", offset, end))
+                .body(chunk);
+            location = self.location("PATCH upload", request).await?;
+            offset = end + 1;
+        }
+        let separator = if location.contains('?') { '&' } else { '?' };
+        let url = format!("{}{}digest={}", location, separator, blob.digest);
+        let response = self
+            .send("PUT upload", self.request(Method::PUT, &url))
+            .await?;
+        match response.status() {
+            StatusCode::CREATED => Ok(()),
+            _ => Err(rejected("PUT upload", response).await),
+        }
+    }
+
+    async fn put_manifest(&self, reference: &str, media_type: &str, raw: Vec<u8>) -> Result<()> {
+        let url = format!(
+            "{}/v2/{}/manifests/{}",
+            self.base, self.repository, reference
+        );
+        let request = self
+            .request(Method::PUT, &url)
+            .header("Content-Type", media_type)
+            .body(raw);
+        let response = self.send("PUT manifest", request).await?;
+        match response.status() {
+            StatusCode::CREATED => Ok(()),
+            _ => Err(rejected("PUT manifest", response).await),
+        }
+    }
+
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let request = self.http.request(method, url);
+        match self.authorization.as_ref() {
+            Some(authorization) => request.header("Authorization", authorization.raw_secret()),
+            None => request,
+        }
+    }
+
+    async fn send(
+        &self,
+        operation: &'static str,
+        request: RequestBuilder,
+    ) -> Result<reqwest::Response> {
+        Ok(request.send().await.map_err(|err| RegistryUnreachable {
+            operation,
+            cause: err.to_string(),
+        })?)
+    }
+
+    /// Sends the given request, which is expected to be answered with a `202 Accepted` and the
+    /// location of the upload session.
+    async fn location(&self, operation: &'static str, request: RequestBuilder) -> Result<String> {
+        let response = self.send(operation, request).await?;
+        if response.status() != StatusCode::ACCEPTED {
+            return Err(rejected(operation, response).await);
+        }
+        let location = response
+            .headers()
+            .get("Location")
+            .and_then(|location| location.to_str().ok())
+            .map(str::to_string);
+        match location {
+            // Registries may answer with a location relative to themselves.
+            Some(location) if location.starts_with('/') => Ok(format!("{}{}", self.base, location)),
+            Some(location) => Ok(location),
+            None => Err(RegistryRequestFailed {
+                operation,
+                status: response.status().as_u16(),
+                body: "the response did not include a Location header".to_string(),
+            }
+            .into()),
+        }
+    }
+}
+
+/// Parses the `realm` and `service` out of the given `WWW-Authenticate` header, should it be a
+/// bearer challenge, such as...
+///
+/// ```text
+/// Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/alpine:pull"
+/// ```
+fn bearer_challenge(header: &str) -> Option<(String, Option<String>)> {
+    let mut rest = header.strip_prefix("Bearer ")?.trim();
+    let (mut realm, mut service) = (None, None);
+    while let Some((key, value)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim();
+        // Quoted values (such as the scope) may themselves contain commas.
+        let (value, remainder) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => value.split_at(value.find(',').unwrap_or(value.len())),
+        };
+        match key {
+            "realm" => realm = Some(value.to_string()),
+            "service" => service = Some(value.to_string()),
+            _ => (),
+        }
+        rest = remainder;
+    }
+    Some((realm?, service))
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadGateway)]
+#[error("The registry rejected our request to {operation} with a {status}. The registry responded with: {body}")]
+#[error_code("AIM-1404")]
+struct RegistryRequestFailed {
+    operation: &'static str,
+    status: u16,
+    body: String,
+}
+
+/// Describes the registry's rejection of one of our requests. A `401 Unauthorized` is reported
+/// as a [RegistryUnauthorized](RegistryUnauthorized) so that callers may retry with fresh credentials.
+async fn rejected(operation: &'static str, response: reqwest::Response) -> Box<dyn AcmError> {
+    if response.status() == StatusCode::UNAUTHORIZED {
+        return RegistryUnauthorized { operation }.into();
+    }
+    RegistryRequestFailed {
+        operation,
+        status: response.status().as_u16(),
+        body: response.text().await.unwrap_or_default(),
+    }
+    .into()
+}
+
+/// The [error code](AcmError::error_code) of [RegistryUnauthorized](RegistryUnauthorized).
+pub const REGISTRY_UNAUTHORIZED: &str = "AIM-1407";
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadGateway)]
+#[error(
+    "The registry refused our credentials on our request to {operation}. They may have expired \
+or been revoked (or, should the image have been pulled from a remote registry, never have been \
+given)."
+)]
+#[error_code("AIM-1407")]
+struct RegistryUnauthorized {
+    operation: &'static str,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadGateway)]
+#[error("Failed to reach the registry to {operation}, {cause}")]
+#[error_code("AIM-1405")]
+struct RegistryUnreachable {
+    operation: &'static str,
+    cause: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error(
+    "Failed to download {digest} into the AIM's temporary directory, {cause}. Please ensure \
+that the temporary directory has enough free space for the image and try again."
+)]
+#[error_code("AIM-1410")]
+struct DownloadFailed {
+    digest: String,
+    cause: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bearer_challenges() {
+        let header =
+            "Bearer realm=\"https://auth.docker.io/token\",service=\"registry.docker.io\",\
+scope=\"repository:library/alpine:pull,push\"";
+        assert_eq!(
+            bearer_challenge(header),
+            Some((
+                "https://auth.docker.io/token".to_string(),
+                Some("registry.docker.io".to_string())
+            ))
+        );
+        assert_eq!(
+            bearer_challenge("Bearer realm=\"https://ghcr.io/token\""),
+            Some(("https://ghcr.io/token".to_string(), None))
+        );
+        assert_eq!(bearer_challenge("Basic realm=\"ECR\""), None);
+        assert_eq!(bearer_challenge("Bearer service=\"registry\""), None);
+    }
+}
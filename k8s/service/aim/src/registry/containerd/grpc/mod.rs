@@ -0,0 +1,367 @@
+//! A client of [containerd's gRPC API](https://github.com/containerd/containerd/tree/main/api)
+//! which backs the import workflow unless the AIM is built with the `ctr` feature, in which case
+//! the workflow shells out to the `ctr` CLI instead.
+//!
+//! Unlike the `ctr` CLI, whose tables must be scraped, every response here is structured.
+mod archive;
+mod content;
+mod distribution;
+
+pub use distribution::REGISTRY_UNAUTHORIZED;
+
+use super::remote::Remote;
+use crate::env;
+use archive::{Descriptor, MalformedArchive};
+use containerd_client::services::v1::content_client::ContentClient;
+use containerd_client::services::v1::images_client::ImagesClient;
+use containerd_client::services::v1::leases_client::LeasesClient;
+use containerd_client::services::v1::namespaces_client::NamespacesClient;
+use containerd_client::services::v1::{
+    CreateImageRequest, CreateRequest, DeleteImageRequest, DeleteNamespaceRequest, DeleteRequest,
+    GetImageRequest, Image, ListContentRequest, ListNamespacesRequest,
+};
+use distribution::Registry;
+use error::*;
+use result::Result;
+use secret::Secret;
+use std::path::{Path, PathBuf};
+use tonic::metadata::MetadataValue;
+use tonic::transport::Channel;
+use tonic::Code;
+
+/// The gRPC metadata key that scopes a request to a containerd namespace.
+const NAMESPACE_HEADER: &str = "containerd-namespace";
+
+/// The gRPC metadata key that attaches the resources created by a request to a lease.
+const LEASE_HEADER: &str = "containerd-lease";
+
+/// A Containerd is a connection to containerd that is scoped to a single namespace.
+///
+/// Every install is conducted under its own lease (of the same name as its namespace), which
+/// protects the blobs written into the content store from containerd's garbage collector until
+/// the image that refers to them has been created. The lease is released alongside the namespace.
+pub struct Containerd {
+    channel: Channel,
+    namespace: String,
+}
+
+impl Containerd {
+    /// Connects to the containerd listening at [containerd_address](env::containerd_address).
+    pub async fn connect<N: AsRef<str>>(namespace: N) -> Result<Containerd> {
+        let address = env::containerd_address();
+        let channel =
+            containerd_client::connect(&address)
+                .await
+                .map_err(|err| ContainerdUnavailable {
+                    address,
+                    cause: err.to_string(),
+                })?;
+        Ok(Containerd {
+            channel,
+            namespace: namespace.as_ref().to_string(),
+        })
+    }
+
+    /// Imports the (possibly gzipped) image tarball at the given path, returning the name and
+    /// the target digest of the new image.
+    ///
+    /// The tarball is unpacked alongside itself (and removed once the import completes), so an
+    /// install momentarily requires twice the size of its image in disk.
+    pub async fn import<P: AsRef<Path>>(&self, path: P) -> Result<(String, String)> {
+        self.lease().await?;
+        let unpacked = Unpacked(path.as_ref().with_extension("unpacked"));
+        let (tarball, dir) = (path.as_ref().to_path_buf(), unpacked.0.clone());
+        let archive = tokio::task::spawn_blocking(move || archive::unpack(&tarball, &dir))
+            .await
+            .map_err(|err| MalformedArchive::from(err.to_string()))??;
+        for blob in archive.blobs.iter() {
+            content::write(self, blob).await?;
+        }
+        self.create(&archive.name, archive.target.clone()).await?;
+        Ok((archive.name, archive.target.digest))
+    }
+
+    /// Pulls the given remote image (every platform of it) into the content store, returning the
+    /// name and the target digest of the new image.
+    ///
+    /// Each blob is downloaded alongside the other staged uploads (and removed once it has been
+    /// written into the content store), so a pull momentarily requires the size of its largest
+    /// blob in disk on top of the content store itself.
+    pub async fn pull(&self, remote: &Remote) -> Result<(String, String)> {
+        self.lease().await?;
+        let downloads = Unpacked(std::env::temp_dir().join(format!("{}.pulled", self.namespace)));
+        let registry = Registry::new(
+            remote.endpoint(),
+            &remote.repository,
+            remote.credentials.clone(),
+            false,
+        )
+        .authenticate()
+        .await?;
+        let target = registry
+            .pull(self, remote.reference.clone(), &downloads.0)
+            .await?;
+        let name = remote.to_string();
+        self.create(&name, target.clone()).await?;
+        Ok((name, target.digest))
+    }
+
+    /// Creates a new image of the given name which refers to the same content as the given reference.
+    pub async fn tag(&self, reference: &str, new_reference: &str) -> Result<()> {
+        let target = self.target(reference).await?;
+        self.create(new_reference, target).await
+    }
+
+    /// Pushes the given image to its registry. The reference MUST be fully qualified
+    /// (that is, `<registry>/<repository>:<tag>`).
+    pub async fn push(
+        &self,
+        reference: &str,
+        credentials: Option<Secret>,
+        plain_http: bool,
+    ) -> Result<()> {
+        let (repository, tag) = reference
+            .rsplit_once(':')
+            .ok_or_else(|| UnqualifiedReference::from(reference))?;
+        let (registry, repository) = repository
+            .split_once('/')
+            .ok_or_else(|| UnqualifiedReference::from(reference))?;
+        let target = self.target(reference).await?;
+        Registry::new(registry, repository, credentials, plain_http)
+            .push(self, target, tag.to_string())
+            .await
+    }
+
+    /// Removes the given image. Removing an image that does not exist succeeds.
+    pub async fn remove_image(&self, reference: &str) -> Result<()> {
+        let request = self.request(DeleteImageRequest {
+            name: reference.to_string(),
+            sync: false,
+        });
+        match ImagesClient::new(self.channel()).delete(request).await {
+            Ok(_) => Ok(()),
+            Err(status) if status.code() == Code::NotFound => Ok(()),
+            Err(status) => Err(rpc("images.Delete", status).into()),
+        }
+    }
+
+    /// Releases this namespace's lease and then removes the namespace itself. containerd
+    /// refuses to remove a namespace that still has images within it.
+    pub async fn remove_namespace(&self) -> Result<()> {
+        let request = self.request(DeleteRequest {
+            id: self.namespace.clone(),
+            sync: false,
+        });
+        match LeasesClient::new(self.channel()).delete(request).await {
+            Ok(_) => (),
+            Err(status) if status.code() == Code::NotFound => (),
+            Err(status) => return Err(rpc("leases.Delete", status).into()),
+        }
+        let request = tonic::Request::new(DeleteNamespaceRequest {
+            name: self.namespace.clone(),
+        });
+        match NamespacesClient::new(self.channel()).delete(request).await {
+            Ok(_) => Ok(()),
+            Err(status) if status.code() == Code::NotFound => Ok(()),
+            Err(status) => Err(rpc("namespaces.Delete", status).into()),
+        }
+    }
+
+    /// Returns this same connection to containerd, scoped to the given namespace instead.
+    pub fn scoped<N: AsRef<str>>(&self, namespace: N) -> Containerd {
+        Containerd {
+            channel: self.channel(),
+            namespace: namespace.as_ref().to_string(),
+        }
+    }
+
+    /// Returns the name of every namespace within containerd, which is as cheap a question as
+    /// containerd may be asked.
+    pub async fn namespaces(&self) -> Result<Vec<String>> {
+        let request = tonic::Request::new(ListNamespacesRequest {
+            filter: String::new(),
+        });
+        Ok(NamespacesClient::new(self.channel())
+            .list(request)
+            .await
+            .map_err(|status| rpc("namespaces.List", status))?
+            .into_inner()
+            .namespaces
+            .into_iter()
+            .map(|namespace| namespace.name)
+            .collect())
+    }
+
+    /// Returns the digest and size of every blob within the content store that is visible to
+    /// this namespace.
+    pub async fn content(&self) -> Result<Vec<(String, u64)>> {
+        let request = self.request(ListContentRequest { filters: vec![] });
+        let mut responses = ContentClient::new(self.channel())
+            .list(request)
+            .await
+            .map_err(|status| rpc("content.List", status))?
+            .into_inner();
+        let mut blobs = vec![];
+        while let Some(response) = responses
+            .message()
+            .await
+            .map_err(|status| rpc("content.List", status))?
+        {
+            blobs.extend(
+                response
+                    .info
+                    .into_iter()
+                    .map(|info| (info.digest, info.size.max(0) as u64)),
+            );
+        }
+        Ok(blobs)
+    }
+
+    async fn lease(&self) -> Result<()> {
+        let request = self.request(CreateRequest {
+            id: self.namespace.clone(),
+            ..Default::default()
+        });
+        match LeasesClient::new(self.channel()).create(request).await {
+            Ok(_) => Ok(()),
+            Err(status) if status.code() == Code::AlreadyExists => Ok(()),
+            Err(status) => Err(rpc("leases.Create", status).into()),
+        }
+    }
+
+    async fn create(&self, name: &str, target: Descriptor) -> Result<()> {
+        let request = self.request(CreateImageRequest {
+            image: Some(Image {
+                name: name.to_string(),
+                target: Some(target.into()),
+                ..Default::default()
+            }),
+        });
+        ImagesClient::new(self.channel())
+            .create(request)
+            .await
+            .map_err(|status| rpc("images.Create", status))?;
+        Ok(())
+    }
+
+    async fn target(&self, reference: &str) -> Result<Descriptor> {
+        let request = self.request(GetImageRequest {
+            name: reference.to_string(),
+        });
+        ImagesClient::new(self.channel())
+            .get(request)
+            .await
+            .map_err(|status| rpc("images.Get", status))?
+            .into_inner()
+            .image
+            .and_then(|image| image.target)
+            .map(Descriptor::from)
+            .ok_or_else(|| rpc("images.Get", tonic::Status::not_found(reference)).into())
+    }
+
+    fn channel(&self) -> Channel {
+        self.channel.clone()
+    }
+
+    /// Wraps the given message in a request that is scoped to this namespace.
+    fn request<T>(&self, message: T) -> tonic::Request<T> {
+        let mut request = tonic::Request::new(message);
+        request
+            .metadata_mut()
+            .insert(NAMESPACE_HEADER, ascii(&self.namespace));
+        request
+    }
+
+    /// Wraps the given message in a request that is scoped to this namespace and whose
+    /// resources are held by this namespace's lease.
+    fn leased<T>(&self, message: T) -> tonic::Request<T> {
+        let mut request = self.request(message);
+        request
+            .metadata_mut()
+            .insert(LEASE_HEADER, ascii(&self.namespace));
+        request
+    }
+}
+
+/// Namespaces are [UUIDs](names::uuid), and thus are always valid ASCII metadata.
+fn ascii(value: &str) -> MetadataValue<tonic::metadata::Ascii> {
+    value.parse().unwrap()
+}
+
+impl From<containerd_client::types::Descriptor> for Descriptor {
+    fn from(descriptor: containerd_client::types::Descriptor) -> Self {
+        Descriptor {
+            media_type: descriptor.media_type,
+            digest: descriptor.digest,
+            size: descriptor.size,
+            annotations: descriptor.annotations,
+        }
+    }
+}
+
+impl From<Descriptor> for containerd_client::types::Descriptor {
+    fn from(descriptor: Descriptor) -> Self {
+        containerd_client::types::Descriptor {
+            media_type: descriptor.media_type,
+            digest: descriptor.digest,
+            size: descriptor.size,
+            annotations: descriptor.annotations,
+        }
+    }
+}
+
+/// An Unpacked is the directory that an image tarball is unpacked into, which is removed upon drop.
+struct Unpacked(PathBuf);
+
+impl Drop for Unpacked {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Maps the given gRPC status into a [ContainerdRequestFailed](ContainerdRequestFailed).
+fn rpc(operation: &'static str, status: tonic::Status) -> ContainerdRequestFailed {
+    ContainerdRequestFailed {
+        operation,
+        code: format!("{:?}", status.code()),
+        message: status.message().to_string(),
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error(
+    "Failed to connect to containerd at {address}, {cause}. containerd runs as a sidecar to the \
+AIM, so perhaps it is still starting up?"
+)]
+#[error_code("AIM-1400")]
+pub struct ContainerdUnavailable {
+    address: String,
+    cause: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error("containerd failed the {operation} request with {code}: {message}")]
+#[error_code("AIM-1401")]
+pub struct ContainerdRequestFailed {
+    operation: &'static str,
+    code: String,
+    message: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error("The image reference '{reference}' is not of the form <registry>/<repository>:<tag>")]
+#[error_code("AIM-1406")]
+pub struct UnqualifiedReference {
+    reference: String,
+}
+
+impl From<&str> for UnqualifiedReference {
+    fn from(reference: &str) -> Self {
+        UnqualifiedReference {
+            reference: reference.to_string(),
+        }
+    }
+}
@@ -1,11 +1,13 @@
+use super::format::{Normalized, UnsupportedImageFormat};
 use super::namespace::Namespace;
+use super::remote::Remote;
+#[cfg(feature = "ctr")]
 use crate::ctr;
 use crate::registry::containerd::retag::Retag;
 use crate::registry::containerd::tmp_image::TmpImage;
 use error::*;
 use kind::Kind;
 use result::Result;
-use rocket::fs::TempFile;
 use std::path::Path;
 use thiserror::Error;
 
@@ -23,22 +25,74 @@ type Digest = String;
 type Tag = String;
 
 impl<'a> Import<'a> {
-    /// Imports the given temporary file into containerd and returns a [Retaggin](Retag) step.
+    /// Imports the given file path into containerd and returns a [Retaggin](Retag) step.
     ///
-    /// This function takes ownership of the provided temporary file and deletes it upon completion.
-    pub async fn import(self, tmp: TempFile<'_>) -> Result<Retag<'a>> {
-        self.import_path(tmp.path().unwrap().to_str().unwrap().to_string())
+    /// The file is first [normalized](Normalized) into an uncompressed tarball, so that gzip and
+    /// zstd compressed uploads are accepted alongside plain ones.
+    pub async fn import_path<P: AsRef<Path>>(self, path: P) -> Result<Retag<'a>> {
+        let upload = path.as_ref().to_path_buf();
+        let normalized = tokio::task::spawn_blocking(move || Normalized::new(&upload))
             .await
+            .map_err(|err| UnsupportedImageFormat::from(err.to_string()))??;
+        Ok(Retag {
+            image: self.import(&normalized.path).await?,
+        })
     }
 
-    /// Imports the given file path into containerd and returns a [Retaggin](Retag) step.
-    pub async fn import_path<P: AsRef<Path>>(self, path: P) -> Result<Retag<'a>> {
+    /// Pulls the given remote image into containerd and returns a [Retagging](Retag) step.
+    pub async fn pull(self, remote: &Remote) -> Result<Retag<'a>> {
+        Ok(Retag {
+            image: self.fetch(remote).await?,
+        })
+    }
+
+    /// Imports the given file over containerd's gRPC API.
+    #[cfg(not(feature = "ctr"))]
+    async fn import<P: AsRef<Path>>(&self, path: P) -> Result<TmpImage<'a>> {
+        let (reference, digest) = super::grpc::Containerd::connect(self.namespace)
+            .await?
+            .import(path)
+            .await?;
+        self.tmp_image(reference, digest)
+    }
+
+    /// Pulls the given remote image over containerd's gRPC API.
+    #[cfg(not(feature = "ctr"))]
+    async fn fetch(&self, remote: &Remote) -> Result<TmpImage<'a>> {
+        let (reference, digest) = super::grpc::Containerd::connect(self.namespace)
+            .await?
+            .pull(remote)
+            .await?;
+        self.tmp_image(reference, digest)
+    }
+
+    #[cfg(not(feature = "ctr"))]
+    fn tmp_image(&self, reference: String, digest: String) -> Result<TmpImage<'a>> {
+        let tag = match reference.rsplit_once(':') {
+            Some((_, tag)) => tag.to_string(),
+            None => {
+                return Err(UnexpectedImageReferenceFormat {
+                    output: reference.clone(),
+                }
+                .into())
+            }
+        };
+        Ok(TmpImage {
+            reference,
+            tag,
+            digest,
+            namespace: self.namespace,
+        })
+    }
+
+    /// Imports the given file by shelling out to `ctr images import`.
+    #[cfg(feature = "ctr")]
+    async fn import<P: AsRef<Path>>(&self, path: P) -> Result<TmpImage<'a>> {
         let path = path.as_ref().to_str().ok_or_else(|| TempPathIsNotUFT8 {
             path: format!("{}", path.as_ref().as_os_str().to_string_lossy()),
         })?;
-        // Possibly figure out what file type it actually is
-        // https://crates.io/crates/infer
         ctr!(
+            options = self.namespace.options(),
             "-n",
             &self.namespace,
             "images",
@@ -47,9 +101,43 @@ impl<'a> Import<'a> {
             &path
         )
         .await?;
-        Ok(Retag {
-            image: Self::extract_image_metadata(self.namespace).await?,
-        })
+        Self::extract_image_metadata(self.namespace).await
+    }
+
+    /// Pulls the given remote image (every platform of it, so that it may be pushed onward as a
+    /// whole) by shelling out to `ctr images pull`.
+    #[cfg(feature = "ctr")]
+    async fn fetch(&self, remote: &Remote) -> Result<TmpImage<'a>> {
+        let reference = remote.to_string();
+        match remote.credentials.as_ref() {
+            Some(credentials) => {
+                ctr!(
+                    options = self.namespace.options(),
+                    "-n",
+                    &self.namespace,
+                    "images",
+                    "pull",
+                    "--all-platforms",
+                    "-u",
+                    credentials,
+                    &reference
+                )
+                .await?
+            }
+            None => {
+                ctr!(
+                    options = self.namespace.options(),
+                    "-n",
+                    &self.namespace,
+                    "images",
+                    "pull",
+                    "--all-platforms",
+                    &reference
+                )
+                .await?
+            }
+        };
+        Self::extract_image_metadata(self.namespace).await
     }
 
     /// Runs `ctr -n <NAMESPACE> images ls` and attempts to extract the reference, tag, and digest
@@ -61,8 +149,16 @@ impl<'a> Import<'a> {
     /// REF                          TYPE                                                 DIGEST                                                                  SIZE     PLATFORMS   LABELS
     /// docker.io/test/tennis:latest application/vnd.docker.distribution.manifest.v2+json sha256:76a5627069e32d0543dd6bec4c352af358974dd4572dfc05dbf7147b5546df4f 19.2 MiB linux/amd64 -      
     /// ```
+    #[cfg(feature = "ctr")]
     async fn extract_image_metadata(namespace: &Namespace) -> Result<TmpImage<'_>> {
-        let images_ls = ctr!("-n", namespace, "images", "ls").await?;
+        let images_ls = ctr!(
+            options = namespace.options(),
+            "-n",
+            namespace,
+            "images",
+            "ls"
+        )
+        .await?;
         let (reference, tag, digest) = Self::extract_image_metadata_from_str(namespace, images_ls)?;
         let image = TmpImage {
             reference,
@@ -83,6 +179,7 @@ impl<'a> Import<'a> {
     /// REF                          TYPE                                                 DIGEST                                                                  SIZE     PLATFORMS   LABELS
     /// docker.io/test/tennis:latest application/vnd.docker.distribution.manifest.v2+json sha256:76a5627069e32d0543dd6bec4c352af358974dd4572dfc05dbf7147b5546df4f 19.2 MiB linux/amd64 -      
     /// ```
+    #[cfg_attr(not(feature = "ctr"), allow(dead_code))]
     fn extract_image_metadata_from_str<T: AsRef<str>, U: AsRef<str>>(
         namespace: T,
         images_ls: U,
@@ -177,6 +274,7 @@ struct UnexpectedImageReferenceFormat {
     output: String,
 }
 
+#[cfg(feature = "ctr")]
 #[derive(Error, Kind, AcmError, HttpCode, Debug)]
 #[error("The temporary filepath for the connector's image was not valid UTF8 (was (lossy) {path}). This is concerning, although the install may work if you just try again.")]
 #[code(Status::InternalServerError)]
@@ -1,21 +1,32 @@
+mod format;
+#[cfg(not(feature = "ctr"))]
+mod grpc;
 mod import;
 mod namespace;
 mod push;
+mod remote;
 pub mod retag;
 mod tmp_image;
 mod workflow;
 
+use crate::admission::InstallCancelled;
 use crate::registry::containerd::namespace::Namespace;
 use crate::registry::containerd::tmp_image::TmpImage;
 use crate::registry::containerd::workflow::WorkFlow;
+use crate::registry::ConnectorLabels;
 use kind::Kind;
+pub use remote::{MalformedRemoteInstall, Remote, RemoteInstall};
 use result::Result;
-use rocket::fs::TempFile;
 use serde::Serialize;
+use std::path::Path;
+use tokio_util::sync::CancellationToken;
 
 /// `ctr` is a convenience macro for executing the [ctr command](https://github.com/containerd/containerd/tree/main/cmd/ctr)
 /// which is a CLI tool for interacting with containerd.
 ///
+/// The import workflow only shells out to `ctr` when the AIM is built with the `ctr` feature.
+/// Otherwise, it speaks to containerd directly over its gRPC API.
+///
 /// This macro returns a future of the output returned by [cmd](os::cmd) with the command `ctr` pre-filled in.
 ///
 /// ```ignore
@@ -23,17 +34,39 @@ use serde::Serialize;
 /// ```
 #[macro_export]
 macro_rules! ctr {
+    (options=$options:expr, $($args:expr),*) => {
+        cmd!(options=$options, "ctr" $(,$args)*)
+    };
     ($($args:expr),*) => {
         cmd!("ctr" $(,$args)*)
     }
 }
 
+/// `ctr_streaming` is to [cmd_streaming](os::cmd_streaming) what [ctr](ctr) is to [cmd](os::cmd),
+/// for those `ctr` commands whose progress is worth surfacing while they run.
+///
+/// ```ignore
+/// let mut lines = ctr_streaming!(options = namespace.options(), "images", "push", &image);
+/// ```
+#[macro_export]
+macro_rules! ctr_streaming {
+    (options=$options:expr, $($args:expr),*) => {
+        cmd_streaming!(options=$options, "ctr" $(,$args)*)
+    };
+    ($($args:expr),*) => {
+        cmd_streaming!("ctr" $(,$args)*)
+    }
+}
+
 /// An Image is a pairing of a tag and a digest and is intended to be the final representation
 /// of an image that is sent back upstream to calling clients.
-#[derive(Serialize, Debug, Kind)]
+#[derive(Serialize, Debug, Clone, Kind)]
 pub struct Image {
     pub tag: String,
     pub digest: String,
+    /// The labels that name the connector within the image, should it have any.
+    #[serde(skip_serializing_if = "ConnectorLabels::is_empty")]
+    pub labels: ConnectorLabels,
 }
 
 /// This conversion consumes the [TmpImage](TmpImage) that was within containerd during
@@ -44,26 +77,129 @@ impl From<TmpImage<'_>> for Image {
         Image {
             tag: image.tag.clone(),
             digest: image.digest.clone(),
+            labels: ConnectorLabels::default(),
         }
     }
 }
 
+/// Confirms that containerd answers, returning where it was found.
+#[cfg(not(feature = "ctr"))]
+pub async fn ping() -> Result<String> {
+    let namespaces = grpc::Containerd::connect("default")
+        .await?
+        .namespaces()
+        .await?;
+    Ok(format!(
+        "{} answered with {} namespaces",
+        crate::env::containerd_address(),
+        namespaces.len()
+    ))
+}
+
+/// Confirms that containerd answers (by way of `ctr version`), returning where it was found.
+#[cfg(feature = "ctr")]
+pub async fn ping() -> Result<String> {
+    crate::ctr!("version").await?;
+    Ok(format!("{} answered", crate::env::containerd_address()))
+}
+
+/// Returns the number of bytes held within containerd's content store, across every namespace.
+/// A blob that is visible to more than one namespace is counted once.
+#[cfg(not(feature = "ctr"))]
+pub async fn disk_usage() -> Result<Option<u64>> {
+    let containerd = grpc::Containerd::connect("default").await?;
+    let mut blobs = std::collections::HashMap::new();
+    for namespace in containerd.namespaces().await? {
+        blobs.extend(containerd.scoped(namespace).content().await?);
+    }
+    Ok(Some(blobs.values().sum()))
+}
+
+/// The `ctr` CLI only reports the sizes of blobs in a human readable form, so the disk usage of
+/// containerd is not known when the AIM is built with the `ctr` feature.
+#[cfg(feature = "ctr")]
+pub async fn disk_usage() -> Result<Option<u64>> {
+    Ok(None)
+}
+
 /// This procedure imports the given OCI compliant image into the configure registry using
 /// containerd as the intermediate for retagging and pushing.
 ///
 /// The pipeline for this procedure is as follows:
 ///
-/// 1. Import the file as is into containerd under a unique namespace.
+/// 1. Import the file (decompressing it first, should it be gzip or zstd compressed) into containerd under a unique namespace.
 /// 2. Retag the imported image with a new <[registry](crate::env::registry)>/<[repository](crate::env::repository)>:<[tag](names::rfc1035_label())>.
 /// 3. Push the newly tagged image into the remote registry.
-pub async fn import(image: TempFile<'_>) -> Result<Image> {
-    let namespace = Namespace::new();
-    let image = WorkFlow::new_workflow(&namespace)
-        .import(image)
-        .await?
-        .retag()
-        .await?
-        .push()
-        .await?;
+///
+/// The given `progress` callback is invoked with each [Step](Step) as it begins. Should the given
+/// `cancellation` token be cancelled, then the pipeline is abandoned wherever it happens to be
+/// (killing any `ctr` command that is running) and an [InstallCancelled](InstallCancelled) is
+/// returned. Whatever had been imported into containerd is cleaned up as usual.
+pub async fn import<P: AsRef<Path>, F: Fn(Step)>(
+    image: P,
+    cancellation: CancellationToken,
+    progress: F,
+) -> Result<Image> {
+    install(Source::Archive(image.as_ref()), cancellation, progress).await
+}
+
+/// This procedure is to [import](import) what `docker pull` is to `docker load`. That is, rather
+/// than importing an uploaded file, the given remote image is pulled into containerd before
+/// being retagged and pushed as usual. Pulling is reported as the [Importing](Step::Importing)
+/// step.
+pub async fn pull<F: Fn(Step)>(
+    remote: &Remote,
+    cancellation: CancellationToken,
+    progress: F,
+) -> Result<Image> {
+    install(Source::Remote(remote), cancellation, progress).await
+}
+
+/// A Source is where an image is imported into containerd from.
+enum Source<'a> {
+    Archive(&'a Path),
+    Remote(&'a Remote),
+}
+
+async fn install<F: Fn(Step)>(
+    source: Source<'_>,
+    cancellation: CancellationToken,
+    progress: F,
+) -> Result<Image> {
+    let namespace = Namespace::new(cancellation.clone());
+    tokio::select! {
+        image = pipeline(&namespace, source, progress) => image,
+        _ = cancellation.cancelled() => Err(InstallCancelled {}.into()),
+    }
+}
+
+async fn pipeline<F: Fn(Step)>(
+    namespace: &Namespace,
+    source: Source<'_>,
+    progress: F,
+) -> Result<Image> {
+    progress(Step::Importing);
+    let workflow = WorkFlow::new_workflow(namespace);
+    let retag = match source {
+        Source::Archive(path) => workflow.import_path(path).await?,
+        Source::Remote(remote) => workflow.pull(remote).await?,
+    };
+    progress(Step::Retagging);
+    let push = retag.retag().await?;
+    progress(Step::Pushing);
+    let image = push.push().await?;
     Ok(image)
 }
+
+/// A Step is a stage of the [import](import) pipeline.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Step {
+    Importing,
+    Retagging,
+    Pushing,
+}
+
+impl Step {
+    /// Every step, in the order that the pipeline takes them.
+    pub const ALL: [Step; 3] = [Step::Importing, Step::Retagging, Step::Pushing];
+}
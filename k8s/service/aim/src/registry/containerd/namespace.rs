@@ -1,7 +1,8 @@
-use crate::ctr;
-use backoff::backoff::Backoff;
+use result::Result;
+use retry::Policy;
 use std::ffi::OsStr;
 use std::fmt::{Display, Formatter};
+use tokio_util::sync::CancellationToken;
 
 /// A Namespace is a randomly generated (UUID) containerd namespace
 /// that is used for conducting the import workflow. All steps of the workflow
@@ -18,14 +19,25 @@ use std::fmt::{Display, Formatter};
 /// made to install two or more of the same image at the same time.
 pub struct Namespace {
     pub namespace: String,
+    /// Cancels every `ctr` command that is run within the namespace by the import workflow.
+    #[cfg_attr(not(feature = "ctr"), allow(dead_code))]
+    cancellation: CancellationToken,
 }
 
 impl Namespace {
-    pub fn new() -> Namespace {
+    pub fn new(cancellation: CancellationToken) -> Namespace {
         Namespace {
             namespace: names::uuid(),
+            cancellation,
         }
     }
+
+    /// The [Options](os::process::Options) of every `ctr` command that the import workflow runs
+    /// within this namespace. Cleanup commands are NOT run with these, as they must run regardless.
+    #[cfg(feature = "ctr")]
+    pub fn options(&self) -> os::process::Options {
+        os::process::Options::new().cancellation(self.cancellation.clone())
+    }
 }
 
 /// The [drop](Drop) implementation for a namespace guarantees that it is always destroyed
@@ -39,7 +51,8 @@ impl Namespace {
 /// for an image, followed by a deletion request for its containing namespace. However, under the
 /// hood, containerd has not yet finished deletion of the the aforementioned temporary image, resulting
 /// in a failure when deleting the namespace. This why this drop method is ran in the background
-/// in order to eventually complete, and with an exponential backoff in order to automatically retry.
+/// in order to eventually complete, and under the [containerd-cleanup](Policy::ContainerdCleanup)
+/// retry policy in order to automatically retry.
 ///
 /// If a namespace does become orphaned for whatever reason then an error is logged. In order to
 /// recover from this error (that is, force a cleanup of the namespace) one need only restart
@@ -47,46 +60,55 @@ impl Namespace {
 impl Drop for Namespace {
     fn drop(&mut self) {
         let namespace = self.namespace.clone();
-        let namespace_display = term_colors::cyan(namespace.clone());
+        let namespace_display = term_colors::highlight(namespace.clone());
         tokio::spawn(async move {
             debug!(
                 "Beginning destruction of temporary namespace {}",
                 namespace_display
             );
-            let mut backoff = backoff::ExponentialBackoff::default();
-            loop {
-                let result = ctr!("namespace", "remove", &namespace).await;
-                let pause = backoff.next_backoff();
-                match (result, pause) {
-                    (Err(err), Some(pause)) => {
-                        trace!(
-                            "Failed to run command to destroy tmp names[ace {}, '{}'",
-                            namespace_display,
-                            err
-                        );
-                        tokio::time::sleep(pause).await;
-                    }
-                    (Err(err), None) => {
-                        error!(
-                            "{}, stopping reattempts so the namespace {} may be orphaned now. \
-                            These orphans can be cleaned up simply by restarted the aim's pod.",
-                            err, namespace_display
-                        );
-                        return;
-                    }
-                    (Ok(_), _) => {
-                        debug!(
-                            "Temporary namespace {} successfully deleted",
-                            namespace_display
-                        );
-                        return;
-                    }
-                };
+            let removal = retry::retry_async(Policy::ContainerdCleanup, || async {
+                remove(&namespace).await.map_err(|err| {
+                    trace!(
+                        "Failed to run command to destroy tmp namespace {}, '{}'",
+                        namespace_display,
+                        err
+                    );
+                    err
+                })
+            })
+            .await;
+            match removal {
+                Err(err) => error!(
+                    "{}, stopping reattempts so the namespace {} may be orphaned now. \
+                    These orphans can be cleaned up simply by restarted the aim's pod.",
+                    err, namespace_display
+                ),
+                Ok(_) => debug!(
+                    "Temporary namespace {} successfully deleted",
+                    namespace_display
+                ),
             }
         });
     }
 }
 
+/// Removes the given namespace (and, when speaking gRPC, releases its lease).
+#[cfg(not(feature = "ctr"))]
+async fn remove(namespace: &str) -> Result<()> {
+    super::grpc::Containerd::connect(namespace)
+        .await?
+        .remove_namespace()
+        .await
+}
+
+/// Removes the given namespace.
+#[cfg(feature = "ctr")]
+async fn remove(namespace: &str) -> Result<()> {
+    crate::ctr!("namespace", "remove", namespace)
+        .await
+        .map(|_| ())
+}
+
 impl AsRef<OsStr> for Namespace {
     fn as_ref(&self) -> &OsStr {
         self.namespace.as_ref()
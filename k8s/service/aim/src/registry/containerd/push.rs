@@ -1,9 +1,11 @@
-use crate::ctr;
-use crate::env::Secret;
 use crate::registry::containerd::tmp_image::TmpImage;
 use crate::registry::ecr;
 use crate::registry::{Image, Implementation};
+use error::AcmError;
+#[cfg(feature = "ctr")]
+use futures::StreamExt;
 use result::Result;
+use secret::Secret;
 
 /// The Push step takes ownership of a [TmpImage](TmpImage) and offers
 /// a single method...[Push::push](Push::push).
@@ -20,32 +22,97 @@ impl<'a> Push<'a> {
         Ok(self.image.into())
     }
 
+    /// Pushes to ECR using the cached ECR password. Should the registry refuse that password
+    /// (say, it was rotated out from under us) then the cache is invalidated and the push is
+    /// attempted exactly once more with a fresh password.
     async fn push_to_ecr(&self) -> Result<()> {
-        let (username, password) = ecr::get_credentials().await?;
-        let credentials = Secret::from(format!("{}:{}", username, password.raw_secret()));
-        ctr!(
-            "-n",
-            &self.image.namespace,
-            "images",
-            "push",
-            "-u",
-            &credentials,
-            &self.image
-        )
-        .await
-        .map(|_| ())
+        match self.push_with(Target::Ecr(ecr_credentials().await?)).await {
+            Err(err) if unauthorized(err.as_ref()) => {
+                warn!("ECR refused the cached password, retrying the push with a fresh one");
+                ecr::invalidate_password().await;
+                self.push_with(Target::Ecr(ecr_credentials().await?)).await
+            }
+            result => result,
+        }
     }
 
     async fn push_to_minikube(&self) -> Result<()> {
-        ctr!(
-            "-n",
-            &self.image.namespace,
-            "images",
-            "push",
-            "--plain-http",
-            &self.image
-        )
-        .await
-        .map(|_| ())
+        self.push_with(Target::Minikube).await
     }
+
+    /// Pushes the image straight out of containerd's content store.
+    #[cfg(not(feature = "ctr"))]
+    async fn push_with(&self, target: Target) -> Result<()> {
+        let (credentials, plain_http) = match target {
+            Target::Ecr(credentials) => (Some(credentials), false),
+            Target::Minikube => (None, true),
+        };
+        super::grpc::Containerd::connect(self.image.namespace)
+            .await?
+            .push(&self.image.reference, credentials, plain_http)
+            .await
+    }
+
+    /// Pushes the image by shelling out to `ctr images push`, logging its progress as it goes.
+    #[cfg(feature = "ctr")]
+    async fn push_with(&self, target: Target) -> Result<()> {
+        let namespace = self.image.namespace;
+        let options = namespace.options();
+        let mut lines = match target {
+            Target::Ecr(credentials) => crate::ctr_streaming!(
+                options = options,
+                "-n",
+                namespace,
+                "images",
+                "push",
+                "-u",
+                &credentials,
+                &self.image
+            ),
+            Target::Minikube => crate::ctr_streaming!(
+                options = options,
+                "-n",
+                namespace,
+                "images",
+                "push",
+                "--plain-http",
+                &self.image
+            ),
+        };
+        while let Some(line) = lines.next().await {
+            debug!("Pushing {}: {}", self.image, line?);
+        }
+        Ok(())
+    }
+}
+
+/// A Target is the registry that an image is pushed to, which decides how the push authenticates
+/// and whether it goes over TLS.
+enum Target {
+    /// ECR, over TLS, with the given `<username>:<password>` credentials.
+    Ecr(Secret),
+    /// Minikube's registry, over plain HTTP and without credentials.
+    Minikube,
+}
+
+/// Returns the ECR credentials in the `<username>:<password>` form expected by registries.
+async fn ecr_credentials() -> Result<Secret> {
+    let (username, password) = ecr::get_credentials().await?;
+    Ok(Secret::from(format!(
+        "{}:{}",
+        username,
+        password.raw_secret()
+    )))
+}
+
+/// Whether the given push failure was the registry refusing our credentials.
+#[cfg(not(feature = "ctr"))]
+fn unauthorized(err: &dyn AcmError) -> bool {
+    err.error_code() == Some(super::grpc::REGISTRY_UNAUTHORIZED)
+}
+
+/// Whether the given push failure was the registry refusing our credentials.
+#[cfg(feature = "ctr")]
+fn unauthorized(err: &dyn AcmError) -> bool {
+    err.to_string().contains("401 Unauthorized")
 }
@@ -0,0 +1,227 @@
+use error::*;
+use result::Result;
+use secret::Secret;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+
+/// The registry that references without one (such as `alation/oracle:1.2.0`) refer to.
+const DOCKER_HUB: &str = "docker.io";
+
+/// The host that actually serves the distribution API on behalf of [DOCKER_HUB](DOCKER_HUB).
+const DOCKER_HUB_ENDPOINT: &str = "registry-1.docker.io";
+
+/// A RemoteInstall is the body of a [remote install](crate::install_remote), that is, the
+/// reference of the image to pull alongside the credentials to pull it with (if any).
+#[derive(Deserialize, Debug)]
+pub struct RemoteInstall {
+    pub reference: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<Secret>,
+}
+
+/// A Remote is an image within a registry other than the configured one, which is pulled (rather
+/// than uploaded) into containerd at the start of an install.
+#[derive(Debug)]
+pub struct Remote {
+    /// The registry as it was named, such as `docker.io` or `public.ecr.aws`.
+    pub registry: String,
+    pub repository: String,
+    /// Either a tag or a digest (that is, `sha256:<hex>`).
+    pub reference: String,
+    /// Credentials in the form of `<username>:<password>`.
+    pub credentials: Option<Secret>,
+}
+
+impl Remote {
+    /// Parses the given image reference in the same way that docker does. That is, a reference
+    /// without a registry refers to Docker Hub (where an unqualified repository is within
+    /// `library/`), and a reference with neither a tag nor a digest refers to `latest`.
+    pub fn parse<R: AsRef<str>>(reference: R, credentials: Option<Secret>) -> Result<Remote> {
+        let raw = reference.as_ref();
+        let malformed = |reason: &str| MalformedReference {
+            reference: raw.to_string(),
+            reason: reason.to_string(),
+        };
+        let (name, reference) = match raw.split_once('@') {
+            Some((_, digest)) if !digest.starts_with("sha256:") => {
+                return Err(malformed("only sha256 digests are supported").into())
+            }
+            Some((name, digest)) => (name, digest.to_string()),
+            None => match raw.rsplit_once(':') {
+                Some((name, tag)) if !tag.contains('/') => (name, tag.to_string()),
+                _ => (raw, "latest".to_string()),
+            },
+        };
+        if reference.is_empty() {
+            return Err(malformed("the tag is empty").into());
+        }
+        let (registry, repository) = match name.split_once('/') {
+            Some((host, repository))
+                if host.contains('.') || host.contains(':') || host == "localhost" =>
+            {
+                (host.to_string(), repository.to_string())
+            }
+            Some(_) => (DOCKER_HUB.to_string(), name.to_string()),
+            None => (DOCKER_HUB.to_string(), format!("library/{}", name)),
+        };
+        if repository.is_empty() || repository.split('/').any(str::is_empty) {
+            return Err(malformed("the repository is empty").into());
+        }
+        if repository.chars().any(|c| c.is_ascii_uppercase()) {
+            return Err(malformed("the repository must be lowercase").into());
+        }
+        Ok(Remote {
+            registry,
+            repository,
+            reference,
+            credentials,
+        })
+    }
+
+    /// The host that serves the distribution API of this image's registry.
+    #[cfg_attr(feature = "ctr", allow(dead_code))]
+    pub fn endpoint(&self) -> &str {
+        match self.registry.as_str() {
+            DOCKER_HUB => DOCKER_HUB_ENDPOINT,
+            registry => registry,
+        }
+    }
+
+    /// Whether this image is referred to by its digest rather than by a tag.
+    pub fn is_digest(&self) -> bool {
+        self.reference.starts_with("sha256:")
+    }
+}
+
+impl RemoteInstall {
+    /// Parses this install into the [Remote](Remote) that it refers to. A username without a
+    /// password (or vice versa) is a [MalformedRemoteInstall](MalformedRemoteInstall).
+    pub fn remote(self) -> Result<Remote> {
+        let credentials = match (self.username, self.password) {
+            (Some(username), Some(password)) => Some(Secret::from(format!(
+                "{}:{}",
+                username,
+                password.raw_secret()
+            ))),
+            (None, None) => None,
+            _ => {
+                return Err(MalformedRemoteInstall {
+                    cause: "a username must be accompanied by a password (and vice versa)"
+                        .to_string(),
+                }
+                .into())
+            }
+        };
+        Remote::parse(self.reference, credentials)
+    }
+}
+
+/// Displays the fully qualified name of the image, such as `docker.io/library/alpine:3.14` or
+/// `public.ecr.aws/alation/oracle@sha256:...`.
+impl Display for Remote {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let separator = if self.is_digest() { '@' } else { ':' };
+        write!(
+            f,
+            "{}/{}{}{}",
+            self.registry, self.repository, separator, self.reference
+        )
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The image reference '{reference}' is malformed, {reason}. A reference takes the form of \
+[<registry>/]<repository>[:<tag>|@<digest>], such as 'public.ecr.aws/alation/oracle:1.2.0'."
+)]
+#[error_code("AIM-1408")]
+pub struct MalformedReference {
+    reference: String,
+    reason: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadRequest)]
+#[error(
+    "The body could not be read as a remote install, {cause}. The body must be a JSON object with \
+a 'reference' field, and optionally both the 'username' and 'password' to pull the image with."
+)]
+#[error_code("AIM-1409")]
+pub struct MalformedRemoteInstall {
+    pub(crate) cause: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(reference: &str) -> String {
+        Remote::parse(reference, None).unwrap().to_string()
+    }
+
+    #[test]
+    fn normalizes() {
+        assert_eq!(parse("alpine"), "docker.io/library/alpine:latest");
+        assert_eq!(
+            parse("alation/oracle:1.2.0"),
+            "docker.io/alation/oracle:1.2.0"
+        );
+        assert_eq!(
+            parse("public.ecr.aws/alation/oracle:1.2.0"),
+            "public.ecr.aws/alation/oracle:1.2.0"
+        );
+        assert_eq!(
+            parse("localhost:5000/oracle"),
+            "localhost:5000/oracle:latest"
+        );
+        assert_eq!(
+            parse("ghcr.io/alation/oracle@sha256:cb1ff0854b"),
+            "ghcr.io/alation/oracle@sha256:cb1ff0854b"
+        );
+    }
+
+    #[test]
+    fn endpoints() {
+        let hub = Remote::parse("alpine", None).unwrap();
+        assert_eq!(hub.endpoint(), "registry-1.docker.io");
+        let ghcr = Remote::parse("ghcr.io/alation/oracle", None).unwrap();
+        assert_eq!(ghcr.endpoint(), "ghcr.io");
+        assert!(!ghcr.is_digest());
+    }
+
+    #[test]
+    fn rejects() {
+        for reference in &[
+            "",
+            "oracle:",
+            "ghcr.io/",
+            "a//b",
+            "Oracle",
+            "oracle@md5:abc",
+        ] {
+            let err = Remote::parse(reference, None).unwrap_err();
+            assert_eq!(err.error_code(), Some("AIM-1408"), "{}", reference);
+        }
+    }
+
+    #[test]
+    fn requires_both_credentials() {
+        let install = RemoteInstall {
+            reference: "alpine".to_string(),
+            username: Some("alation".to_string()),
+            password: None,
+        };
+        let err = install.remote().unwrap_err();
+        assert_eq!(err.error_code(), Some("AIM-1409"));
+        let install = RemoteInstall {
+            reference: "alpine".to_string(),
+            username: Some("alation".to_string()),
+            password: Some(Secret::from("hunter2")),
+        };
+        let remote = install.remote().unwrap();
+        assert_eq!(remote.credentials.unwrap().raw_secret(), "alation:hunter2");
+    }
+}
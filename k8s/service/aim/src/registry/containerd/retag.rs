@@ -1,7 +1,7 @@
+use crate::env;
 use crate::registry::containerd::namespace::Namespace;
 use crate::registry::containerd::push::Push;
 use crate::registry::containerd::tmp_image::TmpImage;
-use crate::{ctr, env};
 use result::Result;
 
 /// The Retag step takes ownership of a [TmpImage](TmpImage) and offers
@@ -26,15 +26,7 @@ impl<'a> Retag<'a> {
         let repository = env::repository();
         let new_tag = names::rfc1035_label();
         let new_reference = format!("{}/{}:{}", registry, repository, new_tag);
-        ctr!(
-            "-n",
-            &self.image.namespace,
-            "images",
-            "tag",
-            &self.image,
-            &new_reference
-        )
-        .await?;
+        self.tag(&new_reference).await?;
         Ok(Push {
             // We have a new reference and tag, however the digest
             // and namespace remain unchanged.
@@ -46,4 +38,27 @@ impl<'a> Retag<'a> {
             },
         })
     }
+
+    #[cfg(not(feature = "ctr"))]
+    async fn tag(&self, new_reference: &str) -> Result<()> {
+        super::grpc::Containerd::connect(self.image.namespace)
+            .await?
+            .tag(&self.image.reference, new_reference)
+            .await
+    }
+
+    #[cfg(feature = "ctr")]
+    async fn tag(&self, new_reference: &str) -> Result<()> {
+        crate::ctr!(
+            options = self.image.namespace.options(),
+            "-n",
+            &self.image.namespace,
+            "images",
+            "tag",
+            &self.image,
+            new_reference
+        )
+        .await
+        .map(|_| ())
+    }
 }
@@ -1,6 +1,6 @@
 use super::namespace::Namespace;
-use crate::ctr;
-use backoff::backoff::Backoff;
+use result::Result;
+use retry::Policy;
 use std::ffi::OsStr;
 use std::fmt::{Display, Formatter};
 use term_colors;
@@ -28,48 +28,58 @@ pub struct TmpImage<'a> {
 /// for an image, followed by a deletion request for its containing namespace. However, under the
 /// hood, containerd has not yet finished deletion of the the aforementioned temporary image, resulting
 /// in a failure when deleting the namespace. This why this drop method is ran in the background
-/// in order to eventually complete, and with an exponential backoff in order to automatically retry.
+/// in order to eventually complete, and under the [containerd-cleanup](Policy::ContainerdCleanup)
+/// retry policy in order to automatically retry.
 ///
 /// While the above issue is more of a problem for [Namespaces](super::namespace::Namespace), we
-/// honor it here as well by running an exponential backoff in a background coroutine.
+/// honor it here as well by retrying in a background coroutine.
 impl Drop for TmpImage<'_> {
     fn drop(&mut self) {
         let namespace = self.namespace.namespace.clone();
         let reference = self.reference.clone();
-        let image_display = term_colors::cyan(format!("{}:{}", namespace, reference));
+        let image_display = term_colors::highlight(format!("{}:{}", namespace, reference));
         tokio::spawn(async move {
             debug!("Beginning destruction of temporary image {}", image_display);
-            let mut backoff = backoff::ExponentialBackoff::default();
-            loop {
-                let result = ctr!("-n", &namespace, "images", "remove", &reference).await;
-                let pause = backoff.next_backoff();
-                match (result, pause) {
-                    (Err(err), Some(pause)) => {
-                        trace!(
-                            "Failed to run command to destroy tmp image {}, '{}'",
-                            image_display,
-                            err
-                        );
-                        tokio::time::sleep(pause).await;
-                    }
-                    (Err(err), None) => {
-                        error!(
-                            "{}, stopping reattempts so the image {} may be orphaned now. \
-                        These orphans can be cleaned up simply by restarted the aim's pod.",
-                            err, image_display
-                        );
-                        return;
-                    }
-                    (Ok(_), _) => {
-                        debug!("Temporary image {} successfully deleted", image_display);
-                        return;
-                    }
-                }
+            let removal = retry::retry_async(Policy::ContainerdCleanup, || async {
+                remove(&namespace, &reference).await.map_err(|err| {
+                    trace!(
+                        "Failed to run command to destroy tmp image {}, '{}'",
+                        image_display,
+                        err
+                    );
+                    err
+                })
+            })
+            .await;
+            match removal {
+                Err(err) => error!(
+                    "{}, stopping reattempts so the image {} may be orphaned now. \
+                    These orphans can be cleaned up simply by restarted the aim's pod.",
+                    err, image_display
+                ),
+                Ok(_) => debug!("Temporary image {} successfully deleted", image_display),
             }
         });
     }
 }
 
+/// Removes the given image from the given namespace.
+#[cfg(not(feature = "ctr"))]
+async fn remove(namespace: &str, reference: &str) -> Result<()> {
+    super::grpc::Containerd::connect(namespace)
+        .await?
+        .remove_image(reference)
+        .await
+}
+
+/// Removes the given image from the given namespace.
+#[cfg(feature = "ctr")]
+async fn remove(namespace: &str, reference: &str) -> Result<()> {
+    crate::ctr!("-n", namespace, "images", "remove", reference)
+        .await
+        .map(|_| ())
+}
+
 impl AsRef<OsStr> for TmpImage<'_> {
     fn as_ref(&self) -> &OsStr {
         self.reference.as_ref()
@@ -0,0 +1,163 @@
+use result::Result;
+use secret::Secret;
+use std::time::{Duration, SystemTime};
+use tokio::sync::RwLock;
+
+/// How long ECR passwords are valid for, should ECR neglect to tell us.
+const DEFAULT_VALIDITY: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// How long before its expiry that a password is proactively refreshed.
+const REFRESH_MARGIN: Duration = Duration::from_secs(60 * 60);
+
+/// How long before its expiry that a cached password is no longer handed out at all. This covers
+/// the time that a caller may take to actually use the password (such as a long push).
+const EXPIRY_MARGIN: Duration = Duration::from_secs(10 * 60);
+
+/// How long to wait before reattempting a failed proactive refresh.
+const REFRESH_RETRY: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    static ref CACHE: RwLock<Option<Token>> = RwLock::new(None);
+}
+
+/// A Token is an ECR password along with the moment that ECR will stop accepting it.
+pub struct Token {
+    pub password: Secret,
+    pub expires_at: SystemTime,
+}
+
+impl Token {
+    /// Constructs a Token that expires at the given number of seconds since the UNIX epoch, or
+    /// after the [default validity](DEFAULT_VALIDITY) if ECR did not say.
+    pub fn new(password: Secret, expires_at: Option<i64>) -> Token {
+        let expires_at = match expires_at {
+            Some(seconds) if seconds > 0 => {
+                SystemTime::UNIX_EPOCH + Duration::from_secs(seconds as u64)
+            }
+            _ => SystemTime::now() + DEFAULT_VALIDITY,
+        };
+        Token {
+            password,
+            expires_at,
+        }
+    }
+
+    fn usable_at(&self, now: SystemTime) -> bool {
+        now + EXPIRY_MARGIN < self.expires_at
+    }
+
+    /// How long from now until this token ought to be refreshed. A token that is already within
+    /// the [refresh margin](REFRESH_MARGIN) (say, as ECR handed out a short lived one, or as the
+    /// clock is skewed) is refreshed no sooner than the [retry](REFRESH_RETRY) of a failed refresh,
+    /// lest ECR be asked for one back to back.
+    fn refresh_in(&self, now: SystemTime) -> Duration {
+        self.expires_at
+            .duration_since(now + REFRESH_MARGIN)
+            .unwrap_or(Duration::ZERO)
+            .max(REFRESH_RETRY)
+    }
+}
+
+/// Returns the cached password, fetching (and caching) a new one from ECR if the cache is
+/// empty or the cached password is about to expire.
+pub async fn password() -> Result<Secret> {
+    if let Some(token) = CACHE.read().await.as_ref() {
+        if token.usable_at(SystemTime::now()) {
+            return Ok(Secret::from(token.password.raw_secret()));
+        }
+    }
+    let mut cache = CACHE.write().await;
+    // Another caller may have refreshed the cache while we waited for the lock.
+    if let Some(token) = cache.as_ref() {
+        if token.usable_at(SystemTime::now()) {
+            return Ok(Secret::from(token.password.raw_secret()));
+        }
+    }
+    let token = super::authorize().await?;
+    let password = Secret::from(token.password.raw_secret());
+    *cache = Some(token);
+    Ok(password)
+}
+
+/// Drops the cached password so that the next call to [password](password) fetches a new one.
+/// This SHOULD be called whenever the registry rejects the cached password.
+pub async fn invalidate() {
+    *CACHE.write().await = None;
+}
+
+/// Spawns a background task which refreshes the cached password an [hour](REFRESH_MARGIN)
+/// ahead of its expiry so that pushes never have to wait on ECR for a new one.
+pub fn refresh_proactively() {
+    tokio::spawn(async {
+        loop {
+            let pause = match super::authorize().await {
+                Ok(token) => {
+                    let pause = token.refresh_in(SystemTime::now());
+                    *CACHE.write().await = Some(token);
+                    debug!(
+                        "Refreshed the ECR password, refreshing again in {:?}",
+                        pause
+                    );
+                    pause
+                }
+                Err(err) => {
+                    warn!("Failed to refresh the ECR password, {}", err);
+                    REFRESH_RETRY
+                }
+            };
+            tokio::time::sleep(pause).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expiry_from_ecr() {
+        let token = Token::new(Secret::from("pw"), Some(1_600_000_000));
+        assert_eq!(
+            token.expires_at,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+        );
+    }
+
+    #[test]
+    fn default_expiry() {
+        let before = SystemTime::now();
+        let token = Token::new(Secret::from("pw"), None);
+        assert!(token.expires_at >= before + DEFAULT_VALIDITY);
+    }
+
+    #[test]
+    fn unusable_near_expiry() {
+        let now = SystemTime::now();
+        let token = Token {
+            password: Secret::from("pw"),
+            expires_at: now + Duration::from_secs(60),
+        };
+        assert!(!token.usable_at(now));
+        assert!(Token::new(Secret::from("pw"), None).usable_at(now));
+    }
+
+    #[test]
+    fn refreshes_ahead_of_expiry() {
+        let now = SystemTime::now();
+        let token = Token {
+            password: Secret::from("pw"),
+            expires_at: now + Duration::from_secs(3 * 60 * 60),
+        };
+        assert_eq!(token.refresh_in(now), Duration::from_secs(2 * 60 * 60));
+        let token = Token {
+            password: Secret::from("pw"),
+            expires_at: now + Duration::from_secs(60),
+        };
+        assert_eq!(token.refresh_in(now), REFRESH_RETRY);
+        let token = Token {
+            password: Secret::from("pw"),
+            expires_at: now + REFRESH_MARGIN + Duration::from_secs(1),
+        };
+        assert_eq!(token.refresh_in(now), REFRESH_RETRY);
+    }
+}
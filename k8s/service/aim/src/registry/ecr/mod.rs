@@ -1,57 +1,37 @@
+mod credentials;
+mod repository;
+
 use crate::env;
-use crate::env::Secret;
+use crate::registry::manifest::{self, ConnectorLabels, Manifest, Platform};
 use crate::registry::Image;
+use aws_sdk_ecr::model::{ImageFailure, ImageFailureCode, ImageIdentifier};
+use aws_sdk_ecr::Client;
 use error::*;
-use os::cmd;
 use result::Result;
-use serde::Deserialize;
+use secret::Secret;
 use std::fmt::{Display, Formatter};
+use tokio::sync::OnceCell;
 
-/// `aws` is a convenience macro for executing the [AWS CLI v2 Tooling](https://aws.amazon.com/cli/).
-///
-/// This macro returns a future of the output returned by [cmd](os::cmd) with the command `aws` pre-filled in.
-///
-/// ```ignore
-/// let password = aws!("ecr", "get-login-password").await.unwrap();
-/// ```
-#[macro_export]
-macro_rules! aws {
-    ($($args:expr),*) => {
-        cmd!("aws" $(,$args)*)
-    }
-}
+pub use credentials::{invalidate as invalidate_password, refresh_proactively};
+pub use repository::bootstrap;
 
-/// `ecr` is a convenience macro for executing the
-/// [AWS CLI v2 Tooling ECR Subcommand](https://awscli.amazonaws.com/v2/documentation/api/latest/reference/ecr/index.html).
-///
-/// This macro returns a future of the output returned by [cmd](os::cmd) with the command `aws ecr` pre-filled in.
-///
-/// ```ignore
-/// let password = ecr!("get-login-password").await.unwrap();
-/// ```
-#[macro_export]
-macro_rules! ecr {
-    ($($args:expr),*) => {
-        cmd!("aws", "ecr" $(,$args)*)
-    }
+lazy_static! {
+    static ref CLIENT: OnceCell<Client> = OnceCell::new();
 }
 
-/// An EcrUninstall is the deserialization target of the JSON returned
-/// by the command `aws ecr batch-delete-image`.
+/// Returns the shared [AWS ECR client](aws_sdk_ecr::Client).
 ///
-/// For more information on this command, please see
-/// [ecr::batch-delete-image](https://awscli.amazonaws.com/v2/documentation/api/latest/reference/ecr/batch-delete-image.html).
-#[derive(Deserialize, Debug, Eq, PartialEq)]
-struct EcrUninstall {
-    #[serde(alias = "imageIds")]
-    #[allow(unused)]
-    image_ids: Vec<EcrImage>,
-    failures: Vec<EcrUninstallFailure>,
+/// The client is configured from the environment (that is, `AWS_REGION`, `AWS_ACCESS_KEY_ID`, and
+/// `AWS_SECRET_ACCESS_KEY`) the first time that it is requested and is reused thereafter.
+async fn client() -> &'static Client {
+    CLIENT
+        .get_or_init(|| async { Client::new(&aws_config::load_from_env().await) })
+        .await
 }
 
-/// Uninstalls the given tag from ECR. This is accomplished by running the
-/// [ecr::batch-delete-image](https://awscli.amazonaws.com/v2/documentation/api/latest/reference/ecr/batch-delete-image.html)
-/// command.
+/// Uninstalls the given tag from ECR. This is accomplished by calling the
+/// [BatchDeleteImage](https://docs.aws.amazon.com/AmazonECR/latest/APIReference/API_BatchDeleteImage.html)
+/// API.
 ///
 /// If multiple tags are assigned to the same digest, then only the tag submitted will be deleted
 /// from ECR - the remaining tags are left in place. Upon deletion of the final tag that was
@@ -59,45 +39,85 @@ struct EcrUninstall {
 ///
 /// If the provided tag was not found within ECR, then this procedure will silently succeed.
 pub async fn uninstall(tag: String) -> Result<()> {
-    let target = format!("imageTag={}", tag);
-    let repository = env::repository();
-    let result: EcrUninstall = serde_json::from_str(
-        &ecr!(
-            "batch-delete-image",
-            "--repository-name",
-            &repository,
-            "--image-ids",
-            &target
-        )
+    let result = client()
+        .await
+        .batch_delete_image()
+        .repository_name(env::repository())
+        .image_ids(ImageIdentifier::builder().image_tag(tag).build())
+        .send()
         .await
         .map_err(|error| UninstallCommandError {
-            error: format!("{}", error).into(),
-        })?,
-    )
-    .map_err(EcrUninstallSerdeError::from)?;
-    match result.failures.as_slice() {
-        [failure, ..] => match failure.failure_code.as_str() {
-            // If there is no such image to delete then we consider that okay
-            // since we were looking to delete it anyways.
-            "ImageNotFound" => Ok(()),
-            // Otherwise, something bad actually happened.
-            _ => Err(EcrUninstallError::from(failure.clone()).into()),
-        },
-        _ => Ok(()),
+            error: error.to_string().into(),
+        })?;
+    check_uninstall(result.failures.unwrap_or_default())
+}
+
+/// Inspects the failures reported by a
+/// [BatchDeleteImage](https://docs.aws.amazon.com/AmazonECR/latest/APIReference/API_BatchDeleteImage.html)
+/// call, returning the first failure that is NOT simply the image already being gone.
+fn check_uninstall(failures: Vec<ImageFailure>) -> Result<()> {
+    match failures.into_iter().next() {
+        // If there is no such image to delete then we consider that okay
+        // since we were looking to delete it anyways.
+        Some(failure) if failure.failure_code == Some(ImageFailureCode::ImageNotFound) => Ok(()),
+        // Otherwise, something bad actually happened.
+        Some(failure) => Err(EcrUninstallError::from(EcrUninstallFailure::from(failure)).into()),
+        None => Ok(()),
     }
 }
 
 /// Returns the current ECR password associated with the globably configured account.
 ///
-/// We say "current" because ECR is configured to rotate this password on a regular basis. As such
-/// clients to this procedure SHOULD NOT call this function upfront and cache the result as the
-/// result is unlikely to be valid for an extended period of time. Instead, clients should
-/// call this procedure each time a password is required.
+/// We say "current" because ECR is configured to rotate this password on a regular basis. The
+/// password is [cached](credentials::password) until shortly before its expiry (and refreshed
+/// ahead of it, if [refresh_proactively](refresh_proactively) is running), so callers SHOULD
+/// call this procedure each time a password is required rather than holding on to the result.
 pub async fn get_password() -> Result<Secret> {
-    Ok(ecr!("get-login-password")
+    credentials::password().await
+}
+
+/// Fetches a brand new password from ECR, along with its expiry.
+async fn authorize() -> Result<credentials::Token> {
+    let result = client()
         .await
-        .map_err(|err| GetPasswordError::from(StringError::from(err)))?
-        .into())
+        .get_authorization_token()
+        .send()
+        .await
+        .map_err(|err| GetPasswordError::from(StringError::from(err.to_string())))?;
+    let data = result
+        .authorization_data
+        .unwrap_or_default()
+        .into_iter()
+        .find(|data| data.authorization_token.is_some())
+        .ok_or_else(|| {
+            GetPasswordError::from(StringError::from(
+                "ECR did not return any authorization data",
+            ))
+        })?;
+    // The find above guarantees that the token is present.
+    let password = password(data.authorization_token.as_deref().unwrap())?;
+    Ok(credentials::Token::new(
+        password,
+        data.expires_at.map(|expires_at| expires_at.epoch_seconds()),
+    ))
+}
+
+/// Extracts the password from an ECR authorization token, which is the base64 encoding
+/// of `<username>:<password>`.
+fn password(token: &str) -> std::result::Result<Secret, GetPasswordError> {
+    let decoded = base64::decode(token)
+        .ok()
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .ok_or_else(|| {
+            StringError::from("ECR returned an authorization token that is not base64")
+        })?;
+    match decoded.split_once(':') {
+        Some((_, password)) => Ok(Secret::from(password)),
+        None => Err(StringError::from(
+            "ECR returned an authorization token that is not of the form <username>:<password>",
+        )
+        .into()),
+    }
 }
 
 // Returning a `(Username, Secrete)` is clearer than returning a `(String, String)`.
@@ -110,67 +130,51 @@ pub async fn get_credentials() -> Result<(Username, Secret)> {
     Ok((env::aws_username() as Username, get_password().await?))
 }
 
-/// An `EcrImage` is the deserialization target of the JSON returned by the
-/// [AWS ECR ClI Tooling](https://awscli.amazonaws.com/v2/documentation/api/latest/reference/ecr/index.html#cli-aws-ecr).
-#[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
-struct EcrImage {
-    #[serde(alias = "imageDigest")]
-    image_digest: String,
-    #[serde(alias = "imageTag")]
-    image_tag: String,
-}
-
-/// The [Display](std::fmt::Display) for an `EcrImage` is the fully qualified reference
-/// (that is, `<registry>/<repository>:<tag>`) followed by the digest
-/// of the image.
-///
-/// This format should typically not be used for anything other than logging and
-/// strings displayed to the end user.
-impl Display for EcrImage {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let reference = format!(
-            "{}/{}:{}, {}",
-            env::registry(),
-            env::repository(),
-            self.image_tag,
-            self.image_digest
-        );
-        f.write_str(&reference)
-    }
-}
-
 /// Converts ECR's representation of a `(tag, digest)` pairing into our own representation.
-impl From<EcrImage> for Image {
-    fn from(image: EcrImage) -> Self {
-        Image {
-            tag: image.image_tag,
-            digest: image.image_digest,
-        }
+///
+/// Untagged images (which ECR happily lists) are not addressable by the AIM and are thus
+/// converted into `None`.
+fn image(identifier: ImageIdentifier) -> Option<Image> {
+    match identifier {
+        ImageIdentifier {
+            image_tag: Some(tag),
+            image_digest: Some(digest),
+            ..
+        } => Some(Image {
+            tag,
+            digest,
+            labels: ConnectorLabels::default(),
+        }),
+        _ => None,
     }
 }
 
-#[derive(Deserialize, Debug, Eq, PartialEq)]
-struct EcrListImages {
-    #[serde(alias = "imageIds")]
-    image_ids: Vec<EcrImage>,
-}
-
 /// Lists all images (if any) currently in the configured ECR repository. This is accomplished
-/// by running the [list-images](https://awscli.amazonaws.com/v2/documentation/api/latest/reference/ecr/list-images.html)
-/// command.
+/// by calling the [ListImages](https://docs.aws.amazon.com/AmazonECR/latest/APIReference/API_ListImages.html)
+/// API, following every page of results.
 pub async fn list() -> Result<Vec<Image>> {
-    let repository = env::repository();
-    let images: EcrListImages = serde_json::from_str(
-        &ecr!(
-            "list-images",
-            "--no-paginate",
-            "--repository-name",
-            &repository
-        )
-        .await?,
-    )
-    .map_err(EcrImageSerdeError::from)?;
-    Ok(images.image_ids.into_iter().map(EcrImage::into).collect())
+    let mut images = vec![];
+    let mut next_token = None;
+    loop {
+        let page = client()
+            .await
+            .list_images()
+            .repository_name(env::repository())
+            .set_next_token(next_token)
+            .send()
+            .await
+            .map_err(|err| ListImagesError::from(StringError::from(err.to_string())))?;
+        images.extend(
+            page.image_ids
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(image),
+        );
+        next_token = page.next_token;
+        if next_token.is_none() {
+            return Ok(images);
+        }
+    }
 }
 
 /// Retrieves the given tag from the configured ECR repository. If no such
@@ -182,48 +186,192 @@ pub async fn get<T: AsRef<str>>(tag: T) -> Result<Option<Image>> {
         .find(|image| image.tag.eq(tag.as_ref())))
 }
 
+/// Returns the platforms of the given tag in the configured ECR repository. If no such tag
+/// exists, then `Ok(None)` is returned.
+///
+/// A manifest list reports its platforms directly. For a single-platform manifest, the image's
+/// config blob is downloaded (via a pre-signed URL) in order to read its platform.
+pub async fn platforms<T: AsRef<str>>(tag: T) -> Result<Option<Vec<Platform>>> {
+    let manifest = match manifest(tag).await? {
+        Some(manifest) => manifest,
+        None => return Ok(None),
+    };
+    if let Some(platforms) = manifest.platforms() {
+        return Ok(Some(platforms));
+    }
+    let config = blob(manifest.config().unwrap()).await?;
+    Ok(Some(vec![manifest::parse_config(config)?]))
+}
+
+/// Downloads the blob of the given digest (via a pre-signed URL) from the configured ECR repository.
+pub async fn blob(digest: &str) -> Result<Vec<u8>> {
+    let layer = client()
+        .await
+        .get_download_url_for_layer()
+        .repository_name(env::repository())
+        .layer_digest(digest)
+        .send()
+        .await
+        .map_err(|err| EcrManifestError::from(StringError::from(err.to_string())))?;
+    let url = layer.download_url.ok_or_else(|| {
+        EcrManifestError::from(StringError::from("ECR did not return a download URL"))
+    })?;
+    Ok(reqwest::get(&url)
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|err| ConfigDownloadError::from(StringError::from(err.to_string())))?
+        .bytes()
+        .await
+        .map_err(|err| ConfigDownloadError::from(StringError::from(err.to_string())))?
+        .to_vec())
+}
+
+/// Returns the manifest of the given reference (either a tag or, for the children of a manifest
+/// list, a digest) in the configured ECR repository. If no such image exists, then `Ok(None)` is
+/// returned.
+pub async fn manifest<R: AsRef<str>>(reference: R) -> Result<Option<Manifest>> {
+    match raw_manifest(reference).await? {
+        Some((manifest, _)) => Ok(Some(Manifest::parse(&manifest)?)),
+        None => Ok(None),
+    }
+}
+
+/// Returns the manifest of the given reference exactly as ECR stores it, alongside its media type.
+async fn raw_manifest<R: AsRef<str>>(reference: R) -> Result<Option<(String, Option<String>)>> {
+    let reference = reference.as_ref();
+    let identifier = if reference.starts_with("sha256:") {
+        ImageIdentifier::builder().image_digest(reference).build()
+    } else {
+        ImageIdentifier::builder().image_tag(reference).build()
+    };
+    let images = client()
+        .await
+        .batch_get_image()
+        .repository_name(env::repository())
+        .image_ids(identifier)
+        .accepted_media_types("application/vnd.docker.distribution.manifest.list.v2+json")
+        .accepted_media_types("application/vnd.oci.image.index.v1+json")
+        .accepted_media_types("application/vnd.docker.distribution.manifest.v2+json")
+        .accepted_media_types("application/vnd.oci.image.manifest.v1+json")
+        .send()
+        .await
+        .map_err(|err| EcrManifestError::from(StringError::from(err.to_string())))?;
+    Ok(images
+        .images
+        .unwrap_or_default()
+        .into_iter()
+        .find_map(|image| {
+            let media_type = image.image_manifest_media_type;
+            image.image_manifest.map(|manifest| (manifest, media_type))
+        }))
+}
+
+/// Tags the image of the given tag with the given alias as well. No image is uploaded, rather its
+/// manifest is put anew under the alias via ECR's
+/// [PutImage](https://docs.aws.amazon.com/AmazonECR/latest/APIReference/API_PutImage.html) API.
+/// ECR keeps the manifest byte for byte, so the alias refers to the very same digest.
+pub async fn retag(tag: &str, alias: &str) -> Result<()> {
+    let (manifest, media_type) = raw_manifest(tag).await?.ok_or_else(|| {
+        EcrManifestError::from(StringError::from(format!(
+            "the manifest of {} has vanished",
+            tag
+        )))
+    })?;
+    let mut request = client()
+        .await
+        .put_image()
+        .repository_name(env::repository())
+        .image_manifest(manifest)
+        .image_tag(alias);
+    if let Some(media_type) = media_type {
+        request = request.image_manifest_media_type(media_type);
+    }
+    request
+        .send()
+        .await
+        .map_err(|err| PutImageError::from(StringError::from(err.to_string())))?;
+    Ok(())
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error(
+    "A failure occurred while retrieving an image manifest from AWS ECR's BatchGetImage or \
+GetDownloadUrlForLayer APIs. Please see \
+https://docs.aws.amazon.com/AmazonECR/latest/APIReference/API_BatchGetImage.html and \
+https://docs.aws.amazon.com/AmazonECR/latest/APIReference/API_GetDownloadUrlForLayer.html"
+)]
+#[error_code("AIM-1200")]
+struct EcrManifestError {
+    #[from]
+    error: StringError,
+}
+
 #[derive(Error, AcmError, Kind, HttpCode, Debug)]
 #[code(Status::InternalServerError)]
 #[error(
-    "A failure occurred while deserializing the JSON representation for the response returned by \
-AWS ECR's deletion endpoint. We expected a data structure similar to that documented in \
-https://awscli.amazonaws.com/v2/documentation/api/latest/reference/ecr/batch-delete-image.html"
+    "A failure occurred while tagging an image anew via AWS ECR's PutImage API. Please see \
+https://docs.aws.amazon.com/AmazonECR/latest/APIReference/API_PutImage.html"
 )]
-struct EcrUninstallSerdeError {
+#[error_code("AIM-1204")]
+struct PutImageError {
+    #[from]
+    error: StringError,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadGateway)]
+#[error("Failed to download an image's config blob from the AWS Elastic Container Registry.")]
+#[error_code("AIM-1201")]
+struct ConfigDownloadError {
     #[from]
-    error: serde_json::Error,
+    cause: StringError,
 }
 
 #[derive(Error, AcmError, Kind, HttpCode, Debug)]
 #[code(Status::InternalServerError)]
 #[error(
-    "A failure occurred while deserializing the JSON representation for the response returned by \
-AWS ECR's list-images endpoint. We expected a data structure similar to that documented in \
-https://awscli.amazonaws.com/v2/documentation/api/latest/reference/ecr/list-images.html"
+    "A failure occurred while listing the images in AWS ECR via its ListImages API. Please see \
+https://docs.aws.amazon.com/AmazonECR/latest/APIReference/API_ListImages.html"
 )]
-struct EcrImageSerdeError {
+struct ListImagesError {
     #[from]
-    error: serde_json::Error,
+    error: StringError,
 }
 
-#[derive(Error, AcmError, Kind, HttpCode, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[derive(Error, AcmError, Kind, HttpCode, Debug, Eq, PartialEq, Clone)]
 #[code(Status::BadRequest)]
 #[error(
     "ECR reported the failure code '{failure_code}' when attempting to uninstall '{image_id}'. \
 The given reason was '{failure_reason}'."
 )]
 struct EcrUninstallFailure {
-    #[serde(alias = "imageId")]
     image_id: EcrFailedImageUninstall,
-    #[serde(alias = "failureCode")]
     failure_code: String,
-    #[serde(alias = "failureReason")]
     failure_reason: String,
 }
 
-#[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
+/// Converts ECR's report of a failed deletion into our own representation.
+impl From<ImageFailure> for EcrUninstallFailure {
+    fn from(failure: ImageFailure) -> Self {
+        EcrUninstallFailure {
+            image_id: EcrFailedImageUninstall {
+                image_tag: failure
+                    .image_id
+                    .and_then(|id| id.image_tag)
+                    .unwrap_or_default(),
+            },
+            failure_code: failure
+                .failure_code
+                .map(|code| code.as_str().to_string())
+                .unwrap_or_default(),
+            failure_reason: failure.failure_reason.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
 struct EcrFailedImageUninstall {
-    #[serde(alias = "imageTag")]
     image_tag: String,
 }
 
@@ -273,102 +421,59 @@ struct GetPasswordError {
 mod tests {
     use super::*;
 
+    fn identifier(tag: Option<&str>, digest: Option<&str>) -> ImageIdentifier {
+        ImageIdentifier::builder()
+            .set_image_tag(tag.map(str::to_string))
+            .set_image_digest(digest.map(str::to_string))
+            .build()
+    }
+
+    fn failure(code: ImageFailureCode) -> ImageFailure {
+        ImageFailure::builder()
+            .image_id(identifier(Some("precise"), None))
+            .failure_code(code)
+            .failure_reason("Requested image not found")
+            .build()
+    }
+
     #[test]
-    fn deserialize_ecr_image() {
-        // Picked up from https://awscli.amazonaws.com/v2/documentation/api/latest/reference/ecr/list-images.html
-        let response = r#"{
-    "imageIds": [
-        {
-            "imageDigest": "sha256:99c6fb4377e9a420a1eb3b410a951c9f464eff3b7dbc76c65e434e39b94b6570",
-            "imageTag": "v1.13.8"
-        },
-        {
-            "imageDigest": "sha256:99c6fb4377e9a420a1eb3b410a951c9f464eff3b7dbc76c65e434e39b94b6570",
-            "imageTag": "v1.13.7"
-        },
-        {
-            "imageDigest": "sha256:4a1c6567c38904384ebc64e35b7eeddd8451110c299e3368d2210066487d97e5",
-            "imageTag": "v1.13.6"
-        }
-    ]
-}"#;
-        let got: EcrListImages = serde_json::from_str(response).unwrap();
-        let want = EcrListImages {
-            image_ids: vec![
-                EcrImage {
-                    image_digest:
-                        "sha256:99c6fb4377e9a420a1eb3b410a951c9f464eff3b7dbc76c65e434e39b94b6570"
-                            .to_string(),
-                    image_tag: "v1.13.8".to_string(),
-                },
-                EcrImage {
-                    image_digest:
-                        "sha256:99c6fb4377e9a420a1eb3b410a951c9f464eff3b7dbc76c65e434e39b94b6570"
-                            .to_string(),
-                    image_tag: "v1.13.7".to_string(),
-                },
-                EcrImage {
-                    image_digest:
-                        "sha256:4a1c6567c38904384ebc64e35b7eeddd8451110c299e3368d2210066487d97e5"
-                            .to_string(),
-                    image_tag: "v1.13.6".to_string(),
-                },
-            ],
-        };
-        assert_eq!(got, want);
+    fn untagged_images_are_skipped() {
+        let digest = "sha256:99c6fb4377e9a420a1eb3b410a951c9f464eff3b7dbc76c65e434e39b94b6570";
+        let got = image(identifier(Some("v1.13.8"), Some(digest))).unwrap();
+        assert_eq!(got.tag, "v1.13.8");
+        assert_eq!(got.digest, digest);
+        assert!(image(identifier(None, Some(digest))).is_none());
     }
 
     #[test]
-    fn deserialize_uninstall_image() {
-        // Picked up from https://awscli.amazonaws.com/v2/documentation/api/latest/reference/ecr/batch-delete-image.html
-        let response = r#"{
-    "failures": [],
-    "imageIds": [
-        {
-            "imageTag": "precise",
-            "imageDigest": "sha256:19665f1e6d1e504117a1743c0a3d3753086354a38375961f2e665416ef4b1b2f"
-        }
-    ]
-}"#;
-        let got: EcrUninstall = serde_json::from_str(response).unwrap();
-        let want = EcrUninstall {
-            image_ids: vec![EcrImage {
-                image_tag: "precise".to_string(),
-                image_digest:
-                    "sha256:19665f1e6d1e504117a1743c0a3d3753086354a38375961f2e665416ef4b1b2f"
-                        .to_string(),
-            }],
-            failures: vec![],
-        };
-        assert_eq!(got, want);
+    fn uninstall_tolerates_missing_images() {
+        assert!(check_uninstall(vec![]).is_ok());
+        assert!(check_uninstall(vec![failure(ImageFailureCode::ImageNotFound)]).is_ok());
     }
 
     #[test]
-    fn deserialize_uninstall_image_error() {
-        let response = r#"{
-    "imageIds": [],
-    "failures": [
-        {
-            "imageId": {
-                "imageTag": "precise"
-            },
-            "failureCode": "ImageNotFound",
-            "failureReason": "Requested image not found"
-        }
-    ]
-}
-"#;
-        let got: EcrUninstall = serde_json::from_str(response).unwrap();
-        let want = EcrUninstall {
-            image_ids: vec![],
-            failures: vec![EcrUninstallFailure {
+    fn uninstall_reports_failures() {
+        let err = check_uninstall(vec![failure(ImageFailureCode::KmsError)]).unwrap_err();
+        assert!(err.to_string().contains("deletion"));
+        assert_eq!(
+            EcrUninstallFailure::from(failure(ImageFailureCode::KmsError)),
+            EcrUninstallFailure {
                 image_id: EcrFailedImageUninstall {
-                    image_tag: "precise".to_string(),
+                    image_tag: "precise".to_string()
                 },
-                failure_code: "ImageNotFound".to_string(),
+                failure_code: "KmsError".to_string(),
                 failure_reason: "Requested image not found".to_string(),
-            }],
-        };
-        assert_eq!(got, want);
+            }
+        );
+    }
+
+    #[test]
+    fn decode_password() {
+        // base64("AWS:hunter2")
+        let secret = password("QVdTOmh1bnRlcjI=").unwrap();
+        assert_eq!(secret.raw_secret(), "hunter2");
+        assert!(password("not base64!").is_err());
+        // base64("hunter2")
+        assert!(password("aHVudGVyMg==").is_err());
     }
 }
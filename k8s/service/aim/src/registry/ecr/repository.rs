@@ -0,0 +1,288 @@
+use super::client;
+use crate::env;
+use aws_sdk_ecr::error::{
+    BatchDeleteImageError, BatchGetImageError, CreateRepositoryError, DescribeRepositoriesError,
+    GetAuthorizationTokenError, InitiateLayerUploadError, ListImagesError, PutLifecyclePolicyError,
+};
+use aws_sdk_ecr::model::ImageIdentifier;
+use aws_sdk_ecr::SdkError;
+use error::*;
+use result::Result;
+use serde_json::json;
+
+/// A tag that is never installed (AIM tags are always [RFC 1035 labels](names::rfc1035_label)
+/// and thus never contain an underscore). It is used to exercise our permissions to read and
+/// delete images without affecting any real image.
+const PERMISSION_CHECK_TAG: &str = "ocf_permission_check";
+
+/// Prepares the configured ECR repository for use by the AIM.
+///
+/// 1. The repository is created if it does not already exist.
+/// 2. The configured [max image count](env::ecr_max_images) and [max image age](env::ecr_max_image_age_days)
+///    are applied as the repository's [lifecycle policy](https://docs.aws.amazon.com/AmazonECR/latest/userguide/LifecyclePolicies.html).
+///    If neither is configured, then any existing lifecycle policy is left untouched.
+/// 3. Every permission that the AIM requires of the repository (pulling, pushing, and deleting) is
+///    exercised so that a missing permission is reported now, by name, rather than surfacing as
+///    an opaque failure in the middle of a user's install.
+pub async fn bootstrap() -> Result<()> {
+    let client = client().await;
+    let repository = env::repository();
+    match client
+        .describe_repositories()
+        .repository_names(&repository)
+        .send()
+        .await
+    {
+        Ok(_) => (),
+        Err(err) if code(&err) == Some("RepositoryNotFoundException") => {
+            info!(
+                "The ECR repository {} does not exist, creating it now.",
+                term_colors::highlight(repository.clone())
+            );
+            client
+                .create_repository()
+                .repository_name(&repository)
+                .send()
+                .await
+                .map_err(|err| failure("ecr:CreateRepository", err))?;
+        }
+        Err(err) => return Err(failure("ecr:DescribeRepositories", err)),
+    };
+    if let Some(policy) = lifecycle_policy(env::ecr_max_images(), env::ecr_max_image_age_days()) {
+        client
+            .put_lifecycle_policy()
+            .repository_name(&repository)
+            .lifecycle_policy_text(policy)
+            .send()
+            .await
+            .map_err(|err| failure("ecr:PutLifecyclePolicy", err))?;
+    }
+    client
+        .get_authorization_token()
+        .send()
+        .await
+        .map_err(|err| failure("ecr:GetAuthorizationToken", err))?;
+    client
+        .list_images()
+        .repository_name(&repository)
+        .max_results(1)
+        .send()
+        .await
+        .map_err(|err| failure("ecr:ListImages", err))?;
+    client
+        .batch_get_image()
+        .repository_name(&repository)
+        .image_ids(
+            ImageIdentifier::builder()
+                .image_tag(PERMISSION_CHECK_TAG)
+                .build(),
+        )
+        .send()
+        .await
+        .map_err(|err| failure("ecr:BatchGetImage", err))?;
+    // Upload sessions that are never completed simply expire, so this leaves nothing behind.
+    client
+        .initiate_layer_upload()
+        .repository_name(&repository)
+        .send()
+        .await
+        .map_err(|err| failure("ecr:InitiateLayerUpload", err))?;
+    client
+        .batch_delete_image()
+        .repository_name(&repository)
+        .image_ids(
+            ImageIdentifier::builder()
+                .image_tag(PERMISSION_CHECK_TAG)
+                .build(),
+        )
+        .send()
+        .await
+        .map_err(|err| failure("ecr:BatchDeleteImage", err))?;
+    Ok(())
+}
+
+/// Renders the lifecycle policy that enforces the given limits, or `None` if there are no limits.
+///
+/// ECR only allows a single rule that selects `any` image, and it must be evaluated last. So when
+/// both limits are configured the age limit selects tagged images by every prefix that an
+/// [AIM tag](names::rfc1035_label) may begin with.
+fn lifecycle_policy(max_images: Option<usize>, max_age_days: Option<usize>) -> Option<String> {
+    let mut rules = vec![];
+    if let Some(days) = max_age_days {
+        let selection = match max_images {
+            Some(_) => json!({
+                "tagStatus": "tagged",
+                "tagPrefixList": ('a'..='z').map(String::from).collect::<Vec<String>>(),
+                "countType": "sinceImagePushed",
+                "countUnit": "days",
+                "countNumber": days
+            }),
+            None => json!({
+                "tagStatus": "any",
+                "countType": "sinceImagePushed",
+                "countUnit": "days",
+                "countNumber": days
+            }),
+        };
+        rules.push(json!({
+            "rulePriority": rules.len() + 1,
+            "description": format!("Expire images pushed more than {} days ago", days),
+            "selection": selection,
+            "action": {"type": "expire"}
+        }));
+    }
+    if let Some(count) = max_images {
+        rules.push(json!({
+            "rulePriority": rules.len() + 1,
+            "description": format!("Keep at most {} images", count),
+            "selection": {
+                "tagStatus": "any",
+                "countType": "imageCountMoreThan",
+                "countNumber": count
+            },
+            "action": {"type": "expire"}
+        }));
+    }
+    if rules.is_empty() {
+        None
+    } else {
+        Some(json!({ "rules": rules }).to_string())
+    }
+}
+
+/// ServiceCode exposes the error code (e.g. `AccessDeniedException`) that ECR responded with.
+trait ServiceCode {
+    fn service_code(&self) -> Option<&str>;
+}
+
+macro_rules! service_code {
+    ($($error:ty),*) => {
+        $(
+            impl ServiceCode for $error {
+                fn service_code(&self) -> Option<&str> {
+                    self.code()
+                }
+            }
+        )*
+    };
+}
+
+service_code!(
+    DescribeRepositoriesError,
+    CreateRepositoryError,
+    PutLifecyclePolicyError,
+    GetAuthorizationTokenError,
+    ListImagesError,
+    BatchGetImageError,
+    InitiateLayerUploadError,
+    BatchDeleteImageError
+);
+
+fn code<E: ServiceCode>(err: &SdkError<E>) -> Option<&str> {
+    match err {
+        SdkError::ServiceError { err, .. } => err.service_code(),
+        _ => None,
+    }
+}
+
+/// Maps a failed call into either an [EcrPermissionDenied](EcrPermissionDenied), which names the
+/// missing permission, or an [EcrBootstrapError](EcrBootstrapError) for anything else.
+fn failure<E: ServiceCode + std::error::Error + 'static>(
+    permission: &'static str,
+    err: SdkError<E>,
+) -> Box<dyn AcmError> {
+    match code(&err) {
+        Some("AccessDeniedException") => EcrPermissionDenied {
+            permission,
+            repository: env::repository(),
+            username: env::aws_username(),
+        }
+        .into(),
+        _ => EcrBootstrapError {
+            permission,
+            repository: env::repository(),
+            cause: err.to_string(),
+        }
+        .into(),
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::Forbidden)]
+#[error(
+    "The IAM user '{username}' is not permitted to call {permission} on the ECR repository \
+'{repository}'. The AIM requires permission to create, pull from, push to, and delete from its \
+repository. Please grant the missing permission to the IAM user (the AmazonEC2ContainerRegistryPowerUser \
+managed policy, along with ecr:CreateRepository and ecr:PutLifecyclePolicy, suffices) and restart the AIM."
+)]
+#[error_code("AIM-1202")]
+struct EcrPermissionDenied {
+    permission: &'static str,
+    repository: String,
+    username: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error(
+    "Failed to call {permission} while preparing the ECR repository '{repository}', {cause}. \
+Please check that the configured REGISTRY, REPOSITORY, and AWS_REGION agree with one another."
+)]
+#[error_code("AIM-1203")]
+struct EcrBootstrapError {
+    permission: &'static str,
+    repository: String,
+    cause: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_limits() {
+        assert_eq!(lifecycle_policy(None, None), None);
+    }
+
+    #[test]
+    fn max_images() {
+        let policy: serde_json::Value =
+            serde_json::from_str(&lifecycle_policy(Some(50), None).unwrap()).unwrap();
+        let rules = policy["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0]["rulePriority"], 1);
+        assert_eq!(rules[0]["selection"]["tagStatus"], "any");
+        assert_eq!(rules[0]["selection"]["countType"], "imageCountMoreThan");
+        assert_eq!(rules[0]["selection"]["countNumber"], 50);
+    }
+
+    #[test]
+    fn max_age() {
+        let policy: serde_json::Value =
+            serde_json::from_str(&lifecycle_policy(None, Some(30)).unwrap()).unwrap();
+        let rules = policy["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0]["selection"]["tagStatus"], "any");
+        assert_eq!(rules[0]["selection"]["countType"], "sinceImagePushed");
+        assert_eq!(rules[0]["selection"]["countNumber"], 30);
+    }
+
+    #[test]
+    fn both_limits_select_any_only_once() {
+        let policy: serde_json::Value =
+            serde_json::from_str(&lifecycle_policy(Some(50), Some(30)).unwrap()).unwrap();
+        let rules = policy["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0]["rulePriority"], 1);
+        assert_eq!(rules[0]["selection"]["tagStatus"], "tagged");
+        assert_eq!(
+            rules[0]["selection"]["tagPrefixList"]
+                .as_array()
+                .unwrap()
+                .len(),
+            26
+        );
+        assert_eq!(rules[1]["rulePriority"], 2);
+        assert_eq!(rules[1]["selection"]["tagStatus"], "any");
+    }
+}
@@ -0,0 +1,451 @@
+use crate::deprecation::Deprecation;
+use error::*;
+use result::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// The media types that the AIM accepts when retrieving a manifest from a registry. Both
+/// single-platform manifests and multi-platform manifest lists (indexes) are accepted.
+pub const ACCEPTED_MANIFESTS: &str = "application/vnd.docker.distribution.manifest.list.v2+json, \
+application/vnd.oci.image.index.v1+json, \
+application/vnd.docker.distribution.manifest.v2+json, \
+application/vnd.oci.image.manifest.v1+json";
+
+/// A Platform is the operating system and CPU architecture that an image was built for.
+///
+/// The [Display](std::fmt::Display) of a Platform follows the familiar `os/architecture[/variant]`
+/// notation (e.g. `linux/arm64/v8`).
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Platform {
+    pub os: String,
+    pub architecture: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.variant.as_ref() {
+            Some(variant) => write!(f, "{}/{}/{}", self.os, self.architecture, variant),
+            None => write!(f, "{}/{}", self.os, self.architecture),
+        }
+    }
+}
+
+/// An Inspection is what is known about an installed image beyond its `tag:digest` pairing.
+#[derive(Serialize, Debug, Kind)]
+pub struct Inspection {
+    pub tag: String,
+    pub digest: String,
+    /// Every platform that the image may be run on. This list is empty if the
+    /// registry did not report the image's platform.
+    pub platforms: Vec<Platform>,
+    /// The image's [deprecation](crate::deprecation::Deprecation), if it has been deprecated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecation: Option<Deprecation>,
+    /// How the image runs, as read out of its config blob.
+    pub config: ImageConfig,
+}
+
+/// An ImageConfig is what an image's config blob says of how it runs, alongside the layers that
+/// its manifest lists. The config of a multi-platform image is that of its first platform.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct ImageConfig {
+    #[serde(default)]
+    pub entrypoint: Vec<String>,
+    #[serde(default)]
+    pub cmd: Vec<String>,
+    /// Every port that the image exposes, in the form of `<port>/<protocol>` (e.g. `8080/tcp`).
+    #[serde(default)]
+    pub exposed_ports: Vec<String>,
+    #[serde(default)]
+    pub env: Vec<String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// The number of layers.
+    #[serde(default)]
+    pub layers: usize,
+    /// The size, in bytes, of the config blob and every layer.
+    #[serde(default)]
+    pub size: u64,
+    /// When the image was built (an RFC 3339 timestamp), should it say.
+    #[serde(default)]
+    pub created: Option<String>,
+}
+
+/// A Manifest is the deserialization target of the manifest returned by a registry for a given tag.
+///
+/// A manifest list describes its platforms directly. A single-platform manifest, however, only
+/// refers to a config blob that must be retrieved (and parsed as a [Config](Config)) in order to
+/// learn its platform.
+#[derive(Deserialize, Debug, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum Manifest {
+    List {
+        manifests: Vec<ManifestEntry>,
+    },
+    Image {
+        config: Descriptor,
+        #[serde(default)]
+        layers: Vec<Descriptor>,
+    },
+}
+
+#[derive(Deserialize, Debug, Eq, PartialEq)]
+pub struct ManifestEntry {
+    pub digest: String,
+    pub platform: Option<Platform>,
+}
+
+#[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Descriptor {
+    pub digest: String,
+    /// The size of the blob, in bytes.
+    #[serde(default)]
+    pub size: u64,
+}
+
+/// A Config is the deserialization target of an image's config blob. Only the fields describing
+/// the platform are of interest.
+#[derive(Deserialize, Debug, Eq, PartialEq)]
+pub struct Config {
+    pub os: String,
+    pub architecture: String,
+    #[serde(default)]
+    pub variant: Option<String>,
+}
+
+/// The [OCI annotation](https://github.com/opencontainers/image-spec/blob/main/annotations.md)
+/// whose label names the connector within an image.
+pub const NAME_LABEL: &str = "org.opencontainers.image.title";
+
+/// The OCI annotation whose label is the version of the connector within an image.
+pub const VERSION_LABEL: &str = "org.opencontainers.image.version";
+
+/// The OCI annotation whose label names who distributes the connector within an image.
+pub const VENDOR_LABEL: &str = "org.opencontainers.image.vendor";
+
+/// ConnectorLabels are the human-meaningful labels of an image (its connector's name, version,
+/// and vendor), as opposed to the UUID that it is tagged with.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct ConnectorLabels {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+}
+
+impl ConnectorLabels {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.version.is_none() && self.vendor.is_none()
+    }
+
+    /// Whether these labels match every one of the given filters. A filter that is not given
+    /// matches anything, while one that is given must match exactly.
+    pub fn matches(&self, name: Option<&str>, version: Option<&str>, vendor: Option<&str>) -> bool {
+        let matches = |label: &Option<String>, filter: Option<&str>| {
+            filter.is_none_or(|filter| label.as_deref() == Some(filter))
+        };
+        matches(&self.name, name)
+            && matches(&self.version, version)
+            && matches(&self.vendor, vendor)
+    }
+}
+
+impl ImageConfig {
+    /// Returns the [ConnectorLabels](ConnectorLabels) among this image's labels.
+    pub fn connector_labels(&self) -> ConnectorLabels {
+        ConnectorLabels {
+            name: self.labels.get(NAME_LABEL).cloned(),
+            version: self.labels.get(VERSION_LABEL).cloned(),
+            vendor: self.labels.get(VENDOR_LABEL).cloned(),
+        }
+    }
+}
+
+/// A RunConfig is the deserialization target of an image's config blob, as far as how the image
+/// runs is concerned. The config blob follows the Docker casing of its fields.
+#[derive(Deserialize, Debug, Default)]
+struct RunConfig {
+    #[serde(default)]
+    created: Option<String>,
+    #[serde(default)]
+    config: Option<Run>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+struct Run {
+    #[serde(default)]
+    entrypoint: Option<Vec<String>>,
+    #[serde(default)]
+    cmd: Option<Vec<String>>,
+    #[serde(default)]
+    exposed_ports: Option<BTreeMap<String, serde_json::Value>>,
+    #[serde(default)]
+    env: Option<Vec<String>>,
+    #[serde(default)]
+    labels: Option<BTreeMap<String, String>>,
+}
+
+impl From<Config> for Platform {
+    fn from(config: Config) -> Self {
+        Platform {
+            os: config.os,
+            architecture: config.architecture,
+            variant: config.variant,
+        }
+    }
+}
+
+impl Manifest {
+    /// Parses the given raw manifest.
+    pub fn parse<B: AsRef<[u8]>>(raw: B) -> Result<Manifest> {
+        Ok(serde_json::from_slice(raw.as_ref()).map_err(ManifestSerdeError::from)?)
+    }
+
+    /// Returns the platforms described by a manifest list, with the `unknown/unknown` entries
+    /// (attestations and the like) filtered out. `None` is returned for a single-platform
+    /// manifest, whose platform is found within its [config](Manifest::config) instead.
+    pub fn platforms(&self) -> Option<Vec<Platform>> {
+        match self {
+            Manifest::List { manifests } => Some(
+                manifests
+                    .iter()
+                    .filter_map(|entry| entry.platform.clone())
+                    .filter(|platform| platform.os != "unknown")
+                    .collect(),
+            ),
+            Manifest::Image { .. } => None,
+        }
+    }
+
+    /// Returns the digest of the config blob of a single-platform manifest.
+    pub fn config(&self) -> Option<&str> {
+        match self {
+            Manifest::List { .. } => None,
+            Manifest::Image { config, .. } => Some(&config.digest),
+        }
+    }
+
+    /// Returns the digest of the first manifest listed by a manifest list that is for a platform
+    /// proper (rather than, say, an attestation). A single-platform manifest lists none.
+    pub fn primary(&self) -> Option<&str> {
+        match self {
+            Manifest::List { manifests } => manifests
+                .iter()
+                .find(|entry| {
+                    entry
+                        .platform
+                        .as_ref()
+                        .map(|platform| platform.os != "unknown")
+                        .unwrap_or(true)
+                })
+                .map(|entry| entry.digest.as_str()),
+            Manifest::Image { .. } => None,
+        }
+    }
+
+    /// Returns the digests of the manifests listed by a manifest list. A single-platform manifest
+    /// lists none.
+    pub fn children(&self) -> Vec<&str> {
+        match self {
+            Manifest::List { manifests } => manifests
+                .iter()
+                .map(|entry| entry.digest.as_str())
+                .collect(),
+            Manifest::Image { .. } => vec![],
+        }
+    }
+
+    /// Returns the blobs (the config and every layer) of a single-platform manifest. The blobs of
+    /// a manifest list are found within its [children](Manifest::children) instead.
+    pub fn blobs(&self) -> Vec<&Descriptor> {
+        match self {
+            Manifest::List { .. } => vec![],
+            Manifest::Image { config, layers } => std::iter::once(config).chain(layers).collect(),
+        }
+    }
+}
+
+/// Parses the given raw config blob into the platform that it describes.
+pub fn parse_config<B: AsRef<[u8]>>(raw: B) -> Result<Platform> {
+    let config: Config = serde_json::from_slice(raw.as_ref()).map_err(ManifestSerdeError::from)?;
+    Ok(config.into())
+}
+
+/// Parses the given raw config blob of the given single-platform manifest into an
+/// [ImageConfig](ImageConfig).
+pub fn parse_image_config<B: AsRef<[u8]>>(raw: B, manifest: &Manifest) -> Result<ImageConfig> {
+    let config: RunConfig =
+        serde_json::from_slice(raw.as_ref()).map_err(ManifestSerdeError::from)?;
+    let run = config.config.unwrap_or_default();
+    let blobs = manifest.blobs();
+    Ok(ImageConfig {
+        entrypoint: run.entrypoint.unwrap_or_default(),
+        cmd: run.cmd.unwrap_or_default(),
+        exposed_ports: run.exposed_ports.unwrap_or_default().into_keys().collect(),
+        env: run.env.unwrap_or_default(),
+        labels: run.labels.unwrap_or_default(),
+        layers: blobs.len().saturating_sub(1),
+        size: blobs.iter().map(|blob| blob.size).sum(),
+        created: config.created,
+    })
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error(
+    "A failure occurred while deserializing an image manifest returned by the registry. We \
+expected either an OCI image index, an OCI image manifest, or their Docker v2 equivalents."
+)]
+#[error_code("AIM-1100")]
+pub(crate) struct ManifestSerdeError {
+    #[from]
+    error: serde_json::Error,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_list() {
+        let raw = r#"{
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.docker.distribution.manifest.list.v2+json",
+            "manifests": [
+                {
+                    "mediaType": "application/vnd.docker.distribution.manifest.v2+json",
+                    "size": 528,
+                    "digest": "sha256:5b0bcabd1ed22e9fb1310cf6c2dec7cdef19f0ad69efa1f392e94a4333501270",
+                    "platform": {"architecture": "amd64", "os": "linux"}
+                },
+                {
+                    "mediaType": "application/vnd.docker.distribution.manifest.v2+json",
+                    "size": 528,
+                    "digest": "sha256:1e4c6f4e4e5a84e1fbb1d1de6bfbe2ab5e5d5a2b0b5b2d2e06fa8b6b1b4e2b2a",
+                    "platform": {"architecture": "arm64", "os": "linux", "variant": "v8"}
+                },
+                {
+                    "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                    "size": 566,
+                    "digest": "sha256:2b7a4b4b5f0b9ad3c7ae1b0a1c7f2c3e0b2c8a1f6e3c6c9b9d2e8f0a1b2c3d4e",
+                    "platform": {"architecture": "unknown", "os": "unknown"}
+                }
+            ]
+        }"#;
+        let manifest = Manifest::parse(raw).unwrap();
+        let platforms: Vec<String> = manifest
+            .platforms()
+            .unwrap()
+            .iter()
+            .map(Platform::to_string)
+            .collect();
+        assert_eq!(platforms, vec!["linux/amd64", "linux/arm64/v8"]);
+        assert_eq!(manifest.config(), None);
+        assert_eq!(manifest.children().len(), 3);
+        assert_eq!(
+            manifest.primary(),
+            Some("sha256:5b0bcabd1ed22e9fb1310cf6c2dec7cdef19f0ad69efa1f392e94a4333501270")
+        );
+        assert!(manifest.blobs().is_empty());
+    }
+
+    #[test]
+    fn single_manifest() {
+        let raw = r#"{
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.docker.distribution.manifest.v2+json",
+            "config": {
+                "mediaType": "application/vnd.docker.container.image.v1+json",
+                "size": 1469,
+                "digest": "sha256:feb5d9fea6a5e9606aa995e879d862b825965ba48de054caab5ef356dc6b3412"
+            },
+            "layers": [
+                {
+                    "mediaType": "application/vnd.docker.image.rootfs.diff.tar.gzip",
+                    "size": 2479,
+                    "digest": "sha256:2db29710123e3e53a794f2694094b9b4338aa9ee5c40b930cb8063a1be392c54"
+                }
+            ]
+        }"#;
+        let manifest = Manifest::parse(raw).unwrap();
+        assert_eq!(manifest.platforms(), None);
+        assert!(manifest.children().is_empty());
+        assert_eq!(
+            manifest
+                .blobs()
+                .iter()
+                .map(|blob| blob.size)
+                .collect::<Vec<u64>>(),
+            vec![1469, 2479]
+        );
+        assert_eq!(
+            manifest.config(),
+            Some("sha256:feb5d9fea6a5e9606aa995e879d862b825965ba48de054caab5ef356dc6b3412")
+        );
+    }
+
+    #[test]
+    fn image_config() {
+        let manifest = Manifest::parse(
+            r#"{
+                "config": {"size": 100, "digest": "sha256:c"},
+                "layers": [{"size": 1000, "digest": "sha256:a"}, {"size": 10, "digest": "sha256:b"}]
+            }"#,
+        )
+        .unwrap();
+        let raw = r#"{
+            "architecture": "amd64",
+            "os": "linux",
+            "created": "2021-09-21T22:12:01.000000000Z",
+            "config": {
+                "Entrypoint": ["/bin/connector"],
+                "ExposedPorts": {"8080/tcp": {}},
+                "Env": ["PATH=/usr/bin"],
+                "Labels": {"org.opencontainers.image.title": "oracle"}
+            },
+            "rootfs": {}
+        }"#;
+        let config = parse_image_config(raw, &manifest).unwrap();
+        assert_eq!(config.entrypoint, vec!["/bin/connector"]);
+        assert!(config.cmd.is_empty());
+        assert_eq!(config.exposed_ports, vec!["8080/tcp"]);
+        assert_eq!(config.env, vec!["PATH=/usr/bin"]);
+        assert_eq!(config.labels["org.opencontainers.image.title"], "oracle");
+        assert_eq!(config.layers, 2);
+        assert_eq!(config.size, 1110);
+        assert_eq!(
+            config.created.as_deref(),
+            Some("2021-09-21T22:12:01.000000000Z")
+        );
+        let labels = config.connector_labels();
+        assert_eq!(labels.name.as_deref(), Some("oracle"));
+        assert_eq!(labels.version, None);
+        assert!(!labels.is_empty());
+    }
+
+    #[test]
+    fn matches_labels() {
+        let labels = ConnectorLabels {
+            name: Some("oracle".to_string()),
+            version: Some("1.2.0".to_string()),
+            vendor: None,
+        };
+        assert!(labels.matches(None, None, None));
+        assert!(labels.matches(Some("oracle"), Some("1.2.0"), None));
+        assert!(!labels.matches(Some("oracle"), Some("1.3.0"), None));
+        assert!(!labels.matches(None, None, Some("Alation")));
+        assert!(ConnectorLabels::default().is_empty());
+    }
+
+    #[test]
+    fn config() {
+        let raw = r#"{"architecture": "amd64", "os": "linux", "config": {}, "rootfs": {}}"#;
+        assert_eq!(parse_config(raw).unwrap().to_string(), "linux/amd64");
+    }
+}
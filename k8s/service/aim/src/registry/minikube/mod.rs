@@ -1,4 +1,12 @@
+//! This module supports the use of the registry plugin with Minikube.
+//! It is for development and testing ONLY, so it's behavior is not as well
+//! documented nor guaranteed.
+//!
+//! Panics are also allowable in this module. Sunny day scenarios are accounted for,
+//! however cases such as the registry not running or an unexpected JSON return structure
+//! will panic the thread.
 use crate::env;
+use crate::registry::manifest::{self, ConnectorLabels, Manifest, Platform};
 use crate::registry::Image;
 use error::*;
 use reqwest::Url;
@@ -6,14 +14,6 @@ use result::Result;
 use serde::Deserialize;
 use sha2::Digest;
 
-/// This module supports the use of the registry plugin with Minikube.
-/// It is for development and testing ONLY, so it's behavior is not as well
-/// documented nor guaranteed.
-///
-/// Panics are also allowable in this module. Sunny day scenarios are accounted for,
-/// however cases such as the registry not running or an unexpected JSON return structure
-/// will panic the thread.
-
 /// An interesting distinction here is that the Minikube registry cannot delete just a single
 /// tag - it can only delete digests. So if you give a tag which is backed by a digest that
 /// has second tag associated with it (that is, you uploaded the same image twice or more), then
@@ -85,7 +85,11 @@ pub async fn list() -> Result<Vec<Image>> {
             .await
             .unwrap();
         let digest = format!("sha256:{:x}", sha2::Sha256::digest(&bytes));
-        images.push(Image { tag, digest })
+        images.push(Image {
+            tag,
+            digest,
+            labels: ConnectorLabels::default(),
+        })
     }
     Ok(images)
 }
@@ -118,9 +122,110 @@ pub async fn get<T: AsRef<str>>(tag: T) -> Result<Option<Image>> {
     Ok(Some(Image {
         tag: tag.as_ref().to_string(),
         digest,
+        labels: ConnectorLabels::default(),
     }))
 }
 
+/// Returns the platforms of the given tag by way of the registry's manifest API. If no such
+/// tag exists, then `Ok(None)` is returned.
+pub async fn platforms<T: AsRef<str>>(tag: T) -> Result<Option<Vec<Platform>>> {
+    let manifest = match manifest(tag).await? {
+        Some(manifest) => manifest,
+        None => return Ok(None),
+    };
+    if let Some(platforms) = manifest.platforms() {
+        return Ok(Some(platforms));
+    }
+    let config = blob(manifest.config().unwrap()).await?;
+    Ok(Some(vec![manifest::parse_config(config)?]))
+}
+
+/// Downloads the blob of the given digest by way of the registry's blob API.
+pub async fn blob(digest: &str) -> Result<Vec<u8>> {
+    let url: Url = format!(
+        "http://{}/v2/{}/blobs/{}",
+        env::registry(),
+        env::repository(),
+        digest
+    )
+    .parse()
+    .unwrap();
+    Ok(reqwest::Client::new()
+        .get(url)
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap()
+        .to_vec())
+}
+
+/// Tags the image of the given tag with the given alias as well, by putting its manifest anew under
+/// the alias (byte for byte, and thus of the same digest).
+pub async fn retag(tag: &str, alias: &str) -> Result<()> {
+    let client = reqwest::Client::new();
+    let url = |reference: &str| -> Url {
+        format!(
+            "http://{}/v2/{}/manifests/{}",
+            env::registry(),
+            env::repository(),
+            reference
+        )
+        .parse()
+        .unwrap()
+    };
+    let response = client
+        .get(url(tag))
+        .header(
+            "Accept",
+            "application/vnd.docker.distribution.manifest.v2+json",
+        )
+        .send()
+        .await
+        .unwrap();
+    let media_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .cloned()
+        .unwrap();
+    let manifest = response.bytes().await.unwrap();
+    let response = client
+        .put(url(alias))
+        .header(reqwest::header::CONTENT_TYPE, media_type)
+        .body(manifest)
+        .send()
+        .await
+        .unwrap();
+    match response.status() {
+        reqwest::StatusCode::CREATED => Ok(()),
+        status => Err(ImageDeleteError { status }.into()),
+    }
+}
+
+/// Returns the manifest of the given reference (either a tag or a digest) by way of the
+/// registry's manifest API. If no such image exists, then `Ok(None)` is returned.
+pub async fn manifest<R: AsRef<str>>(reference: R) -> Result<Option<Manifest>> {
+    let url: Url = format!(
+        "http://{}/v2/{}/manifests/{}",
+        env::registry(),
+        env::repository(),
+        reference.as_ref()
+    )
+    .parse()
+    .unwrap();
+    let response = reqwest::Client::new()
+        .get(url)
+        .header("Accept", manifest::ACCEPTED_MANIFESTS)
+        .send()
+        .await
+        .unwrap();
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(Manifest::parse(response.bytes().await.unwrap())?))
+}
+
 #[derive(Error, AcmError, Kind, HttpCode, Debug)]
 #[code(Status::ServiceUnavailable)]
 #[error("Received status code {status} from the registry")]
@@ -1,13 +1,20 @@
 pub mod containerd;
 mod ecr;
+pub mod manifest;
 mod minikube;
 
-use crate::{aws, env};
-pub use containerd::Image;
+use crate::deprecation;
+use crate::env;
+pub use containerd::{Image, MalformedRemoteInstall, Remote, RemoteInstall, Step};
 use error::*;
+pub use manifest::{ConnectorLabels, ImageConfig, Inspection};
 use result::Result;
 use rocket::fs::TempFile;
+use serde::Serialize;
+use sha2::Digest;
+use std::path::{Path, PathBuf};
 use std::sync::Once;
+use tokio_util::sync::CancellationToken;
 
 static INIT: Once = Once::new();
 
@@ -47,6 +54,9 @@ impl Implementation {
     ///
     /// This function PANICS should any failure occur.
     ///
+    /// For ECR, the configured repository is [bootstrapped](ecr::bootstrap) (that is, created if
+    /// absent, given its lifecycle policy, and checked for every permission that the AIM requires).
+    ///
     /// Consumers SHOULD call this function AT LEAST once during initialization in their main.
     /// While `configure` is indeed called at the beginning of all entry points into the registry
     /// module, since this function panics it is more desirable to see this panic occur upfront
@@ -58,40 +68,103 @@ impl Implementation {
     /// to only ever be executed exactly once.
     pub fn configure() {
         INIT.call_once(|| {
-            futures::executor::block_on(async {
-                match Implementation::which() {
-                    Implementation::Minikube => {
-                        warn!("This runtime is configured for use with Minikube. This should be for dev {}!", term_colors::red("ONLY"));
-                    }
-                    Implementation::Ecr => {
-                        info!("Configuring this runtime for the {} (AWS ECR).", term_colors::bold("Elastic Container Registry"));
-                        let key_id = env::aws_access_key_id();
-                        let access_key = env::aws_secret_access_key();
-                        let region = env::aws_region();
-                        // Just assert that AWS_USERNAME is present.
-                        let _ = env::aws_username();
-                        aws!("configure", "set", "aws_access_key_id", &key_id)
-                            .await
-                            .unwrap();
-                        aws!("configure", "set", "aws_secret_access_key", &access_key)
-                            .await
-                            .unwrap();
-                        aws!("configure", "set", "region", &region).await.unwrap();
+            match Implementation::which() {
+                Implementation::Minikube => {
+                    warn!("This runtime is configured for use with Minikube. This should be for dev {}!", term_colors::red("ONLY"));
+                }
+                Implementation::Ecr => {
+                    info!("Configuring this runtime for the {} (AWS ECR).", term_colors::bold("Elastic Container Registry"));
+                    // The AWS SDK reads these from the environment on its own, we merely
+                    // assert that they are present so that a misconfiguration fails fast.
+                    let _ = env::aws_access_key_id();
+                    let _ = env::aws_secret_access_key();
+                    let _ = env::aws_region();
+                    let _ = env::aws_username();
+                    // We are (almost certainly) being called from within the runtime, so we
+                    // borrow it rather than spinning up one of our own.
+                    let bootstrap = tokio::task::block_in_place(|| {
+                        tokio::runtime::Handle::current().block_on(ecr::bootstrap())
+                    });
+                    if let Err(err) = bootstrap {
+                        panic!("{}", err);
                     }
-                };
-            });
+                    ecr::refresh_proactively();
+                }
+            };
         })
     }
 }
 
-/// Imports the given tmp file as an image into the configure repository.
+/// Imports the image at the given path into the configure repository.
 ///
 /// The image first undergoes a sanitization wherein it is imported
 /// into `containerd` and retagged to an OCF normalized form before
-/// being pushed to that target repository.
-pub async fn import(image: TempFile<'_>) -> Result<Image> {
+/// being pushed to that target repository. The given `progress` callback
+/// is invoked with each [Step](Step) of this pipeline as it begins, while the given
+/// `cancellation` token aborts it.
+pub async fn import<P: AsRef<Path>, F: Fn(Step)>(
+    image: P,
+    cancellation: CancellationToken,
+    progress: F,
+) -> Result<Image> {
     Implementation::configure();
-    containerd::import(image).await
+    containerd::import(image, cancellation, progress).await
+}
+
+/// Pulls the given remote image into the configured repository. The image undergoes the same
+/// sanitization as an [imported](import) image, only it is pulled into `containerd` rather than
+/// uploaded.
+pub async fn pull<F: Fn(Step)>(
+    remote: &Remote,
+    cancellation: CancellationToken,
+    progress: F,
+) -> Result<Image> {
+    Implementation::configure();
+    containerd::pull(remote, cancellation, progress).await
+}
+
+/// Moves the given upload out from under Rocket's management and into a uniquely named file
+/// within the [spool path](crate::env::spool_path), returning the [Staged](Staged) file.
+///
+/// Rocket deletes its temporary files as soon as the request completes, which would pull the
+/// image out from under any install that outlives its request. The staged file is rather deleted
+/// once the returned [Staged](Staged) is dropped.
+pub async fn stage(image: &mut TempFile<'_>) -> Result<Staged> {
+    let path = crate::env::spool_path().join(names::uuid());
+    image
+        .persist_to(&path)
+        .await
+        .map_err(|source| StagingError {
+            path: path.to_string_lossy().to_string(),
+            source,
+        })?;
+    Ok(Staged { path })
+}
+
+/// A Staged is an upload that was [staged](stage) into the spool path. The file is removed once
+/// this is dropped, such that an install whose client goes away part way through (taking the
+/// install with it) does not leave its upload behind.
+#[derive(Debug)]
+pub struct Staged {
+    path: PathBuf,
+}
+
+impl Staged {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Staged {
+    fn drop(&mut self) {
+        // The upload may have long since been released by the spool (or moved into the quarantine).
+        match std::fs::remove_file(&self.path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                warn!("Failed to remove staged image {:?}, {}", self.path, err)
+            }
+            _ => (),
+        }
+    }
 }
 
 /// Uninstalls the given tag from the configured repository. If no such
@@ -132,10 +205,316 @@ pub async fn get(tag: String) -> Result<Image> {
     })?)
 }
 
+/// Promotes the image of the given tag by tagging it with the given alias as well (say,
+/// `production`). The image is not uploaded anew, rather the alias is applied to the very same
+/// digest, so that a tag that was vetted in staging may be deployed by a stable, human-readable
+/// name.
+///
+/// The alias must be a valid [RFC 1035 label](names::is_valid_rfc1035_label), otherwise a
+/// [MalformedAlias](MalformedAlias) is returned. Promoting a tag to an alias that it already
+/// carries succeeds, whereas an alias that is already carried by another digest is an
+/// [AliasConflict](AliasConflict) (uninstall the alias first in order to move it). If no such tag
+/// exists, then a [TagNotFound](TagNotFound) is returned.
+pub async fn promote(tag: String, alias: String) -> Result<Image> {
+    if !names::is_valid_rfc1035_label(&alias) {
+        return Err(MalformedAlias { alias }.into());
+    }
+    let image = get(tag).await?;
+    let existing = match Implementation::which() {
+        Implementation::Ecr => ecr::get(&alias).await,
+        Implementation::Minikube => minikube::get(&alias).await,
+    }?;
+    match existing {
+        Some(existing) if existing.digest == image.digest => return Ok(existing),
+        Some(existing) => {
+            return Err(AliasConflict {
+                alias,
+                digest: existing.digest,
+            }
+            .into())
+        }
+        None => (),
+    }
+    match Implementation::which() {
+        Implementation::Ecr => ecr::retag(&image.tag, &alias).await,
+        Implementation::Minikube => minikube::retag(&image.tag, &alias).await,
+    }?;
+    Ok(Image {
+        tag: alias,
+        digest: image.digest,
+        labels: image.labels,
+    })
+}
+
+/// Returns every tag (including the given one) that refers to the same digest as the given tag,
+/// that is, the given tag and all of its [aliases](promote). If no such tag exists, then a
+/// [TagNotFound](TagNotFound) is returned.
+pub async fn aliases(tag: String) -> Result<Vec<Image>> {
+    let image = get(tag).await?;
+    Ok(same_digest(list().await?, &image.digest))
+}
+
+fn same_digest(images: Vec<Image>, digest: &str) -> Vec<Image> {
+    let mut images: Vec<Image> = images
+        .into_iter()
+        .filter(|image| image.digest == digest)
+        .collect();
+    images.sort_by(|a, b| a.tag.cmp(&b.tag));
+    images
+}
+
+/// A Lookup is whether a tag is installed within the configured repository. Unlike [get](get), a
+/// tag that is not installed is an answer rather than an error.
+#[derive(Serialize, Debug, Kind)]
+pub struct Lookup {
+    pub tag: String,
+    /// The image reference (that is, `<registry>/<repository>:<tag>`) that the tag is pulled by.
+    pub reference: String,
+    pub installed: bool,
+    /// The digest of the tag, should it be installed.
+    pub digest: Option<String>,
+}
+
+/// Looks up whether the given tag is installed within the configured repository.
+pub async fn lookup(tag: String) -> Result<Lookup> {
+    Implementation::configure();
+    let image = match Implementation::which() {
+        Implementation::Ecr => ecr::get(&tag).await,
+        Implementation::Minikube => minikube::get(&tag).await,
+    }?;
+    Ok(Lookup {
+        reference: format!("{}/{}:{}", env::registry(), env::repository(), tag),
+        tag,
+        installed: image.is_some(),
+        digest: image.map(|image| image.digest),
+    })
+}
+
+/// Returns the [Inspection](Inspection) of the given tag, including its [deprecation](deprecation)
+/// (if any) and its [metadata](crate::metadata). If no such tag exists, then an error of a [TagNotFound](TagNotFound) is returned.
+pub async fn inspect(tag: String) -> Result<Inspection> {
+    let image = get(tag).await?;
+    let platforms = match Implementation::which() {
+        Implementation::Ecr => ecr::platforms(&image.tag).await,
+        Implementation::Minikube => minikube::platforms(&image.tag).await,
+    }?;
+    let deprecation = deprecation::get(&image.tag).await?;
+    let config = crate::metadata::get(&image).await?;
+    Ok(Inspection {
+        tag: image.tag,
+        digest: image.digest,
+        platforms: platforms.unwrap_or_default(),
+        deprecation,
+        config,
+    })
+}
+
+/// Returns every blob (the config and every layer, of every platform) of the given tag within the
+/// configured repository. If no such tag exists, then `Ok(None)` is returned.
+///
+/// A manifest list refers to the manifest of each of its platforms, each of which is retrieved in
+/// turn. Platforms frequently share layers, so the same blob may be returned more than once.
+pub async fn blobs<T: AsRef<str>>(tag: T) -> Result<Option<Vec<manifest::Descriptor>>> {
+    Implementation::configure();
+    let manifest = match manifest(tag.as_ref()).await? {
+        Some(manifest) => manifest,
+        None => return Ok(None),
+    };
+    let mut blobs: Vec<manifest::Descriptor> = manifest.blobs().into_iter().cloned().collect();
+    for child in manifest.children() {
+        if let Some(child) = self::manifest(child).await? {
+            blobs.extend(child.blobs().into_iter().cloned());
+        }
+    }
+    Ok(Some(blobs))
+}
+
+/// Reads the [ImageConfig](ImageConfig) of the given tag out of its config blob. The config of a
+/// multi-platform image is that of its [primary](manifest::Manifest::primary) platform. If no such
+/// tag exists, then a [TagNotFound](TagNotFound) is returned.
+pub async fn config<T: AsRef<str>>(tag: T) -> Result<ImageConfig> {
+    Implementation::configure();
+    let not_found = || TagNotFound {
+        tag: tag.as_ref().to_string(),
+        registry: format!("{}/{}", env::registry(), env::repository()),
+    };
+    let mut image = manifest(tag.as_ref()).await?.ok_or_else(not_found)?;
+    if let Some(primary) = image.primary().map(str::to_string) {
+        image = manifest(&primary).await?.ok_or_else(not_found)?;
+    }
+    let digest = image.config().ok_or_else(not_found)?;
+    let raw = match Implementation::which() {
+        Implementation::Ecr => ecr::blob(digest).await,
+        Implementation::Minikube => minikube::blob(digest).await,
+    }?;
+    manifest::parse_image_config(raw, &image)
+}
+
+async fn manifest(reference: &str) -> Result<Option<manifest::Manifest>> {
+    match Implementation::which() {
+        Implementation::Ecr => ecr::manifest(reference).await,
+        Implementation::Minikube => minikube::manifest(reference).await,
+    }
+}
+
+/// Confirms that the configured registry answers the [distribution API](https://github.com/opencontainers/distribution-spec/blob/main/spec.md#api)
+/// at all, returning the status that it answered with. Any answer will do, as ECR refuses
+/// anonymous requests with a `401` while Minikube's registry permits them.
+pub async fn ping() -> Result<String> {
+    let scheme = match Implementation::which() {
+        Implementation::Ecr => "https",
+        Implementation::Minikube => "http",
+    };
+    let url = format!("{}://{}/v2/", scheme, env::registry());
+    match reqwest::get(&url).await {
+        Ok(response) => Ok(format!("{} answered {}", url, response.status())),
+        Err(err) => Err(RegistryUnreachable {
+            url,
+            cause: err.to_string(),
+        }
+        .into()),
+    }
+}
+
+/// Confirms that credentials to push to the configured registry may be had, returning whom
+/// they are for. Minikube's registry requires none.
+pub async fn check_credentials() -> Result<String> {
+    match Implementation::which() {
+        Implementation::Ecr => {
+            let (username, _) = ecr::get_credentials().await?;
+            Ok(format!("issued for {}", username))
+        }
+        Implementation::Minikube => Ok("none required".to_string()),
+    }
+}
+
+/// Returns an opaque fingerprint of the given registry state suitable for use as an HTTP ETag.
+///
+/// The fingerprint is the SHA256 of every `tag@digest` pair, sorted, so that two listings
+/// of the same registry produce the same ETag regardless of the order in which the underlying
+/// implementation happened to enumerate them.
+pub fn etag(images: &[&Image]) -> String {
+    let mut pairs: Vec<String> = images
+        .iter()
+        .map(|image| format!("{}@{}", image.tag, image.digest))
+        .collect();
+    pairs.sort();
+    let mut hasher = sha2::Sha256::new();
+    for pair in pairs {
+        hasher.update(pair.as_bytes());
+        hasher.update(b"\n");
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[error(
+    "Failed to stage the uploaded image at {path}. Please ensure that the AIM's temporary \
+directory has enough free space for the image and try again."
+)]
+#[code(Status::InternalServerError)]
+#[error_code("AIM-1001")]
+pub struct StagingError {
+    path: String,
+    #[source]
+    source: std::io::Error,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[error(
+    "The registry could not be reached at {url}, {cause}. Please ensure that the REGISTRY \
+configuration names a registry that is reachable from the AIM."
+)]
+#[code(Status::ServiceUnavailable)]
+#[error_code("AIM-1002")]
+pub struct RegistryUnreachable {
+    url: String,
+    cause: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[error(
+    "The alias '{alias}' is not a valid RFC 1035 label. An alias must be at most 63 characters \
+long, consist of lowercase alphanumeric characters or '-', start with a letter, and end with a \
+letter or digit (e.g. 'production' or 'staging-2')."
+)]
+#[code(Status::BadRequest)]
+#[error_code("AIM-1003")]
+pub struct MalformedAlias {
+    alias: String,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[error(
+    "The alias '{alias}' already refers to {digest}. Please uninstall the alias before promoting \
+another image to it."
+)]
+#[code(Status::Conflict)]
+#[error_code("AIM-1004")]
+pub struct AliasConflict {
+    alias: String,
+    digest: String,
+}
+
 #[derive(Error, AcmError, Kind, HttpCode, Debug)]
 #[error("The OCF image tag '{tag}' does not exist in {registry}")]
 #[code(Status::NotFound)]
+#[error_code("AIM-1000")]
 pub struct TagNotFound {
     tag: String,
     registry: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(tag: &str, digest: &str) -> Image {
+        Image {
+            tag: tag.to_string(),
+            digest: digest.to_string(),
+            labels: Default::default(),
+        }
+    }
+
+    #[test]
+    fn etag_is_order_independent() {
+        let a = image("a", "sha256:1");
+        let b = image("b", "sha256:2");
+        assert_eq!(etag(&[&a, &b]), etag(&[&b, &a]));
+    }
+
+    #[test]
+    fn etag_tracks_changes() {
+        let a = image("a", "sha256:1");
+        let b = image("b", "sha256:2");
+        let retagged = image("a", "sha256:3");
+        assert_ne!(etag(&[&a]), etag(&[&a, &b]));
+        assert_ne!(etag(&[&a]), etag(&[&retagged]));
+        assert_ne!(etag(&[]), etag(&[&a]));
+    }
+
+    #[test]
+    fn aliases_share_a_digest() {
+        let images = vec![
+            image("production", "sha256:1"),
+            image("b", "sha256:2"),
+            image("a", "sha256:1"),
+        ];
+        let aliases: Vec<String> = same_digest(images, "sha256:1")
+            .into_iter()
+            .map(|image| image.tag)
+            .collect();
+        assert_eq!(aliases, vec!["a", "production"]);
+    }
+
+    #[test]
+    fn staged_uploads_are_removed_once_dropped() {
+        let path = std::env::temp_dir().join(names::uuid());
+        std::fs::write(&path, b"image").unwrap();
+        drop(Staged { path: path.clone() });
+        assert!(!path.exists());
+        // An upload that was already released (or quarantined) is of no concern.
+        drop(Staged { path });
+    }
+}
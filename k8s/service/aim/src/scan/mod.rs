@@ -0,0 +1,298 @@
+//! Malware scanning of uploaded images. Should a [scanner](env::scanner) be configured, then every
+//! uploaded image is run through it before anything is imported into containerd. An image that is
+//! flagged is moved into the [quarantine](env::quarantine_path) (for the security team to look
+//! over) and its install is refused with a [MalwareDetected](MalwareDetected).
+//!
+//! The supported scanners are
+//!
+//! 1. `clamd`: The image is streamed to a [clamd](https://docs.clamav.net/manual/Usage/Scanning.html#clamd)
+//!    listening upon the [CLAMD_SOCKET](env::clamd_socket) via its `INSTREAM` command. Note that
+//!    clamd refuses streams larger than its `StreamMaxLength` (25M by default), which SHOULD be
+//!    raised to the largest image that is expected to be installed.
+//! 2. `webhook`: The image is POSTed (as `application/octet-stream`) to the [SCAN_WEBHOOK](env::scan_webhook),
+//!    which answers with a [WebhookVerdict](WebhookVerdict).
+//! 3. `none`: Images are not scanned.
+//!
+//! Scanning fails closed. That is, should the scanner be unreachable (or answer with something
+//! other than a verdict), then the install is refused with a [ScanFailed](ScanFailed).
+use crate::env;
+use error::*;
+use result::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// The size of each chunk of the image that is streamed to clamd.
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// How long the webhook is waited upon for its verdict. Images are several gigabytes, so this is
+/// generous.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// The header by which the webhook is told which install the image belongs to.
+pub const INSTALL_ID_HEADER: &str = "X-Install-Id";
+
+/// A Scanner is what uploaded images are run through.
+#[derive(Debug, Clone)]
+pub enum Scanner {
+    Clamd(PathBuf),
+    Webhook(String),
+}
+
+/// A Verdict is what a [Scanner](Scanner) made of an image.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Verdict {
+    Clean,
+    /// The image was flagged under the given signature (such as `Win.Test.EICAR_HDB-1`).
+    Infected(String),
+}
+
+/// A WebhookVerdict is the JSON body that a scanning webhook answers with, such as
+///
+/// ```json
+/// {"infected": true, "signature": "Win.Test.EICAR_HDB-1"}
+/// ```
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebhookVerdict {
+    pub infected: bool,
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl Scanner {
+    /// Returns the [configured](env::scanner) Scanner, if any.
+    pub fn configured() -> Option<Scanner> {
+        match env::scanner().as_str() {
+            "clamd" => Some(Scanner::Clamd(env::clamd_socket())),
+            "webhook" => Some(Scanner::Webhook(env::scan_webhook())),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Scanner::Clamd(_) => "clamd",
+            Scanner::Webhook(_) => "webhook",
+        }
+    }
+
+    /// Runs the image at the given path through this Scanner.
+    pub async fn scan<P: AsRef<Path>>(&self, id: &str, path: P) -> Result<Verdict> {
+        let verdict = match self {
+            Scanner::Clamd(socket) => clamd(socket, path.as_ref()).await,
+            Scanner::Webhook(url) => webhook(url, id, path.as_ref()).await,
+        };
+        verdict.map_err(|cause| {
+            ScanFailed {
+                scanner: self.name(),
+                cause,
+            }
+            .into()
+        })
+    }
+}
+
+/// Scans the image at the given path (should a [scanner](env::scanner) be configured). An image
+/// that is flagged is [quarantined](quarantine) and refused with a [MalwareDetected](MalwareDetected),
+/// otherwise the image is left where it is.
+pub async fn scan<P: AsRef<Path>>(id: &str, path: P) -> Result<()> {
+    let scanner = match Scanner::configured() {
+        Some(scanner) => scanner,
+        None => return Ok(()),
+    };
+    match scanner.scan(id, &path).await? {
+        Verdict::Clean => Ok(()),
+        Verdict::Infected(signature) => {
+            warn!(
+                "The upload of install {} was flagged by {} as {}",
+                id,
+                scanner.name(),
+                signature
+            );
+            let quarantined = quarantine(id, &path, &scanner, &signature).await;
+            Err(MalwareDetected {
+                id: id.to_string(),
+                scanner: scanner.name(),
+                signature,
+                quarantined,
+            }
+            .into())
+        }
+    }
+}
+
+/// A Quarantined is the record written alongside a quarantined image, as `<id>.json`.
+#[derive(Serialize, Debug)]
+struct Quarantined<'a> {
+    id: &'a str,
+    scanner: &'static str,
+    signature: &'a str,
+}
+
+/// Moves the given image into the [quarantine](env::quarantine_path) as `<id>.img`, alongside a
+/// record of why. Returns whether the image was quarantined. An image that cannot be quarantined
+/// is removed instead (it is never imported either way).
+async fn quarantine<P: AsRef<Path>>(id: &str, path: P, scanner: &Scanner, signature: &str) -> bool {
+    let dir = env::quarantine_path();
+    let moved = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        let target = dir.join(format!("{}.img", id));
+        // The quarantine is likely upon another volume than the staged uploads, in which case the
+        // image cannot simply be renamed.
+        if tokio::fs::rename(&path, &target).await.is_err() {
+            tokio::fs::copy(&path, &target).await?;
+            tokio::fs::remove_file(&path).await?;
+        }
+        let record = serde_json::to_vec_pretty(&Quarantined {
+            id,
+            scanner: scanner.name(),
+            signature,
+        })?;
+        tokio::fs::write(dir.join(format!("{}.json", id)), record).await
+    };
+    match moved.await {
+        Ok(()) => true,
+        Err(err) => {
+            warn!("Failed to quarantine the upload of install {}, {}", id, err);
+            if let Err(err) = tokio::fs::remove_file(&path).await {
+                warn!("Failed to remove staged image {:?}, {}", path.as_ref(), err);
+            }
+            false
+        }
+    }
+}
+
+/// Streams the image to clamd via the `INSTREAM` command, which takes the image as a sequence of
+/// chunks (each prefixed by its length as a big endian u32) terminated by an empty chunk.
+async fn clamd(socket: &Path, path: &Path) -> std::result::Result<Verdict, String> {
+    let mut clamd = tokio::net::UnixStream::connect(socket)
+        .await
+        .map_err(|err| format!("failed to connect to {:?}, {}", socket, err))?;
+    let mut image = tokio::fs::File::open(path)
+        .await
+        .map_err(|err| err.to_string())?;
+    let streamed: std::io::Result<()> = async {
+        clamd.write_all(b"zINSTREAM\0").await?;
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        loop {
+            let read = image.read(&mut buffer).await?;
+            clamd.write_all(&(read as u32).to_be_bytes()).await?;
+            if read == 0 {
+                break;
+            }
+            clamd.write_all(&buffer[..read]).await?;
+        }
+        clamd.flush().await
+    }
+    .await;
+    // clamd hangs up upon a stream that exceeds its StreamMaxLength, having answered as to why.
+    let mut reply = vec![];
+    let replied = clamd.read_to_end(&mut reply).await;
+    match (streamed, replied) {
+        (_, Ok(_)) if !reply.is_empty() => clamd_reply(&String::from_utf8_lossy(&reply)),
+        (Err(err), _) | (_, Err(err)) => Err(err.to_string()),
+        _ => Err("clamd hung up without a reply".to_string()),
+    }
+}
+
+/// Parses the reply of clamd to an `INSTREAM`, which is one of either
+///
+/// ```text
+/// stream: OK
+/// stream: Win.Test.EICAR_HDB-1 FOUND
+/// INSTREAM size limit exceeded. ERROR
+/// ```
+fn clamd_reply(reply: &str) -> std::result::Result<Verdict, String> {
+    let reply = reply.trim_end_matches(|c: char| c == '\0' || c.is_whitespace());
+    let result = reply.strip_prefix("stream: ").unwrap_or(reply);
+    if result == "OK" {
+        Ok(Verdict::Clean)
+    } else if let Some(signature) = result.strip_suffix(" FOUND") {
+        Ok(Verdict::Infected(signature.to_string()))
+    } else {
+        Err(format!("clamd replied with '{}'", reply))
+    }
+}
+
+/// POSTs the image to the given webhook.
+async fn webhook(url: &str, id: &str, path: &Path) -> std::result::Result<Verdict, String> {
+    let image = tokio::fs::File::open(path)
+        .await
+        .map_err(|err| err.to_string())?;
+    let body = reqwest::Body::wrap_stream(tokio_util::io::ReaderStream::new(image));
+    let verdict: WebhookVerdict = reqwest::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()
+        .map_err(|err| err.to_string())?
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .header(INSTALL_ID_HEADER, id)
+        .body(body)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|err| err.to_string())?
+        .json()
+        .await
+        .map_err(|err| format!("the webhook did not answer with a verdict, {}", err))?;
+    if verdict.infected {
+        Ok(Verdict::Infected(
+            verdict
+                .signature
+                .unwrap_or_else(|| "an unnamed signature".to_string()),
+        ))
+    } else {
+        Ok(Verdict::Clean)
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::UnprocessableEntity)]
+#[error(
+    "The uploaded image was flagged by the {scanner} scanner as {signature}, so it will not be \
+installed. The upload of install {id} has been quarantined (quarantined: {quarantined}) for \
+review by your security team."
+)]
+#[error_code("AIM-1900")]
+pub struct MalwareDetected {
+    id: String,
+    scanner: &'static str,
+    signature: String,
+    quarantined: bool,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::BadGateway)]
+#[error(
+    "The uploaded image could not be scanned by the {scanner} scanner, {cause}. Uploads are not \
+installed unless they have been scanned, so please check upon the scanner and try again."
+)]
+#[error_code("AIM-1901")]
+pub struct ScanFailed {
+    scanner: &'static str,
+    cause: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamd_replies() {
+        assert_eq!(clamd_reply("stream: OK\0"), Ok(Verdict::Clean));
+        assert_eq!(
+            clamd_reply("stream: Win.Test.EICAR_HDB-1 FOUND\0"),
+            Ok(Verdict::Infected("Win.Test.EICAR_HDB-1".to_string()))
+        );
+        assert!(clamd_reply("INSTREAM size limit exceeded. ERROR\0").is_err());
+        assert!(clamd_reply("").is_err());
+    }
+
+    #[test]
+    fn webhook_verdicts() {
+        let verdict: WebhookVerdict = serde_json::from_str(r#"{"infected": false}"#).unwrap();
+        assert!(!verdict.infected);
+        assert_eq!(verdict.signature, None);
+    }
+}
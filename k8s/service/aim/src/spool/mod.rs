@@ -0,0 +1,356 @@
+//! The spool that uploaded images are held within between being received and being imported into
+//! containerd. Uploads are always received into the [spool path](env::spool_path) (which MAY be a
+//! mounted PersistentVolumeClaim), whereas where they are then held depends upon the configured
+//! [spool](env::spool).
+//!
+//! 1. `disk`: Uploads are held where they were received.
+//! 2. `s3`: Uploads are moved into the [spool bucket](env::spool_bucket) and only brought back
+//!    onto the disk when their install is ready to import them.
+//!
+//! Before an upload is received, its `Content-Length` is [checked](preflight) against the free
+//! space of the spool path, such that an upload that would not fit is refused up front (with a
+//! `507 Insufficient Storage`) rather than after gigabytes have been streamed. A compressed upload
+//! is likewise only decompressed within the [headroom] of the spool. Every install is
+//! lost upon a restart of the AIM, so whatever remains within the spool upon startup is
+//! [cleaned up](cleanup).
+use crate::env;
+use aws_sdk_s3::{ByteStream, Client};
+use error::*;
+use futures::TryStreamExt;
+use result::Result;
+use rocket::request::{FromRequest, Outcome, Request};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::OnceCell;
+
+lazy_static! {
+    static ref CLIENT: OnceCell<Client> = OnceCell::new();
+}
+
+/// Returns the shared [AWS S3 client](aws_sdk_s3::Client), configured from the environment just as
+/// the ECR client is.
+async fn client() -> &'static Client {
+    CLIENT
+        .get_or_init(|| async { Client::new(&aws_config::load_from_env().await) })
+        .await
+}
+
+/// A Spooled is an upload that is being held by the spool.
+#[derive(Debug)]
+pub enum Spooled {
+    /// The upload is held at the given path.
+    Disk(PathBuf),
+    /// The upload is held within the spool bucket under the given key, and is brought back to the
+    /// given path once it is [fetched](Spooled::fetch).
+    S3 { key: String, path: PathBuf },
+}
+
+impl Spooled {
+    /// Returns the path at which the upload may be read, bringing it back from the spool bucket
+    /// should it be held there.
+    pub async fn fetch(&self) -> Result<PathBuf> {
+        match self {
+            Spooled::Disk(path) => Ok(path.clone()),
+            Spooled::S3 { key, path } => {
+                download(key, path).await.map_err(|cause| SpoolFailed {
+                    operation: "fetch",
+                    upload: key.clone(),
+                    cause,
+                })?;
+                Ok(path.clone())
+            }
+        }
+    }
+
+    /// Removes the upload from the spool. Failing to do so is logged rather than returned, as
+    /// whatever remains is [cleaned up](cleanup) upon the next startup.
+    pub async fn release(&self) {
+        let path = match self {
+            Spooled::Disk(path) => path,
+            Spooled::S3 { key, path } => {
+                let deleted = client()
+                    .await
+                    .delete_object()
+                    .bucket(env::spool_bucket())
+                    .key(key)
+                    .send()
+                    .await;
+                if let Err(err) = deleted {
+                    warn!(
+                        "Failed to remove spooled image s3://{}/{}, {}",
+                        env::spool_bucket(),
+                        key,
+                        err
+                    );
+                }
+                path
+            }
+        };
+        // A flagged image will have been moved into the quarantine, while an image held in the
+        // spool bucket may never have been fetched.
+        if path.exists() {
+            if let Err(err) = tokio::fs::remove_file(path).await {
+                warn!("Failed to remove staged image {:?}, {}", path, err);
+            }
+        }
+    }
+}
+
+/// Hands the given (staged) upload over to the configured [spool](env::spool).
+pub async fn hold(path: PathBuf) -> Result<Spooled> {
+    if env::spool() != "s3" {
+        return Ok(Spooled::Disk(path));
+    }
+    let key = key(&path);
+    upload(&key, &path).await.map_err(|cause| SpoolFailed {
+        operation: "hold",
+        upload: key.clone(),
+        cause,
+    })?;
+    if let Err(err) = tokio::fs::remove_file(&path).await {
+        warn!("Failed to remove staged image {:?}, {}", path, err);
+    }
+    Ok(Spooled::S3 { key, path })
+}
+
+/// The key under which the upload at the given path is held within the spool bucket.
+fn key(path: &Path) -> String {
+    format!(
+        "{}/{}",
+        env::spool_prefix(),
+        path.file_name().unwrap_or_default().to_string_lossy()
+    )
+}
+
+async fn upload(key: &str, path: &Path) -> std::result::Result<(), String> {
+    let body = ByteStream::from_path(path)
+        .await
+        .map_err(|err| err.to_string())?;
+    client()
+        .await
+        .put_object()
+        .bucket(env::spool_bucket())
+        .key(key)
+        .body(body)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+async fn download(key: &str, path: &Path) -> std::result::Result<(), String> {
+    let object = client()
+        .await
+        .get_object()
+        .bucket(env::spool_bucket())
+        .key(key)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    let mut body = object.body;
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|err| err.to_string())?;
+    while let Some(chunk) = body.try_next().await.map_err(|err| err.to_string())? {
+        file.write_all(&chunk)
+            .await
+            .map_err(|err| err.to_string())?;
+    }
+    file.flush().await.map_err(|err| err.to_string())
+}
+
+/// Refuses with an [InsufficientSpoolSpace](InsufficientSpoolSpace) should an upload of the given
+/// length not fit within the spool path while leaving [spool_min_free](env::spool_min_free) bytes
+/// to spare. An upload of unknown length need only leave that much to spare.
+pub async fn preflight(length: Option<u64>) -> std::result::Result<(), InsufficientSpoolSpace> {
+    let dir = env::spool_path();
+    let available = tokio::task::spawn_blocking(move || fs2::available_space(dir))
+        .await
+        .map_err(|err| err.to_string())
+        .and_then(|available| available.map_err(|err| err.to_string()));
+    // The upload fails soon enough should the spool truly be unusable, so the preflight does not
+    // stand in its way.
+    let available = match available {
+        Ok(available) => available,
+        Err(err) => {
+            warn!("Failed to check the free space of the spool, {}", err);
+            return Ok(());
+        }
+    };
+    check(available, length, env::spool_min_free())
+}
+
+/// Returns how many bytes may yet be written beside the given path while leaving
+/// [spool_min_free](env::spool_min_free) bytes to spare, or None should its free space not be known.
+///
+/// This procedure does blocking IO and SHOULD be ran via [spawn_blocking](tokio::task::spawn_blocking).
+pub fn headroom(path: &Path) -> Option<u64> {
+    match fs2::available_space(path) {
+        Ok(available) => Some(available.saturating_sub(env::spool_min_free())),
+        Err(err) => {
+            warn!("Failed to check the free space beside {:?}, {}", path, err);
+            None
+        }
+    }
+}
+
+fn check(
+    available: u64,
+    length: Option<u64>,
+    min_free: u64,
+) -> std::result::Result<(), InsufficientSpoolSpace> {
+    let required = length.unwrap_or(0).saturating_add(min_free);
+    if available < required {
+        return Err(InsufficientSpoolSpace {
+            length: length.unwrap_or(0),
+            available,
+            min_free,
+        });
+    }
+    Ok(())
+}
+
+/// Creates the spool path (should it not exist) and removes every upload that was abandoned within
+/// the spool (that is, both the spool path and the spool bucket) by a previous run of the AIM.
+///
+/// Consumers SHOULD call this function upon startup, before any install is received.
+pub async fn cleanup() {
+    let dir = env::spool_path();
+    if let Err(err) = tokio::fs::create_dir_all(&dir).await {
+        warn!("Failed to create the spool at {:?}, {}", dir, err);
+    }
+    match tokio::fs::read_dir(&dir).await {
+        Ok(mut entries) => {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let path = entry.path();
+                let removed = if path.is_dir() {
+                    tokio::fs::remove_dir_all(&path).await
+                } else {
+                    tokio::fs::remove_file(&path).await
+                };
+                match removed {
+                    Ok(()) => info!("Removed abandoned spool {:?}", path),
+                    Err(err) => warn!("Failed to remove abandoned spool {:?}, {}", path, err),
+                }
+            }
+        }
+        Err(err) => warn!("Failed to read the spool at {:?}, {}", dir, err),
+    }
+    if env::spool() == "s3" {
+        if let Err(err) = cleanup_bucket().await {
+            warn!(
+                "Failed to remove abandoned spools from s3://{}/{}, {}",
+                env::spool_bucket(),
+                env::spool_prefix(),
+                err
+            );
+        }
+    }
+}
+
+async fn cleanup_bucket() -> std::result::Result<(), String> {
+    let (bucket, prefix) = (env::spool_bucket(), format!("{}/", env::spool_prefix()));
+    let listed = client()
+        .await
+        .list_objects_v2()
+        .bucket(&bucket)
+        .prefix(&prefix)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    for object in listed.contents.unwrap_or_default() {
+        let key = match object.key {
+            Some(key) => key,
+            None => continue,
+        };
+        client()
+            .await
+            .delete_object()
+            .bucket(&bucket)
+            .key(&key)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        info!("Removed abandoned spool s3://{}/{}", bucket, key);
+    }
+    Ok(())
+}
+
+/// A Preflight is a request guard that [checks](preflight) that an upload fits within the spool
+/// before it is received. Its failure is rendered by the [insufficient_storage](insufficient_storage)
+/// catcher.
+///
+/// As Rocket evaluates the data guard of a route last, the Preflight MUST be a parameter of any
+/// route that receives an upload.
+pub struct Preflight;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Preflight {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let length = request
+            .headers()
+            .get_one("Content-Length")
+            .and_then(|length| length.parse().ok());
+        match preflight(length).await {
+            Ok(()) => Outcome::Success(Preflight),
+            Err(err) => {
+                request.local_cache(|| Some(err));
+                Outcome::Failure((rocket::http::Status::InsufficientStorage, ()))
+            }
+        }
+    }
+}
+
+/// Renders the [InsufficientSpoolSpace](InsufficientSpoolSpace) that failed a [Preflight](Preflight).
+#[catch(507)]
+pub fn insufficient_storage(request: &Request<'_>) -> Box<dyn AcmError> {
+    match request.local_cache(|| None::<InsufficientSpoolSpace>) {
+        Some(err) => err.clone().into(),
+        None => InsufficientSpoolSpace {
+            length: 0,
+            available: 0,
+            min_free: env::spool_min_free(),
+        }
+        .into(),
+    }
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug, Clone)]
+#[code(Status::InsufficientStorage)]
+#[error(
+    "The upload of {length} bytes would not fit within the spool, which has {available} bytes \
+available of which {min_free} must remain free. Please try again once other installs have \
+finished, or mount a larger volume at the SPOOL_PATH."
+)]
+#[error_code("AIM-2000")]
+pub struct InsufficientSpoolSpace {
+    length: u64,
+    available: u64,
+    min_free: u64,
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InternalServerError)]
+#[error("Failed to {operation} the upload {upload} within the spool, {cause}.")]
+#[error_code("AIM-2001")]
+pub struct SpoolFailed {
+    operation: &'static str,
+    upload: String,
+    cause: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_free_space() {
+        assert!(check(10, Some(5), 5).is_ok());
+        assert!(check(10, Some(6), 5).is_err());
+        assert!(check(10, None, 10).is_ok());
+        assert!(check(10, None, 11).is_err());
+        assert!(check(10, Some(u64::MAX), 1).is_err());
+    }
+}
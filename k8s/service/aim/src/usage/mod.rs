@@ -0,0 +1,158 @@
+//! The disk usage of the AIM, both within the registry (the size of every installed tag, as found
+//! by inspecting its manifest) and locally (containerd's content store, and the temporary
+//! directory that uploads are spooled within).
+//!
+//! Should a [registry quota](env::registry_quota) be configured, then installs are refused with
+//! a [RegistryQuotaExceeded](RegistryQuotaExceeded) once the registry's usage reaches it.
+use crate::env;
+use crate::registry::{self, containerd};
+use error::*;
+use result::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A Usage is how much disk the AIM is using, in bytes.
+#[derive(Serialize, Debug, Kind)]
+pub struct Usage {
+    /// The size of every blob within the repository. Blobs shared between tags are counted once.
+    pub registry: u64,
+    /// The configured [registry quota](env::registry_quota), if any.
+    pub quota: Option<u64>,
+    /// The size of every tag, largest first.
+    pub tags: Vec<TagUsage>,
+    /// The size of containerd's content store, should it be known (it is not when the AIM is
+    /// built with the `ctr` feature).
+    pub containerd: Option<u64>,
+    /// The size of every upload staged within the [spool path](env::spool_path).
+    pub temp_dir: u64,
+}
+
+/// The size of a single tag. Blobs shared with other tags count toward the size of each of them.
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct TagUsage {
+    pub tag: String,
+    pub digest: String,
+    pub size: u64,
+}
+
+/// Returns the current disk usage of the AIM.
+pub async fn usage() -> Result<Usage> {
+    let (registry, tags) = registry_usage().await?;
+    Ok(Usage {
+        registry,
+        quota: env::registry_quota(),
+        tags,
+        containerd: containerd::disk_usage().await?,
+        temp_dir: temp_dir_usage(env::spool_path()).await,
+    })
+}
+
+/// Refuses with a [RegistryQuotaExceeded](RegistryQuotaExceeded) should the registry's usage
+/// have reached the configured [registry quota](env::registry_quota). Without a quota, this
+/// succeeds without so much as looking at the registry.
+///
+/// Checking the quota inspects the manifest of every installed tag, so it is done once per install.
+pub async fn check_quota() -> Result<()> {
+    let quota = match env::registry_quota() {
+        Some(quota) => quota,
+        None => return Ok(()),
+    };
+    let (usage, _) = registry_usage().await?;
+    if usage >= quota {
+        return Err(RegistryQuotaExceeded { usage, quota }.into());
+    }
+    Ok(())
+}
+
+/// Returns the size of the whole repository alongside that of each of its tags.
+async fn registry_usage() -> Result<(u64, Vec<TagUsage>)> {
+    let mut blobs = HashMap::new();
+    let mut tags = vec![];
+    for image in registry::list().await? {
+        let descriptors = registry::blobs(&image.tag).await?.unwrap_or_default();
+        let sizes: HashMap<String, u64> = descriptors
+            .into_iter()
+            .map(|blob| (blob.digest, blob.size))
+            .collect();
+        tags.push(TagUsage {
+            tag: image.tag,
+            digest: image.digest,
+            size: sizes.values().sum(),
+        });
+        blobs.extend(sizes);
+    }
+    Ok((blobs.values().sum(), largest_first(tags)))
+}
+
+fn largest_first(mut tags: Vec<TagUsage>) -> Vec<TagUsage> {
+    tags.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.tag.cmp(&b.tag)));
+    tags
+}
+
+/// Returns the size of every file beneath the given directory. Files that vanish (or cannot be
+/// read) while the directory is being walked are not counted.
+async fn temp_dir_usage<P: AsRef<Path>>(dir: P) -> u64 {
+    let dir = dir.as_ref().to_path_buf();
+    tokio::task::spawn_blocking(move || walk(&dir))
+        .await
+        .unwrap_or(0)
+}
+
+fn walk(dir: &Path) -> u64 {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => walk(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+#[derive(Error, AcmError, Kind, HttpCode, Debug)]
+#[code(Status::InsufficientStorage)]
+#[error(
+    "The registry holds {usage} bytes of images, which has reached its quota of {quota} bytes. \
+Please uninstall images that are no longer needed (or raise the REGISTRY_QUOTA) and try again."
+)]
+#[error_code("AIM-1600")]
+pub struct RegistryQuotaExceeded {
+    usage: u64,
+    quota: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(tag: &str, size: u64) -> TagUsage {
+        TagUsage {
+            tag: tag.to_string(),
+            digest: format!("sha256:{}", tag),
+            size,
+        }
+    }
+
+    #[test]
+    fn sorts_largest_first() {
+        let tags = largest_first(vec![tag("b", 10), tag("a", 30), tag("c", 10)]);
+        let order: Vec<&str> = tags.iter().map(|tag| tag.tag.as_str()).collect();
+        assert_eq!(order, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn walks() {
+        let dir = std::env::temp_dir().join(names::uuid());
+        std::fs::create_dir_all(dir.join("unpacked")).unwrap();
+        std::fs::write(dir.join("image"), vec![0u8; 100]).unwrap();
+        std::fs::write(dir.join("unpacked").join("blob"), vec![0u8; 20]).unwrap();
+        assert_eq!(walk(&dir), 120);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(walk(&dir), 0);
+    }
+}